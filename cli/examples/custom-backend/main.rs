@@ -21,8 +21,8 @@ use async_trait::async_trait;
 use jj_cli::cli_util::{CliRunner, CommandError, CommandHelper};
 use jj_cli::ui::Ui;
 use jj_lib::backend::{
-    Backend, BackendInitError, BackendLoadError, BackendResult, ChangeId, Commit, CommitId,
-    Conflict, ConflictId, FileId, SigningFn, SymlinkId, Tree, TreeId,
+    Backend, BackendGcStats, BackendInitError, BackendLoadError, BackendResult, ChangeId, Commit,
+    CommitId, Conflict, ConflictId, FileId, SigningFn, SymlinkId, Tree, TreeId,
 };
 use jj_lib::git_backend::GitBackend;
 use jj_lib::index::Index;
@@ -30,7 +30,7 @@ use jj_lib::repo::StoreFactories;
 use jj_lib::repo_path::RepoPath;
 use jj_lib::settings::UserSettings;
 use jj_lib::signing::Signer;
-use jj_lib::workspace::{Workspace, WorkspaceInitError};
+use jj_lib::workspace::{default_working_copy_factory, Workspace, WorkspaceInitError};
 
 #[derive(clap::Parser, Clone, Debug)]
 enum CustomCommand {
@@ -64,6 +64,7 @@ fn run_custom_command(
                 &|settings, store_path| Ok(Box::new(JitBackend::init(settings, store_path)?)),
                 Signer::from_settings(command_helper.settings())
                     .map_err(WorkspaceInitError::SignInit)?,
+                &*default_working_copy_factory(),
             )?;
             Ok(())
         }
@@ -173,7 +174,12 @@ impl Backend for JitBackend {
         self.inner.write_commit(contents, sign_with)
     }
 
-    fn gc(&self, index: &dyn Index, keep_newer: SystemTime) -> BackendResult<()> {
-        self.inner.gc(index, keep_newer)
+    fn gc(
+        &self,
+        index: &dyn Index,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<BackendGcStats> {
+        self.inner.gc(index, keep_newer, dry_run)
     }
 }
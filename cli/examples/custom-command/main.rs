@@ -39,7 +39,7 @@ fn run_custom_command(
         CustomCommand::Frobnicate(args) => {
             let mut workspace_command = command_helper.workspace_helper(ui)?;
             let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-            let mut tx = workspace_command.start_transaction();
+            let mut tx = workspace_command.start_transaction()?;
             let new_commit = tx
                 .mut_repo()
                 .rewrite_commit(command_helper.settings(), &commit)
@@ -21,8 +21,10 @@ use jj_cli::cli_util::{CliRunner, CommandError, CommandHelper};
 use jj_cli::ui::Ui;
 use jj_lib::backend::{Backend, MergedTreeId};
 use jj_lib::commit::Commit;
+use jj_lib::fsync::Fsync;
 use jj_lib::git_backend::GitBackend;
 use jj_lib::local_working_copy::LocalWorkingCopy;
+use jj_lib::lock::LockBackend;
 use jj_lib::merged_tree::MergedTree;
 use jj_lib::op_store::{OperationId, WorkspaceId};
 use jj_lib::repo::ReadonlyRepo;
@@ -31,8 +33,8 @@ use jj_lib::settings::UserSettings;
 use jj_lib::signing::Signer;
 use jj_lib::store::Store;
 use jj_lib::working_copy::{
-    CheckoutError, CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions,
-    WorkingCopy, WorkingCopyFactory, WorkingCopyStateError,
+    CheckoutError, CheckoutOptions, CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError,
+    SnapshotOptions, WorkingCopy, WorkingCopyFactory, WorkingCopyStateError,
 };
 use jj_lib::workspace::{default_working_copy_factories, Workspace, WorkspaceInitError};
 
@@ -107,6 +109,8 @@ impl ConflictsWorkingCopy {
         state_path: PathBuf,
         operation_id: OperationId,
         workspace_id: WorkspaceId,
+        lock_backend: LockBackend,
+        fsync: Fsync,
     ) -> Result<Self, WorkingCopyStateError> {
         let inner = LocalWorkingCopy::init(
             store,
@@ -114,14 +118,23 @@ impl ConflictsWorkingCopy {
             state_path,
             operation_id,
             workspace_id,
+            lock_backend,
+            fsync,
         )?;
         Ok(ConflictsWorkingCopy {
             inner: Box::new(inner),
         })
     }
 
-    fn load(store: Arc<Store>, working_copy_path: PathBuf, state_path: PathBuf) -> Self {
-        let inner = LocalWorkingCopy::load(store, working_copy_path, state_path);
+    fn load(
+        store: Arc<Store>,
+        working_copy_path: PathBuf,
+        state_path: PathBuf,
+        lock_backend: LockBackend,
+        fsync: Fsync,
+    ) -> Self {
+        let inner =
+            LocalWorkingCopy::load(store, working_copy_path, state_path, lock_backend, fsync);
         ConflictsWorkingCopy {
             inner: Box::new(inner),
         }
@@ -176,6 +189,7 @@ impl WorkingCopyFactory for ConflictsWorkingCopyFactory {
         state_path: PathBuf,
         operation_id: OperationId,
         workspace_id: WorkspaceId,
+        settings: &UserSettings,
     ) -> Result<Box<dyn WorkingCopy>, WorkingCopyStateError> {
         Ok(Box::new(ConflictsWorkingCopy::init(
             store,
@@ -183,6 +197,8 @@ impl WorkingCopyFactory for ConflictsWorkingCopyFactory {
             state_path,
             operation_id,
             workspace_id,
+            settings.lock_backend().unwrap_or_default(),
+            settings.fsync().clone(),
         )?))
     }
 
@@ -191,11 +207,14 @@ impl WorkingCopyFactory for ConflictsWorkingCopyFactory {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        settings: &UserSettings,
     ) -> Box<dyn WorkingCopy> {
         Box::new(ConflictsWorkingCopy::load(
             store,
             working_copy_path,
             state_path,
+            settings.lock_backend().unwrap_or_default(),
+            settings.fsync().clone(),
         ))
     }
 }
@@ -227,14 +246,18 @@ impl LockedWorkingCopy for LockedConflictsWorkingCopy {
         self.inner.snapshot(options)
     }
 
-    fn check_out(&mut self, commit: &Commit) -> Result<CheckoutStats, CheckoutError> {
+    fn check_out(
+        &mut self,
+        commit: &Commit,
+        options: CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError> {
         let conflicts = commit
             .tree()?
             .conflicts()
             .map(|(path, _value)| format!("{}\n", path.as_internal_file_string()))
             .join("");
         std::fs::write(self.wc_path.join(".conflicts"), conflicts).unwrap();
-        self.inner.check_out(commit)
+        self.inner.check_out(commit, options)
     }
 
     fn reset(&mut self, new_tree: &MergedTree) -> Result<(), ResetError> {
@@ -248,8 +271,9 @@ impl LockedWorkingCopy for LockedConflictsWorkingCopy {
     fn set_sparse_patterns(
         &mut self,
         new_sparse_patterns: Vec<RepoPathBuf>,
+        options: CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
-        self.inner.set_sparse_patterns(new_sparse_patterns)
+        self.inner.set_sparse_patterns(new_sparse_patterns, options)
     }
 
     fn finish(
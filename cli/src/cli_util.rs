@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use core::fmt;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::env::{self, ArgsOs, VarError};
 use std::ffi::{OsStr, OsString};
@@ -33,13 +34,17 @@ use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
 use jj_lib::backend::{BackendError, ChangeId, CommitId, MergedTreeId};
 use jj_lib::commit::Commit;
+use jj_lib::fileset::{
+    self, FilesetExpression, FilesetParseContext, FilesetParseError, FilesetParseErrorKind,
+};
 use jj_lib::git::{GitConfigParseError, GitExportError, GitImportError, GitRemoteManagementError};
 use jj_lib::git_backend::GitBackend;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::hex_util::to_reverse_hex;
 use jj_lib::id_prefix::IdPrefixContext;
-use jj_lib::matchers::{EverythingMatcher, Matcher, PrefixMatcher};
-use jj_lib::merged_tree::MergedTree;
+use jj_lib::local_working_copy::LocalWorkingCopy;
+use jj_lib::matchers::Matcher;
+use jj_lib::merged_tree::{MergeStrategy, MergedTree};
 use jj_lib::object_id::ObjectId;
 use jj_lib::op_heads_store::{self, OpHeadResolutionError};
 use jj_lib::op_store::{OpStoreError, OperationId, WorkspaceId};
@@ -56,15 +61,15 @@ use jj_lib::revset::{
     RevsetParseError, RevsetParseErrorKind, RevsetResolutionError, RevsetWorkspaceContext,
 };
 use jj_lib::rewrite::restore_tree;
-use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
 use jj_lib::signing::SignInitError;
 use jj_lib::str_util::{StringPattern, StringPatternParseError};
 use jj_lib::transaction::Transaction;
 use jj_lib::tree::TreeMergeError;
 use jj_lib::view::View;
 use jj_lib::working_copy::{
-    CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions, WorkingCopy,
-    WorkingCopyFactory, WorkingCopyStateError,
+    CheckoutOptions, CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions,
+    WorkingCopy, WorkingCopyFactory, WorkingCopyStateError,
 };
 use jj_lib::workspace::{
     default_working_copy_factories, LockedWorkspace, Workspace, WorkspaceInitError,
@@ -79,21 +84,29 @@ use tracing_chrome::ChromeLayerBuilder;
 use tracing_subscriber::prelude::*;
 
 use crate::config::{
-    new_config_path, AnnotatedValue, CommandNameAndArgs, ConfigSource, LayeredConfigs,
+    new_config_path, AnnotatedValue, CommandNameAndArgs, ConfigConditionContext, ConfigSource,
+    LayeredConfigs, ProjectConfig, PROJECT_CONFIG_FILE,
 };
+use crate::config_schema::ConfigProblem;
+use crate::error_code::ErrorCode;
 use crate::formatter::{FormatRecorder, Formatter, PlainTextFormatter};
-use crate::git_util::{print_failed_git_export, print_git_import_stats};
+use crate::git_util::{
+    print_failed_git_export, print_git_import_stats, set_up_fetch_missing_objects_progress,
+};
+use crate::hooks::{HookEvent, Hooks};
 use crate::merge_tools::{ConflictResolveError, DiffEditError, DiffGenerateError};
+use crate::patch_util::{LineSelection, PatchParseError, SelectLinesError};
 use crate::template_parser::{TemplateAliasesMap, TemplateParseError};
 use crate::templater::Template;
 use crate::ui::{ColorChoice, Ui};
-use crate::{commit_templater, text_util};
+use crate::{commit_templater, maintenance, text_util, tr};
 
 #[derive(Clone, Debug)]
 pub enum CommandError {
     UserError {
         err: Arc<dyn std::error::Error + Send + Sync>,
         hint: Option<String>,
+        code: Option<ErrorCode>,
     },
     ConfigError(String),
     /// Invalid command line
@@ -101,9 +114,27 @@ pub enum CommandError {
     /// Invalid command line detected by clap
     ClapCliError(Arc<clap::Error>),
     BrokenPipe,
+    /// Exit with the code without reporting anything, because the error was
+    /// already reported, e.g. by an external subcommand.
+    ExitCode(u8),
     InternalError(Arc<dyn std::error::Error + Send + Sync>),
 }
 
+impl CommandError {
+    /// Attaches a stable code to a user error. Other kinds of errors are
+    /// returned as is.
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        match self {
+            CommandError::UserError { err, hint, .. } => CommandError::UserError {
+                err,
+                hint,
+                code: Some(code),
+            },
+            err => err,
+        }
+    }
+}
+
 /// Wraps error with user-visible message.
 #[derive(Debug, Error)]
 #[error("{message}")]
@@ -157,6 +188,7 @@ pub fn user_error_with_hint_opt(
     CommandError::UserError {
         err: Arc::from(err.into()),
         hint,
+        code: None,
     }
 }
 
@@ -189,9 +221,9 @@ fn print_error_sources(ui: &Ui, source: Option<&dyn std::error::Error>) -> io::R
         return Ok(());
     };
     if err.source().is_none() {
-        writeln!(ui.stderr(), "Caused by: {err}")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "caused-by", message = err))?;
     } else {
-        writeln!(ui.stderr(), "Caused by:")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "caused-by-list"))?;
         for (i, err) in iter::successors(Some(err), |err| err.source()).enumerate() {
             writeln!(ui.stderr(), "{n}: {err}", n = i + 1)?;
         }
@@ -345,6 +377,21 @@ impl From<DiffGenerateError> for CommandError {
     }
 }
 
+impl From<PatchParseError> for CommandError {
+    fn from(err: PatchParseError) -> Self {
+        user_error_with_message("Failed to parse patch", err)
+    }
+}
+
+impl From<SelectLinesError> for CommandError {
+    fn from(err: SelectLinesError) -> Self {
+        match err {
+            SelectLinesError::Unsupported { .. } => user_error(err),
+            SelectLinesError::Backend(err) => err.into(),
+        }
+    }
+}
+
 impl From<ConflictResolveError> for CommandError {
     fn from(err: ConflictResolveError) -> Self {
         user_error_with_message("Failed to resolve conflicts", err)
@@ -423,23 +470,33 @@ impl From<RevsetParseError> for CommandError {
             _ => None,
         };
         user_error_with_hint_opt(format!("Failed to parse revset: {message}"), hint)
+            .with_code(ErrorCode::RevsetParse)
     }
 }
 
 impl From<RevsetResolutionError> for CommandError {
     fn from(err: RevsetResolutionError) -> Self {
-        let hint = match &err {
+        let (hint, code) = match &err {
             RevsetResolutionError::NoSuchRevision {
                 name: _,
                 candidates,
-            } => format_similarity_hint(candidates),
+            } => (
+                format_similarity_hint(candidates),
+                Some(ErrorCode::NoSuchRevision),
+            ),
+            RevsetResolutionError::AmbiguousCommitIdPrefix(_)
+            | RevsetResolutionError::AmbiguousChangeIdPrefix(_) => {
+                (None, Some(ErrorCode::AmbiguousIdPrefix))
+            }
             RevsetResolutionError::EmptyString
             | RevsetResolutionError::WorkspaceMissingWorkingCopy { .. }
-            | RevsetResolutionError::AmbiguousCommitIdPrefix(_)
-            | RevsetResolutionError::AmbiguousChangeIdPrefix(_)
-            | RevsetResolutionError::StoreError(_) => None,
+            | RevsetResolutionError::StoreError(_) => (None, None),
         };
-        user_error_with_hint_opt(err, hint)
+        let err = user_error_with_hint_opt(err, hint);
+        match code {
+            Some(code) => err.with_code(code),
+            None => err,
+        }
     }
 }
 
@@ -450,6 +507,19 @@ impl From<TemplateParseError> for CommandError {
     }
 }
 
+impl From<FilesetParseError> for CommandError {
+    fn from(err: FilesetParseError) -> Self {
+        let hint = match err.kind() {
+            FilesetParseErrorKind::NoSuchFunction {
+                name: _,
+                candidates,
+            } => format_similarity_hint(candidates),
+            _ => None,
+        };
+        user_error_with_hint_opt(format!("Failed to parse fileset: {err}"), hint)
+    }
+}
+
 impl From<FsPathParseError> for CommandError {
     fn from(err: FsPathParseError) -> Self {
         user_error(err)
@@ -474,29 +544,30 @@ impl From<WorkingCopyStateError> for CommandError {
     }
 }
 
+type ChromeTracingLayer = Option<tracing_chrome::ChromeLayer<tracing_subscriber::Registry>>;
+
+type ChromeTracingRegistry = tracing_subscriber::layer::Layered<
+    tracing_subscriber::reload::Layer<ChromeTracingLayer, tracing_subscriber::Registry>,
+    tracing_subscriber::Registry,
+>;
+
+/// Handle to initialize or change tracing subscription.
 #[derive(Clone)]
-struct ChromeTracingFlushGuard {
-    _inner: Option<Rc<tracing_chrome::FlushGuard>>,
+pub struct TracingSubscription {
+    reload_log_filter:
+        tracing_subscriber::reload::Handle<tracing_subscriber::EnvFilter, ChromeTracingRegistry>,
+    reload_chrome_tracing:
+        tracing_subscriber::reload::Handle<ChromeTracingLayer, tracing_subscriber::Registry>,
+    chrome_tracing_flush_guard: Rc<RefCell<Option<tracing_chrome::FlushGuard>>>,
 }
 
-impl Debug for ChromeTracingFlushGuard {
+impl Debug for TracingSubscription {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let Self { _inner } = self;
-        f.debug_struct("ChromeTracingFlushGuard")
+        f.debug_struct("TracingSubscription")
             .finish_non_exhaustive()
     }
 }
 
-/// Handle to initialize or change tracing subscription.
-#[derive(Clone, Debug)]
-pub struct TracingSubscription {
-    reload_log_filter: tracing_subscriber::reload::Handle<
-        tracing_subscriber::EnvFilter,
-        tracing_subscriber::Registry,
-    >,
-    _chrome_tracing_flush_guard: ChromeTracingFlushGuard,
-}
-
 impl TracingSubscription {
     /// Initializes tracing with the default configuration. This should be
     /// called as early as possible.
@@ -506,7 +577,8 @@ impl TracingSubscription {
             .from_env_lossy();
         let (filter, reload_log_filter) = tracing_subscriber::reload::Layer::new(filter);
 
-        let (chrome_tracing_layer, chrome_tracing_flush_guard) = match std::env::var("JJ_TRACE") {
+        let mut chrome_tracing_flush_guard = None;
+        let chrome_tracing_layer = match std::env::var("JJ_TRACE") {
             Ok(filename) => {
                 let filename = if filename.is_empty() {
                     format!(
@@ -519,32 +591,35 @@ impl TracingSubscription {
                 } else {
                     filename
                 };
-                let include_args = std::env::var("JJ_TRACE_INCLUDE_ARGS").is_ok();
-                let (layer, guard) = ChromeLayerBuilder::new()
-                    .file(filename)
-                    .include_args(include_args)
-                    .build();
-                (
-                    Some(layer),
-                    ChromeTracingFlushGuard {
-                        _inner: Some(Rc::new(guard)),
-                    },
-                )
+                match std::fs::File::create(&filename) {
+                    Ok(file) => {
+                        let (layer, guard) = build_chrome_tracing_layer(file);
+                        chrome_tracing_flush_guard = Some(guard);
+                        Some(layer)
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to create trace file {filename}: {err}");
+                        None
+                    }
+                }
             }
-            Err(_) => (None, ChromeTracingFlushGuard { _inner: None }),
+            Err(_) => None,
         };
+        let (chrome_tracing_layer, reload_chrome_tracing) =
+            tracing_subscriber::reload::Layer::new(chrome_tracing_layer);
 
         tracing_subscriber::registry()
+            .with(chrome_tracing_layer)
             .with(
                 tracing_subscriber::fmt::Layer::default()
                     .with_writer(std::io::stderr)
                     .with_filter(filter),
             )
-            .with(chrome_tracing_layer)
             .init();
         TracingSubscription {
             reload_log_filter,
-            _chrome_tracing_flush_guard: chrome_tracing_flush_guard,
+            reload_chrome_tracing,
+            chrome_tracing_flush_guard: Rc::new(RefCell::new(chrome_tracing_flush_guard)),
         }
     }
 
@@ -559,6 +634,40 @@ impl TracingSubscription {
         tracing::info!("verbose logging enabled");
         Ok(())
     }
+
+    /// Records the spans of the rest of the command as a Chrome trace in the
+    /// file at `path`. This replaces the trace requested by `$JJ_TRACE`, if
+    /// any.
+    pub fn enable_chrome_tracing(&self, path: &Path) -> Result<(), CommandError> {
+        let file = std::fs::File::create(path).map_err(|err| {
+            user_error_with_message(
+                format!("Failed to create trace file {}", path.display()),
+                err,
+            )
+        })?;
+        let (layer, guard) = build_chrome_tracing_layer(file);
+        // Flush the previous trace before replacing its layer
+        self.chrome_tracing_flush_guard.borrow_mut().take();
+        self.reload_chrome_tracing
+            .reload(Some(layer))
+            .map_err(|err| internal_error_with_message("failed to enable tracing", err))?;
+        *self.chrome_tracing_flush_guard.borrow_mut() = Some(guard);
+        tracing::info!(?path, "chrome tracing enabled");
+        Ok(())
+    }
+}
+
+fn build_chrome_tracing_layer(
+    file: std::fs::File,
+) -> (
+    tracing_chrome::ChromeLayer<tracing_subscriber::Registry>,
+    tracing_chrome::FlushGuard,
+) {
+    let include_args = std::env::var("JJ_TRACE_INCLUDE_ARGS").is_ok();
+    ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(include_args)
+        .build()
 }
 
 pub struct CommandHelper {
@@ -590,7 +699,7 @@ impl CommandHelper {
     ) -> Self {
         // `cwd` is canonicalized for consistency with `Workspace::workspace_root()` and
         // to easily compute relative paths between them.
-        let cwd = cwd.canonicalize().unwrap_or(cwd);
+        let cwd = file_util::canonicalize(&cwd).unwrap_or(cwd);
 
         Self {
             app,
@@ -637,6 +746,11 @@ impl CommandHelper {
         self.layered_configs.resolved_config_values(prefix)
     }
 
+    /// Checks the user and repo config files against the config schema.
+    pub fn check_config_files(&self) -> Vec<ConfigProblem> {
+        self.layered_configs.check_config_files()
+    }
+
     /// Loads template aliases from the configs.
     ///
     /// For most commands that depend on a loaded repo, you should use
@@ -657,17 +771,72 @@ impl CommandHelper {
         Ok(workspace_command)
     }
 
+    /// Like `workspace_helper()`, but for commands that only read the repo.
+    /// Doesn't wait for locks held by other processes. If another process is
+    /// updating the working copy, the working copy isn't snapshotted. If
+    /// another process is merging concurrent operations, they're merged in
+    /// memory without recording the result.
+    ///
+    /// If `ui.default-command-mode` is `readonly`, the command runs in
+    /// read-only mode as if `--read-only` had been specified.
+    #[instrument(skip(self, ui))]
+    pub fn workspace_helper_for_read(
+        &self,
+        ui: &mut Ui,
+    ) -> Result<WorkspaceCommandHelper, CommandError> {
+        let read_only =
+            self.global_args.read_only || self.default_command_mode()? == CommandMode::Readonly;
+        let mut workspace_command = self.load_workspace_helper(ui, read_only, false)?;
+        workspace_command.maybe_snapshot(ui)?;
+        Ok(workspace_command)
+    }
+
     /// Loads workspace and repo, but never snapshots the working copy. Most
     /// commands should use `workspace_helper()` instead.
     #[instrument(skip(self, ui))]
     pub fn workspace_helper_no_snapshot(
         &self,
         ui: &mut Ui,
+    ) -> Result<WorkspaceCommandHelper, CommandError> {
+        self.load_workspace_helper(ui, self.global_args.read_only, true)
+    }
+
+    fn load_workspace_helper(
+        &self,
+        ui: &mut Ui,
+        read_only: bool,
+        wait_for_locks: bool,
     ) -> Result<WorkspaceCommandHelper, CommandError> {
         let workspace = self.load_workspace()?;
-        let op_head = self.resolve_operation(ui, workspace.repo_loader())?;
-        let repo = workspace.repo_loader().load_at(&op_head)?;
-        self.for_loaded_repo(ui, workspace, repo)
+        let repo = self.load_repo_impl(ui, workspace.repo_loader(), read_only, wait_for_locks)?;
+        let mut workspace_command = self.for_loaded_repo(ui, workspace, repo)?;
+        workspace_command.wait_for_working_copy_lock = wait_for_locks;
+        if read_only {
+            workspace_command.read_only = true;
+            workspace_command.may_update_working_copy = false;
+        }
+        Ok(workspace_command)
+    }
+
+    /// Returns an error if `--read-only` was specified. Commands that modify
+    /// the repo without a transaction should call this.
+    pub fn check_repo_writable(&self) -> Result<(), CommandError> {
+        if self.global_args.read_only {
+            Err(read_only_mode_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn default_command_mode(&self) -> Result<CommandMode, CommandError> {
+        self.settings
+            .config()
+            .get::<CommandMode>("ui.default-command-mode")
+            .optional()
+            .map(Option::unwrap_or_default)
+            .map_err(|err| {
+                CommandError::ConfigError(format!("Invalid `ui.default-command-mode`: {err}"))
+            })
     }
 
     pub fn get_working_copy_factory(&self) -> Result<&dyn WorkingCopyFactory, CommandError> {
@@ -682,6 +851,27 @@ impl CommandHelper {
         Ok(factory)
     }
 
+    /// Returns the factory for the working copies of new repos, which is
+    /// chosen by `core.working-copy` among the registered working-copy types.
+    pub fn init_working_copy_factory(&self) -> Result<&dyn WorkingCopyFactory, CommandError> {
+        let working_copy_type = self
+            .settings
+            .config()
+            .get_string("core.working-copy")
+            .optional()?
+            .unwrap_or_else(|| LocalWorkingCopy::name().to_owned());
+        match self.working_copy_factories.get(&working_copy_type) {
+            Some(factory) => Ok(factory.as_ref()),
+            None => Err(user_error_with_hint(
+                format!("Unknown working-copy type \"{working_copy_type}\""),
+                format!(
+                    "Available types are: {}",
+                    self.working_copy_factories.keys().sorted().join(", ")
+                ),
+            )),
+        }
+    }
+
     #[instrument(skip_all)]
     pub fn load_workspace(&self) -> Result<Workspace, CommandError> {
         let loader = self.workspace_loader()?;
@@ -694,47 +884,102 @@ impl CommandHelper {
             .map_err(|err| map_workspace_load_error(err, self.global_args.repository.as_deref()))
     }
 
+    /// Loads the workspace at `workspace_root` instead of the current one.
+    #[instrument(skip_all)]
+    pub fn load_workspace_at(&self, workspace_root: &Path) -> Result<Workspace, CommandError> {
+        Workspace::load(
+            &self.settings,
+            workspace_root,
+            &self.store_factories,
+            &self.working_copy_factories,
+        )
+        .map_err(|err| map_workspace_load_error(err, workspace_root.to_str()))
+    }
+
+    /// Resolves the operation to load the repo at. In read-only mode, the
+    /// operation merging concurrent operations isn't written, so it can't be
+    /// loaded from the returned operation.
     #[instrument(skip_all)]
     pub fn resolve_operation(
         &self,
         ui: &mut Ui,
         repo_loader: &RepoLoader,
     ) -> Result<Operation, CommandError> {
+        let repo = self.load_repo_impl(ui, repo_loader, self.global_args.read_only, true)?;
+        Ok(repo.operation().clone())
+    }
+
+    /// Loads the repo at the operation to use. In read-only mode, concurrent
+    /// operations are merged in memory, without writing anything, so the merge
+    /// isn't recorded in the operation log, and the descendants of commits
+    /// rewritten by one of the operations aren't rebased.
+    fn load_repo_impl(
+        &self,
+        ui: &mut Ui,
+        repo_loader: &RepoLoader,
+        read_only: bool,
+        wait_for_lock: bool,
+    ) -> Result<Arc<ReadonlyRepo>, CommandError> {
         if self.global_args.at_operation == "@" {
-            op_heads_store::resolve_op_heads(
-                repo_loader.op_heads_store().as_ref(),
-                repo_loader.op_store(),
-                |op_heads| {
-                    writeln!(
-                        ui.stderr(),
-                        "Concurrent modification detected, resolving automatically.",
-                    )?;
-                    let base_repo = repo_loader.load_at(&op_heads[0])?;
-                    // TODO: It may be helpful to print each operation we're merging here
-                    let mut tx =
-                        start_repo_transaction(&base_repo, &self.settings, &self.string_args);
-                    for other_op_head in op_heads.into_iter().skip(1) {
-                        tx.merge_operation(other_op_head)?;
-                        let num_rebased = tx.mut_repo().rebase_descendants(&self.settings)?;
-                        if num_rebased > 0 {
-                            writeln!(
-                                ui.stderr(),
-                                "Rebased {num_rebased} descendant commits onto commits rewritten \
-                                 by other operation"
-                            )?;
-                        }
+            let mut unwritten_repo = None;
+            let resolver = |op_heads: Vec<Operation>| -> Result<Operation, CommandError> {
+                writeln!(
+                    ui.stderr(),
+                    "Concurrent modification detected, resolving automatically.",
+                )?;
+                let base_repo = repo_loader.load_at(&op_heads[0])?;
+                // TODO: It may be helpful to print each operation we're merging here
+                let mut tx = start_repo_transaction(&base_repo, &self.settings, &self.string_args);
+                for other_op_head in op_heads.into_iter().skip(1) {
+                    tx.merge_operation(other_op_head)?;
+                    if read_only {
+                        continue;
                     }
-                    Ok(tx
-                        .write("resolve concurrent operations")
-                        .leave_unpublished()
-                        .operation()
-                        .clone())
-                },
-            )
+                    let num_rebased = tx.mut_repo().rebase_descendants(&self.settings)?;
+                    if num_rebased > 0 {
+                        writeln!(
+                            ui.stderr(),
+                            "Rebased {num_rebased} descendant commits onto commits rewritten by \
+                             other operation"
+                        )?;
+                    }
+                }
+                if read_only {
+                    let repo = tx.into_unwritten_repo("resolve concurrent operations");
+                    let operation = repo.operation().clone();
+                    unwritten_repo = Some(repo);
+                    return Ok(operation);
+                }
+                Ok(tx
+                    .write("resolve concurrent operations")
+                    .leave_unpublished()
+                    .operation()
+                    .clone())
+            };
+            let heads_store = repo_loader.op_heads_store().as_ref();
+            let operation = if read_only {
+                op_heads_store::resolve_op_heads_read_only(
+                    heads_store,
+                    repo_loader.op_store(),
+                    resolver,
+                )?
+            } else if wait_for_lock {
+                op_heads_store::resolve_op_heads(heads_store, repo_loader.op_store(), resolver)?
+            } else {
+                op_heads_store::resolve_op_heads_without_waiting(
+                    heads_store,
+                    repo_loader.op_store(),
+                    resolver,
+                )?
+            };
+            match unwritten_repo {
+                Some(repo) => Ok(repo),
+                None => Ok(repo_loader.load_at(&operation)?),
+            }
         } else {
             let operation =
                 op_walk::resolve_op_for_load(repo_loader, &self.global_args.at_operation)?;
-            Ok(operation)
+            Ok(repo_loader.load_at(&operation)?)
         }
     }
 
@@ -797,7 +1042,10 @@ pub struct WorkspaceCommandHelper {
     revset_aliases_map: RevsetAliasesMap,
     template_aliases_map: TemplateAliasesMap,
     may_update_working_copy: bool,
+    read_only: bool,
+    wait_for_working_copy_lock: bool,
     working_copy_shared_with_git: bool,
+    hooks: Hooks,
 }
 
 impl WorkspaceCommandHelper {
@@ -824,6 +1072,8 @@ impl WorkspaceCommandHelper {
         let loaded_at_head = command.global_args.at_operation == "@";
         let may_update_working_copy = loaded_at_head && !command.global_args.ignore_working_copy;
         let working_copy_shared_with_git = is_colocated_git_workspace(&workspace, &repo);
+        set_up_fetch_missing_objects_progress(ui, repo.store());
+        let hooks = Hooks::from_settings(&command.settings, !command.global_args.no_hooks)?;
         let helper = Self {
             cwd: command.cwd.clone(),
             string_args: command.string_args.clone(),
@@ -834,7 +1084,10 @@ impl WorkspaceCommandHelper {
             revset_aliases_map,
             template_aliases_map,
             may_update_working_copy,
+            read_only: false,
+            wait_for_working_copy_lock: true,
             working_copy_shared_with_git,
+            hooks,
         };
         // Parse short-prefixes revset early to report error before starting mutable
         // operation.
@@ -842,6 +1095,23 @@ impl WorkspaceCommandHelper {
         Ok(helper)
     }
 
+    pub fn settings(&self) -> &UserSettings {
+        &self.settings
+    }
+
+    /// Runs the hook for the `event` in the workspace root, if one is
+    /// configured. See [`Hooks::run()`].
+    pub fn run_hook(
+        &self,
+        ui: &Ui,
+        event: HookEvent,
+        envs: &[(&str, String)],
+        input: &[u8],
+    ) -> Result<(), CommandError> {
+        self.hooks
+            .run(ui, event, self.workspace_root(), envs, input)
+    }
+
     pub fn git_backend(&self) -> Option<&GitBackend> {
         self.user_repo.git_backend()
     }
@@ -850,7 +1120,9 @@ impl WorkspaceCommandHelper {
         if self.may_update_working_copy {
             Ok(())
         } else {
-            let hint = if self.global_args.ignore_working_copy {
+            let hint = if self.read_only {
+                "Don't use --read-only."
+            } else if self.global_args.ignore_working_copy {
                 "Don't use --ignore-working-copy."
             } else {
                 "Don't use --at-op."
@@ -891,7 +1163,7 @@ impl WorkspaceCommandHelper {
     #[instrument(skip_all)]
     fn import_git_head(&mut self, ui: &mut Ui) -> Result<(), CommandError> {
         assert!(self.may_update_working_copy);
-        let mut tx = self.start_transaction();
+        let mut tx = self.start_transaction()?;
         git::import_head(tx.mut_repo())?;
         if !tx.mut_repo().has_changes() {
             return Ok(());
@@ -919,7 +1191,13 @@ impl WorkspaceCommandHelper {
             let new_git_head_commit = tx.mut_repo().store().get_commit(new_git_head_id)?;
             tx.mut_repo()
                 .check_out(workspace_id, &self.settings, &new_git_head_commit)?;
-            let mut locked_ws = self.workspace.start_working_copy_mutation()?;
+            // If another process is updating the working copy, it will import
+            // the HEAD as well.
+            let Some(mut locked_ws) =
+                lock_working_copy(&mut self.workspace, self.wait_for_working_copy_lock)?
+            else {
+                return Ok(());
+            };
             // The working copy was presumably updated by the git command that updated
             // HEAD, so we just need to reset our working copy
             // state to it without updating working copy files.
@@ -953,7 +1231,7 @@ impl WorkspaceCommandHelper {
     #[instrument(skip_all)]
     pub fn import_git_refs(&mut self, ui: &mut Ui) -> Result<(), CommandError> {
         let git_settings = self.settings.git_settings();
-        let mut tx = self.start_transaction();
+        let mut tx = self.start_transaction()?;
         // Automated import shouldn't fail because of reserved remote name.
         let stats = git::import_some_refs(tx.mut_repo(), &git_settings, |ref_name| {
             !git::is_reserved_git_remote_ref(ref_name)
@@ -1042,19 +1320,67 @@ impl WorkspaceCommandHelper {
         RepoPathBuf::parse_fs_path(&self.cwd, self.workspace_root(), input)
     }
 
-    pub fn matcher_from_values(&self, values: &[String]) -> Result<Box<dyn Matcher>, CommandError> {
-        if values.is_empty() {
-            Ok(Box::new(EverythingMatcher))
-        } else {
-            // TODO: Add support for globs and other formats
-            let paths: Vec<_> = values
-                .iter()
-                .map(|v| self.parse_file_path(v))
-                .try_collect()?;
-            Ok(Box::new(PrefixMatcher::new(paths)))
+    /// Resolves the paths of the line `selections` relative to the current
+    /// directory.
+    pub fn parse_line_selections(
+        &self,
+        selections: &[LineSelection],
+    ) -> Result<Vec<LineSelection<RepoPathBuf>>, CommandError> {
+        selections
+            .iter()
+            .map(|selection| {
+                Ok(LineSelection {
+                    path: self.parse_file_path(&selection.path)?,
+                    ranges: selection.ranges.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn fileset_parse_context(&self) -> FilesetParseContext<'_> {
+        FilesetParseContext {
+            cwd: &self.cwd,
+            workspace_root: self.workspace_root(),
         }
     }
 
+    /// Parses the fileset `values` into the union of them, which matches
+    /// everything if there are no values.
+    ///
+    /// A value that isn't a valid fileset expression is taken as a literal
+    /// path relative to the current directory, so file names like `foo.txt~`
+    /// or `x (1).png` can still be passed as is.
+    pub fn parse_union_filesets(
+        &self,
+        values: &[String],
+    ) -> Result<FilesetExpression, CommandError> {
+        if values.is_empty() {
+            return Ok(FilesetExpression::all());
+        }
+        let context = self.fileset_parse_context();
+        let expressions: Vec<_> = values
+            .iter()
+            .map(|value| {
+                fileset::parse(value, &context).or_else(|err| {
+                    let path = self.parse_file_path(value).map_err(|_| err)?;
+                    Ok::<_, FilesetParseError>(FilesetExpression::prefix_path(path))
+                })
+            })
+            .try_collect()?;
+        Ok(FilesetExpression::union_all(expressions))
+    }
+
+    /// Parses the fileset `values` into a matcher. Predicates like
+    /// `executable()` match the files in any of the `trees`.
+    pub fn matcher_from_values(
+        &self,
+        values: &[String],
+        trees: &[&MergedTree],
+    ) -> Result<Box<dyn Matcher>, CommandError> {
+        let expression = self.parse_union_filesets(values)?;
+        Ok(expression.resolve_predicates(trees)?.to_matcher())
+    }
+
     #[instrument(skip_all)]
     pub fn base_ignores(&self) -> Arc<GitIgnoreFile> {
         fn get_excludes_file_path(config: &gix::config::File) -> Option<PathBuf> {
@@ -1063,7 +1389,7 @@ impl WorkspaceCommandHelper {
             if let Some(value) = config.string_by_key("core.excludesFile") {
                 str::from_utf8(&value)
                     .ok()
-                    .map(crate::git_util::expand_git_path)
+                    .map(|path| PathBuf::from(expand_config_path(path)))
             } else {
                 xdg_config_home().ok().map(|x| x.join("git").join("ignore"))
             }
@@ -1112,7 +1438,8 @@ impl WorkspaceCommandHelper {
             (Some(commit), None) => Ok(commit?),
             (None, _) => Err(user_error(format!(
                 r#"Revset "{revision_str}" didn't resolve to any revisions"#
-            ))),
+            ))
+            .with_code(ErrorCode::EmptyRevset)),
             (Some(commit0), Some(commit1)) => {
                 let mut iter = [commit0, commit1].into_iter().chain(iter);
                 let commits: Vec<_> = iter.by_ref().take(5).try_collect()?;
@@ -1127,7 +1454,7 @@ impl WorkspaceCommandHelper {
                     format!(
                         r#"The revset "{revision_str}" resolved to these revisions:
 {commits_summary}
-Some of these commits have the same change id. Abandon one of them with `jj abandon -r <REVISION>`."#,
+Some of these commits have the same change id. Abandon one of them with `jj abandon -r <REVISION>`, or combine them with `jj resolve-divergence`."#,
                     )
                 } else if let RevsetExpression::CommitRef(RevsetCommitRef::Symbol(branch_name)) =
                     revset_expression.as_ref()
@@ -1148,7 +1475,8 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
                 Err(user_error_with_hint(
                     format!(r#"Revset "{revision_str}" resolved to more than one revision"#),
                     hint,
-                ))
+                )
+                .with_code(ErrorCode::MultipleRevisions))
             }
         }
     }
@@ -1178,8 +1506,9 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
         } else {
             self.resolve_single_rev(revision_str, ui)
                 .map_err(|err| match err {
-                    CommandError::UserError { err, hint } => CommandError::UserError {
+                    CommandError::UserError { err, hint, code } => CommandError::UserError {
                         err,
+                        code,
                         hint: Some(format!(
                             "{old_hint}Prefix the expression with 'all' to allow any number of \
                              revisions (i.e. 'all:{}').",
@@ -1373,21 +1702,22 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
                 .map(|commit| commit.id().clone())
                 .collect(),
         );
-        let (params, immutable_heads_str) = self
-            .revset_aliases_map
-            .get_function("immutable_heads")
-            .unwrap();
-        if !params.is_empty() {
-            return Err(user_error(
-                r#"The `revset-aliases.immutable_heads()` function must be declared without arguments."#,
-            ));
-        }
-        let immutable_heads_revset = self.parse_revset(immutable_heads_str, None)?;
-        let immutable_revset = immutable_heads_revset
-            .ancestors()
-            .union(&RevsetExpression::commit(
-                self.repo().store().root_commit_id().clone(),
-            ));
+        let root_revset = RevsetExpression::commit(self.repo().store().root_commit_id().clone());
+        let immutable_revset = if self.global_args.ignore_immutable {
+            root_revset
+        } else {
+            let (params, immutable_heads_str) = self
+                .revset_aliases_map
+                .get_function("immutable_heads")
+                .unwrap();
+            if !params.is_empty() {
+                return Err(user_error(
+                    r#"The `revset-aliases.immutable_heads()` function must be declared without arguments."#,
+                ));
+            }
+            let immutable_heads_revset = self.parse_revset(immutable_heads_str, None)?;
+            immutable_heads_revset.ancestors().union(&root_revset)
+        };
         let revset = self.evaluate_revset(to_rewrite_revset.intersection(&immutable_revset))?;
         if let Some(commit) = revset.iter().commits(self.repo().store()).next() {
             let commit = commit?;
@@ -1400,10 +1730,11 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
                 user_error_with_hint(
                     format!("Commit {} is immutable", short_commit_hash(commit.id()),),
                     "Configure the set of immutable commits via \
-                     `revset-aliases.immutable_heads()`.",
+                     `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite \
+                     it anyway.",
                 )
             };
-            return Err(error);
+            return Err(error.with_code(ErrorCode::ImmutableCommit));
         }
 
         Ok(())
@@ -1431,10 +1762,19 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
             // committing the working copy.
             return Ok(());
         };
+        self.run_hook(ui, HookEvent::PreSnapshot, &[], &[])?;
         let base_ignores = self.base_ignores();
 
         // Compare working-copy tree and operation with repo's, and reload as needed.
-        let mut locked_ws = self.workspace.start_working_copy_mutation()?;
+        let Some(mut locked_ws) =
+            lock_working_copy(&mut self.workspace, self.wait_for_working_copy_lock)?
+        else {
+            writeln!(
+                ui.warning(),
+                "The working copy is being updated by another process, so it wasn't snapshotted."
+            )?;
+            return Ok(());
+        };
         let old_op_id = locked_ws.locked_wc().old_operation_id().clone();
         let (repo, wc_commit) =
             match check_stale_working_copy(locked_ws.locked_wc(), &wc_commit, &repo)? {
@@ -1458,7 +1798,8 @@ Set which revision the branch points to with `jj branch set {branch_name} -r <RE
                         "Run `jj workspace update-stale` to update it.
 See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-working-copy \
                          for more information.",
-                    ));
+                    )
+                    .with_code(ErrorCode::StaleWorkingCopy));
                 }
                 WorkingCopyFreshness::SiblingOperation => {
                     return Err(internal_error(format!(
@@ -1503,6 +1844,14 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
             }
 
             self.user_repo = ReadonlyUserRepo::new(tx.commit("snapshot working copy"));
+            locked_ws.finish(self.user_repo.repo.op_id().clone())?;
+            let envs = [
+                ("JJ_OPERATION_ID", self.repo().op_id().hex()),
+                ("JJ_COMMIT_ID", commit.id().hex()),
+            ];
+            self.run_hook(ui, HookEvent::PostSnapshot, &envs, &[])?;
+            self.run_hook(ui, HookEvent::PostOperation, &envs[..1], &[])?;
+            return Ok(());
         }
         locked_ws.finish(self.user_repo.repo.op_id().clone())?;
         Ok(())
@@ -1516,20 +1865,20 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
     ) -> Result<(), CommandError> {
         assert!(self.may_update_working_copy);
         let stats = update_working_copy(
+            ui,
             &self.user_repo.repo,
             &mut self.workspace,
             maybe_old_commit,
             new_commit,
         )?;
         if Some(new_commit) != maybe_old_commit {
-            write!(ui.stderr(), "Working copy now at: ")?;
+            write!(ui.stderr(), "{}", tr!(ui, "working-copy-now-at"))?;
             ui.stderr_formatter().with_label("working_copy", |fmt| {
                 self.write_commit_summary(fmt, new_commit)
             })?;
             writeln!(ui.stderr())?;
             for parent in new_commit.parents() {
-                //                  "Working copy now at: "
-                write!(ui.stderr(), "Parent commit      : ")?;
+                write!(ui.stderr(), "{}", tr!(ui, "parent-commit"))?;
                 self.write_commit_summary(ui.stderr_formatter().as_mut(), &parent)?;
                 writeln!(ui.stderr())?;
             }
@@ -1540,9 +1889,15 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
         Ok(())
     }
 
-    pub fn start_transaction(&mut self) -> WorkspaceCommandTransaction {
+    /// Starts a transaction on the repo. Fails in read-only mode, so commands
+    /// are rejected before they have any effect outside the transaction, like
+    /// pushing to a remote or updating the working copy.
+    pub fn start_transaction(&mut self) -> Result<WorkspaceCommandTransaction, CommandError> {
+        if self.read_only {
+            return Err(read_only_mode_error());
+        }
         let tx = start_repo_transaction(self.repo(), &self.settings, &self.string_args);
-        WorkspaceCommandTransaction { helper: self, tx }
+        Ok(WorkspaceCommandTransaction { helper: self, tx })
     }
 
     fn finish_transaction(
@@ -1552,12 +1907,16 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
         description: impl Into<String>,
     ) -> Result<(), CommandError> {
         if !tx.mut_repo().has_changes() {
-            writeln!(ui.stderr(), "Nothing changed.")?;
+            writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
             return Ok(());
         }
         let num_rebased = tx.mut_repo().rebase_descendants(&self.settings)?;
         if num_rebased > 0 {
-            writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
+            writeln!(
+                ui.stderr(),
+                "{}",
+                tr!(ui, "rebased-descendants", count = num_rebased)
+            )?;
         }
 
         let old_repo = tx.base_repo().clone();
@@ -1583,6 +1942,7 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
         }
         self.user_repo = ReadonlyUserRepo::new(tx.commit(description));
         self.report_repo_changes(ui, &old_repo)?;
+        maintenance::maybe_start_in_background(&self.settings, self.workspace_root(), self.repo());
 
         if self.may_update_working_copy {
             if let Some(new_commit) = &maybe_new_wc_commit {
@@ -1592,6 +1952,17 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
                 // update it.
             }
         }
+        let envs = [("JJ_OPERATION_ID", self.repo().op_id().hex())];
+        if self.hooks.has_hook(HookEvent::PostRewrite)? {
+            let input = find_rewritten_commits(&old_repo, self.repo())?
+                .iter()
+                .map(|(old_id, new_id)| format!("{} {}\n", old_id.hex(), new_id.hex()))
+                .join("");
+            if !input.is_empty() {
+                self.run_hook(ui, HookEvent::PostRewrite, &envs, input.as_bytes())?;
+            }
+        }
+        self.run_hook(ui, HookEvent::PostOperation, &envs, &[])?;
         let settings = &self.settings;
         if settings.user_name().is_empty() || settings.user_email().is_empty() {
             writeln!(
@@ -1621,8 +1992,9 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
         // are millions of commits added to the repo, assuming the revset engine can
         // efficiently skip non-conflicting commits. Filter out empty commits mostly so
         // `jj new <conflicted commit>` doesn't result in a message about new conflicts.
-        let conflicts = RevsetExpression::filter(RevsetFilterPredicate::HasConflict)
-            .intersection(&RevsetExpression::filter(RevsetFilterPredicate::File(None)));
+        let conflicts = RevsetExpression::filter(RevsetFilterPredicate::HasConflict).intersection(
+            &RevsetExpression::filter(RevsetFilterPredicate::File(FilesetExpression::all())),
+        );
         let removed_conflicts_expr = new_heads.range(&old_heads).intersection(&conflicts);
         let added_conflicts_expr = old_heads.range(&new_heads).intersection(&conflicts);
 
@@ -1868,6 +2240,55 @@ fn find_workspace_dir(cwd: &Path) -> &Path {
         .unwrap_or(cwd)
 }
 
+/// Adds the project config in the workspace root to the configs if the user
+/// trusts it, asking them first if the file is new or has changed.
+fn load_project_config(
+    ui: &mut Ui,
+    layered_configs: &mut LayeredConfigs,
+    loader: &WorkspaceLoader,
+) -> Result<(), CommandError> {
+    let Some(project_config) = ProjectConfig::load(loader.workspace_root(), loader.repo_path())?
+    else {
+        return Ok(());
+    };
+    if !project_config.is_trusted() {
+        let trusted = Ui::can_prompt() && {
+            if project_config.was_trusted() {
+                writeln!(
+                    ui.warning(),
+                    "The project config in {PROJECT_CONFIG_FILE} has changed since you trusted it."
+                )?;
+            } else {
+                writeln!(
+                    ui.stderr(),
+                    "This repo has a project config in {PROJECT_CONFIG_FILE}. It can set revset \
+                     and template aliases, immutable heads, and fix tools to run on your files."
+                )?;
+            }
+            ui.prompt_yes_no(&format!("Trust {PROJECT_CONFIG_FILE}?"), Some(false))?
+        };
+        if !trusted {
+            writeln!(
+                ui.warning(),
+                "Ignoring the untrusted project config in {PROJECT_CONFIG_FILE}"
+            )?;
+            writeln!(
+                ui.hint(),
+                "Hint: Review the file and run `jj config trust` to use it."
+            )?;
+            return Ok(());
+        }
+        project_config.trust()?;
+    }
+    for key in layered_configs.set_project_config(&project_config)? {
+        writeln!(
+            ui.warning(),
+            "Ignoring `{key}` in {PROJECT_CONFIG_FILE}, which can't be set by a project config"
+        )?;
+    }
+    Ok(())
+}
+
 fn map_workspace_load_error(err: WorkspaceLoadError, workspace_path: Option<&str>) -> CommandError {
     match err {
         WorkspaceLoadError::NoWorkspaceHere(wc_path) => {
@@ -1875,7 +2296,7 @@ fn map_workspace_load_error(err: WorkspaceLoadError, workspace_path: Option<&str
             let workspace_path_str = workspace_path.unwrap_or(".");
             let message = format!(r#"There is no jj repo in "{workspace_path_str}""#);
             let git_dir = wc_path.join(".git");
-            if git_dir.is_dir() {
+            let err = if git_dir.is_dir() {
                 user_error_with_hint(
                     message,
                     "It looks like this is a git repo. You can create a jj repo backed by it by \
@@ -1884,7 +2305,8 @@ jj init --git-repo=.",
                 )
             } else {
                 user_error(message)
-            }
+            };
+            err.with_code(ErrorCode::NoRepo)
         }
         WorkspaceLoadError::RepoDoesNotExist(repo_dir) => user_error(format!(
             "The repository directory at {} is missing. Was it moved?",
@@ -2032,27 +2454,47 @@ pub fn print_checkout_stats(
     new_commit: &Commit,
 ) -> Result<(), std::io::Error> {
     if stats.added_files > 0 || stats.updated_files > 0 || stats.removed_files > 0 {
-        writeln!(
-            ui.stderr(),
-            "Added {} files, modified {} files, removed {} files",
-            stats.added_files,
-            stats.updated_files,
-            stats.removed_files
-        )?;
+        let message = tr!(
+            ui,
+            "checkout-stats",
+            added = stats.added_files,
+            modified = stats.updated_files,
+            removed = stats.removed_files,
+        );
+        writeln!(ui.stderr(), "{message}")?;
     }
     if stats.skipped_files != 0 {
+        let message = tr!(ui, "checkout-skipped", count = stats.skipped_files);
+        writeln!(ui.warning(), "{message}")?;
+        let hint = tr!(
+            ui,
+            "checkout-skipped-hint",
+            commit = short_commit_hash(new_commit.id()),
+        );
+        writeln!(ui.hint(), "{hint}")?;
+    }
+    Ok(())
+}
+
+/// Lists the `paths` of the conflicts in `commit` that were resolved with
+/// `--strategy`.
+pub fn print_resolved_conflicts(
+    ui: &Ui,
+    tx: &WorkspaceCommandTransaction,
+    commit: &Commit,
+    paths: &[RepoPathBuf],
+) -> Result<(), CommandError> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    write!(ui.stderr(), "Resolved conflicts in ")?;
+    tx.write_commit_summary(ui.stderr_formatter().as_mut(), commit)?;
+    writeln!(ui.stderr(), ":")?;
+    for path in paths {
         writeln!(
-            ui.warning(),
-            "{} of those updates were skipped because there were conflicting changes in the \
-             working copy.",
-            stats.skipped_files
-        )?;
-        writeln!(
-            ui.hint(),
-            "Hint: Inspect the changes compared to the intended target with `jj diff --from {}`.
-Discard the conflicting changes with `jj restore --from {}`.",
-            short_commit_hash(new_commit.id()),
-            short_commit_hash(new_commit.id())
+            ui.stderr(),
+            "  {}",
+            tx.base_workspace_helper().format_file_path(path)
         )?;
     }
     Ok(())
@@ -2179,7 +2621,53 @@ pub fn resolve_multiple_nonempty_revsets_default_single(
     Ok(all_commits)
 }
 
+fn read_only_mode_error() -> CommandError {
+    user_error_with_hint(
+        "Cannot modify the repo in read-only mode.",
+        "Don't use --read-only.",
+    )
+}
+
+/// Locks the working copy. If `wait` is false, returns `None` instead of
+/// waiting if the working copy is locked by another process.
+fn lock_working_copy(
+    workspace: &mut Workspace,
+    wait: bool,
+) -> Result<Option<LockedWorkspace>, CommandError> {
+    if wait {
+        Ok(Some(workspace.start_working_copy_mutation()?))
+    } else {
+        Ok(workspace.try_start_working_copy_mutation()?)
+    }
+}
+
+/// Returns the commits in the `old_repo` that were rewritten in the
+/// `new_repo`, paired with the commits they were rewritten to. Parents come
+/// before their children.
+fn find_rewritten_commits(
+    old_repo: &ReadonlyRepo,
+    new_repo: &ReadonlyRepo,
+) -> Result<Vec<(CommitId, CommitId)>, CommandError> {
+    let old_heads = old_repo.view().heads().iter().cloned().collect_vec();
+    let new_heads = new_repo.view().heads().iter().cloned().collect_vec();
+    let mut rewrites = vec![];
+    for commit in revset::walk_revs(new_repo, &new_heads, &old_heads)?
+        .iter()
+        .commits(new_repo.store())
+    {
+        let commit = commit?;
+        for predecessor_id in commit.predecessor_ids() {
+            if old_repo.index().has_id(predecessor_id) {
+                rewrites.push((predecessor_id.clone(), commit.id().clone()));
+            }
+        }
+    }
+    rewrites.reverse();
+    Ok(rewrites)
+}
+
 pub fn update_working_copy(
+    ui: &Ui,
     repo: &Arc<ReadonlyRepo>,
     workspace: &mut Workspace,
     old_commit: Option<&Commit>,
@@ -2189,8 +2677,17 @@ pub fn update_working_copy(
     let stats = if Some(new_commit.tree_id()) != old_tree_id.as_ref() {
         // TODO: CheckoutError::ConcurrentCheckout should probably just result in a
         // warning for most commands (but be an error for the checkout command)
+        let progress = crate::progress::checkout_progress(ui);
+        let options = CheckoutOptions {
+            progress: progress.as_ref().map(|x| x as _),
+        };
         let stats = workspace
-            .check_out(repo.op_id().clone(), old_tree_id.as_ref(), new_commit)
+            .check_out(
+                repo.op_id().clone(),
+                old_tree_id.as_ref(),
+                new_commit,
+                options,
+            )
             .map_err(|err| {
                 internal_error_with_message(
                     format!("Failed to check out commit {}", new_commit.id().hex()),
@@ -2404,6 +2901,32 @@ pub fn get_new_config_file_path(
     Ok(edit_path)
 }
 
+/// Checks that the config file at `path`, if any, is valid TOML.
+pub fn check_config_file(path: &Path) -> Result<(), CommandError> {
+    // Directories of config files aren't checked.
+    if path.is_dir() {
+        return Ok(());
+    }
+    let config_toml = match std::fs::read_to_string(path) {
+        Ok(config_toml) => config_toml,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(user_error_with_message(
+                format!("Failed to read file {path}", path = path.display()),
+                err,
+            ))
+        }
+    };
+    toml_edit::Document::from_str(&config_toml).map_err(|err| {
+        user_error_with_message_and_hint(
+            format!("Failed to parse file {path}", path = path.display()),
+            "Run `jj config edit` again to fix the syntax error.",
+            err,
+        )
+    })?;
+    Ok(())
+}
+
 pub fn run_ui_editor(settings: &UserSettings, edit_path: &PathBuf) -> Result<(), CommandError> {
     let editor: CommandNameAndArgs = settings
         .config()
@@ -2556,6 +3079,16 @@ pub struct Args {
     pub global_args: GlobalArgs,
 }
 
+/// Mode selected by `ui.default-command-mode` for commands that only read the
+/// repo.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all(deserialize = "kebab-case"))]
+enum CommandMode {
+    #[default]
+    Normal,
+    Readonly,
+}
+
 #[derive(clap::Args, Clone, Debug)]
 #[command(next_help_heading = "Global Options")]
 pub struct GlobalArgs {
@@ -2579,6 +3112,33 @@ pub struct GlobalArgs {
     /// implies `--ignore-working-copy`.
     #[arg(long, global = true)]
     pub ignore_working_copy: bool,
+    /// Don't modify the repo or the working copy
+    ///
+    /// The working copy isn't snapshotted, as if `--ignore-working-copy` had
+    /// been specified. Concurrent operations are merged, but the merge isn't
+    /// recorded in the operation log. Commands that would modify the repo
+    /// fail. This is useful for command prompts and editor integrations that
+    /// run `jj` in the background.
+    ///
+    /// The commit index may still be updated, since it's only a cache.
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    /// Allow rewriting immutable commits
+    ///
+    /// By default, Jujutsu prevents rewriting commits in the configured set of
+    /// immutable commits. This option disables that check and lets you rewrite
+    /// any commit but the root commit.
+    ///
+    /// This doesn't impact the default revset of `jj log`.
+    #[arg(long, global = true)]
+    pub ignore_immutable: bool,
+    /// Don't run hooks
+    ///
+    /// By default, the commands configured in `hooks.<event>` run when
+    /// something happens in the repo, such as before the working copy is
+    /// snapshotted or before branches are pushed.
+    #[arg(long, global = true)]
+    pub no_hooks: bool,
     /// Operation to load the repo at
     ///
     /// Operation to load the repo at. By default, Jujutsu loads the repo at the
@@ -2602,6 +3162,14 @@ pub struct GlobalArgs {
     /// Enable verbose logging
     #[arg(long, short = 'v', global = true)]
     pub verbose: bool,
+    /// Write a trace of the command's instrumented spans to this file
+    ///
+    /// The trace is in the Chrome trace event format, which can be opened in
+    /// e.g. `https://ui.perfetto.dev/` to see which parts of the command were
+    /// slow. Spans from before the arguments were parsed aren't recorded; use
+    /// `$JJ_TRACE` to record them too.
+    #[arg(long, value_name = "FILE", global = true, value_hint = clap::ValueHint::FilePath)]
+    pub profile: Option<PathBuf>,
 
     #[command(flatten)]
     pub early_args: EarlyArgs,
@@ -2673,6 +3241,24 @@ impl ValueParserFactory for RevisionArg {
     }
 }
 
+/// The side to take in conflicts that are resolved with `--strategy`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeStrategyArg {
+    /// Take the side of the destination
+    Ours,
+    /// Take the side of the revision being applied
+    Theirs,
+}
+
+impl From<MergeStrategyArg> for MergeStrategy {
+    fn from(arg: MergeStrategyArg) -> Self {
+        match arg {
+            MergeStrategyArg::Ours => MergeStrategy::Ours,
+            MergeStrategyArg::Theirs => MergeStrategy::Theirs,
+        }
+    }
+}
+
 fn resolve_default_command(
     ui: &Ui,
     config: &config::Config,
@@ -2716,7 +3302,7 @@ fn resolve_default_command(
     Ok(string_args)
 }
 
-fn resolve_aliases(
+pub fn resolve_aliases(
     config: &config::Config,
     app: &Command,
     mut string_args: Vec<String>,
@@ -2781,6 +3367,55 @@ fn resolve_aliases(
     }
 }
 
+/// Inserts the arguments from `command-defaults.<command>` right after the
+/// (sub)command name, so the arguments given on the command line come after
+/// them and can override them.
+fn resolve_command_defaults(
+    config: &config::Config,
+    app: &Command,
+    mut string_args: Vec<String>,
+) -> Result<Vec<String>, CommandError> {
+    let parse = |args: &[String]| {
+        let matches = app
+            .clone()
+            .ignore_errors(true)
+            .try_get_matches_from(args)
+            .ok();
+        let mut command_path = vec![];
+        let mut matches = matches.as_ref();
+        while let Some((name, submatches)) = matches.and_then(|m| m.subcommand()) {
+            command_path.push(name.to_owned());
+            matches = Some(submatches);
+        }
+        command_path
+    };
+    let command_path = parse(&string_args);
+    if command_path.is_empty() {
+        return Ok(string_args);
+    }
+    let command_name = command_path.join(" ");
+    let key = format!("command-defaults.{}", command_path.join("."));
+    let Some(value) = config.get::<config::Value>(&key).optional()? else {
+        return Ok(string_args);
+    };
+    if matches!(value.kind, config::ValueKind::Table(_)) {
+        // Only defaults for subcommands are configured
+        return Ok(string_args);
+    }
+    let Ok(default_args) = value.try_deserialize::<Vec<String>>() else {
+        return Err(user_error(format!(
+            r#"Default arguments for "{command_name}" must be a string list"#
+        )));
+    };
+    // Find where the subcommand name ends by parsing growing prefixes of the
+    // arguments, since clap doesn't tell the index of subcommands.
+    let end = (1..=string_args.len())
+        .find(|&len| parse(&string_args[..len]) == command_path)
+        .unwrap();
+    string_args.splice(end..end, default_args);
+    Ok(string_args)
+}
+
 /// Parse args that must be interpreted early, e.g. before printing help.
 fn handle_early_args(
     ui: &mut Ui,
@@ -2827,7 +3462,8 @@ pub fn expand_args(
     }
 
     let string_args = resolve_default_command(ui, config, app, string_args)?;
-    resolve_aliases(config, app, string_args)
+    let string_args = resolve_aliases(config, app, string_args)?;
+    resolve_command_defaults(config, app, string_args)
 }
 
 pub fn parse_args(
@@ -2838,17 +3474,41 @@ pub fn parse_args(
     layered_configs: &mut LayeredConfigs,
 ) -> Result<(ArgMatches, Args), CommandError> {
     handle_early_args(ui, app, string_args, layered_configs)?;
-    let matches = app
-        .clone()
-        .arg_required_else_help(true)
-        .subcommand_required(true)
-        .try_get_matches_from(string_args)?;
+    // Arguments given more than once override the earlier ones, so that the
+    // ones on the command line override `command-defaults`.
+    let parse = |app: Command| {
+        app.arg_required_else_help(true)
+            .subcommand_required(true)
+            .args_override_self(true)
+            .try_get_matches_from(string_args)
+    };
+    let matches = match parse(app.clone()) {
+        Ok(matches) => matches,
+        // Only look for external subcommands on PATH if the command isn't a
+        // built-in one, or to list them in the help of the top-level command.
+        Err(err)
+            if match err.kind() {
+                clap::error::ErrorKind::InvalidSubcommand => true,
+                clap::error::ErrorKind::DisplayHelp
+                | clap::error::ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+                    crate::plugins::help_lists_plugins(app, string_args)
+                }
+                _ => false,
+            } =>
+        {
+            parse(crate::plugins::add_plugin_subcommands(app.clone()))?
+        }
+        Err(err) => return Err(err.into()),
+    };
 
     let args: Args = Args::from_arg_matches(&matches).unwrap();
     if args.global_args.verbose {
         // TODO: set up verbose logging as early as possible
         tracing_subscription.enable_verbose_logging()?;
     }
+    if let Some(path) = &args.global_args.profile {
+        tracing_subscription.enable_chrome_tracing(path)?;
+    }
 
     Ok((matches, args))
 }
@@ -2861,24 +3521,25 @@ pub fn handle_command_result(
 ) -> std::io::Result<ExitCode> {
     match &result {
         Ok(()) => Ok(ExitCode::SUCCESS),
-        Err(CommandError::UserError { err, hint }) => {
-            writeln!(ui.error(), "Error: {err}")?;
+        Err(CommandError::UserError { err, hint, code }) => {
+            let message = match code {
+                Some(code) => tr!(ui, "error-with-code", code = code, message = err),
+                None => tr!(ui, "error", message = err),
+            };
+            writeln!(ui.error(), "{message}")?;
             print_error_sources(ui, err.source())?;
             if let Some(hint) = hint {
-                writeln!(ui.hint(), "Hint: {hint}")?;
+                writeln!(ui.hint(), "{}", tr!(ui, "hint", hint = hint))?;
             }
             Ok(ExitCode::from(1))
         }
         Err(CommandError::ConfigError(message)) => {
-            writeln!(ui.error(), "Config error: {message}")?;
-            writeln!(
-                ui.hint(),
-                "For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md."
-            )?;
+            writeln!(ui.error(), "{}", tr!(ui, "config-error", message = message))?;
+            writeln!(ui.hint(), "{}", tr!(ui, "config-error-help"))?;
             Ok(ExitCode::from(1))
         }
         Err(CommandError::CliError(message)) => {
-            writeln!(ui.error(), "Error: {message}")?;
+            writeln!(ui.error(), "{}", tr!(ui, "error", message = message))?;
             Ok(ExitCode::from(2))
         }
         Err(CommandError::ClapCliError(inner)) => {
@@ -2912,8 +3573,9 @@ pub fn handle_command_result(
             // A broken pipe is not an error, but a signal to exit gracefully.
             Ok(ExitCode::from(BROKEN_PIPE_EXIT_CODE))
         }
+        Err(CommandError::ExitCode(code)) => Ok(ExitCode::from(*code)),
         Err(CommandError::InternalError(err)) => {
-            writeln!(ui.error(), "Internal error: {err}")?;
+            writeln!(ui.error(), "{}", tr!(ui, "internal-error", message = err))?;
             print_error_sources(ui, err.source())?;
             Ok(ExitCode::from(255))
         }
@@ -3039,6 +3701,9 @@ impl CliRunner {
         // than the path resolution.
         let maybe_cwd_workspace_loader = WorkspaceLoader::init(find_workspace_dir(&cwd))
             .map_err(|err| map_workspace_load_error(err, None));
+        layered_configs.set_condition_context(ConfigConditionContext::new(
+            maybe_cwd_workspace_loader.as_ref().ok(),
+        ));
         layered_configs.read_user_config()?;
         if let Ok(loader) = &maybe_cwd_workspace_loader {
             layered_configs.read_repo_config(loader.repo_path())?;
@@ -3062,11 +3727,17 @@ impl CliRunner {
             // Invalid -R path is an error. No need to proceed.
             let loader = WorkspaceLoader::init(&cwd.join(path))
                 .map_err(|err| map_workspace_load_error(err, Some(path)))?;
+            // Conditional configs are matched against the -R workspace.
+            layered_configs.set_condition_context(ConfigConditionContext::new(Some(&loader)));
+            layered_configs.read_user_config()?;
             layered_configs.read_repo_config(loader.repo_path())?;
             Ok(loader)
         } else {
             maybe_cwd_workspace_loader
         };
+        if let Ok(loader) = &maybe_workspace_loader {
+            load_project_config(ui, &mut layered_configs, loader)?;
+        }
 
         // Apply workspace configs and --config-toml arguments.
         let config = layered_configs.merge();
@@ -3084,7 +3755,21 @@ impl CliRunner {
             }
         }
 
+        // Only likely misspellings are reported here. `jj config check` reports
+        // the other problems.
+        for problem in layered_configs.check_config_files() {
+            if let Some(suggestion) = &problem.suggestion {
+                writeln!(ui.warning(), "{problem}")?;
+                let hint = tr!(ui, "did-you-mean", suggestion = suggestion);
+                writeln!(ui.hint(), "{hint}")?;
+            }
+        }
+
         let settings = UserSettings::from_config(config);
+        // Reject invalid lock and fsync settings up front, since the stores
+        // that use them can't all report them.
+        settings.lock_backend()?;
+        settings.fsync_config()?;
         let working_copy_factories = self
             .working_copy_factories
             .unwrap_or_else(default_working_copy_factories);
@@ -3103,6 +3788,9 @@ impl CliRunner {
         for start_hook_fn in self.start_hook_fns {
             start_hook_fn(ui, &command_helper)?;
         }
+        if let Some((name, args)) = crate::plugins::plugin_invocation(command_helper.matches()) {
+            return crate::plugins::run_plugin(ui, &command_helper, name, &args);
+        }
         (self.dispatch_fn)(ui, &command_helper)
     }
 
@@ -49,7 +49,7 @@ pub(crate) fn cmd_abandon(
     let mut workspace_command = command.workspace_helper(ui)?;
     let to_abandon = resolve_multiple_nonempty_revsets(&args.revisions, &workspace_command, ui)?;
     workspace_command.check_rewritable(to_abandon.iter())?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for commit in &to_abandon {
         tx.mut_repo().record_abandoned_commit(commit.id().clone());
     }
@@ -0,0 +1,691 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::{fs, slice, str};
+
+use clap::ArgGroup;
+use itertools::Itertools;
+use jj_lib::backend::{ChangeId, MillisSinceEpoch, Signature, Timestamp};
+use jj_lib::commit::Commit;
+use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
+use jj_lib::revset::RevsetExpression;
+use jj_lib::rewrite::{merge_commit_trees, rebase_commit};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::instrument;
+
+use crate::cli_util::{
+    user_error, user_error_with_hint, user_error_with_message, CommandError, CommandHelper,
+    WorkspaceCommandHelper,
+};
+use crate::patch_util::{apply_patches, parse_patch};
+use crate::text_util;
+use crate::ui::Ui;
+
+/// Apply patches from emails
+///
+/// The patches are read from mbox files, like the ones written by `jj
+/// format-patch` or `git format-patch`, or from stdin if no files are given.
+/// Each patch is committed with the author, date, and description from its
+/// email. The commits are inserted between the working-copy commit and its
+/// parents.
+///
+/// If a patch doesn't apply, the changes that do apply are committed, and that
+/// commit becomes the working-copy commit. Fix it, and then run `jj am
+/// --continue` to apply the remaining patches.
+#[derive(clap::Args, Clone, Debug)]
+#[command(group(ArgGroup::new("action").args(&["paths", "continue_", "abort"])))]
+pub(crate) struct AmArgs {
+    /// Mbox or patch files to apply
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    paths: Vec<String>,
+    /// Apply the remaining patches after fixing a patch that didn't apply
+    #[arg(long = "continue")]
+    continue_: bool,
+    /// Forget the remaining patches after a patch didn't apply
+    #[arg(long)]
+    abort: bool,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_am(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &AmArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let state_dir = workspace_command.workspace_root().join(".jj").join("am");
+    let no_series_error = || user_error("No patches are being applied");
+    if args.abort {
+        let state = AmState::load(&state_dir)?.ok_or_else(no_series_error)?;
+        fs::remove_dir_all(&state_dir)?;
+        writeln!(
+            ui.stderr(),
+            "Forgot {} remaining patches",
+            state.messages.len()
+        )?;
+        return Ok(());
+    }
+
+    let wc_commit_id = workspace_command
+        .get_wc_commit_id()
+        .ok_or_else(|| user_error("This command requires a working copy"))?
+        .clone();
+    let wc_commit = workspace_command.repo().store().get_commit(&wc_commit_id)?;
+    // The patches are applied on top of the `parents`, and the working-copy
+    // commit from before the patches were applied is rebased onto them
+    let (messages, parents, old_wc_commit) = if args.continue_ {
+        let state = AmState::load(&state_dir)?.ok_or_else(no_series_error)?;
+        let old_wc_commit = match &state.working_copy {
+            Some(change_id) => find_visible_commit(&workspace_command, change_id)?,
+            None => None,
+        };
+        (state.messages, vec![wc_commit], old_wc_commit)
+    } else {
+        if state_dir.exists() {
+            return Err(user_error_with_hint(
+                "Patches from a previous `jj am` are still being applied",
+                "Run `jj am --continue` to apply them, or `jj am --abort` to forget them.",
+            ));
+        }
+        let mut messages = vec![];
+        if args.paths.is_empty() {
+            let mut text = vec![];
+            io::stdin().read_to_end(&mut text)?;
+            messages.extend(split_mbox(&text).into_iter().map(<[u8]>::to_vec));
+        }
+        for path in &args.paths {
+            let path = command.cwd().join(path);
+            let text = fs::read(&path).map_err(|err| {
+                user_error_with_message(format!("Failed to read {}", path.display()), err)
+            })?;
+            messages.extend(split_mbox(&text).into_iter().map(<[u8]>::to_vec));
+        }
+        if messages.is_empty() {
+            return Err(user_error("No patches to apply"));
+        }
+        (messages, wc_commit.parents(), Some(wc_commit))
+    };
+    let mails: Vec<MailPatch> = messages
+        .iter()
+        .enumerate()
+        .map(|(i, message)| {
+            MailPatch::parse(message).map_err(|err| user_error(format!("Patch {}: {err}", i + 1)))
+        })
+        .try_collect()?;
+
+    let settings = command.settings();
+    let mut tx = workspace_command.start_transaction()?;
+    let mut parents = parents;
+    for (i, mail) in mails.iter().enumerate() {
+        let file_patches = parse_patch(&mail.diff, 1)?;
+        let base_tree = merge_commit_trees(tx.repo(), &parents)?;
+        let applied = apply_patches(tx.repo().store(), &base_tree, &file_patches, 0)?;
+        let author = Signature {
+            name: mail.author_name.clone(),
+            email: mail.author_email.clone(),
+            timestamp: mail
+                .timestamp
+                .clone()
+                .unwrap_or_else(|| settings.signature().timestamp),
+        };
+        let commit = tx
+            .mut_repo()
+            .new_commit(
+                settings,
+                parents.iter().map(|commit| commit.id().clone()).collect(),
+                applied.tree_id,
+            )
+            .set_author(author)
+            .set_description(&mail.description)
+            .write()?;
+        parents = vec![commit.clone()];
+        if applied.rejected.is_empty() {
+            write!(ui.stderr(), "Applied ")?;
+            tx.write_commit_summary(ui.stderr_formatter().as_mut(), &commit)?;
+            writeln!(ui.stderr())?;
+            continue;
+        }
+
+        // Stop at the partially applied patch, so it can be fixed
+        if let Some(old_wc_commit) = &old_wc_commit {
+            rebase_commit(
+                settings,
+                tx.mut_repo(),
+                old_wc_commit,
+                slice::from_ref(&commit),
+            )?;
+            tx.mut_repo().rebase_descendants(settings)?;
+        }
+        tx.edit(&commit)?;
+        let mut rejected_diff = vec![];
+        for rejected in &applied.rejected {
+            rejected.patch.write_to(&rejected.hunks, &mut rejected_diff);
+        }
+        let state = AmState {
+            messages: messages[i + 1..].to_vec(),
+            working_copy: old_wc_commit.map(|commit| commit.change_id().clone()),
+        };
+        let description = format!("apply {} patches", i + 1);
+        tx.finish(ui, description)?;
+        state.save(&state_dir)?;
+        let rejected_path = state_dir.join("rejected.diff");
+        fs::write(&rejected_path, rejected_diff)?;
+        for rejected in &applied.rejected {
+            writeln!(
+                ui.warning(),
+                "{}: {}",
+                workspace_command.format_file_path(rejected.patch.path()),
+                rejected.reason
+            )?;
+        }
+        return Err(user_error_with_hint(
+            format!("Failed to apply patch: {}", mail.subject()),
+            format!(
+                "The changes that don't apply are saved in {}. Make them in the working copy, and \
+                 then run `jj am --continue`.",
+                rejected_path.display()
+            ),
+        ));
+    }
+
+    let last_commit = &parents[0];
+    match &old_wc_commit {
+        Some(old_wc_commit) => {
+            rebase_commit(settings, tx.mut_repo(), old_wc_commit, &parents)?;
+        }
+        None => {
+            tx.check_out(last_commit)?;
+        }
+    }
+    tx.finish(ui, format!("apply {} patches", mails.len()))?;
+    if args.continue_ {
+        fs::remove_dir_all(&state_dir)?;
+    }
+    Ok(())
+}
+
+/// Returns the visible commit with the `change_id`, if there's one.
+fn find_visible_commit(
+    workspace_command: &WorkspaceCommandHelper,
+    change_id: &ChangeId,
+) -> Result<Option<Commit>, CommandError> {
+    let repo = workspace_command.repo();
+    let Some(commit_ids) = repo.resolve_change_id(change_id) else {
+        return Ok(None);
+    };
+    let visible = RevsetExpression::commits(commit_ids)
+        .intersection(&RevsetExpression::visible_heads().ancestors())
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .next();
+    match visible {
+        Some(commit_id) => Ok(Some(repo.store().get_commit(&commit_id)?)),
+        None => Ok(None),
+    }
+}
+
+/// Patches left to apply after a patch didn't apply.
+struct AmState {
+    /// The emails of the remaining patches.
+    messages: Vec<Vec<u8>>,
+    /// The working-copy commit from before the patches were applied.
+    working_copy: Option<ChangeId>,
+}
+
+impl AmState {
+    fn load(dir: &Path) -> Result<Option<Self>, CommandError> {
+        if !dir.exists() {
+            return Ok(None);
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter_ok(|path| path.extension().is_some_and(|ext| ext == "eml"))
+            .try_collect()?;
+        paths.sort();
+        let messages = paths.iter().map(fs::read).try_collect()?;
+        let working_copy = match fs::read_to_string(dir.join("working-copy")) {
+            Ok(hex) => Some(
+                ChangeId::try_from_hex(hex.trim())
+                    .map_err(|err| user_error_with_message("Invalid state of `jj am`", err))?,
+            ),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(AmState {
+            messages,
+            working_copy,
+        }))
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), CommandError> {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        fs::create_dir_all(dir)?;
+        for (i, message) in self.messages.iter().enumerate() {
+            fs::write(dir.join(format!("{:04}.eml", i + 1)), message)?;
+        }
+        if let Some(change_id) = &self.working_copy {
+            fs::write(dir.join("working-copy"), change_id.hex())?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits an mbox file into messages. Text that doesn't start with an mbox
+/// separator line is a single message.
+fn split_mbox(text: &[u8]) -> Vec<&[u8]> {
+    let is_separator = |line: &[u8]| {
+        line.strip_prefix(b"From ")
+            .and_then(|rest| rest.iter().position(|b| *b == b' '))
+            .is_some_and(|pos| pos > 0)
+    };
+    let mut messages = vec![];
+    let mut start = None;
+    let mut offset = 0;
+    let mut after_blank_line = true;
+    for line in text.split_inclusive(|b| *b == b'\n') {
+        if after_blank_line && is_separator(line) {
+            if let Some(start) = start {
+                messages.push(&text[start..offset]);
+            }
+            start = Some(offset + line.len());
+        } else if start.is_none() && offset == 0 {
+            start = Some(0);
+        }
+        after_blank_line = line == b"\n" || line == b"\r\n";
+        offset += line.len();
+    }
+    if let Some(start) = start {
+        if text[start..].iter().any(|b| !b.is_ascii_whitespace()) {
+            messages.push(&text[start..]);
+        }
+    }
+    messages
+}
+
+/// A patch and its metadata from an email.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct MailPatch {
+    author_name: String,
+    author_email: String,
+    timestamp: Option<Timestamp>,
+    description: String,
+    /// The part of the body after the description.
+    diff: Vec<u8>,
+}
+
+impl MailPatch {
+    fn parse(message: &[u8]) -> Result<Self, String> {
+        let (headers, body) = parse_headers(message);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .rev()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let body = match header("Content-Transfer-Encoding").map(str::trim) {
+            Some(encoding) if encoding.eq_ignore_ascii_case("quoted-printable") => {
+                decode_quoted_printable(body, true)
+            }
+            Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+                decode_base64(body).ok_or("invalid base64 body")?
+            }
+            _ => body.to_vec(),
+        };
+        let mut from = header("From").map(decode_header);
+        let mut subject = header("Subject").map(decode_header).unwrap_or_default();
+        let mut date = header("Date").map(str::to_owned);
+
+        // Headers at the start of the body override the ones of the email
+        let (body_headers, rest) = parse_headers(&body);
+        let has_body_headers = !body_headers.is_empty()
+            && body_headers.iter().all(|(key, _)| {
+                ["From", "Subject", "Date"]
+                    .iter()
+                    .any(|name| key.eq_ignore_ascii_case(name))
+            });
+        let body = if has_body_headers {
+            for (key, value) in body_headers {
+                match key.to_ascii_lowercase().as_str() {
+                    "from" => from = Some(decode_header(&value)),
+                    "subject" => subject = decode_header(&value),
+                    _ => date = Some(value),
+                }
+            }
+            rest
+        } else {
+            &body
+        };
+
+        let from = from.ok_or("no author (From header)")?;
+        let (author_name, author_email) = parse_address(&from);
+        let timestamp = match date {
+            Some(date) => Some(parse_date(&date).ok_or_else(|| format!("invalid date: {date}"))?),
+            None => None,
+        };
+        let (message, diff) = split_body(body);
+        let description = format_description(&clean_subject(&subject), message);
+        Ok(MailPatch {
+            author_name,
+            author_email,
+            timestamp,
+            description,
+            diff: diff.to_vec(),
+        })
+    }
+
+    fn subject(&self) -> &str {
+        self.description.lines().next().unwrap_or("")
+    }
+}
+
+/// Parses the headers at the start of the `message`, and returns them with
+/// the rest of the message. Folded header lines are unfolded.
+fn parse_headers(message: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let mut headers: Vec<(String, String)> = vec![];
+    let mut offset = 0;
+    for line in message.split_inclusive(|b| *b == b'\n') {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if text.is_empty() {
+            return (headers, &message[offset + line.len()..]);
+        }
+        if text.starts_with([' ', '\t']) {
+            match headers.last_mut() {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(text.trim_start());
+                }
+                None => return (vec![], message),
+            }
+        } else {
+            match text.split_once(':') {
+                Some((key, value))
+                    if !key.is_empty() && !key.contains(|c: char| c.is_whitespace()) =>
+                {
+                    headers.push((key.to_owned(), value.trim().to_owned()));
+                }
+                _ => return (vec![], message),
+            }
+        }
+        offset += line.len();
+    }
+    (headers, &message[offset..])
+}
+
+/// Decodes the encoded words (RFC 2047) in a header value.
+fn decode_header(value: &str) -> String {
+    static ENCODED_WORD: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"=\?([^?]+)\?([QqBb])\?([^?]*)\?=").unwrap());
+    let mut decoded = String::new();
+    let mut last_end = 0;
+    for captures in ENCODED_WORD.captures_iter(value) {
+        let word = captures.get(0).unwrap();
+        let gap = &value[last_end..word.start()];
+        // Whitespace between encoded words is ignored
+        if last_end == 0 || !gap.trim().is_empty() {
+            decoded.push_str(gap);
+        }
+        last_end = word.end();
+        let text = captures[3].as_bytes();
+        let bytes = if captures[2].eq_ignore_ascii_case("q") {
+            let text = text
+                .iter()
+                .map(|&b| if b == b'_' { b' ' } else { b })
+                .collect_vec();
+            Some(decode_quoted_printable(&text, false))
+        } else {
+            decode_base64(text)
+        };
+        let Some(bytes) = bytes else {
+            decoded.push_str(word.as_str());
+            continue;
+        };
+        let charset = captures[1].to_ascii_lowercase();
+        if charset == "iso-8859-1" || charset == "latin1" {
+            decoded.extend(bytes.iter().map(|&b| char::from(b)));
+        } else {
+            decoded.push_str(&String::from_utf8_lossy(&bytes));
+        }
+    }
+    decoded.push_str(&value[last_end..]);
+    decoded
+}
+
+fn decode_quoted_printable(text: &[u8], soft_line_breaks: bool) -> Vec<u8> {
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == b'=' {
+            let rest = &text[i + 1..];
+            if soft_line_breaks && rest.starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            } else if soft_line_breaks && rest.starts_with(b"\n") {
+                i += 2;
+                continue;
+            } else if let Some(byte) = rest
+                .get(..2)
+                .and_then(|hex| str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(text[i]);
+        i += 1;
+    }
+    decoded
+}
+
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &b in text {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\r' | b'\n' | b' ' | b'\t' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Parses an address like `Name <email>` into the name and the email.
+fn parse_address(address: &str) -> (String, String) {
+    match address.rsplit_once('<') {
+        Some((name, email)) if email.contains('>') => {
+            let email = email.split('>').next().unwrap().trim();
+            let name = name.trim();
+            let name = match name
+                .strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+            {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => name.to_owned(),
+            };
+            (name, email.to_owned())
+        }
+        _ => (String::new(), address.trim().to_owned()),
+    }
+}
+
+fn parse_date(date: &str) -> Option<Timestamp> {
+    let date = chrono::DateTime::parse_from_rfc2822(date.trim()).ok()?;
+    Some(Timestamp {
+        timestamp: MillisSinceEpoch(date.timestamp_millis()),
+        tz_offset: date.offset().local_minus_utc() / 60,
+    })
+}
+
+/// Removes the `[PATCH]`-like prefixes and `Re:` from a subject.
+fn clean_subject(subject: &str) -> String {
+    let mut subject = subject.trim();
+    loop {
+        if subject.starts_with('[') {
+            if let Some(end) = subject.find(']') {
+                subject = subject[end + 1..].trim_start();
+                continue;
+            }
+        }
+        if subject.len() >= 3 && subject[..3].eq_ignore_ascii_case("re:") {
+            subject = subject[3..].trim_start();
+            continue;
+        }
+        return subject.to_owned();
+    }
+}
+
+/// Splits the body of an email into the message and the diff. The message
+/// ends at a `---` line, or where the diff starts if there's no such line.
+fn split_body(body: &[u8]) -> (&[u8], &[u8]) {
+    let mut offset = 0;
+    let lines = body.split_inclusive(|b| *b == b'\n').collect_vec();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.strip_suffix(b"\n").unwrap_or(line);
+        let trimmed = trimmed.strip_suffix(b"\r").unwrap_or(trimmed);
+        if trimmed == b"---" {
+            return (&body[..offset], &body[offset + line.len()..]);
+        }
+        let starts_diff = line.starts_with(b"diff ")
+            || line.starts_with(b"Index: ")
+            || (line.starts_with(b"--- ")
+                && lines
+                    .get(i + 1)
+                    .is_some_and(|next| next.starts_with(b"+++ ")));
+        if starts_diff {
+            return (&body[..offset], &body[offset..]);
+        }
+        offset += line.len();
+    }
+    (body, b"")
+}
+
+/// Returns the description from the subject and the message of an email.
+/// The change ID trailer added by `jj format-patch` is removed.
+fn format_description(subject: &str, message: &[u8]) -> String {
+    static CHANGE_ID_TRAILER: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^Change-Id: [k-z]{32}\s*\z").unwrap());
+    let message = String::from_utf8_lossy(message).replace("\r\n", "\n");
+    let message = CHANGE_ID_TRAILER.replace(message.trim(), "");
+    let message = message.trim();
+    if message.is_empty() {
+        text_util::complete_newline(subject)
+    } else {
+        text_util::complete_newline(format!("{subject}\n\n{message}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_mbox() {
+        let mbox = b"From 1234 Mon Sep 17 00:00:00 2001\nSubject: a\n\nFrom me\n\n\
+                     From 5678 Mon Sep 17 00:00:00 2001\nSubject: b\n";
+        assert_eq!(
+            split_mbox(mbox),
+            vec![
+                b"Subject: a\n\nFrom me\n\n".as_slice(),
+                b"Subject: b\n".as_slice()
+            ]
+        );
+        assert_eq!(
+            split_mbox(b"Subject: a\n"),
+            vec![b"Subject: a\n".as_slice()]
+        );
+        assert!(split_mbox(b"").is_empty());
+    }
+
+    #[test]
+    fn test_parse_mail() {
+        let mail = MailPatch::parse(
+            b"From: =?UTF-8?q?Zo=C3=AB?= User <zoe@example.com>\n\
+              Date: Sat, 3 Feb 2001 04:05:07 +0700\n\
+              Subject: [PATCH 1/2] Fix\n \
+              the bug\n\
+              \n\
+              The body.\n\
+              \n\
+              Change-Id: qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu\n\
+              ---\n\
+              \x20file | 2 +-\n\
+              \n\
+              diff --git a/file b/file\n",
+        )
+        .unwrap();
+        assert_eq!(mail.author_name, "Zoë User");
+        assert_eq!(mail.author_email, "zoe@example.com");
+        assert_eq!(
+            mail.timestamp,
+            Some(Timestamp {
+                timestamp: MillisSinceEpoch(981147907000),
+                tz_offset: 420,
+            })
+        );
+        assert_eq!(mail.description, "Fix the bug\n\nThe body.\n");
+        assert_eq!(mail.diff, b" file | 2 +-\n\ndiff --git a/file b/file\n");
+
+        // Headers in the body override the ones of the email
+        let mail = MailPatch::parse(
+            b"From: Sender <sender@example.com>\n\
+              Subject: [PATCH] Fix\n\
+              \n\
+              From: \"Author, The\" <author@example.com>\n\
+              \n\
+              diff --git a/file b/file\n",
+        )
+        .unwrap();
+        assert_eq!(mail.author_name, "Author, The");
+        assert_eq!(mail.description, "Fix\n");
+        assert_eq!(mail.timestamp, None);
+
+        assert!(MailPatch::parse(b"Subject: Fix\n\n").is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_header("plain"), "plain");
+        assert_eq!(
+            decode_header("=?utf-8?B?Wm/Dqw==?= =?iso-8859-1?q?_caf=E9?= ok"),
+            "Zoë café ok"
+        );
+        assert_eq!(
+            decode_quoted_printable(b"a=3Db=\nc", true),
+            b"a=bc".to_vec()
+        );
+        assert_eq!(clean_subject("Re: [PATCH v2 3/4] [RFC] Fix"), "Fix");
+    }
+}
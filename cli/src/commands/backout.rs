@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use jj_lib::merged_tree::MergeStrategy;
 use jj_lib::object_id::ObjectId;
-use jj_lib::rewrite::back_out_commit;
+use jj_lib::rewrite::back_out_commit_with_strategy;
 use tracing::instrument;
 
-use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::cli_util::{
+    print_resolved_conflicts, CommandError, CommandHelper, MergeStrategyArg, RevisionArg,
+};
 use crate::ui::Ui;
 
 /// Apply the reverse of a revision on top of another revision
@@ -30,6 +33,12 @@ pub(crate) struct BackoutArgs {
     // copy should be rebased on top?
     #[arg(long, short, default_value = "@")]
     destination: Vec<RevisionArg>,
+    /// Resolve the conflicts by taking one side
+    ///
+    /// "ours" is the destination, and "theirs" is the reverse of the revision.
+    /// The files that were resolved are listed.
+    #[arg(long, value_enum)]
+    strategy: Option<MergeStrategyArg>,
 }
 
 #[instrument(skip_all)]
@@ -45,13 +54,15 @@ pub(crate) fn cmd_backout(
         let destination = workspace_command.resolve_single_rev(revision_str, ui)?;
         parents.push(destination);
     }
-    let mut tx = workspace_command.start_transaction();
-    back_out_commit(
+    let mut tx = workspace_command.start_transaction()?;
+    let (new_commit, resolved_paths) = back_out_commit_with_strategy(
         command.settings(),
         tx.mut_repo(),
         &commit_to_back_out,
         &parents,
+        args.strategy.map(MergeStrategy::from),
     )?;
+    print_resolved_conflicts(ui, &tx, &new_commit, &resolved_paths)?;
     tx.finish(
         ui,
         format!("back out commit {}", commit_to_back_out.id().hex()),
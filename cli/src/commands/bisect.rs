@@ -0,0 +1,233 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+use std::process::Command;
+
+use itertools::Itertools;
+use jj_lib::backend::CommitId;
+use jj_lib::bisect::{BisectionResult, Bisector, Evaluation, NextStep};
+use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
+use jj_lib::revset::RevsetExpression;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper,
+};
+use crate::ui::Ui;
+
+/// Find a bad revision by bisection
+#[derive(clap::Subcommand, Clone, Debug)]
+pub(crate) enum BisectCommand {
+    Run(BisectRunArgs),
+}
+
+/// Run a command to find the first bad revision in a range
+///
+/// The revisions in the range are checked out one at a time, picking one in
+/// the middle of the remaining candidates each time, and the command is run in
+/// the workspace root. Its exit status says whether the revision is good or
+/// bad:
+///
+/// * 0: the revision is good, and so are its ancestors
+/// * 125: the revision can't be tested, for example because it doesn't build
+/// * 1-127 otherwise: the revision is bad, and so are its descendants
+///
+/// Any other exit status, or the command being killed by a signal, aborts the
+/// bisection. Each revision is checked out in a new working-copy commit on top
+/// of it, which is abandoned afterwards, and the original working-copy commit
+/// is checked out again at the end.
+///
+/// For example, to find the revision that broke the tests:
+///
+/// $ jj bisect run --range 'v1.0..@' -- cargo test
+#[derive(clap::Args, Clone, Debug)]
+#[command(verbatim_doc_comment)]
+pub(crate) struct BisectRunArgs {
+    /// Range of revisions to bisect
+    #[arg(long, short, required = true)]
+    range: Vec<RevisionArg>,
+    /// Command to run on each revision
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_bisect(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &BisectCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        BisectCommand::Run(sub_args) => cmd_bisect_run(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_bisect_run(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BisectRunArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let range = resolve_multiple_nonempty_revsets(&args.range, &workspace_command, ui)?
+        .iter()
+        .map(|commit| commit.id().clone())
+        .collect_vec();
+    let original_wc_commit_id = workspace_command
+        .get_wc_commit_id()
+        .ok_or_else(|| user_error("This command requires a working copy"))?
+        .clone();
+
+    let mut temp_wc_commit_id = None;
+    let result = run_bisection(
+        ui,
+        command,
+        &mut workspace_command,
+        RevsetExpression::commits(range),
+        &args.command,
+        &mut temp_wc_commit_id,
+    );
+
+    // Restore the working copy even if the bisection failed
+    if let Some(temp_wc_commit_id) = temp_wc_commit_id {
+        workspace_command.maybe_snapshot(ui)?;
+        let mut tx = workspace_command.start_transaction()?;
+        let workspace_id = tx.base_workspace_helper().workspace_id().clone();
+        tx.mut_repo()
+            .set_wc_commit(workspace_id, original_wc_commit_id)?;
+        tx.mut_repo().record_abandoned_commit(temp_wc_commit_id);
+        tx.finish(ui, "restore working copy after bisection")?;
+    }
+    let result = result?;
+
+    let repo = workspace_command.repo().clone();
+    let write_commits = |ui: &mut Ui, commit_ids: &[CommitId]| -> Result<(), CommandError> {
+        let mut formatter = ui.stdout_formatter();
+        for commit_id in commit_ids {
+            let commit = repo.store().get_commit(commit_id)?;
+            write!(formatter, "  ")?;
+            workspace_command.write_commit_summary(formatter.as_mut(), &commit)?;
+            writeln!(formatter)?;
+        }
+        Ok(())
+    };
+    match result {
+        BisectionResult::NoBadCommits => {
+            writeln!(ui.stdout(), "None of the revisions in the range is bad")?;
+        }
+        BisectionResult::Found(commit_ids) => {
+            if commit_ids.len() == 1 {
+                writeln!(ui.stdout(), "The first bad revision is:")?;
+            } else {
+                writeln!(ui.stdout(), "The first bad revisions are:")?;
+            }
+            write_commits(ui, &commit_ids)?;
+            for commit_id in &commit_ids {
+                let commit = repo.store().get_commit(commit_id)?;
+                if commit.description().trim_end().contains('\n') {
+                    writeln!(ui.stdout())?;
+                    for line in commit.description().lines() {
+                        writeln!(ui.stdout(), "    {line}")?;
+                    }
+                }
+            }
+        }
+        BisectionResult::Indeterminate(commit_ids) => {
+            writeln!(
+                ui.stdout(),
+                "The first bad revision is one of these, but some of them were skipped:"
+            )?;
+            write_commits(ui, &commit_ids)?;
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates revisions until the bisection is done. `temp_wc_commit_id` is
+/// set to the working-copy commit created for the revision being evaluated.
+fn run_bisection(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    workspace_command: &mut WorkspaceCommandHelper,
+    input_range: std::rc::Rc<RevsetExpression>,
+    shell_command: &[String],
+    temp_wc_commit_id: &mut Option<CommitId>,
+) -> Result<BisectionResult, CommandError> {
+    let repo = workspace_command.repo().clone();
+    let mut bisector = Bisector::new(repo.as_ref(), input_range);
+    loop {
+        let commit_id = match bisector.next_step()? {
+            NextStep::Evaluate(commit_id) => commit_id,
+            NextStep::Done(result) => return Ok(result),
+        };
+        let commit = repo.store().get_commit(&commit_id)?;
+
+        workspace_command.maybe_snapshot(ui)?;
+        let mut tx = workspace_command.start_transaction()?;
+        write!(ui.stderr(), "Now evaluating: ")?;
+        tx.write_commit_summary(ui.stderr_formatter().as_mut(), &commit)?;
+        writeln!(ui.stderr())?;
+        let wc_commit = tx
+            .mut_repo()
+            .new_commit(
+                command.settings(),
+                vec![commit_id.clone()],
+                commit.tree_id().clone(),
+            )
+            .write()?;
+        let workspace_id = tx.base_workspace_helper().workspace_id().clone();
+        tx.mut_repo()
+            .set_wc_commit(workspace_id, wc_commit.id().clone())?;
+        if let Some(old_temp_wc_commit_id) = temp_wc_commit_id.replace(wc_commit.id().clone()) {
+            tx.mut_repo().record_abandoned_commit(old_temp_wc_commit_id);
+        }
+        tx.finish(
+            ui,
+            format!("check out commit {} for bisection", commit_id.hex()),
+        )?;
+
+        let evaluation = run_command(workspace_command, shell_command)?;
+        let message = match evaluation {
+            Evaluation::Good => "The revision is good.",
+            Evaluation::Bad => "The revision is bad.",
+            Evaluation::Skip => "The revision can't be evaluated. Skipping it.",
+        };
+        writeln!(ui.stderr(), "{message}")?;
+        writeln!(ui.stderr())?;
+        bisector.mark(commit_id, evaluation);
+    }
+}
+
+fn run_command(
+    workspace_command: &WorkspaceCommandHelper,
+    shell_command: &[String],
+) -> Result<Evaluation, CommandError> {
+    let (name, args) = shell_command.split_first().unwrap();
+    let status = Command::new(name)
+        .args(args)
+        .current_dir(workspace_command.workspace_root())
+        .status()
+        .map_err(|err| user_error(format!("Failed to run {name}: {err}")))?;
+    match status.code() {
+        Some(0) => Ok(Evaluation::Good),
+        Some(125) => Ok(Evaluation::Skip),
+        Some(1..=127) => Ok(Evaluation::Bad),
+        _ => Err(user_error(format!(
+            "{name} exited with {status}; aborting the bisection"
+        ))),
+    }
+}
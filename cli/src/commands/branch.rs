@@ -31,6 +31,7 @@ use crate::cli_util::{
     parse_string_pattern, user_error, user_error_with_hint, CommandError, CommandHelper,
     RemoteBranchName, RemoteBranchNamePattern, RevisionArg,
 };
+use crate::error_code::ErrorCode;
 use crate::formatter::Formatter;
 use crate::ui::Ui;
 
@@ -117,6 +118,18 @@ pub struct BranchListArgs {
     /// wouldn't have a local target.
     #[arg(long, short)]
     revisions: Vec<RevisionArg>,
+
+    /// Show only branches that track a remote branch
+    ///
+    /// Branches that only track the underlying Git repo aren't shown. Combine
+    /// with names or revisions to narrow down the list further.
+    #[arg(long, short, conflicts_with = "all")]
+    tracked: bool,
+
+    /// Show only branches whose local target or a remote target is
+    /// conflicted
+    #[arg(long, short)]
+    conflicted: bool,
 }
 
 /// Forget everything about a branch, including its local and remote
@@ -255,7 +268,7 @@ fn cmd_branch_create(
         )?;
     }
 
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for branch_name in branch_names {
         tx.mut_repo()
             .set_local_branch_target(branch_name, RefTarget::normal(target_commit.id().clone()));
@@ -281,7 +294,9 @@ fn cmd_branch_rename(
     let old_branch = &args.old;
     let ref_target = view.get_local_branch(old_branch).clone();
     if ref_target.is_absent() {
-        return Err(user_error(format!("No such branch: {old_branch}")));
+        return Err(
+            user_error(format!("No such branch: {old_branch}")).with_code(ErrorCode::NoSuchBranch)
+        );
     }
 
     let new_branch = &args.new;
@@ -302,7 +317,7 @@ fn cmd_branch_rename(
         )?;
     }
 
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     tx.mut_repo()
         .set_local_branch_target(new_branch, ref_target);
     tx.mut_repo()
@@ -342,7 +357,8 @@ fn cmd_branch_set(
             return Err(user_error_with_hint(
                 format!("No such branch: {name}"),
                 "Use `jj branch create` to create it.",
-            ));
+            )
+            .with_code(ErrorCode::NoSuchBranch));
         }
         if !args.allow_backwards && !is_fast_forward(old_target) {
             return Err(user_error_with_hint(
@@ -360,7 +376,7 @@ fn cmd_branch_set(
         )?;
     }
 
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for branch_name in branch_names {
         tx.mut_repo()
             .set_local_branch_target(branch_name, RefTarget::normal(target_commit.id().clone()));
@@ -416,7 +432,9 @@ fn find_branches_with<'a, I: Iterator<Item = String>>(
             matching_branches.dedup();
             Ok(matching_branches)
         }
-        [pattern] if pattern.is_exact() => Err(user_error(format!("No such branch: {pattern}"))),
+        [pattern] if pattern.is_exact() => {
+            Err(user_error(format!("No such branch: {pattern}")).with_code(ErrorCode::NoSuchBranch))
+        }
         patterns => Err(user_error(format!(
             "No matching branches for patterns: {}",
             patterns.iter().join(", ")
@@ -477,7 +495,7 @@ fn cmd_branch_delete(
     }
     let name_patterns = [&args.names[..], &args.glob[..]].concat();
     let names = find_local_branches(view, &name_patterns)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for branch_name in names.iter() {
         tx.mut_repo()
             .set_local_branch_target(branch_name, RefTarget::absent());
@@ -504,7 +522,7 @@ fn cmd_branch_forget(
     }
     let name_patterns = [&args.names[..], &args.glob[..]].concat();
     let names = find_forgettable_branches(view, &name_patterns)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for branch_name in names.iter() {
         tx.mut_repo().remove_branch(branch_name);
     }
@@ -530,7 +548,7 @@ fn cmd_branch_track(
             names.push(name);
         }
     }
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for name in &names {
         tx.mut_repo()
             .track_remote_branch(&name.branch, &name.remote);
@@ -567,7 +585,7 @@ fn cmd_branch_untrack(
             names.push(name);
         }
     }
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     for name in &names {
         tx.mut_repo()
             .untrack_remote_branch(&name.branch, &name.remote);
@@ -666,10 +684,20 @@ fn cmd_branch_list(
     let mut formatter = ui.stdout_formatter();
     let formatter = formatter.as_mut();
 
-    let branches_to_list = view.branches().filter(|&(name, _)| {
+    // The --tracked and --conflicted filters are AND-ed with names and
+    // revisions.
+    let branches_to_list = view.branches().filter(|(name, branch_target)| {
+        let mut remote_refs = branch_target.remote_refs.iter();
         branch_names_to_list
             .as_ref()
             .map_or(true, |branch_names| branch_names.contains(name))
+            && (!args.tracked
+                || remote_refs.clone().any(|&(remote, remote_ref)| {
+                    remote_ref.is_tracking() && remote != git::REMOTE_NAME_FOR_LOCAL_GIT_REPO
+                }))
+            && (!args.conflicted
+                || branch_target.local_target.has_conflict()
+                || remote_refs.any(|(_, remote_ref)| remote_ref.target.has_conflict()))
     });
     for (name, branch_target) in branches_to_list {
         let (tracking_remote_refs, untracked_remote_refs) =
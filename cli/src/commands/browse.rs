@@ -0,0 +1,573 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::io::{self, IsTerminal as _, Write};
+use std::ops::Range;
+use std::process::Command;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, queue};
+use itertools::Itertools as _;
+use jj_lib::backend::CommitId;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo as _;
+use jj_lib::revset::{self, RevsetExpression};
+use jj_lib::revset_graph::TopoGroupedRevsetGraphIterator;
+use tracing::instrument;
+
+use crate::cli_util::{
+    short_commit_hash, user_error, CommandError, CommandHelper, LogContentFormat, RevisionArg,
+    WorkspaceCommandHelper,
+};
+use crate::commands::log::to_graphlog_edges;
+use crate::diff_util::{self, DiffFormatArgs};
+use crate::graphlog::get_graphlog;
+use crate::pager::{render_line, AlternateScreen, Content, KeyAction, PagerState};
+use crate::ui::Ui;
+
+const HELP: &str = "Enter: diff  e: edit  n: new  d: describe  a: abandon  q: quit";
+
+/// Browse the commit graph interactively
+///
+/// Shows the log on a full screen, where you can move between commits and
+/// act on the selected one:
+///
+/// * `j` and `k`, or the arrow keys: select the next or previous commit
+/// * `g` and `G`: select the first or last commit
+/// * `Enter`: show the description and diff of the commit
+/// * `e`: edit the commit (`jj edit`)
+/// * `n`: create a new commit on top of it (`jj new`)
+/// * `d`: update its description (`jj describe`)
+/// * `a`: abandon it (`jj abandon`), after confirming
+/// * `q`: quit
+///
+/// The commands run the same way as on the command line, and the log is shown
+/// again when they're done.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct BrowseArgs {
+    /// Which revisions to show. Defaults to the `revsets.log` setting, like in
+    /// `jj log`.
+    #[arg(long, short)]
+    revisions: Vec<RevisionArg>,
+    #[command(flatten)]
+    diff_format: DiffFormatArgs,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_browse(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BrowseArgs,
+) -> Result<(), CommandError> {
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return Err(user_error("jj browse must be run in a terminal"));
+    }
+    let mut selected_id = None;
+    let mut message = None;
+    loop {
+        let workspace_command = command.workspace_helper(ui)?;
+        let graph = render_graph(ui, command, &workspace_command, args)?;
+        let (width, height) = terminal::size()?;
+        let mut state = BrowseState::new(graph, width, height);
+        if let Some(id) = &selected_id {
+            state.select_commit(id);
+        }
+        state.message = message.take();
+        let jj_args = {
+            let _screen = AlternateScreen::enter()?;
+            run_browser(ui, command, &workspace_command, args, &mut state)?
+        };
+        let Some(jj_args) = jj_args else {
+            return Ok(());
+        };
+        selected_id = state.selected_commit_id().cloned();
+        message = Some(run_jj(&workspace_command, &jj_args)?);
+    }
+}
+
+/// The rendered graph, and the lines of each commit in it.
+#[derive(Debug, Default)]
+struct Graph {
+    content: Content,
+    commits: Vec<(CommitId, Range<usize>)>,
+}
+
+/// Writes to a buffer that can be read between writes.
+struct SharedBuffer<'a>(&'a RefCell<Vec<u8>>);
+
+impl Write for SharedBuffer<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn render_graph(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    args: &BrowseArgs,
+) -> Result<Graph, CommandError> {
+    let expression = if args.revisions.is_empty() {
+        workspace_command.parse_revset(&command.settings().default_revset(), Some(ui))?
+    } else {
+        let expressions: Vec<_> = args
+            .revisions
+            .iter()
+            .map(|revision_str| workspace_command.parse_revset(revision_str, Some(ui)))
+            .try_collect()?;
+        RevsetExpression::union_all(&expressions)
+    };
+    let revset = workspace_command.evaluate_revset(revset::optimize(expression))?;
+    let store = workspace_command.repo().store();
+    let wc_commit_id = workspace_command.get_wc_commit_id();
+    let template_string = command.settings().config().get_string("templates.log")?;
+    let template = workspace_command.parse_commit_template(&template_string)?;
+    let with_content_format = LogContentFormat::new(ui, command.settings())?;
+
+    let buffer = RefCell::new(vec![]);
+    let count_lines = || buffer.borrow().iter().filter(|&&b| b == b'\n').count();
+    let mut commits = vec![];
+    {
+        let mut writer = SharedBuffer(&buffer);
+        let mut graph = get_graphlog(command.settings(), &mut writer);
+        let default_node_symbol = graph.default_node_symbol().to_owned();
+        for (commit_id, edges) in TopoGroupedRevsetGraphIterator::new(revset.iter_graph()) {
+            let edges = to_graphlog_edges(edges);
+            let commit = store.get_commit(&commit_id)?;
+            let mut text = vec![];
+            with_content_format.write_graph_text(
+                ui.new_formatter(&mut text).as_mut(),
+                |formatter| template.format(&commit, formatter),
+                || graph.width(&commit_id, &edges),
+            )?;
+            if !text.ends_with(b"\n") {
+                text.push(b'\n');
+            }
+            let node_symbol = if Some(&commit_id) == wc_commit_id {
+                "@"
+            } else {
+                &default_node_symbol
+            };
+            let start = count_lines();
+            graph.add_node(
+                &commit_id,
+                &edges,
+                node_symbol,
+                &String::from_utf8_lossy(&text),
+            )?;
+            commits.push((commit_id, start..count_lines()));
+        }
+    }
+    let mut content = Content::default();
+    content.push(&buffer.into_inner());
+    content.finish();
+    Ok(Graph { content, commits })
+}
+
+/// Shows the graph until the user quits or selects a command to run. Returns
+/// the arguments of the command.
+fn run_browser(
+    ui: &Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    args: &BrowseArgs,
+    state: &mut BrowseState,
+) -> Result<Option<Vec<String>>, CommandError> {
+    let mut stdout = io::stdout();
+    loop {
+        state.draw(&mut stdout)?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match state.handle_key(key) {
+                BrowseAction::Continue => {}
+                BrowseAction::Quit => return Ok(None),
+                BrowseAction::Run(jj_args) => return Ok(Some(jj_args)),
+                BrowseAction::ShowDiff => {
+                    let Some(id) = state.selected_commit_id() else {
+                        continue;
+                    };
+                    let content = render_diff(ui, command, workspace_command, args, id, state)?;
+                    show_in_pager(content, state)?;
+                }
+            },
+            Event::Resize(width, height) => state.resize(width, height),
+            _ => {}
+        }
+    }
+}
+
+/// Renders the commit like `jj show`.
+fn render_diff(
+    ui: &Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    args: &BrowseArgs,
+    commit_id: &CommitId,
+    state: &BrowseState,
+) -> Result<Content, CommandError> {
+    let commit = workspace_command.repo().store().get_commit(commit_id)?;
+    let template_string = command.settings().config().get_string("templates.show")?;
+    let template = workspace_command.parse_commit_template(&template_string)?;
+    let diff_formats = diff_util::diff_formats_for(command.settings(), &args.diff_format)?;
+    let mut buffer = vec![];
+    {
+        let mut formatter = ui.new_formatter(&mut buffer);
+        template.format(&commit, formatter.as_mut())?;
+        diff_util::show_patch(
+            ui,
+            formatter.as_mut(),
+            workspace_command,
+            &commit,
+            &EverythingMatcher,
+            &diff_formats,
+            usize::from(state.width),
+        )?;
+    }
+    let mut content = Content::default();
+    content.push(&buffer);
+    content.finish();
+    Ok(content)
+}
+
+fn show_in_pager(content: Content, state: &mut BrowseState) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let mut pager = PagerState::new(content, state.width, state.height);
+    loop {
+        pager.draw(&mut stdout, true)?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match pager.handle_key(key) {
+                KeyAction::Quit => return Ok(()),
+                KeyAction::Continue => {}
+            },
+            Event::Resize(width, height) => {
+                pager.resize(width, height);
+                state.resize(width, height);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Runs `jj` with the arguments on the normal screen. Returns a message
+/// describing the result.
+fn run_jj(
+    workspace_command: &WorkspaceCommandHelper,
+    jj_args: &[String],
+) -> Result<String, CommandError> {
+    let command_line = format!("jj {}", jj_args.join(" "));
+    let status = Command::new(std::env::current_exe()?)
+        .arg("--repository")
+        .arg(workspace_command.workspace_root())
+        .args(jj_args)
+        .status()?;
+    if status.success() {
+        Ok(format!("Ran `{command_line}`"))
+    } else {
+        // Let the user read the error before it's hidden by the graph
+        eprint!("Press Enter to continue");
+        io::stdin().read_line(&mut String::new())?;
+        Ok(format!("`{command_line}` failed"))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum BrowseAction {
+    Continue,
+    Quit,
+    ShowDiff,
+    Run(Vec<String>),
+}
+
+#[derive(Debug)]
+struct BrowseState {
+    graph: Graph,
+    /// Index of the selected commit in `graph.commits`.
+    selected: usize,
+    /// Index of the first line on the screen.
+    top: usize,
+    width: u16,
+    height: u16,
+    /// Whether the user is asked to confirm abandoning the selected commit.
+    confirm_abandon: bool,
+    message: Option<String>,
+}
+
+impl BrowseState {
+    fn new(graph: Graph, width: u16, height: u16) -> Self {
+        BrowseState {
+            graph,
+            selected: 0,
+            top: 0,
+            width,
+            height,
+            confirm_abandon: false,
+            message: None,
+        }
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.scroll_to_selected();
+    }
+
+    /// Number of lines that fit above the status line.
+    fn page_height(&self) -> usize {
+        usize::from(self.height).saturating_sub(1).max(1)
+    }
+
+    fn selected_commit_id(&self) -> Option<&CommitId> {
+        self.graph.commits.get(self.selected).map(|(id, _)| id)
+    }
+
+    fn select(&mut self, index: usize) {
+        if !self.graph.commits.is_empty() {
+            self.selected = index.min(self.graph.commits.len() - 1);
+            self.scroll_to_selected();
+        }
+    }
+
+    fn select_commit(&mut self, commit_id: &CommitId) {
+        if let Some(index) = self
+            .graph
+            .commits
+            .iter()
+            .position(|(id, _)| id == commit_id)
+        {
+            self.select(index);
+        }
+    }
+
+    /// Selects the commit shown on the line, or the last one if the line is
+    /// past the end.
+    fn select_line(&mut self, line: usize) {
+        let index = self
+            .graph
+            .commits
+            .iter()
+            .position(|(_, lines)| line < lines.end)
+            .unwrap_or(usize::MAX);
+        self.select(index);
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let Some((_, lines)) = self.graph.commits.get(self.selected) else {
+            return;
+        };
+        let page = self.page_height();
+        if lines.end > self.top + page {
+            self.top = lines.end - page;
+        }
+        if lines.start < self.top {
+            self.top = lines.start;
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> BrowseAction {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return BrowseAction::Quit;
+        }
+        self.message = None;
+        let Some(commit_id) = self.selected_commit_id().cloned() else {
+            return match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => BrowseAction::Quit,
+                _ => BrowseAction::Continue,
+            };
+        };
+        let run = |command: &str| BrowseAction::Run(vec![command.to_owned(), commit_id.hex()]);
+        if self.confirm_abandon {
+            self.confirm_abandon = false;
+            if key.code == KeyCode::Char('y') {
+                return run("abandon");
+            }
+            return BrowseAction::Continue;
+        }
+        let page = self.page_height();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return BrowseAction::Quit,
+            KeyCode::Char('j') | KeyCode::Down => self.select(self.selected + 1),
+            KeyCode::Char('k') | KeyCode::Up => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char('g') | KeyCode::Home => self.select(0),
+            KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                let line = self.graph.commits[self.selected].1.start + page;
+                self.select_line(line);
+            }
+            KeyCode::PageUp => {
+                let line = self.graph.commits[self.selected]
+                    .1
+                    .start
+                    .saturating_sub(page);
+                self.select_line(line);
+            }
+            KeyCode::Enter => return BrowseAction::ShowDiff,
+            KeyCode::Char('e') => return run("edit"),
+            KeyCode::Char('n') => return run("new"),
+            KeyCode::Char('d') => return run("describe"),
+            KeyCode::Char('a') => {
+                self.confirm_abandon = true;
+                self.message = Some(format!(
+                    "Abandon commit {}? (y/n)",
+                    short_commit_hash(&commit_id)
+                ));
+            }
+            _ => {}
+        }
+        BrowseAction::Continue
+    }
+
+    fn status_line(&self) -> &str {
+        self.message.as_deref().unwrap_or(HELP)
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let width = usize::from(self.width);
+        let selected_lines = self
+            .graph
+            .commits
+            .get(self.selected)
+            .map_or(0..0, |(_, lines)| lines.clone());
+        for row in 0..self.page_height() {
+            let index = self.top + row;
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            if let Some(line) = self.graph.content.lines.get(index) {
+                let marker = if index == selected_lines.start {
+                    "> "
+                } else {
+                    "  "
+                };
+                out.write_all(marker.as_bytes())?;
+                let line = render_line(line, 0, width.saturating_sub(marker.len()));
+                out.write_all(line.as_bytes())?;
+                queue!(out, SetAttribute(Attribute::Reset))?;
+            }
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, self.height.saturating_sub(1)),
+            SetAttribute(Attribute::Reverse),
+        )?;
+        out.write_all(render_line(self.status_line(), 0, width).as_bytes())?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Reset),
+            Clear(ClearType::UntilNewLine),
+        )?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    /// Creates a graph with commits that have the given numbers of lines.
+    fn graph_of(line_counts: &[usize]) -> Graph {
+        let mut graph = Graph::default();
+        let mut start = 0;
+        for (i, &count) in line_counts.iter().enumerate() {
+            for line in 0..count {
+                graph.content.lines.push(format!("commit {i} line {line}"));
+            }
+            let id = CommitId::new(vec![i as u8; 20]);
+            graph.commits.push((id, start..start + count));
+            start += count;
+        }
+        graph
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut state = BrowseState::new(graph_of(&[2, 2, 3, 2]), 20, 4);
+        assert_eq!(state.page_height(), 3);
+        state.handle_key(key(KeyCode::Char('j')));
+        assert_eq!((state.selected, state.top), (1, 1));
+        state.handle_key(key(KeyCode::Down));
+        assert_eq!((state.selected, state.top), (2, 4));
+        state.handle_key(key(KeyCode::Char('G')));
+        assert_eq!((state.selected, state.top), (3, 6));
+        state.handle_key(key(KeyCode::Char('j')));
+        assert_eq!((state.selected, state.top), (3, 6));
+        state.handle_key(key(KeyCode::Up));
+        assert_eq!((state.selected, state.top), (2, 4));
+        state.handle_key(key(KeyCode::PageUp));
+        assert_eq!((state.selected, state.top), (0, 0));
+        state.handle_key(key(KeyCode::PageDown));
+        assert_eq!((state.selected, state.top), (1, 1));
+
+        state.select_commit(&CommitId::new(vec![2; 20]));
+        assert_eq!(state.selected, 2);
+        state.handle_key(key(KeyCode::Char('g')));
+        assert_eq!((state.selected, state.top), (0, 0));
+    }
+
+    #[test]
+    fn test_actions() {
+        let mut state = BrowseState::new(graph_of(&[1, 1]), 20, 4);
+        state.handle_key(key(KeyCode::Char('j')));
+        let run = |command: &str| BrowseAction::Run(vec![command.to_owned(), "01".repeat(20)]);
+        assert_eq!(state.handle_key(key(KeyCode::Char('e'))), run("edit"));
+        assert_eq!(state.handle_key(key(KeyCode::Char('n'))), run("new"));
+        assert_eq!(state.handle_key(key(KeyCode::Char('d'))), run("describe"));
+        assert_eq!(
+            state.handle_key(key(KeyCode::Enter)),
+            BrowseAction::ShowDiff
+        );
+
+        // Abandoning must be confirmed
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('a'))),
+            BrowseAction::Continue
+        );
+        assert_eq!(state.status_line(), "Abandon commit 010101010101? (y/n)");
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('n'))),
+            BrowseAction::Continue
+        );
+        assert_eq!(state.status_line(), HELP);
+        state.handle_key(key(KeyCode::Char('a')));
+        assert_eq!(state.handle_key(key(KeyCode::Char('y'))), run("abandon"));
+
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('q'))),
+            BrowseAction::Quit
+        );
+    }
+
+    #[test]
+    fn test_empty_graph() {
+        let mut state = BrowseState::new(Graph::default(), 20, 4);
+        assert_eq!(state.selected_commit_id(), None);
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('e'))),
+            BrowseAction::Continue
+        );
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('q'))),
+            BrowseAction::Quit
+        );
+    }
+}
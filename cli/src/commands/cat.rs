@@ -29,7 +29,7 @@ pub(crate) struct CatArgs {
     #[arg(long, short, default_value = "@")]
     revision: RevisionArg,
     /// The file to print
-    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
     path: String,
 }
 
@@ -39,7 +39,7 @@ pub(crate) fn cmd_cat(
     command: &CommandHelper,
     args: &CatArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
     let tree = commit.tree()?;
     let path = workspace_command.parse_file_path(&args.path)?;
@@ -51,7 +51,7 @@ pub(crate) fn cmd_checkout(
     )?;
     let mut workspace_command = command.workspace_helper(ui)?;
     let target = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let commit_builder = tx
         .mut_repo()
         .new_commit(
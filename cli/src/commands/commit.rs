@@ -18,7 +18,9 @@ use jj_lib::rewrite::merge_commit_trees;
 use tracing::instrument;
 
 use crate::cli_util::{join_message_paragraphs, user_error, CommandError, CommandHelper};
-use crate::description_util::{description_template_for_commit, edit_description};
+use crate::description_util::{
+    add_commit_trailers, check_description, description_template_for_commit, edit_description,
+};
 use crate::ui::Ui;
 
 /// Update the description and create a new change on top.
@@ -34,6 +36,9 @@ pub(crate) struct CommitArgs {
     /// Put these paths in the first commit
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Don't check the description against `description-checks`
+    #[arg(long)]
+    skip_checks: bool,
 }
 
 #[instrument(skip_all)]
@@ -48,9 +53,11 @@ pub(crate) fn cmd_commit(
         .get_wc_commit_id()
         .ok_or_else(|| user_error("This command requires a working copy"))?;
     let commit = workspace_command.repo().store().get_commit(commit_id)?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
-    let mut tx = workspace_command.start_transaction();
-    let base_tree = merge_commit_trees(tx.repo(), &commit.parents())?;
+    let base_tree = merge_commit_trees(workspace_command.repo().as_ref(), &commit.parents())?;
+    let commit_tree = commit.tree()?;
+    let matcher =
+        workspace_command.matcher_from_values(&args.paths, &[&base_tree, &commit_tree])?;
+    let mut tx = workspace_command.start_transaction()?;
     let instructions = format!(
         "\
 You are splitting the working-copy commit: {}
@@ -64,7 +71,7 @@ new working-copy commit.
     let tree_id = tx.select_diff(
         ui,
         &base_tree,
-        &commit.tree()?,
+        &commit_tree,
         matcher.as_ref(),
         &instructions,
         args.interactive,
@@ -93,6 +100,15 @@ new working-copy commit.
     } else {
         edit_description(tx.base_repo(), &template, command.settings())?
     };
+    let description = add_commit_trailers(
+        command.settings(),
+        tx.base_workspace_helper(),
+        &commit,
+        description,
+    )?;
+    if !args.skip_checks {
+        check_description(command.settings(), &description)?;
+    }
 
     let new_commit = tx
         .mut_repo()
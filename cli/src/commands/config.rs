@@ -19,10 +19,11 @@ use itertools::Itertools;
 use tracing::instrument;
 
 use crate::cli_util::{
-    get_new_config_file_path, run_ui_editor, serialize_config_value, user_error,
-    write_config_value_to_file, CommandError, CommandHelper,
+    check_config_file, get_new_config_file_path, run_ui_editor, serialize_config_value, user_error,
+    user_error_with_hint, write_config_value_to_file, CommandError, CommandHelper,
 };
-use crate::config::{AnnotatedValue, ConfigSource};
+use crate::config::{AnnotatedValue, ConfigSource, ProjectConfig, PROJECT_CONFIG_FILE};
+use crate::formatter::available_themes;
 use crate::ui::Ui;
 
 #[derive(clap::Args, Clone, Debug)]
@@ -69,6 +70,10 @@ pub(crate) enum ConfigCommand {
     Edit(ConfigEditArgs),
     #[command(visible_alias("p"))]
     Path(ConfigPathArgs),
+    Check(ConfigCheckArgs),
+    Trust(ConfigTrustArgs),
+    #[command(subcommand)]
+    Theme(ConfigThemeCommand),
 }
 
 /// List variables set in config file, along with their values.
@@ -90,7 +95,10 @@ pub(crate) struct ConfigListArgs {
     /// Target the repo-level config
     #[arg(long)]
     repo: bool,
-    // TODO(#1047): Support --show-origin using LayeredConfigs.
+    /// Print where each value comes from after it, as one of "default",
+    /// "env", "project", "user", "repo", or "cli"
+    #[arg(long)]
+    pub include_origin: bool,
 }
 
 impl ConfigListArgs {
@@ -136,7 +144,7 @@ pub(crate) struct ConfigSetArgs {
 /// Start an editor on a jj config file.
 ///
 /// Creates the file if it doesn't already exist regardless of what the editor
-/// does.
+/// does. The file is checked to be valid TOML after the editor exits.
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct ConfigEditArgs {
     #[clap(flatten)]
@@ -154,6 +162,55 @@ pub(crate) struct ConfigPathArgs {
     pub config_args: ConfigArgs,
 }
 
+/// Check the config files for unknown keys and invalid values
+///
+/// The user config and the repo config are checked against the schema printed
+/// by `jj util config-schema`. Each problem is printed with the file and line
+/// it's at.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct ConfigCheckArgs {}
+
+/// Trust the project config in the workspace root
+///
+/// A project can commit a `.jjconfig.toml` file to the root of its tree to
+/// share revset aliases, template aliases, immutable heads, and fix tools with
+/// everyone working on it. Since the file could make jj run arbitrary tools,
+/// it's only used once you trust it. jj asks about a new or changed file when
+/// it can prompt; this command trusts the current contents of the file without
+/// asking.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct ConfigTrustArgs {}
+
+/// Manage color themes
+///
+/// A theme is a set of colors under `themes.<name>.colors`, optionally with
+/// `light` and `dark` variants for terminals with light and dark backgrounds.
+/// The theme in `ui.theme` is used, and the `colors` table overrides the colors
+/// of individual labels.
+#[derive(clap::Subcommand, Clone, Debug)]
+pub(crate) enum ConfigThemeCommand {
+    List(ConfigThemeListArgs),
+    Set(ConfigThemeSetArgs),
+}
+
+/// List the available themes
+///
+/// The current theme is marked with `*`.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct ConfigThemeListArgs {}
+
+/// Set the theme to use
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct ConfigThemeSetArgs {
+    /// The name of the theme
+    name: String,
+    /// The variant of the theme to use
+    #[arg(long, value_parser = ["dark", "light"])]
+    variant: Option<String>,
+    #[clap(flatten)]
+    config_args: ConfigArgs,
+}
+
 #[instrument(skip_all)]
 pub(crate) fn cmd_config(
     ui: &mut Ui,
@@ -166,6 +223,14 @@ pub(crate) fn cmd_config(
         ConfigCommand::Set(sub_args) => cmd_config_set(ui, command, sub_args),
         ConfigCommand::Edit(sub_args) => cmd_config_edit(ui, command, sub_args),
         ConfigCommand::Path(sub_args) => cmd_config_path(ui, command, sub_args),
+        ConfigCommand::Check(sub_args) => cmd_config_check(ui, command, sub_args),
+        ConfigCommand::Trust(sub_args) => cmd_config_trust(ui, command, sub_args),
+        ConfigCommand::Theme(ConfigThemeCommand::List(sub_args)) => {
+            cmd_config_theme_list(ui, command, sub_args)
+        }
+        ConfigCommand::Theme(ConfigThemeCommand::Set(sub_args)) => {
+            cmd_config_theme_set(ui, command, sub_args)
+        }
     }
 }
 
@@ -204,13 +269,17 @@ pub(crate) fn cmd_config_list(
         if !args.include_defaults && *source == ConfigSource::Default {
             continue;
         }
-        writeln!(
+        write!(
             ui.stdout(),
             "{}{}={}",
             if *is_overridden { "# " } else { "" },
             path.join("."),
             serialize_config_value(value)
         )?;
+        if args.include_origin {
+            write!(ui.stdout(), " # {source}")?;
+        }
+        writeln!(ui.stdout())?;
         wrote_values = true;
     }
     if !wrote_values {
@@ -285,7 +354,8 @@ pub(crate) fn cmd_config_edit(
     args: &ConfigEditArgs,
 ) -> Result<(), CommandError> {
     let config_path = get_new_config_file_path(&args.config_args.get_source_kind(), command)?;
-    run_ui_editor(command.settings(), &config_path)
+    run_ui_editor(command.settings(), &config_path)?;
+    check_config_file(&config_path)
 }
 
 #[instrument(skip_all)]
@@ -304,3 +374,108 @@ pub(crate) fn cmd_config_path(
     )?;
     Ok(())
 }
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_config_check(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &ConfigCheckArgs,
+) -> Result<(), CommandError> {
+    let problems = command.check_config_files();
+    for problem in &problems {
+        write!(ui.stdout(), "{problem}")?;
+        if let Some(suggestion) = &problem.suggestion {
+            write!(ui.stdout(), r#" (did you mean "{suggestion}"?)"#)?;
+        }
+        writeln!(ui.stdout())?;
+    }
+    match problems.len() {
+        0 => {
+            writeln!(ui.stderr(), "No problems found in the config files.")?;
+            Ok(())
+        }
+        1 => Err(user_error("Found 1 problem in the config files")),
+        n => Err(user_error(format!(
+            "Found {n} problems in the config files"
+        ))),
+    }
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_config_trust(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &ConfigTrustArgs,
+) -> Result<(), CommandError> {
+    let loader = command.workspace_loader()?;
+    let project_config = ProjectConfig::load(loader.workspace_root(), loader.repo_path())?
+        .ok_or_else(|| {
+            user_error(format!(
+                "There is no {PROJECT_CONFIG_FILE} in the workspace root"
+            ))
+        })?;
+    project_config.trust()?;
+    writeln!(
+        ui.stderr(),
+        "Trusted the project config in {PROJECT_CONFIG_FILE}"
+    )?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_config_theme_list(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &ConfigThemeListArgs,
+) -> Result<(), CommandError> {
+    let config = command.settings().config();
+    let current_theme = config.get_string("ui.theme").ok();
+    for (name, variants) in available_themes(config)? {
+        let marker = if current_theme.as_ref() == Some(&name) {
+            '*'
+        } else {
+            ' '
+        };
+        write!(ui.stdout(), "{marker} {name}")?;
+        if !variants.is_empty() {
+            write!(ui.stdout(), " ({})", variants.join(", "))?;
+        }
+        writeln!(ui.stdout())?;
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_config_theme_set(
+    _ui: &mut Ui,
+    command: &CommandHelper,
+    args: &ConfigThemeSetArgs,
+) -> Result<(), CommandError> {
+    let themes = available_themes(command.settings().config())?;
+    let Some((_, variants)) = themes.iter().find(|(name, _)| *name == args.name) else {
+        return Err(user_error_with_hint(
+            format!("No such theme: {}", args.name),
+            "Use `jj config theme list` to see the available themes.",
+        ));
+    };
+    if let Some(variant) = &args.variant {
+        if !variants.contains(&variant.as_str()) {
+            return Err(user_error(format!(
+                "Theme {} has no {variant} variant",
+                args.name
+            )));
+        }
+    }
+    let config_path = get_new_config_file_path(&args.config_args.get_source_kind(), command)?;
+    if config_path.is_dir() {
+        return Err(user_error(format!(
+            "Can't set config in path {path} (dirs not supported)",
+            path = config_path.display()
+        )));
+    }
+    write_config_value_to_file("ui.theme", &args.name, &config_path)?;
+    if let Some(variant) = &args.variant {
+        write_config_value_to_file("ui.theme-variant", variant, &config_path)?;
+    }
+    Ok(())
+}
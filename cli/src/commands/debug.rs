@@ -15,11 +15,15 @@
 use std::any::Any;
 use std::fmt::Debug;
 use std::io::Write as _;
+use std::ops::Range;
+use std::time::Instant;
 
 use clap::Subcommand;
 use jj_lib::default_index::{AsCompositeIndex as _, DefaultIndexStore, DefaultReadonlyIndex};
 use jj_lib::local_working_copy::LocalWorkingCopy;
 use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
+use jj_lib::revset::{ResolvedExpression, ResolvedPredicateExpression, GENERATION_RANGE_FULL};
 use jj_lib::working_copy::WorkingCopy;
 use jj_lib::{op_walk, revset};
 
@@ -27,6 +31,14 @@ use crate::cli_util::{internal_error, user_error, CommandError, CommandHelper, R
 use crate::template_parser;
 use crate::ui::Ui;
 
+mod fsck;
+mod repo_archive;
+
+use self::fsck::{cmd_debug_fsck, DebugFsckArgs};
+use self::repo_archive::{
+    cmd_debug_export_repo, cmd_debug_import_repo, DebugExportRepoArgs, DebugImportRepoArgs,
+};
+
 /// Low-level commands not intended for users
 #[derive(Subcommand, Clone, Debug)]
 #[command(hide = true)]
@@ -38,9 +50,13 @@ pub enum DebugCommand {
     Index(DebugIndexArgs),
     #[command(name = "reindex")]
     ReIndex(DebugReIndexArgs),
+    IndexChangedPaths(DebugIndexChangedPathsArgs),
     #[command(visible_alias = "view")]
     Operation(DebugOperationArgs),
     Tree(DebugTreeArgs),
+    Fsck(DebugFsckArgs),
+    ExportRepo(DebugExportRepoArgs),
+    ImportRepo(DebugImportRepoArgs),
     #[command(subcommand)]
     Watchman(DebugWatchmanSubcommand),
 }
@@ -49,6 +65,12 @@ pub enum DebugCommand {
 #[derive(clap::Args, Clone, Debug)]
 pub struct DebugRevsetArgs {
     revision: String,
+    /// Also evaluate each node of the resolved expression on its own, and show
+    /// how many commits it contains and how long that took
+    ///
+    /// The time of a node includes the time of the nodes below it.
+    #[arg(long)]
+    explain: bool,
 }
 
 /// Show information about the working copy state
@@ -69,6 +91,13 @@ pub struct DebugIndexArgs {}
 #[derive(clap::Args, Clone, Debug)]
 pub struct DebugReIndexArgs {}
 
+/// Build changed-path filters to speed up path-limited revsets
+///
+/// Once built, the filters are updated when commits are added by an operation,
+/// and by `jj util maintenance` for the commits indexed some other way.
+#[derive(clap::Args, Clone, Debug)]
+pub struct DebugIndexChangedPathsArgs {}
+
 /// Show information about an operation and its view
 #[derive(clap::Args, Clone, Debug)]
 pub struct DebugOperationArgs {
@@ -117,8 +146,12 @@ pub fn cmd_debug(
         DebugCommand::Template(args) => cmd_debug_template(ui, command, args),
         DebugCommand::Index(args) => cmd_debug_index(ui, command, args),
         DebugCommand::ReIndex(args) => cmd_debug_reindex(ui, command, args),
+        DebugCommand::IndexChangedPaths(args) => cmd_debug_index_changed_paths(ui, command, args),
         DebugCommand::Operation(args) => cmd_debug_operation(ui, command, args),
         DebugCommand::Tree(args) => cmd_debug_tree(ui, command, args),
+        DebugCommand::Fsck(args) => cmd_debug_fsck(ui, command, args),
+        DebugCommand::ExportRepo(args) => cmd_debug_export_repo(ui, command, args),
+        DebugCommand::ImportRepo(args) => cmd_debug_import_repo(ui, command, args),
         DebugCommand::Watchman(args) => cmd_debug_watchman(ui, command, args),
     }
 }
@@ -153,6 +186,12 @@ fn cmd_debug_revset(
     writeln!(ui.stdout(), "{revset:#?}")?;
     writeln!(ui.stdout())?;
 
+    if args.explain {
+        writeln!(ui.stdout(), "-- Profile:")?;
+        profile_revset_expression(ui, repo, &expression, 0)?;
+        writeln!(ui.stdout())?;
+    }
+
     writeln!(ui.stdout(), "-- Commit IDs:")?;
     for commit_id in revset.iter() {
         writeln!(ui.stdout(), "{}", commit_id.hex())?;
@@ -160,6 +199,115 @@ fn cmd_debug_revset(
     Ok(())
 }
 
+/// Prints a line for the `expression` and each node below it, with the number
+/// of commits in it and the time it took to evaluate and iterate.
+fn profile_revset_expression(
+    ui: &mut Ui,
+    repo: &dyn Repo,
+    expression: &ResolvedExpression,
+    depth: usize,
+) -> Result<(), CommandError> {
+    let start = Instant::now();
+    let revset = expression.evaluate(repo)?;
+    let count = revset.iter().count();
+    let elapsed = start.elapsed();
+    let (label, children): (_, Vec<&ResolvedExpression>) = match expression {
+        ResolvedExpression::Commits(commit_ids) => {
+            (format!("Commits ({} ids)", commit_ids.len()), vec![])
+        }
+        ResolvedExpression::Ancestors { heads, generation } => (
+            format!("Ancestors{}", format_generation(generation)),
+            vec![heads],
+        ),
+        ResolvedExpression::Range {
+            roots,
+            heads,
+            generation,
+        } => (
+            format!("Range{}", format_generation(generation)),
+            vec![roots, heads],
+        ),
+        ResolvedExpression::DagRange {
+            roots,
+            heads,
+            generation_from_roots,
+        } => (
+            format!("DagRange{}", format_generation(generation_from_roots)),
+            vec![roots, heads],
+        ),
+        ResolvedExpression::Heads(candidates) => ("Heads".to_owned(), vec![candidates]),
+        ResolvedExpression::Roots(candidates) => ("Roots".to_owned(), vec![candidates]),
+        ResolvedExpression::Latest { candidates, count } => {
+            (format!("Latest (count {count})"), vec![candidates])
+        }
+        ResolvedExpression::Union(expression1, expression2) => {
+            ("Union".to_owned(), vec![expression1, expression2])
+        }
+        ResolvedExpression::FilterWithin { candidates, .. } => {
+            ("FilterWithin".to_owned(), vec![candidates])
+        }
+        ResolvedExpression::Intersection(expression1, expression2) => {
+            ("Intersection".to_owned(), vec![expression1, expression2])
+        }
+        ResolvedExpression::Difference(expression1, expression2) => {
+            ("Difference".to_owned(), vec![expression1, expression2])
+        }
+    };
+    let indent = "  ".repeat(depth);
+    writeln!(
+        ui.stdout(),
+        "{indent}{label}: {count} commits in {elapsed:?}"
+    )?;
+    for child in children {
+        profile_revset_expression(ui, repo, child, depth + 1)?;
+    }
+    if let ResolvedExpression::FilterWithin { predicate, .. } = expression {
+        profile_revset_predicate(ui, repo, predicate, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Prints a line for the `predicate` of a filter and each node below it.
+/// Predicates are tested on the candidates of the filter, so only the sets
+/// inside them can be profiled.
+fn profile_revset_predicate(
+    ui: &mut Ui,
+    repo: &dyn Repo,
+    predicate: &ResolvedPredicateExpression,
+    depth: usize,
+) -> Result<(), CommandError> {
+    let indent = "  ".repeat(depth);
+    match predicate {
+        ResolvedPredicateExpression::Filter(filter) => {
+            writeln!(ui.stdout(), "{indent}Predicate {filter:?}")?;
+        }
+        ResolvedPredicateExpression::Set(expression) => {
+            writeln!(ui.stdout(), "{indent}Predicate Set")?;
+            profile_revset_expression(ui, repo, expression, depth + 1)?;
+        }
+        ResolvedPredicateExpression::NotIn(complement) => {
+            writeln!(ui.stdout(), "{indent}Predicate NotIn")?;
+            profile_revset_predicate(ui, repo, complement, depth + 1)?;
+        }
+        ResolvedPredicateExpression::Union(predicate1, predicate2) => {
+            writeln!(ui.stdout(), "{indent}Predicate Union")?;
+            profile_revset_predicate(ui, repo, predicate1, depth + 1)?;
+            profile_revset_predicate(ui, repo, predicate2, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+fn format_generation(generation: &Range<u64>) -> String {
+    if generation == &GENERATION_RANGE_FULL {
+        String::new()
+    } else if generation.end == u64::MAX {
+        format!(" (generation {}..)", generation.start)
+    } else {
+        format!(" (generation {}..{})", generation.start, generation.end)
+    }
+}
+
 fn cmd_debug_working_copy(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -217,6 +365,13 @@ fn cmd_debug_index(
         )?;
         writeln!(ui.stdout(), "Number of heads: {}", stats.num_heads)?;
         writeln!(ui.stdout(), "Number of changes: {}", stats.num_changes)?;
+        if let Some(changed_paths) = default_index.changed_paths() {
+            writeln!(
+                ui.stdout(),
+                "Number of commits with changed-path filters: {}",
+                changed_paths.num_commits()
+            )?;
+        }
         writeln!(ui.stdout(), "Stats per level:")?;
         for (i, level) in stats.levels.iter().enumerate() {
             writeln!(ui.stdout(), "  Level {i}:")?;
@@ -262,6 +417,49 @@ fn cmd_debug_reindex(
     Ok(())
 }
 
+fn cmd_debug_index_changed_paths(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &DebugIndexChangedPathsArgs,
+) -> Result<(), CommandError> {
+    let workspace = command.load_workspace()?;
+    let repo_loader = workspace.repo_loader();
+    let op = op_walk::resolve_op_for_load(repo_loader, &command.global_args().at_operation)?;
+    let index_store = repo_loader.index_store();
+    if let Some(default_index_store) = index_store.as_any().downcast_ref::<DefaultIndexStore>() {
+        let index = index_store
+            .get_index_at_op(&op, repo_loader.store())
+            .map_err(internal_error)?;
+        let Some(default_index) = index.as_any().downcast_ref::<DefaultReadonlyIndex>() else {
+            return Err(user_error(format!(
+                "Cannot build changed-path filters for indexes of type '{}'",
+                index_store.name()
+            )));
+        };
+        let num_before = default_index
+            .changed_paths()
+            .map_or(0, |changed_paths| changed_paths.num_commits());
+        let default_index = default_index_store
+            .build_changed_path_index(default_index, repo_loader.store())
+            .map_err(internal_error)?;
+        let Some(changed_paths) = default_index.changed_paths() else {
+            return Err(user_error("No changed-path filters were built"));
+        };
+        let num_after = changed_paths.num_commits();
+        writeln!(
+            ui.stderr(),
+            "Built changed-path filters for {} commits.",
+            num_after - num_before
+        )?;
+    } else {
+        return Err(user_error(format!(
+            "Cannot build changed-path filters for indexes of type '{}'",
+            index_store.name()
+        )));
+    }
+    Ok(())
+}
+
 fn cmd_debug_operation(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -293,7 +491,7 @@ fn cmd_debug_tree(
     let workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
     let tree = commit.tree()?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&tree])?;
     for (path, value) in tree.entries_matching(matcher.as_ref()) {
         let ui_path = workspace_command.format_file_path(&path);
         writeln!(ui.stdout(), "{ui_path}: {value:?}")?;
@@ -0,0 +1,602 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io::{Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use blake2::{Blake2b512, Digest as _};
+use itertools::Itertools as _;
+use jj_lib::backend::{Backend as _, BackendError, CommitId, MergedTreeId, TreeValue};
+use jj_lib::content_hash::blake2b_hash;
+use jj_lib::git_backend::GitBackend;
+use jj_lib::local_backend::LocalBackend;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::{OpStoreError, OperationId, ViewId};
+use jj_lib::repo::{ReadonlyRepo, Repo as _};
+use jj_lib::repo_path::{RepoPath, RepoPathBuf};
+use jj_lib::simple_op_store::SimpleOpStore;
+use jj_lib::store::Store;
+
+use crate::cli_util::{user_error, CommandError, CommandHelper};
+use crate::ui::Ui;
+
+/// Check the integrity of the repo's storage
+///
+/// Walks the operation log, the views of the operations, the commits reachable
+/// from the views, and their trees and files. Reports objects that are missing
+/// or can't be read, and objects whose contents don't match their hash.
+#[derive(clap::Args, Clone, Debug)]
+pub struct DebugFsckArgs {
+    /// Move the files of corrupt commits, trees, and files out of the store
+    ///
+    /// They are moved to `.jj/repo/quarantine/`. Objects in Git pack files
+    /// can't be moved.
+    #[arg(long)]
+    quarantine: bool,
+}
+
+/// The hash function the store's object ids are computed with.
+enum StoreHasher {
+    /// The native backend hashes the objects with BLAKE2b.
+    Local { store_path: PathBuf },
+    /// The Git backend uses the ids of the Git objects.
+    Git { git_repo: Box<gix::Repository> },
+    /// Hashes of other backends aren't checked.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ObjectKind {
+    Operation,
+    View,
+    Commit,
+    Tree,
+    File,
+    Symlink,
+    Conflict,
+}
+
+impl ObjectKind {
+    fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Operation => "operation",
+            ObjectKind::View => "view",
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::File => "file",
+            ObjectKind::Symlink => "symlink",
+            ObjectKind::Conflict => "conflict",
+        }
+    }
+
+    /// The directory of the native backend that stores the objects.
+    fn local_dir(self) -> &'static str {
+        match self {
+            ObjectKind::Operation => "operations",
+            ObjectKind::View => "views",
+            ObjectKind::Commit => "commits",
+            ObjectKind::Tree => "trees",
+            ObjectKind::File => "files",
+            ObjectKind::Symlink => "symlinks",
+            ObjectKind::Conflict => "conflicts",
+        }
+    }
+}
+
+struct Problem {
+    kind: ObjectKind,
+    id: String,
+    /// The path of the tree or file in its commit.
+    path: Option<String>,
+    missing: bool,
+    message: Option<String>,
+}
+
+#[derive(Default)]
+struct Counts {
+    operations: usize,
+    views: usize,
+    commits: usize,
+    trees: usize,
+    files: usize,
+    symlinks: usize,
+    conflicts: usize,
+}
+
+struct Fsck<'a> {
+    repo: &'a ReadonlyRepo,
+    store: &'a Arc<Store>,
+    hasher: StoreHasher,
+    verify_op_hashes: bool,
+    problems: Vec<Problem>,
+    counts: Counts,
+    visited_trees: HashSet<Vec<u8>>,
+    visited_files: HashSet<Vec<u8>>,
+}
+
+pub fn cmd_debug_fsck(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &DebugFsckArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let repo = workspace_command.repo().as_ref();
+    let store = repo.store();
+    let backend = store.backend_impl();
+    let hasher = if backend.downcast_ref::<LocalBackend>().is_some() {
+        StoreHasher::Local {
+            store_path: repo.repo_path().join("store"),
+        }
+    } else if let Some(git_backend) = backend.downcast_ref::<GitBackend>() {
+        StoreHasher::Git {
+            git_repo: Box::new(git_backend.git_repo()),
+        }
+    } else {
+        StoreHasher::Unknown
+    };
+    let verify_op_hashes = repo
+        .op_store()
+        .as_any()
+        .downcast_ref::<SimpleOpStore>()
+        .is_some();
+    let mut fsck = Fsck {
+        repo,
+        store,
+        hasher,
+        verify_op_hashes,
+        problems: vec![],
+        counts: Counts::default(),
+        visited_trees: HashSet::new(),
+        visited_files: HashSet::new(),
+    };
+    let commit_ids = fsck.check_operations();
+    fsck.check_commits(commit_ids);
+
+    let quarantine_dir = repo.repo_path().join("quarantine");
+    let mut formatter = ui.stdout_formatter();
+    for problem in &fsck.problems {
+        let state = if problem.missing {
+            "Missing"
+        } else {
+            "Corrupt"
+        };
+        write!(
+            formatter.labeled("error"),
+            "{state} {} {}",
+            problem.kind.name(),
+            problem.id
+        )?;
+        if let Some(path) = &problem.path {
+            write!(formatter, " at {path}")?;
+        }
+        match &problem.message {
+            Some(message) => writeln!(formatter, ": {message}")?,
+            None => writeln!(formatter)?,
+        }
+        if args.quarantine && !problem.missing {
+            match fsck.hasher.object_file(problem.kind, &problem.id) {
+                Some(path) => {
+                    let target = quarantine_object(&path, &quarantine_dir, problem)?;
+                    writeln!(
+                        formatter,
+                        "  Moved {} to {}",
+                        path.display(),
+                        target.display()
+                    )?;
+                }
+                None => writeln!(formatter, "  Can't be moved to the quarantine")?,
+            }
+        }
+    }
+    drop(formatter);
+
+    let counts = &fsck.counts;
+    writeln!(
+        ui.stderr(),
+        "Checked {} operations, {} views, {} commits, {} trees, {} files, {} symlinks, {} \
+         conflicts.",
+        counts.operations,
+        counts.views,
+        counts.commits,
+        counts.trees,
+        counts.files,
+        counts.symlinks,
+        counts.conflicts
+    )?;
+    match fsck.problems.len() {
+        0 => Ok(()),
+        1 => Err(user_error("Found 1 problem")),
+        n => Err(user_error(format!("Found {n} problems"))),
+    }
+}
+
+impl Fsck<'_> {
+    fn report(&mut self, kind: ObjectKind, id: String, path: Option<&RepoPath>, err: String) {
+        self.problems.push(Problem {
+            kind,
+            id,
+            path: path.map(|path| path.as_internal_file_string().to_owned()),
+            missing: false,
+            message: Some(err),
+        });
+    }
+
+    fn report_backend_error(
+        &mut self,
+        kind: ObjectKind,
+        id: String,
+        path: Option<&RepoPath>,
+        err: &BackendError,
+    ) {
+        let missing = matches!(err, BackendError::ObjectNotFound { .. });
+        self.problems.push(Problem {
+            kind,
+            id,
+            path: path.map(|path| path.as_internal_file_string().to_owned()),
+            missing,
+            message: (!missing).then(|| format_error_chain(err)),
+        });
+    }
+
+    /// Walks the operation log from the current heads, checking the operations
+    /// and their views. Returns the commits referenced by the views.
+    fn check_operations(&mut self) -> Vec<CommitId> {
+        let op_store = self.repo.op_store();
+        let root_op_id = op_store.root_operation_id();
+        let mut commit_ids = vec![];
+        let mut visited_ops = HashSet::new();
+        let mut visited_views = HashSet::new();
+        let mut queue: VecDeque<OperationId> = self.repo.op_heads_store().get_op_heads().into();
+        while let Some(op_id) = queue.pop_front() {
+            if !visited_ops.insert(op_id.clone()) {
+                continue;
+            }
+            self.counts.operations += 1;
+            let operation = match op_store.read_operation(&op_id) {
+                Ok(operation) => operation,
+                Err(err) => {
+                    self.report_op_store_error(ObjectKind::Operation, op_id.hex(), &err);
+                    continue;
+                }
+            };
+            let is_root = op_id == *root_op_id;
+            if self.verify_op_hashes && !is_root {
+                let hash = blake2b_hash(&operation).to_vec();
+                if hash != op_id.as_bytes() {
+                    self.report_hash_mismatch(ObjectKind::Operation, op_id.hex(), None, &hash);
+                }
+            }
+            queue.extend(operation.parents.iter().cloned());
+
+            let view_id = operation.view_id;
+            if !visited_views.insert(view_id.clone()) {
+                continue;
+            }
+            self.counts.views += 1;
+            let view = match op_store.read_view(&view_id) {
+                Ok(view) => view,
+                Err(err) => {
+                    self.report_op_store_error(ObjectKind::View, view_id.hex(), &err);
+                    continue;
+                }
+            };
+            if self.verify_op_hashes && !is_root {
+                self.check_view_hash(&view_id, &view);
+            }
+            let view = jj_lib::view::View::new(view);
+            commit_ids.extend(view.all_referenced_commit_ids().cloned());
+        }
+        commit_ids
+    }
+
+    fn check_view_hash(&mut self, view_id: &ViewId, view: &jj_lib::op_store::View) {
+        let hash = blake2b_hash(view).to_vec();
+        if hash != view_id.as_bytes() {
+            self.report_hash_mismatch(ObjectKind::View, view_id.hex(), None, &hash);
+        }
+    }
+
+    fn report_op_store_error(&mut self, kind: ObjectKind, id: String, err: &OpStoreError) {
+        let missing = matches!(err, OpStoreError::ObjectNotFound { .. });
+        self.problems.push(Problem {
+            kind,
+            id,
+            path: None,
+            missing,
+            message: (!missing).then(|| format_error_chain(err)),
+        });
+    }
+
+    fn report_hash_mismatch(
+        &mut self,
+        kind: ObjectKind,
+        id: String,
+        path: Option<&RepoPath>,
+        actual: &[u8],
+    ) {
+        let message = format!("The contents hash to {}", hex::encode(actual));
+        self.report(kind, id, path, message);
+    }
+
+    /// Checks the commits and their ancestors and predecessors.
+    fn check_commits(&mut self, commit_ids: Vec<CommitId>) {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from(commit_ids);
+        while let Some(commit_id) = queue.pop_front() {
+            if !visited.insert(commit_id.clone()) {
+                continue;
+            }
+            self.counts.commits += 1;
+            let commit = match self.store.get_commit(&commit_id) {
+                Ok(commit) => commit,
+                Err(err) => {
+                    self.report_backend_error(ObjectKind::Commit, commit_id.hex(), None, &err);
+                    continue;
+                }
+            };
+            if commit_id != *self.store.root_commit_id() {
+                match &self.hasher {
+                    StoreHasher::Local { .. } => {
+                        let hash = blake2b_hash(commit.store_commit()).to_vec();
+                        if hash != commit_id.as_bytes() {
+                            self.report_hash_mismatch(
+                                ObjectKind::Commit,
+                                commit_id.hex(),
+                                None,
+                                &hash,
+                            );
+                        }
+                    }
+                    StoreHasher::Git { .. } => {
+                        self.check_git_object(ObjectKind::Commit, commit_id.as_bytes(), None);
+                    }
+                    StoreHasher::Unknown => {}
+                }
+            }
+            queue.extend(commit.parent_ids().iter().cloned());
+            queue.extend(commit.predecessor_ids().iter().cloned());
+            let tree_ids = match commit.tree_id() {
+                MergedTreeId::Legacy(tree_id) => vec![tree_id.clone()],
+                MergedTreeId::Merge(tree_ids) => tree_ids.iter().cloned().collect_vec(),
+            };
+            for tree_id in tree_ids {
+                self.check_tree(RepoPathBuf::root(), &tree_id);
+            }
+        }
+    }
+
+    fn check_tree(&mut self, dir: RepoPathBuf, tree_id: &jj_lib::backend::TreeId) {
+        if !self.visited_trees.insert(tree_id.to_bytes()) {
+            return;
+        }
+        self.counts.trees += 1;
+        let tree = match self.store.get_tree(&dir, tree_id) {
+            Ok(tree) => tree,
+            Err(err) => {
+                self.report_backend_error(ObjectKind::Tree, tree_id.hex(), Some(&dir), &err);
+                return;
+            }
+        };
+        if tree_id != self.store.empty_tree_id() {
+            match &self.hasher {
+                StoreHasher::Local { .. } => {
+                    let hash = blake2b_hash(tree.data()).to_vec();
+                    if hash != tree_id.as_bytes() {
+                        self.report_hash_mismatch(
+                            ObjectKind::Tree,
+                            tree_id.hex(),
+                            Some(&dir),
+                            &hash,
+                        );
+                    }
+                }
+                StoreHasher::Git { .. } => {
+                    self.check_git_object(ObjectKind::Tree, tree_id.as_bytes(), Some(&dir));
+                }
+                StoreHasher::Unknown => {}
+            }
+        }
+        for entry in tree.data().entries() {
+            let path = dir.join(entry.name());
+            match entry.value() {
+                TreeValue::Tree(id) => self.check_tree(path, id),
+                TreeValue::File { id, .. } => self.check_file(&path, id),
+                TreeValue::Symlink(id) => self.check_symlink(&path, id),
+                TreeValue::Conflict(id) => self.check_conflict(&path, id),
+                TreeValue::GitSubmodule(_) => {}
+            }
+        }
+    }
+
+    fn check_file(&mut self, path: &RepoPath, id: &jj_lib::backend::FileId) {
+        if !self.visited_files.insert(id.to_bytes()) {
+            return;
+        }
+        self.counts.files += 1;
+        if let StoreHasher::Git { .. } = &self.hasher {
+            self.check_git_object(ObjectKind::File, id.as_bytes(), Some(path));
+            return;
+        }
+        let result = self.store.read_file(path, id).and_then(|mut reader| {
+            let mut hasher = Blake2b512::new();
+            let mut buf = [0; 8192];
+            loop {
+                let len = reader
+                    .read(&mut buf)
+                    .map_err(|err| BackendError::ReadObject {
+                        object_type: "file".to_owned(),
+                        hash: id.hex(),
+                        source: err.into(),
+                    })?;
+                if len == 0 {
+                    break;
+                }
+                hasher.update(&buf[..len]);
+            }
+            Ok(hasher.finalize().to_vec())
+        });
+        match result {
+            Ok(hash) => {
+                if matches!(self.hasher, StoreHasher::Local { .. }) && hash != id.as_bytes() {
+                    self.report_hash_mismatch(ObjectKind::File, id.hex(), Some(path), &hash);
+                }
+            }
+            Err(err) => self.report_backend_error(ObjectKind::File, id.hex(), Some(path), &err),
+        }
+    }
+
+    fn check_symlink(&mut self, path: &RepoPath, id: &jj_lib::backend::SymlinkId) {
+        if !self.visited_files.insert(id.to_bytes()) {
+            return;
+        }
+        self.counts.symlinks += 1;
+        if let StoreHasher::Git { .. } = &self.hasher {
+            self.check_git_object(ObjectKind::Symlink, id.as_bytes(), Some(path));
+            return;
+        }
+        match self.store.read_symlink(path, id) {
+            Ok(target) => {
+                let hash = Blake2b512::digest(target.as_bytes()).to_vec();
+                if matches!(self.hasher, StoreHasher::Local { .. }) && hash != id.as_bytes() {
+                    self.report_hash_mismatch(ObjectKind::Symlink, id.hex(), Some(path), &hash);
+                }
+            }
+            Err(err) => {
+                self.report_backend_error(ObjectKind::Symlink, id.hex(), Some(path), &err);
+            }
+        }
+    }
+
+    fn check_conflict(&mut self, path: &RepoPath, id: &jj_lib::backend::ConflictId) {
+        if !self.visited_files.insert(id.to_bytes()) {
+            return;
+        }
+        self.counts.conflicts += 1;
+        let conflict = match self.store.read_conflict(path, id) {
+            Ok(conflict) => conflict,
+            Err(err) => {
+                self.report_backend_error(ObjectKind::Conflict, id.hex(), Some(path), &err);
+                return;
+            }
+        };
+        match &self.hasher {
+            StoreHasher::Local { .. } => {
+                let local_backend: &LocalBackend =
+                    self.store.backend_impl().downcast_ref().unwrap();
+                if let Ok(backend_conflict) = local_backend.read_conflict(path, id) {
+                    let hash = blake2b_hash(&backend_conflict).to_vec();
+                    if hash != id.as_bytes() {
+                        self.report_hash_mismatch(
+                            ObjectKind::Conflict,
+                            id.hex(),
+                            Some(path),
+                            &hash,
+                        );
+                    }
+                }
+            }
+            StoreHasher::Git { .. } => {
+                self.check_git_object(ObjectKind::Conflict, id.as_bytes(), Some(path));
+            }
+            StoreHasher::Unknown => {}
+        }
+        for value in conflict.iter().flatten() {
+            if let TreeValue::File { id, .. } = value {
+                self.check_file(path, id);
+            }
+        }
+    }
+
+    /// Checks that the Git object exists and that its contents hash to its id.
+    fn check_git_object(&mut self, kind: ObjectKind, id: &[u8], path: Option<&RepoPath>) {
+        let StoreHasher::Git { git_repo } = &self.hasher else {
+            return;
+        };
+        let oid = gix::ObjectId::from(id);
+        let result = match git_repo.find_object(oid) {
+            Ok(object) => Ok(gix::objs::compute_hash(
+                git_repo.object_hash(),
+                object.kind,
+                &object.data,
+            )),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(actual) if actual == oid => {}
+            Ok(actual) => self.report_hash_mismatch(kind, oid.to_string(), path, actual.as_bytes()),
+            Err(gix::object::find::existing::Error::NotFound { .. }) => {
+                self.problems.push(Problem {
+                    kind,
+                    id: oid.to_string(),
+                    path: path.map(|path| path.as_internal_file_string().to_owned()),
+                    missing: true,
+                    message: None,
+                });
+            }
+            Err(err) => self.report(kind, oid.to_string(), path, format_error_chain(&err)),
+        }
+    }
+}
+
+impl StoreHasher {
+    /// The file the object is stored in, if it can be moved to the quarantine.
+    fn object_file(&self, kind: ObjectKind, id: &str) -> Option<PathBuf> {
+        let path = match self {
+            StoreHasher::Local { store_path } => match kind {
+                ObjectKind::Operation | ObjectKind::View => return None,
+                ObjectKind::File => {
+                    let path = store_path.join("files").join(id);
+                    if path.is_file() {
+                        path
+                    } else {
+                        store_path.join("chunked_files").join(id)
+                    }
+                }
+                _ => store_path.join(kind.local_dir()).join(id),
+            },
+            StoreHasher::Git { git_repo } => match kind {
+                ObjectKind::Operation | ObjectKind::View => return None,
+                _ => git_repo
+                    .objects
+                    .store_ref()
+                    .path()
+                    .join(&id[..2])
+                    .join(&id[2..]),
+            },
+            StoreHasher::Unknown => return None,
+        };
+        path.is_file().then_some(path)
+    }
+}
+
+fn quarantine_object(
+    path: &Path,
+    quarantine_dir: &Path,
+    problem: &Problem,
+) -> Result<PathBuf, CommandError> {
+    let dir = quarantine_dir.join(problem.kind.local_dir());
+    fs::create_dir_all(&dir)?;
+    let target = dir.join(&problem.id);
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+fn format_error_chain(err: &dyn std::error::Error) -> String {
+    std::iter::successors(Some(err), |err| err.source())
+        .map(|err| err.to_string())
+        .join(": ")
+}
@@ -0,0 +1,313 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Archives of a whole repo, including the operation log.
+//!
+//! An archive is a zstd-compressed stream that starts with [`MAGIC`] and is
+//! followed by entries. An entry is a tag byte, the length of the path as a
+//! little-endian `u32`, and the path relative to the `.jj` directory. File
+//! entries are followed by the length of the contents as a little-endian
+//! `u64` and the contents. The stream ends with [`TAG_END`].
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use itertools::Itertools as _;
+use jj_lib::file_util;
+use jj_lib::working_copy::CheckoutOptions;
+
+use crate::cli_util::{
+    internal_error_with_message, print_checkout_stats, user_error, CommandError, CommandHelper,
+};
+use crate::tr;
+use crate::ui::Ui;
+
+const MAGIC: &[u8] = b"jj-repo-archive 1\n";
+const TAG_DIR: u8 = b'd';
+const TAG_FILE: u8 = b'f';
+const TAG_END: u8 = b'e';
+
+/// Export the repo, including the operation log, to an archive
+///
+/// The archive contains the commits, the operation log and views, and the
+/// working-copy state of the current workspace, so the repo can be moved to
+/// another machine or kept as a backup. The working copy is snapshotted
+/// first. The index isn't included; it's rebuilt when the repo is imported.
+///
+/// If the repo is backed by an external or colocated Git repo, the Git repo is
+/// included in the archive, and the imported repo uses it as an internal Git
+/// repo.
+#[derive(clap::Args, Clone, Debug)]
+pub struct DebugExportRepoArgs {
+    /// Path of the archive to create
+    archive: PathBuf,
+}
+
+/// Import a repo from an archive created by `jj debug export-repo`
+///
+/// The repo is created in a new workspace at the destination, and the
+/// working-copy commit is checked out.
+#[derive(clap::Args, Clone, Debug)]
+pub struct DebugImportRepoArgs {
+    /// Path of the archive
+    archive: PathBuf,
+    /// Where to create the workspace. Must not exist or be an empty directory.
+    destination: PathBuf,
+}
+
+pub fn cmd_debug_export_repo(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &DebugExportRepoArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let repo_path = workspace_command.repo().repo_path();
+    let wc_state_path = workspace_command
+        .workspace_root()
+        .join(".jj")
+        .join("working_copy");
+
+    let file = File::create(&args.archive).map_err(|err| {
+        user_error(format!(
+            "Failed to create {}: {err}",
+            args.archive.display()
+        ))
+    })?;
+    let external_git_path = external_git_repo_path(repo_path);
+    let mut writer = ArchiveWriter::new(BufWriter::new(file))?;
+    writer.add_dir_recursive(repo_path, "repo", &|path| {
+        if external_git_path.is_some() && path == "repo/store/git_target" {
+            return false;
+        }
+        // The index can be rebuilt from the operation log
+        matches!(path, "repo/index/type" | "repo/index/operations")
+            || !path.starts_with("repo/index/")
+    })?;
+    if let Some(git_path) = &external_git_path {
+        writer.add_file_contents("repo/store/git_target", b"git")?;
+        writer.add_dir_recursive(git_path, "repo/store/git", &|_| true)?;
+    }
+    writer.add_dir_recursive(&wc_state_path, "working_copy", &|path| {
+        // The tree state records file timestamps, which aren't portable
+        path != "working_copy/tree_state"
+    })?;
+    let (file_count, size) = (writer.file_count, writer.size);
+    writer.finish()?;
+    writeln!(
+        ui.stderr(),
+        "Exported {file_count} files ({size} bytes) to {}",
+        args.archive.display()
+    )?;
+    Ok(())
+}
+
+pub fn cmd_debug_import_repo(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &DebugImportRepoArgs,
+) -> Result<(), CommandError> {
+    let destination = command.cwd().join(&args.destination);
+    if destination.exists() {
+        if fs::read_dir(&destination)?.next().is_some() {
+            return Err(user_error(format!(
+                r#"Destination "{}" is not empty"#,
+                args.destination.display()
+            )));
+        }
+    } else {
+        fs::create_dir_all(&destination)?;
+    }
+    let file = File::open(&args.archive)
+        .map_err(|err| user_error(format!("Failed to open {}: {err}", args.archive.display())))?;
+    let jj_dir = destination.join(".jj");
+    fs::create_dir(&jj_dir)?;
+    read_archive(BufReader::new(file), &jj_dir)?;
+    let git_path = jj_dir.join("repo").join("store").join("git");
+    if git_path.is_dir() {
+        // An exported colocated Git repo isn't bare
+        git2::Repository::open(&git_path)?
+            .config()?
+            .set_bool("core.bare", true)?;
+    }
+
+    let workspace = command.load_workspace_at(&destination)?;
+    let repo = workspace.repo_loader().load_at_head(command.settings())?;
+    let mut workspace_command = command.for_loaded_repo(ui, workspace, repo.clone())?;
+    let relative_path = file_util::relative_path(command.cwd(), &destination);
+    writeln!(
+        ui.stderr(),
+        "Imported repo to \"{}\"",
+        relative_path.display()
+    )?;
+    if workspace_command.get_wc_commit_id().is_none() {
+        return Ok(());
+    }
+    // The working-copy state wasn't exported, so all files are checked out
+    let (mut locked_ws, wc_commit) = workspace_command.unchecked_start_working_copy_mutation()?;
+    let progress = crate::progress::checkout_progress(ui);
+    let options = CheckoutOptions {
+        progress: progress.as_ref().map(|x| x as _),
+    };
+    let stats = locked_ws
+        .locked_wc()
+        .check_out(&wc_commit, options)
+        .map_err(|err| internal_error_with_message("Failed to check out the working copy", err))?;
+    drop(progress);
+    locked_ws.finish(repo.op_id().clone())?;
+    write!(ui.stderr(), "{}", tr!(ui, "working-copy-now-at"))?;
+    ui.stderr_formatter().with_label("working_copy", |fmt| {
+        workspace_command.write_commit_summary(fmt, &wc_commit)
+    })?;
+    writeln!(ui.stderr())?;
+    print_checkout_stats(ui, stats, &wc_commit)?;
+    Ok(())
+}
+
+/// Returns the path of the backing Git repo if it's outside the repo
+/// directory.
+fn external_git_repo_path(repo_path: &Path) -> Option<PathBuf> {
+    let store_path = repo_path.join("store");
+    let target = fs::read_to_string(store_path.join("git_target")).ok()?;
+    let git_path = store_path.join(target.trim()).canonicalize().ok()?;
+    let repo_path = repo_path.canonicalize().ok()?;
+    (!git_path.starts_with(repo_path)).then_some(git_path)
+}
+
+fn is_lock_file(path: &str) -> bool {
+    path.ends_with("/lock") || path.ends_with(".lock")
+}
+
+struct ArchiveWriter<W: Write> {
+    encoder: zstd::Encoder<'static, W>,
+    file_count: usize,
+    size: u64,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    fn new(writer: W) -> io::Result<Self> {
+        let mut encoder = zstd::Encoder::new(writer, 0)?;
+        encoder.write_all(MAGIC)?;
+        Ok(ArchiveWriter {
+            encoder,
+            file_count: 0,
+            size: 0,
+        })
+    }
+
+    fn write_header(&mut self, tag: u8, path: &str) -> io::Result<()> {
+        let path_len = u32::try_from(path.len()).unwrap();
+        self.encoder.write_all(&[tag])?;
+        self.encoder.write_all(&path_len.to_le_bytes())?;
+        self.encoder.write_all(path.as_bytes())
+    }
+
+    fn add_file_contents(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.write_header(TAG_FILE, path)?;
+        self.encoder
+            .write_all(&(contents.len() as u64).to_le_bytes())?;
+        self.encoder.write_all(contents)?;
+        self.file_count += 1;
+        self.size += contents.len() as u64;
+        Ok(())
+    }
+
+    /// Adds the files in `disk_dir` under the archive path `dir`. Lock files
+    /// and paths for which `include` returns false are skipped.
+    fn add_dir_recursive(
+        &mut self,
+        disk_dir: &Path,
+        dir: &str,
+        include: &dyn Fn(&str) -> bool,
+    ) -> Result<(), CommandError> {
+        self.write_header(TAG_DIR, dir)?;
+        let entries: Vec<_> = fs::read_dir(disk_dir)?.try_collect()?;
+        for entry in entries.iter().sorted_by_key(|entry| entry.file_name()) {
+            let Some(name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+                continue;
+            };
+            let path = format!("{dir}/{name}");
+            if is_lock_file(&path) || !include(&path) {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.add_dir_recursive(&entry.path(), &path, include)?;
+            } else if file_type.is_file() {
+                // Files that disappear while exporting, such as temporary files,
+                // are skipped
+                let Ok(contents) = fs::read(entry.path()) else {
+                    continue;
+                };
+                self.add_file_contents(&path, &contents)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.encoder.write_all(&[TAG_END])?;
+        self.encoder.finish()?.flush()
+    }
+}
+
+fn read_archive(reader: impl Read, jj_dir: &Path) -> Result<(), CommandError> {
+    let invalid = |message: &str| user_error(format!("Invalid repo archive: {message}"));
+    let mut decoder = zstd::Decoder::new(reader)?;
+    let mut magic = vec![0; MAGIC.len()];
+    if decoder.read_exact(&mut magic).is_err() || magic != MAGIC {
+        return Err(invalid("Unknown file format"));
+    }
+    loop {
+        let mut tag = [0; 1];
+        decoder
+            .read_exact(&mut tag)
+            .map_err(|_| invalid("Unexpected end of archive"))?;
+        if tag[0] == TAG_END {
+            return Ok(());
+        }
+        let mut len = [0; 4];
+        decoder
+            .read_exact(&mut len)
+            .map_err(|_| invalid("Unexpected end of archive"))?;
+        let mut path = vec![0; u32::from_le_bytes(len) as usize];
+        decoder
+            .read_exact(&mut path)
+            .map_err(|_| invalid("Unexpected end of archive"))?;
+        let path = String::from_utf8(path).map_err(|_| invalid("Path is not UTF-8"))?;
+        if !Path::new(&path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(invalid(&format!("Invalid path {path:?}")));
+        }
+        let disk_path = jj_dir.join(&path);
+        match tag[0] {
+            TAG_DIR => fs::create_dir_all(&disk_path)?,
+            TAG_FILE => {
+                let mut len = [0; 8];
+                decoder
+                    .read_exact(&mut len)
+                    .map_err(|_| invalid("Unexpected end of archive"))?;
+                let len = u64::from_le_bytes(len);
+                let mut file = File::create(&disk_path)?;
+                let copied = io::copy(&mut (&mut decoder).take(len), &mut file)?;
+                if copied != len {
+                    return Err(invalid("Unexpected end of archive"));
+                }
+            }
+            _ => return Err(invalid("Unknown entry type")),
+        }
+    }
+}
@@ -18,7 +18,10 @@ use jj_lib::object_id::ObjectId;
 use tracing::instrument;
 
 use crate::cli_util::{join_message_paragraphs, CommandError, CommandHelper, RevisionArg};
-use crate::description_util::{description_template_for_describe, edit_description};
+use crate::description_util::{
+    add_commit_trailers, check_description, description_template_for_describe, edit_description,
+};
+use crate::tr;
 use crate::ui::Ui;
 
 /// Update the change description or other metadata
@@ -55,6 +58,9 @@ pub(crate) struct DescribeArgs {
     /// $ JJ_USER='Foo Bar' JJ_EMAIL=foo@bar.com jj describe --reset-author
     #[arg(long)]
     reset_author: bool,
+    /// Don't check the description against `description-checks`
+    #[arg(long)]
+    skip_checks: bool,
 }
 
 #[instrument(skip_all)]
@@ -79,10 +85,17 @@ pub(crate) fn cmd_describe(
             description_template_for_describe(ui, command.settings(), &workspace_command, &commit)?;
         edit_description(workspace_command.repo(), &template, command.settings())?
     };
+    let description =
+        add_commit_trailers(command.settings(), &workspace_command, &commit, description)?;
+    // Only the new descriptions are checked, so that e.g. `--reset-author`
+    // works on old commits
+    if !args.skip_checks && description != *commit.description() {
+        check_description(command.settings(), &description)?;
+    }
     if description == *commit.description() && !args.reset_author {
-        writeln!(ui.stderr(), "Nothing changed.")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
     } else {
-        let mut tx = workspace_command.start_transaction();
+        let mut tx = workspace_command.start_transaction()?;
         let mut commit_builder = tx
             .mut_repo()
             .rewrite_commit(command.settings(), &commit)
@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
+use std::io::Write;
+
 use jj_lib::rewrite::merge_commit_trees;
 use tracing::instrument;
 
-use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
-use crate::diff_util::{diff_formats_for, show_diff, DiffFormatArgs};
+use crate::cli_util::{CommandError, CommandHelper, RevisionArg, WorkspaceCommandHelper};
+use crate::diff_util::{diff_formats_for, show_diff, DiffFormat, DiffFormatArgs};
 use crate::ui::Ui;
 
 /// Compare file contents between two commits
@@ -27,6 +30,9 @@ pub(crate) struct DiffArgs {
     /// If the revision is a merge commit, this shows changes *from* the
     /// automatic merge of the contents of all of its parents *to* the contents
     /// of the revision itself.
+    ///
+    /// With `--name-only`, this can be a revset, and the paths changed in any
+    /// of the revisions are listed.
     #[arg(long, short)]
     revision: Option<RevisionArg>,
     /// Show changes from this revision
@@ -48,7 +54,11 @@ pub(crate) fn cmd_diff(
     command: &CommandHelper,
     args: &DiffArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
+    let diff_formats = diff_formats_for(command.settings(), &args.format)?;
+    if let (Some(revision), [DiffFormat::NameOnly]) = (&args.revision, diff_formats.as_slice()) {
+        return show_changed_paths(ui, &workspace_command, revision, &args.paths);
+    }
     let from_tree;
     let to_tree;
     if args.from.is_some() || args.to.is_some() {
@@ -63,8 +73,7 @@ pub(crate) fn cmd_diff(
         from_tree = merge_commit_trees(workspace_command.repo().as_ref(), &parents)?;
         to_tree = commit.tree()?
     }
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
-    let diff_formats = diff_formats_for(command.settings(), &args.format)?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&from_tree, &to_tree])?;
     ui.request_pager();
     show_diff(
         ui,
@@ -74,6 +83,39 @@ pub(crate) fn cmd_diff(
         &to_tree,
         matcher.as_ref(),
         &diff_formats,
+        usize::from(ui.term_width().unwrap_or(80)),
     )?;
     Ok(())
 }
+
+/// Lists the paths changed in any of the revisions in `revision`, compared to
+/// their parents.
+fn show_changed_paths(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    revision: &str,
+    paths: &[String],
+) -> Result<(), CommandError> {
+    let repo = workspace_command.repo().as_ref();
+    let commits = workspace_command.resolve_revset(revision, ui)?;
+    let mut changed_paths = BTreeSet::new();
+    for commit in &commits {
+        let from_tree = merge_commit_trees(repo, &commit.parents())?;
+        let to_tree = commit.tree()?;
+        let matcher = workspace_command.matcher_from_values(paths, &[&from_tree, &to_tree])?;
+        for (repo_path, diff) in from_tree.diff(&to_tree, matcher.as_ref()) {
+            diff?;
+            changed_paths.insert(repo_path);
+        }
+    }
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    for repo_path in &changed_paths {
+        writeln!(
+            formatter,
+            "{}",
+            workspace_command.format_file_path(repo_path)
+        )?;
+    }
+    Ok(())
+}
@@ -20,6 +20,7 @@ use jj_lib::rewrite::merge_commit_trees;
 use tracing::instrument;
 
 use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::tr;
 use crate::ui::Ui;
 
 /// Touch up the content changes in a revision with a diff editor
@@ -78,7 +79,7 @@ pub(crate) fn cmd_diffedit(
     };
     workspace_command.check_rewritable([&target_commit])?;
 
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let instructions = format!(
         "\
 You are editing changes in: {}
@@ -93,7 +94,7 @@ don't make any changes, then the operation will be aborted.",
     let tree = target_commit.tree()?;
     let tree_id = tx.edit_diff(ui, &base_tree, &tree, &EverythingMatcher, &instructions)?;
     if tree_id == *target_commit.tree_id() {
-        writeln!(ui.stderr(), "Nothing changed.")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
     } else {
         let mut_repo = tx.mut_repo();
         let new_commit = mut_repo
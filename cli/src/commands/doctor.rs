@@ -0,0 +1,362 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use itertools::Itertools as _;
+use jj_lib::git;
+use jj_lib::gitignore::GitIgnoreFile;
+use jj_lib::merged_tree::MergedTree;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo as _;
+use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::revset::RevsetExpression;
+use tracing::instrument;
+
+use crate::cli_util::{user_error, CommandError, CommandHelper, WorkspaceCommandHelper};
+use crate::ui::Ui;
+
+/// At most this many unreadable commits are reported.
+const MAX_REPORTED_COMMITS: usize = 10;
+
+/// Check the repo and the config for common problems
+///
+/// This checks for lock files left behind by interrupted jj processes,
+/// problems with the filesystem monitor, changes in a colocated Git repo that
+/// haven't been imported, commits whose objects can't be read, untracked
+/// files that are too large to be snapshotted, and problems in the config.
+/// A fix is suggested for each problem found.
+///
+/// Unlike most commands, this doesn't snapshot the working copy or import
+/// changes from the Git repo, so that they can be checked.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct DoctorArgs {}
+
+/// A problem found by `jj doctor`, with a suggested fix.
+struct Finding {
+    message: String,
+    fix: String,
+}
+
+impl Finding {
+    fn new(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            message: message.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_doctor(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    DoctorArgs {}: &DoctorArgs,
+) -> Result<(), CommandError> {
+    let mut findings = check_config(command);
+    let mut workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    findings.extend(check_locks(&workspace_command));
+    findings.extend(check_fsmonitor(&workspace_command)?);
+    findings.extend(check_colocated_git(&mut workspace_command)?);
+    findings.extend(check_objects(&workspace_command)?);
+    findings.extend(check_large_files(&workspace_command)?);
+
+    let mut formatter = ui.stdout_formatter();
+    for finding in &findings {
+        writeln!(formatter.labeled("warning"), "{}", finding.message)?;
+        writeln!(formatter.labeled("hint"), "Fix: {}", finding.fix)?;
+    }
+    drop(formatter);
+    match findings.len() {
+        0 => {
+            writeln!(ui.stderr(), "No problems found.")?;
+            Ok(())
+        }
+        1 => Err(user_error("Found 1 problem")),
+        n => Err(user_error(format!("Found {n} problems"))),
+    }
+}
+
+fn check_config(command: &CommandHelper) -> Vec<Finding> {
+    let mut findings = vec![];
+    let settings = command.settings();
+    if settings.user_name().is_empty() || settings.user_email().is_empty() {
+        findings.push(Finding::new(
+            "Your name and email aren't configured",
+            r#"Run `jj config set --user user.name "Some One"` and `jj config set --user user.email "someone@example.com"`."#,
+        ));
+    }
+    for problem in command.check_config_files() {
+        let fix = match &problem.suggestion {
+            Some(suggestion) => format!(r#"Did you mean "{suggestion}"?"#),
+            None => "Fix or remove the setting. Run `jj config check` to check the config files \
+                     again."
+                .to_owned(),
+        };
+        findings.push(Finding::new(format!("Config problem: {problem}"), fix));
+    }
+    findings
+}
+
+fn check_locks(workspace_command: &WorkspaceCommandHelper) -> Vec<Finding> {
+    let repo_path = workspace_command.repo().repo_path();
+    let lock_paths = [
+        workspace_command
+            .workspace_root()
+            .join(".jj")
+            .join("working_copy")
+            .join("working_copy.lock"),
+        repo_path.join("op_heads").join("lock"),
+    ];
+    lock_paths
+        .iter()
+        .filter(|path| path.is_file())
+        .map(|path| {
+            Finding::new(
+                format!("Found a lock file that may be stale: {}", path.display()),
+                "If no other jj process is running, an earlier one was probably interrupted. \
+                 Delete the lock file.",
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "watchman")]
+fn check_fsmonitor(
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<Vec<Finding>, CommandError> {
+    use jj_lib::fsmonitor::FsmonitorKind;
+    use jj_lib::local_working_copy::LocalWorkingCopy;
+
+    if !matches!(
+        workspace_command.settings().fsmonitor_kind()?,
+        Some(FsmonitorKind::Watchman)
+    ) {
+        return Ok(vec![]);
+    }
+    let Some(wc) = workspace_command
+        .working_copy()
+        .as_any()
+        .downcast_ref::<LocalWorkingCopy>()
+    else {
+        return Ok(vec![]);
+    };
+    let finding = match wc.query_watchman() {
+        Ok((_clock, Some(_changed_files))) => return Ok(vec![]),
+        Ok((_clock, None)) => Finding::new(
+            "The Watchman state of the working copy is outdated, so the next snapshot will scan \
+             all files",
+            "Run `jj status` to snapshot the working copy and update the state.",
+        ),
+        Err(err) => Finding::new(
+            format!(
+                "{err}: {}",
+                std::error::Error::source(&err)
+                    .map(|err| err.to_string())
+                    .unwrap_or_default()
+            ),
+            "Make sure Watchman is installed and running, or unset `core.fsmonitor`.",
+        ),
+    };
+    Ok(vec![finding])
+}
+
+#[cfg(not(feature = "watchman"))]
+fn check_fsmonitor(
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<Vec<Finding>, CommandError> {
+    use jj_lib::fsmonitor::FsmonitorKind;
+
+    if matches!(
+        workspace_command.settings().fsmonitor_kind()?,
+        Some(FsmonitorKind::Watchman)
+    ) {
+        Ok(vec![Finding::new(
+            "`core.fsmonitor` is set to Watchman, but jj was not compiled with the `watchman` \
+             feature",
+            "Unset `core.fsmonitor`.",
+        )])
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Checks if the HEAD and refs of a colocated Git repo were changed since
+/// they were last imported, by importing them in a transaction that is
+/// discarded.
+fn check_colocated_git(
+    workspace_command: &mut WorkspaceCommandHelper,
+) -> Result<Vec<Finding>, CommandError> {
+    if !workspace_command.working_copy_shared_with_git() {
+        return Ok(vec![]);
+    }
+    let git_settings = workspace_command.settings().git_settings();
+    let old_view = workspace_command.repo().view();
+    let old_git_head = old_view.git_head().clone();
+    let old_git_refs = old_view.git_refs().clone();
+    let mut tx = workspace_command.start_transaction()?;
+    git::import_head(tx.mut_repo())?;
+    git::import_some_refs(tx.mut_repo(), &git_settings, |ref_name| {
+        !git::is_reserved_git_remote_ref(ref_name)
+    })?;
+    let new_view = tx.repo().view();
+
+    let mut findings = vec![];
+    if *new_view.git_head() != old_git_head {
+        findings.push(Finding::new(
+            "The HEAD of the colocated Git repo was moved, but hasn't been imported yet",
+            "Run a command that snapshots the working copy, such as `jj status`, to import it.",
+        ));
+    }
+    let changed_refs = old_git_refs
+        .iter()
+        .merge_join_by(new_view.git_refs(), |(old, _), (new, _)| old.cmp(new))
+        .filter(|entry| match entry {
+            itertools::EitherOrBoth::Both((_, old), (_, new)) => old != new,
+            _ => true,
+        })
+        .map(|entry| entry.reduce(|old, _| old).0)
+        .collect_vec();
+    if !changed_refs.is_empty() {
+        findings.push(Finding::new(
+            format!(
+                "Git refs were changed in the colocated Git repo, but haven't been imported yet: \
+                 {}",
+                changed_refs.iter().join(", ")
+            ),
+            "Run `jj git import`, or a command that snapshots the working copy, such as `jj \
+             status`.",
+        ));
+    }
+    Ok(findings)
+}
+
+/// Checks that the visible commits and their trees can be read from the
+/// store.
+fn check_objects(workspace_command: &WorkspaceCommandHelper) -> Result<Vec<Finding>, CommandError> {
+    let repo = workspace_command.repo();
+    let store = repo.store();
+    let mut unreadable = vec![];
+    let revset = RevsetExpression::visible_heads()
+        .ancestors()
+        .evaluate_programmatic(repo.as_ref())?;
+    for commit_id in revset.iter() {
+        let result = store
+            .get_commit(&commit_id)
+            .and_then(|commit| commit.tree().map(|_| ()));
+        if let Err(err) = result {
+            unreadable.push(format!("{}: {err}", commit_id.hex()));
+            if unreadable.len() >= MAX_REPORTED_COMMITS {
+                break;
+            }
+        }
+    }
+    if unreadable.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![Finding::new(
+        format!(
+            "Some commits can't be read from the store:\n{}",
+            unreadable.iter().map(|line| format!("  {line}")).join("\n")
+        ),
+        "The object files may have been deleted. Restore them from a backup or another clone of \
+         the repo.",
+    )])
+}
+
+/// Finds untracked files that won't be snapshotted because they're larger
+/// than `snapshot.max-new-file-size`.
+fn check_large_files(
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<Vec<Finding>, CommandError> {
+    let max_new_file_size = workspace_command.settings().max_new_file_size()?;
+    let Some(wc_commit_id) = workspace_command.get_wc_commit_id() else {
+        return Ok(vec![]);
+    };
+    let tree = workspace_command
+        .repo()
+        .store()
+        .get_commit(wc_commit_id)?
+        .tree()?;
+    let mut large_files = vec![];
+    find_large_files(
+        workspace_command.workspace_root(),
+        "",
+        &workspace_command.base_ignores(),
+        &tree,
+        max_new_file_size,
+        &mut large_files,
+    )?;
+    Ok(large_files
+        .into_iter()
+        .map(|(path, size)| {
+            let path = workspace_command.format_file_path(&path);
+            Finding::new(
+                format!(
+                    "Untracked file {path} won't be snapshotted because it's {size} bytes, more \
+                     than `snapshot.max-new-file-size` ({max_new_file_size} bytes)"
+                ),
+                "Add it to your `.gitignore` file, or increase `snapshot.max-new-file-size` if \
+                 you want it to be snapshotted.",
+            )
+        })
+        .collect())
+}
+
+fn find_large_files(
+    disk_dir: &Path,
+    dir: &str,
+    git_ignore: &Arc<GitIgnoreFile>,
+    tree: &MergedTree,
+    max_size: u64,
+    large_files: &mut Vec<(RepoPathBuf, u64)>,
+) -> Result<(), CommandError> {
+    let git_ignore = git_ignore.chain_with_file(dir, disk_dir.join(".gitignore"));
+    let entries: Vec<_> = fs::read_dir(disk_dir)?.try_collect()?;
+    for entry in entries.iter().sorted_by_key(|entry| entry.file_name()) {
+        let Some(name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+            continue;
+        };
+        if dir.is_empty() && (name == ".jj" || name == ".git") {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let sub_dir = format!("{dir}{name}/");
+            if !git_ignore.matches(&sub_dir) {
+                find_large_files(
+                    &entry.path(),
+                    &sub_dir,
+                    &git_ignore,
+                    tree,
+                    max_size,
+                    large_files,
+                )?;
+            }
+        } else if file_type.is_file() {
+            let path = RepoPathBuf::from_internal_string(format!("{dir}{name}"));
+            let size = entry.metadata()?.len();
+            if size > max_size
+                && !git_ignore.matches(path.as_internal_file_string())
+                && tree.path_value(&path).is_absent()
+            {
+                large_files.push((path, size));
+            }
+        }
+    }
+    Ok(())
+}
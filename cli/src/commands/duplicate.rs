@@ -15,17 +15,24 @@
 use std::io::Write;
 
 use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
 use jj_lib::commit::Commit;
 use jj_lib::repo::Repo;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt};
+use jj_lib::rewrite::{merge_commit_trees, rebase_commit};
 use tracing::instrument;
 
 use crate::cli_util::{
-    resolve_multiple_nonempty_revsets, short_commit_hash, user_error, CommandError, CommandHelper,
-    RevisionArg,
+    resolve_all_revs, resolve_multiple_nonempty_revsets, short_commit_hash, user_error,
+    CommandError, CommandHelper, RevisionArg,
 };
 use crate::ui::Ui;
 
 /// Create a new change with the same content as an existing one
+///
+/// By default, the duplicates have the same parents as the originals. With
+/// `--destination` or `--insert-after`, the changes are copied onto other
+/// revisions instead, like a cherry-pick.
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct DuplicateArgs {
     /// The revision(s) to duplicate
@@ -34,6 +41,20 @@ pub(crate) struct DuplicateArgs {
     /// Ignored (but lets you pass `-r` for consistency with other commands)
     #[arg(short = 'r', hide = true)]
     unused_revision: bool,
+    /// The revision(s) to put the duplicates on top of
+    #[arg(long, short)]
+    destination: Vec<RevisionArg>,
+    /// The revision(s) to insert the duplicates after
+    ///
+    /// The duplicates are put on top of these revisions, and their children
+    /// are rebased onto the duplicates.
+    #[arg(
+        long,
+        short = 'A',
+        visible_alias = "after",
+        conflicts_with = "destination"
+    )]
+    insert_after: Vec<RevisionArg>,
 }
 
 #[instrument(skip_all)]
@@ -51,9 +72,39 @@ pub(crate) fn cmd_duplicate(
     {
         return Err(user_error("Cannot duplicate the root commit"));
     }
+    let destinations: Option<Vec<Commit>> = if !args.destination.is_empty() {
+        Some(
+            resolve_all_revs(&workspace_command, ui, &args.destination)?
+                .into_iter()
+                .collect(),
+        )
+    } else if !args.insert_after.is_empty() {
+        Some(
+            resolve_all_revs(&workspace_command, ui, &args.insert_after)?
+                .into_iter()
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let children_to_rebase: Vec<Commit> = if args.insert_after.is_empty() {
+        vec![]
+    } else {
+        let destination_ids = destinations
+            .iter()
+            .flatten()
+            .map(|commit| commit.id().clone());
+        RevsetExpression::commits(destination_ids.collect())
+            .children()
+            .evaluate_programmatic(workspace_command.repo().as_ref())?
+            .iter()
+            .commits(workspace_command.repo().store())
+            .try_collect()?
+    };
+    workspace_command.check_rewritable(&children_to_rebase)?;
     let mut duplicated_old_to_new: IndexMap<Commit, Commit> = IndexMap::new();
 
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let base_repo = tx.base_repo().clone();
     let store = base_repo.store();
     let mut_repo = tx.mut_repo();
@@ -66,27 +117,73 @@ pub(crate) fn cmd_duplicate(
         // Topological order ensures that any parents of `original_commit` are
         // either not in `to_duplicate` or were already duplicated.
         let original_commit = store.get_commit(&original_commit_id).unwrap();
-        let new_parents = original_commit
-            .parents()
-            .iter()
-            .map(|parent| {
-                if let Some(duplicated_parent) = duplicated_old_to_new.get(parent) {
-                    duplicated_parent
-                } else {
-                    parent
-                }
-                .id()
-                .clone()
-            })
-            .collect();
+        let original_parents = original_commit.parents();
+        let new_parents = match &destinations {
+            // The duplicates of the roots go onto the destinations
+            Some(destinations)
+                if original_parents
+                    .iter()
+                    .all(|parent| !duplicated_old_to_new.contains_key(parent)) =>
+            {
+                destinations.clone()
+            }
+            _ => original_parents
+                .iter()
+                .map(|parent| duplicated_old_to_new.get(parent).unwrap_or(parent).clone())
+                .collect_vec(),
+        };
+        let new_tree_id = if destinations.is_some() {
+            let old_base_tree = merge_commit_trees(mut_repo, &original_parents)?;
+            let new_base_tree = merge_commit_trees(mut_repo, &new_parents)?;
+            let new_tree = new_base_tree.merge(&old_base_tree, &original_commit.tree()?)?;
+            new_tree.id()
+        } else {
+            original_commit.tree_id().clone()
+        };
         let new_commit = mut_repo
             .rewrite_commit(command.settings(), &original_commit)
             .generate_new_change_id()
-            .set_parents(new_parents)
+            .set_parents(
+                new_parents
+                    .iter()
+                    .map(|commit| commit.id().clone())
+                    .collect(),
+            )
+            .set_tree_id(new_tree_id)
             .write()?;
         duplicated_old_to_new.insert(original_commit, new_commit);
     }
 
+    let mut num_rebased = 0;
+    if !children_to_rebase.is_empty() {
+        // Put the children of the `--insert-after` revisions on top of the
+        // duplicates
+        let new_ids = duplicated_old_to_new.values().map(|commit| commit.id());
+        let new_heads: Vec<Commit> = mut_repo
+            .index()
+            .heads(&mut new_ids.into_iter())
+            .iter()
+            .map(|id| store.get_commit(id))
+            .try_collect()?;
+        let destinations = destinations.unwrap();
+        for child in &children_to_rebase {
+            let new_parents = child
+                .parents()
+                .into_iter()
+                .flat_map(|parent| {
+                    if destinations.contains(&parent) {
+                        new_heads.clone()
+                    } else {
+                        vec![parent]
+                    }
+                })
+                .unique()
+                .collect_vec();
+            rebase_commit(command.settings(), mut_repo, child, &new_parents)?;
+        }
+        num_rebased = children_to_rebase.len() + mut_repo.rebase_descendants(command.settings())?;
+    }
+
     for (old, new) in duplicated_old_to_new.iter() {
         write!(
             ui.stderr(),
@@ -96,6 +193,9 @@ pub(crate) fn cmd_duplicate(
         tx.write_commit_summary(ui.stderr_formatter().as_mut(), new)?;
         writeln!(ui.stderr())?;
     }
+    if num_rebased > 0 {
+        writeln!(ui.stderr(), "Rebased {num_rebased} commits")?;
+    }
     tx.finish(ui, format!("duplicating {} commit(s)", to_duplicate.len()))?;
     Ok(())
 }
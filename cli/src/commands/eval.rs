@@ -0,0 +1,91 @@
+// Copyright 2020 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+
+use itertools::Itertools as _;
+use jj_lib::hex_util::to_reverse_hex;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo as _;
+use jj_lib::revset::{self, RevsetExpression, RevsetIteratorExt as _};
+use tracing::instrument;
+
+use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::formatter::PlainTextFormatter;
+use crate::ui::Ui;
+
+/// Print the ids of the commits in a revset
+///
+/// This is meant for scripts. It prints the full commit id of each commit,
+/// one per line, newest first, without a graph. Unlike `jj log`, the output
+/// doesn't depend on settings such as `templates.log` or `ui.color`, so it
+/// stays the same across configurations and versions.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct EvalArgs {
+    /// The revisions to print
+    #[arg(required = true)]
+    revisions: Vec<RevisionArg>,
+    /// Print the full change ids instead of the commit ids
+    #[arg(long, conflicts_with = "template")]
+    change_id: bool,
+    /// Render each commit using the given template instead of printing its id
+    ///
+    /// The template is rendered without colors. For the syntax, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/templates.md
+    #[arg(long, short = 'T')]
+    template: Option<String>,
+    /// Separate the output for each commit with a NUL character instead of a
+    /// newline
+    #[arg(long, short = '0')]
+    null: bool,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_eval(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &EvalArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_for_read(ui)?;
+    let expressions: Vec<_> = args
+        .revisions
+        .iter()
+        .map(|revision_str| workspace_command.parse_revset(revision_str, Some(ui)))
+        .try_collect()?;
+    let expression = revset::optimize(RevsetExpression::union_all(&expressions));
+    let revset = workspace_command.evaluate_revset(expression)?;
+    let template = args
+        .template
+        .as_ref()
+        .map(|template_string| workspace_command.parse_commit_template(template_string))
+        .transpose()?;
+    let separator = if args.null { b'\0' } else { b'\n' };
+
+    let store = workspace_command.repo().store();
+    let mut stdout = ui.stdout();
+    for commit in revset.iter().commits(store) {
+        let commit = commit?;
+        let mut output = vec![];
+        if let Some(template) = &template {
+            template.format(&commit, &mut PlainTextFormatter::new(&mut output))?;
+        } else if args.change_id {
+            output.extend(to_reverse_hex(&commit.change_id().hex()).unwrap().bytes());
+        } else {
+            output.extend(commit.id().hex().bytes());
+        }
+        output.push(separator);
+        stdout.write_all(&output)?;
+    }
+    Ok(())
+}
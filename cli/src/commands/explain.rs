@@ -0,0 +1,61 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use tracing::instrument;
+
+use crate::cli_util::{user_error_with_hint, CommandError, CommandHelper};
+use crate::error_code::ErrorCode;
+use crate::ui::Ui;
+
+/// Explain an error code
+///
+/// Some errors are printed with a code, as in `Error[E1012]: ...`. This prints
+/// the causes of the error and how to fix it. Without a code, the list of
+/// error codes is printed.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct ExplainArgs {
+    /// The error code, e.g. E1012
+    code: Option<String>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_explain(
+    ui: &mut Ui,
+    _command: &CommandHelper,
+    args: &ExplainArgs,
+) -> Result<(), CommandError> {
+    ui.request_pager();
+    let Some(code) = &args.code else {
+        let mut formatter = ui.stdout_formatter();
+        for &error_code in ErrorCode::ALL {
+            write!(formatter.labeled("error_code"), "{error_code}")?;
+            writeln!(formatter, ": {}", error_code.title())?;
+        }
+        return Ok(());
+    };
+    let Some(error_code) = ErrorCode::from_code(code) else {
+        return Err(user_error_with_hint(
+            format!("Unknown error code: {code}"),
+            "Run `jj explain` to list the error codes.",
+        ));
+    };
+    let mut formatter = ui.stdout_formatter();
+    write!(formatter.labeled("error_code"), "{error_code}")?;
+    writeln!(formatter, ": {}", error_code.title())?;
+    writeln!(formatter)?;
+    write!(formatter, "{}", error_code.explanation())?;
+    Ok(())
+}
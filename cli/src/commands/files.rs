@@ -12,11 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::io::Write;
 
+use jj_lib::backend::TreeValue;
+use jj_lib::repo::Repo as _;
+use jj_lib::repo_path::RepoPath;
 use tracing::instrument;
 
 use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::file_templater::{self, FileEntry};
 use crate::ui::Ui;
 
 /// List files in a revision
@@ -28,6 +33,17 @@ pub(crate) struct FilesArgs {
     /// Only list files matching these prefixes (instead of all files)
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Render each file using the given template
+    ///
+    /// The keywords `path`, `type`, `executable`, `size`, and `blob_id` are
+    /// available. `type` is one of `file`, `symlink`, `directory`,
+    /// `git-submodule`, and `conflict`. For the syntax, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/templates.md
+    #[arg(long, short = 'T')]
+    template: Option<String>,
+    /// Also list the directories containing the files, before their content
+    #[arg(long)]
+    tree: bool,
 }
 
 #[instrument(skip_all)]
@@ -36,17 +52,58 @@ pub(crate) fn cmd_files(
     command: &CommandHelper,
     args: &FilesArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
     let tree = commit.tree()?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&tree])?;
+    let template = args
+        .template
+        .as_ref()
+        .map(|template_string| {
+            file_templater::parse(
+                workspace_command.repo().store(),
+                command.cwd(),
+                workspace_command.workspace_root(),
+                template_string,
+                workspace_command.template_aliases_map(),
+            )
+        })
+        .transpose()?;
     ui.request_pager();
-    for (name, _value) in tree.entries_matching(matcher.as_ref()) {
-        writeln!(
-            ui.stdout(),
-            "{}",
-            &workspace_command.format_file_path(&name)
-        )?;
+    let mut formatter = ui.stdout_formatter();
+    let mut listed_dirs = HashSet::new();
+    for (path, value) in tree.entries_matching(matcher.as_ref()) {
+        let mut entries = vec![];
+        if args.tree {
+            // Entries are sorted by path, so listing the directories that
+            // weren't listed yet puts them before their content.
+            let mut dirs: Vec<&RepoPath> = path
+                .parent()
+                .into_iter()
+                .flat_map(|dir| std::iter::successors(Some(dir), |dir| dir.parent()))
+                .take_while(|dir| !dir.is_root() && !listed_dirs.contains(*dir))
+                .collect();
+            dirs.reverse();
+            for dir in dirs {
+                listed_dirs.insert(dir.to_owned());
+                entries.push(FileEntry {
+                    path: dir.to_owned(),
+                    value: tree.path_value(dir),
+                });
+            }
+        }
+        entries.push(FileEntry { path, value });
+        for entry in entries {
+            if let Some(template) = &template {
+                template.format(&entry, formatter.as_mut())?;
+            } else {
+                let mut path = workspace_command.format_file_path(&entry.path);
+                if let Some(Some(TreeValue::Tree(_))) = entry.value.as_resolved() {
+                    path.push(std::path::MAIN_SEPARATOR);
+                }
+                writeln!(formatter, "{path}")?;
+            }
+        }
     }
     Ok(())
 }
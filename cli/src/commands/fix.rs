@@ -0,0 +1,534 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use blake2::{Blake2b512, Digest as _};
+use itertools::Itertools;
+use jj_lib::backend::{BackendError, CommitId, FileId, TreeValue};
+use jj_lib::commit::Commit;
+use jj_lib::file_util::persist_content_addressed_temp_file;
+use jj_lib::merged_tree::MergedTreeBuilder;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo;
+use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt};
+use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
+use jj_lib::store::Store;
+use rayon::prelude::*;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, CommandError, CommandHelper, RevisionArg,
+};
+use crate::config::CommandNameAndArgs;
+use crate::ui::Ui;
+
+/// Update files with formatting fixes or other changes
+///
+/// The files changed in the given revisions are run through the tools
+/// configured in `fix.tools` whose patterns match their paths. A tool gets the
+/// content of a file on stdin and prints the fixed content on stdout. If more
+/// than one tool matches a file, they run in the order of their names, each
+/// getting the output of the previous one. If a tool fails, the file keeps the
+/// content it had before that tool.
+///
+/// The files fixed in a revision are also fixed in its descendants, instead of
+/// rebasing the descendants onto the fixes, which could cause conflicts.
+///
+/// Each distinct file content is only run through the tools once, and the
+/// tools run in parallel, so fixing a long stack of revisions takes about as
+/// long as fixing the files that differ between them. If all the tools that
+/// match a file have a `cache-key`, the result is also cached in the repo, so
+/// later runs don't run the same file through the same tools again. Change the
+/// `cache-key` when the output of a tool changes, e.g. when it's upgraded.
+/// `jj util gc` removes the cached results.
+///
+/// Example config:
+///
+/// [fix.tools.rustfmt]
+/// command = ["rustfmt", "--emit", "stdout", "--edition", "2021"]
+/// patterns = ["*.rs"]
+///
+/// The patterns are globs matched against the path of the file relative to
+/// the root of the repo. `$path` in the command is replaced by that path.
+#[derive(clap::Args, Clone, Debug)]
+#[command(verbatim_doc_comment)]
+pub(crate) struct FixArgs {
+    /// Fix files in the specified revision(s) and their descendants
+    #[arg(long, short, default_value = "@")]
+    source: Vec<RevisionArg>,
+    /// Fix only these paths
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    paths: Vec<String>,
+    /// How many tools to run in parallel (default: the number of cores)
+    #[arg(long, short)]
+    jobs: Option<usize>,
+}
+
+/// A tool configured in `fix.tools`.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ToolConfig {
+    command: CommandNameAndArgs,
+    patterns: Vec<String>,
+    #[serde(default)]
+    cache_key: Option<String>,
+}
+
+#[derive(Debug)]
+struct Tool {
+    name: String,
+    command: CommandNameAndArgs,
+    patterns: Vec<glob::Pattern>,
+    cache_key: Option<String>,
+}
+
+impl Tool {
+    fn matches(&self, path: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.matches(path))
+    }
+}
+
+/// A file to run through the tools. Since the tools only see the content and
+/// the path, files with the same content at the same path are fixed once no
+/// matter how many revisions they appear in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ToolInput {
+    file_id: FileId,
+    repo_path: RepoPathBuf,
+}
+
+/// The output of the tools from previous runs, stored in the repo. An entry is
+/// keyed by a hash of the commands and cache keys of the tools, the path of the
+/// file, and the id of its content, and holds the fixed content. Only runs of
+/// tools that all have a cache key, and in which no tool failed, are cached.
+#[derive(Debug)]
+pub(crate) struct FixCache {
+    dir: PathBuf,
+}
+
+impl FixCache {
+    pub(crate) fn new(repo_path: &Path) -> Self {
+        FixCache {
+            dir: repo_path.join("fix_cache"),
+        }
+    }
+
+    /// Returns the key of the result of running the `tools` on the `input`, or
+    /// `None` if the result shouldn't be cached.
+    fn key(tools: &[&Tool], input: &ToolInput) -> Option<String> {
+        if tools.is_empty() || tools.iter().any(|tool| tool.cache_key.is_none()) {
+            return None;
+        }
+        let mut hasher = Blake2b512::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        for tool in tools {
+            update(tool.name.as_bytes());
+            update(tool.cache_key.as_deref().unwrap_or_default().as_bytes());
+            let (name, args) = tool.command.split_name_and_args();
+            update(name.as_bytes());
+            for arg in args.iter() {
+                update(arg.as_bytes());
+            }
+            if let CommandNameAndArgs::Structured { env, .. } = &tool.command {
+                for (key, value) in env.iter().sorted() {
+                    update(key.as_bytes());
+                    update(value.as_bytes());
+                }
+            }
+        }
+        update(input.repo_path.as_internal_file_string().as_bytes());
+        update(input.file_id.as_bytes());
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(key)).ok()
+    }
+
+    fn insert(&self, key: &str, content: &[u8]) {
+        // The cache is only an optimization, so failing to write to it isn't
+        // an error.
+        let write = || -> io::Result<()> {
+            fs::create_dir_all(&self.dir)?;
+            let mut temp_file = tempfile::NamedTempFile::new_in(&self.dir)?;
+            temp_file.write_all(content)?;
+            persist_content_addressed_temp_file(temp_file, self.dir.join(key))?;
+            Ok(())
+        };
+        write().ok();
+    }
+
+    /// Removes the entries that were written before `keep_newer`, and returns
+    /// how many there were.
+    pub(crate) fn gc(&self, keep_newer: SystemTime, dry_run: bool) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        let mut num_removed = 0;
+        for entry in entries {
+            let entry = entry?;
+            if entry.metadata()?.modified()? >= keep_newer {
+                continue;
+            }
+            if !dry_run {
+                fs::remove_file(entry.path())?;
+            }
+            num_removed += 1;
+        }
+        Ok(num_removed)
+    }
+}
+
+/// A tool that exited with an error.
+#[derive(Debug)]
+struct ToolFailure {
+    tool_name: String,
+    repo_path: RepoPathBuf,
+    stderr: String,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_fix(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &FixArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let tools = get_tools(command.settings())?;
+    let jobs = match args.jobs {
+        Some(0) => return Err(user_error("must pass at least one job")),
+        Some(jobs) => jobs,
+        None => rayon::current_num_threads(),
+    };
+    let root_commits: Vec<Commit> =
+        resolve_multiple_nonempty_revsets(&args.source, &workspace_command, ui)?
+            .into_iter()
+            .collect();
+    workspace_command.check_rewritable(root_commits.iter())?;
+    let root_trees: Vec<_> = root_commits
+        .iter()
+        .map(|commit| commit.tree())
+        .try_collect()?;
+    let matcher =
+        workspace_command.matcher_from_values(&args.paths, &root_trees.iter().collect_vec())?;
+
+    let mut tx = workspace_command.start_transaction()?;
+    let root_ids = root_commits
+        .iter()
+        .map(|commit| commit.id().clone())
+        .collect();
+    // In topological order, parents first
+    let commits: Vec<Commit> = RevsetExpression::commits(root_ids)
+        .descendants()
+        .evaluate_programmatic(tx.base_repo().as_ref())?
+        .iter()
+        .commits(tx.repo().store())
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .rev()
+        .collect();
+
+    // Collect the files to fix in each commit: the ones it changed, plus the
+    // ones fixed in its ancestors so the descendants don't lose the fixes.
+    let mut unique_inputs: HashSet<ToolInput> = HashSet::new();
+    let mut commit_paths: HashMap<CommitId, Vec<RepoPathBuf>> = HashMap::new();
+    for commit in &commits {
+        let mut paths: HashSet<RepoPathBuf> = commit
+            .parent_ids()
+            .iter()
+            .filter_map(|parent_id| commit_paths.get(parent_id))
+            .flatten()
+            .cloned()
+            .collect();
+        let parent_tree = merge_commit_trees(tx.repo(), &commit.parents())?;
+        let tree = commit.tree()?;
+        for (repo_path, diff) in parent_tree.diff(&tree, matcher.as_ref()) {
+            diff?;
+            paths.insert(repo_path);
+        }
+        for repo_path in &paths {
+            let path = repo_path.as_internal_file_string();
+            if !tools.iter().any(|tool| tool.matches(path)) {
+                continue;
+            }
+            // Only regular files are fixed. Each side of a conflict is fixed
+            // separately.
+            for value in tree.path_value(repo_path).adds().flatten() {
+                if let TreeValue::File { id, executable: _ } = value {
+                    unique_inputs.insert(ToolInput {
+                        file_id: id.clone(),
+                        repo_path: repo_path.clone(),
+                    });
+                }
+            }
+        }
+        commit_paths.insert(commit.id().clone(), paths.into_iter().collect());
+    }
+
+    let store = tx.repo().store().clone();
+    let cache = FixCache::new(tx.base_repo().repo_path());
+    let (fixed_file_ids, failures) = run_tools(
+        jobs,
+        &store,
+        &cache,
+        tx.base_workspace_helper().workspace_root(),
+        &tools,
+        unique_inputs,
+    )?;
+    for failure in &failures {
+        writeln!(
+            ui.warning(),
+            "Fix tool `{}` failed on {}:",
+            failure.tool_name,
+            tx.base_workspace_helper()
+                .format_file_path(&failure.repo_path),
+        )?;
+        write!(ui.warning(), "{}", failure.stderr)?;
+    }
+
+    // Rewrite the commits, parents first
+    let mut new_commit_ids: HashMap<CommitId, CommitId> = HashMap::new();
+    let mut num_fixed_commits = 0;
+    for commit in &commits {
+        let tree = commit.tree()?;
+        let mut tree_builder = MergedTreeBuilder::new(commit.tree_id().clone());
+        let mut changed = false;
+        for repo_path in &commit_paths[commit.id()] {
+            let old_value = tree.path_value(repo_path);
+            let new_value = old_value.map(|value| match value {
+                Some(TreeValue::File { id, executable }) => {
+                    let input = ToolInput {
+                        file_id: id.clone(),
+                        repo_path: repo_path.clone(),
+                    };
+                    match fixed_file_ids.get(&input) {
+                        Some(new_id) => Some(TreeValue::File {
+                            id: new_id.clone(),
+                            executable: *executable,
+                        }),
+                        None => value.clone(),
+                    }
+                }
+                value => value.clone(),
+            });
+            if new_value != old_value {
+                tree_builder.set_or_remove(repo_path.clone(), new_value);
+                changed = true;
+            }
+        }
+        let new_parent_ids = commit
+            .parent_ids()
+            .iter()
+            .map(|id| new_commit_ids.get(id).unwrap_or(id).clone())
+            .collect_vec();
+        if !changed && new_parent_ids == commit.parent_ids() {
+            continue;
+        }
+        if changed {
+            num_fixed_commits += 1;
+        }
+        let new_tree_id = tree_builder.write_tree(&store)?;
+        let new_commit = tx
+            .mut_repo()
+            .rewrite_commit(command.settings(), commit)
+            .set_parents(new_parent_ids)
+            .set_tree_id(new_tree_id)
+            .write()?;
+        new_commit_ids.insert(commit.id().clone(), new_commit.id().clone());
+    }
+    tx.mut_repo().rebase_descendants(command.settings())?;
+    writeln!(
+        ui.stderr(),
+        "Fixed {num_fixed_commits} commits of {} checked.",
+        commits.len()
+    )?;
+    tx.finish(ui, format!("fixed {num_fixed_commits} commits"))
+}
+
+fn get_tools(settings: &UserSettings) -> Result<Vec<Tool>, CommandError> {
+    let configs: HashMap<String, ToolConfig> = settings
+        .config()
+        .get("fix.tools")
+        .optional()?
+        .unwrap_or_default();
+    if configs.is_empty() {
+        return Err(user_error(
+            "No fix tools are configured. Add one in `fix.tools.<name>`.",
+        ));
+    }
+    configs
+        .into_iter()
+        .sorted_by(|(name1, _), (name2, _)| name1.cmp(name2))
+        .map(|(name, config)| {
+            let patterns = config
+                .patterns
+                .iter()
+                .map(|pattern| glob::Pattern::new(pattern))
+                .try_collect()
+                .map_err(|err| {
+                    user_error(format!("Invalid pattern in `fix.tools.{name}`: {err}"))
+                })?;
+            Ok(Tool {
+                name,
+                command: config.command,
+                patterns,
+                cache_key: config.cache_key,
+            })
+        })
+        .try_collect()
+}
+
+/// Runs the matching tools on each of the `inputs` on a pool of `jobs`
+/// threads, and returns the ids of the files whose content changed.
+fn run_tools(
+    jobs: usize,
+    store: &Arc<Store>,
+    cache: &FixCache,
+    workspace_root: &Path,
+    tools: &[Tool],
+    inputs: HashSet<ToolInput>,
+) -> Result<(HashMap<ToolInput, FileId>, Vec<ToolFailure>), CommandError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|err| user_error(format!("Failed to start the fix tools: {err}")))?;
+    let results: Vec<_> = pool.install(|| {
+        inputs
+            .into_par_iter()
+            .map(|input| {
+                let (new_file_id, failures) =
+                    fix_file(store, cache, workspace_root, tools, &input)?;
+                Ok::<_, BackendError>((input, new_file_id, failures))
+            })
+            .collect()
+    });
+    let mut fixed_file_ids = HashMap::new();
+    let mut all_failures = vec![];
+    for result in results {
+        let (input, new_file_id, failures) = result?;
+        if let Some(new_file_id) = new_file_id {
+            fixed_file_ids.insert(input, new_file_id);
+        }
+        all_failures.extend(failures);
+    }
+    all_failures.sort_by(|a, b| (&a.repo_path, &a.tool_name).cmp(&(&b.repo_path, &b.tool_name)));
+    Ok((fixed_file_ids, all_failures))
+}
+
+/// Runs the file through the tools that match its path, unless the result is
+/// cached. Returns the id of the fixed file if its content changed.
+fn fix_file(
+    store: &Store,
+    cache: &FixCache,
+    workspace_root: &Path,
+    tools: &[Tool],
+    input: &ToolInput,
+) -> Result<(Option<FileId>, Vec<ToolFailure>), BackendError> {
+    let path = input.repo_path.as_internal_file_string();
+    let matching_tools = tools.iter().filter(|tool| tool.matches(path)).collect_vec();
+    let cache_key = FixCache::key(&matching_tools, input);
+    if let Some(content) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        let new_file_id = store.write_file(&input.repo_path, &mut content.as_slice())?;
+        return Ok((
+            (new_file_id != input.file_id).then_some(new_file_id),
+            vec![],
+        ));
+    }
+    let mut old_content = vec![];
+    store
+        .read_file(&input.repo_path, &input.file_id)?
+        .read_to_end(&mut old_content)
+        .map_err(|err| BackendError::ReadObject {
+            object_type: "file".to_string(),
+            hash: input.file_id.hex(),
+            source: err.into(),
+        })?;
+    let mut content = old_content.clone();
+    let mut failures = vec![];
+    for tool in matching_tools {
+        match run_tool(tool, workspace_root, path, &content) {
+            Ok(new_content) => content = new_content,
+            Err(stderr) => failures.push(ToolFailure {
+                tool_name: tool.name.clone(),
+                repo_path: input.repo_path.clone(),
+                stderr,
+            }),
+        }
+    }
+    if let Some(cache_key) = cache_key.filter(|_| failures.is_empty()) {
+        cache.insert(&cache_key, &content);
+    }
+    if content == old_content {
+        return Ok((None, failures));
+    }
+    let new_file_id = store.write_file(&input.repo_path, &mut content.as_slice())?;
+    Ok((Some(new_file_id), failures))
+}
+
+/// Runs a single tool, returning its output, or its error output if it
+/// failed.
+fn run_tool(
+    tool: &Tool,
+    workspace_root: &Path,
+    path: &str,
+    content: &[u8],
+) -> Result<Vec<u8>, String> {
+    let (name, args) = tool.command.split_name_and_args();
+    let mut command = Command::new(expand_config_path(&name));
+    if let CommandNameAndArgs::Structured { env, .. } = &tool.command {
+        command.envs(env);
+    }
+    command.args(args.iter().map(|arg| arg.replace("$path", path)));
+    let mut child = command
+        .current_dir(workspace_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("{err}\n"))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let output = std::thread::scope(|scope| {
+        // Write the content from another thread so that a tool that writes
+        // before reading all its input can't deadlock.
+        scope.spawn(move || {
+            // A tool may exit without reading its input.
+            stdin.write_all(content).ok();
+        });
+        child.wait_with_output()
+    })
+    .map_err(|err| format!("{err}\n"))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let mut stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !stderr.is_empty() && !stderr.ends_with('\n') {
+            stderr.push('\n');
+        }
+        Err(stderr)
+    }
+}
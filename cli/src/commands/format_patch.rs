@@ -0,0 +1,302 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::PathBuf;
+
+use jj_lib::backend::{Signature, Timestamp};
+use jj_lib::commit::Commit;
+use jj_lib::hex_util::to_reverse_hex;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
+use jj_lib::rewrite::merge_commit_trees;
+use once_cell::sync::Lazy;
+use tracing::instrument;
+
+use crate::cli_util::{
+    short_commit_hash, user_error, user_error_with_message, CommandError, CommandHelper,
+    RevisionArg, WorkspaceCommandHelper,
+};
+use crate::diff_util::{self, LineDiffOptions};
+use crate::formatter::PlainTextFormatter;
+use crate::time_util::{format_absolute_timestamp_with, FormattingItems};
+use crate::ui::Ui;
+
+/// Export revisions as patch files for sending by email
+///
+/// Each revision is written to a separate file in the mbox format, with the
+/// author, date, and description of the revision in the email headers. The
+/// files can be applied with `git am`. Merge revisions are skipped.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct FormatPatchArgs {
+    /// The revisions to export
+    #[arg(long, short, default_value = "@")]
+    revisions: RevisionArg,
+    /// Directory to write the patch files to (default: the current directory)
+    #[arg(long, short, value_hint = clap::ValueHint::DirPath)]
+    output_directory: Option<PathBuf>,
+}
+
+/// Width of the diffstat, like `git format-patch` uses.
+const STAT_WIDTH: usize = 72;
+
+/// Maximum length of the part of the file names taken from the subjects.
+const MAX_FILE_NAME_SUBJECT_LEN: usize = 52;
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_format_patch(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &FormatPatchArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let mut commits = workspace_command.resolve_revset(&args.revisions, ui)?;
+    // Oldest first
+    commits.reverse();
+    let root_commit_id = workspace_command.repo().store().root_commit_id();
+    if commits.iter().any(|commit| commit.id() == root_commit_id) {
+        return Err(user_error("Cannot format the root commit as a patch"));
+    }
+    let (merges, commits): (Vec<_>, Vec<_>) = commits
+        .into_iter()
+        .partition(|commit| commit.parent_ids().len() > 1);
+    for commit in &merges {
+        writeln!(
+            ui.warning(),
+            "Skipping merge commit {}",
+            short_commit_hash(commit.id())
+        )?;
+    }
+
+    let output_dir = args
+        .output_directory
+        .as_ref()
+        .map(|dir| command.cwd().join(dir));
+    if let Some(dir) = &output_dir {
+        std::fs::create_dir_all(dir).map_err(|err| {
+            user_error_with_message(format!("Failed to create {}", dir.display()), err)
+        })?;
+    }
+    for (i, commit) in commits.iter().enumerate() {
+        let numbered = (commits.len() > 1).then_some((i + 1, commits.len()));
+        let patch = Patch::new(&workspace_command, commit, numbered)?.to_mbox(commit);
+        let file_name = patch_file_name(i + 1, commit.description());
+        let (path, display_path) = match (&output_dir, &args.output_directory) {
+            (Some(dir), Some(arg)) => (dir.join(&file_name), arg.join(&file_name)),
+            _ => (command.cwd().join(&file_name), PathBuf::from(&file_name)),
+        };
+        std::fs::write(&path, patch).map_err(|err| {
+            user_error_with_message(format!("Failed to write {}", path.display()), err)
+        })?;
+        writeln!(ui.stdout(), "{}", display_path.display())?;
+    }
+    Ok(())
+}
+
+/// A revision formatted as an email.
+pub(crate) struct Patch {
+    pub author: Signature,
+    /// The subject, including the `[PATCH]` prefix.
+    pub subject: String,
+    /// The message body: the rest of the description and the diff.
+    pub body: Vec<u8>,
+    /// Whether the description has non-ASCII text, so the charset must be
+    /// declared in the headers.
+    pub non_ascii: bool,
+}
+
+impl Patch {
+    /// Formats the `commit`. The subject is prefixed by `[PATCH n/m]` if
+    /// `numbered` is set, and by `[PATCH]` otherwise.
+    pub fn new(
+        workspace_command: &WorkspaceCommandHelper,
+        commit: &Commit,
+        numbered: Option<(usize, usize)>,
+    ) -> Result<Self, CommandError> {
+        let description = commit.description().trim();
+        let (subject, body) = description.split_once('\n').unwrap_or((description, ""));
+        let body = body.trim();
+
+        let mut text = String::new();
+        if !body.is_empty() {
+            writeln!(text, "{body}\n").unwrap();
+        }
+        let change_id = to_reverse_hex(&commit.change_id().hex()).unwrap();
+        writeln!(text, "Change-Id: {change_id}").unwrap();
+        text.push_str("---\n");
+
+        let mut output = text.into_bytes();
+        let parents = commit.parents();
+        let from_tree = merge_commit_trees(workspace_command.repo().as_ref(), &parents)?;
+        let to_tree = commit.tree()?;
+        let options = LineDiffOptions::default();
+        let mut formatter = PlainTextFormatter::new(&mut output);
+        diff_util::show_diff_stat(
+            &mut formatter,
+            workspace_command,
+            from_tree.diff_stream(&to_tree, &EverythingMatcher),
+            &options,
+            STAT_WIDTH,
+        )?;
+        writeln!(formatter)?;
+        diff_util::show_git_diff(
+            &mut formatter,
+            workspace_command,
+            from_tree.diff_stream(&to_tree, &EverythingMatcher),
+            &options,
+        )?;
+        Ok(Patch {
+            author: commit.author().clone(),
+            subject: format!("{} {subject}", subject_prefix(numbered)),
+            body: output,
+            non_ascii: !body.is_ascii(),
+        })
+    }
+
+    /// Returns the patch in the mbox format, as written by `git format-patch`.
+    fn to_mbox(&self, commit: &Commit) -> Vec<u8> {
+        let mut headers = String::new();
+        writeln!(
+            headers,
+            "From {} Mon Sep 17 00:00:00 2001",
+            commit.id().hex()
+        )
+        .unwrap();
+        writeln!(headers, "From: {}", format_address(&self.author)).unwrap();
+        writeln!(headers, "Date: {}", format_date(&self.author.timestamp)).unwrap();
+        writeln!(headers, "Subject: {}", encode_word(&self.subject)).unwrap();
+        if self.non_ascii {
+            write_mime_headers(&mut headers);
+        }
+        headers.push('\n');
+        let mut output = headers.into_bytes();
+        output.extend_from_slice(&self.body);
+        output
+    }
+}
+
+/// Returns the `[PATCH n/m]` prefix of a subject.
+pub(crate) fn subject_prefix(numbered: Option<(usize, usize)>) -> String {
+    match numbered {
+        Some((number, total)) => format!("[PATCH {number}/{total}]"),
+        None => "[PATCH]".to_owned(),
+    }
+}
+
+/// Writes the headers declaring a UTF-8 body.
+pub(crate) fn write_mime_headers(headers: &mut String) {
+    headers.push_str("MIME-Version: 1.0\n");
+    headers.push_str("Content-Type: text/plain; charset=UTF-8\n");
+    headers.push_str("Content-Transfer-Encoding: 8bit\n");
+}
+
+/// Formats the date for a `Date` header.
+pub(crate) fn format_date(timestamp: &Timestamp) -> String {
+    static DATE_FORMAT: Lazy<FormattingItems> =
+        Lazy::new(|| FormattingItems::parse("%a, %-d %b %Y %H:%M:%S %z").unwrap());
+    format_absolute_timestamp_with(timestamp, &DATE_FORMAT)
+}
+
+/// Formats the name and email of the `signature` as an address.
+pub(crate) fn format_address(signature: &Signature) -> String {
+    format!(
+        "{} <{}>",
+        encode_display_name(&signature.name),
+        signature.email
+    )
+}
+
+/// Returns the name of the patch file, like `0001-fix-typo.patch`.
+fn patch_file_name(number: usize, description: &str) -> String {
+    let subject = description.lines().next().unwrap_or("");
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_FILE_NAME_SUBJECT_LEN);
+    let slug = slug.trim_matches(|c| c == '-' || c == '.');
+    if slug.is_empty() {
+        format!("{number:04}.patch")
+    } else {
+        format!("{number:04}-{slug}.patch")
+    }
+}
+
+/// Encodes non-ASCII text for an email header as specified by RFC 2047.
+pub(crate) fn encode_word(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_owned();
+    }
+    let mut encoded = "=?UTF-8?q?".to_owned();
+    for b in text.bytes() {
+        match b {
+            b' ' => encoded.push('_'),
+            b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                encoded.push(char::from(b));
+            }
+            _ => write!(encoded, "={b:02X}").unwrap(),
+        }
+    }
+    encoded.push_str("?=");
+    encoded
+}
+
+/// Encodes the name in a `From` header, quoting it if it contains special
+/// characters.
+pub(crate) fn encode_display_name(name: &str) -> String {
+    const SPECIALS: &[char] = &[
+        '(', ')', '<', '>', '[', ']', ':', ';', '@', '\\', ',', '.', '"',
+    ];
+    if !name.is_ascii() {
+        encode_word(name)
+    } else if name.contains(SPECIALS) {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_file_name() {
+        assert_eq!(patch_file_name(1, "Fix a typo\n"), "0001-Fix-a-typo.patch");
+        assert_eq!(
+            patch_file_name(12, "cli: don't crash (again)\n\nbody"),
+            "0012-cli-don-t-crash-again.patch"
+        );
+        assert_eq!(patch_file_name(3, ""), "0003.patch");
+        assert_eq!(
+            patch_file_name(1, &"x".repeat(100)),
+            format!("0001-{}.patch", "x".repeat(52))
+        );
+    }
+
+    #[test]
+    fn test_encode_header() {
+        assert_eq!(encode_word("plain text"), "plain text");
+        assert_eq!(encode_word("café au lait"), "=?UTF-8?q?caf=C3=A9_au_lait?=");
+        assert_eq!(encode_display_name("Test User"), "Test User");
+        assert_eq!(encode_display_name("User, Test"), "\"User, Test\"");
+        assert_eq!(encode_display_name("Zoë"), "=?UTF-8?q?Zo=C3=AB?=");
+    }
+}
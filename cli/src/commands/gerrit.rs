@@ -0,0 +1,348 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use jj_lib::backend::{ChangeId, CommitId};
+use jj_lib::commit::Commit;
+use jj_lib::git::GitRefUpdate;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo;
+use jj_lib::revset::{self, RevsetIteratorExt as _};
+use jj_lib::settings::ConfigResultExt as _;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, user_error_with_hint, CommandError,
+    CommandHelper, RevisionArg, WorkspaceCommandHelper,
+};
+use crate::commands::git::{
+    check_commits_to_push, push_updates_with_hook, resolve_push_remote, PushGuards,
+};
+use crate::description_util::{add_trailers, parse_trailers, rewrite_descriptions};
+use crate::git_util::get_git_repo;
+use crate::ui::Ui;
+
+/// Commands for working with Gerrit
+///
+/// Gerrit identifies a change by the `Change-Id` trailer in its description.
+/// The Change-Id of a revision is derived from its change id, so the revision
+/// stays the same Gerrit change when it's rewritten.
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum GerritCommand {
+    Send(GerritSendArgs),
+}
+
+/// Send revisions to Gerrit for review
+///
+/// The revisions and their ancestors that aren't on the remote yet are pushed
+/// to `refs/for/<branch>`, which creates a Gerrit change for each of them, or
+/// uploads a new patch set if the change exists. A `Change-Id` trailer is added
+/// to the descriptions that don't have one.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct GerritSendArgs {
+    /// The revisions to send
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The branch the changes are for (default: `gerrit.default-branch`)
+    #[arg(long, short)]
+    branch: Option<String>,
+    /// The remote to push to (default: `gerrit.default-remote`, or the remote
+    /// `jj git push` uses)
+    #[arg(long)]
+    remote: Option<String>,
+    /// The topic to set on the changes
+    #[arg(long)]
+    topic: Option<String>,
+    /// Add a reviewer to the changes
+    #[arg(long = "reviewer", value_name = "EMAIL")]
+    reviewers: Vec<String>,
+    /// Mark the changes as work in progress
+    #[arg(long)]
+    wip: bool,
+    /// Only display what will be sent
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub(crate) fn cmd_gerrit(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &GerritCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        GerritCommand::Send(sub_args) => cmd_gerrit_send(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_gerrit_send(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &GerritSendArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let config = GerritConfig::new(command)?;
+    let remote = match &args.remote {
+        Some(remote) => remote.clone(),
+        None => match config.default_remote {
+            Some(remote) => remote,
+            None => resolve_push_remote(ui, command, &workspace_command, None)?,
+        },
+    };
+    let options = GerritPushOptions {
+        topic: args.topic.clone(),
+        reviewers: args.reviewers.clone(),
+        wip: args.wip,
+    };
+    send_changes(
+        ui,
+        command,
+        workspace_command,
+        &args.revisions,
+        &remote,
+        args.branch.as_deref(),
+        &options,
+        args.dry_run,
+    )
+}
+
+/// Pushes the `revisions` and their ancestors that aren't on the `remote` yet
+/// for review on the `branch` (default: `gerrit.default-branch`).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn send_changes(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    mut workspace_command: WorkspaceCommandHelper,
+    revisions: &[RevisionArg],
+    remote: &str,
+    branch: Option<&str>,
+    options: &GerritPushOptions,
+    dry_run: bool,
+) -> Result<(), CommandError> {
+    let config = GerritConfig::new(command)?;
+    let git_repo = get_git_repo(workspace_command.repo().store())?;
+    let branch = branch
+        .map(str::to_owned)
+        .or(config.default_branch)
+        .ok_or_else(|| {
+            user_error_with_hint(
+                "No branch to send the changes for",
+                "Give the branch on the command line, or set gerrit.default-branch.",
+            )
+        })?;
+
+    // Send everything that isn't on the remote yet, since Gerrit needs the
+    // ancestors of a change to be either merged or changes themselves.
+    let revisions = resolve_multiple_nonempty_revsets(revisions, &workspace_command, ui)?;
+    let repo = workspace_command.repo().clone();
+    let wanted = revisions
+        .iter()
+        .map(|commit| commit.id().clone())
+        .collect_vec();
+    let mut unwanted = repo
+        .view()
+        .remote_branches(remote)
+        .flat_map(|(_, remote_ref)| remote_ref.target.added_ids())
+        .cloned()
+        .collect_vec();
+    if unwanted.is_empty() {
+        unwanted.push(repo.store().root_commit_id().clone());
+    }
+    let commits: Vec<Commit> = revset::walk_revs(repo.as_ref(), &wanted, &unwanted)?
+        .iter()
+        .commits(repo.store())
+        .try_collect()?;
+    if commits.is_empty() {
+        return Err(user_error(format!("The revisions are already on {remote}")));
+    }
+    let push_guards = PushGuards::from_settings(command.settings())?;
+    check_commits_to_push(ui, &workspace_command, &commits, &push_guards)?;
+    let heads = repo
+        .index()
+        .heads(&mut commits.iter().map(|commit| commit.id()));
+
+    // Add the missing Change-Ids, rewriting the commits from the bottom up
+    let mut tx = workspace_command.start_transaction()?;
+    let mut num_added = 0;
+    let commits = commits.into_iter().rev().collect_vec();
+    let new_commits = rewrite_descriptions(&mut tx, command.settings(), &commits, |commit| {
+        let description = add_change_id(commit.description(), commit.change_id())?;
+        num_added += 1;
+        Some(description)
+    })?;
+    let rewritten: HashMap<&CommitId, &CommitId> = commits
+        .iter()
+        .map(Commit::id)
+        .zip(new_commits.iter().map(Commit::id))
+        .collect();
+    if num_added > 0 {
+        writeln!(ui.stderr(), "Added Change-Id to {num_added} commits")?;
+    }
+    let num_rebased = tx.mut_repo().rebase_descendants(command.settings())?;
+    if num_rebased > 0 {
+        writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
+    }
+    writeln!(
+        ui.stderr(),
+        "Changes to send to {remote} for review on {branch}:"
+    )?;
+    for commit in new_commits.iter().rev() {
+        write!(ui.stderr(), "  ")?;
+        tx.write_commit_summary(ui.stderr_formatter().as_mut(), commit)?;
+        writeln!(ui.stderr())?;
+    }
+    if dry_run {
+        writeln!(ui.stderr(), "Dry-run requested, not pushing.")?;
+        return Ok(());
+    }
+
+    let qualified_name = options.ref_name(&branch);
+    for head in &heads {
+        // The refs/for/ refs are magic, so there's nothing to fast-forward
+        let update = GitRefUpdate {
+            qualified_name: qualified_name.clone(),
+            force: true,
+            new_target: Some(rewritten[head].clone()),
+        };
+        push_updates_with_hook(
+            ui,
+            tx.base_workspace_helper(),
+            &git_repo,
+            remote,
+            &[(update, None)],
+        )?;
+    }
+    if tx.mut_repo().has_changes() {
+        tx.finish(ui, format!("send changes to gerrit for {branch}"))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct GerritConfig {
+    default_branch: Option<String>,
+    default_remote: Option<String>,
+}
+
+impl GerritConfig {
+    fn new(command: &CommandHelper) -> Result<Self, CommandError> {
+        Ok(command
+            .settings()
+            .config()
+            .get("gerrit")
+            .optional()?
+            .unwrap_or_default())
+    }
+}
+
+/// Options for the changes that Gerrit reads from the ref they're pushed to.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GerritPushOptions {
+    pub topic: Option<String>,
+    pub reviewers: Vec<String>,
+    pub wip: bool,
+}
+
+impl GerritPushOptions {
+    /// Returns the ref to push to for review on the `branch`.
+    fn ref_name(&self, branch: &str) -> String {
+        let options = self
+            .topic
+            .iter()
+            .map(|topic| format!("topic={topic}"))
+            .chain(
+                self.reviewers
+                    .iter()
+                    .map(|reviewer| format!("r={reviewer}")),
+            )
+            .chain(self.wip.then(|| "wip".to_owned()))
+            .join(",");
+        if options.is_empty() {
+            format!("refs/for/{branch}")
+        } else {
+            format!("refs/for/{branch}%{options}")
+        }
+    }
+}
+
+/// Returns the Gerrit Change-Id for the `change_id`.
+fn gerrit_change_id(change_id: &ChangeId) -> String {
+    // Gerrit wants 40 hex digits, and change ids have 32, so pad them with
+    // "jjid" in hex.
+    format!("I6a6a6964{}", change_id.hex())
+}
+
+/// Appends a Change-Id trailer for the `change_id` to the `description`, or
+/// returns `None` if it already has one.
+fn add_change_id(description: &str, change_id: &ChangeId) -> Option<String> {
+    let has_change_id = parse_trailers(description)
+        .iter()
+        .any(|line| line.starts_with("Change-Id:"));
+    let trailer = format!("Change-Id: {}", gerrit_change_id(change_id));
+    (!has_change_id).then(|| add_trailers(description, &[trailer]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_change_id() {
+        let change_id = ChangeId::from_hex("0123456789abcdef0123456789abcdef");
+        let trailer = "Change-Id: I6a6a69640123456789abcdef0123456789abcdef";
+        assert_eq!(
+            add_change_id("Title\n", &change_id),
+            Some(format!("Title\n\n{trailer}\n"))
+        );
+        assert_eq!(
+            add_change_id("Title\n\nBody.\n\n", &change_id),
+            Some(format!("Title\n\nBody.\n\n{trailer}\n"))
+        );
+        // Trailers are kept in one paragraph
+        assert_eq!(
+            add_change_id("Title\n\nBug: 123\n", &change_id),
+            Some(format!("Title\n\nBug: 123\n{trailer}\n"))
+        );
+        // A title isn't a trailer
+        assert_eq!(
+            add_change_id("fix: the bug\n", &change_id),
+            Some(format!("fix: the bug\n\n{trailer}\n"))
+        );
+        // An existing Change-Id is kept
+        assert_eq!(
+            add_change_id("Title\n\nChange-Id: I1234\nBug: 123\n", &change_id),
+            None
+        );
+    }
+
+    #[test]
+    fn test_gerrit_ref_name() {
+        let options = GerritPushOptions::default();
+        assert_eq!(options.ref_name("main"), "refs/for/main");
+        let options = GerritPushOptions {
+            topic: Some("feature".to_owned()),
+            reviewers: vec!["a@example.com".to_owned(), "b@example.com".to_owned()],
+            wip: true,
+        };
+        assert_eq!(
+            options.ref_name("main"),
+            "refs/for/main%topic=feature,r=a@example.com,r=b@example.com,wip"
+        );
+    }
+}
@@ -12,42 +12,54 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Write;
+use std::num::NonZeroU32;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::{fmt, fs, io};
+use std::sync::Mutex;
+use std::{fmt, fs, io, iter, thread};
 
 use clap::{ArgGroup, Subcommand};
-use itertools::Itertools;
-use jj_lib::backend::TreeValue;
+use itertools::{EitherOrBoth, Itertools};
+use jj_lib::backend::{CommitId, TreeValue};
+use jj_lib::commit::Commit;
+use jj_lib::default_index::DefaultIndexStore;
 use jj_lib::file_util;
 use jj_lib::git::{
-    self, parse_gitmodules, GitBranchPushTargets, GitFetchError, GitFetchStats, GitPushError,
+    self, parse_gitmodules, FetchDepth, GitBranchPushTargets, GitFetchError, GitFetchStats,
+    GitPushError, GitPushStats, GitRefUpdate,
 };
 use jj_lib::object_id::ObjectId;
 use jj_lib::op_store::RefTarget;
 use jj_lib::refs::{
     classify_branch_push_action, BranchPushAction, BranchPushUpdate, TrackingRefPair,
 };
-use jj_lib::repo::Repo;
+use jj_lib::repo::{MutableRepo, Repo};
 use jj_lib::repo_path::RepoPath;
 use jj_lib::revset::{self, RevsetExpression, RevsetIteratorExt as _};
+use jj_lib::rewrite::{merge_commit_trees, sign_commits};
 use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use jj_lib::signing::SignBehavior;
 use jj_lib::str_util::StringPattern;
 use jj_lib::view::View;
 use jj_lib::workspace::Workspace;
 use maplit::hashset;
 
 use crate::cli_util::{
-    parse_string_pattern, print_trackable_remote_branches, resolve_multiple_nonempty_revsets,
-    short_change_hash, short_commit_hash, user_error, user_error_with_hint,
-    user_error_with_hint_opt, user_error_with_message, CommandError, CommandHelper, RevisionArg,
-    WorkspaceCommandHelper,
+    internal_error, parse_string_pattern, print_trackable_remote_branches,
+    resolve_multiple_nonempty_revsets, short_change_hash, short_commit_hash, user_error,
+    user_error_with_hint, user_error_with_hint_opt, user_error_with_message, CommandError,
+    CommandHelper, RevisionArg, WorkspaceCommandHelper, WorkspaceCommandTransaction,
 };
+use crate::error_code::ErrorCode;
+use crate::formatter::PlainTextFormatter;
 use crate::git_util::{
     get_git_repo, print_failed_git_export, print_git_import_stats, with_remote_git_callbacks,
+    with_shared_remote_git_callbacks,
 };
+use crate::hooks::HookEvent;
+use crate::tr;
 use crate::ui::Ui;
 
 /// Commands for working with the underlying Git repo
@@ -61,6 +73,7 @@ pub enum GitCommand {
     Fetch(GitFetchArgs),
     Clone(GitCloneArgs),
     Push(GitPushArgs),
+    Sync(GitSyncArgs),
     Import(GitImportArgs),
     Export(GitExportArgs),
     #[command(subcommand, hide = true)]
@@ -123,6 +136,15 @@ pub struct GitFetchArgs {
     /// Fetch from all remotes
     #[arg(long, conflicts_with = "remotes")]
     all_remotes: bool,
+    /// Fetch only this many commits from the tip of each branch
+    ///
+    /// The repo becomes shallow: the commits at the cut-off are shown as if
+    /// they had no parents until more history is fetched.
+    #[arg(long, conflicts_with = "deepen")]
+    depth: Option<NonZeroU32>,
+    /// Fetch this many more commits below the shallow boundary of the repo
+    #[arg(long)]
+    deepen: Option<NonZeroU32>,
 }
 
 /// Create a new repo backed by a clone of a Git repo
@@ -139,6 +161,41 @@ pub struct GitCloneArgs {
     /// Whether or not to colocate the Jujutsu repo with the git repo
     #[arg(long)]
     colocate: bool,
+    /// Create a shallow clone with only this many commits from the tip of
+    /// each branch
+    #[arg(long)]
+    depth: Option<NonZeroU32>,
+    /// Check out this branch instead of the remote's default branch
+    #[arg(long, short)]
+    branch: Option<String>,
+    /// Fetch only the branch given by `--branch`
+    ///
+    /// The Git remote is also set up to fetch only that branch.
+    #[arg(long, requires = "branch")]
+    single_branch: bool,
+}
+
+/// Make a Git remote mirror the repo, or the other way around
+///
+/// By default, the branches and tags on the remote are made to match the
+/// local branches and tags exactly: they're force-pushed, and the ones that
+/// don't exist locally are deleted from the remote. With `--from-remote`, the
+/// local branches and tags are made to match the remote's instead, and the
+/// ones that don't exist on the remote are deleted locally.
+///
+/// Like `jj git push`, this refuses to push commits that aren't ready to be
+/// shared, such as commits without a description (see `git.push-guard`).
+#[derive(clap::Args, Clone, Debug)]
+pub struct GitSyncArgs {
+    /// The remote to mirror
+    #[arg(long, value_name = "REMOTE")]
+    mirror: String,
+    /// Make the repo mirror the remote instead
+    #[arg(long)]
+    from_remote: bool,
+    /// Only display what will change on the remote
+    #[arg(long, conflicts_with = "from_remote")]
+    dry_run: bool,
 }
 
 /// Push to a Git remote
@@ -151,9 +208,13 @@ pub struct GitCloneArgs {
 #[command(group(ArgGroup::new("specific").args(&["branch", "change", "revisions"]).multiple(true)))]
 #[command(group(ArgGroup::new("what").args(&["all", "deleted"]).conflicts_with("specific")))]
 pub struct GitPushArgs {
-    /// The remote to push to (only named remotes are supported)
-    #[arg(long)]
-    remote: Option<String>,
+    /// The remote to push to (only named remotes are supported, can be
+    /// repeated)
+    ///
+    /// The name of a group of remotes in `git.remote-groups` pushes to each of
+    /// them. Pushes to several remotes run in parallel.
+    #[arg(long = "remote", value_name = "remote")]
+    remotes: Vec<String>,
     /// Push only this branch (can be repeated)
     ///
     /// By default, the specified name matches exactly. Use `glob:` prefix to
@@ -172,8 +233,21 @@ pub struct GitPushArgs {
     revisions: Vec<RevisionArg>,
     /// Push this commit by creating a branch based on its change ID (can be
     /// repeated)
+    ///
+    /// The branch is named by `git.push-branch-template` if it's set, and by
+    /// `git.push-branch-prefix` followed by the change ID otherwise.
     #[arg(long, short)]
     change: Vec<RevisionArg>,
+    /// Push commits with conflicts
+    #[arg(long)]
+    allow_conflicts: bool,
+    /// Push commits that don't change any files, if `git.push-guard.empty` is
+    /// set
+    #[arg(long)]
+    allow_empty: bool,
+    /// Push commits without a description
+    #[arg(long)]
+    allow_empty_description: bool,
     /// Only display what will change on the remote
     #[arg(long)]
     dry_run: bool,
@@ -211,7 +285,7 @@ fn make_branch_term(branch_names: &[impl fmt::Display]) -> String {
     }
 }
 
-fn map_git_error(err: git2::Error) -> CommandError {
+pub(crate) fn map_git_error(err: git2::Error) -> CommandError {
     if err.class() == git2::ErrorClass::Ssh {
         let hint =
             if err.code() == git2::ErrorCode::Certificate && std::env::var_os("HOME").is_none() {
@@ -249,6 +323,7 @@ fn cmd_git_remote_add(
     command: &CommandHelper,
     args: &GitRemoteAddArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let workspace_command = command.workspace_helper(ui)?;
     let repo = workspace_command.repo();
     let git_repo = get_git_repo(repo.store())?;
@@ -261,10 +336,11 @@ fn cmd_git_remote_remove(
     command: &CommandHelper,
     args: &GitRemoteRemoveArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
     let repo = workspace_command.repo();
     let git_repo = get_git_repo(repo.store())?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     git::remove_remote(tx.mut_repo(), &git_repo, &args.remote)?;
     if tx.mut_repo().has_changes() {
         tx.finish(ui, format!("remove git remote {}", &args.remote))
@@ -278,10 +354,11 @@ fn cmd_git_remote_rename(
     command: &CommandHelper,
     args: &GitRemoteRenameArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
     let repo = workspace_command.repo();
     let git_repo = get_git_repo(repo.store())?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     git::rename_remote(tx.mut_repo(), &git_repo, &args.old, &args.new)?;
     if tx.mut_repo().has_changes() {
         tx.finish(
@@ -324,8 +401,12 @@ pub fn git_init(
 
     if let Some(git_store_str) = git_repo {
         let git_store_path = cwd.join(git_store_str);
-        let (workspace, repo) =
-            Workspace::init_external_git(command.settings(), workspace_root, &git_store_path)?;
+        let (workspace, repo) = Workspace::init_external_git(
+            command.settings(),
+            workspace_root,
+            &git_store_path,
+            command.init_working_copy_factory()?,
+        )?;
         let mut workspace_command = command.for_loaded_repo(ui, workspace, repo)?;
         maybe_add_gitignore(&workspace_command)?;
         // Import refs first so all the reachable commits are indexed in
@@ -333,7 +414,7 @@ pub fn git_init(
         workspace_command.import_git_refs(ui)?;
         workspace_command.maybe_snapshot(ui)?;
         if !workspace_command.working_copy_shared_with_git() {
-            let mut tx = workspace_command.start_transaction();
+            let mut tx = workspace_command.start_transaction()?;
             jj_lib::git::import_head(tx.mut_repo())?;
             if let Some(git_head_id) = tx.mut_repo().view().git_head().as_normal().cloned() {
                 let git_head_commit = tx.mut_repo().store().get_commit(&git_head_id)?;
@@ -355,7 +436,11 @@ pub fn git_init(
             ));
         }
 
-        Workspace::init_internal_git(command.settings(), workspace_root)?;
+        Workspace::init_internal_git(
+            command.settings(),
+            workspace_root,
+            command.init_working_copy_factory()?,
+        )?;
     }
 
     Ok(())
@@ -367,6 +452,7 @@ fn cmd_git_fetch(
     command: &CommandHelper,
     args: &GitFetchArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
     let git_repo = get_git_repo(workspace_command.repo().store())?;
     let remotes = if args.all_remotes {
@@ -376,18 +462,38 @@ fn cmd_git_fetch(
     } else {
         args.remotes.clone()
     };
-    let mut tx = workspace_command.start_transaction();
+    let prune_merged_branches = command
+        .settings()
+        .config()
+        .get_bool("git.prune-merged-branches")
+        .optional()?
+        .unwrap_or(false);
+    let depth = match (args.depth, args.deepen) {
+        (Some(depth), _) => FetchDepth::Depth(depth),
+        (_, Some(deepen)) => FetchDepth::Deepen(deepen),
+        (None, None) => FetchDepth::Unlimited,
+    };
+    let mut deepened = false;
+    let mut tx = workspace_command.start_transaction()?;
     for remote in &remotes {
-        let stats = with_remote_git_callbacks(ui, |cb| {
+        let tracked_branches: Vec<String> = tx
+            .repo()
+            .view()
+            .remote_branches(remote)
+            .filter(|(_, remote_ref)| remote_ref.is_tracking() && remote_ref.is_present())
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        let stats = with_remote_git_callbacks(ui, command.settings(), remote, |cb| {
             git::fetch(
                 tx.mut_repo(),
                 &git_repo,
                 remote,
                 &args.branch,
+                depth,
                 cb,
                 &command.settings().git_settings(),
             )
-        })
+        })?
         .map_err(|err| match err {
             GitFetchError::InvalidBranchPattern => {
                 if args
@@ -408,11 +514,84 @@ fn cmd_git_fetch(
             _ => user_error(err),
         })?;
         print_git_import_stats(ui, &stats.import_stats)?;
+        deepened |= !stats.unshallowed_commits.is_empty();
+        if prune_merged_branches {
+            prune_merged_branches_after_fetch(ui, tx.mut_repo(), remote, &tracked_branches)?;
+        }
     }
     tx.finish(
         ui,
         format!("fetch from git remote(s) {}", remotes.iter().join(",")),
     )?;
+    if deepened {
+        reindex_after_deepening(ui, command, &workspace_command)?;
+    }
+    Ok(())
+}
+
+/// Rebuilds the commit index after history below the shallow boundary was
+/// fetched. The commits that were at the boundary are indexed as children of
+/// the root commit, which they no longer are.
+fn reindex_after_deepening(
+    ui: &Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<(), CommandError> {
+    let repo = workspace_command.repo();
+    let index_store = repo.index_store();
+    let Some(default_index_store) = index_store.as_any().downcast_ref::<DefaultIndexStore>() else {
+        return Ok(());
+    };
+    // Load the store again, since the loaded one has cached the commits at
+    // the old boundary.
+    let workspace = command.load_workspace()?;
+    default_index_store
+        .compact(repo.operation(), workspace.repo_loader().store())
+        .map_err(internal_error)?;
+    writeln!(
+        ui.stderr(),
+        "Reindexed the commits below the old shallow boundary."
+    )?;
+    Ok(())
+}
+
+/// Deletes the local branches among the `tracked_branches` whose branch on the
+/// `remote` was deleted, if all of their commits are reachable from the
+/// remaining branches on the `remote`.
+fn prune_merged_branches_after_fetch(
+    ui: &Ui,
+    mut_repo: &mut MutableRepo,
+    remote: &str,
+    tracked_branches: &[String],
+) -> Result<(), CommandError> {
+    let view = mut_repo.view();
+    let remote_head_ids = view
+        .remote_branches(remote)
+        .flat_map(|(_, remote_ref)| remote_ref.target.added_ids())
+        .cloned()
+        .collect_vec();
+    let mut merged_branches = vec![];
+    for branch_name in tracked_branches {
+        if view.get_remote_branch(branch_name, remote).is_present() {
+            continue;
+        }
+        let local_target = view.get_local_branch(branch_name);
+        if local_target.is_absent() {
+            continue;
+        }
+        let local_ids = local_target.added_ids().cloned().collect_vec();
+        if revset::walk_revs(mut_repo, &local_ids, &remote_head_ids)?.is_empty() {
+            merged_branches.push(branch_name);
+        }
+    }
+    for branch_name in merged_branches {
+        mut_repo.set_local_branch_target(branch_name, RefTarget::absent());
+        writeln!(
+            ui.stderr(),
+            "Deleted branch {branch_name}: {branch_name}@{remote} was deleted and its commits are \
+             merged"
+        )?;
+    }
     Ok(())
 }
 
@@ -526,14 +705,7 @@ fn cmd_git_clone(
     let canonical_wc_path: PathBuf = wc_path
         .canonicalize()
         .map_err(|err| user_error_with_message(format!("Failed to create {wc_path_str}"), err))?;
-    let clone_result = do_git_clone(
-        ui,
-        command,
-        args.colocate,
-        remote_name,
-        &source,
-        &canonical_wc_path,
-    );
+    let clone_result = do_git_clone(ui, command, args, remote_name, &source, &canonical_wc_path);
     if clone_result.is_err() {
         let clean_up_dirs = || -> io::Result<()> {
             fs::remove_dir_all(canonical_wc_path.join(".jj"))?;
@@ -557,21 +729,26 @@ fn cmd_git_clone(
     }
 
     let (mut workspace_command, stats) = clone_result?;
-    if let Some(default_branch) = &stats.default_branch {
-        let default_branch_remote_ref = workspace_command
+    if let Some(branch) = args.branch.as_ref().or(stats.default_branch.as_ref()) {
+        let branch_remote_ref = workspace_command
             .repo()
             .view()
-            .get_remote_branch(default_branch, remote_name);
-        if let Some(commit_id) = default_branch_remote_ref.target.as_normal().cloned() {
-            let mut checkout_tx = workspace_command.start_transaction();
+            .get_remote_branch(branch, remote_name);
+        if let Some(commit_id) = branch_remote_ref.target.as_normal().cloned() {
+            let mut checkout_tx = workspace_command.start_transaction()?;
             // For convenience, create local branch as Git would do.
             checkout_tx
                 .mut_repo()
-                .track_remote_branch(default_branch, remote_name);
+                .track_remote_branch(branch, remote_name);
             if let Ok(commit) = checkout_tx.repo().store().get_commit(&commit_id) {
                 checkout_tx.check_out(&commit)?;
             }
-            checkout_tx.finish(ui, "check out git remote's default branch")?;
+            let description = if args.branch.is_some() {
+                format!("check out git remote's branch {branch}")
+            } else {
+                "check out git remote's default branch".to_owned()
+            };
+            checkout_tx.finish(ui, description)?;
         }
     }
     Ok(())
@@ -580,15 +757,23 @@ fn cmd_git_clone(
 fn do_git_clone(
     ui: &mut Ui,
     command: &CommandHelper,
-    colocate: bool,
+    args: &GitCloneArgs,
     remote_name: &str,
     source: &str,
     wc_path: &Path,
 ) -> Result<(WorkspaceCommandHelper, GitFetchStats), CommandError> {
-    let (workspace, repo) = if colocate {
-        Workspace::init_colocated_git(command.settings(), wc_path)?
+    let (workspace, repo) = if args.colocate {
+        Workspace::init_colocated_git(
+            command.settings(),
+            wc_path,
+            command.init_working_copy_factory()?,
+        )?
     } else {
-        Workspace::init_internal_git(command.settings(), wc_path)?
+        Workspace::init_internal_git(
+            command.settings(),
+            wc_path,
+            command.init_working_copy_factory()?,
+        )?
     };
     let git_repo = get_git_repo(repo.store())?;
     writeln!(
@@ -598,29 +783,53 @@ fn do_git_clone(
     )?;
     let mut workspace_command = command.for_loaded_repo(ui, workspace, repo)?;
     maybe_add_gitignore(&workspace_command)?;
-    git_repo.remote(remote_name, source).unwrap();
-    let mut fetch_tx = workspace_command.start_transaction();
+    let branch_patterns = match &args.branch {
+        Some(branch) if args.single_branch => {
+            let refspec = format!("+refs/heads/{branch}:refs/remotes/{remote_name}/{branch}");
+            git_repo
+                .remote_with_fetch(remote_name, source, &refspec)
+                .map_err(|err| user_error_with_message("Invalid branch name", err))?;
+            vec![StringPattern::exact(branch)]
+        }
+        _ => {
+            git_repo.remote(remote_name, source).unwrap();
+            vec![StringPattern::everything()]
+        }
+    };
+    let mut fetch_tx = workspace_command.start_transaction()?;
 
-    let stats = with_remote_git_callbacks(ui, |cb| {
+    let stats = with_remote_git_callbacks(ui, command.settings(), remote_name, |cb| {
         git::fetch(
             fetch_tx.mut_repo(),
             &git_repo,
             remote_name,
-            &[StringPattern::everything()],
+            &branch_patterns,
+            args.depth.map_or(FetchDepth::Unlimited, FetchDepth::Depth),
             cb,
             &command.settings().git_settings(),
         )
-    })
+    })?
     .map_err(|err| match err {
         GitFetchError::NoSuchRemote(_) => {
             panic!("shouldn't happen as we just created the git remote")
         }
         GitFetchError::GitImportError(err) => CommandError::from(err),
         GitFetchError::InternalGitError(err) => map_git_error(err),
-        GitFetchError::InvalidBranchPattern => {
-            unreachable!("we didn't provide any globs")
-        }
+        // Only possible with a `--branch` name that Git wouldn't accept.
+        err @ GitFetchError::InvalidBranchPattern => user_error(err),
     })?;
+    if let Some(branch) = &args.branch {
+        if fetch_tx
+            .repo()
+            .view()
+            .get_remote_branch(branch, remote_name)
+            .is_absent()
+        {
+            return Err(user_error(format!(
+                "Branch {branch} doesn't exist on the remote"
+            )));
+        }
+    }
     print_git_import_stats(ui, &stats.import_stats)?;
     fetch_tx.finish(ui, "fetch from git remote into empty repo")?;
     Ok((workspace_command, stats))
@@ -631,15 +840,18 @@ fn cmd_git_push(
     command: &CommandHelper,
     args: &GitPushArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
     let git_repo = get_git_repo(workspace_command.repo().store())?;
 
-    let remote = if let Some(name) = &args.remote {
-        name.clone()
+    let remotes = if args.remotes.is_empty() {
+        vec![get_default_push_remote(ui, command.settings(), &git_repo)?]
     } else {
-        get_default_push_remote(ui, command.settings(), &git_repo)?
+        args.remotes.clone()
     };
+    let remotes = expand_remote_groups(command.settings(), &git_repo, &remotes)?;
 
+    let push_policy = BranchPushPolicy::from_settings(command.settings())?;
     let repo = workspace_command.repo().clone();
     let wc_commit_id = workspace_command.get_wc_commit_id().cloned();
     let change_commits: Vec<_> = args
@@ -648,224 +860,570 @@ fn cmd_git_push(
         .map(|change_str| workspace_command.resolve_single_rev(change_str, ui))
         .try_collect()?;
 
-    let mut tx = workspace_command.start_transaction();
-    let tx_description;
-    let mut branch_updates = vec![];
-    if args.all {
-        for (branch_name, targets) in repo.view().local_remote_branches(&remote) {
-            match classify_branch_update(branch_name, &remote, targets) {
-                Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
-                Ok(None) => {}
-                Err(reason) => reason.print(ui)?,
-            }
-        }
-        tx_description = format!("push all branches to git remote {remote}");
-    } else if args.deleted {
-        for (branch_name, targets) in repo.view().local_remote_branches(&remote) {
-            if targets.local_target.is_present() {
-                continue;
+    let mut tx = workspace_command.start_transaction()?;
+    let mut remote_branch_updates = vec![];
+    let mut any_branches_targeted = false;
+    for remote in &remotes {
+        let mut branch_updates = vec![];
+        if args.all {
+            for (branch_name, targets) in repo.view().local_remote_branches(remote) {
+                match classify_branch_update(branch_name, remote, targets, &push_policy) {
+                    Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
+                    Ok(None) => {}
+                    Err(reason) => reason.print(ui)?,
+                }
             }
-            match classify_branch_update(branch_name, &remote, targets) {
-                Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
-                Ok(None) => {}
-                Err(reason) => reason.print(ui)?,
+        } else if args.deleted {
+            for (branch_name, targets) in repo.view().local_remote_branches(remote) {
+                if targets.local_target.is_present() {
+                    continue;
+                }
+                match classify_branch_update(branch_name, remote, targets, &push_policy) {
+                    Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
+                    Ok(None) => {}
+                    Err(reason) => reason.print(ui)?,
+                }
             }
-        }
-        tx_description = format!("push all deleted branches to git remote {remote}");
-    } else {
-        let mut seen_branches = hashset! {};
-        let branches_by_name =
-            find_branches_to_push(repo.view(), &args.branch, &remote, &mut seen_branches)?;
-        for (branch_name, targets) in branches_by_name {
-            match classify_branch_update(branch_name, &remote, targets) {
-                Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
-                Ok(None) => writeln!(
-                    ui.stderr(),
-                    "Branch {branch_name}@{remote} already matches {branch_name}",
-                )?,
-                Err(reason) => return Err(reason.into()),
+        } else {
+            let mut seen_branches = hashset! {};
+            let branches_by_name =
+                find_branches_to_push(repo.view(), &args.branch, remote, &mut seen_branches)?;
+            for (branch_name, targets) in branches_by_name {
+                match classify_branch_update(branch_name, remote, targets, &push_policy) {
+                    Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
+                    Ok(None) => writeln!(
+                        ui.stderr(),
+                        "Branch {branch_name}@{remote} already matches {branch_name}",
+                    )?,
+                    Err(reason) => return Err(reason.into()),
+                }
             }
-        }
 
-        for (change_str, commit) in std::iter::zip(args.change.iter(), change_commits) {
-            let mut branch_name = format!(
-                "{}{}",
-                command.settings().push_branch_prefix(),
-                commit.change_id().hex()
-            );
-            if !seen_branches.insert(branch_name.clone()) {
-                continue;
-            }
-            let view = tx.base_repo().view();
-            if view.get_local_branch(&branch_name).is_absent() {
-                // A local branch with the full change ID doesn't exist already, so use the
-                // short ID if it's not ambiguous (which it shouldn't be most of the time).
-                let short_change_id = short_change_hash(commit.change_id());
-                if tx
-                    .base_workspace_helper()
-                    .resolve_single_rev(&short_change_id, ui)
-                    .is_ok()
-                {
-                    // Short change ID is not ambiguous, so update the branch name to use it.
-                    branch_name = format!(
-                        "{}{}",
-                        command.settings().push_branch_prefix(),
-                        short_change_id
-                    );
-                };
-            }
-            if view.get_local_branch(&branch_name).is_absent() {
-                writeln!(
-                    ui.stderr(),
-                    "Creating branch {} for revision {}",
-                    branch_name,
-                    change_str.deref()
+            for (change_str, commit) in std::iter::zip(args.change.iter(), &change_commits) {
+                let branch_name = change_branch_name(
+                    ui,
+                    command.settings(),
+                    tx.base_workspace_helper(),
+                    remote,
+                    commit,
                 )?;
+                if !seen_branches.insert(branch_name.clone()) {
+                    continue;
+                }
+                let view = tx.repo().view();
+                if view.get_local_branch(&branch_name).is_absent() {
+                    writeln!(
+                        ui.stderr(),
+                        "Creating branch {} for revision {}",
+                        branch_name,
+                        change_str.deref()
+                    )?;
+                }
+                tx.mut_repo()
+                    .set_local_branch_target(&branch_name, RefTarget::normal(commit.id().clone()));
+                let targets = TrackingRefPair {
+                    local_target: tx.repo().view().get_local_branch(&branch_name),
+                    remote_ref: tx.repo().view().get_remote_branch(&branch_name, remote),
+                };
+                match classify_branch_update(&branch_name, remote, targets, &push_policy) {
+                    Ok(Some(update)) => branch_updates.push((branch_name.clone(), update)),
+                    Ok(None) => writeln!(
+                        ui.stderr(),
+                        "Branch {branch_name}@{remote} already matches {branch_name}",
+                    )?,
+                    Err(reason) => return Err(reason.into()),
+                }
             }
-            tx.mut_repo()
-                .set_local_branch_target(&branch_name, RefTarget::normal(commit.id().clone()));
-            let targets = TrackingRefPair {
-                local_target: tx.repo().view().get_local_branch(&branch_name),
-                remote_ref: tx.repo().view().get_remote_branch(&branch_name, &remote),
+
+            let use_default_revset =
+                args.branch.is_empty() && args.change.is_empty() && args.revisions.is_empty();
+            let revision_commit_ids: HashSet<_> = if use_default_revset {
+                let Some(wc_commit_id) = &wc_commit_id else {
+                    return Err(user_error("Nothing checked out in this workspace"));
+                };
+                let current_branches_expression = RevsetExpression::remote_branches(
+                    StringPattern::everything(),
+                    StringPattern::Exact(remote.to_owned()),
+                )
+                .range(&RevsetExpression::commit(wc_commit_id.clone()))
+                .intersection(&RevsetExpression::branches(StringPattern::everything()));
+                let current_branches_revset = tx
+                    .base_workspace_helper()
+                    .evaluate_revset(current_branches_expression)?;
+                current_branches_revset.iter().collect()
+            } else {
+                // TODO: Narrow search space to local target commits.
+                // TODO: Remove redundant CommitId -> Commit -> CommitId round trip.
+                resolve_multiple_nonempty_revsets(&args.revisions, tx.base_workspace_helper(), ui)?
+                    .iter()
+                    .map(|commit| commit.id().clone())
+                    .collect()
             };
-            match classify_branch_update(&branch_name, &remote, targets) {
-                Ok(Some(update)) => branch_updates.push((branch_name.clone(), update)),
-                Ok(None) => writeln!(
-                    ui.stderr(),
-                    "Branch {branch_name}@{remote} already matches {branch_name}",
-                )?,
-                Err(reason) => return Err(reason.into()),
+            let branches_targeted = repo
+                .view()
+                .local_remote_branches(remote)
+                .filter(|(_, targets)| {
+                    let mut local_ids = targets.local_target.added_ids();
+                    local_ids.any(|id| revision_commit_ids.contains(id))
+                })
+                .collect_vec();
+            any_branches_targeted |= !branches_targeted.is_empty();
+            for &(branch_name, targets) in &branches_targeted {
+                if !seen_branches.insert(branch_name.to_owned()) {
+                    continue;
+                }
+                match classify_branch_update(branch_name, remote, targets, &push_policy) {
+                    Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
+                    Ok(None) => {}
+                    Err(reason) => reason.print(ui)?,
+                }
             }
         }
+        if !branch_updates.is_empty() {
+            remote_branch_updates.push((remote.clone(), branch_updates));
+        }
+    }
+    let use_revisions = !args.all
+        && !args.deleted
+        && (!args.revisions.is_empty() || (args.branch.is_empty() && args.change.is_empty()));
+    if use_revisions && !any_branches_targeted {
+        writeln!(
+            ui.warning(),
+            "No branches point to the specified revisions."
+        )?;
+    }
+    if remote_branch_updates.is_empty() {
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
+        return Ok(());
+    }
 
-        let use_default_revset =
-            args.branch.is_empty() && args.change.is_empty() && args.revisions.is_empty();
-        let revision_commit_ids: HashSet<_> = if use_default_revset {
-            let Some(wc_commit_id) = wc_commit_id else {
-                return Err(user_error("Nothing checked out in this workspace"));
-            };
-            let current_branches_expression = RevsetExpression::remote_branches(
-                StringPattern::everything(),
-                StringPattern::Exact(remote.to_owned()),
-            )
-            .range(&RevsetExpression::commit(wc_commit_id))
-            .intersection(&RevsetExpression::branches(StringPattern::everything()));
-            let current_branches_revset = tx
-                .base_workspace_helper()
-                .evaluate_revset(current_branches_expression)?;
-            current_branches_revset.iter().collect()
-        } else {
-            // TODO: Narrow search space to local target commits.
-            // TODO: Remove redundant CommitId -> Commit -> CommitId round trip.
-            resolve_multiple_nonempty_revsets(&args.revisions, tx.base_workspace_helper(), ui)?
-                .iter()
-                .map(|commit| commit.id().clone())
-                .collect()
-        };
-        let branches_targeted = repo
-            .view()
-            .local_remote_branches(&remote)
-            .filter(|(_, targets)| {
-                let mut local_ids = targets.local_target.added_ids();
-                local_ids.any(|id| revision_commit_ids.contains(id))
-            })
+    let remote_term = match &remotes[..] {
+        [remote] => format!("git remote {remote}"),
+        remotes => format!("git remotes {}", remotes.iter().join(", ")),
+    };
+    let tx_description = if args.all {
+        format!("push all branches to {remote_term}")
+    } else if args.deleted {
+        format!("push all deleted branches to {remote_term}")
+    } else {
+        let branch_names = remote_branch_updates
+            .iter()
+            .flat_map(|(_, branch_updates)| branch_updates)
+            .map(|(branch, _)| branch.as_str())
+            .unique()
             .collect_vec();
-        for &(branch_name, targets) in &branches_targeted {
-            if !seen_branches.insert(branch_name.to_owned()) {
-                continue;
-            }
-            match classify_branch_update(branch_name, &remote, targets) {
-                Ok(Some(update)) => branch_updates.push((branch_name.to_owned(), update)),
-                Ok(None) => {}
-                Err(reason) => reason.print(ui)?,
-            }
-        }
-        if (!args.revisions.is_empty() || use_default_revset) && branches_targeted.is_empty() {
-            writeln!(
-                ui.warning(),
-                "No branches point to the specified revisions."
-            )?;
+        format!("push {} to {remote_term}", make_branch_term(&branch_names))
+    };
+
+    let remote_count = remote_branch_updates.len();
+    let mut push_guards = PushGuards::from_settings(command.settings())?;
+    push_guards.conflicted &= !args.allow_conflicts;
+    push_guards.empty &= !args.allow_empty;
+    push_guards.undescribed &= !args.allow_empty_description;
+    push_guards.has_allow_flags = true;
+    let failed_remotes = push_branch_updates(
+        ui,
+        &mut tx,
+        &git_repo,
+        remote_branch_updates,
+        &push_guards,
+        args.dry_run,
+    )?;
+    if args.dry_run {
+        return Ok(());
+    }
+    if failed_remotes.len() < remote_count {
+        tx.finish(ui, tx_description)?;
+    }
+    if !failed_remotes.is_empty() {
+        return Err(user_error(format!(
+            "Failed to push to {}",
+            failed_remotes.iter().join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Expands the names of the groups in `git.remote-groups` to the remotes in
+/// them. Names of existing remotes are kept as is.
+fn expand_remote_groups(
+    settings: &UserSettings,
+    git_repo: &git2::Repository,
+    names: &[String],
+) -> Result<Vec<String>, CommandError> {
+    let groups: HashMap<String, Vec<String>> = settings
+        .config()
+        .get("git.remote-groups")
+        .optional()?
+        .unwrap_or_default();
+    let mut remotes = vec![];
+    for name in names {
+        match groups.get(name) {
+            Some(group) if git_repo.find_remote(name).is_err() => remotes.extend(group.clone()),
+            _ => remotes.push(name.clone()),
         }
+    }
+    Ok(remotes.into_iter().unique().collect())
+}
 
-        tx_description = format!(
-            "push {} to git remote {}",
-            make_branch_term(
-                &branch_updates
-                    .iter()
-                    .map(|(branch, _)| branch.as_str())
-                    .collect_vec()
-            ),
-            &remote
-        );
+/// Pushes the local branches with the `branch_names` to the `remote`.
+pub(crate) fn push_branches(
+    ui: &mut Ui,
+    tx: &mut WorkspaceCommandTransaction,
+    git_repo: &git2::Repository,
+    remote: &str,
+    branch_names: &[String],
+) -> Result<(), CommandError> {
+    let push_policy = BranchPushPolicy::from_settings(tx.base_workspace_helper().settings())?;
+    let mut branch_updates = vec![];
+    for branch_name in branch_names {
+        let targets = TrackingRefPair {
+            local_target: tx.repo().view().get_local_branch(branch_name),
+            remote_ref: tx.repo().view().get_remote_branch(branch_name, remote),
+        };
+        match classify_branch_update(branch_name, remote, targets, &push_policy) {
+            Ok(Some(update)) => branch_updates.push((branch_name.clone(), update)),
+            Ok(None) => writeln!(
+                ui.stderr(),
+                "Branch {branch_name}@{remote} already matches {branch_name}",
+            )?,
+            Err(reason) => return Err(reason.into()),
+        }
     }
     if branch_updates.is_empty() {
-        writeln!(ui.stderr(), "Nothing changed.")?;
         return Ok(());
     }
+    let push_guards = PushGuards::from_settings(tx.base_workspace_helper().settings())?;
+    push_branch_updates(
+        ui,
+        tx,
+        git_repo,
+        vec![(remote.to_owned(), branch_updates)],
+        &push_guards,
+        false,
+    )?;
+    Ok(())
+}
 
-    let mut new_heads = vec![];
-    let mut force_pushed_branches = hashset! {};
-    for (branch_name, update) in &branch_updates {
-        if let Some(new_target) = &update.new_target {
-            new_heads.push(new_target.clone());
-            let force = match &update.old_target {
-                None => false,
-                Some(old_target) => !repo.index().is_ancestor(old_target, new_target),
-            };
-            if force {
-                force_pushed_branches.insert(branch_name.to_string());
-            }
+/// Settings in `git.remotes.<name>` for pushing to the remote.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct RemotePushConfig {
+    /// Overrides `git.push-branch-template` for the remote.
+    push_branch_template: Option<String>,
+}
+
+/// Returns the template from `git.remotes.<remote>.push-branch-template` or
+/// `git.push-branch-template`, if any.
+fn push_branch_template(
+    settings: &UserSettings,
+    remote: &str,
+) -> Result<Option<String>, CommandError> {
+    let mut remotes: HashMap<String, RemotePushConfig> = settings
+        .config()
+        .get("git.remotes")
+        .optional()?
+        .unwrap_or_default();
+    match remotes
+        .remove(remote)
+        .and_then(|config| config.push_branch_template)
+    {
+        Some(template) => Ok(Some(template)),
+        None => Ok(settings
+            .config()
+            .get_string("git.push-branch-template")
+            .optional()?),
+    }
+}
+
+/// Returns the name of the branch that `jj git push --change` creates for the
+/// `commit` when pushing to the `remote`.
+pub(crate) fn change_branch_name(
+    ui: &mut Ui,
+    settings: &UserSettings,
+    workspace_command: &WorkspaceCommandHelper,
+    remote: &str,
+    commit: &Commit,
+) -> Result<String, CommandError> {
+    if let Some(template_text) = push_branch_template(settings, remote)? {
+        let template = workspace_command.parse_commit_template(&template_text)?;
+        let mut output = Vec::new();
+        template.format(commit, &mut PlainTextFormatter::new(&mut output))?;
+        let output = String::from_utf8(output).expect("template output should be utf-8 bytes");
+        let branch_name = output.trim();
+        if branch_name.is_empty() || branch_name.contains(char::is_whitespace) {
+            return Err(user_error_with_hint(
+                format!(
+                    "Invalid branch name {branch_name:?} generated for commit {}",
+                    short_commit_hash(commit.id())
+                ),
+                "Check the template in `git.push-branch-template`.",
+            ));
         }
+        return Ok(branch_name.to_owned());
+    }
+    let prefix = settings.push_branch_prefix();
+    let branch_name = format!("{prefix}{}", commit.change_id().hex());
+    let view = workspace_command.repo().view();
+    if view.get_local_branch(&branch_name).is_present() {
+        return Ok(branch_name);
+    }
+    // A local branch with the full change ID doesn't exist already, so use the
+    // short ID if it's not ambiguous (which it shouldn't be most of the time).
+    let short_change_id = short_change_hash(commit.change_id());
+    if workspace_command
+        .resolve_single_rev(&short_change_id, ui)
+        .is_ok()
+    {
+        Ok(format!("{prefix}{short_change_id}"))
+    } else {
+        Ok(branch_name)
     }
+}
 
-    // Check if there are conflicts in any commits we're about to push that haven't
-    // already been pushed.
-    let mut old_heads = repo
-        .view()
-        .remote_branches(&remote)
-        .flat_map(|(_, old_head)| old_head.target.added_ids())
-        .cloned()
+/// Returns the ref updates that push the branch `targets`, with the targets
+/// the branches had on the remote.
+fn branch_ref_updates(targets: &GitBranchPushTargets) -> Vec<(GitRefUpdate, Option<CommitId>)> {
+    iter::zip(targets.ref_updates(), &targets.branch_updates)
+        .map(|(ref_update, (_, update))| (ref_update, update.old_target.clone()))
+        .collect()
+}
+
+/// Runs the pre-push hook for the ref `updates` to the `remote`. Each update
+/// is given with the target the ref had on the remote.
+///
+/// Like Git's pre-push hook, the hook gets a line per ref with the local ref
+/// and commit, and the remote ref and commit.
+fn run_pre_push_hook(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    remote: &str,
+    updates: &[(GitRefUpdate, Option<CommitId>)],
+) -> Result<(), CommandError> {
+    let format_target = |target: &Option<CommitId>| match target {
+        Some(id) => id.hex(),
+        None => "0".repeat(40),
+    };
+    let input = updates
+        .iter()
+        .map(|(update, old_target)| {
+            format!(
+                "{name} {} {name} {}\n",
+                format_target(&update.new_target),
+                format_target(old_target),
+                name = update.qualified_name,
+            )
+        })
+        .join("");
+    workspace_command.run_hook(
+        ui,
+        HookEvent::PrePush,
+        &[("JJ_REMOTE", remote.to_owned())],
+        input.as_bytes(),
+    )
+}
+
+/// Pushes the ref `updates` to the `remote` after running the pre-push hook.
+/// Each update is given with the target the ref had on the remote. Commands
+/// push through this so that the hook isn't skipped.
+pub(crate) fn push_updates_with_hook(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    git_repo: &git2::Repository,
+    remote: &str,
+    updates: &[(GitRefUpdate, Option<CommitId>)],
+) -> Result<GitPushStats, CommandError> {
+    run_pre_push_hook(ui, workspace_command, remote, updates)?;
+    let ref_updates = updates
+        .iter()
+        .map(|(update, _)| update.clone())
         .collect_vec();
-    if old_heads.is_empty() {
-        old_heads.push(repo.store().root_commit_id().clone());
+    with_remote_git_callbacks(ui, workspace_command.settings(), remote, |cb| {
+        git::push_updates(git_repo, remote, &ref_updates, cb)
+    })?
+    .map_err(map_git_push_error)
+}
+
+/// Pushes the branch updates to each remote, unless `dry_run` is set. Fails if
+/// any of the commits to push isn't ready to be shared.
+///
+/// Pushes to several remotes run in parallel. A failure to push to one of them
+/// is reported without affecting the others, and the names of the remotes that
+/// failed are returned.
+fn push_branch_updates(
+    ui: &mut Ui,
+    tx: &mut WorkspaceCommandTransaction,
+    git_repo: &git2::Repository,
+    mut remote_branch_updates: Vec<(String, Vec<(String, BranchPushUpdate)>)>,
+    push_guards: &PushGuards,
+    dry_run: bool,
+) -> Result<Vec<String>, CommandError> {
+    let repo = tx.base_repo().clone();
+    let mut commit_ranges = vec![];
+    let mut remote_force_pushed_branches = vec![];
+    for (remote, branch_updates) in &remote_branch_updates {
+        let mut new_heads = vec![];
+        let mut force_pushed_branches = hashset! {};
+        for (branch_name, update) in branch_updates {
+            if let Some(new_target) = &update.new_target {
+                new_heads.push(new_target.clone());
+                let force = match &update.old_target {
+                    None => false,
+                    Some(old_target) => !repo.index().is_ancestor(old_target, new_target),
+                };
+                if force {
+                    force_pushed_branches.insert(branch_name.to_string());
+                }
+            }
+        }
+        remote_force_pushed_branches.push(force_pushed_branches);
+
+        // Check if there are conflicts in any commits we're about to push that
+        // haven't already been pushed.
+        let mut old_heads = repo
+            .view()
+            .remote_branches(remote)
+            .flat_map(|(_, old_head)| old_head.target.added_ids())
+            .cloned()
+            .collect_vec();
+        if old_heads.is_empty() {
+            old_heads.push(repo.store().root_commit_id().clone());
+        }
+        commit_ranges.push(
+            RevsetExpression::commits(old_heads).range(&RevsetExpression::commits(new_heads)),
+        );
     }
-    for commit in revset::walk_revs(repo.as_ref(), &new_heads, &old_heads)?
+    let commits_to_push: Vec<_> = RevsetExpression::union_all(&commit_ranges)
+        .evaluate_programmatic(repo.as_ref())?
         .iter()
         .commits(repo.store())
-    {
-        let commit = commit?;
-        let mut reasons = vec![];
-        if commit.description().is_empty() {
-            reasons.push("it has no description");
-        }
-        if commit.author().name.is_empty()
-            || commit.author().name == UserSettings::USER_NAME_PLACEHOLDER
-            || commit.author().email.is_empty()
-            || commit.author().email == UserSettings::USER_EMAIL_PLACEHOLDER
-            || commit.committer().name.is_empty()
-            || commit.committer().name == UserSettings::USER_NAME_PLACEHOLDER
-            || commit.committer().email.is_empty()
-            || commit.committer().email == UserSettings::USER_EMAIL_PLACEHOLDER
-        {
-            reasons.push("it has no author and/or committer set");
-        }
-        if commit.has_conflict()? {
-            reasons.push("it has conflicts");
+        .try_collect()?;
+    check_commits_to_push(
+        ui,
+        tx.base_workspace_helper(),
+        &commits_to_push,
+        push_guards,
+    )?;
+
+    let settings = tx.base_workspace_helper().settings().clone();
+    let mut sign_settings = settings.sign_settings();
+    if sign_settings.sign_on_push && repo.store().signer().can_sign() && !dry_run {
+        sign_settings.behavior = SignBehavior::Own;
+        let commits_to_sign = commits_to_push.into_iter().rev().collect_vec();
+        let rewritten = sign_commits(&settings, tx.mut_repo(), &commits_to_sign, &sign_settings)?;
+        if !rewritten.is_empty() {
+            writeln!(
+                ui.stderr(),
+                "Rewrote {} commits to sign them before pushing",
+                rewritten.len()
+            )?;
+            tx.mut_repo().rebase_descendants(&settings)?;
+            for (_, update) in remote_branch_updates
+                .iter_mut()
+                .flat_map(|(_, branch_updates)| branch_updates)
+            {
+                if let Some(new_target) = &mut update.new_target {
+                    if let Some(new_id) = rewritten.get(new_target) {
+                        *new_target = new_id.clone();
+                    }
+                }
+            }
         }
-        if !reasons.is_empty() {
-            return Err(user_error(format!(
-                "Won't push commit {} since {}",
-                short_commit_hash(commit.id()),
-                reasons.join(" and ")
-            )));
+    }
+
+    let remote_targets = iter::zip(remote_branch_updates, remote_force_pushed_branches)
+        .map(|((remote, branch_updates), force_pushed_branches)| {
+            let targets = GitBranchPushTargets {
+                branch_updates,
+                force_pushed_branches,
+            };
+            (remote, targets)
+        })
+        .collect_vec();
+    for (remote, targets) in &remote_targets {
+        print_branch_push_targets(ui, remote, targets)?;
+    }
+
+    if dry_run {
+        writeln!(ui.stderr(), "Dry-run requested, not pushing.")?;
+        return Ok(vec![]);
+    }
+
+    if let [(remote, targets)] = &remote_targets[..] {
+        let stats = push_updates_with_hook(
+            ui,
+            tx.base_workspace_helper(),
+            git_repo,
+            remote,
+            &branch_ref_updates(targets),
+        )?;
+        git::record_pushed_branches(tx.mut_repo(), remote, targets);
+        print_pushed_branches(ui, remote, targets, &stats)?;
+        return Ok(vec![]);
+    }
+
+    // The hooks run before any of the parallel pushes start, since they may
+    // need the terminal.
+    for (remote, targets) in &remote_targets {
+        run_pre_push_hook(
+            ui,
+            tx.base_workspace_helper(),
+            remote,
+            &branch_ref_updates(targets),
+        )?;
+    }
+    let git_dir = git_repo.path();
+    let shared_ui = Mutex::new(&mut *ui);
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles = remote_targets
+            .iter()
+            .map(|(remote, targets)| {
+                let shared_ui = &shared_ui;
+                let settings = &settings;
+                scope.spawn(move || {
+                    let git_repo = git2::Repository::open(git_dir)?;
+                    with_shared_remote_git_callbacks(shared_ui, settings, remote, |cb| {
+                        git::push_updates(&git_repo, remote, &targets.ref_updates(), cb)
+                    })
+                })
+            })
+            .collect_vec();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .try_collect()
+    })?;
+
+    let mut failed_remotes = vec![];
+    for ((remote, targets), result) in iter::zip(&remote_targets, results) {
+        match result {
+            Ok(stats) => {
+                git::record_pushed_branches(tx.mut_repo(), remote, targets);
+                print_pushed_branches(ui, remote, targets, &stats)?;
+            }
+            Err(err) => {
+                let message = match &err {
+                    GitPushError::InternalGitError(err) => err.message().to_owned(),
+                    err => err.to_string(),
+                };
+                writeln!(ui.warning(), "Failed to push to {remote}: {message}")?;
+                failed_remotes.push(remote.clone());
+            }
         }
     }
+    Ok(failed_remotes)
+}
 
-    writeln!(ui.stderr(), "Branch changes to push to {}:", &remote)?;
-    for (branch_name, update) in &branch_updates {
+fn print_branch_push_targets(
+    ui: &Ui,
+    remote: &str,
+    targets: &GitBranchPushTargets,
+) -> Result<(), CommandError> {
+    writeln!(ui.stderr(), "Branch changes to push to {}:", remote)?;
+    for (branch_name, update) in &targets.branch_updates {
         match (&update.old_target, &update.new_target) {
             (Some(old_target), Some(new_target)) => {
-                if force_pushed_branches.contains(branch_name) {
+                if targets.force_pushed_branches.contains(branch_name) {
                     writeln!(
                         ui.stderr(),
                         "  Force branch {branch_name} from {} to {}",
@@ -900,20 +1458,212 @@ fn cmd_git_push(
             }
         }
     }
+    Ok(())
+}
 
-    if args.dry_run {
-        writeln!(ui.stderr(), "Dry-run requested, not pushing.")?;
+/// Fails if any of the `commits` to push isn't ready to be shared: if it has
+/// no author or committer, is refused by the `guards`, or matches
+/// `git.push-block-revset`. Commands check the commits with this before
+/// pushing them.
+pub(crate) fn check_commits_to_push(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    commits: &[Commit],
+    guards: &PushGuards,
+) -> Result<(), CommandError> {
+    for commit in commits {
+        check_commit_can_be_pushed(commit)?;
+    }
+    check_push_guards(ui, workspace_command, commits, guards)?;
+    check_push_block_revset(ui, workspace_command, commits)
+}
+
+/// Fails if the `commit` isn't ready to be shared because it has no author
+/// or committer. The checks that can be turned off are in `PushGuards`.
+fn check_commit_can_be_pushed(commit: &Commit) -> Result<(), CommandError> {
+    if commit.author().name.is_empty()
+        || commit.author().name == UserSettings::USER_NAME_PLACEHOLDER
+        || commit.author().email.is_empty()
+        || commit.author().email == UserSettings::USER_EMAIL_PLACEHOLDER
+        || commit.committer().name.is_empty()
+        || commit.committer().name == UserSettings::USER_NAME_PLACEHOLDER
+        || commit.committer().email.is_empty()
+        || commit.committer().email == UserSettings::USER_EMAIL_PLACEHOLDER
+    {
+        return Err(user_error(format!(
+            "Won't push commit {} since it has no author and/or committer set",
+            short_commit_hash(commit.id()),
+        )));
+    }
+    Ok(())
+}
+
+/// The kinds of commits that are refused when pushing, from the
+/// `git.push-guard` config.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct PushGuards {
+    /// Refuse commits with conflicts.
+    conflicted: bool,
+    /// Refuse commits that don't change any files.
+    empty: bool,
+    /// Refuse commits without a description.
+    undescribed: bool,
+    /// Whether the calling command has `--allow-*` flags that skip the checks,
+    /// to be suggested in the error hint.
+    #[serde(skip)]
+    has_allow_flags: bool,
+}
+
+impl Default for PushGuards {
+    fn default() -> Self {
+        PushGuards {
+            conflicted: true,
+            empty: false,
+            undescribed: true,
+            has_allow_flags: false,
+        }
+    }
+}
+
+impl PushGuards {
+    pub(crate) fn from_settings(settings: &UserSettings) -> Result<Self, CommandError> {
+        Ok(settings
+            .config()
+            .get("git.push-guard")
+            .optional()?
+            .unwrap_or_default())
+    }
+}
+
+/// Fails if any of the `commits` is refused by the `guards`, after listing
+/// the ones that are.
+fn check_push_guards(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    commits: &[Commit],
+    guards: &PushGuards,
+) -> Result<(), CommandError> {
+    let repo = workspace_command.repo().as_ref();
+    let mut conflicted = vec![];
+    let mut empty = vec![];
+    let mut undescribed = vec![];
+    for commit in commits {
+        if guards.conflicted && commit.has_conflict()? {
+            conflicted.push(commit);
+        }
+        if guards.empty && is_empty_commit(repo, commit)? {
+            empty.push(commit);
+        }
+        if guards.undescribed && commit.description().is_empty() {
+            undescribed.push(commit);
+        }
+    }
+    let refused = [
+        (
+            "Won't push commits with conflicts:",
+            "conflicted",
+            "--allow-conflicts",
+            &conflicted,
+        ),
+        (
+            "Won't push empty commits:",
+            "empty",
+            "--allow-empty",
+            &empty,
+        ),
+        (
+            "Won't push commits without a description:",
+            "undescribed",
+            "--allow-empty-description",
+            &undescribed,
+        ),
+    ];
+    let mut config_keys = vec![];
+    let mut flags = vec![];
+    for (heading, config_key, flag, refused_commits) in refused {
+        if refused_commits.is_empty() {
+            continue;
+        }
+        writeln!(ui.stderr(), "{heading}")?;
+        for commit in refused_commits {
+            let mut formatter = ui.stderr_formatter();
+            write!(formatter, "  ")?;
+            workspace_command.write_commit_summary(formatter.as_mut(), commit)?;
+            writeln!(formatter)?;
+        }
+        config_keys.push(format!("`git.push-guard.{config_key}`"));
+        flags.push(flag);
+    }
+    if config_keys.is_empty() {
         return Ok(());
     }
+    let hint = if guards.has_allow_flags {
+        format!(
+            "Rewrite the commits, pass `{}`, or set {} to false.",
+            flags.join(" "),
+            config_keys.join(" and ")
+        )
+    } else {
+        format!(
+            "Rewrite the commits, or set {} to false.",
+            config_keys.join(" and ")
+        )
+    };
+    Err(user_error_with_hint(
+        "Won't push the commits listed above",
+        hint,
+    ))
+}
 
-    let targets = GitBranchPushTargets {
-        branch_updates,
-        force_pushed_branches,
+fn is_empty_commit(repo: &dyn Repo, commit: &Commit) -> Result<bool, CommandError> {
+    if let [parent] = &commit.parents()[..] {
+        return Ok(parent.tree_id() == commit.tree_id());
+    }
+    let parent_tree = merge_commit_trees(repo, &commit.parents())?;
+    Ok(*commit.tree_id() == parent_tree.id())
+}
+
+/// Fails if any of the `commits` matches the `git.push-block-revset` config,
+/// after listing the ones that do.
+fn check_push_block_revset(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    commits: &[Commit],
+) -> Result<(), CommandError> {
+    let Some(revset_str) = workspace_command
+        .settings()
+        .config()
+        .get_string("git.push-block-revset")
+        .optional()?
+    else {
+        return Ok(());
     };
-    with_remote_git_callbacks(ui, |cb| {
-        git::push_branches(tx.mut_repo(), &git_repo, &remote, &targets, cb)
-    })
-    .map_err(|err| match err {
+    let block_expression = workspace_command.parse_revset(&revset_str, None)?;
+    let commit_ids = commits.iter().map(|commit| commit.id().clone()).collect();
+    let blocked_commits: Vec<_> = workspace_command
+        .evaluate_revset(RevsetExpression::commits(commit_ids).intersection(&block_expression))?
+        .iter()
+        .commits(workspace_command.repo().store())
+        .try_collect()?;
+    if blocked_commits.is_empty() {
+        return Ok(());
+    }
+    writeln!(ui.stderr(), "These commits match `git.push-block-revset`:")?;
+    for commit in &blocked_commits {
+        let mut formatter = ui.stderr_formatter();
+        write!(formatter, "  ")?;
+        workspace_command.write_commit_summary(formatter.as_mut(), commit)?;
+        writeln!(formatter)?;
+    }
+    Err(user_error_with_hint(
+        "Won't push commits that match `git.push-block-revset`",
+        "Rewrite the commits so that they don't match, or change the config.",
+    ))
+}
+
+pub(crate) fn map_git_push_error(err: GitPushError) -> CommandError {
+    match err {
         GitPushError::InternalGitError(err) => map_git_error(err),
         GitPushError::NotFastForward => user_error_with_hint(
             "The push conflicts with changes made on the remote (it is not fast-forwardable).",
@@ -921,12 +1671,46 @@ fn cmd_git_push(
              and push again.",
         ),
         _ => user_error(err),
-    })?;
-    tx.finish(ui, tx_description)?;
+    }
+}
+
+/// Prints the branches that the remote reported as updated.
+fn print_pushed_branches(
+    ui: &Ui,
+    remote: &str,
+    targets: &GitBranchPushTargets,
+    stats: &GitPushStats,
+) -> Result<(), CommandError> {
+    let format_target = |target: &Option<CommitId>, absent: &str| match target {
+        Some(id) => short_commit_hash(id),
+        None => absent.to_owned(),
+    };
+    let pushed_branches: HashSet<&str> = stats
+        .pushed_refs
+        .iter()
+        .filter_map(|name| name.strip_prefix("refs/heads/"))
+        .collect();
+    writeln!(ui.stderr(), "Pushed to {remote}:")?;
+    for (branch_name, update) in &targets.branch_updates {
+        if !pushed_branches.contains(branch_name.as_str()) {
+            continue;
+        }
+        let forced = if targets.force_pushed_branches.contains(branch_name) {
+            " (forced)"
+        } else {
+            ""
+        };
+        writeln!(
+            ui.stderr(),
+            "  {branch_name}: {} -> {}{forced}",
+            format_target(&update.old_target, "(new)"),
+            format_target(&update.new_target, "(deleted)"),
+        )?;
+    }
     Ok(())
 }
 
-fn get_default_push_remote(
+pub(crate) fn get_default_push_remote(
     ui: &Ui,
     settings: &UserSettings,
     git_repo: &git2::Repository,
@@ -944,6 +1728,36 @@ fn get_default_push_remote(
     }
 }
 
+/// Returns the `remote`, or the remote `jj git push` pushes to by default.
+pub(crate) fn resolve_push_remote(
+    ui: &Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    remote: Option<&str>,
+) -> Result<String, CommandError> {
+    match remote {
+        Some(remote) => Ok(remote.to_owned()),
+        None => {
+            let git_repo = get_git_repo(workspace_command.repo().store())?;
+            get_default_push_remote(ui, command.settings(), &git_repo)
+        }
+    }
+}
+
+/// Returns the URL of the `remote`.
+pub(crate) fn get_remote_url(
+    workspace_command: &WorkspaceCommandHelper,
+    remote: &str,
+) -> Result<String, CommandError> {
+    let git_repo = get_git_repo(workspace_command.repo().store())?;
+    let url = git_repo
+        .find_remote(remote)
+        .ok()
+        .and_then(|remote| remote.url().map(str::to_owned))
+        .ok_or_else(|| user_error(format!("No git remote named '{remote}'")))?;
+    Ok(url)
+}
+
 #[derive(Clone, Debug)]
 struct RejectedBranchUpdateReason {
     message: String,
@@ -954,7 +1768,7 @@ impl RejectedBranchUpdateReason {
     fn print(&self, ui: &Ui) -> io::Result<()> {
         writeln!(ui.warning(), "{}", self.message)?;
         if let Some(hint) = &self.hint {
-            writeln!(ui.hint(), "Hint: {hint}")?;
+            writeln!(ui.hint(), "{}", tr!(ui, "hint", hint = hint))?;
         }
         Ok(())
     }
@@ -967,10 +1781,60 @@ impl From<RejectedBranchUpdateReason> for CommandError {
     }
 }
 
+/// Branch name patterns from `git.push-allow` and `git.push-deny` that limit
+/// which branches can be pushed.
+#[derive(Clone, Debug, Default)]
+struct BranchPushPolicy {
+    /// If not empty, only the branches matching one of these can be pushed.
+    allow: Vec<StringPattern>,
+    /// The branches matching one of these can never be pushed.
+    deny: Vec<StringPattern>,
+}
+
+impl BranchPushPolicy {
+    fn from_settings(settings: &UserSettings) -> Result<Self, CommandError> {
+        let parse_patterns = |key: &str| -> Result<Vec<StringPattern>, CommandError> {
+            let sources: Vec<String> = settings.config().get(key).optional()?.unwrap_or_default();
+            sources
+                .iter()
+                .map(|source| {
+                    parse_string_pattern(source).map_err(|err| {
+                        user_error_with_message(format!("Invalid pattern in {key}: {source}"), err)
+                    })
+                })
+                .try_collect()
+        };
+        Ok(BranchPushPolicy {
+            allow: parse_patterns("git.push-allow")?,
+            deny: parse_patterns("git.push-deny")?,
+        })
+    }
+
+    fn check(&self, branch_name: &str) -> Result<(), RejectedBranchUpdateReason> {
+        let matches = |patterns: &[StringPattern]| {
+            patterns.iter().any(|pattern| pattern.matches(branch_name))
+        };
+        if matches(&self.deny) {
+            Err(RejectedBranchUpdateReason {
+                message: format!("Branch {branch_name} is not allowed to be pushed"),
+                hint: Some("It matches a pattern in `git.push-deny`.".to_owned()),
+            })
+        } else if !self.allow.is_empty() && !matches(&self.allow) {
+            Err(RejectedBranchUpdateReason {
+                message: format!("Branch {branch_name} is not allowed to be pushed"),
+                hint: Some("It doesn't match any pattern in `git.push-allow`.".to_owned()),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
 fn classify_branch_update(
     branch_name: &str,
     remote_name: &str,
     targets: TrackingRefPair,
+    push_policy: &BranchPushPolicy,
 ) -> Result<Option<BranchPushUpdate>, RejectedBranchUpdateReason> {
     let push_action = classify_branch_push_action(targets);
     match push_action {
@@ -991,7 +1855,10 @@ fn classify_branch_update(
                 "Run `jj branch track {branch_name}@{remote_name}` to import the remote branch."
             )),
         }),
-        BranchPushAction::Update(update) => Ok(Some(update)),
+        BranchPushAction::Update(update) => {
+            push_policy.check(branch_name)?;
+            Ok(Some(update))
+        }
     }
 }
 
@@ -1020,7 +1887,9 @@ fn find_branches_to_push<'a>(
     }
     match &unmatched_patterns[..] {
         [] => Ok(matching_branches),
-        [pattern] if pattern.is_exact() => Err(user_error(format!("No such branch: {pattern}"))),
+        [pattern] if pattern.is_exact() => {
+            Err(user_error(format!("No such branch: {pattern}")).with_code(ErrorCode::NoSuchBranch))
+        }
         patterns => Err(user_error(format!(
             "No matching branches for patterns: {}",
             patterns.iter().join(", ")
@@ -1033,8 +1902,9 @@ fn cmd_git_import(
     command: &CommandHelper,
     _args: &GitImportArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     // In non-colocated repo, HEAD@git will never be moved internally by jj.
     // That's why cmd_git_export() doesn't export the HEAD ref.
     git::import_head(tx.mut_repo())?;
@@ -1049,8 +1919,9 @@ fn cmd_git_export(
     command: &CommandHelper,
     _args: &GitExportArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let failed_branches = git::export_refs(tx.mut_repo())?;
     tx.finish(ui, "export git refs")?;
     print_failed_git_export(ui, &failed_branches)?;
@@ -1091,6 +1962,233 @@ fn cmd_git_submodule_print_gitmodules(
     Ok(())
 }
 
+fn cmd_git_sync(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &GitSyncArgs,
+) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let git_repo = get_git_repo(workspace_command.repo().store())?;
+    let remote = &args.mirror;
+    let map_fetch_error = |err| match err {
+        GitFetchError::GitImportError(err) => err.into(),
+        GitFetchError::InternalGitError(err) => map_git_error(err),
+        _ => user_error(err),
+    };
+
+    if args.from_remote {
+        let mut tx = workspace_command.start_transaction()?;
+        let old_refs = local_git_refs(tx.repo().view());
+        let stats = with_remote_git_callbacks(ui, command.settings(), remote, |cb| {
+            git::mirror_from_remote(
+                tx.mut_repo(),
+                &git_repo,
+                remote,
+                cb,
+                &command.settings().git_settings(),
+            )
+        })?
+        .map_err(map_fetch_error)?;
+        print_git_import_stats(ui, &stats)?;
+        let new_refs = local_git_refs(tx.repo().view());
+        let changes = diff_git_refs(&old_refs, &new_refs);
+        if !changes.is_empty() {
+            writeln!(ui.stderr(), "Local changes to mirror {remote}:")?;
+            print_git_ref_changes(ui, &changes)?;
+        }
+        tx.finish(ui, format!("mirror git remote {remote}"))?;
+        return Ok(());
+    }
+
+    let view = workspace_command.repo().view();
+    let local_refs = local_git_refs(view);
+    if let Some((name, _)) = view
+        .local_branches()
+        .find(|(_, target)| target.has_conflict())
+    {
+        return Err(user_error_with_hint(
+            format!("Branch {name} is conflicted"),
+            "Run `jj branch list` to inspect, and use `jj branch set` to fix it up.",
+        ));
+    }
+    if let Some((name, _)) = view.tags().iter().find(|(_, target)| target.has_conflict()) {
+        return Err(user_error(format!("Tag {name} is conflicted")));
+    }
+    let remote_refs: BTreeMap<String, RefTarget> =
+        with_remote_git_callbacks(ui, command.settings(), remote, |cb| {
+            git::list_remote_refs(&git_repo, remote, cb)
+        })?
+        .map_err(map_fetch_error)?
+        .into_iter()
+        .map(|(name, id)| (name, RefTarget::normal(id)))
+        .collect();
+    let changes = diff_git_refs(&remote_refs, &local_refs);
+    if changes.is_empty() {
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
+        return Ok(());
+    }
+    writeln!(ui.stderr(), "Ref changes to mirror to {remote}:")?;
+    print_git_ref_changes(ui, &changes)?;
+    // Like `jj git push`, refuse to push the commits that aren't on the
+    // remote yet if they aren't ready to be shared.
+    let repo = workspace_command.repo().clone();
+    let mut old_heads = remote_refs
+        .values()
+        .filter_map(RefTarget::as_normal)
+        .filter(|id| repo.index().has_id(id))
+        .cloned()
+        .collect_vec();
+    if old_heads.is_empty() {
+        old_heads.push(repo.store().root_commit_id().clone());
+    }
+    let new_heads = changes
+        .iter()
+        .filter_map(|(_, _, new_target)| new_target.as_normal())
+        .cloned()
+        .collect_vec();
+    let commits_to_push: Vec<_> = RevsetExpression::commits(old_heads)
+        .range(&RevsetExpression::commits(new_heads))
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .commits(repo.store())
+        .try_collect()?;
+    let push_guards = PushGuards::from_settings(command.settings())?;
+    check_commits_to_push(ui, &workspace_command, &commits_to_push, &push_guards)?;
+    if args.dry_run {
+        writeln!(ui.stderr(), "Dry-run requested, not pushing.")?;
+        return Ok(());
+    }
+
+    let mut branch_updates = vec![];
+    let mut tag_updates = vec![];
+    for (ref_name, old_target, new_target) in changes {
+        if let Some(branch_name) = ref_name.strip_prefix("refs/heads/") {
+            let update = BranchPushUpdate {
+                old_target: old_target.as_normal().cloned(),
+                new_target: new_target.as_normal().cloned(),
+            };
+            branch_updates.push((branch_name.to_owned(), update));
+        } else {
+            let update = GitRefUpdate {
+                qualified_name: ref_name.to_owned(),
+                force: true,
+                new_target: new_target.as_normal().cloned(),
+            };
+            tag_updates.push((update, old_target.as_normal().cloned()));
+        }
+    }
+    let targets = GitBranchPushTargets {
+        force_pushed_branches: branch_updates
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect(),
+        branch_updates,
+    };
+    let mut tx = workspace_command.start_transaction()?;
+    if !targets.branch_updates.is_empty() {
+        push_updates_with_hook(
+            ui,
+            tx.base_workspace_helper(),
+            &git_repo,
+            remote,
+            &branch_ref_updates(&targets),
+        )?;
+        git::record_pushed_branches(tx.mut_repo(), remote, &targets);
+    }
+    if !tag_updates.is_empty() {
+        push_updates_with_hook(
+            ui,
+            tx.base_workspace_helper(),
+            &git_repo,
+            remote,
+            &tag_updates,
+        )?;
+    }
+    tx.finish(ui, format!("mirror to git remote {remote}"))?;
+    Ok(())
+}
+
+/// Returns the local branches and tags by their Git ref names.
+fn local_git_refs(view: &View) -> BTreeMap<String, RefTarget> {
+    let branches = view
+        .local_branches()
+        .map(|(name, target)| (format!("refs/heads/{name}"), target.clone()));
+    let tags = view
+        .tags()
+        .iter()
+        .map(|(name, target)| (format!("refs/tags/{name}"), target.clone()));
+    branches.chain(tags).collect()
+}
+
+/// Returns the refs whose targets differ between `old_refs` and `new_refs`,
+/// with their old and new targets.
+fn diff_git_refs<'a>(
+    old_refs: &'a BTreeMap<String, RefTarget>,
+    new_refs: &'a BTreeMap<String, RefTarget>,
+) -> Vec<(&'a str, &'a RefTarget, &'a RefTarget)> {
+    old_refs
+        .iter()
+        .merge_join_by(new_refs, |(old_name, _), (new_name, _)| {
+            old_name.cmp(new_name)
+        })
+        .filter_map(|entry| {
+            let (name, old_target, new_target) = match entry {
+                EitherOrBoth::Both((name, old_target), (_, new_target)) => {
+                    (name, old_target, new_target)
+                }
+                EitherOrBoth::Left((name, old_target)) => {
+                    (name, old_target, RefTarget::absent_ref())
+                }
+                EitherOrBoth::Right((name, new_target)) => {
+                    (name, RefTarget::absent_ref(), new_target)
+                }
+            };
+            (old_target != new_target).then_some((name.as_str(), old_target, new_target))
+        })
+        .collect()
+}
+
+fn print_git_ref_changes(
+    ui: &Ui,
+    changes: &[(&str, &RefTarget, &RefTarget)],
+) -> Result<(), CommandError> {
+    let format_target = |target: &RefTarget| match target.as_normal() {
+        Some(id) => short_commit_hash(id),
+        None => "(conflicted)".to_owned(),
+    };
+    for &(ref_name, old_target, new_target) in changes {
+        let (kind, name) = match ref_name.strip_prefix("refs/heads/") {
+            Some(name) => ("branch", name),
+            None => (
+                "tag",
+                ref_name.strip_prefix("refs/tags/").unwrap_or(ref_name),
+            ),
+        };
+        if old_target.is_absent() {
+            writeln!(
+                ui.stderr(),
+                "  Add {kind} {name} to {}",
+                format_target(new_target)
+            )?;
+        } else if new_target.is_absent() {
+            writeln!(
+                ui.stderr(),
+                "  Delete {kind} {name} from {}",
+                format_target(old_target)
+            )?;
+        } else {
+            writeln!(
+                ui.stderr(),
+                "  Move {kind} {name} from {} to {}",
+                format_target(old_target),
+                format_target(new_target)
+            )?;
+        }
+    }
+    Ok(())
+}
+
 pub fn cmd_git(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -1108,6 +2206,7 @@ pub fn cmd_git(
         }
         GitCommand::Remote(GitRemoteCommand::List(args)) => cmd_git_remote_list(ui, command, args),
         GitCommand::Push(args) => cmd_git_push(ui, command, args),
+        GitCommand::Sync(args) => cmd_git_sync(ui, command, args),
         GitCommand::Import(args) => cmd_git_import(ui, command, args),
         GitCommand::Export(args) => cmd_git_export(ui, command, args),
         GitCommand::Submodule(GitSubmoduleCommand::PrintGitmodules(args)) => {
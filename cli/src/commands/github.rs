@@ -0,0 +1,528 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use clap::Subcommand;
+use itertools::Itertools;
+use jj_lib::backend::CommitId;
+use jj_lib::commit::Commit;
+use jj_lib::op_store::RefTarget;
+use jj_lib::repo::Repo;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _};
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, short_commit_hash, user_error, user_error_with_hint,
+    user_error_with_message, CommandError, CommandHelper, RevisionArg, WorkspaceCommandHelper,
+};
+use crate::commands::git::{
+    change_branch_name, get_remote_url, push_branches, resolve_push_remote,
+};
+use crate::git_util::{get_git_repo, split_remote_url};
+use crate::ui::Ui;
+
+/// Commands for working with GitHub
+///
+/// The commands use the GitHub CLI, `gh`, which must be installed and logged
+/// in. The GitHub repository is found from the URL of the remote.
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum GithubCommand {
+    #[command(subcommand)]
+    Pr(GithubPrCommand),
+}
+
+/// Manage pull requests
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum GithubPrCommand {
+    Create(GithubPrCreateArgs),
+    Update(GithubPrUpdateArgs),
+}
+
+/// Push revisions and create a pull request for each of them
+///
+/// Each revision is pushed to a branch pointing to it. If there's no such
+/// branch, one is created like with `jj git push --change`. The title and body
+/// of the pull request are taken from the revision's description.
+///
+/// The revisions can form a stack. The pull request of a revision whose parent
+/// is also given is based on the parent's branch, so it only shows the changes
+/// in that revision.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct GithubPrCreateArgs {
+    /// The revisions to create pull requests for
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The branch to merge the bottom of the stack into (default: the
+    /// repository's default branch)
+    #[arg(long)]
+    base: Option<String>,
+    /// The remote to push to (only GitHub remotes are supported)
+    #[arg(long)]
+    remote: Option<String>,
+    /// Create the pull requests as drafts
+    #[arg(long)]
+    draft: bool,
+}
+
+/// Push revisions and update their pull requests
+///
+/// The title and body of each pull request are updated from the revision's
+/// description, and its base from the stack of revisions, as with `jj github
+/// pr create`.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct GithubPrUpdateArgs {
+    /// The revisions to update the pull requests of
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The branch to merge the bottom of the stack into (default: keep the
+    /// current base)
+    #[arg(long)]
+    base: Option<String>,
+    /// The remote to push to (only GitHub remotes are supported)
+    #[arg(long)]
+    remote: Option<String>,
+}
+
+pub(crate) fn cmd_github(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &GithubCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        GithubCommand::Pr(GithubPrCommand::Create(sub_args)) => {
+            cmd_github_pr_create(ui, command, sub_args)
+        }
+        GithubCommand::Pr(GithubPrCommand::Update(sub_args)) => {
+            cmd_github_pr_update(ui, command, sub_args)
+        }
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_github_pr_create(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &GithubPrCreateArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let remote = resolve_push_remote(ui, command, &workspace_command, args.remote.as_deref())?;
+    let github = GithubRepo::new(command, &workspace_command, &remote)?;
+    let stack =
+        PullRequestStack::new(ui, command, &mut workspace_command, &args.revisions, remote)?;
+    for (commit, branch_name) in stack.commits.iter().zip(&stack.branch_names) {
+        if let Some(pull_request) = github.find_pull_request(branch_name)? {
+            return Err(user_error_with_hint(
+                format!(
+                    "Commit {} already has pull request {}",
+                    short_commit_hash(commit.id()),
+                    pull_request.url
+                ),
+                "Use `jj github pr update` to update it.",
+            ));
+        }
+    }
+    stack.push(ui, &mut workspace_command, "create pull requests")?;
+    for i in 0..stack.commits.len() {
+        let base = stack.base_branch(i).or(args.base.as_deref());
+        let url = github.create_pull_request(&stack, i, base, args.draft)?;
+        writeln!(ui.stderr(), "Created pull request {url}")?;
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+fn cmd_github_pr_update(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &GithubPrUpdateArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let remote = resolve_push_remote(ui, command, &workspace_command, args.remote.as_deref())?;
+    let github = GithubRepo::new(command, &workspace_command, &remote)?;
+    let stack =
+        PullRequestStack::new(ui, command, &mut workspace_command, &args.revisions, remote)?;
+    let mut pull_requests = vec![];
+    for (commit, branch_name) in stack.commits.iter().zip(&stack.branch_names) {
+        let pull_request = github.find_pull_request(branch_name)?.ok_or_else(|| {
+            user_error_with_hint(
+                format!(
+                    "Commit {} doesn't have a pull request",
+                    short_commit_hash(commit.id())
+                ),
+                "Use `jj github pr create` to create one.",
+            )
+        })?;
+        pull_requests.push(pull_request);
+    }
+    stack.push(ui, &mut workspace_command, "update pull requests")?;
+    for (i, pull_request) in pull_requests.iter().enumerate() {
+        let base = stack.base_branch(i).or(args.base.as_deref());
+        github.update_pull_request(&stack, i, pull_request, base)?;
+        writeln!(ui.stderr(), "Updated pull request {}", pull_request.url)?;
+    }
+    Ok(())
+}
+
+/// Pushes the `revisions` to the GitHub `remote`, and creates or updates a pull
+/// request for each of them.
+pub(crate) fn submit_pull_requests(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    mut workspace_command: WorkspaceCommandHelper,
+    revisions: &[RevisionArg],
+    remote: &str,
+    base: Option<&str>,
+    draft: bool,
+) -> Result<(), CommandError> {
+    let github = GithubRepo::new(command, &workspace_command, remote)?;
+    let stack = PullRequestStack::new(
+        ui,
+        command,
+        &mut workspace_command,
+        revisions,
+        remote.to_owned(),
+    )?;
+    let pull_requests: Vec<_> = stack
+        .branch_names
+        .iter()
+        .map(|branch_name| github.find_pull_request(branch_name))
+        .try_collect()?;
+    stack.push(ui, &mut workspace_command, "submit pull requests")?;
+    for (i, pull_request) in pull_requests.iter().enumerate() {
+        let base = stack.base_branch(i).or(base);
+        if let Some(pull_request) = pull_request {
+            github.update_pull_request(&stack, i, pull_request, base)?;
+            writeln!(ui.stderr(), "Updated pull request {}", pull_request.url)?;
+        } else {
+            let url = github.create_pull_request(&stack, i, base, draft)?;
+            writeln!(ui.stderr(), "Created pull request {url}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Revisions to create or update pull requests for, from the bottom of the
+/// stack to the top, with the branches they're pushed to.
+pub(crate) struct PullRequestStack {
+    pub remote: String,
+    pub commits: Vec<Commit>,
+    pub branch_names: Vec<String>,
+    /// Index of the parent of each commit, if it's in the stack.
+    parent_indices: Vec<Option<usize>>,
+}
+
+impl PullRequestStack {
+    pub(crate) fn new(
+        ui: &mut Ui,
+        command: &CommandHelper,
+        workspace_command: &mut WorkspaceCommandHelper,
+        revisions: &[RevisionArg],
+        remote: String,
+    ) -> Result<Self, CommandError> {
+        // Sort the commits topologically, parents first
+        let revset_commits = resolve_multiple_nonempty_revsets(revisions, workspace_command, ui)?;
+        let repo = workspace_command.repo();
+        let commits: Vec<Commit> = repo
+            .index()
+            .topo_order(&mut revset_commits.iter().map(|commit| commit.id()))
+            .iter()
+            .map(|id| repo.store().get_commit(id))
+            .try_collect()?;
+        let positions: HashMap<&CommitId, usize> = commits
+            .iter()
+            .enumerate()
+            .map(|(i, commit)| (commit.id(), i))
+            .collect();
+        let mut parent_indices = vec![];
+        for commit in &commits {
+            let parents = commit
+                .parent_ids()
+                .iter()
+                .filter_map(|id| positions.get(id).copied())
+                .collect_vec();
+            if parents.len() > 1 {
+                return Err(user_error(format!(
+                    "Can't create a pull request for merge commit {}",
+                    short_commit_hash(commit.id())
+                )));
+            }
+            parent_indices.push(parents.first().copied());
+        }
+
+        let branch_names = commits
+            .iter()
+            .map(|commit| {
+                let view = workspace_command.repo().view();
+                let existing = view
+                    .local_branches()
+                    .filter(|(_, target)| target.added_ids().contains(commit.id()))
+                    .map(|(name, _)| name.to_owned())
+                    .next();
+                match existing {
+                    Some(name) => Ok(name),
+                    None => change_branch_name(
+                        ui,
+                        command.settings(),
+                        workspace_command,
+                        &remote,
+                        commit,
+                    ),
+                }
+            })
+            .try_collect()?;
+        Ok(PullRequestStack {
+            remote,
+            commits,
+            branch_names,
+            parent_indices,
+        })
+    }
+
+    /// The branch the pull request of the `i`th commit is based on, if it's
+    /// determined by the stack.
+    pub(crate) fn base_branch(&self, i: usize) -> Option<&str> {
+        self.parent_indices[i].map(|parent| self.branch_names[parent].as_str())
+    }
+
+    /// Creates the missing branches and pushes them.
+    pub(crate) fn push(
+        &self,
+        ui: &mut Ui,
+        workspace_command: &mut WorkspaceCommandHelper,
+        description: &str,
+    ) -> Result<(), CommandError> {
+        let git_repo = get_git_repo(workspace_command.repo().store())?;
+        let mut tx = workspace_command.start_transaction()?;
+        for (commit, branch_name) in self.commits.iter().zip(&self.branch_names) {
+            if tx.repo().view().get_local_branch(branch_name).is_absent() {
+                writeln!(
+                    ui.stderr(),
+                    "Creating branch {} for revision {}",
+                    branch_name,
+                    short_commit_hash(commit.id())
+                )?;
+                tx.mut_repo()
+                    .set_local_branch_target(branch_name, RefTarget::normal(commit.id().clone()));
+            }
+        }
+        push_branches(ui, &mut tx, &git_repo, &self.remote, &self.branch_names)?;
+        tx.finish(ui, description)
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct GithubConfig {
+    program: Option<String>,
+    repository: Option<String>,
+}
+
+/// A pull request found on GitHub.
+#[derive(Debug, serde::Deserialize)]
+struct PullRequest {
+    number: u64,
+    url: String,
+}
+
+/// A GitHub repository, accessed with the GitHub CLI.
+struct GithubRepo {
+    program: String,
+    /// The repository in `[HOST/]OWNER/REPO` form.
+    name: String,
+}
+
+impl GithubRepo {
+    fn new(
+        command: &CommandHelper,
+        workspace_command: &WorkspaceCommandHelper,
+        remote: &str,
+    ) -> Result<Self, CommandError> {
+        let remote_url = get_remote_url(workspace_command, remote)?;
+        let config: GithubConfig = command
+            .settings()
+            .config()
+            .get("github")
+            .optional()?
+            .unwrap_or_default();
+        let name = match config.repository {
+            Some(name) => name,
+            None => github_repo_name(&remote_url).ok_or_else(|| {
+                user_error_with_hint(
+                    format!("Remote {remote} is not a GitHub repository: {remote_url}"),
+                    "If it is, set github.repository to its [HOST/]OWNER/REPO name.",
+                )
+            })?,
+        };
+        Ok(GithubRepo {
+            program: config
+                .program
+                .map_or_else(|| "gh".to_owned(), |program| expand_config_path(&program)),
+            name,
+        })
+    }
+
+    /// Runs the GitHub CLI with the `args`, and returns its output.
+    fn run(&self, args: &[&str]) -> Result<String, CommandError> {
+        let output = Command::new(&self.program)
+            .args(args)
+            .args(["--repo", &self.name])
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|err| {
+                user_error_with_message(format!("Failed to run {}", self.program), err)
+            })?;
+        if !output.status.success() {
+            return Err(user_error(format!(
+                "{} failed with {}",
+                self.program, output.status
+            )));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|err| user_error_with_message("Invalid output from GitHub CLI", err))
+    }
+
+    /// Finds the open pull request for the `branch_name`.
+    fn find_pull_request(&self, branch_name: &str) -> Result<Option<PullRequest>, CommandError> {
+        let output = self.run(&[
+            "pr",
+            "list",
+            "--head",
+            branch_name,
+            "--state",
+            "open",
+            "--json",
+            "number,url",
+        ])?;
+        let pull_requests: Vec<PullRequest> = serde_json::from_str(&output)
+            .map_err(|err| user_error_with_message("Invalid output from GitHub CLI", err))?;
+        Ok(pull_requests.into_iter().next())
+    }
+
+    /// Creates a pull request for the `i`th commit of the `stack`, and returns
+    /// its URL.
+    fn create_pull_request(
+        &self,
+        stack: &PullRequestStack,
+        i: usize,
+        base: Option<&str>,
+        draft: bool,
+    ) -> Result<String, CommandError> {
+        let (title, body) = split_description(stack.commits[i].description());
+        let mut args = vec![
+            "pr",
+            "create",
+            "--head",
+            &stack.branch_names[i],
+            "--title",
+            title,
+            "--body",
+            body,
+        ];
+        if let Some(base) = base {
+            args.extend(["--base", base]);
+        }
+        if draft {
+            args.push("--draft");
+        }
+        let url = self.run(&args)?;
+        Ok(url.trim_end().to_owned())
+    }
+
+    /// Updates the `pull_request` of the `i`th commit of the `stack`.
+    fn update_pull_request(
+        &self,
+        stack: &PullRequestStack,
+        i: usize,
+        pull_request: &PullRequest,
+        base: Option<&str>,
+    ) -> Result<(), CommandError> {
+        let (title, body) = split_description(stack.commits[i].description());
+        let number = pull_request.number.to_string();
+        let mut args = vec!["pr", "edit", &number, "--title", title, "--body", body];
+        if let Some(base) = base {
+            args.extend(["--base", base]);
+        }
+        self.run(&args)?;
+        Ok(())
+    }
+}
+
+/// Returns the `[HOST/]OWNER/REPO` name of the GitHub repository at the `url`.
+fn github_repo_name(url: &str) -> Option<String> {
+    let (host, path) = split_remote_url(url)?;
+    let (owner, repo) = path.split_once('/')?;
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    if host == "github.com" {
+        Some(format!("{owner}/{repo}"))
+    } else if host.contains("github") {
+        Some(format!("{host}/{owner}/{repo}"))
+    } else {
+        None
+    }
+}
+
+/// Splits a description into the title and body of a pull request.
+pub(crate) fn split_description(description: &str) -> (&str, &str) {
+    let description = description.trim();
+    match description.split_once('\n') {
+        Some((title, body)) => (title.trim_end(), body.trim()),
+        None => (description, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_repo_name() {
+        for url in [
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo.git",
+            "https://user@github.com/owner/repo/",
+            "ssh://git@github.com/owner/repo.git",
+            "ssh://git@github.com:22/owner/repo.git",
+            "git@github.com:owner/repo.git",
+        ] {
+            assert_eq!(
+                github_repo_name(url).as_deref(),
+                Some("owner/repo"),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            github_repo_name("https://github.example.com/owner/repo").as_deref(),
+            Some("github.example.com/owner/repo")
+        );
+        assert_eq!(github_repo_name("https://gitlab.com/owner/repo"), None);
+        assert_eq!(github_repo_name("/path/to/repo"), None);
+        assert_eq!(github_repo_name("https://github.com/owner"), None);
+    }
+
+    #[test]
+    fn test_split_description() {
+        assert_eq!(split_description(""), ("", ""));
+        assert_eq!(split_description("title\n"), ("title", ""));
+        assert_eq!(
+            split_description("title\n\nbody\n\nmore\n"),
+            ("title", "body\n\nmore")
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use itertools::Itertools as _;
+use jj_lib::backend::CommitId;
+use jj_lib::commit::Commit;
+use jj_lib::merged_tree::MergedTree;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::MutableRepo;
+use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::settings::UserSettings;
+use tracing::instrument;
+
+use crate::cli_util::{
+    edit_temp_file, short_commit_hash, user_error, user_error_with_hint, CommandError,
+    CommandHelper, RevisionArg,
+};
+use crate::description_util::{combine_descriptions, edit_description};
+use crate::ui::Ui;
+
+/// Reorder, squash, drop, or reword a stack of commits
+///
+/// Opens an editor with one line per commit, oldest first, each starting with
+/// `pick`. Reorder the lines to reorder the commits, and change `pick` to one
+/// of these to change what happens to the commit:
+///
+/// * `reword`: edit the description of the commit
+/// * `squash`: combine the commit with the one above it
+/// * `drop`: abandon the commit
+///
+/// The commits must form a linear chain of non-merge commits. All changes are
+/// made in a single operation, so `jj undo` reverts all of them. Commits on top
+/// of the last edited commit are moved onto the new last commit, and other
+/// descendants are rebased along with their parents.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct HisteditArgs {
+    /// The commits to edit
+    #[arg(long, short, required = true)]
+    revisions: Vec<RevisionArg>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Action {
+    Pick,
+    Reword,
+    Squash,
+    Drop,
+}
+
+/// The commit being built from a picked commit and the ones squashed into it.
+struct PendingCommit {
+    source: Commit,
+    parent: Commit,
+    tree: MergedTree,
+    description: String,
+    squashed: Vec<CommitId>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_histedit(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &HisteditArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let mut commits = vec![];
+    for revision_str in &args.revisions {
+        commits.extend(workspace_command.resolve_revset(revision_str, ui)?);
+    }
+    let commits = sort_linear_chain(commits)?;
+    workspace_command.check_rewritable(&commits)?;
+
+    let mut plan_text = String::new();
+    for commit in &commits {
+        let first_line = commit.description().lines().next();
+        plan_text.push_str(&format!(
+            "pick {} {}\n",
+            short_commit_hash(commit.id()),
+            first_line.unwrap_or("(no description set)")
+        ));
+    }
+    plan_text.push_str(
+        "\
+JJ: Commits are listed oldest first. Reorder the lines to reorder the commits,
+JJ: and change `pick` to one of these to change what happens to the commit:
+JJ:   reword: edit the description of the commit
+JJ:   squash: combine the commit with the one above it
+JJ:   drop:   abandon the commit
+JJ: Remove all lines to leave the commits unchanged.
+JJ: Lines starting with \"JJ: \" (like this one) will be removed.
+",
+    );
+    let plan_text = edit_temp_file(
+        "plan",
+        ".jjhistedit",
+        workspace_command.repo().repo_path(),
+        &plan_text,
+        command.settings(),
+    )?;
+    let plan = parse_plan(&plan_text, &commits)?;
+    if plan.is_empty() {
+        writeln!(ui.stderr(), "Nothing changed.")?;
+        return Ok(());
+    }
+
+    let base_repo = workspace_command.repo().clone();
+    let head = commits.last().unwrap();
+    let mut tx = workspace_command.start_transaction()?;
+    let mut_repo = tx.mut_repo();
+    let mut tip = commits[0].parents().pop().unwrap();
+    let mut pending: Option<PendingCommit> = None;
+    let mut dropped = HashSet::new();
+    for (action, commit) in plan {
+        let parent_tree = merge_commit_trees(mut_repo, &commit.parents())?;
+        match action {
+            Action::Pick | Action::Reword => {
+                if let Some(pending) = pending.take() {
+                    tip = write_pending(mut_repo, command.settings(), pending)?;
+                }
+                let tree = tip.tree()?.merge(&parent_tree, &commit.tree()?)?;
+                let description = if action == Action::Reword {
+                    edit_description(&base_repo, commit.description(), command.settings())?
+                } else {
+                    commit.description().to_owned()
+                };
+                pending = Some(PendingCommit {
+                    source: commit.clone(),
+                    parent: tip.clone(),
+                    tree,
+                    description,
+                    squashed: vec![],
+                });
+            }
+            Action::Squash => {
+                let Some(pending) = &mut pending else {
+                    return Err(user_error(format!(
+                        "Cannot squash commit {} because there's no commit above it",
+                        short_commit_hash(commit.id())
+                    )));
+                };
+                pending.tree = pending.tree.merge(&parent_tree, &commit.tree()?)?;
+                pending.description = combine_descriptions(
+                    &base_repo,
+                    &pending.description,
+                    commit.description(),
+                    command.settings(),
+                )?;
+                pending.squashed.push(commit.id().clone());
+            }
+            Action::Drop => {
+                mut_repo.record_abandoned_commit(commit.id().clone());
+                dropped.insert(commit.id());
+            }
+        }
+    }
+    if let Some(pending) = pending.take() {
+        tip = write_pending(mut_repo, command.settings(), pending)?;
+    }
+    if tip.id() != head.id() && !dropped.contains(head.id()) {
+        // Keep the commits on top of the stack, and its branches, on top of it
+        mut_repo.set_rewritten_commit(head.id().clone(), [tip.id().clone()]);
+    }
+    let num_rebased = mut_repo.rebase_descendants(command.settings())?;
+    if num_rebased > 0 {
+        writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
+    }
+    tx.finish(
+        ui,
+        format!(
+            "edit history of {} commits starting at {}",
+            commits.len(),
+            commits[0].id().hex()
+        ),
+    )?;
+    Ok(())
+}
+
+/// Checks that the commits form a linear chain and returns them oldest first.
+fn sort_linear_chain(commits: Vec<Commit>) -> Result<Vec<Commit>, CommandError> {
+    let commits = commits.into_iter().unique().collect_vec();
+    if commits.is_empty() {
+        return Err(user_error("No commits to edit"));
+    }
+    if let Some(merge) = commits.iter().find(|commit| commit.parent_ids().len() != 1) {
+        return Err(user_error(format!(
+            "Cannot edit merge commit {}",
+            short_commit_hash(merge.id())
+        )));
+    }
+    let ids: HashSet<_> = commits.iter().map(|commit| commit.id()).collect();
+    let roots = commits
+        .iter()
+        .filter(|commit| !ids.contains(&commit.parent_ids()[0]))
+        .collect_vec();
+    let mut children = commits
+        .iter()
+        .into_group_map_by(|commit| &commit.parent_ids()[0]);
+    let not_linear = || {
+        user_error_with_hint(
+            "The commits to edit must form a linear chain",
+            "Each commit except the first must be the only child of the one before it in the \
+             revset.",
+        )
+    };
+    let [root] = roots[..] else {
+        return Err(not_linear());
+    };
+    let mut sorted = vec![root.clone()];
+    while let Some(children) = children.remove(sorted.last().unwrap().id()) {
+        let [child] = children[..] else {
+            return Err(not_linear());
+        };
+        sorted.push(child.clone());
+    }
+    if sorted.len() != commits.len() {
+        return Err(not_linear());
+    }
+    Ok(sorted)
+}
+
+fn parse_plan<'a>(
+    text: &str,
+    commits: &'a [Commit],
+) -> Result<Vec<(Action, &'a Commit)>, CommandError> {
+    let mut plan = vec![];
+    let mut seen = HashSet::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("JJ: ") {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let action = match words.next().unwrap() {
+            "pick" | "p" => Action::Pick,
+            "reword" | "r" => Action::Reword,
+            "squash" | "s" => Action::Squash,
+            "drop" | "d" => Action::Drop,
+            word => return Err(user_error(format!("Unknown histedit action: {word}"))),
+        };
+        let prefix = words
+            .next()
+            .ok_or_else(|| user_error(format!("Missing commit in histedit line: {line}")))?;
+        let matches = commits
+            .iter()
+            .filter(|commit| commit.id().hex().starts_with(prefix))
+            .collect_vec();
+        let [commit] = matches[..] else {
+            return Err(user_error(format!(
+                "Commit {prefix} doesn't identify exactly one of the commits being edited"
+            )));
+        };
+        if !seen.insert(commit.id()) {
+            return Err(user_error(format!(
+                "Commit {} is listed more than once",
+                short_commit_hash(commit.id())
+            )));
+        }
+        plan.push((action, commit));
+    }
+    if !plan.is_empty() {
+        if let Some(missing) = commits.iter().find(|commit| !seen.contains(commit.id())) {
+            return Err(user_error_with_hint(
+                format!(
+                    "Commit {} is missing from the plan",
+                    short_commit_hash(missing.id())
+                ),
+                "Use `drop` to abandon a commit.",
+            ));
+        }
+    }
+    Ok(plan)
+}
+
+fn write_pending(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    pending: PendingCommit,
+) -> Result<Commit, CommandError> {
+    let PendingCommit {
+        source,
+        parent,
+        tree,
+        description,
+        squashed,
+    } = pending;
+    if source.parent_ids() == [parent.id().clone()]
+        && tree.id() == *source.tree_id()
+        && description == source.description()
+        && squashed.is_empty()
+    {
+        return Ok(source);
+    }
+    let mut predecessors = vec![source.id().clone()];
+    predecessors.extend(squashed.iter().cloned());
+    let new_commit = mut_repo
+        .rewrite_commit(settings, &source)
+        .set_parents(vec![parent.id().clone()])
+        .set_tree_id(tree.id())
+        .set_description(description)
+        .set_predecessors(predecessors)
+        .write()?;
+    for id in squashed {
+        mut_repo.record_rewritten_commit(id, new_commit.id().clone());
+    }
+    Ok(new_commit)
+}
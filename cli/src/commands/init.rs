@@ -63,7 +63,11 @@ pub(crate) fn cmd_init(
 Set `ui.allow-init-native` to allow initializing a repo with the native backend.",
             ));
         }
-        Workspace::init_local(command.settings(), &wc_path)?;
+        Workspace::init_local(
+            command.settings(),
+            &wc_path,
+            command.init_working_copy_factory()?,
+        )?;
     }
 
     let relative_wc_path = file_util::relative_path(&cwd, &wc_path);
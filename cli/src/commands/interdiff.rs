@@ -47,13 +47,13 @@ pub(crate) fn cmd_interdiff(
     command: &CommandHelper,
     args: &InterdiffArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
     let from = workspace_command.resolve_single_rev(args.from.as_deref().unwrap_or("@"), ui)?;
     let to = workspace_command.resolve_single_rev(args.to.as_deref().unwrap_or("@"), ui)?;
 
     let from_tree = rebase_to_dest_parent(workspace_command.repo().as_ref(), &from, &to)?;
     let to_tree = to.tree()?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&from_tree, &to_tree])?;
     let diff_formats = diff_util::diff_formats_for(command.settings(), &args.format)?;
     ui.request_pager();
     diff_util::show_diff(
@@ -64,5 +64,6 @@ pub(crate) fn cmd_interdiff(
         &to_tree,
         matcher.as_ref(),
         &diff_formats,
+        usize::from(ui.term_width().unwrap_or(80)),
     )
 }
@@ -12,20 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal as _, Write as _};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{cursor, queue};
 use itertools::Itertools;
 use jj_lib::backend::CommitId;
+use jj_lib::commit::Commit;
+use jj_lib::fileset::FilesetExpression;
+use jj_lib::matchers::Matcher;
 use jj_lib::repo::Repo;
+use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::revset::{self, RevsetExpression, RevsetFilterPredicate, RevsetIteratorExt};
 use jj_lib::revset_graph::{
-    ReverseRevsetGraphIterator, RevsetGraphEdgeType, TopoGroupedRevsetGraphIterator,
+    ReverseRevsetGraphIterator, RevsetGraphEdge, RevsetGraphEdgeType,
+    TopoGroupedRevsetGraphIterator,
 };
+use jj_lib::rewrite;
 use tracing::instrument;
 
-use crate::cli_util::{CommandError, CommandHelper, LogContentFormat, RevisionArg};
+use crate::cli_util::{
+    user_error, CommandError, CommandHelper, LogContentFormat, RevisionArg, WorkspaceCommandHelper,
+};
 use crate::diff_util::{self, DiffFormatArgs};
 use crate::graphlog::{get_graphlog, Edge};
 use crate::ui::Ui;
 
+/// How often `jj log --watch` checks for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Show commit history
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct LogArgs {
@@ -37,6 +56,14 @@ pub(crate) struct LogArgs {
     /// Show commits modifying the given paths
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Follow the history of the path across renames and copies
+    ///
+    /// Requires a single path. Going back in history, when the path was added
+    /// as a renamed or copied file, the commits modifying the file it came
+    /// from are shown instead. Renames are detected like in diffs, with the
+    /// `ui.diff.rename-similarity` setting.
+    #[arg(long, requires = "paths")]
+    follow: bool,
     /// Show revisions in the opposite order (older revisions first)
     #[arg(long)]
     reversed: bool,
@@ -56,8 +83,24 @@ pub(crate) struct LogArgs {
     /// Show patch
     #[arg(long, short = 'p')]
     patch: bool,
+    /// Verify commit signatures and show the result below each revision
+    ///
+    /// The result is rendered with the `format_commit_signature(signature)`
+    /// template alias.
+    #[arg(long)]
+    show_signatures: bool,
     #[command(flatten)]
     diff_format: DiffFormatArgs,
+    /// Keep running, and show the log again whenever the repo changes
+    ///
+    /// The operation log is checked every second for new operations, such as
+    /// ones created by `jj` in another terminal. If `core.fsmonitor` is set to
+    /// `watchman`, the working copy is snapshotted when Watchman reports
+    /// changed files. Otherwise, it's snapshotted every second. If anything
+    /// changed, the screen is cleared and the log is shown again. Stop with
+    /// Ctrl-C.
+    #[arg(long)]
+    watch: bool,
 }
 
 #[instrument(skip_all)]
@@ -66,8 +109,156 @@ pub(crate) fn cmd_log(
     command: &CommandHelper,
     args: &LogArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    if args.follow && args.paths.len() != 1 {
+        return Err(user_error("--follow requires a single path"));
+    }
+    if args.watch {
+        return watch_log(ui, command, args);
+    }
+    let workspace_command = command.workspace_helper_for_read(ui)?;
+    ui.request_pager();
+    show_log(ui, command, &workspace_command, args)
+}
+
+/// Shows the log again whenever the operation the repo is loaded at changes.
+fn watch_log(ui: &mut Ui, command: &CommandHelper, args: &LogArgs) -> Result<(), CommandError> {
+    if command.global_args().at_operation != "@" {
+        return Err(user_error("--watch cannot be used with --at-op"));
+    }
+    let workspace = command.load_workspace()?;
+    let op_heads_store = workspace.repo_loader().op_heads_store().clone();
+    let mut watcher = WorkingCopyWatcher::new(ui, command, workspace.workspace_root())?;
+    let mut last_op_id = None;
+    let mut last_op_heads = HashSet::new();
+    let mut files_changed = true;
+    loop {
+        // Snapshotting the working copy is expensive, so the repo is only
+        // reloaded if files changed or an operation was added.
+        let op_heads: HashSet<_> = op_heads_store.get_op_heads().into_iter().collect();
+        if files_changed || op_heads != last_op_heads {
+            let workspace_command = command.workspace_helper_for_read(ui)?;
+            let op_id = workspace_command.repo().op_id();
+            if last_op_id.as_ref() != Some(op_id) {
+                if io::stdout().is_terminal() {
+                    let mut stdout = ui.stdout();
+                    queue!(stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+                }
+                show_log(ui, command, &workspace_command, args)?;
+                ui.stdout().flush()?;
+                last_op_id = Some(op_id.clone());
+            }
+            last_op_heads = op_heads;
+        }
+        files_changed = watcher.wait(ui)?;
+    }
+}
+
+/// Waits for the files in the working copy to change. Uses Watchman if it's
+/// the configured filesystem monitor, and polls otherwise.
+struct WorkingCopyWatcher {
+    #[cfg(feature = "watchman")]
+    watchman: Option<jj_lib::fsmonitor::watchman::FileWatcher>,
+}
 
+impl WorkingCopyWatcher {
+    #[cfg(feature = "watchman")]
+    fn new(ui: &Ui, command: &CommandHelper, workspace_root: &Path) -> Result<Self, CommandError> {
+        use jj_lib::fsmonitor::watchman::FileWatcher;
+        use jj_lib::fsmonitor::FsmonitorKind;
+
+        if !matches!(
+            command.settings().fsmonitor_kind()?,
+            Some(FsmonitorKind::Watchman)
+        ) {
+            return Ok(WorkingCopyWatcher { watchman: None });
+        }
+        let watchman = match FileWatcher::new(workspace_root) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                writeln!(
+                    ui.warning(),
+                    "Failed to watch the working copy with Watchman, so it will be polled: {err}"
+                )?;
+                None
+            }
+        };
+        Ok(WorkingCopyWatcher { watchman })
+    }
+
+    #[cfg(not(feature = "watchman"))]
+    fn new(
+        _ui: &Ui,
+        _command: &CommandHelper,
+        _workspace_root: &Path,
+    ) -> Result<Self, CommandError> {
+        Ok(WorkingCopyWatcher {})
+    }
+
+    /// Waits for up to `WATCH_INTERVAL` and returns whether files may have
+    /// changed. When polling, that's always the case.
+    fn wait(&mut self, ui: &Ui) -> Result<bool, CommandError> {
+        #[cfg(feature = "watchman")]
+        if let Some(watcher) = &mut self.watchman {
+            match watcher.wait_for_changes(WATCH_INTERVAL) {
+                Ok(changed) => return Ok(changed),
+                Err(err) => {
+                    writeln!(
+                        ui.warning(),
+                        "Stopped watching the working copy with Watchman, so it will be polled: \
+                         {err}"
+                    )?;
+                    self.watchman = None;
+                }
+            }
+        }
+        #[cfg(not(feature = "watchman"))]
+        let _ = ui;
+        thread::sleep(WATCH_INTERVAL);
+        Ok(true)
+    }
+}
+
+/// Extends a commit template to show the verified signature of the commit on a
+/// line after it.
+pub(crate) fn with_signatures(template_string: &str) -> String {
+    format!("({template_string}) ++ if(signature, format_commit_signature(signature) ++ \"\\n\")")
+}
+
+/// Converts the edges from the revset graph iterator to the edges to render.
+pub(crate) fn to_graphlog_edges(edges: Vec<RevsetGraphEdge>) -> Vec<Edge<CommitId>> {
+    let mut graphlog_edges = vec![];
+    // TODO: Should we update RevsetGraphIterator to yield this flag instead of all
+    // the missing edges since we don't care about where they point here
+    // anyway?
+    let mut has_missing = false;
+    for edge in edges {
+        match edge.edge_type {
+            RevsetGraphEdgeType::Missing => {
+                has_missing = true;
+            }
+            RevsetGraphEdgeType::Direct => graphlog_edges.push(Edge::Present {
+                direct: true,
+                target: edge.target,
+            }),
+            RevsetGraphEdgeType::Indirect => graphlog_edges.push(Edge::Present {
+                direct: false,
+                target: edge.target,
+            }),
+        }
+    }
+    if has_missing {
+        graphlog_edges.push(Edge::Missing);
+    }
+    graphlog_edges
+}
+
+fn show_log(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    args: &LogArgs,
+) -> Result<(), CommandError> {
+    let mut fileset = workspace_command.parse_union_filesets(&args.paths)?;
     let revset_expression = {
         let mut expression = if args.revisions.is_empty() {
             workspace_command.parse_revset(&command.settings().default_revset(), Some(ui))?
@@ -79,21 +270,27 @@ pub(crate) fn cmd_log(
                 .try_collect()?;
             RevsetExpression::union_all(&expressions)
         };
-        if !args.paths.is_empty() {
-            let repo_paths: Vec<_> = args
-                .paths
-                .iter()
-                .map(|path_arg| workspace_command.parse_file_path(path_arg))
-                .try_collect()?;
+        if args.follow {
+            let path = workspace_command.parse_file_path(&args.paths[0])?;
+            let revset = workspace_command.evaluate_revset(revset::optimize(expression))?;
+            let (commit_ids, followed_paths) =
+                follow_renames(workspace_command, revset.iter(), path)?;
+            fileset = FilesetExpression::union_all(
+                followed_paths
+                    .into_iter()
+                    .map(FilesetExpression::prefix_path)
+                    .collect(),
+            );
+            expression = RevsetExpression::commits(commit_ids);
+        } else if !args.paths.is_empty() {
             expression = expression.intersection(&RevsetExpression::filter(
-                RevsetFilterPredicate::File(Some(repo_paths)),
+                RevsetFilterPredicate::File(fileset.clone()),
             ));
         }
         revset::optimize(expression)
     };
     let repo = workspace_command.repo();
     let wc_commit_id = workspace_command.get_wc_commit_id();
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
     let revset = workspace_command.evaluate_revset(revset_expression)?;
 
     let store = repo.store();
@@ -104,11 +301,15 @@ pub(crate) fn cmd_log(
         Some(value) => value.to_string(),
         None => command.settings().config().get_string("templates.log")?,
     };
+    let template_string = if args.show_signatures {
+        with_signatures(&template_string)
+    } else {
+        template_string
+    };
     let template = workspace_command.parse_commit_template(&template_string)?;
     let with_content_format = LogContentFormat::new(ui, command.settings())?;
 
     {
-        ui.request_pager();
         let mut formatter = ui.stdout_formatter();
         let formatter = formatter.as_mut();
 
@@ -122,29 +323,7 @@ pub(crate) fn cmd_log(
                 Box::new(forward_iter)
             };
             for (commit_id, edges) in iter.take(args.limit.unwrap_or(usize::MAX)) {
-                let mut graphlog_edges = vec![];
-                // TODO: Should we update RevsetGraphIterator to yield this flag instead of all
-                // the missing edges since we don't care about where they point here
-                // anyway?
-                let mut has_missing = false;
-                for edge in edges {
-                    match edge.edge_type {
-                        RevsetGraphEdgeType::Missing => {
-                            has_missing = true;
-                        }
-                        RevsetGraphEdgeType::Direct => graphlog_edges.push(Edge::Present {
-                            direct: true,
-                            target: edge.target,
-                        }),
-                        RevsetGraphEdgeType::Indirect => graphlog_edges.push(Edge::Present {
-                            direct: false,
-                            target: edge.target,
-                        }),
-                    }
-                }
-                if has_missing {
-                    graphlog_edges.push(Edge::Missing);
-                }
+                let graphlog_edges = to_graphlog_edges(edges);
                 let mut buffer = vec![];
                 let commit = store.get_commit(&commit_id)?;
                 with_content_format.write_graph_text(
@@ -157,13 +336,16 @@ pub(crate) fn cmd_log(
                 }
                 if !diff_formats.is_empty() {
                     let mut formatter = ui.new_formatter(&mut buffer);
+                    let width = usize::from(ui.term_width().unwrap_or(80))
+                        .saturating_sub(graph.width(&commit_id, &graphlog_edges));
                     diff_util::show_patch(
                         ui,
                         formatter.as_mut(),
-                        &workspace_command,
+                        workspace_command,
                         &commit,
-                        matcher.as_ref(),
+                        diff_matcher(repo.as_ref(), &fileset, &commit)?.as_ref(),
                         &diff_formats,
+                        width,
                     )?;
                 }
                 let node_symbol = if Some(&commit_id) == wc_commit_id {
@@ -193,10 +375,11 @@ pub(crate) fn cmd_log(
                     diff_util::show_patch(
                         ui,
                         formatter,
-                        &workspace_command,
+                        workspace_command,
                         &commit,
-                        matcher.as_ref(),
+                        diff_matcher(repo.as_ref(), &fileset, &commit)?.as_ref(),
                         &diff_formats,
+                        usize::from(ui.term_width().unwrap_or(80)),
                     )?;
                 }
             }
@@ -227,3 +410,68 @@ pub(crate) fn cmd_log(
 
     Ok(())
 }
+
+/// Returns the matcher for the diff of the `commit`, with the predicates in the
+/// `fileset` resolved against its trees.
+fn diff_matcher(
+    repo: &dyn Repo,
+    fileset: &FilesetExpression,
+    commit: &Commit,
+) -> Result<Box<dyn Matcher>, CommandError> {
+    if !fileset.has_predicates() {
+        return Ok(fileset.to_matcher());
+    }
+    let from_tree = rewrite::merge_commit_trees(repo, &commit.parents())?;
+    let to_tree = commit.tree()?;
+    Ok(fileset
+        .resolve_predicates(&[&from_tree, &to_tree])?
+        .to_matcher())
+}
+
+/// Finds the commits among `commit_ids` that modify the file at `path`, or the
+/// files it was renamed or copied from. The `commit_ids` must be in reverse
+/// topological order. Returns the commits, and all paths the file had.
+fn follow_renames(
+    workspace_command: &WorkspaceCommandHelper,
+    commit_ids: impl Iterator<Item = CommitId>,
+    path: RepoPathBuf,
+) -> Result<(Vec<CommitId>, Vec<RepoPathBuf>), CommandError> {
+    let repo = workspace_command.repo();
+    // The path of the file in each commit, as seen from its descendants
+    let mut paths_by_commit: HashMap<CommitId, RepoPathBuf> = HashMap::new();
+    let mut followed_paths = vec![path.clone()];
+    let mut followed_ids = vec![];
+    for commit_id in commit_ids {
+        let commit = repo.store().get_commit(&commit_id)?;
+        let mut commit_path = paths_by_commit
+            .remove(&commit_id)
+            .unwrap_or_else(|| path.clone());
+        let from_tree = rewrite::merge_commit_trees(repo.as_ref(), &commit.parents())?;
+        let to_tree = commit.tree()?;
+        let before = from_tree.path_value(&commit_path);
+        let after = to_tree.path_value(&commit_path);
+        if before != after {
+            followed_ids.push(commit_id);
+            if before.is_absent() {
+                let source = diff_util::find_copy_source(
+                    workspace_command,
+                    &from_tree,
+                    &to_tree,
+                    &commit_path,
+                )?;
+                if let Some(source) = source {
+                    if !followed_paths.contains(&source) {
+                        followed_paths.push(source.clone());
+                    }
+                    commit_path = source;
+                }
+            }
+        }
+        for parent_id in commit.parent_ids() {
+            paths_by_commit
+                .entry(parent_id.clone())
+                .or_insert_with(|| commit_path.clone());
+        }
+    }
+    Ok((followed_ids, followed_paths))
+}
@@ -13,10 +13,13 @@
 // limitations under the License.
 
 mod abandon;
+mod am;
 mod backout;
 #[cfg(feature = "bench")]
 mod bench;
+mod bisect;
 mod branch;
+mod browse;
 mod cat;
 mod checkout;
 mod chmod;
@@ -26,10 +29,18 @@ mod debug;
 mod describe;
 mod diff;
 mod diffedit;
+mod doctor;
 mod duplicate;
 mod edit;
+mod eval;
+mod explain;
 mod files;
+mod fix;
+mod format_patch;
+mod gerrit;
 mod git;
+mod github;
+mod histedit;
 mod init;
 mod interdiff;
 mod log;
@@ -39,18 +50,25 @@ mod new;
 mod next;
 mod obslog;
 mod operation;
+mod patch;
+mod pin;
 mod prev;
+mod prompt;
 mod rebase;
 mod resolve;
+mod resolve_divergence;
 mod restore;
 mod root;
 mod run;
+mod send_email;
 mod show;
 mod sparse;
 mod split;
 mod squash;
 mod status;
+mod submit;
 mod tag;
+mod trailer;
 mod unsquash;
 mod untrack;
 mod util;
@@ -68,12 +86,16 @@ use crate::ui::Ui;
 #[derive(clap::Parser, Clone, Debug)]
 enum Command {
     Abandon(abandon::AbandonArgs),
+    Am(am::AmArgs),
     Backout(backout::BackoutArgs),
     #[cfg(feature = "bench")]
     #[command(subcommand)]
     Bench(bench::BenchCommand),
     #[command(subcommand)]
+    Bisect(bisect::BisectCommand),
+    #[command(subcommand)]
     Branch(branch::BranchCommand),
+    Browse(browse::BrowseArgs),
     #[command(alias = "print")]
     Cat(cat::CatArgs),
     #[command(hide = true)]
@@ -87,11 +109,21 @@ enum Command {
     Describe(describe::DescribeArgs),
     Diff(diff::DiffArgs),
     Diffedit(diffedit::DiffeditArgs),
+    Doctor(doctor::DoctorArgs),
     Duplicate(duplicate::DuplicateArgs),
     Edit(edit::EditArgs),
+    Eval(eval::EvalArgs),
+    Explain(explain::ExplainArgs),
     Files(files::FilesArgs),
+    Fix(fix::FixArgs),
+    FormatPatch(format_patch::FormatPatchArgs),
+    #[command(subcommand)]
+    Gerrit(gerrit::GerritCommand),
     #[command(subcommand)]
     Git(git::GitCommand),
+    #[command(subcommand)]
+    Github(github::GithubCommand),
+    Histedit(histedit::HisteditArgs),
     Init(init::InitArgs),
     Interdiff(interdiff::InterdiffArgs),
     Log(log::LogArgs),
@@ -113,9 +145,15 @@ enum Command {
     #[command(subcommand)]
     #[command(visible_alias = "op")]
     Operation(operation::OperationCommand),
+    #[command(subcommand)]
+    Patch(patch::PatchCommand),
+    #[command(subcommand)]
+    Pin(pin::PinCommand),
     Prev(prev::PrevArgs),
+    Prompt(prompt::PromptArgs),
     Rebase(rebase::RebaseArgs),
     Resolve(resolve::ResolveArgs),
+    ResolveDivergence(resolve_divergence::ResolveDivergenceArgs),
     Restore(restore::RestoreArgs),
     #[command(
         hide = true,
@@ -126,15 +164,19 @@ enum Command {
     #[command(hide = true)]
     // TODO: Flesh out.
     Run(run::RunArgs),
+    SendEmail(send_email::SendEmailArgs),
     Show(show::ShowArgs),
     #[command(subcommand)]
     Sparse(sparse::SparseArgs),
     Split(split::SplitArgs),
     Squash(squash::SquashArgs),
     Status(status::StatusArgs),
+    Submit(submit::SubmitArgs),
     #[command(subcommand)]
     Tag(tag::TagCommand),
     #[command(subcommand)]
+    Trailer(trailer::TrailerCommand),
+    #[command(subcommand)]
     Util(util::UtilCommand),
     /// Undo an operation (shortcut for `jj op undo`)
     Undo(operation::OperationUndoArgs),
@@ -166,6 +208,10 @@ pub fn run_command(ui: &mut Ui, command_helper: &CommandHelper) -> Result<(), Co
         Command::Checkout(sub_args) => checkout::cmd_checkout(ui, command_helper, sub_args),
         Command::Untrack(sub_args) => untrack::cmd_untrack(ui, command_helper, sub_args),
         Command::Files(sub_args) => files::cmd_files(ui, command_helper, sub_args),
+        Command::Fix(sub_args) => fix::cmd_fix(ui, command_helper, sub_args),
+        Command::FormatPatch(sub_args) => {
+            format_patch::cmd_format_patch(ui, command_helper, sub_args)
+        }
         Command::Cat(sub_args) => cat::cmd_cat(ui, command_helper, sub_args),
         Command::Diff(sub_args) => diff::cmd_diff(ui, command_helper, sub_args),
         Command::Show(sub_args) => show::cmd_show(ui, command_helper, sub_args),
@@ -177,31 +223,49 @@ pub fn run_command(ui: &mut Ui, command_helper: &CommandHelper) -> Result<(), Co
         Command::Commit(sub_args) => commit::cmd_commit(ui, command_helper, sub_args),
         Command::Duplicate(sub_args) => duplicate::cmd_duplicate(ui, command_helper, sub_args),
         Command::Abandon(sub_args) => abandon::cmd_abandon(ui, command_helper, sub_args),
+        Command::Am(sub_args) => am::cmd_am(ui, command_helper, sub_args),
+        Command::Doctor(sub_args) => doctor::cmd_doctor(ui, command_helper, sub_args),
         Command::Edit(sub_args) => edit::cmd_edit(ui, command_helper, sub_args),
+        Command::Eval(sub_args) => eval::cmd_eval(ui, command_helper, sub_args),
+        Command::Explain(sub_args) => explain::cmd_explain(ui, command_helper, sub_args),
         Command::Next(sub_args) => next::cmd_next(ui, command_helper, sub_args),
         Command::Prev(sub_args) => prev::cmd_prev(ui, command_helper, sub_args),
+        Command::Prompt(sub_args) => prompt::cmd_prompt(ui, command_helper, sub_args),
         Command::New(sub_args) => new::cmd_new(ui, command_helper, sub_args),
         Command::Move(sub_args) => r#move::cmd_move(ui, command_helper, sub_args),
         Command::Squash(sub_args) => squash::cmd_squash(ui, command_helper, sub_args),
+        Command::Submit(sub_args) => submit::cmd_submit(ui, command_helper, sub_args),
         Command::Unsquash(sub_args) => unsquash::cmd_unsquash(ui, command_helper, sub_args),
         Command::Restore(sub_args) => restore::cmd_restore(ui, command_helper, sub_args),
         Command::Revert(_args) => revert(),
         Command::Root(sub_args) => root::cmd_root(ui, command_helper, sub_args),
         Command::Run(sub_args) => run::cmd_run(ui, command_helper, sub_args),
+        Command::SendEmail(sub_args) => send_email::cmd_send_email(ui, command_helper, sub_args),
         Command::Diffedit(sub_args) => diffedit::cmd_diffedit(ui, command_helper, sub_args),
         Command::Split(sub_args) => split::cmd_split(ui, command_helper, sub_args),
         Command::Merge(sub_args) => merge::cmd_merge(ui, command_helper, sub_args),
         Command::Rebase(sub_args) => rebase::cmd_rebase(ui, command_helper, sub_args),
         Command::Backout(sub_args) => backout::cmd_backout(ui, command_helper, sub_args),
         Command::Resolve(sub_args) => resolve::cmd_resolve(ui, command_helper, sub_args),
+        Command::ResolveDivergence(sub_args) => {
+            resolve_divergence::cmd_resolve_divergence(ui, command_helper, sub_args)
+        }
+        Command::Bisect(sub_args) => bisect::cmd_bisect(ui, command_helper, sub_args),
         Command::Branch(sub_args) => branch::cmd_branch(ui, command_helper, sub_args),
+        Command::Browse(sub_args) => browse::cmd_browse(ui, command_helper, sub_args),
         Command::Undo(sub_args) => operation::cmd_op_undo(ui, command_helper, sub_args),
         Command::Operation(sub_args) => operation::cmd_operation(ui, command_helper, sub_args),
+        Command::Patch(sub_args) => patch::cmd_patch(ui, command_helper, sub_args),
+        Command::Pin(sub_args) => pin::cmd_pin(ui, command_helper, sub_args),
         Command::Workspace(sub_args) => workspace::cmd_workspace(ui, command_helper, sub_args),
         Command::Sparse(sub_args) => sparse::cmd_sparse(ui, command_helper, sub_args),
         Command::Tag(sub_args) => tag::cmd_tag(ui, command_helper, sub_args),
+        Command::Trailer(sub_args) => trailer::cmd_trailer(ui, command_helper, sub_args),
         Command::Chmod(sub_args) => chmod::cmd_chmod(ui, command_helper, sub_args),
+        Command::Gerrit(sub_args) => gerrit::cmd_gerrit(ui, command_helper, sub_args),
         Command::Git(sub_args) => git::cmd_git(ui, command_helper, sub_args),
+        Command::Github(sub_args) => github::cmd_github(ui, command_helper, sub_args),
+        Command::Histedit(sub_args) => histedit::cmd_histedit(ui, command_helper, sub_args),
         Command::Util(sub_args) => util::cmd_util(ui, command_helper, sub_args),
         #[cfg(feature = "bench")]
         Command::Bench(sub_args) => bench::cmd_bench(ui, command_helper, sub_args),
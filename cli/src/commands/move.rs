@@ -20,15 +20,16 @@ use tracing::instrument;
 
 use crate::cli_util::{user_error, CommandError, CommandHelper, RevisionArg};
 use crate::description_util::combine_messages;
+use crate::patch_util::{select_lines, LineSelection};
 use crate::ui::Ui;
 
 /// Move changes from one revision into another
 ///
-/// Use `--interactive` to move only part of the source revision into the
-/// destination. The selected changes (or all the changes in the source revision
-/// if not using `--interactive`) will be moved into the destination. The
-/// changes will be removed from the source. If that means that the source is
-/// now empty compared to its parent, it will be abandoned. Without
+/// Use `--interactive` or `--lines` to move only part of the source revision
+/// into the destination. The selected changes (or all the changes in the source
+/// revision if not using `--interactive`) will be moved into the destination.
+/// The changes will be removed from the source. If that means that the source
+/// is now empty compared to its parent, it will be abandoned. Without
 /// `--interactive`, the source change will always be empty.
 ///
 /// If the source became empty and both the source and destination had a
@@ -49,6 +50,17 @@ pub(crate) struct MoveArgs {
     /// Move only changes to these paths (instead of all paths)
     #[arg(conflicts_with = "interactive", value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Move only the changes to these lines of a file, like `src/main.rs:10-25`
+    ///
+    /// The lines are numbered as in the source, and can be a comma-separated
+    /// list of line numbers and ranges. A changed hunk is moved if it touches
+    /// any of the lines.
+    #[arg(
+        long,
+        value_name = "PATH:LINES",
+        conflicts_with_all = ["interactive", "paths"]
+    )]
+    lines: Vec<LineSelection>,
 }
 
 #[instrument(skip_all)]
@@ -65,10 +77,12 @@ pub(crate) fn cmd_move(
         return Err(user_error("Source and destination cannot be the same."));
     }
     workspace_command.check_rewritable([&source, &destination])?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
-    let mut tx = workspace_command.start_transaction();
-    let parent_tree = merge_commit_trees(tx.repo(), &source.parents())?;
+    let parent_tree = merge_commit_trees(workspace_command.repo().as_ref(), &source.parents())?;
     let source_tree = source.tree()?;
+    let matcher =
+        workspace_command.matcher_from_values(&args.paths, &[&parent_tree, &source_tree])?;
+    let line_selections = workspace_command.parse_line_selections(&args.lines)?;
+    let mut tx = workspace_command.start_transaction()?;
     let instructions = format!(
         "\
 You are moving changes from: {}
@@ -85,15 +99,24 @@ from the source will be moved into the destination.
         tx.format_commit_summary(&source),
         tx.format_commit_summary(&destination)
     );
-    let new_parent_tree_id = tx.select_diff(
-        ui,
-        &parent_tree,
-        &source_tree,
-        matcher.as_ref(),
-        &instructions,
-        args.interactive,
-    )?;
-    if args.interactive && new_parent_tree_id == parent_tree.id() {
+    let new_parent_tree_id = if line_selections.is_empty() {
+        tx.select_diff(
+            ui,
+            &parent_tree,
+            &source_tree,
+            matcher.as_ref(),
+            &instructions,
+            args.interactive,
+        )?
+    } else {
+        select_lines(
+            tx.repo().store(),
+            &parent_tree,
+            &source_tree,
+            &line_selections,
+        )?
+    };
+    if (args.interactive || !line_selections.is_empty()) && new_parent_tree_id == parent_tree.id() {
         return Err(user_error("No changes to move"));
     }
     let new_parent_tree = tx.repo().store().get_root_tree(&new_parent_tree_id)?;
@@ -17,13 +17,16 @@ use std::io::Write;
 use clap::ArgGroup;
 use itertools::Itertools;
 use jj_lib::commit::Commit;
+use jj_lib::merged_tree::MergedTree;
 use jj_lib::repo::Repo;
+use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::revset::{RevsetExpression, RevsetIteratorExt};
 use jj_lib::rewrite::{merge_commit_trees, rebase_commit};
 use tracing::instrument;
 
 use crate::cli_util::{
-    self, short_commit_hash, user_error, CommandError, CommandHelper, RevisionArg,
+    self, print_resolved_conflicts, short_commit_hash, user_error, CommandError, CommandHelper,
+    MergeStrategyArg, RevisionArg,
 };
 use crate::ui::Ui;
 
@@ -79,6 +82,12 @@ pub(crate) struct NewArgs {
         overrides_with = "insert_before"
     )]
     insert_before: bool,
+    /// Resolve the conflicts between the parents by taking one side
+    ///
+    /// "ours" is the first parent, and "theirs" is the last parent. The files
+    /// that were resolved are listed.
+    #[arg(long, value_enum)]
+    strategy: Option<MergeStrategyArg>,
 }
 
 #[instrument(skip_all)]
@@ -102,9 +111,10 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
         .into_iter()
         .collect_vec();
     let target_ids = target_commits.iter().map(|c| c.id().clone()).collect_vec();
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let mut num_rebased;
     let new_commit;
+    let resolved_paths;
     if args.insert_before {
         // Instead of having the new commit as a child of the changes given on the
         // command line, add it between the changes' parents and the changes.
@@ -137,7 +147,9 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
             let root_commit = tx.repo().store().root_commit();
             new_parents_commits.retain(|c| c != &root_commit);
         }
-        let merged_tree = merge_commit_trees(tx.repo(), &new_parents_commits)?;
+        let merged_tree;
+        (merged_tree, resolved_paths) =
+            merge_parent_trees(tx.repo(), &new_parents_commits, args.strategy)?;
         let new_parents_commit_id = new_parents_commits.iter().map(|c| c.id().clone()).collect();
         new_commit = tx
             .mut_repo()
@@ -170,7 +182,9 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
         };
         tx.base_workspace_helper()
             .check_rewritable(&commits_to_rebase)?;
-        let merged_tree = merge_commit_trees(tx.repo(), &target_commits)?;
+        let merged_tree;
+        (merged_tree, resolved_paths) =
+            merge_parent_trees(tx.repo(), &target_commits, args.strategy)?;
         new_commit = tx
             .mut_repo()
             .new_commit(command.settings(), target_ids.clone(), merged_tree.id())
@@ -206,6 +220,21 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
     if num_rebased > 0 {
         writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
     }
+    print_resolved_conflicts(ui, &tx, &new_commit, &resolved_paths)?;
     tx.finish(ui, "new empty commit")?;
     Ok(())
 }
+
+/// Merges the trees of the `parents`, and resolves the conflicts with the
+/// `strategy`, if any. Also returns the paths of the resolved conflicts.
+fn merge_parent_trees(
+    repo: &dyn Repo,
+    parents: &[Commit],
+    strategy: Option<MergeStrategyArg>,
+) -> Result<(MergedTree, Vec<RepoPathBuf>), CommandError> {
+    let merged_tree = merge_commit_trees(repo, parents)?;
+    match strategy {
+        Some(strategy) => Ok(merged_tree.resolve_conflicts_with(strategy.into())?),
+        None => Ok((merged_tree, vec![])),
+    }
+}
@@ -145,7 +145,7 @@ pub(crate) fn cmd_next(
     if edit {
         // We're editing, the target must be rewritable.
         workspace_command.check_rewritable([target])?;
-        let mut tx = workspace_command.start_transaction();
+        let mut tx = workspace_command.start_transaction()?;
         tx.edit(target)?;
         tx.finish(
             ui,
@@ -153,7 +153,7 @@ pub(crate) fn cmd_next(
         )?;
         return Ok(());
     }
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     // Move the working-copy commit to the new parent.
     tx.check_out(target)?;
     tx.finish(ui, format!("next: {current_short} -> {target_short}"))?;
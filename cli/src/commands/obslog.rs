@@ -19,7 +19,8 @@ use jj_lib::rewrite::rebase_to_dest_parent;
 use tracing::instrument;
 
 use crate::cli_util::{
-    CommandError, CommandHelper, LogContentFormat, RevisionArg, WorkspaceCommandHelper,
+    short_commit_hash, user_error, CommandError, CommandHelper, LogContentFormat, RevisionArg,
+    WorkspaceCommandHelper,
 };
 use crate::diff_util::{self, DiffFormat, DiffFormatArgs};
 use crate::formatter::Formatter;
@@ -51,6 +52,21 @@ pub(crate) struct ObslogArgs {
     /// contaminated by unrelated changes.
     #[arg(long, short = 'p')]
     patch: bool,
+    /// Show the diff between two versions of this change instead of the log
+    ///
+    /// The older version is temporarily rebased to the parents of the newer
+    /// version, so the diff shows how the change itself evolved. By default,
+    /// the version given by `-r` is compared to its previous version.
+    #[arg(long, conflicts_with_all = ["limit", "no_graph", "template", "patch"])]
+    interdiff: bool,
+    /// The older version to compare with `--interdiff` [default: the previous
+    /// version of `--to`]
+    #[arg(long, requires = "interdiff")]
+    from: Option<RevisionArg>,
+    /// The newer version to compare with `--interdiff` [default: the revision
+    /// given by `-r`]
+    #[arg(long, requires = "interdiff")]
+    to: Option<RevisionArg>,
     #[command(flatten)]
     diff_format: DiffFormatArgs,
 }
@@ -61,9 +77,12 @@ pub(crate) fn cmd_obslog(
     command: &CommandHelper,
     args: &ObslogArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
 
     let start_commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
+    if args.interdiff {
+        return show_interdiff(ui, command, &workspace_command, start_commit, args);
+    }
     let wc_commit_id = workspace_command.get_wc_commit_id();
 
     let diff_formats =
@@ -108,12 +127,15 @@ pub(crate) fn cmd_obslog(
             }
             if !diff_formats.is_empty() {
                 let mut formatter = ui.new_formatter(&mut buffer);
+                let width = usize::from(ui.term_width().unwrap_or(80))
+                    .saturating_sub(graph.width(commit.id(), &edges));
                 show_predecessor_patch(
                     ui,
                     formatter.as_mut(),
                     &workspace_command,
                     &commit,
                     &diff_formats,
+                    width,
                 )?;
             }
             let node_symbol = if Some(commit.id()) == wc_commit_id {
@@ -133,7 +155,15 @@ pub(crate) fn cmd_obslog(
             with_content_format
                 .write(formatter, |formatter| template.format(&commit, formatter))?;
             if !diff_formats.is_empty() {
-                show_predecessor_patch(ui, formatter, &workspace_command, &commit, &diff_formats)?;
+                let width = usize::from(ui.term_width().unwrap_or(80));
+                show_predecessor_patch(
+                    ui,
+                    formatter,
+                    &workspace_command,
+                    &commit,
+                    &diff_formats,
+                    width,
+                )?;
             }
         }
     }
@@ -147,6 +177,7 @@ fn show_predecessor_patch(
     workspace_command: &WorkspaceCommandHelper,
     commit: &Commit,
     diff_formats: &[DiffFormat],
+    width: usize,
 ) -> Result<(), CommandError> {
     let predecessors = commit.predecessors();
     let predecessor = match predecessors.first() {
@@ -164,5 +195,60 @@ fn show_predecessor_patch(
         &tree,
         &EverythingMatcher,
         diff_formats,
+        width,
+    )
+}
+
+fn show_interdiff(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    workspace_command: &WorkspaceCommandHelper,
+    start_commit: Commit,
+    args: &ObslogArgs,
+) -> Result<(), CommandError> {
+    let versions = topo_order_reverse(
+        vec![start_commit.clone()],
+        |commit: &Commit| commit.id().clone(),
+        |commit: &Commit| commit.predecessors(),
+    );
+    let mut resolve_version = |revision: &str| -> Result<Commit, CommandError> {
+        let commit = workspace_command.resolve_single_rev(revision, ui)?;
+        if versions.contains(&commit) {
+            Ok(commit)
+        } else {
+            Err(user_error(format!(
+                "Commit {} is not a version of the change in commit {}",
+                short_commit_hash(commit.id()),
+                short_commit_hash(start_commit.id()),
+            )))
+        }
+    };
+    let to = match &args.to {
+        Some(revision) => resolve_version(revision)?,
+        None => start_commit.clone(),
+    };
+    let from = match &args.from {
+        Some(revision) => resolve_version(revision)?,
+        None => to.predecessors().into_iter().next().ok_or_else(|| {
+            user_error(format!(
+                "Commit {} has no previous version",
+                short_commit_hash(to.id())
+            ))
+        })?,
+    };
+
+    let from_tree = rebase_to_dest_parent(workspace_command.repo().as_ref(), &from, &to)?;
+    let to_tree = to.tree()?;
+    let diff_formats = diff_util::diff_formats_for(command.settings(), &args.diff_format)?;
+    ui.request_pager();
+    diff_util::show_diff(
+        ui,
+        ui.stdout_formatter().as_mut(),
+        workspace_command,
+        &from_tree,
+        &to_tree,
+        &EverythingMatcher,
+        &diff_formats,
+        usize::from(ui.term_width().unwrap_or(80)),
     )
 }
@@ -12,25 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
 use std::io::Write as _;
 use std::slice;
 
 use clap::Subcommand;
 use itertools::Itertools as _;
+use jj_lib::git::{self, GitOpSyncError, GitPushError};
 use jj_lib::object_id::ObjectId;
-use jj_lib::op_store::OperationId;
+use jj_lib::op_store::{OperationId, RefTarget};
 use jj_lib::op_walk;
 use jj_lib::operation::Operation;
 use jj_lib::repo::Repo;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt as _};
 
+use super::git::{map_git_error, map_git_push_error};
+use super::workspace::update_stale_working_copy;
 use crate::cli_util::{
-    short_operation_hash, user_error, user_error_with_hint, CommandError, CommandHelper,
-    LogContentFormat,
+    short_commit_hash, short_operation_hash, user_error, user_error_with_hint, CommandError,
+    CommandHelper, LogContentFormat, WorkspaceCommandTransaction,
 };
+use crate::git_util::{get_git_repo, with_remote_git_callbacks};
 use crate::graphlog::{get_graphlog, Edge};
-use crate::operation_templater;
 use crate::templater::Template as _;
 use crate::ui::Ui;
+use crate::{operation_templater, tr};
 
 /// Commands for working with the operation log
 ///
@@ -42,6 +48,7 @@ pub enum OperationCommand {
     Log(OperationLogArgs),
     Undo(OperationUndoArgs),
     Restore(OperationRestoreArgs),
+    Sync(OperationSyncArgs),
 }
 
 /// Show the operation log
@@ -92,6 +99,14 @@ pub struct OperationUndoArgs {
     #[arg(default_value = "@")]
     operation: String,
 
+    /// Choose the operation from a list of recent operations
+    ///
+    /// You can choose to undo an operation, or to restore the repo to the
+    /// state after it. The branches and commits that would change are shown
+    /// before asking for confirmation.
+    #[arg(long, short, conflicts_with = "operation")]
+    interactive: bool,
+
     /// What portions of the local state to restore (can be repeated)
     ///
     /// This option is EXPERIMENTAL.
@@ -116,6 +131,25 @@ pub struct OperationAbandonArgs {
     operation: String,
 }
 
+/// Synchronize the operation log with a Git remote
+///
+/// This fetches the operation log that was synchronized to the remote, merges
+/// it with the local one, and pushes the result back, along with the commits
+/// the operations refer to. Run it on each machine that works on the same
+/// repo to make the operation log, and so `jj undo` and `jj op restore`,
+/// follow you between machines.
+///
+/// The operation log is stored in the `refs/jj/sync/op-log` ref on the
+/// remote, so it doesn't show up as a branch. All commits that the operations
+/// refer to are pushed, including hidden ones, and they stay on the remote
+/// even after `jj util gc` removes them locally.
+#[derive(clap::Args, Clone, Debug)]
+pub struct OperationSyncArgs {
+    /// The Git remote to synchronize with
+    #[arg(default_value = "origin")]
+    remote: String,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 enum UndoWhatToRestore {
     /// The jj repo state and local branches
@@ -159,6 +193,14 @@ fn cmd_op_log(
         [op] => Some(op.id()),
         _ => None,
     };
+    if head_ops.len() > 1 {
+        writeln!(
+            ui.warning(),
+            "The operation log has {} heads from concurrent operations. They will be merged by \
+             the next command that loads the repo.",
+            head_ops.len()
+        )?;
+    }
 
     let template_string = match &args.template {
         Some(value) => value.to_owned(),
@@ -242,6 +284,7 @@ fn view_with_desired_portions_restored(
         head_ids: repo_source.head_ids.clone(),
         local_branches: repo_source.local_branches.clone(),
         tags: repo_source.tags.clone(),
+        pins: repo_source.pins.clone(),
         remote_views: remote_source.remote_views.clone(),
         git_refs: current_view.git_refs.clone(),
         git_head: current_view.git_head.clone(),
@@ -254,8 +297,24 @@ pub fn cmd_op_undo(
     command: &CommandHelper,
     args: &OperationUndoArgs,
 ) -> Result<(), CommandError> {
+    if args.interactive {
+        return undo_interactively(ui, command, &args.what);
+    }
     let mut workspace_command = command.workspace_helper(ui)?;
     let bad_op = workspace_command.resolve_single_op(&args.operation)?;
+    let mut tx = workspace_command.start_transaction()?;
+    undo_operation(&mut tx, &bad_op, &args.what)?;
+    tx.finish(ui, format!("undo operation {}", bad_op.id().hex()))?;
+
+    Ok(())
+}
+
+/// Applies the inverse of `bad_op` to the repo in the transaction.
+fn undo_operation(
+    tx: &mut WorkspaceCommandTransaction,
+    bad_op: &Operation,
+    what: &[UndoWhatToRestore],
+) -> Result<(), CommandError> {
     let mut parent_ops = bad_op.parents();
     let Some(parent_op) = parent_ops.next().transpose()? else {
         return Err(user_error("Cannot undo repo initialization"));
@@ -264,22 +323,167 @@ pub fn cmd_op_undo(
         return Err(user_error("Cannot undo a merge operation"));
     }
 
-    let mut tx = workspace_command.start_transaction();
     let repo_loader = tx.base_repo().loader();
-    let bad_repo = repo_loader.load_at(&bad_op)?;
+    let bad_repo = repo_loader.load_at(bad_op)?;
     let parent_repo = repo_loader.load_at(&parent_op)?;
     tx.mut_repo().merge(&bad_repo, &parent_repo);
     let new_view = view_with_desired_portions_restored(
         tx.repo().view().store_view(),
         tx.base_repo().view().store_view(),
-        &args.what,
+        what,
     );
     tx.mut_repo().set_view(new_view);
-    tx.finish(ui, format!("undo operation {}", bad_op.id().hex()))?;
+    Ok(())
+}
+
+/// Number of operations to choose from in `jj undo --interactive`.
+const INTERACTIVE_UNDO_OPERATIONS: usize = 10;
 
+fn undo_interactively(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    what: &[UndoWhatToRestore],
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let current_op = workspace_command.repo().operation().clone();
+    // The root operation can be neither undone nor restored to
+    let ops: Vec<_> = op_walk::walk_ancestors(slice::from_ref(&current_op))
+        .filter_ok(|op| !op.parent_ids().is_empty())
+        .take(INTERACTIVE_UNDO_OPERATIONS)
+        .try_collect()?;
+
+    writeln!(ui.stdout(), "Recent operations:")?;
+    let mut choices = vec![];
+    for (i, op) in ops.iter().enumerate() {
+        writeln!(
+            ui.stdout(),
+            "{}: {} {}",
+            i + 1,
+            short_operation_hash(op.id()),
+            op.store_operation().metadata.description
+        )?;
+        choices.push(format!("{}", i + 1));
+        choices.push(format!("r{}", i + 1));
+    }
+    writeln!(ui.stdout(), "q: quit the prompt")?;
+    choices.push("q".to_string());
+    let choice = ui.prompt_choice(
+        "enter the index of the operation to undo, or r and the index to restore the repo to the \
+         state after it",
+        &choices,
+        None,
+    )?;
+    if choice == "q" {
+        writeln!(ui.stderr(), "Nothing changed.")?;
+        return Ok(());
+    }
+    let (restore, index) = match choice.strip_prefix('r') {
+        Some(index) => (true, index),
+        None => (false, choice.as_str()),
+    };
+    let op = &ops[index.parse::<usize>().unwrap() - 1];
+
+    let mut tx = workspace_command.start_transaction()?;
+    let (verb, tx_description) = if restore {
+        restore_operation(&mut tx, op, what)?;
+        (
+            "Restore to",
+            format!("restore to operation {}", op.id().hex()),
+        )
+    } else {
+        undo_operation(&mut tx, op, what)?;
+        ("Undo", format!("undo operation {}", op.id().hex()))
+    };
+    if !write_view_changes(ui, &tx)? {
+        writeln!(ui.stderr(), "Nothing changed.")?;
+        return Ok(());
+    }
+    let prompt = format!("{verb} operation {}?", short_operation_hash(op.id()));
+    if !ui.prompt_yes_no(&prompt, None)? {
+        writeln!(ui.stderr(), "Nothing changed.")?;
+        return Ok(());
+    }
+    tx.finish(ui, tx_description)?;
     Ok(())
 }
 
+/// Maximum number of commits listed in each group of the preview.
+const PREVIEW_COMMITS: usize = 10;
+
+/// Writes the branches and commits that would change if the transaction is
+/// committed. Returns false if nothing would change.
+fn write_view_changes(ui: &Ui, tx: &WorkspaceCommandTransaction) -> Result<bool, CommandError> {
+    let old_view = tx.base_repo().view();
+    let new_view = tx.repo().view();
+    let mut formatter = ui.stdout_formatter();
+    let mut changed = false;
+
+    let format_target = |target: &RefTarget| {
+        if let Some(id) = target.as_normal() {
+            short_commit_hash(id)
+        } else if target.is_absent() {
+            "(absent)".to_owned()
+        } else {
+            "(conflicted)".to_owned()
+        }
+    };
+    let branch_names: BTreeSet<_> = old_view
+        .local_branches()
+        .chain(new_view.local_branches())
+        .map(|(name, _)| name)
+        .collect();
+    for name in branch_names {
+        let old_target = old_view.get_local_branch(name);
+        let new_target = new_view.get_local_branch(name);
+        if old_target != new_target {
+            if !changed {
+                writeln!(formatter, "Changes:")?;
+                changed = true;
+            }
+            writeln!(
+                formatter,
+                "  Branch {name}: {} -> {}",
+                format_target(old_target),
+                format_target(new_target)
+            )?;
+        }
+    }
+
+    let old_heads = RevsetExpression::commits(old_view.heads().iter().cloned().collect());
+    let new_heads = RevsetExpression::commits(new_view.heads().iter().cloned().collect());
+    for (heading, expression) in [
+        ("Commits that would be hidden:", new_heads.range(&old_heads)),
+        (
+            "Commits that would become visible:",
+            old_heads.range(&new_heads),
+        ),
+    ] {
+        let commits: Vec<_> = expression
+            .evaluate_programmatic(tx.repo())?
+            .iter()
+            .commits(tx.repo().store())
+            .try_collect()?;
+        if commits.is_empty() {
+            continue;
+        }
+        changed = true;
+        writeln!(formatter, "{heading}")?;
+        for commit in commits.iter().take(PREVIEW_COMMITS) {
+            write!(formatter, "  ")?;
+            tx.write_commit_summary(formatter.as_mut(), commit)?;
+            writeln!(formatter)?;
+        }
+        if commits.len() > PREVIEW_COMMITS {
+            writeln!(
+                formatter,
+                "  ... and {} more",
+                commits.len() - PREVIEW_COMMITS
+            )?;
+        }
+    }
+    Ok(changed)
+}
+
 fn cmd_op_restore(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -287,15 +491,25 @@ fn cmd_op_restore(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let target_op = workspace_command.resolve_single_op(&args.operation)?;
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
+    restore_operation(&mut tx, &target_op, &args.what)?;
+    tx.finish(ui, format!("restore to operation {}", target_op.id().hex()))?;
+
+    Ok(())
+}
+
+/// Sets the repo in the transaction to its state after `target_op`.
+fn restore_operation(
+    tx: &mut WorkspaceCommandTransaction,
+    target_op: &Operation,
+    what: &[UndoWhatToRestore],
+) -> Result<(), CommandError> {
     let new_view = view_with_desired_portions_restored(
         target_op.view()?.store_view(),
         tx.base_repo().view().store_view(),
-        &args.what,
+        what,
     );
     tx.mut_repo().set_view(new_view);
-    tx.finish(ui, format!("restore to operation {}", target_op.id().hex()))?;
-
     Ok(())
 }
 
@@ -304,6 +518,7 @@ fn cmd_op_abandon(
     command: &CommandHelper,
     args: &OperationAbandonArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     // Don't load the repo so that this command can be used to recover from
     // corrupted repo state.
     let mut workspace = command.load_workspace()?;
@@ -359,7 +574,7 @@ fn cmd_op_abandon(
     )?;
     let [new_head_id]: [OperationId; 1] = stats.new_head_ids.try_into().unwrap();
     if current_head_op.id() == &new_head_id {
-        writeln!(ui.stderr(), "Nothing changed.")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
         return Ok(());
     }
     writeln!(
@@ -390,6 +605,67 @@ fn cmd_op_abandon(
     Ok(())
 }
 
+fn cmd_op_sync(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &OperationSyncArgs,
+) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
+    let remote = &args.remote;
+    let workspace_command = command.workspace_helper(ui)?;
+    let known_wc_commit = match workspace_command.get_wc_commit_id() {
+        Some(id) => Some(workspace_command.repo().store().get_commit(id)?),
+        None => None,
+    };
+    let git_repo = get_git_repo(workspace_command.repo().store())?;
+    let imported_count = with_remote_git_callbacks(ui, command.settings(), remote, |cb| {
+        git::fetch_op_log(workspace_command.repo(), &git_repo, remote, cb)
+    })?
+    .map_err(map_op_sync_error)?;
+    // Reload the repo so the fetched operations are merged with the local ones
+    let mut workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let exported_count = with_remote_git_callbacks(ui, command.settings(), remote, |cb| {
+        git::push_op_log(workspace_command.repo(), &git_repo, remote, cb)
+    })?
+    .map_err(map_op_sync_error)?;
+    if imported_count == 0 && exported_count == 0 {
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
+        return Ok(());
+    }
+    if imported_count > 0 {
+        writeln!(
+            ui.stderr(),
+            "Imported {imported_count} operations from {remote}"
+        )?;
+    }
+    if exported_count > 0 {
+        writeln!(
+            ui.stderr(),
+            "Exported {exported_count} operations to {remote}"
+        )?;
+    }
+    // The working-copy commit may have been moved on another machine
+    if let Some(known_wc_commit) = &known_wc_commit {
+        update_stale_working_copy(ui, &mut workspace_command, known_wc_commit)?;
+    }
+    Ok(())
+}
+
+fn map_op_sync_error(err: GitOpSyncError) -> CommandError {
+    match err {
+        GitOpSyncError::Push(GitPushError::NotFastForward) => user_error_with_hint(
+            "The operation log on the remote was changed while it was being synchronized",
+            "Run `jj op sync` again.",
+        ),
+        GitOpSyncError::Push(err) => map_git_push_error(err),
+        GitOpSyncError::InternalGitError(err) => map_git_error(err),
+        GitOpSyncError::Backend(err) => err.into(),
+        GitOpSyncError::OpStore(err) => err.into(),
+        GitOpSyncError::Io(err) => err.into(),
+        _ => user_error(err),
+    }
+}
+
 pub fn cmd_operation(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -400,5 +676,6 @@ pub fn cmd_operation(
         OperationCommand::Log(args) => cmd_op_log(ui, command, args),
         OperationCommand::Restore(args) => cmd_op_restore(ui, command, args),
         OperationCommand::Undo(args) => cmd_op_undo(ui, command, args),
+        OperationCommand::Sync(args) => cmd_op_sync(ui, command, args),
     }
 }
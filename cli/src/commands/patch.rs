@@ -0,0 +1,162 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::io::{self, Read as _, Write as _};
+
+use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
+use tracing::instrument;
+
+use crate::cli_util::{
+    join_message_paragraphs, user_error, user_error_with_hint, user_error_with_message,
+    CommandError, CommandHelper, RevisionArg,
+};
+use crate::patch_util::{apply_patches, parse_patch};
+use crate::ui::Ui;
+
+/// Work with patches in the unified diff format
+#[derive(clap::Subcommand, Clone, Debug)]
+pub(crate) enum PatchCommand {
+    Apply(PatchApplyArgs),
+}
+
+/// Apply a patch to the working-copy commit or as a new commit
+///
+/// The patch can be in Git's format, like the output of `jj diff --git`, or in
+/// the plain unified format. If any of its changes don't apply, nothing is
+/// changed.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct PatchApplyArgs {
+    /// The patch file to apply (stdin if not given)
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    path: Option<String>,
+    /// Create a new commit with the patch on top of this revision, instead of
+    /// applying it to the working-copy commit
+    #[arg(long, short)]
+    revision: Option<RevisionArg>,
+    /// The description of the new commit created with `--revision`
+    #[arg(long = "message", short, value_name = "MESSAGE", requires = "revision")]
+    message_paragraphs: Vec<String>,
+    /// Number of leading path components to remove from the paths in the patch
+    #[arg(long, short = 'p', default_value_t = 1, value_name = "NUM")]
+    strip: usize,
+    /// Number of context lines around a change that may not match
+    ///
+    /// Context lines are ignored from the outside in, as with the `patch` tool.
+    #[arg(long, default_value_t = 0, value_name = "LINES")]
+    fuzz: usize,
+    /// Apply the reverse of the patch
+    #[arg(long)]
+    reverse: bool,
+}
+
+pub(crate) fn cmd_patch(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &PatchCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        PatchCommand::Apply(sub_args) => cmd_patch_apply(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_patch_apply(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PatchApplyArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let text = match &args.path {
+        Some(path) => {
+            let path = command.cwd().join(path);
+            fs::read(&path).map_err(|err| {
+                user_error_with_message(format!("Failed to read {}", path.display()), err)
+            })?
+        }
+        None => {
+            let mut text = vec![];
+            io::stdin().read_to_end(&mut text)?;
+            text
+        }
+    };
+    let mut file_patches = parse_patch(&text, args.strip)?;
+    if file_patches.is_empty() {
+        return Err(user_error("No changes found in the patch"));
+    }
+    if args.reverse {
+        file_patches = file_patches.iter().map(|patch| patch.reversed()).collect();
+    }
+
+    let (base_commit, create_commit) = match &args.revision {
+        Some(revision) => (workspace_command.resolve_single_rev(revision, ui)?, true),
+        None => {
+            let wc_commit_id = workspace_command
+                .get_wc_commit_id()
+                .ok_or_else(|| user_error("This command requires a working copy"))?;
+            let commit = workspace_command.repo().store().get_commit(wc_commit_id)?;
+            workspace_command.check_rewritable([&commit])?;
+            (commit, false)
+        }
+    };
+    let base_tree = base_commit.tree()?;
+    let applied = apply_patches(
+        workspace_command.repo().store(),
+        &base_tree,
+        &file_patches,
+        args.fuzz,
+    )?;
+    if !applied.rejected.is_empty() {
+        for rejected in &applied.rejected {
+            writeln!(
+                ui.warning(),
+                "{}: {}",
+                workspace_command.format_file_path(rejected.patch.path()),
+                rejected.reason
+            )?;
+        }
+        return Err(user_error_with_hint(
+            "Failed to apply the patch",
+            "Check that the patch is for this revision. Use --fuzz to allow context lines to \
+             differ.",
+        ));
+    }
+
+    let mut tx = workspace_command.start_transaction()?;
+    if create_commit {
+        let new_commit = tx
+            .mut_repo()
+            .new_commit(
+                command.settings(),
+                vec![base_commit.id().clone()],
+                applied.tree_id,
+            )
+            .set_description(join_message_paragraphs(&args.message_paragraphs))
+            .write()?;
+        write!(ui.stderr(), "Created new commit ")?;
+        tx.write_commit_summary(ui.stderr_formatter().as_mut(), &new_commit)?;
+        writeln!(ui.stderr())?;
+    } else {
+        tx.mut_repo()
+            .rewrite_commit(command.settings(), &base_commit)
+            .set_tree_id(applied.tree_id)
+            .write()?;
+    }
+    tx.finish(
+        ui,
+        format!("apply patch to commit {}", base_commit.id().hex()),
+    )?;
+    Ok(())
+}
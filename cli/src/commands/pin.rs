@@ -0,0 +1,187 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use clap::builder::NonEmptyStringValueParser;
+use itertools::Itertools;
+use jj_lib::object_id::ObjectId;
+use jj_lib::op_store::RefTarget;
+use jj_lib::repo::Repo;
+use jj_lib::str_util::StringPattern;
+use tracing::instrument;
+
+use crate::cli_util::{parse_string_pattern, user_error, CommandError, CommandHelper, RevisionArg};
+use crate::ui::Ui;
+
+/// Manage pins
+///
+/// Pins are local names for revisions, for example to keep track of
+/// interesting revisions during a long investigation. Unlike branches, they
+/// aren't exported to Git, and aren't moved when the revisions are rewritten.
+/// Use `pin(NAME)` to refer to a pinned revision in a revset.
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum PinCommand {
+    Add(PinAddArgs),
+    #[command(visible_alias("l"))]
+    List(PinListArgs),
+    Remove(PinRemoveArgs),
+}
+
+/// Pin a revision under a name
+///
+/// If the name is already used, the pin is moved to the revision.
+#[derive(clap::Args, Clone, Debug)]
+pub struct PinAddArgs {
+    /// The revision to pin
+    #[arg(long, short, default_value = "@")]
+    revision: RevisionArg,
+    /// The name of the pin
+    #[arg(value_parser = NonEmptyStringValueParser::new())]
+    name: String,
+}
+
+/// List pins and their revisions
+#[derive(clap::Args, Clone, Debug)]
+pub struct PinListArgs {
+    /// Show pins whose name matches
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` prefix to
+    /// select pins by wildcard pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(value_parser = parse_string_pattern)]
+    names: Vec<StringPattern>,
+}
+
+/// Remove pins
+///
+/// The pinned revisions aren't affected.
+#[derive(clap::Args, Clone, Debug)]
+pub struct PinRemoveArgs {
+    /// The pins to remove
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` prefix to
+    /// select pins by wildcard pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(required = true, value_parser = parse_string_pattern)]
+    names: Vec<StringPattern>,
+}
+
+pub fn cmd_pin(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &PinCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        PinCommand::Add(sub_args) => cmd_pin_add(ui, command, sub_args),
+        PinCommand::List(sub_args) => cmd_pin_list(ui, command, sub_args),
+        PinCommand::Remove(sub_args) => cmd_pin_remove(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_pin_add(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PinAddArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let target_commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
+    let mut tx = workspace_command.start_transaction()?;
+    tx.mut_repo()
+        .set_pin_target(&args.name, RefTarget::normal(target_commit.id().clone()));
+    tx.finish(
+        ui,
+        format!("pin commit {} as {}", target_commit.id().hex(), args.name),
+    )?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+fn cmd_pin_list(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PinListArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo();
+
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    let formatter = formatter.as_mut();
+    for (name, target) in repo.view().pins() {
+        if !args.names.is_empty() && !args.names.iter().any(|pattern| pattern.matches(name)) {
+            continue;
+        }
+        write!(formatter.labeled("pin"), "{name}")?;
+        if let Some(id) = target.as_normal() {
+            write!(formatter, ": ")?;
+            let commit = repo.store().get_commit(id)?;
+            workspace_command.write_commit_summary(formatter, &commit)?;
+            writeln!(formatter)?;
+        } else {
+            write!(formatter, " ")?;
+            write!(formatter.labeled("conflict"), "(conflicted)")?;
+            writeln!(formatter, ":")?;
+            for id in target.added_ids() {
+                let commit = repo.store().get_commit(id)?;
+                write!(formatter, "  + ")?;
+                workspace_command.write_commit_summary(formatter, &commit)?;
+                writeln!(formatter)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+fn cmd_pin_remove(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PinRemoveArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    let mut names = vec![];
+    let mut unmatched_patterns = vec![];
+    for pattern in &args.names {
+        let mut matches = pattern.filter_btree_map(view.pins()).peekable();
+        if matches.peek().is_none() {
+            unmatched_patterns.push(pattern);
+        }
+        names.extend(matches.map(|(name, _)| name.clone()));
+    }
+    match &unmatched_patterns[..] {
+        [] => {}
+        [pattern] if pattern.is_exact() => {
+            return Err(user_error(format!("No such pin: {pattern}")));
+        }
+        patterns => {
+            return Err(user_error(format!(
+                "No matching pins for patterns: {}",
+                patterns.iter().join(", ")
+            )));
+        }
+    }
+    let names = names.into_iter().unique().collect_vec();
+    let mut tx = workspace_command.start_transaction()?;
+    for name in &names {
+        tx.mut_repo().set_pin_target(name, RefTarget::absent());
+    }
+    tx.finish(ui, format!("remove pins {}", names.join(", ")))?;
+    if names.len() > 1 {
+        writeln!(ui.stderr(), "Removed {} pins.", names.len())?;
+    }
+    Ok(())
+}
@@ -111,7 +111,7 @@ pub(crate) fn cmd_prev(
     if edit {
         // The target must be rewritable if we're editing.
         workspace_command.check_rewritable([target])?;
-        let mut tx = workspace_command.start_transaction();
+        let mut tx = workspace_command.start_transaction()?;
         tx.edit(target)?;
         tx.finish(
             ui,
@@ -119,7 +119,7 @@ pub(crate) fn cmd_prev(
         )?;
         return Ok(());
     }
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     tx.check_out(target)?;
     tx.finish(ui, format!("prev: {current_short} -> {target_short}"))?;
     Ok(())
@@ -0,0 +1,105 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use itertools::Itertools as _;
+use jj_lib::repo::Repo as _;
+use jj_lib::rewrite::merge_commit_trees;
+use tracing::instrument;
+
+use crate::cli_util::{
+    short_change_hash, short_commit_hash, short_operation_hash, user_error, CommandError,
+    CommandHelper,
+};
+use crate::ui::Ui;
+
+/// Print a summary of the working-copy commit for shell prompts
+///
+/// This is meant to be called by shell prompts (e.g. starship or `PS1`) on
+/// every command, so it's kept fast: the working copy isn't snapshotted, so
+/// the summary reflects the last time a jj command snapshotted it.
+///
+/// Each line of the output is a `key=value` pair:
+///
+/// change_id, commit_id: The short ids of the working-copy commit.
+///
+/// description: The first line of its description.
+///
+/// branches: The local branches pointing to it, separated by spaces. A
+/// conflicted branch is followed by `??`.
+///
+/// conflict, divergent, empty: Whether it has conflicts, has a change id shared
+/// with other visible commits, or doesn't change any files (`true` or
+/// `false`).
+///
+/// operation_id: The short id of the current operation.
+#[derive(clap::Args, Clone, Debug)]
+#[command(verbatim_doc_comment)]
+pub(crate) struct PromptArgs {}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_prompt(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    PromptArgs {}: &PromptArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let repo = workspace_command.repo();
+    let Some(wc_commit_id) = workspace_command.get_wc_commit_id() else {
+        return Err(user_error("This workspace has no working-copy commit"));
+    };
+    let commit = repo.store().get_commit(wc_commit_id)?;
+    let description = commit.description().lines().next().unwrap_or_default();
+    let branches = repo
+        .view()
+        .local_branches()
+        .filter(|(_, target)| target.added_ids().contains(commit.id()))
+        .map(|(name, target)| {
+            if target.has_conflict() {
+                format!("{name}??")
+            } else {
+                name.to_owned()
+            }
+        })
+        .join(" ");
+    let divergent = repo
+        .resolve_change_id(commit.change_id())
+        .is_some_and(|commit_ids| commit_ids.len() > 1);
+    let empty = if let [parent] = &commit.parents()[..] {
+        parent.tree_id() == commit.tree_id()
+    } else {
+        let parent_tree = merge_commit_trees(repo.as_ref(), &commit.parents())?;
+        *commit.tree_id() == parent_tree.id()
+    };
+
+    let mut stdout = ui.stdout();
+    writeln!(
+        stdout,
+        "change_id={}",
+        short_change_hash(commit.change_id())
+    )?;
+    writeln!(stdout, "commit_id={}", short_commit_hash(commit.id()))?;
+    writeln!(stdout, "description={description}")?;
+    writeln!(stdout, "branches={branches}")?;
+    writeln!(stdout, "conflict={}", commit.has_conflict()?)?;
+    writeln!(stdout, "divergent={divergent}")?;
+    writeln!(stdout, "empty={empty}")?;
+    writeln!(
+        stdout,
+        "operation_id={}",
+        short_operation_hash(repo.op_id())
+    )?;
+    Ok(())
+}
@@ -12,25 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::sync::Arc;
 
 use clap::ArgGroup;
 use indexmap::IndexSet;
 use itertools::Itertools;
-use jj_lib::backend::CommitId;
+use jj_lib::backend::{BackendError, CommitId};
 use jj_lib::commit::Commit;
+use jj_lib::merged_tree::MergeStrategy;
 use jj_lib::object_id::ObjectId;
 use jj_lib::repo::{ReadonlyRepo, Repo};
+use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::revset::{RevsetExpression, RevsetIteratorExt};
-use jj_lib::rewrite::{rebase_commit, rebase_commit_with_options, EmptyBehaviour, RebaseOptions};
-use jj_lib::settings::UserSettings;
+use jj_lib::rewrite::{
+    merge_commit_trees, rebase_commit, rebase_commit_with_options, EmptyBehaviour, RebaseOptions,
+};
+use jj_lib::settings::{ConfigResultExt as _, UserSettings};
 use tracing::instrument;
 
 use crate::cli_util::{
-    self, resolve_multiple_nonempty_revsets_default_single, short_commit_hash, user_error,
-    CommandError, CommandHelper, RevisionArg, WorkspaceCommandHelper,
+    self, print_resolved_conflicts, resolve_multiple_nonempty_revsets_default_single,
+    short_commit_hash, user_error, CommandError, CommandHelper, MergeStrategyArg, RevisionArg,
+    WorkspaceCommandHelper,
 };
 use crate::ui::Ui;
 
@@ -116,9 +121,28 @@ use crate::ui::Ui;
 /// |/         |/
 /// J          J
 /// ```
+///
+/// Instead of `-d`, you can use `--insert-after` (`-A`) or `--insert-before`
+/// (`-B`) with `-s` or `-b` to insert the revisions between existing commits.
+/// The children of the `-A` revisions, or the `-B` revisions themselves, are
+/// rebased onto the rebased revisions. For example, `jj rebase -s M -A J` would
+/// transform your history like this:
+///
+/// ```text
+/// M          K'
+/// |          |
+/// | K   =>   M'
+/// |/         |
+/// J          J
+/// ```
 #[derive(clap::Args, Clone, Debug)]
 #[command(verbatim_doc_comment)]
 #[command(group(ArgGroup::new("to_rebase").args(&["branch", "source", "revision"])))]
+#[command(group(
+    ArgGroup::new("target")
+        .args(&["destination", "insert_after", "insert_before"])
+        .required(true)
+))]
 pub(crate) struct RebaseArgs {
     /// Rebase the whole branch relative to destination's ancestors (can be
     /// repeated)
@@ -151,15 +175,52 @@ pub(crate) struct RebaseArgs {
     revision: Option<RevisionArg>,
     /// The revision(s) to rebase onto (can be repeated to create a merge
     /// commit)
-    #[arg(long, short, required = true)]
+    #[arg(long, short)]
     destination: Vec<RevisionArg>,
+    /// The revision(s) to insert after (can be repeated to create a merge
+    /// commit)
+    ///
+    /// The rebased revisions are rebased onto these revisions, and the children
+    /// of these revisions are rebased onto the rebased revisions.
+    #[arg(
+        long,
+        short = 'A',
+        visible_alias = "after",
+        conflicts_with = "revision"
+    )]
+    insert_after: Vec<RevisionArg>,
+    /// The revision(s) to insert before (can be repeated to create a merge
+    /// commit)
+    ///
+    /// The rebased revisions are rebased onto the parents of these revisions,
+    /// and these revisions are rebased onto the rebased revisions.
+    #[arg(
+        long,
+        short = 'B',
+        visible_alias = "before",
+        conflicts_with = "revision"
+    )]
+    insert_before: Vec<RevisionArg>,
 
     /// If true, when rebasing would produce an empty commit, the commit is
     /// skipped.
+    /// Commits that were already empty are kept.
     /// Will never skip merge commits with multiple non-empty parents.
     /// Will never skip the working commit.
+    ///
+    /// This is the default if `rebase.skip-empty` is set.
     #[arg(long, conflicts_with = "revision")]
     skip_empty: bool,
+    /// Keep commits that become empty, even if `rebase.skip-empty` is set
+    #[arg(long, overrides_with = "skip_empty")]
+    keep_empty: bool,
+
+    /// Resolve the conflicts in the rebased revisions by taking one side
+    ///
+    /// "ours" is the destination, and "theirs" is the revision being rebased.
+    /// The files that were resolved are listed.
+    #[arg(long, value_enum, conflicts_with = "revision")]
+    strategy: Option<MergeStrategyArg>,
 
     /// Deprecated. Please prefix the revset with `all:` instead.
     #[arg(long, short = 'L', hide = true)]
@@ -179,16 +240,50 @@ Please use `jj rebase -d 'all:x|y'` instead of `jj rebase --allow-large-revsets
         ));
     }
 
+    let skip_empty = if args.skip_empty {
+        true
+    } else if args.keep_empty || args.revision.is_some() {
+        false
+    } else {
+        command
+            .settings()
+            .config()
+            .get_bool("rebase.skip-empty")
+            .optional()?
+            .unwrap_or(false)
+    };
     let rebase_options = RebaseOptions {
-        empty: match args.skip_empty {
-            true => EmptyBehaviour::AbandonAllEmpty,
+        empty: match skip_empty {
+            true => EmptyBehaviour::AbandonNewlyEmpty,
             false => EmptyBehaviour::Keep,
         },
+        strategy: args.strategy.map(MergeStrategy::from),
     };
     let mut workspace_command = command.workspace_helper(ui)?;
-    let new_parents = cli_util::resolve_all_revs(&workspace_command, ui, &args.destination)?
-        .into_iter()
-        .collect_vec();
+    let (new_parents, insertion) = if !args.insert_after.is_empty() {
+        let after_commits = cli_util::resolve_all_revs(&workspace_command, ui, &args.insert_after)?
+            .into_iter()
+            .collect_vec();
+        (after_commits.clone(), Some(Insertion::After(after_commits)))
+    } else if !args.insert_before.is_empty() {
+        let before_commits =
+            cli_util::resolve_all_revs(&workspace_command, ui, &args.insert_before)?
+                .into_iter()
+                .collect_vec();
+        workspace_command.check_rewritable(&before_commits)?;
+        let new_parents = before_commits
+            .iter()
+            .flat_map(|commit| commit.parents())
+            .filter(|parent| !before_commits.contains(parent))
+            .unique()
+            .collect_vec();
+        (new_parents, Some(Insertion::Before(before_commits)))
+    } else {
+        let new_parents = cli_util::resolve_all_revs(&workspace_command, ui, &args.destination)?
+            .into_iter()
+            .collect_vec();
+        (new_parents, None)
+    };
     if let Some(rev_str) = &args.revision {
         assert_eq!(
             // In principle, `-r --skip-empty` could mean to abandon the `-r`
@@ -201,9 +296,9 @@ Please use `jj rebase -d 'all:x|y'` instead of `jj rebase --allow-large-revsets
             // emptied. But it would also make sense for the descendants of the
             // `--before` commit to be abandoned if emptied. A commit can easily
             // be in both categories.
-            rebase_options.empty,
-            EmptyBehaviour::Keep,
-            "clap should forbid `-r --skip-empty`"
+            rebase_options,
+            RebaseOptions::default(),
+            "clap should forbid `-r --skip-empty` and `-r --strategy`"
         );
         rebase_revision(
             ui,
@@ -220,6 +315,7 @@ Please use `jj rebase -d 'all:x|y'` instead of `jj rebase --allow-large-revsets
             command.settings(),
             &mut workspace_command,
             &new_parents,
+            insertion.as_ref(),
             &source_commits,
             rebase_options,
         )?;
@@ -234,6 +330,7 @@ Please use `jj rebase -d 'all:x|y'` instead of `jj rebase --allow-large-revsets
             command.settings(),
             &mut workspace_command,
             &new_parents,
+            insertion.as_ref(),
             &branch_commits,
             rebase_options,
         )?;
@@ -241,11 +338,20 @@ Please use `jj rebase -d 'all:x|y'` instead of `jj rebase --allow-large-revsets
     Ok(())
 }
 
+/// Existing commits to insert the rebased revisions next to.
+enum Insertion {
+    /// Insert between these commits and their children.
+    After(Vec<Commit>),
+    /// Insert between these commits and their parents.
+    Before(Vec<Commit>),
+}
+
 fn rebase_branch(
     ui: &mut Ui,
     settings: &UserSettings,
     workspace_command: &mut WorkspaceCommandHelper,
     new_parents: &[Commit],
+    insertion: Option<&Insertion>,
     branch_commits: &IndexSet<Commit>,
     rebase_options: RebaseOptions,
 ) -> Result<(), CommandError> {
@@ -271,6 +377,7 @@ fn rebase_branch(
         settings,
         workspace_command,
         new_parents,
+        insertion,
         &root_commits,
         rebase_options,
     )
@@ -281,29 +388,124 @@ fn rebase_descendants(
     settings: &UserSettings,
     workspace_command: &mut WorkspaceCommandHelper,
     new_parents: &[Commit],
+    insertion: Option<&Insertion>,
     old_commits: &IndexSet<Commit>,
     rebase_options: RebaseOptions,
 ) -> Result<(), CommandError> {
     workspace_command.check_rewritable(old_commits)?;
+    let (new_children, old_head_ids) = match insertion {
+        Some(insertion) => find_insertion_children(workspace_command, insertion, old_commits)?,
+        None => (vec![], vec![]),
+    };
     for old_commit in old_commits.iter() {
         check_rebase_destinations(workspace_command.repo(), new_parents, old_commit)?;
     }
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     // `rebase_descendants` takes care of sorting in reverse topological order, so
     // no need to do it here.
+    let strategy_options = RebaseOptions {
+        strategy: rebase_options.strategy,
+        ..Default::default()
+    };
+    let mut rebased_ids = HashMap::new();
     for old_commit in old_commits {
-        rebase_commit_with_options(
+        let new_commit = rebase_commit_with_options(
             settings,
             tx.mut_repo(),
             old_commit,
             new_parents,
             &rebase_options,
         )?;
+        rebased_ids.insert(old_commit.id().clone(), new_commit.id().clone());
+    }
+    rebased_ids.extend(
+        tx.mut_repo()
+            .rebase_descendants_with_options_return_map(settings, rebase_options.clone())?,
+    );
+    // Abandoned commits are mapped to their parents, which have other change ids
+    let mut abandoned_commits = vec![];
+    for (old_id, new_id) in &rebased_ids {
+        let old_commit = tx.base_repo().store().get_commit(old_id)?;
+        let new_commit = tx.repo().store().get_commit(new_id)?;
+        if old_commit.change_id() != new_commit.change_id() {
+            abandoned_commits.push(old_commit);
+        }
+    }
+    if let Some(insertion) = insertion {
+        // Put the children on top of the rebased revisions
+        let new_head_ids = old_head_ids
+            .iter()
+            .map(|id| rebased_ids.get(id).unwrap_or(id).clone())
+            .unique()
+            .collect_vec();
+        for child in &new_children {
+            let new_parent_ids = match insertion {
+                Insertion::After(after_commits) => child
+                    .parent_ids()
+                    .iter()
+                    .flat_map(|id| {
+                        if after_commits.iter().any(|commit| commit.id() == id) {
+                            new_head_ids.clone()
+                        } else {
+                            vec![id.clone()]
+                        }
+                    })
+                    .unique()
+                    .collect_vec(),
+                Insertion::Before(_) => new_head_ids.clone(),
+            };
+            let new_parents: Vec<_> = new_parent_ids
+                .iter()
+                .map(|id| tx.repo().store().get_commit(id))
+                .try_collect()?;
+            let new_child = rebase_commit_with_options(
+                settings,
+                tx.mut_repo(),
+                child,
+                &new_parents,
+                &strategy_options,
+            )?;
+            rebased_ids.insert(child.id().clone(), new_child.id().clone());
+        }
+        rebased_ids.extend(
+            tx.mut_repo()
+                .rebase_descendants_with_options_return_map(settings, strategy_options)?,
+        );
+    }
+    writeln!(
+        ui.stderr(),
+        "Rebased {} commits",
+        rebased_ids.len() - abandoned_commits.len()
+    )?;
+    if !abandoned_commits.is_empty() {
+        writeln!(
+            ui.stderr(),
+            "Abandoned {} commits that became empty:",
+            abandoned_commits.len()
+        )?;
+        abandoned_commits.sort_by_key(|commit| commit.committer().timestamp.clone());
+        for commit in abandoned_commits.iter().rev() {
+            write!(ui.stderr(), "  ")?;
+            tx.write_commit_summary(ui.stderr_formatter().as_mut(), commit)?;
+            writeln!(ui.stderr())?;
+        }
+    }
+    if rebase_options.strategy.is_some() {
+        let mut rebased_commits: Vec<(Commit, Commit)> = rebased_ids
+            .iter()
+            .map(|(old_id, new_id)| {
+                let old_commit = tx.base_repo().store().get_commit(old_id)?;
+                let new_commit = tx.repo().store().get_commit(new_id)?;
+                Ok::<_, BackendError>((old_commit, new_commit))
+            })
+            .filter_ok(|(old_commit, new_commit)| old_commit.change_id() == new_commit.change_id())
+            .try_collect()?;
+        rebased_commits.sort_by_key(|(old_commit, _)| old_commit.committer().timestamp.clone());
+        for (old_commit, new_commit) in rebased_commits.iter().rev() {
+            let paths = find_resolved_conflicts(tx.repo(), old_commit, new_commit)?;
+            print_resolved_conflicts(ui, &tx, new_commit, &paths)?;
+        }
     }
-    let num_rebased = old_commits.len()
-        + tx.mut_repo()
-            .rebase_descendants_with_options(settings, rebase_options)?;
-    writeln!(ui.stderr(), "Rebased {num_rebased} commits")?;
     let tx_message = if old_commits.len() == 1 {
         format!(
             "rebase commit {} and descendants",
@@ -344,7 +546,7 @@ fn rebase_revision(
     debug_assert!(workspace_command.check_rewritable(&child_commits).is_ok());
 
     // First, rebase the children of `old_commit`.
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     let mut rebased_commit_ids = HashMap::new();
     for child_commit in &child_commits {
         let new_child_parent_ids: Vec<CommitId> = child_commit
@@ -444,6 +646,77 @@ fn rebase_revision(
     Ok(())
 }
 
+/// Returns the paths that conflicted when `old_commit` was rebased into
+/// `new_commit`, and so were resolved by the merge strategy.
+fn find_resolved_conflicts(
+    repo: &dyn Repo,
+    old_commit: &Commit,
+    new_commit: &Commit,
+) -> Result<Vec<RepoPathBuf>, CommandError> {
+    let old_base_tree = merge_commit_trees(repo, &old_commit.parents())?;
+    let new_base_tree = merge_commit_trees(repo, &new_commit.parents())?;
+    if old_base_tree.id() == new_base_tree.id() {
+        return Ok(vec![]);
+    }
+    let merged_tree = new_base_tree.merge(&old_base_tree, &old_commit.tree()?)?;
+    Ok(merged_tree.conflicts().map(|(path, _)| path).collect())
+}
+
+/// Returns the commits to rebase onto the rebased `old_commits` and their
+/// descendants, and the heads of those to rebase them onto.
+fn find_insertion_children(
+    workspace_command: &WorkspaceCommandHelper,
+    insertion: &Insertion,
+    old_commits: &IndexSet<Commit>,
+) -> Result<(Vec<Commit>, Vec<CommitId>), CommandError> {
+    let repo = workspace_command.repo();
+    let moved_expression = RevsetExpression::commits(
+        old_commits
+            .iter()
+            .map(|commit| commit.id().clone())
+            .collect(),
+    )
+    .descendants();
+    let moved_ids: HashSet<_> = moved_expression
+        .clone()
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .collect();
+    let head_ids = moved_expression
+        .heads()
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .collect_vec();
+    let new_children: Vec<Commit> = match insertion {
+        Insertion::After(after_commits) => RevsetExpression::commits(
+            after_commits
+                .iter()
+                .map(|commit| commit.id().clone())
+                .collect(),
+        )
+        .children()
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .filter(|id| !moved_ids.contains(id))
+        .commits(repo.store())
+        .try_collect()?,
+        Insertion::Before(before_commits) => {
+            if let Some(commit) = before_commits
+                .iter()
+                .find(|commit| moved_ids.contains(commit.id()))
+            {
+                return Err(user_error(format!(
+                    "Cannot insert revisions before {}, which is being rebased",
+                    short_commit_hash(commit.id())
+                )));
+            }
+            before_commits.clone()
+        }
+    };
+    workspace_command.check_rewritable(&new_children)?;
+    Ok((new_children, head_ids))
+}
+
 fn check_rebase_destinations(
     repo: &Arc<ReadonlyRepo>,
     new_parents: &[Commit],
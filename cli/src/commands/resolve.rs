@@ -12,17 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::io::Write;
 
-use itertools::Itertools;
-use jj_lib::backend::TreeValue;
-use jj_lib::merge::MergedTreeValue;
+use itertools::{Either, Itertools};
+use jj_lib::backend::{BackendResult, MergedTreeId, TreeValue};
+use jj_lib::commit::Commit;
+use jj_lib::merge::{Merge, MergedTreeValue};
+use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
 use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo as _;
 use jj_lib::repo_path::RepoPathBuf;
 use tracing::instrument;
 
-use crate::cli_util::{CommandError, CommandHelper, WorkspaceCommandHelper};
+use crate::cli_util::{user_error, CommandError, CommandHelper, WorkspaceCommandHelper};
+use crate::conflict_templater::{self, ConflictEntry};
 use crate::formatter::Formatter;
 use crate::ui::Ui;
 
@@ -31,6 +35,11 @@ use crate::ui::Ui;
 /// Only conflicts that can be resolved with a 3-way merge are supported. See
 /// docs for merge tool configuration instructions.
 ///
+/// A conflict between a file and a deleted file, a directory, or a symlink
+/// can't be resolved by merging the file contents. When run in a terminal,
+/// this command asks which side of such a conflict to keep. The conflicts
+/// between a modified and a deleted file can also be resolved with `--take`.
+///
 /// Note that conflicts can also be resolved without using this command. You may
 /// edit the conflict markers in the conflicted file directly with a text
 /// editor.
@@ -46,22 +55,61 @@ pub(crate) struct ResolveArgs {
     #[arg(long, short, default_value = "@")]
     revision: String,
     /// Instead of resolving one conflict, list all the conflicts
+    ///
+    /// Each conflict is listed with its number, which can be used instead of
+    /// its path to select it. Resolving a conflict doesn't change the numbers
+    /// of the others.
     // TODO: Also have a `--summary` option. `--list` currently acts like
     // `diff --summary`, but should be more verbose.
     #[arg(long, short)]
     list: bool,
+    /// Render each conflict listed by `--list` using the given template
+    ///
+    /// The keywords `number`, `path`, `sides`, `types`, and `sizes` are
+    /// available. `types` and `sizes` have one element per side of the
+    /// conflict. A type is one of `file`, `executable`, `symlink`, `directory`,
+    /// `git-submodule`, and `absent`. For the syntax, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/templates.md
+    #[arg(long, short = 'T', requires = "list")]
+    template: Option<String>,
     /// Do not print the list of remaining conflicts (if any) after resolving a
     /// conflict
     #[arg(long, short, conflicts_with = "list")]
     quiet: bool,
+    /// Resolve all the conflicts between a modified and a deleted file by
+    /// taking the given side
+    #[arg(long, value_enum, conflicts_with = "list")]
+    take: Option<TakeSide>,
+    /// Resolve all the conflicts one after the other, except the ones with
+    /// these numbers
+    #[arg(
+        long,
+        value_name = "NUMBERS",
+        value_delimiter = ',',
+        num_args = 0..,
+        conflicts_with_all = ["list", "paths"],
+    )]
+    all_except: Option<Vec<usize>>,
     /// Restrict to these paths when searching for a conflict to resolve. We
     /// will attempt to resolve the first conflict we can find. You can use
     /// the `--list` argument to find paths to use here.
+    ///
+    /// A number selects the conflict with that number in the `--list` output.
+    /// To select a file whose name is a number, prefix it with `./`.
     // TODO: Find the conflict we can resolve even if it's not the first one.
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
 }
 
+/// The side to take in a conflict between a modified and a deleted file.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TakeSide {
+    /// Delete the file
+    Deleted,
+    /// Keep the modified file
+    Modified,
+}
+
 #[instrument(skip_all)]
 pub(crate) fn cmd_resolve(
     ui: &mut Ui,
@@ -69,12 +117,37 @@ pub(crate) fn cmd_resolve(
     args: &ResolveArgs,
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
     let tree = commit.tree()?;
-    let conflicts = tree
-        .conflicts()
-        .filter(|path| matcher.matches(&path.0))
+    let all_conflicts = numbered_conflicts(&commit)?;
+    let (numbers, paths): (Vec<usize>, Vec<String>) =
+        args.paths
+            .iter()
+            .partition_map(|arg| match arg.parse::<usize>() {
+                Ok(number) => Either::Left(number),
+                Err(_) => Either::Right(arg.clone()),
+            });
+    for &number in numbers.iter().chain(args.all_except.iter().flatten()) {
+        if !all_conflicts.iter().any(|entry| entry.number == number) {
+            return Err(user_error(format!(
+                "There is no conflict number {number} at this revision"
+            )));
+        }
+    }
+    let matcher = workspace_command.matcher_from_values(&paths, &[&tree])?;
+    let conflicts = all_conflicts
+        .into_iter()
+        .filter(|entry| {
+            args.paths.is_empty()
+                || numbers.contains(&entry.number)
+                || (!paths.is_empty() && matcher.matches(&entry.path))
+        })
+        .filter(|entry| {
+            !args
+                .all_except
+                .as_ref()
+                .is_some_and(|excluded| excluded.contains(&entry.number))
+        })
         .collect_vec();
     if conflicts.is_empty() {
         return Err(CommandError::CliError(format!(
@@ -87,22 +160,61 @@ pub(crate) fn cmd_resolve(
         )));
     }
     if args.list {
-        return print_conflicted_paths(
+        if let Some(template_string) = &args.template {
+            let template = conflict_templater::parse(
+                workspace_command.repo().store(),
+                command.cwd(),
+                workspace_command.workspace_root(),
+                template_string,
+                workspace_command.template_aliases_map(),
+            )?;
+            let mut formatter = ui.stdout_formatter();
+            for entry in &conflicts {
+                template.format(entry, formatter.as_mut())?;
+            }
+            return Ok(());
+        }
+        return print_numbered_conflicts(
             &conflicts,
             ui.stdout_formatter().as_mut(),
             &workspace_command,
         );
     };
 
-    let (repo_path, _) = conflicts.first().unwrap();
     workspace_command.check_rewritable([&commit])?;
-    writeln!(
-        ui.stderr(),
-        "Resolving conflicts in: {}",
-        workspace_command.format_file_path(repo_path)
-    )?;
-    let mut tx = workspace_command.start_transaction();
-    let new_tree_id = tx.run_mergetool(ui, &tree, repo_path)?;
+    let mut tx = workspace_command.start_transaction()?;
+    let conflicts = conflicts
+        .into_iter()
+        .map(|entry| (entry.path, entry.value))
+        .collect_vec();
+    let new_tree_id = if let Some(take) = args.take {
+        take_modify_delete_sides(ui, tx.base_workspace_helper(), &tree, &conflicts, take)?
+    } else {
+        // Without `--all-except`, only the first conflict is resolved
+        let num_to_resolve = if args.all_except.is_some() {
+            conflicts.len()
+        } else {
+            1
+        };
+        let mut tree = tree;
+        for (repo_path, conflict) in conflicts.iter().take(num_to_resolve) {
+            writeln!(
+                ui.stderr(),
+                "Resolving conflicts in: {}",
+                tx.base_workspace_helper().format_file_path(repo_path)
+            )?;
+            let new_tree_id = match choose_tree_conflict_side(ui, conflict)? {
+                Some(value) => {
+                    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+                    tree_builder.set_or_remove(repo_path.clone(), Merge::resolved(value));
+                    tree_builder.write_tree(tree.store())?
+                }
+                None => tx.run_mergetool(ui, &tree, repo_path)?,
+            };
+            tree = tree.store().get_root_tree(&new_tree_id)?;
+        }
+        tree.id()
+    };
     let new_commit = tx
         .mut_repo()
         .rewrite_commit(command.settings(), &commit)
@@ -114,14 +226,13 @@ pub(crate) fn cmd_resolve(
     )?;
 
     if !args.quiet {
-        let new_tree = new_commit.tree()?;
-        let new_conflicts = new_tree.conflicts().collect_vec();
+        let new_conflicts = numbered_conflicts(&new_commit)?;
         if !new_conflicts.is_empty() {
             writeln!(
                 ui.stderr(),
                 "After this operation, some files at this revision still have conflicts:"
             )?;
-            print_conflicted_paths(
+            print_numbered_conflicts(
                 &new_conflicts,
                 ui.stderr_formatter().as_mut(),
                 &workspace_command,
@@ -131,93 +242,293 @@ pub(crate) fn cmd_resolve(
     Ok(())
 }
 
+/// Returns the conflicts in the commit, numbered from 1 in path order.
+///
+/// The conflicts are numbered among the conflicts of the oldest predecessor
+/// that had all of them, so the numbers don't change when some of the
+/// conflicts are resolved, by `jj resolve` or by editing the files.
+fn numbered_conflicts(commit: &Commit) -> BackendResult<Vec<ConflictEntry>> {
+    let conflicts = commit.tree()?.conflicts().collect_vec();
+    let mut original_paths = conflicts.iter().map(|(path, _)| path.clone()).collect_vec();
+    let mut commit = commit.clone();
+    'walk: loop {
+        for predecessor in commit.predecessors() {
+            if !predecessor.has_conflict()? {
+                continue;
+            }
+            let paths = predecessor
+                .tree()?
+                .conflicts()
+                .map(|(path, _)| path)
+                .collect_vec();
+            let path_set: HashSet<_> = paths.iter().collect();
+            if original_paths.iter().all(|path| path_set.contains(path)) {
+                original_paths = paths;
+                commit = predecessor;
+                continue 'walk;
+            }
+        }
+        break;
+    }
+    // Both lists are in path order, and `original_paths` contains all the paths
+    let paths: HashSet<_> = conflicts.iter().map(|(path, _)| path.clone()).collect();
+    let numbers = original_paths
+        .iter()
+        .positions(|path| paths.contains(path))
+        .map(|i| i + 1);
+    Ok(std::iter::zip(numbers, conflicts)
+        .map(|(number, (path, value))| ConflictEntry {
+            number,
+            path,
+            value,
+        })
+        .collect())
+}
+
+/// Returns the value of the modified side if the conflict is between a
+/// modified and a deleted file.
+fn modified_side(conflict: &MergedTreeValue) -> Option<&TreeValue> {
+    if conflict.num_sides() != 2 {
+        return None;
+    }
+    match conflict.adds().collect_vec()[..] {
+        [Some(value), None] | [None, Some(value)] => Some(value),
+        _ => None,
+    }
+}
+
+/// Whether the conflict is between two sides that aren't both files, so it
+/// can't be resolved by merging the file contents.
+fn is_tree_conflict(conflict: &MergedTreeValue) -> bool {
+    conflict.num_sides() == 2
+        && conflict
+            .adds()
+            .any(|term| !matches!(term, Some(TreeValue::File { .. })))
+}
+
+fn take_modify_delete_sides(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    tree: &MergedTree,
+    conflicts: &[(RepoPathBuf, MergedTreeValue)],
+    take: TakeSide,
+) -> Result<MergedTreeId, CommandError> {
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    let mut resolved_any = false;
+    for (repo_path, conflict) in conflicts {
+        let Some(modified) = modified_side(conflict) else {
+            continue;
+        };
+        let value = match take {
+            TakeSide::Deleted => None,
+            TakeSide::Modified => Some(modified.clone()),
+        };
+        writeln!(
+            ui.stderr(),
+            "Resolving conflicts in: {}",
+            workspace_command.format_file_path(repo_path)
+        )?;
+        tree_builder.set_or_remove(repo_path.clone(), Merge::resolved(value));
+        resolved_any = true;
+    }
+    if !resolved_any {
+        return Err(user_error(
+            "No conflicts between a modified and a deleted file found",
+        ));
+    }
+    Ok(tree_builder.write_tree(tree.store())?)
+}
+
+/// Asks which side to keep if the conflict can't be resolved by merging the
+/// file contents. Returns `None` if the merge tool should be used instead.
+fn choose_tree_conflict_side(
+    ui: &mut Ui,
+    conflict: &MergedTreeValue,
+) -> Result<Option<Option<TreeValue>>, CommandError> {
+    if !is_tree_conflict(conflict) || !Ui::can_prompt() {
+        return Ok(None);
+    }
+    let sides = conflict.adds().collect_vec();
+    let mut formatter = ui.stdout_formatter();
+    writeln!(
+        formatter,
+        "The conflict can't be merged, choose the side to keep:"
+    )?;
+    let mut choices: Vec<String> = Default::default();
+    for (i, side) in sides.iter().enumerate() {
+        writeln!(formatter, "{}: {}", i + 1, describe_side(side.as_ref()))?;
+        choices.push(format!("{}", i + 1));
+    }
+    // A deleted file is merged as an empty file by the merge tool
+    let mergeable = conflict.to_file_merge().is_some();
+    if mergeable {
+        writeln!(formatter, "m: merge the contents with the merge tool")?;
+        choices.push("m".to_string());
+    }
+    writeln!(formatter, "q: quit the prompt")?;
+    choices.push("q".to_string());
+    drop(formatter);
+
+    let choice = ui.prompt_choice("enter the side to keep", &choices, None)?;
+    match choice.as_str() {
+        "q" => Err(user_error("The conflict was left unresolved")),
+        "m" => Ok(None),
+        index => Ok(Some(sides[index.parse::<usize>().unwrap() - 1].clone())),
+    }
+}
+
+fn describe_side(side: Option<&TreeValue>) -> &'static str {
+    match side {
+        None => "deleted",
+        Some(TreeValue::File {
+            executable: false, ..
+        }) => "a file",
+        Some(TreeValue::File {
+            executable: true, ..
+        }) => "an executable file",
+        Some(TreeValue::Symlink(_)) => "a symlink",
+        Some(TreeValue::Tree(_)) => "a directory",
+        Some(TreeValue::GitSubmodule(_)) => "a git submodule",
+        Some(TreeValue::Conflict(_)) => "another conflict",
+    }
+}
+
 #[instrument(skip_all)]
 pub(crate) fn print_conflicted_paths(
     conflicts: &[(RepoPathBuf, MergedTreeValue)],
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
 ) -> Result<(), CommandError> {
-    let formatted_paths = conflicts
+    let formatted_paths = format_conflicted_paths(
+        conflicts.iter().map(|(path, _conflict)| path),
+        workspace_command,
+    );
+    for ((_, conflict), formatted_path) in std::iter::zip(conflicts.iter(), formatted_paths) {
+        write!(formatter, "{formatted_path} ",)?;
+        write_conflict_description(formatter, conflict)?;
+        writeln!(formatter)?;
+    }
+    Ok(())
+}
+
+/// Like `print_conflicted_paths()`, but also prints the number of each
+/// conflict.
+fn print_numbered_conflicts(
+    conflicts: &[ConflictEntry],
+    formatter: &mut dyn Formatter,
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<(), CommandError> {
+    let formatted_paths =
+        format_conflicted_paths(conflicts.iter().map(|entry| &entry.path), workspace_command);
+    let max_number = conflicts
         .iter()
-        .map(|(path, _conflict)| workspace_command.format_file_path(path))
+        .map(|entry| entry.number)
+        .max()
+        .unwrap_or(0);
+    let number_width = max_number.to_string().len();
+    for (entry, formatted_path) in std::iter::zip(conflicts.iter(), formatted_paths) {
+        write!(
+            formatter,
+            "{number:>number_width$}: {formatted_path} ",
+            number = entry.number
+        )?;
+        write_conflict_description(formatter, &entry.value)?;
+        writeln!(formatter)?;
+    }
+    Ok(())
+}
+
+/// Formats the paths, padded to the same width.
+fn format_conflicted_paths<'a>(
+    paths: impl Iterator<Item = &'a RepoPathBuf>,
+    workspace_command: &WorkspaceCommandHelper,
+) -> Vec<String> {
+    let formatted_paths = paths
+        .map(|path| workspace_command.format_file_path(path))
         .collect_vec();
     let max_path_len = formatted_paths.iter().map(|p| p.len()).max().unwrap_or(0);
-    let formatted_paths = formatted_paths
+    formatted_paths
         .into_iter()
-        .map(|p| format!("{:width$}", p, width = max_path_len.min(32) + 3));
+        .map(|p| format!("{:width$}", p, width = max_path_len.min(32) + 3))
+        .collect()
+}
 
-    for ((_, conflict), formatted_path) in std::iter::zip(conflicts.iter(), formatted_paths) {
-        let sides = conflict.num_sides();
-        let n_adds = conflict.adds().flatten().count();
-        let deletions = sides - n_adds;
-
-        let mut seen_objects = BTreeMap::new(); // Sort for consistency and easier testing
-        if deletions > 0 {
-            seen_objects.insert(
-                format!(
-                    // Starting with a number sorts this first
-                    "{deletions} deletion{}",
-                    if deletions > 1 { "s" } else { "" }
-                ),
-                "normal", // Deletions don't interfere with `jj resolve` or diff display
-            );
-        }
-        // TODO: We might decide it's OK for `jj resolve` to ignore special files in the
-        // `removes` of a conflict (see e.g. https://github.com/martinvonz/jj/pull/978). In
-        // that case, `conflict.removes` should be removed below.
-        for term in itertools::chain(conflict.removes(), conflict.adds()).flatten() {
-            seen_objects.insert(
-                match term {
-                    TreeValue::File {
-                        executable: false, ..
-                    } => continue,
-                    TreeValue::File {
-                        executable: true, ..
-                    } => "an executable",
-                    TreeValue::Symlink(_) => "a symlink",
-                    TreeValue::Tree(_) => "a directory",
-                    TreeValue::GitSubmodule(_) => "a git submodule",
-                    TreeValue::Conflict(_) => "another conflict (you found a bug!)",
-                }
-                .to_string(),
-                "difficult",
-            );
-        }
+fn write_conflict_description(
+    formatter: &mut dyn Formatter,
+    conflict: &MergedTreeValue,
+) -> Result<(), CommandError> {
+    let sides = conflict.num_sides();
+    let n_adds = conflict.adds().flatten().count();
+    let deletions = sides - n_adds;
+    let modify_delete = modified_side(conflict).is_some();
 
-        write!(formatter, "{formatted_path} ",)?;
-        formatter.with_label("conflict_description", |formatter| {
-            let print_pair = |formatter: &mut dyn Formatter, (text, label): &(String, &str)| {
-                formatter.with_label(label, |fmt| fmt.write_str(text))
-            };
-            print_pair(
-                formatter,
-                &(
-                    format!("{sides}-sided"),
-                    if sides > 2 { "difficult" } else { "normal" },
-                ),
-            )?;
-            formatter.write_str(" conflict")?;
-
-            if !seen_objects.is_empty() {
-                formatter.write_str(" including ")?;
-                let seen_objects = seen_objects.into_iter().collect_vec();
-                match &seen_objects[..] {
-                    [] => unreachable!(),
-                    [only] => print_pair(formatter, only)?,
-                    [first, middle @ .., last] => {
-                        print_pair(formatter, first)?;
-                        for pair in middle {
-                            formatter.write_str(", ")?;
-                            print_pair(formatter, pair)?;
-                        }
-                        formatter.write_str(" and ")?;
-                        print_pair(formatter, last)?;
-                    }
-                };
+    let mut seen_objects = BTreeMap::new(); // Sort for consistency and easier testing
+    if deletions > 0 && !modify_delete {
+        seen_objects.insert(
+            format!(
+                // Starting with a number sorts this first
+                "{deletions} deletion{}",
+                if deletions > 1 { "s" } else { "" }
+            ),
+            "normal", // Deletions don't interfere with `jj resolve` or diff display
+        );
+    }
+    // TODO: We might decide it's OK for `jj resolve` to ignore special files in the
+    // `removes` of a conflict (see e.g. https://github.com/martinvonz/jj/pull/978). In
+    // that case, `conflict.removes` should be removed below.
+    for term in itertools::chain(conflict.removes(), conflict.adds()).flatten() {
+        seen_objects.insert(
+            match term {
+                TreeValue::File {
+                    executable: false, ..
+                } => continue,
+                TreeValue::File {
+                    executable: true, ..
+                } => "an executable",
+                TreeValue::Symlink(_) => "a symlink",
+                TreeValue::Tree(_) => "a directory",
+                TreeValue::GitSubmodule(_) => "a git submodule",
+                TreeValue::Conflict(_) => "another conflict (you found a bug!)",
             }
-            Ok(())
-        })?;
-        writeln!(formatter)?;
+            .to_string(),
+            "difficult",
+        );
     }
+
+    formatter.with_label("conflict_description", |formatter| {
+        let print_pair = |formatter: &mut dyn Formatter, (text, label): &(String, &str)| {
+            formatter.with_label(label, |fmt| fmt.write_str(text))
+        };
+        print_pair(
+            formatter,
+            &(
+                format!("{sides}-sided"),
+                if sides > 2 { "difficult" } else { "normal" },
+            ),
+        )?;
+        if modify_delete {
+            formatter.write_str(" modify/delete")?;
+        }
+        formatter.write_str(" conflict")?;
+
+        if !seen_objects.is_empty() {
+            formatter.write_str(" including ")?;
+            let seen_objects = seen_objects.into_iter().collect_vec();
+            match &seen_objects[..] {
+                [] => unreachable!(),
+                [only] => print_pair(formatter, only)?,
+                [first, middle @ .., last] => {
+                    print_pair(formatter, first)?;
+                    for pair in middle {
+                        formatter.write_str(", ")?;
+                        print_pair(formatter, pair)?;
+                    }
+                    formatter.write_str(" and ")?;
+                    print_pair(formatter, last)?;
+                }
+            };
+        }
+        Ok(())
+    })?;
     Ok(())
 }
@@ -0,0 +1,240 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+
+use itertools::Itertools;
+use jj_lib::commit::Commit;
+use jj_lib::repo::Repo;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt};
+use jj_lib::rewrite::{merge_commit_trees, rebase_to_dest_parent};
+use tracing::instrument;
+
+use crate::cli_util::{
+    short_change_hash, short_commit_hash, user_error, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper, WorkspaceCommandTransaction,
+};
+use crate::description_util::edit_description;
+use crate::ui::Ui;
+
+/// Resolve a divergent change by keeping or merging its versions
+///
+/// A change becomes divergent when there are several visible commits with its
+/// change id, for example after it was rewritten in two workspaces or
+/// concurrently on two machines. This command lists the divergent versions
+/// and lets you pick the one to keep (`--keep`) or merge them into a single
+/// version (`--merge`). If neither is given, you'll be asked which to do.
+///
+/// The other versions are abandoned. Their descendants are rebased onto the
+/// resulting version, and branches pointing to them are moved to it.
+///
+/// When merging, the changes of the other versions are rebased onto the
+/// parents of the first version listed, and combined with its changes. If the
+/// versions have different descriptions, you will be asked for the combined
+/// description.
+#[derive(clap::Args, Clone, Debug)]
+#[command(alias = "evolve")]
+pub(crate) struct ResolveDivergenceArgs {
+    /// A revision of the divergent change
+    ///
+    /// You can pass the change id itself, even though it resolves to several
+    /// revisions.
+    #[arg(default_value = "@")]
+    revision: RevisionArg,
+    /// Keep this version of the change, and abandon the others
+    #[arg(long, value_name = "REVISION", conflicts_with = "merge")]
+    keep: Option<RevisionArg>,
+    /// Merge all versions of the change into one
+    #[arg(long)]
+    merge: bool,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_resolve_divergence(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &ResolveDivergenceArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let versions = resolve_divergent_versions(ui, &workspace_command, &args.revision)?;
+    workspace_command.check_rewritable(versions.iter())?;
+    let change_hash = short_change_hash(versions[0].change_id());
+    let repo = workspace_command.repo().clone();
+    for (version, other) in versions.iter().tuple_combinations() {
+        for (ancestor, descendant) in [(version, other), (other, version)] {
+            if repo.index().is_ancestor(ancestor.id(), descendant.id()) {
+                return Err(user_error(format!(
+                    "Commit {} is an ancestor of commit {}, so they can't be combined",
+                    short_commit_hash(ancestor.id()),
+                    short_commit_hash(descendant.id()),
+                )));
+            }
+        }
+    }
+
+    let keep = match &args.keep {
+        Some(revision) => {
+            let commit = workspace_command.resolve_single_rev(revision, ui)?;
+            if !versions.contains(&commit) {
+                return Err(user_error(format!(
+                    "Commit {} is not a version of change {change_hash}",
+                    short_commit_hash(commit.id()),
+                )));
+            }
+            Some(commit)
+        }
+        None if args.merge => None,
+        None => choose_version(ui, &workspace_command, &versions)?,
+    };
+
+    let mut tx = workspace_command.start_transaction()?;
+    let resolved = match keep {
+        Some(keep) => keep,
+        None => {
+            let first = &versions[0];
+            let base_tree = merge_commit_trees(tx.repo(), &first.parents())?;
+            let mut tree = first.tree()?;
+            for other in &versions[1..] {
+                let other_tree = rebase_to_dest_parent(tx.repo(), other, first)?;
+                tree = tree.merge(&base_tree, &other_tree)?;
+            }
+            let description = combine_version_descriptions(command, &tx, &versions)?;
+            tx.mut_repo()
+                .rewrite_commit(command.settings(), first)
+                .set_tree_id(tree.id())
+                .set_predecessors(versions.iter().map(|commit| commit.id().clone()).collect())
+                .set_description(description)
+                .write()?
+        }
+    };
+    for version in &versions {
+        if version.id() != resolved.id() {
+            tx.mut_repo()
+                .set_rewritten_commit(version.id().clone(), [resolved.id().clone()]);
+        }
+    }
+    let num_rebased = tx.mut_repo().rebase_descendants(command.settings())?;
+
+    write!(ui.stderr(), "Resolved divergent change to ")?;
+    tx.write_commit_summary(ui.stderr_formatter().as_mut(), &resolved)?;
+    writeln!(ui.stderr())?;
+    if num_rebased > 0 {
+        writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
+    }
+    tx.finish(ui, format!("resolve divergent change {}", change_hash))?;
+    Ok(())
+}
+
+/// Returns the visible commits of the change that `revision` resolves to,
+/// failing if the change isn't divergent.
+fn resolve_divergent_versions(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    revision: &str,
+) -> Result<Vec<Commit>, CommandError> {
+    let commits = workspace_command.resolve_revset(revision, ui)?;
+    let change_ids = commits
+        .iter()
+        .map(|commit| commit.change_id())
+        .unique()
+        .collect_vec();
+    let change_id = match change_ids[..] {
+        [change_id] => change_id.clone(),
+        [] => {
+            return Err(user_error(format!(
+                r#"Revset "{revision}" didn't resolve to any revisions"#
+            )));
+        }
+        _ => {
+            return Err(user_error(format!(
+                r#"Revset "{revision}" resolved to revisions of more than one change"#
+            )));
+        }
+    };
+    let repo = workspace_command.repo();
+    let commit_ids = repo.resolve_change_id(&change_id).unwrap_or_default();
+    let versions: Vec<Commit> = RevsetExpression::commits(commit_ids)
+        .intersection(&RevsetExpression::visible_heads().ancestors())
+        .evaluate_programmatic(repo.as_ref())?
+        .iter()
+        .commits(repo.store())
+        .try_collect()?;
+    if versions.len() < 2 {
+        return Err(user_error(format!(
+            "Change {} is not divergent",
+            short_change_hash(&change_id)
+        )));
+    }
+    Ok(versions)
+}
+
+/// Asks the user which version to keep. Returns `None` if they should be
+/// merged.
+fn choose_version(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    versions: &[Commit],
+) -> Result<Option<Commit>, CommandError> {
+    writeln!(
+        ui.stdout(),
+        "Change {} is divergent, choose the version to keep:",
+        short_change_hash(versions[0].change_id())
+    )?;
+    let mut formatter = ui.stdout_formatter();
+    let mut choices: Vec<String> = Default::default();
+    for (i, commit) in versions.iter().enumerate() {
+        write!(formatter, "{}: ", i + 1)?;
+        workspace_command.write_commit_summary(formatter.as_mut(), commit)?;
+        writeln!(formatter)?;
+        choices.push(format!("{}", i + 1));
+    }
+    writeln!(formatter, "m: merge all versions")?;
+    writeln!(formatter, "q: quit the prompt")?;
+    choices.push("m".to_string());
+    choices.push("q".to_string());
+    drop(formatter);
+
+    let choice = ui.prompt_choice("enter the version to keep", &choices, None)?;
+    match choice.as_str() {
+        "q" => Err(user_error("Divergent change was left unresolved")),
+        "m" => Ok(None),
+        index => Ok(Some(versions[index.parse::<usize>().unwrap() - 1].clone())),
+    }
+}
+
+fn combine_version_descriptions(
+    command: &CommandHelper,
+    tx: &WorkspaceCommandTransaction,
+    versions: &[Commit],
+) -> Result<String, CommandError> {
+    let descriptions = versions
+        .iter()
+        .map(|commit| commit.description())
+        .filter(|description| !description.is_empty())
+        .unique()
+        .collect_vec();
+    match descriptions[..] {
+        [] => Ok(String::new()),
+        [description] => Ok(description.to_owned()),
+        _ => {
+            let mut combined =
+                "JJ: Enter a description for the merged versions of the change.\n".to_string();
+            for (i, description) in descriptions.iter().enumerate() {
+                combined.push_str(&format!("JJ: Description from version {}:\n", i + 1));
+                combined.push_str(description);
+            }
+            edit_description(tx.base_repo(), &combined, command.settings())
+        }
+    }
+}
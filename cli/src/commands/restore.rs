@@ -19,6 +19,7 @@ use jj_lib::rewrite::{merge_commit_trees, restore_tree};
 use tracing::instrument;
 
 use crate::cli_util::{user_error, CommandError, CommandHelper, RevisionArg};
+use crate::tr;
 use crate::ui::Ui;
 
 /// Restore paths from another revision
@@ -96,13 +97,13 @@ pub(crate) fn cmd_restore(
     }
     workspace_command.check_rewritable([&to_commit])?;
 
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
     let to_tree = to_commit.tree()?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&from_tree, &to_tree])?;
     let new_tree_id = restore_tree(&from_tree, &to_tree, matcher.as_ref())?;
     if &new_tree_id == to_commit.tree_id() {
-        writeln!(ui.stderr(), "Nothing changed.")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
     } else {
-        let mut tx = workspace_command.start_transaction();
+        let mut tx = workspace_command.start_transaction()?;
         let mut_repo = tx.mut_repo();
         let new_commit = mut_repo
             .rewrite_commit(command.settings(), &to_commit)
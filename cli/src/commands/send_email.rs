@@ -0,0 +1,475 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use itertools::Itertools;
+use jj_lib::backend::{MillisSinceEpoch, Signature, Timestamp};
+use jj_lib::commit::Commit;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::repo::Repo;
+use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _};
+use lettre::address::Envelope;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport as _};
+use tracing::instrument;
+
+use super::format_patch::{
+    encode_display_name, encode_word, format_date, subject_prefix, write_mime_headers, Patch,
+};
+use crate::cli_util::{
+    short_commit_hash, user_error, user_error_with_hint, user_error_with_message, CommandError,
+    CommandHelper, RevisionArg, WorkspaceCommandHelper,
+};
+use crate::description_util::edit_description;
+use crate::diff_util::{self, LineDiffOptions};
+use crate::formatter::PlainTextFormatter;
+use crate::ui::Ui;
+
+/// Send revisions as patches by email
+///
+/// Each revision is sent as a separate email, formatted like by `jj
+/// format-patch`. The emails are threaded as replies to the first one (the
+/// cover letter if there is one). Merge revisions are skipped.
+///
+/// The SMTP server, the sender, and the default recipients are set in the
+/// `send-email` config table.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct SendEmailArgs {
+    /// The revisions to send
+    #[arg(long, short, default_value = "@")]
+    revisions: RevisionArg,
+    /// Addresses to send the emails to, in addition to `send-email.to`
+    #[arg(long, value_name = "ADDRESS")]
+    to: Vec<String>,
+    /// Addresses to send copies to, in addition to `send-email.cc`
+    #[arg(long, value_name = "ADDRESS")]
+    cc: Vec<String>,
+    /// Write a cover letter for the patches in an editor and send it first
+    ///
+    /// A short log of the patches and a summary of the changes are added to
+    /// the text.
+    #[arg(long)]
+    cover_letter: bool,
+    /// Print the emails instead of sending them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SmtpEncryption {
+    /// TLS from the start of the connection (usually on port 465)
+    Tls,
+    /// Upgrade to TLS with the STARTTLS command (usually on port 587)
+    #[default]
+    Starttls,
+    /// No encryption (usually on port 25)
+    None,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct SendEmailConfig {
+    from: Option<String>,
+    to: Vec<String>,
+    cc: Vec<String>,
+    smtp_server: Option<String>,
+    smtp_server_port: Option<u16>,
+    smtp_encryption: SmtpEncryption,
+    smtp_user: Option<String>,
+    smtp_pass: Option<String>,
+}
+
+/// An email ready to be sent.
+struct Email {
+    subject: String,
+    message: Vec<u8>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_send_email(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &SendEmailArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let config: SendEmailConfig = command
+        .settings()
+        .config()
+        .get("send-email")
+        .optional()?
+        .unwrap_or_default();
+    let mut commits = workspace_command.resolve_revset(&args.revisions, ui)?;
+    // Oldest first
+    commits.reverse();
+    let root_commit_id = workspace_command.repo().store().root_commit_id();
+    if commits.iter().any(|commit| commit.id() == root_commit_id) {
+        return Err(user_error("Cannot send the root commit as a patch"));
+    }
+    let (merges, commits): (Vec<_>, Vec<_>) = commits
+        .into_iter()
+        .partition(|commit| commit.parent_ids().len() > 1);
+    for commit in &merges {
+        writeln!(
+            ui.warning(),
+            "Skipping merge commit {}",
+            short_commit_hash(commit.id())
+        )?;
+    }
+    if commits.is_empty() {
+        writeln!(ui.stderr(), "No revisions to send")?;
+        return Ok(());
+    }
+
+    let settings = command.settings();
+    let sender = match &config.from {
+        Some(from) => parse_mailbox(from)?,
+        None if settings.user_email().is_empty() => {
+            return Err(user_error_with_hint(
+                "No sender address configured",
+                "Set send-email.from or user.email in the config.",
+            ));
+        }
+        None if settings.user_name().is_empty() => parse_mailbox(&settings.user_email())?,
+        None => parse_mailbox(&format!(
+            "{} <{}>",
+            settings.user_name(),
+            settings.user_email()
+        ))?,
+    };
+    let to = config
+        .to
+        .iter()
+        .chain(&args.to)
+        .map(|address| parse_mailbox(address))
+        .try_collect()?;
+    let cc = config
+        .cc
+        .iter()
+        .chain(&args.cc)
+        .map(|address| parse_mailbox(address))
+        .try_collect()?;
+    let headers = HeaderWriter {
+        sender,
+        to,
+        cc,
+        start_time: settings.signature().timestamp,
+    };
+    if headers.to.is_empty() {
+        return Err(user_error_with_hint(
+            "No recipients for the emails",
+            "Use --to or set send-email.to in the config.",
+        ));
+    }
+
+    let mut emails = vec![];
+    let numbered = args.cover_letter || commits.len() > 1;
+    if args.cover_letter {
+        let (subject, body) = cover_letter(&workspace_command, &commits)?;
+        let subject = format!("{} {subject}", subject_prefix(Some((0, commits.len()))));
+        let non_ascii = !body.is_ascii();
+        let message = headers.message(0, &subject, body.into_bytes(), non_ascii);
+        emails.push(Email { subject, message });
+    }
+    for (i, commit) in commits.iter().enumerate() {
+        let patch = Patch::new(
+            &workspace_command,
+            commit,
+            numbered.then_some((i + 1, commits.len())),
+        )?;
+        let mut body = vec![];
+        let mut non_ascii = patch.non_ascii;
+        // The author is given in the first line of the body if they're not
+        // the sender, so it's kept when the patch is applied
+        if !headers.is_sender(&patch.author) {
+            let author = &patch.author;
+            writeln!(body, "From: {} <{}>\n", author.name, author.email)?;
+            non_ascii |= !patch.author.name.is_ascii();
+        }
+        body.extend_from_slice(&patch.body);
+        let message = headers.message(emails.len(), &patch.subject, body, non_ascii);
+        emails.push(Email {
+            subject: patch.subject,
+            message,
+        });
+    }
+
+    if args.dry_run {
+        for email in &emails {
+            ui.stdout().write_all(&email.message)?;
+            writeln!(ui.stdout())?;
+        }
+        return Ok(());
+    }
+    let recipients = headers
+        .to
+        .iter()
+        .chain(&headers.cc)
+        .map(|mailbox| mailbox.email.clone())
+        .collect_vec();
+    let envelope = Envelope::new(Some(headers.sender.email.clone()), recipients)
+        .map_err(|err| user_error_with_message("Invalid recipients", err))?;
+    let mut sender = EmailSender::new(ui, &config)?;
+    for email in &emails {
+        sender.send(&envelope, &email.message)?;
+        writeln!(ui.stderr(), "Sent {}", email.subject)?;
+    }
+    Ok(())
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, CommandError> {
+    address
+        .trim()
+        .parse()
+        .map_err(|err| user_error_with_message(format!("Invalid email address: {address}"), err))
+}
+
+fn format_mailbox(mailbox: &Mailbox) -> String {
+    match &mailbox.name {
+        Some(name) => format!("{} <{}>", encode_display_name(name), mailbox.email),
+        None => mailbox.email.to_string(),
+    }
+}
+
+/// Writes the headers of the emails of a series.
+struct HeaderWriter {
+    sender: Mailbox,
+    to: Vec<Mailbox>,
+    cc: Vec<Mailbox>,
+    start_time: Timestamp,
+}
+
+impl HeaderWriter {
+    /// Returns the id of the `index`th email of the series. Emails after the
+    /// first one are replies to it.
+    fn message_id(&self, index: usize) -> String {
+        format!(
+            "<jj.{}.{index}.{}>",
+            self.start_time.timestamp.0, self.sender.email
+        )
+    }
+
+    fn is_sender(&self, signature: &Signature) -> bool {
+        self.sender.email.to_string() == signature.email
+            && self.sender.name.as_ref() == Some(&signature.name)
+    }
+
+    /// Returns the `index`th email of the series. The charset is declared if
+    /// the body has `non_ascii` text.
+    fn message(&self, index: usize, subject: &str, body: Vec<u8>, non_ascii: bool) -> Vec<u8> {
+        // Space the dates a second apart, so the emails are sorted by date in
+        // the order they're sent
+        let date = Timestamp {
+            timestamp: MillisSinceEpoch(self.start_time.timestamp.0 + 1000 * index as i64),
+            tz_offset: self.start_time.tz_offset,
+        };
+        let mut headers = String::new();
+        writeln!(headers, "From: {}", format_mailbox(&self.sender)).unwrap();
+        writeln!(
+            headers,
+            "To: {}",
+            self.to.iter().map(format_mailbox).join(", ")
+        )
+        .unwrap();
+        if !self.cc.is_empty() {
+            writeln!(
+                headers,
+                "Cc: {}",
+                self.cc.iter().map(format_mailbox).join(", ")
+            )
+            .unwrap();
+        }
+        writeln!(headers, "Subject: {}", encode_word(subject)).unwrap();
+        writeln!(headers, "Date: {}", format_date(&date)).unwrap();
+        writeln!(headers, "Message-Id: {}", self.message_id(index)).unwrap();
+        if index > 0 {
+            writeln!(headers, "In-Reply-To: {}", self.message_id(0)).unwrap();
+            writeln!(headers, "References: {}", self.message_id(0)).unwrap();
+        }
+        if non_ascii {
+            write_mime_headers(&mut headers);
+        }
+        headers.push('\n');
+        let mut message = headers.into_bytes();
+        message.extend_from_slice(&body);
+        message
+    }
+}
+
+/// Returns the subject and body of the cover letter for the `commits`, with
+/// the text written by the user.
+fn cover_letter(
+    workspace_command: &WorkspaceCommandHelper,
+    commits: &[Commit],
+) -> Result<(String, String), CommandError> {
+    let mut template = "\n\n".to_owned();
+    writeln!(
+        template,
+        "JJ: Enter the subject of the cover letter on the first line, followed"
+    )
+    .unwrap();
+    writeln!(template, "JJ: by a description of the patches:").unwrap();
+    for commit in commits {
+        writeln!(template, "JJ:   {}", first_line(commit.description())).unwrap();
+    }
+    let text = edit_description(
+        workspace_command.repo(),
+        template.trim_end(),
+        workspace_command.settings(),
+    )?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(user_error("Aborted because the cover letter is empty"));
+    }
+    let (subject, description) = text.split_once('\n').unwrap_or((text, ""));
+    let mut body = String::new();
+    let description = description.trim();
+    if !description.is_empty() {
+        writeln!(body, "{description}\n").unwrap();
+    }
+    body.push_str(&short_log(commits));
+
+    // The combined diff is only meaningful if the commits are a linear
+    // series, like they usually are
+    let repo = workspace_command.repo();
+    let from_tree = merge_commit_trees(repo.as_ref(), &commits[0].parents())?;
+    let to_tree = commits[commits.len() - 1].tree()?;
+    let mut stat = vec![];
+    diff_util::show_diff_stat(
+        &mut PlainTextFormatter::new(&mut stat),
+        workspace_command,
+        from_tree.diff_stream(&to_tree, &EverythingMatcher),
+        &LineDiffOptions::default(),
+        72,
+    )?;
+    body.push_str(&String::from_utf8_lossy(&stat));
+    Ok((subject.trim().to_owned(), body))
+}
+
+/// Lists the subjects of the `commits` by author, like `git shortlog`.
+fn short_log(commits: &[Commit]) -> String {
+    let by_author = commits
+        .iter()
+        .map(|commit| (&commit.author().name, commit))
+        .into_group_map();
+    let mut log = String::new();
+    for (name, commits) in by_author.iter().sorted_by_key(|(name, _)| *name) {
+        writeln!(log, "{name} ({}):", commits.len()).unwrap();
+        for commit in commits {
+            writeln!(log, "  {}", first_line(commit.description())).unwrap();
+        }
+        log.push('\n');
+    }
+    log
+}
+
+fn first_line(description: &str) -> &str {
+    description.lines().next().unwrap_or("")
+}
+
+/// Sends emails through an SMTP server, or a sendmail-like program if the
+/// server is an absolute path.
+enum EmailSender {
+    Smtp(SmtpTransport),
+    Program(String),
+}
+
+impl EmailSender {
+    fn new(ui: &mut Ui, config: &SendEmailConfig) -> Result<Self, CommandError> {
+        let Some(server) = &config.smtp_server else {
+            return Err(user_error_with_hint(
+                "No SMTP server configured",
+                "Set send-email.smtp-server in the config.",
+            ));
+        };
+        let server = &expand_config_path(server);
+        if server.starts_with('/') {
+            return Ok(EmailSender::Program(server.clone()));
+        }
+        let smtp_error =
+            |err| user_error_with_message(format!("Failed to connect to {server}"), err);
+        let mut builder = match config.smtp_encryption {
+            SmtpEncryption::Tls => SmtpTransport::relay(server).map_err(smtp_error)?,
+            SmtpEncryption::Starttls => {
+                SmtpTransport::starttls_relay(server).map_err(smtp_error)?
+            }
+            SmtpEncryption::None => SmtpTransport::builder_dangerous(server),
+        };
+        if let Some(port) = config.smtp_server_port {
+            builder = builder.port(port);
+        }
+        if let Some(user) = &config.smtp_user {
+            let password = match &config.smtp_pass {
+                Some(password) => password.clone(),
+                None => ui.prompt_password(&format!("Password for {user}@{server}"))?,
+            };
+            builder = builder.credentials(Credentials::new(user.clone(), password));
+        }
+        Ok(EmailSender::Smtp(builder.build()))
+    }
+
+    fn send(&mut self, envelope: &Envelope, message: &[u8]) -> Result<(), CommandError> {
+        match self {
+            EmailSender::Smtp(transport) => {
+                transport
+                    .send_raw(envelope, &to_crlf(message))
+                    .map_err(|err| user_error_with_message("Failed to send email", err))?;
+            }
+            EmailSender::Program(program) => {
+                let mut child = Command::new(&*program)
+                    .arg("-i")
+                    .args(envelope.to().iter().map(|address| address.to_string()))
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|err| {
+                        user_error_with_message(format!("Failed to run {program}"), err)
+                    })?;
+                child.stdin.take().unwrap().write_all(message)?;
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(user_error(format!("{program} failed with {status}")));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts the line endings to CRLF, as required by SMTP.
+fn to_crlf(message: &[u8]) -> Vec<u8> {
+    let mut converted = Vec::with_capacity(message.len());
+    for (i, &b) in message.iter().enumerate() {
+        if b == b'\n' && (i == 0 || message[i - 1] != b'\r') {
+            converted.push(b'\r');
+        }
+        converted.push(b);
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_crlf() {
+        assert_eq!(to_crlf(b"a\nb\r\n\n"), b"a\r\nb\r\n\r\n");
+        assert_eq!(to_crlf(b"\n.\n"), b"\r\n.\r\n");
+    }
+}
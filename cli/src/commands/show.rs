@@ -16,6 +16,7 @@ use jj_lib::matchers::EverythingMatcher;
 use tracing::instrument;
 
 use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::commands::log::with_signatures;
 use crate::diff_util::{self, DiffFormatArgs};
 use crate::ui::Ui;
 
@@ -28,6 +29,9 @@ pub(crate) struct ShowArgs {
     /// Ignored (but lets you pass `-r` for consistency with other commands)
     #[arg(short = 'r', hide = true)]
     unused_revision: bool,
+    /// Verify the commit signature and show the result
+    #[arg(long)]
+    show_signatures: bool,
     #[command(flatten)]
     format: DiffFormatArgs,
 }
@@ -38,9 +42,12 @@ pub(crate) fn cmd_show(
     command: &CommandHelper,
     args: &ShowArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    let workspace_command = command.workspace_helper_for_read(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    let template_string = command.settings().config().get_string("templates.show")?;
+    let mut template_string = command.settings().config().get_string("templates.show")?;
+    if args.show_signatures {
+        template_string = with_signatures(&template_string);
+    }
     let template = workspace_command.parse_commit_template(&template_string)?;
     let diff_formats = diff_util::diff_formats_for(command.settings(), &args.format)?;
     ui.request_pager();
@@ -54,6 +61,7 @@ pub(crate) fn cmd_show(
         &commit,
         &EverythingMatcher,
         &diff_formats,
+        usize::from(ui.term_width().unwrap_or(80)),
     )?;
     Ok(())
 }
@@ -20,12 +20,15 @@ use std::path::Path;
 use clap::Subcommand;
 use itertools::Itertools;
 use jj_lib::file_util;
+use jj_lib::fileset::FilesetExpression;
 use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::settings::UserSettings;
+use jj_lib::working_copy::CheckoutOptions;
 use tracing::instrument;
 
 use crate::cli_util::{
-    edit_temp_file, internal_error_with_message, print_checkout_stats, CommandError, CommandHelper,
+    edit_temp_file, internal_error_with_message, print_checkout_stats, user_error_with_hint,
+    CommandError, CommandHelper,
 };
 use crate::ui::Ui;
 
@@ -53,9 +56,14 @@ pub(crate) struct SparseListArgs {}
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct SparseSetArgs {
     /// Patterns to add to the working copy
+    ///
+    /// Patterns are paths, or filesets made of paths, like `'a | b'`.
     #[arg(long, value_hint = clap::ValueHint::AnyPath)]
     add: Vec<String>,
     /// Patterns to remove from the working copy
+    ///
+    /// Besides the paths of patterns, filesets like `'glob:"lib/*"'` remove
+    /// all patterns they match.
     #[arg(long, conflicts_with = "clear", value_hint = clap::ValueHint::AnyPath)]
     remove: Vec<String>,
     /// Include no files in the working copy (combine with --add)
@@ -101,17 +109,25 @@ fn cmd_sparse_set(
     command: &CommandHelper,
     args: &SparseSetArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
-    let paths_to_add: Vec<_> = args
-        .add
-        .iter()
-        .map(|v| workspace_command.parse_file_path(v))
-        .try_collect()?;
-    let paths_to_remove: Vec<_> = args
-        .remove
-        .iter()
-        .map(|v| workspace_command.parse_file_path(v))
-        .try_collect()?;
+    let fileset_to_add = workspace_command.parse_union_filesets(&args.add)?;
+    let paths_to_add: Vec<RepoPathBuf> = if args.add.is_empty() {
+        vec![]
+    } else {
+        fileset_to_add
+            .as_explicit_paths()
+            .ok_or_else(|| {
+                user_error_with_hint(
+                    "Only paths can be added to the sparse patterns",
+                    "Patterns like globs can only be removed.",
+                )
+            })?
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect()
+    };
+    let fileset_to_remove = workspace_command.parse_union_filesets(&args.remove)?;
     // Determine inputs of `edit` operation now, since `workspace_command` is
     // inaccessible while the working copy is locked.
     let edit_inputs = args.edit.then(|| {
@@ -127,8 +143,8 @@ fn cmd_sparse_set(
     } else {
         if !args.clear {
             new_patterns.extend(locked_ws.locked_wc().sparse_patterns()?.iter().cloned());
-            for path in paths_to_remove {
-                new_patterns.remove(&path);
+            if !args.remove.is_empty() {
+                remove_sparse_patterns(&mut new_patterns, &fileset_to_remove);
             }
         }
         for path in paths_to_add {
@@ -146,10 +162,15 @@ fn cmd_sparse_set(
         )?;
         new_patterns.sort();
     }
+    let progress = crate::progress::checkout_progress(ui);
+    let options = CheckoutOptions {
+        progress: progress.as_ref().map(|x| x as _),
+    };
     let stats = locked_ws
         .locked_wc()
-        .set_sparse_patterns(new_patterns)
+        .set_sparse_patterns(new_patterns, options)
         .map_err(|err| internal_error_with_message("Failed to update working copy paths", err))?;
+    drop(progress);
     let operation_id = locked_ws.locked_wc().old_operation_id().clone();
     locked_ws.finish(operation_id)?;
     print_checkout_stats(ui, stats, &wc_commit)?;
@@ -199,3 +220,16 @@ fn edit_sparse(
         })
         .try_collect()
 }
+
+/// Removes the patterns in the `fileset` if it consists of paths, or else the
+/// patterns it matches.
+fn remove_sparse_patterns(patterns: &mut HashSet<RepoPathBuf>, fileset: &FilesetExpression) {
+    if let Some(paths) = fileset.as_explicit_paths() {
+        for path in paths {
+            patterns.remove(path);
+        }
+    } else {
+        let matcher = fileset.to_matcher();
+        patterns.retain(|path| !matcher.matches(path));
+    }
+}
@@ -13,13 +13,17 @@
 // limitations under the License.
 use std::io::Write;
 
+use itertools::Itertools;
+use jj_lib::commit::Commit;
 use jj_lib::object_id::ObjectId;
 use jj_lib::repo::Repo;
-use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt as _};
+use jj_lib::rewrite::{merge_commit_trees, rebase_commit};
 use tracing::instrument;
 
 use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
 use crate::description_util::{description_template_for_commit, edit_description};
+use crate::tr;
 use crate::ui::Ui;
 
 /// Split a revision in two
@@ -34,6 +38,10 @@ use crate::ui::Ui;
 /// change description for each commit. If the change did not have a
 /// description, the second part will not get a description, and you will be
 /// asked for a description only for the first part.
+///
+/// With `--siblings`, the two parts are put side by side on the parents of the
+/// revision instead, and the children of the revision become merges of both
+/// parts.
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct SplitArgs {
     /// Interactively choose which parts to split. This is the default if no
@@ -43,6 +51,9 @@ pub(crate) struct SplitArgs {
     /// The revision to split
     #[arg(long, short, default_value = "@")]
     revision: RevisionArg,
+    /// Make the two parts siblings instead of parent and child
+    #[arg(long)]
+    siblings: bool,
     /// Put these paths in the first commit
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
@@ -57,10 +68,10 @@ pub(crate) fn cmd_split(
     let mut workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
     workspace_command.check_rewritable([&commit])?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
-    let mut tx = workspace_command.start_transaction();
     let end_tree = commit.tree()?;
-    let base_tree = merge_commit_trees(tx.repo(), &commit.parents())?;
+    let base_tree = merge_commit_trees(workspace_command.repo().as_ref(), &commit.parents())?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&base_tree, &end_tree])?;
+    let mut tx = workspace_command.start_transaction()?;
     let interactive = args.interactive || args.paths.is_empty();
     let instructions = format!(
         "\
@@ -68,11 +79,16 @@ You are splitting a commit in two: {}
 
 The diff initially shows the changes in the commit you're splitting.
 
-Adjust the right side until it shows the contents you want for the first
-(parent) commit. The remainder will be in the second commit. If you
+Adjust the right side until it shows the contents you want for the {} commit. The remainder will \
+         be in the second commit. If you
 don't make any changes, then the operation will be aborted.
 ",
-        tx.format_commit_summary(&commit)
+        tx.format_commit_summary(&commit),
+        if args.siblings {
+            "first"
+        } else {
+            "first\n(parent)"
+        },
     );
     let tree_id = tx.select_diff(
         ui,
@@ -83,7 +99,7 @@ don't make any changes, then the operation will be aborted.
         interactive,
     )?;
     if &tree_id == commit.tree_id() && interactive {
-        writeln!(ui.stderr(), "Nothing changed.")?;
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
         return Ok(());
     }
     let middle_tree = tx.repo().store().get_root_tree(&tree_id)?;
@@ -99,7 +115,11 @@ don't make any changes, then the operation will be aborted.
         ui,
         command.settings(),
         tx.base_workspace_helper(),
-        "Enter commit description for the first part (parent).",
+        if args.siblings {
+            "Enter commit description for the first part."
+        } else {
+            "Enter commit description for the first part (parent)."
+        },
         commit.description(),
         &base_tree,
         &middle_tree,
@@ -119,29 +139,67 @@ don't make any changes, then the operation will be aborted.
             ui,
             command.settings(),
             tx.base_workspace_helper(),
-            "Enter commit description for the second part (child).",
+            if args.siblings {
+                "Enter commit description for the second part."
+            } else {
+                "Enter commit description for the second part (child)."
+            },
             commit.description(),
             &middle_tree,
             &end_tree,
         )?;
         edit_description(tx.base_repo(), &second_template, command.settings())?
     };
+    let (second_parent_ids, second_tree_id) = if args.siblings {
+        // The second part has the rest of the changes, on the same parents
+        let second_tree = end_tree.merge(&middle_tree, &base_tree)?;
+        (commit.parent_ids().to_vec(), second_tree.id())
+    } else {
+        (vec![first_commit.id().clone()], commit.tree_id().clone())
+    };
     let second_commit = tx
         .mut_repo()
         .rewrite_commit(command.settings(), &commit)
-        .set_parents(vec![first_commit.id().clone()])
-        .set_tree_id(commit.tree_id().clone())
+        .set_parents(second_parent_ids)
+        .set_tree_id(second_tree_id)
         .generate_new_change_id()
         .set_description(second_description)
         .write()?;
 
+    let mut num_rebased = 0;
+    if args.siblings {
+        // Rebase the children onto both parts, since neither has all the
+        // changes they were based on
+        let children: Vec<Commit> = RevsetExpression::commit(commit.id().clone())
+            .children()
+            .evaluate_programmatic(tx.base_repo().as_ref())?
+            .iter()
+            .commits(tx.base_repo().store())
+            .try_collect()?;
+        for child in &children {
+            let new_parents = child
+                .parents()
+                .into_iter()
+                .flat_map(|parent| {
+                    if parent.id() == commit.id() {
+                        vec![first_commit.clone(), second_commit.clone()]
+                    } else {
+                        vec![parent]
+                    }
+                })
+                .collect_vec();
+            rebase_commit(command.settings(), tx.mut_repo(), child, &new_parents)?;
+            num_rebased += 1;
+        }
+    }
+
     // Currently, `rebase_descendents` would treat `commit` as being rewritten to
     // *both* `first_commit` and `second_commit`, as if it was becoming divergent.
     // However, we want only the `second_commit` to inherit `commit`'s branches and
     // descendants.
     tx.mut_repo()
         .set_rewritten_commit(commit.id().clone(), [second_commit.id().clone()]);
-    let num_rebased = tx.mut_repo().rebase_descendants(command.settings())?;
+    num_rebased += tx.mut_repo().rebase_descendants(command.settings())?;
     if num_rebased > 0 {
         writeln!(ui.stderr(), "Rebased {num_rebased} descendant commits")?;
     }
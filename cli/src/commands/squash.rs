@@ -14,11 +14,13 @@
 
 use clap::parser::ValueSource;
 use jj_lib::object_id::ObjectId;
+use jj_lib::repo::Repo;
 use jj_lib::revset;
 use tracing::instrument;
 
 use crate::cli_util::{self, user_error, CommandError, CommandHelper, RevisionArg};
 use crate::description_util::combine_messages;
+use crate::patch_util::{select_lines, LineSelection};
 use crate::ui::Ui;
 
 /// Move changes from a revision into its parent
@@ -26,7 +28,7 @@ use crate::ui::Ui;
 /// After moving the changes into the parent, the child revision will have the
 /// same content state as before. If that means that the change is now empty
 /// compared to its parent, it will be abandoned.
-/// Without `--interactive`, the child change will always be empty.
+/// Without `--interactive` or `--lines`, the child change will always be empty.
 ///
 /// If the source became empty and both the source and destination had a
 /// non-empty description, you will be asked for the combined description. If
@@ -45,6 +47,17 @@ pub(crate) struct SquashArgs {
     /// Move only changes to these paths (instead of all paths)
     #[arg(conflicts_with = "interactive", value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Move only the changes to these lines of a file, like `src/main.rs:10-25`
+    ///
+    /// The lines are numbered as in the revision, and can be a comma-separated
+    /// list of line numbers and ranges. A changed hunk is moved if it touches
+    /// any of the lines.
+    #[arg(
+        long,
+        value_name = "PATH:LINES",
+        conflicts_with_all = ["interactive", "paths"]
+    )]
+    lines: Vec<LineSelection>,
 }
 
 #[instrument(skip_all)]
@@ -62,8 +75,11 @@ pub(crate) fn cmd_squash(
     }
     let parent = &parents[0];
     workspace_command.check_rewritable(&parents[..1])?;
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
-    let mut tx = workspace_command.start_transaction();
+    let parent_tree = parent.tree()?;
+    let tree = commit.tree()?;
+    let matcher = workspace_command.matcher_from_values(&args.paths, &[&parent_tree, &tree])?;
+    let line_selections = workspace_command.parse_line_selections(&args.lines)?;
+    let mut tx = workspace_command.start_transaction()?;
     let instructions = format!(
         "\
 You are moving changes from: {}
@@ -80,18 +96,20 @@ from the source will be moved into the parent.
         tx.format_commit_summary(&commit),
         tx.format_commit_summary(parent)
     );
-    let parent_tree = parent.tree()?;
-    let tree = commit.tree()?;
-    let new_parent_tree_id = tx.select_diff(
-        ui,
-        &parent_tree,
-        &tree,
-        matcher.as_ref(),
-        &instructions,
-        args.interactive,
-    )?;
+    let new_parent_tree_id = if line_selections.is_empty() {
+        tx.select_diff(
+            ui,
+            &parent_tree,
+            &tree,
+            matcher.as_ref(),
+            &instructions,
+            args.interactive,
+        )?
+    } else {
+        select_lines(tx.repo().store(), &parent_tree, &tree, &line_selections)?
+    };
     if &new_parent_tree_id == parent.tree_id() {
-        if args.interactive {
+        if args.interactive || !line_selections.is_empty() {
             return Err(user_error("No changes selected"));
         }
 
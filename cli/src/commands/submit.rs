@@ -0,0 +1,331 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use itertools::Itertools;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _};
+use tracing::instrument;
+
+use crate::cli_util::{
+    user_error, user_error_with_hint, user_error_with_message, CommandError, CommandHelper,
+    RevisionArg, WorkspaceCommandHelper,
+};
+use crate::commands::gerrit::{self, GerritPushOptions};
+use crate::commands::git::{get_remote_url, resolve_push_remote};
+use crate::commands::github::{self, split_description, PullRequestStack};
+use crate::git_util::split_remote_url;
+use crate::ui::Ui;
+
+/// Send revisions for review on the forge that hosts the remote
+///
+/// The revisions are mapped onto the review model of the forge:
+///
+/// * GitHub: each revision is pushed to a branch, and gets a pull request, like
+///   with `jj github pr create`. A stack of revisions becomes a stack of pull
+///   requests.
+///
+/// * GitLab: the same, with merge requests. The GitLab CLI, `glab`, must be
+///   installed and logged in.
+///
+/// * Gerrit: the revisions are sent for review like with `jj gerrit send`.
+///
+/// Existing pull requests, merge requests, and changes are updated.
+///
+/// The forge is found from the URL of the remote, or can be set with
+/// `submit.remotes.<remote>.forge`.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct SubmitArgs {
+    /// The revisions to submit
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The remote to submit to (default: the remote `jj git push` uses)
+    #[arg(long)]
+    remote: Option<String>,
+    /// The branch to merge the revisions into (default: the repository's
+    /// default branch, or `gerrit.default-branch` for Gerrit)
+    #[arg(long)]
+    base: Option<String>,
+    /// Create the reviews as drafts, or as work in progress on Gerrit
+    #[arg(long)]
+    draft: bool,
+}
+
+/// A code review system that revisions can be submitted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Forge {
+    Github,
+    Gitlab,
+    Gerrit,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct SubmitConfig {
+    remotes: HashMap<String, SubmitRemoteConfig>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct SubmitRemoteConfig {
+    forge: Option<Forge>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_submit(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &SubmitArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let remote = resolve_push_remote(ui, command, &workspace_command, args.remote.as_deref())?;
+    let remote_url = get_remote_url(&workspace_command, &remote)?;
+    let config: SubmitConfig = command
+        .settings()
+        .config()
+        .get("submit")
+        .optional()?
+        .unwrap_or_default();
+    let forge = config
+        .remotes
+        .get(&remote)
+        .and_then(|remote_config| remote_config.forge)
+        .or_else(|| detect_forge(&remote_url))
+        .ok_or_else(|| {
+            user_error_with_hint(
+                format!("Don't know which forge hosts remote {remote}: {remote_url}"),
+                format!(r#"Set submit.remotes.{remote}.forge to "github", "gitlab", or "gerrit"."#),
+            )
+        })?;
+    match forge {
+        Forge::Github => github::submit_pull_requests(
+            ui,
+            command,
+            workspace_command,
+            &args.revisions,
+            &remote,
+            args.base.as_deref(),
+            args.draft,
+        ),
+        Forge::Gitlab => submit_merge_requests(
+            ui,
+            command,
+            workspace_command,
+            &args.revisions,
+            &remote_url,
+            remote,
+            args.base.as_deref(),
+            args.draft,
+        ),
+        Forge::Gerrit => {
+            let options = GerritPushOptions {
+                wip: args.draft,
+                ..Default::default()
+            };
+            gerrit::send_changes(
+                ui,
+                command,
+                workspace_command,
+                &args.revisions,
+                &remote,
+                args.base.as_deref(),
+                &options,
+                false,
+            )
+        }
+    }
+}
+
+/// Guesses the forge from the host name in the `url`.
+fn detect_forge(url: &str) -> Option<Forge> {
+    let (host, _path) = split_remote_url(url)?;
+    if host.contains("github") {
+        Some(Forge::Github)
+    } else if host.contains("gitlab") {
+        Some(Forge::Gitlab)
+    } else {
+        None
+    }
+}
+
+/// Pushes the `revisions` to the GitLab `remote`, and creates or updates a
+/// merge request for each of them.
+#[allow(clippy::too_many_arguments)]
+fn submit_merge_requests(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    mut workspace_command: WorkspaceCommandHelper,
+    revisions: &[RevisionArg],
+    remote_url: &str,
+    remote: String,
+    base: Option<&str>,
+    draft: bool,
+) -> Result<(), CommandError> {
+    let config: GitlabConfig = command
+        .settings()
+        .config()
+        .get("gitlab")
+        .optional()?
+        .unwrap_or_default();
+    let gitlab = GitlabProject {
+        program: config
+            .program
+            .map_or_else(|| "glab".to_owned(), |program| expand_config_path(&program)),
+        repository: config.repository.unwrap_or_else(|| remote_url.to_owned()),
+    };
+    let stack = PullRequestStack::new(ui, command, &mut workspace_command, revisions, remote)?;
+    let merge_requests: Vec<_> = stack
+        .branch_names
+        .iter()
+        .map(|branch_name| gitlab.find_merge_request(branch_name))
+        .try_collect()?;
+    stack.push(ui, &mut workspace_command, "submit merge requests")?;
+    for (i, merge_request) in merge_requests.into_iter().enumerate() {
+        let (title, description) = split_description(stack.commits[i].description());
+        let branch_name = &stack.branch_names[i];
+        let base = stack.base_branch(i).or(base);
+        if let Some(merge_request) = merge_request {
+            let iid = merge_request.iid.to_string();
+            let mut args = vec![
+                "mr",
+                "update",
+                &iid,
+                "--title",
+                title,
+                "--description",
+                description,
+            ];
+            if let Some(base) = base {
+                args.extend(["--target-branch", base]);
+            }
+            gitlab.run(&args)?;
+            writeln!(
+                ui.stderr(),
+                "Updated merge request {}",
+                merge_request.web_url
+            )?;
+        } else {
+            let mut args = vec![
+                "mr",
+                "create",
+                "--source-branch",
+                branch_name,
+                "--title",
+                title,
+                "--description",
+                description,
+                "--yes",
+            ];
+            if let Some(base) = base {
+                args.extend(["--target-branch", base]);
+            }
+            if draft {
+                args.push("--draft");
+            }
+            gitlab.run(&args)?;
+            let merge_request = gitlab.find_merge_request(branch_name)?.ok_or_else(|| {
+                user_error(format!(
+                    "Can't find the merge request created for branch {branch_name}"
+                ))
+            })?;
+            writeln!(
+                ui.stderr(),
+                "Created merge request {}",
+                merge_request.web_url
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct GitlabConfig {
+    program: Option<String>,
+    repository: Option<String>,
+}
+
+/// A merge request found on GitLab.
+#[derive(Debug, serde::Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    web_url: String,
+}
+
+/// A GitLab project, accessed with the GitLab CLI.
+struct GitlabProject {
+    program: String,
+    /// The project as a URL or `[HOST/]GROUP/PROJECT` path.
+    repository: String,
+}
+
+impl GitlabProject {
+    /// Runs the GitLab CLI with the `args`, and returns its output.
+    fn run(&self, args: &[&str]) -> Result<String, CommandError> {
+        let output = Command::new(&self.program)
+            .args(args)
+            .args(["--repo", &self.repository])
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|err| {
+                user_error_with_message(format!("Failed to run {}", self.program), err)
+            })?;
+        if !output.status.success() {
+            return Err(user_error(format!(
+                "{} failed with {}",
+                self.program, output.status
+            )));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|err| user_error_with_message("Invalid output from GitLab CLI", err))
+    }
+
+    /// Finds the open merge request for the `branch_name`.
+    fn find_merge_request(&self, branch_name: &str) -> Result<Option<MergeRequest>, CommandError> {
+        let output = self.run(&[
+            "mr",
+            "list",
+            "--source-branch",
+            branch_name,
+            "--output",
+            "json",
+        ])?;
+        let merge_requests: Vec<MergeRequest> = serde_json::from_str(&output)
+            .map_err(|err| user_error_with_message("Invalid output from GitLab CLI", err))?;
+        Ok(merge_requests.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_forge() {
+        assert_eq!(
+            detect_forge("git@github.com:owner/repo.git"),
+            Some(Forge::Github)
+        );
+        assert_eq!(
+            detect_forge("https://gitlab.example.com/group/subgroup/project.git"),
+            Some(Forge::Gitlab)
+        );
+        assert_eq!(detect_forge("ssh://review.example.com:29418/project"), None);
+        assert_eq!(detect_forge("/path/to/repo"), None);
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use jj_lib::commit::Commit;
+use jj_lib::repo::Repo;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper,
+};
+use crate::description_util::{add_trailers, is_trailer, remove_trailers, rewrite_descriptions};
+use crate::tr;
+use crate::ui::Ui;
+
+/// Add or remove trailers in the descriptions of revisions
+///
+/// Trailers are "Key: value" lines in the last paragraph of a description,
+/// like "Signed-off-by: Name <email>". To add trailers automatically when
+/// describing a commit, set `templates.commit_trailers`.
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum TrailerCommand {
+    Add(TrailerAddArgs),
+    Remove(TrailerRemoveArgs),
+}
+
+/// Add trailers to the descriptions of revisions
+///
+/// Trailers that a description already has aren't added again. Revisions
+/// without a description are left alone.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct TrailerAddArgs {
+    /// The revisions to add the trailers to
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The trailers to add, like "Reviewed-by: Name <email>"
+    #[arg(required = true, value_parser = parse_trailer)]
+    trailers: Vec<String>,
+}
+
+/// Remove trailers from the descriptions of revisions
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct TrailerRemoveArgs {
+    /// The revisions to remove the trailers from
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// The trailers to remove, either a whole "Key: value" trailer, or a key
+    /// to remove all trailers with that key
+    #[arg(required = true)]
+    trailers: Vec<String>,
+}
+
+fn parse_trailer(text: &str) -> Result<String, String> {
+    if is_trailer(text) {
+        Ok(text.trim().to_owned())
+    } else {
+        Err(r#"expected a trailer like "Key: value""#.to_owned())
+    }
+}
+
+pub(crate) fn cmd_trailer(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &TrailerCommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        TrailerCommand::Add(sub_args) => cmd_trailer_add(ui, command, sub_args),
+        TrailerCommand::Remove(sub_args) => cmd_trailer_remove(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_trailer_add(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TrailerAddArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    edit_trailers(
+        ui,
+        command,
+        workspace_command,
+        &args.revisions,
+        "add trailers",
+        |description| {
+            if description.is_empty() {
+                description.to_owned()
+            } else {
+                add_trailers(description, &args.trailers)
+            }
+        },
+    )
+}
+
+#[instrument(skip_all)]
+fn cmd_trailer_remove(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TrailerRemoveArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    edit_trailers(
+        ui,
+        command,
+        workspace_command,
+        &args.revisions,
+        "remove trailers",
+        |description| remove_trailers(description, &args.trailers),
+    )
+}
+
+/// Rewrites the descriptions of the `revisions` with `edit`.
+fn edit_trailers(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    mut workspace_command: WorkspaceCommandHelper,
+    revisions: &[RevisionArg],
+    tx_description: &str,
+    edit: impl Fn(&str) -> String,
+) -> Result<(), CommandError> {
+    let revset_commits = resolve_multiple_nonempty_revsets(revisions, &workspace_command, ui)?;
+    let repo = workspace_command.repo();
+    let commits: Vec<Commit> = repo
+        .index()
+        .topo_order(&mut revset_commits.iter().map(|commit| commit.id()))
+        .iter()
+        .map(|id| repo.store().get_commit(id))
+        .try_collect()?;
+    workspace_command.check_rewritable(&commits)?;
+
+    let mut num_changed = 0;
+    let mut tx = workspace_command.start_transaction()?;
+    rewrite_descriptions(&mut tx, command.settings(), &commits, |commit| {
+        let description = edit(commit.description());
+        if description == commit.description() {
+            return None;
+        }
+        num_changed += 1;
+        Some(description)
+    })?;
+    if num_changed == 0 {
+        writeln!(ui.stderr(), "{}", tr!(ui, "nothing-changed"))?;
+        return Ok(());
+    }
+    writeln!(ui.stderr(), "Updated the trailers of {num_changed} commits")?;
+    tx.finish(ui, format!("{tx_description} in {num_changed} commits"))
+}
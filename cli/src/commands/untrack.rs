@@ -43,14 +43,15 @@ pub(crate) fn cmd_untrack(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let store = workspace_command.repo().store().clone();
-    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let fileset = workspace_command.parse_union_filesets(&args.paths)?;
 
-    let mut tx = workspace_command.start_transaction().into_inner();
+    let mut tx = workspace_command.start_transaction()?.into_inner();
     let base_ignores = workspace_command.base_ignores();
     let (mut locked_ws, wc_commit) = workspace_command.start_working_copy_mutation()?;
     // Create a new tree without the unwanted files
     let mut tree_builder = MergedTreeBuilder::new(wc_commit.tree_id().clone());
     let wc_tree = wc_commit.tree()?;
+    let matcher = fileset.resolve_predicates(&[&wc_tree])?.to_matcher();
     for (path, _value) in wc_tree.entries_matching(matcher.as_ref()) {
         tree_builder.set_or_remove(path, Merge::absent());
     }
@@ -17,17 +17,30 @@ use std::slice;
 use std::time::{Duration, SystemTime};
 
 use clap::Subcommand;
-use jj_lib::repo::Repo;
+use itertools::Itertools as _;
+use jj_lib::default_index::{DefaultIndexStore, DefaultReadonlyIndex};
+use jj_lib::git_backend::GitBackend;
+use jj_lib::repo::{ReadonlyRepo, Repo};
 use tracing::instrument;
 
-use crate::cli_util::{user_error, CommandError, CommandHelper};
+use super::fix::FixCache;
+use crate::cli_util::{
+    internal_error, resolve_aliases, user_error, user_error_with_message, CommandError,
+    CommandHelper,
+};
+use crate::complete;
+use crate::config_schema::CONFIG_SCHEMA;
+use crate::maintenance::{self, AutoMaintenanceSettings};
 use crate::ui::Ui;
 
 /// Infrequently used commands such as for generating shell completions
 #[derive(Subcommand, Clone, Debug)]
 pub(crate) enum UtilCommand {
     Completion(UtilCompletionArgs),
+    #[command(hide = true)]
+    Complete(UtilCompleteArgs),
     Gc(UtilGcArgs),
+    Maintenance(UtilMaintenanceArgs),
     Mangen(UtilMangenArgs),
     MarkdownHelp(UtilMarkdownHelp),
     ConfigSchema(UtilConfigSchemaArgs),
@@ -61,7 +74,32 @@ pub(crate) struct UtilCompletionArgs {
     zsh: bool,
 }
 
+/// Print the candidates for completing a command line
+///
+/// This is called by the completion scripts to complete values that depend on
+/// the repo, such as revisions, branches, remotes, and paths. It prints nothing
+/// if the last word isn't such a value.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct UtilCompleteArgs {
+    /// Print a description after each candidate, separated by a tab
+    #[arg(long)]
+    descriptions: bool,
+    /// The words of the command line up to the one being completed, starting
+    /// with the program name
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    words: Vec<String>,
+}
+
 /// Run backend-dependent garbage collection.
+///
+/// This removes operations and views that are no longer reachable from the
+/// current operation, for example because they were abandoned by `jj op
+/// abandon`. Commits that aren't reachable from the remaining operations are
+/// then removed from the backend. Commits that are only hidden are kept, so
+/// `jj undo` and `jj op restore` still work. In repos backed by Git, the
+/// unreachable commits are released, and `git gc` is run to prune them and to
+/// pack the objects. The results of `jj fix` cached before the time threshold
+/// are removed too.
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct UtilGcArgs {
     /// Time threshold
@@ -74,6 +112,27 @@ pub(crate) struct UtilGcArgs {
     /// release.
     #[arg(long)]
     expire: Option<String>,
+    /// Report what would be removed, without removing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Run repo maintenance tasks
+///
+/// This packs the Git refs and writes the Git commit-graph file if the repo is
+/// backed by Git, garbage collects unreachable objects older than 2 weeks like
+/// `jj util gc`, and compacts the commit index.
+///
+/// If `maintenance.auto` is set to true, maintenance runs automatically in a
+/// detached background process once `maintenance.auto-after-operations`
+/// operations were created, or `maintenance.auto-after-days` days have passed,
+/// since it last ran.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct UtilMaintenanceArgs {
+    /// Only run if maintenance is due, and exit quietly if it's already
+    /// running
+    #[arg(long, hide = true)]
+    auto: bool,
 }
 
 /// Print a ROFF (manpage)
@@ -96,7 +155,9 @@ pub(crate) fn cmd_util(
 ) -> Result<(), CommandError> {
     match subcommand {
         UtilCommand::Completion(args) => cmd_util_completion(ui, command, args),
+        UtilCommand::Complete(args) => cmd_util_complete(ui, command, args),
         UtilCommand::Gc(args) => cmd_util_gc(ui, command, args),
+        UtilCommand::Maintenance(args) => cmd_util_maintenance(ui, command, args),
         UtilCommand::Mangen(args) => cmd_util_mangen(ui, command, args),
         UtilCommand::MarkdownHelp(args) => cmd_util_markdownhelp(ui, command, args),
         UtilCommand::ConfigSchema(args) => cmd_util_config_schema(ui, command, args),
@@ -118,10 +179,86 @@ fn cmd_util_completion(
         clap_complete::Shell::Bash
     };
     clap_complete::generate(shell, &mut app, "jj", &mut buf);
+    let dynamic_completion_script = match shell {
+        clap_complete::Shell::Zsh => ZSH_DYNAMIC_COMPLETION,
+        clap_complete::Shell::Fish => FISH_DYNAMIC_COMPLETION,
+        _ => BASH_DYNAMIC_COMPLETION,
+    };
+    buf.extend_from_slice(dynamic_completion_script.as_bytes());
     ui.stdout_formatter().write_all(&buf)?;
     Ok(())
 }
 
+// The scripts below use `jj util complete` for values that depend on the repo,
+// and fall back to the static completions generated by clap.
+
+const BASH_DYNAMIC_COMPLETION: &str = r#"
+_jj_dynamic() {
+    local candidates
+    candidates=$(jj util complete -- "${COMP_WORDS[@]:0:COMP_CWORD+1}" 2>/dev/null)
+    if [[ -n "$candidates" ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($candidates)
+    else
+        _jj "$@"
+    fi
+}
+complete -F _jj_dynamic -o bashdefault -o default jj
+"#;
+
+const ZSH_DYNAMIC_COMPLETION: &str = r#"
+_jj_dynamic() {
+    local -a candidates
+    candidates=("${(@f)$(jj util complete -- "${(@)words[1,CURRENT]}" 2>/dev/null)}")
+    if [[ -n "${candidates[1]}" ]]; then
+        compadd -a candidates
+    else
+        _jj "$@"
+    fi
+}
+compdef _jj_dynamic jj
+"#;
+
+const FISH_DYNAMIC_COMPLETION: &str = r#"
+complete -c jj -a '(jj util complete --descriptions -- (commandline -opc) (commandline -ct) 2>/dev/null)'
+"#;
+
+fn cmd_util_complete(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &UtilCompleteArgs,
+) -> Result<(), CommandError> {
+    let Some((current, preceding)) = args.words.split_last() else {
+        return Ok(());
+    };
+    // Aliases are resolved the same way as when running the command, but the
+    // word being completed is left as is.
+    let mut words = resolve_aliases(
+        command.settings().config(),
+        command.app(),
+        preceding.to_vec(),
+    )
+    .unwrap_or_else(|_| preceding.to_vec());
+    words.push(current.clone());
+    let Some(kind) = complete::completion_kind(command.app(), &words) else {
+        return Ok(());
+    };
+    // Outside of a repo, there's just nothing to complete
+    let Ok(workspace_command) = command.workspace_helper_no_snapshot(ui) else {
+        return Ok(());
+    };
+    let candidates = complete::complete(ui, &workspace_command, &kind, current)?;
+    let mut formatter = ui.stdout_formatter();
+    for (value, description) in candidates {
+        if args.descriptions && !description.is_empty() {
+            writeln!(formatter, "{value}\t{description}")?;
+        } else {
+            writeln!(formatter, "{value}")?;
+        }
+    }
+    Ok(())
+}
+
 fn cmd_util_gc(
     ui: &mut Ui,
     command: &CommandHelper,
@@ -137,12 +274,135 @@ fn cmd_util_gc(
         Some("now") => SystemTime::now() - Duration::ZERO,
         _ => return Err(user_error("--expire only accepts 'now'")),
     };
+    if !args.dry_run {
+        command.check_repo_writable()?;
+    }
     let workspace_command = command.workspace_helper(ui)?;
+    gc_repo(ui, workspace_command.repo(), keep_newer, args.dry_run)
+}
+
+/// Garbage collects the operation store and the backend, and reports what was
+/// removed.
+fn gc_repo(
+    ui: &mut Ui,
+    repo: &ReadonlyRepo,
+    keep_newer: SystemTime,
+    dry_run: bool,
+) -> Result<(), CommandError> {
+    let op_stats = repo
+        .op_store()
+        .gc(slice::from_ref(repo.op_id()), keep_newer, dry_run)?;
+    let backend_stats = repo.store().gc(repo.index(), keep_newer, dry_run)?;
+    let index_store = repo.index_store();
+    let removed_index_files = match index_store.as_any().downcast_ref::<DefaultIndexStore>() {
+        Some(default_index_store) => default_index_store
+            .gc(
+                slice::from_ref(repo.op_id()),
+                repo.store(),
+                keep_newer,
+                dry_run,
+            )
+            .map_err(internal_error)?,
+        None => 0,
+    };
+    let removed_fix_results = FixCache::new(repo.repo_path()).gc(keep_newer, dry_run)?;
+    let counts = [
+        (op_stats.removed_operations, "operations"),
+        (op_stats.removed_views, "views"),
+        (backend_stats.removed_commits, "commits"),
+        (backend_stats.removed_objects, "other objects"),
+        (removed_index_files, "index files"),
+        (removed_fix_results, "cached fix results"),
+    ];
+    if counts.iter().all(|(count, _)| *count == 0) {
+        writeln!(ui.stderr(), "Nothing to remove.")?;
+        return Ok(());
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    writeln!(
+        ui.stderr(),
+        "{verb} unreachable objects: {}",
+        counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, kind)| format!("{count} {kind}"))
+            .join(", ")
+    )?;
+    Ok(())
+}
 
-    let repo = workspace_command.repo();
-    repo.op_store()
-        .gc(slice::from_ref(repo.op_id()), keep_newer)?;
-    repo.store().gc(repo.index(), keep_newer)?;
+fn cmd_util_maintenance(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &UtilMaintenanceArgs,
+) -> Result<(), CommandError> {
+    if command.global_args().at_operation != "@" {
+        return Err(user_error(
+            "Cannot run maintenance from a non-head operation",
+        ));
+    }
+    command.check_repo_writable()?;
+    let workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo().clone();
+    let Some(_lock) = maintenance::try_lock(&repo, command.settings().lock_backend()?)? else {
+        if args.auto {
+            return Ok(());
+        }
+        return Err(user_error("Maintenance is already running"));
+    };
+    if args.auto
+        && !maintenance::is_due(
+            &repo,
+            &AutoMaintenanceSettings::from_settings(command.settings()),
+        )?
+    {
+        return Ok(());
+    }
+
+    if let Some(git_backend) = repo.store().backend_impl().downcast_ref::<GitBackend>() {
+        git_backend
+            .run_maintenance()
+            .map_err(|err| user_error_with_message("Failed to run Git maintenance", err))?;
+        writeln!(ui.stderr(), "Packed Git refs and wrote the commit-graph.")?;
+    }
+    gc_repo(
+        ui,
+        &repo,
+        SystemTime::now() - Duration::from_secs(14 * 86400),
+        false,
+    )?;
+    let index_store = repo.index_store();
+    if let Some(default_index_store) = index_store.as_any().downcast_ref::<DefaultIndexStore>() {
+        default_index_store
+            .compact(repo.operation(), repo.store())
+            .map_err(internal_error)?;
+        writeln!(ui.stderr(), "Compacted the commit index.")?;
+        // Build the changed-path filters of the commits that were indexed
+        // without one, if the filters are used
+        let index = repo
+            .readonly_index()
+            .as_any()
+            .downcast_ref::<DefaultReadonlyIndex>();
+        if let Some((index, changed_paths)) =
+            index.and_then(|index| Some((index, index.changed_paths()?)))
+        {
+            let num_before = changed_paths.num_commits();
+            let index = default_index_store
+                .build_changed_path_index(index, repo.store())
+                .map_err(internal_error)?;
+            let num_after = index
+                .changed_paths()
+                .map_or(num_before, |changed_paths| changed_paths.num_commits());
+            if num_after > num_before {
+                writeln!(
+                    ui.stderr(),
+                    "Built changed-path filters for {} commits.",
+                    num_after - num_before
+                )?;
+            }
+        }
+    }
+    maintenance::record_run(&repo)?;
     Ok(())
 }
 
@@ -176,7 +436,6 @@ fn cmd_util_config_schema(
     _args: &UtilConfigSchemaArgs,
 ) -> Result<(), CommandError> {
     // TODO(#879): Consider generating entire schema dynamically vs. static file.
-    let buf = include_bytes!("../config-schema.json");
-    ui.stdout_formatter().write_all(buf)?;
+    ui.stdout_formatter().write_all(CONFIG_SCHEMA.as_bytes())?;
     Ok(())
 }
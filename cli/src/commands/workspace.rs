@@ -18,18 +18,23 @@ use std::io::Write;
 
 use clap::Subcommand;
 use itertools::Itertools;
+use jj_lib::commit::Commit;
 use jj_lib::file_util;
+use jj_lib::local_working_copy::LocalWorkingCopy;
 use jj_lib::object_id::ObjectId;
 use jj_lib::op_store::WorkspaceId;
 use jj_lib::repo::Repo;
 use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::working_copy::CheckoutOptions;
 use jj_lib::workspace::Workspace;
 use tracing::instrument;
 
 use crate::cli_util::{
-    self, check_stale_working_copy, internal_error_with_message, print_checkout_stats, user_error,
-    CommandError, CommandHelper, RevisionArg, WorkspaceCommandHelper,
+    self, check_stale_working_copy, internal_error_with_message, print_checkout_stats,
+    short_commit_hash, user_error, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper,
 };
+use crate::tr;
 use crate::ui::Ui;
 
 /// Commands for working with workspaces
@@ -46,6 +51,7 @@ pub(crate) enum WorkspaceCommand {
     Add(WorkspaceAddArgs),
     Forget(WorkspaceForgetArgs),
     List(WorkspaceListArgs),
+    Repair(WorkspaceRepairArgs),
     Root(WorkspaceRootArgs),
     UpdateStale(WorkspaceUpdateStaleArgs),
 }
@@ -92,6 +98,16 @@ pub(crate) struct WorkspaceForgetArgs {
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct WorkspaceListArgs {}
 
+/// Rebuild the working copy state of the current workspace
+///
+/// Use this if the working copy state in `.jj/working_copy/` was corrupted or
+/// deleted. The state is rebuilt from the workspace's working-copy commit,
+/// without touching the files on disk. The working copy is then snapshotted,
+/// so changes to the files, including new untracked files, are recorded in the
+/// working-copy commit.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct WorkspaceRepairArgs {}
+
 /// Show the current workspace root directory
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct WorkspaceRootArgs {}
@@ -113,6 +129,7 @@ pub(crate) fn cmd_workspace(
         WorkspaceCommand::Add(args) => cmd_workspace_add(ui, command, args),
         WorkspaceCommand::Forget(args) => cmd_workspace_forget(ui, command, args),
         WorkspaceCommand::List(args) => cmd_workspace_list(ui, command, args),
+        WorkspaceCommand::Repair(args) => cmd_workspace_repair(ui, command, args),
         WorkspaceCommand::Root(args) => cmd_workspace_root(ui, command, args),
         WorkspaceCommand::UpdateStale(args) => cmd_workspace_update_stale(ui, command, args),
     }
@@ -124,6 +141,7 @@ fn cmd_workspace_add(
     command: &CommandHelper,
     args: &WorkspaceAddArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let old_workspace_command = command.workspace_helper(ui)?;
     let destination_path = command.cwd().join(&args.destination);
     if destination_path.exists() {
@@ -165,7 +183,7 @@ fn cmd_workspace_add(
     )?;
 
     let mut new_workspace_command = WorkspaceCommandHelper::new(ui, command, new_workspace, repo)?;
-    let mut tx = new_workspace_command.start_transaction();
+    let mut tx = new_workspace_command.start_transaction()?;
 
     // If no parent revisions are specified, create a working-copy commit based
     // on the parent of the current working-copy commit.
@@ -208,6 +226,7 @@ fn cmd_workspace_forget(
     command: &CommandHelper,
     args: &WorkspaceForgetArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     let mut workspace_command = command.workspace_helper(ui)?;
 
     let wss: Vec<WorkspaceId> = if args.workspaces.is_empty() {
@@ -232,7 +251,7 @@ fn cmd_workspace_forget(
 
     // bundle every workspace forget into a single transaction, so that e.g.
     // undo correctly restores all of them at once.
-    let mut tx = workspace_command.start_transaction();
+    let mut tx = workspace_command.start_transaction()?;
     wss.iter().for_each(|ws| tx.mut_repo().remove_wc_commit(ws));
     let description = if let [ws] = wss.as_slice() {
         format!("forget workspace {}", ws.as_str())
@@ -264,6 +283,55 @@ fn cmd_workspace_list(
     Ok(())
 }
 
+#[instrument(skip_all)]
+fn cmd_workspace_repair(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &WorkspaceRepairArgs,
+) -> Result<(), CommandError> {
+    // The working copy state can't be read, so it must not be snapshotted
+    // before it's been rebuilt.
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    workspace_command.check_working_copy_writable()?;
+    let repo = workspace_command.repo();
+    let Some(wc_commit_id) = workspace_command.get_wc_commit_id() else {
+        return Err(user_error("This workspace has no working-copy commit"));
+    };
+    let wc_commit = repo.store().get_commit(wc_commit_id)?;
+    let Some(wc) = workspace_command
+        .working_copy()
+        .as_any()
+        .downcast_ref::<LocalWorkingCopy>()
+    else {
+        return Err(user_error(format!(
+            "Can't repair a working copy of type {}",
+            workspace_command.working_copy().name()
+        )));
+    };
+    wc.recover(
+        &wc_commit,
+        repo.op_id().clone(),
+        workspace_command.workspace_id().clone(),
+    )
+    .map_err(|err| internal_error_with_message("Failed to repair the working copy", err))?;
+    writeln!(
+        ui.stderr(),
+        "Rebuilt the working copy state from commit {}",
+        short_commit_hash(wc_commit.id())
+    )?;
+
+    // Snapshot the files on disk against the rebuilt state
+    let workspace_command = command.workspace_helper(ui)?;
+    let wc_commit_id = workspace_command.get_wc_commit_id().unwrap();
+    let wc_commit = workspace_command.repo().store().get_commit(wc_commit_id)?;
+    write!(ui.stderr(), "{}", tr!(ui, "working-copy-now-at"))?;
+    ui.stderr_formatter().with_label("working_copy", |fmt| {
+        workspace_command.write_commit_summary(fmt, &wc_commit)
+    })?;
+    writeln!(ui.stderr())?;
+    Ok(())
+}
+
 #[instrument(skip_all)]
 fn cmd_workspace_root(
     ui: &mut Ui,
@@ -285,6 +353,7 @@ fn cmd_workspace_update_stale(
     command: &CommandHelper,
     _args: &WorkspaceUpdateStaleArgs,
 ) -> Result<(), CommandError> {
+    command.check_repo_writable()?;
     // Snapshot the current working copy on top of the last known working-copy
     // operation, then merge the concurrent operations. The wc_commit_id of the
     // merged repo wouldn't change because the old one wins, but it's probably
@@ -296,40 +365,57 @@ fn cmd_workspace_update_stale(
         workspace_command.repo().store().get_commit(wc_commit_id)?
     };
     let mut workspace_command = command.workspace_helper_no_snapshot(ui)?;
-
-    let repo = workspace_command.repo().clone();
-    let (mut locked_ws, desired_wc_commit) =
-        workspace_command.unchecked_start_working_copy_mutation()?;
-    if !check_stale_working_copy(locked_ws.locked_wc(), &desired_wc_commit, &repo)?.is_stale() {
+    if !update_stale_working_copy(ui, &mut workspace_command, &known_wc_commit)? {
         writeln!(
             ui.stderr(),
             "Nothing to do (the working copy is not stale)."
         )?;
-    } else {
-        // The same check as start_working_copy_mutation(), but with the stale
-        // working-copy commit.
-        if known_wc_commit.tree_id() != locked_ws.locked_wc().old_tree_id() {
-            return Err(user_error("Concurrent working copy operation. Try again."));
-        }
-        let stats = locked_ws
-            .locked_wc()
-            .check_out(&desired_wc_commit)
-            .map_err(|err| {
-                internal_error_with_message(
-                    format!(
-                        "Failed to check out commit {}",
-                        desired_wc_commit.id().hex()
-                    ),
-                    err,
-                )
-            })?;
-        locked_ws.finish(repo.op_id().clone())?;
-        write!(ui.stderr(), "Working copy now at: ")?;
-        ui.stderr_formatter().with_label("working_copy", |fmt| {
-            workspace_command.write_commit_summary(fmt, &desired_wc_commit)
-        })?;
-        writeln!(ui.stderr())?;
-        print_checkout_stats(ui, stats, &desired_wc_commit)?;
     }
     Ok(())
 }
+
+/// Checks out the working-copy commit of the repo if the working copy is
+/// stale. `known_wc_commit` is the commit the working copy was last
+/// snapshotted to. Returns whether the working copy was updated.
+pub(crate) fn update_stale_working_copy(
+    ui: &mut Ui,
+    workspace_command: &mut WorkspaceCommandHelper,
+    known_wc_commit: &Commit,
+) -> Result<bool, CommandError> {
+    let repo = workspace_command.repo().clone();
+    let (mut locked_ws, desired_wc_commit) =
+        workspace_command.unchecked_start_working_copy_mutation()?;
+    if !check_stale_working_copy(locked_ws.locked_wc(), &desired_wc_commit, &repo)?.is_stale() {
+        return Ok(false);
+    }
+    // The same check as start_working_copy_mutation(), but with the stale
+    // working-copy commit.
+    if known_wc_commit.tree_id() != locked_ws.locked_wc().old_tree_id() {
+        return Err(user_error("Concurrent working copy operation. Try again."));
+    }
+    let progress = crate::progress::checkout_progress(ui);
+    let options = CheckoutOptions {
+        progress: progress.as_ref().map(|x| x as _),
+    };
+    let stats = locked_ws
+        .locked_wc()
+        .check_out(&desired_wc_commit, options)
+        .map_err(|err| {
+            internal_error_with_message(
+                format!(
+                    "Failed to check out commit {}",
+                    desired_wc_commit.id().hex()
+                ),
+                err,
+            )
+        })?;
+    drop(progress);
+    locked_ws.finish(repo.op_id().clone())?;
+    write!(ui.stderr(), "Working copy now at: ")?;
+    ui.stderr_formatter().with_label("working_copy", |fmt| {
+        workspace_command.write_commit_summary(fmt, &desired_wc_commit)
+    })?;
+    writeln!(ui.stderr())?;
+    print_checkout_stats(ui, stats, &desired_wc_commit)?;
+    Ok(true)
+}
@@ -25,6 +25,7 @@ use jj_lib::id_prefix::IdPrefixContext;
 use jj_lib::object_id::ObjectId as _;
 use jj_lib::op_store::{RefTarget, WorkspaceId};
 use jj_lib::repo::Repo;
+use jj_lib::signing::{SigStatus, Verification};
 use jj_lib::{git, rewrite};
 use once_cell::unsync::OnceCell;
 
@@ -98,6 +99,9 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo, '_> {
             CommitTemplatePropertyKind::ShortestIdPrefix(property) => {
                 build_shortest_id_prefix_method(self, build_ctx, property, function)
             }
+            CommitTemplatePropertyKind::CryptographicSignature(property) => {
+                build_cryptographic_signature_method(self, build_ctx, property, function)
+            }
         }
     }
 }
@@ -146,6 +150,13 @@ impl<'repo> CommitTemplateLanguage<'repo, '_> {
     ) -> CommitTemplatePropertyKind<'repo> {
         CommitTemplatePropertyKind::ShortestIdPrefix(Box::new(property))
     }
+
+    fn wrap_cryptographic_signature(
+        &self,
+        property: impl TemplateProperty<Commit, Output = CryptographicSignature> + 'repo,
+    ) -> CommitTemplatePropertyKind<'repo> {
+        CommitTemplatePropertyKind::CryptographicSignature(Box::new(property))
+    }
 }
 
 enum CommitTemplatePropertyKind<'repo> {
@@ -156,6 +167,9 @@ enum CommitTemplatePropertyKind<'repo> {
     RefNameList(Box<dyn TemplateProperty<Commit, Output = Vec<RefName>> + 'repo>),
     CommitOrChangeId(Box<dyn TemplateProperty<Commit, Output = CommitOrChangeId> + 'repo>),
     ShortestIdPrefix(Box<dyn TemplateProperty<Commit, Output = ShortestIdPrefix> + 'repo>),
+    CryptographicSignature(
+        Box<dyn TemplateProperty<Commit, Output = CryptographicSignature> + 'repo>,
+    ),
 }
 
 impl<'repo> IntoTemplateProperty<'repo, Commit> for CommitTemplatePropertyKind<'repo> {
@@ -172,6 +186,11 @@ impl<'repo> IntoTemplateProperty<'repo, Commit> for CommitTemplatePropertyKind<'
             }
             CommitTemplatePropertyKind::CommitOrChangeId(_) => None,
             CommitTemplatePropertyKind::ShortestIdPrefix(_) => None,
+            CommitTemplatePropertyKind::CryptographicSignature(property) => {
+                Some(Box::new(TemplateFunction::new(property, |sig| {
+                    sig.is_signed()
+                })))
+            }
         }
     }
 
@@ -207,6 +226,7 @@ impl<'repo> IntoTemplateProperty<'repo, Commit> for CommitTemplatePropertyKind<'
             CommitTemplatePropertyKind::ShortestIdPrefix(property) => {
                 Some(property.into_template())
             }
+            CommitTemplatePropertyKind::CryptographicSignature(_) => None,
         }
     }
 }
@@ -378,6 +398,9 @@ fn build_commit_keyword_opt<'repo>(
         "root" => language.wrap_boolean(wrap_fn(property, move |commit| {
             commit.id() == repo.store().root_commit_id()
         })),
+        "signature" => language.wrap_cryptographic_signature(wrap_fn(property, |commit| {
+            CryptographicSignature::new(commit)
+        })),
         _ => return None,
     };
     Some(property)
@@ -710,6 +733,93 @@ fn build_shortest_id_prefix_method<'repo>(
     Ok(property)
 }
 
+/// Verification result of the signature of a commit.
+#[derive(Clone, Debug)]
+enum CryptographicSignature {
+    Unsigned,
+    Verified(Verification),
+    /// The signature couldn't be verified, e.g. because no backend can read it.
+    Invalid,
+}
+
+impl CryptographicSignature {
+    fn new(commit: &Commit) -> Self {
+        // Verification is cached by the signer, so this is only slow once per
+        // commit.
+        match commit.verification() {
+            Ok(Some(verification)) => CryptographicSignature::Verified(verification),
+            Ok(None) if commit.is_signed() => CryptographicSignature::Invalid,
+            Ok(None) => CryptographicSignature::Unsigned,
+            Err(_) => CryptographicSignature::Invalid,
+        }
+    }
+
+    fn is_signed(&self) -> bool {
+        !matches!(self, CryptographicSignature::Unsigned)
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            CryptographicSignature::Unsigned => "",
+            CryptographicSignature::Verified(verification) => match verification.status {
+                SigStatus::Good => "good",
+                SigStatus::Unknown => "unknown",
+                SigStatus::Bad => "bad",
+            },
+            CryptographicSignature::Invalid => "invalid",
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            CryptographicSignature::Verified(verification) => {
+                verification.key.clone().unwrap_or_default()
+            }
+            _ => "".to_owned(),
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            CryptographicSignature::Verified(verification) => {
+                verification.display.clone().unwrap_or_default()
+            }
+            _ => "".to_owned(),
+        }
+    }
+}
+
+fn build_cryptographic_signature_method<'repo>(
+    language: &CommitTemplateLanguage<'repo, '_>,
+    _build_ctx: &BuildContext<CommitTemplatePropertyKind<'repo>>,
+    self_property: impl TemplateProperty<Commit, Output = CryptographicSignature> + 'repo,
+    function: &FunctionCallNode,
+) -> TemplateParseResult<CommitTemplatePropertyKind<'repo>> {
+    let property = match function.name {
+        "status" => {
+            template_parser::expect_no_arguments(function)?;
+            language.wrap_string(TemplateFunction::new(self_property, |sig| {
+                sig.status().to_owned()
+            }))
+        }
+        "key" => {
+            template_parser::expect_no_arguments(function)?;
+            language.wrap_string(TemplateFunction::new(self_property, |sig| sig.key()))
+        }
+        "display" => {
+            template_parser::expect_no_arguments(function)?;
+            language.wrap_string(TemplateFunction::new(self_property, |sig| sig.display()))
+        }
+        _ => {
+            return Err(TemplateParseError::no_such_method(
+                "CryptographicSignature",
+                function,
+            ))
+        }
+    };
+    Ok(property)
+}
+
 pub fn parse<'repo>(
     repo: &'repo dyn Repo,
     workspace_id: &WorkspaceId,
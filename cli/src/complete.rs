@@ -0,0 +1,259 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dynamic command-line completion of values that depend on the repo, such as
+//! revisions, branches, and paths.
+
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+
+use clap::{Arg, ArgAction, Command, ValueHint};
+use itertools::Itertools as _;
+use jj_lib::repo::Repo as _;
+use jj_lib::revset::RevsetIteratorExt as _;
+
+use crate::cli_util::{
+    short_change_hash, CommandError, RemoteBranchNamePattern, RevisionArg, WorkspaceCommandHelper,
+};
+use crate::git_util::get_git_repo;
+use crate::ui::Ui;
+
+/// At most this many change ids are offered when completing a revision.
+const MAX_CHANGE_ID_CANDIDATES: usize = 100;
+
+/// What the word being completed is expected to be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A revision, completed with branches, tags, and change ids.
+    Revision,
+    /// The name of a local branch.
+    Branch,
+    /// A remote branch as `<name>@<remote>`.
+    RemoteBranch,
+    /// The name of a Git remote.
+    Remote,
+    /// A path in the given revision.
+    Path { revision: String },
+}
+
+/// Finds what the last of the command-line `words` is expected to be. The
+/// first word is the program name. Returns `None` if the word isn't a value
+/// that depends on the repo, e.g. if it's a flag or a subcommand name, which
+/// the static completions already handle.
+pub fn completion_kind(app: &Command, words: &[String]) -> Option<CompletionKind> {
+    let (current, preceding) = words.get(1..)?.split_last()?;
+    let mut commands = vec![app];
+    let mut pending_option: Option<&Arg> = None;
+    let mut option_values: HashMap<&str, &str> = HashMap::new();
+    let mut num_positionals = 0;
+    let mut only_positionals = false;
+    for word in preceding {
+        let command = *commands.last().unwrap();
+        if let Some(arg) = pending_option.take() {
+            option_values.insert(arg.get_id().as_str(), word);
+        } else if only_positionals || word == "-" || !word.starts_with('-') {
+            if num_positionals == 0 && !only_positionals {
+                if let Some(subcommand) = command.find_subcommand(word) {
+                    commands.push(subcommand);
+                    continue;
+                }
+            }
+            num_positionals += 1;
+        } else if word == "--" {
+            only_positionals = true;
+        } else if let Some(long) = word.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            let arg = find_option(&commands, |arg| arg.get_long() == Some(name));
+            match (arg, value) {
+                (Some(arg), Some(value)) => {
+                    option_values.insert(arg.get_id().as_str(), value);
+                }
+                (Some(arg), None) if arg.get_action().takes_values() => {
+                    pending_option = Some(arg);
+                }
+                _ => {}
+            }
+        } else {
+            // Flags can be combined as in `-ab`, and the last one may be
+            // followed by its value as in `-rREV`.
+            for (i, c) in word.char_indices().skip(1) {
+                let Some(arg) = find_option(&commands, |arg| arg.get_short() == Some(c)) else {
+                    break;
+                };
+                if arg.get_action().takes_values() {
+                    let value = &word[i + c.len_utf8()..];
+                    if value.is_empty() {
+                        pending_option = Some(arg);
+                    } else {
+                        option_values.insert(arg.get_id().as_str(), value);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    let command = *commands.last().unwrap();
+    let command_path = commands[1..].iter().map(|c| c.get_name()).collect_vec();
+    let arg = if let Some(arg) = pending_option {
+        arg
+    } else if !only_positionals && current.starts_with('-') {
+        return None;
+    } else {
+        let positionals = command.get_positionals().collect_vec();
+        match positionals.get(num_positionals) {
+            Some(&arg) => arg,
+            None => {
+                let arg = *positionals.last()?;
+                if !matches!(arg.get_action(), ArgAction::Append) {
+                    return None;
+                }
+                arg
+            }
+        }
+    };
+    arg_completion_kind(arg, &command_path, &option_values)
+}
+
+fn find_option<'a>(commands: &[&'a Command], predicate: impl Fn(&Arg) -> bool) -> Option<&'a Arg> {
+    // Global options are defined on the parent commands
+    commands
+        .iter()
+        .rev()
+        .find_map(|command| command.get_arguments().find(|arg| predicate(arg)))
+}
+
+fn arg_completion_kind(
+    arg: &Arg,
+    command_path: &[&str],
+    option_values: &HashMap<&str, &str>,
+) -> Option<CompletionKind> {
+    let value_type = arg.get_value_parser().type_id();
+    if value_type == TypeId::of::<RevisionArg>() {
+        return Some(CompletionKind::Revision);
+    }
+    if value_type == TypeId::of::<RemoteBranchNamePattern>() {
+        return Some(CompletionKind::RemoteBranch);
+    }
+    if matches!(
+        arg.get_value_hint(),
+        ValueHint::FilePath | ValueHint::DirPath | ValueHint::ExecutablePath
+    ) {
+        // Paths in the file system are left to the shell
+        return None;
+    }
+    match (arg.get_id().as_str(), command_path) {
+        ("branch" | "branches", _) => Some(CompletionKind::Branch),
+        ("names" | "glob" | "old", ["branch", subcommand, ..]) if *subcommand != "create" => {
+            Some(CompletionKind::Branch)
+        }
+        ("remote", ["git", "remote", "add"]) => None,
+        ("remote" | "remotes", _) | ("old", ["git", "remote", "rename"]) => {
+            Some(CompletionKind::Remote)
+        }
+        ("path" | "paths", _) => {
+            let revision = option_values.get("revision").copied().unwrap_or("@");
+            Some(CompletionKind::Path {
+                revision: revision.to_owned(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns the candidates of the given kind that start with `prefix`, with
+/// short descriptions (which may be empty).
+pub fn complete(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    kind: &CompletionKind,
+    prefix: &str,
+) -> Result<Vec<(String, String)>, CommandError> {
+    let repo = workspace_command.repo();
+    let view = repo.view();
+    let mut candidates = vec![];
+    match kind {
+        CompletionKind::Revision => {
+            for (name, _) in view.local_branches() {
+                candidates.push((name.to_owned(), "branch".to_owned()));
+            }
+            for ((name, remote), _) in view.all_remote_branches() {
+                if remote != jj_lib::git::REMOTE_NAME_FOR_LOCAL_GIT_REPO {
+                    candidates.push((format!("{name}@{remote}"), "remote branch".to_owned()));
+                }
+            }
+            for name in view.tags().keys() {
+                candidates.push((name.clone(), "tag".to_owned()));
+            }
+            let revset_string = workspace_command.settings().default_revset();
+            let expression = workspace_command.parse_revset(&revset_string, None)?;
+            let revset = workspace_command.evaluate_revset(expression)?;
+            for commit in revset
+                .iter()
+                .commits(repo.store())
+                .take(MAX_CHANGE_ID_CANDIDATES)
+            {
+                let commit = commit?;
+                let description = commit.description().lines().next().unwrap_or_default();
+                candidates.push((
+                    short_change_hash(commit.change_id()),
+                    description.to_owned(),
+                ));
+            }
+        }
+        CompletionKind::Branch => {
+            for (name, _) in view.local_branches() {
+                candidates.push((name.to_owned(), String::new()));
+            }
+        }
+        CompletionKind::RemoteBranch => {
+            for ((name, remote), _) in view.all_remote_branches() {
+                if remote != jj_lib::git::REMOTE_NAME_FOR_LOCAL_GIT_REPO {
+                    candidates.push((format!("{name}@{remote}"), String::new()));
+                }
+            }
+        }
+        CompletionKind::Remote => {
+            let Ok(git_repo) = get_git_repo(repo.store()) else {
+                return Ok(vec![]);
+            };
+            for name in git_repo.remotes()?.iter().flatten() {
+                candidates.push((name.to_owned(), String::new()));
+            }
+        }
+        CompletionKind::Path { revision } => {
+            let Ok(commit) = workspace_command.resolve_single_rev(revision, ui) else {
+                return Ok(vec![]);
+            };
+            // Only complete up to the next directory separator, like shells do
+            let mut paths = BTreeSet::new();
+            for (path, _) in commit.tree()?.entries() {
+                let path = workspace_command.format_file_path(&path);
+                if let Some(rest) = path.strip_prefix(prefix) {
+                    let end = rest.find('/').map_or(path.len(), |i| prefix.len() + i + 1);
+                    paths.insert(path[..end].to_owned());
+                }
+            }
+            candidates.extend(paths.into_iter().map(|path| (path, String::new())));
+        }
+    }
+    Ok(candidates
+        .into_iter()
+        .filter(|(value, _)| value.starts_with(prefix))
+        .unique_by(|(value, _)| value.clone())
+        .collect())
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -20,10 +21,13 @@ use std::{env, fmt};
 
 use config::Source;
 use itertools::Itertools;
-use jj_lib::settings::ConfigResultExt as _;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _};
+use jj_lib::workspace::WorkspaceLoader;
 use thiserror::Error;
 use tracing::instrument;
 
+use crate::config_schema::{check_config_file, ConfigProblem};
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error(transparent)]
@@ -32,18 +36,35 @@ pub enum ConfigError {
     AmbiguousSource(PathBuf, PathBuf),
     #[error(transparent)]
     ConfigCreateError(#[from] std::io::Error),
+    #[error("Invalid pattern in conditional config: {0}")]
+    ConditionPattern(#[from] glob::PatternError),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ConfigSource {
     Default,
     Env,
+    Project,
     // TODO: Track explicit file paths, especially for when user config is a dir.
     User,
     Repo,
     CommandArg,
 }
 
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfigSource::Default => "default",
+            ConfigSource::Env => "env",
+            ConfigSource::Project => "project",
+            ConfigSource::User => "user",
+            ConfigSource::Repo => "repo",
+            ConfigSource::CommandArg => "cli",
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct AnnotatedValue {
     pub path: Vec<String>,
@@ -57,19 +78,31 @@ pub struct AnnotatedValue {
 /// Sources from the lowest precedence:
 /// 1. Default
 /// 2. Base environment variables
-/// 3. [User config](https://github.com/martinvonz/jj/blob/main/docs/config.md#configuration)
-/// 4. Repo config `.jj/repo/config.toml`
-/// 5. TODO: Workspace config `.jj/config.toml`
-/// 6. Override environment variables
-/// 7. Command-line arguments `--config-toml`
+/// 3. Trusted project config `.jjconfig.toml` in the workspace root
+/// 4. [User config](https://github.com/martinvonz/jj/blob/main/docs/config.md#configuration)
+/// 5. Repo config `.jj/repo/config.toml`
+/// 6. TODO: Workspace config `.jj/config.toml`
+/// 7. Override environment variables
+/// 8. Command-line arguments `--config-toml`
+///
+/// The `[[conditional]]` sections of the user and repo configs are applied on
+/// top of their layer if they match the condition context.
 #[derive(Clone, Debug)]
 pub struct LayeredConfigs {
     default: config::Config,
     env_base: config::Config,
+    project: Option<config::Config>,
     user: Option<config::Config>,
     repo: Option<config::Config>,
     env_overrides: config::Config,
     arg_overrides: Option<config::Config>,
+    condition_context: ConfigConditionContext,
+    /// The file that the project config was read from.
+    project_file: Option<PathBuf>,
+    /// The files that the user config was read from.
+    user_files: Vec<PathBuf>,
+    /// The file that the repo config was read from.
+    repo_file: Option<PathBuf>,
 }
 
 impl LayeredConfigs {
@@ -78,27 +111,72 @@ impl LayeredConfigs {
         LayeredConfigs {
             default,
             env_base: env_base(),
+            project: None,
             user: None,
             repo: None,
             env_overrides: env_overrides(),
             arg_overrides: None,
+            condition_context: ConfigConditionContext::default(),
+            project_file: None,
+            user_files: vec![],
+            repo_file: None,
         }
     }
 
+    /// Sets the context that conditional configs are matched against. Configs
+    /// read after this call are affected.
+    pub fn set_condition_context(&mut self, context: ConfigConditionContext) {
+        self.condition_context = context;
+    }
+
     #[instrument]
     pub fn read_user_config(&mut self) -> Result<(), ConfigError> {
-        self.user = existing_config_path()?
-            .map(|path| read_config_path(&path))
+        let path = existing_config_path()?;
+        self.user_files = path
+            .as_deref()
+            .map(config_files_in_path)
+            .unwrap_or_default();
+        self.user = path
+            .map(|_| read_config_files(&self.user_files, &self.condition_context))
             .transpose()?;
         Ok(())
     }
 
     #[instrument]
     pub fn read_repo_config(&mut self, repo_path: &Path) -> Result<(), ConfigError> {
-        self.repo = Some(read_config_file(&repo_path.join("config.toml"))?);
+        let path = repo_path.join("config.toml");
+        let config = read_config_file(&path)?;
+        self.repo = Some(apply_conditional_configs(config, &self.condition_context)?);
+        self.repo_file = Some(path);
         Ok(())
     }
 
+    /// Adds the project config as the layer below the user config. The caller
+    /// is responsible for checking that the user trusts it.
+    ///
+    /// Returns the top-level keys that were ignored because a project config
+    /// isn't allowed to set them.
+    pub fn set_project_config(
+        &mut self,
+        project_config: &ProjectConfig,
+    ) -> Result<Vec<String>, ConfigError> {
+        let (config, ignored_keys) = project_config.parse()?;
+        self.project = Some(config);
+        self.project_file = Some(project_config.path.clone());
+        Ok(ignored_keys)
+    }
+
+    /// Checks the project, user and repo config files against the config
+    /// schema.
+    pub fn check_config_files(&self) -> Vec<ConfigProblem> {
+        self.project_file
+            .iter()
+            .chain(&self.user_files)
+            .chain(&self.repo_file)
+            .flat_map(|path| check_config_file(path))
+            .collect()
+    }
+
     pub fn parse_config_args(&mut self, toml_strs: &[String]) -> Result<(), ConfigError> {
         let config = toml_strs
             .iter()
@@ -126,6 +204,7 @@ impl LayeredConfigs {
         let config_sources = [
             (ConfigSource::Default, Some(&self.default)),
             (ConfigSource::Env, Some(&self.env_base)),
+            (ConfigSource::Project, self.project.as_ref()),
             (ConfigSource::User, self.user.as_ref()),
             (ConfigSource::Repo, self.repo.as_ref()),
             (ConfigSource::Env, Some(&self.env_overrides)),
@@ -195,6 +274,250 @@ impl LayeredConfigs {
     }
 }
 
+/// Where jj is running, to be matched against the `when` conditions of
+/// `[[conditional]]` config sections.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigConditionContext {
+    workspace_root: Option<PathBuf>,
+    repo_path: Option<PathBuf>,
+    hostname: String,
+    remote_urls: OnceCell<Vec<String>>,
+}
+
+impl ConfigConditionContext {
+    pub fn new(workspace_loader: Option<&WorkspaceLoader>) -> Self {
+        ConfigConditionContext {
+            workspace_root: workspace_loader.map(|loader| loader.workspace_root().to_owned()),
+            repo_path: workspace_loader.map(|loader| loader.repo_path().to_owned()),
+            hostname: whoami::hostname(),
+            remote_urls: OnceCell::new(),
+        }
+    }
+
+    /// The URLs of the remotes of the backing Git repo, if any. They're only
+    /// looked up if a condition needs them.
+    fn remote_urls(&self) -> &[String] {
+        self.remote_urls.get_or_init(|| {
+            self.repo_path
+                .as_deref()
+                .map(read_git_remote_urls)
+                .unwrap_or_default()
+        })
+    }
+}
+
+fn read_git_remote_urls(repo_path: &Path) -> Vec<String> {
+    let store_path = repo_path.join("store");
+    let Ok(git_target) = std::fs::read_to_string(store_path.join("git_target")) else {
+        return vec![];
+    };
+    let Ok(git_repo) = git2::Repository::open(store_path.join(git_target)) else {
+        return vec![];
+    };
+    let Ok(remote_names) = git_repo.remotes() else {
+        return vec![];
+    };
+    remote_names
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let remote = git_repo.find_remote(name).ok()?;
+            remote.url().map(|url| url.to_owned())
+        })
+        .collect()
+}
+
+/// A `[[conditional]]` config section.
+#[derive(Debug, serde::Deserialize)]
+struct ConditionalConfig {
+    #[serde(default)]
+    when: ConfigCondition,
+    #[serde(default)]
+    config: config::Map<String, config::Value>,
+}
+
+/// The conditions of a `[[conditional]]` config section, which all have to
+/// match for the section to apply. Each of them matches if any of its items
+/// does.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigCondition {
+    /// Directories that the workspace has to be in.
+    repositories: Option<Vec<String>>,
+    /// Glob patterns for the URLs of the remotes of the backing Git repo.
+    remote_urls: Option<Vec<String>>,
+    /// Glob patterns for the hostname.
+    hostnames: Option<Vec<String>>,
+}
+
+impl ConfigCondition {
+    fn matches(&self, context: &ConfigConditionContext) -> Result<bool, ConfigError> {
+        if let Some(repositories) = &self.repositories {
+            let Some(workspace_root) = &context.workspace_root else {
+                return Ok(false);
+            };
+            let workspace_root = canonicalize_if_exists(workspace_root);
+            if !repositories
+                .iter()
+                .any(|dir| workspace_root.starts_with(canonicalize_if_exists(&expand_home(dir))))
+            {
+                return Ok(false);
+            }
+        }
+        if let Some(remote_urls) = &self.remote_urls {
+            if !any_glob_matches(remote_urls, context.remote_urls())? {
+                return Ok(false);
+            }
+        }
+        if let Some(hostnames) = &self.hostnames {
+            if !any_glob_matches(hostnames, &[&context.hostname])? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn any_glob_matches(patterns: &[String], values: &[impl AsRef<str>]) -> Result<bool, ConfigError> {
+    for pattern in patterns {
+        let pattern = glob::Pattern::new(pattern)?;
+        if values.iter().any(|value| pattern.matches(value.as_ref())) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home_dir)) => home_dir.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn canonicalize_if_exists(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+/// A table of config values, to be used as a config source.
+#[derive(Clone, Debug)]
+struct ConfigTable(config::Map<String, config::Value>);
+
+impl config::Source for ConfigTable {
+    fn clone_into_box(&self) -> Box<dyn config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<config::Map<String, config::Value>, config::ConfigError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Removes the `[[conditional]]` sections from the `config`, and applies the
+/// ones that match the `context` on top of it in order.
+fn apply_conditional_configs(
+    config: config::Config,
+    context: &ConfigConditionContext,
+) -> Result<config::Config, ConfigError> {
+    let mut table = config.collect()?;
+    let Some(conditionals) = table.remove("conditional") else {
+        return Ok(config);
+    };
+    let conditionals: Vec<ConditionalConfig> = conditionals.try_deserialize()?;
+    let mut builder = config::Config::builder().add_source(ConfigTable(table));
+    for conditional in conditionals {
+        if conditional.when.matches(context)? {
+            builder = builder.add_source(ConfigTable(conditional.config));
+        }
+    }
+    Ok(builder.build()?)
+}
+
+/// Name of the config file that a project can commit to the root of its tree
+/// to share settings with everyone working on it.
+pub const PROJECT_CONFIG_FILE: &str = ".jjconfig.toml";
+
+/// The top-level tables that a project config can set. Other settings, such
+/// as the editor or the command aliases, are left to the user.
+const PROJECT_CONFIG_TABLES: &[&str] = &["fix", "revset-aliases", "revsets", "template-aliases"];
+
+/// A project config found in the workspace root.
+///
+/// Since the file comes from whoever wrote the commit that's checked out, it's
+/// only used once the user trusts it. The trusted contents are recorded in the
+/// repo, so the user is asked again when the file changes.
+#[derive(Clone, Debug)]
+pub struct ProjectConfig {
+    path: PathBuf,
+    trust_path: PathBuf,
+    text: String,
+}
+
+impl ProjectConfig {
+    /// Reads the project config in `workspace_root`, if there is one.
+    pub fn load(workspace_root: &Path, repo_path: &Path) -> Result<Option<Self>, ConfigError> {
+        let path = workspace_root.join(PROJECT_CONFIG_FILE);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(ProjectConfig {
+            path,
+            trust_path: repo_path.join("trusted-project-config.toml"),
+            text,
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the user trusted the current contents of the file.
+    pub fn is_trusted(&self) -> bool {
+        std::fs::read_to_string(&self.trust_path).is_ok_and(|trusted| trusted == self.text)
+    }
+
+    /// Whether the user trusted an earlier version of the file.
+    pub fn was_trusted(&self) -> bool {
+        self.trust_path.exists()
+    }
+
+    /// Records that the user trusts the current contents of the file.
+    pub fn trust(&self) -> std::io::Result<()> {
+        std::fs::write(&self.trust_path, &self.text)
+    }
+
+    /// Parses the contents that are checked for trust, dropping the top-level
+    /// keys that aren't allowed.
+    fn parse(&self) -> Result<(config::Config, Vec<String>), ConfigError> {
+        let mut table = config::File::from_str(&self.text, config::FileFormat::Toml)
+            .collect()
+            .map_err(|err| match err {
+                config::ConfigError::FileParse { uri: None, cause } => {
+                    config::ConfigError::FileParse {
+                        uri: Some(self.path.to_string_lossy().into_owned()),
+                        cause,
+                    }
+                }
+                err => err,
+            })?;
+        let ignored_keys = table
+            .keys()
+            .filter(|key| !PROJECT_CONFIG_TABLES.contains(&key.as_str()))
+            .sorted()
+            .cloned()
+            .collect_vec();
+        for key in &ignored_keys {
+            table.remove(key);
+        }
+        let config = config::Config::builder()
+            .add_source(ConfigTable(table))
+            .build()?;
+        Ok((config, ignored_keys))
+    }
+}
+
 enum ConfigPath {
     /// Existing config file path.
     Existing(PathBuf),
@@ -344,7 +667,8 @@ pub fn default_config() -> config::Config {
         };
     }
     let mut builder = config::Config::builder()
-        .add_source(from_toml!("config/colors.toml"))
+        .add_source(from_toml!("config/themes.toml"))
+        .add_source(from_toml!("config/diff_drivers.toml"))
         .add_source(from_toml!("config/merge_tools.toml"))
         .add_source(from_toml!("config/misc.toml"))
         .add_source(from_toml!("config/revsets.toml"))
@@ -404,7 +728,9 @@ fn read_config_file(path: &Path) -> Result<config::Config, config::ConfigError>
         .build()
 }
 
-fn read_config_path(config_path: &Path) -> Result<config::Config, config::ConfigError> {
+/// Returns the config files at `config_path`, which is either a file or a
+/// directory of files.
+fn config_files_in_path(config_path: &Path) -> Vec<PathBuf> {
     let mut files = vec![];
     if config_path.is_dir() {
         if let Ok(read_dir) = config_path.read_dir() {
@@ -420,18 +746,23 @@ fn read_config_path(config_path: &Path) -> Result<config::Config, config::Config
     } else {
         files.push(config_path.to_owned());
     }
-
     files
+}
+
+fn read_config_files(
+    files: &[PathBuf],
+    condition_context: &ConfigConditionContext,
+) -> Result<config::Config, ConfigError> {
+    // Conditional configs are applied per file so that the `[[conditional]]`
+    // arrays of different files don't override each other.
+    let builder = files
         .iter()
-        .fold(config::Config::builder(), |builder, path| {
+        .try_fold(config::Config::builder(), |builder, path| {
             // TODO: Accept other formats and/or accept only certain file extensions?
-            builder.add_source(
-                config::File::from(path.as_ref())
-                    .required(false)
-                    .format(config::FileFormat::Toml),
-            )
-        })
-        .build()
+            let config = apply_conditional_configs(read_config_file(path)?, condition_context)?;
+            Ok::<_, ConfigError>(builder.add_source(config))
+        })?;
+    Ok(builder.build()?)
 }
 
 /// Command name and arguments specified by config.
@@ -473,10 +804,11 @@ impl CommandNameAndArgs {
         }
     }
 
-    /// Returns process builder configured with this.
+    /// Returns process builder configured with this. Home directory and
+    /// environment variables in the command name are expanded.
     pub fn to_command(&self) -> Command {
         let (name, args) = self.split_name_and_args();
-        let mut cmd = Command::new(name.as_ref());
+        let mut cmd = Command::new(expand_config_path(&name));
         if let CommandNameAndArgs::Structured { env, .. } = self {
             cmd.envs(env);
         }
@@ -601,10 +933,15 @@ mod tests {
         let layered_configs = LayeredConfigs {
             default: empty_config.to_owned(),
             env_base: empty_config.to_owned(),
+            project: None,
             user: None,
             repo: None,
             env_overrides: empty_config,
             arg_overrides: None,
+            condition_context: ConfigConditionContext::default(),
+            project_file: None,
+            user_files: vec![],
+            repo_file: None,
         };
         assert_eq!(layered_configs.resolved_config_values(&[]).unwrap(), []);
     }
@@ -627,10 +964,15 @@ mod tests {
         let layered_configs = LayeredConfigs {
             default: empty_config.to_owned(),
             env_base: env_base_config,
+            project: None,
             user: None,
             repo: Some(repo_config),
             env_overrides: empty_config,
             arg_overrides: None,
+            condition_context: ConfigConditionContext::default(),
+            project_file: None,
+            user_files: vec![],
+            repo_file: None,
         };
         // Note: "email" is alphabetized, before "name" from same layer.
         insta::assert_debug_snapshot!(
@@ -702,10 +1044,15 @@ mod tests {
         let layered_configs = LayeredConfigs {
             default: empty_config.to_owned(),
             env_base: empty_config.to_owned(),
+            project: None,
             user: Some(user_config),
             repo: Some(repo_config),
             env_overrides: empty_config,
             arg_overrides: None,
+            condition_context: ConfigConditionContext::default(),
+            project_file: None,
+            user_files: vec![],
+            repo_file: None,
         };
         insta::assert_debug_snapshot!(
             layered_configs
@@ -0,0 +1,441 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of config files against the config schema.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+use serde_spanned::Spanned;
+
+/// The JSON schema of the config, as printed by `jj util config-schema`.
+pub const CONFIG_SCHEMA: &str = include_str!("config-schema.json");
+
+/// A problem found in a config file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigProblem {
+    pub path: PathBuf,
+    /// The 1-based line number of the key with the problem.
+    pub line: usize,
+    pub message: String,
+    /// A similar key that was probably meant, if the key is unknown.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{path}:{line}: {message}",
+            path = self.path.display(),
+            line = self.line,
+            message = self.message
+        )
+    }
+}
+
+/// Checks the config file at `path` against the config schema. Files that
+/// can't be read or parsed are left to the config loader to report.
+pub fn check_config_file(path: &Path) -> Vec<ConfigProblem> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return vec![];
+    };
+    check_config_text(path, &text)
+}
+
+fn check_config_text(path: &Path, text: &str) -> Vec<ConfigProblem> {
+    let root = match toml_edit::de::from_str::<TomlNode>(text) {
+        Ok(root) => root,
+        Err(_) => return vec![],
+    };
+    let schema: JsonValue =
+        serde_json::from_str(CONFIG_SCHEMA).expect("config schema should be valid JSON");
+    let mut checker = SchemaChecker {
+        root_schema: &schema,
+        problems: vec![],
+    };
+    checker.check(&schema, &root, &[], 0);
+    checker
+        .problems
+        .into_iter()
+        .map(|(offset, message, suggestion)| ConfigProblem {
+            path: path.to_owned(),
+            line: text[..offset].matches('\n').count() + 1,
+            message,
+            suggestion,
+        })
+        .collect()
+}
+
+/// A parsed TOML value, with the positions of the keys in the file.
+#[derive(Debug)]
+enum TomlNode {
+    Scalar(JsonValue),
+    Array(Vec<TomlNode>),
+    Table(Vec<(Spanned<String>, TomlNode)>),
+}
+
+impl TomlNode {
+    fn type_name(&self) -> &'static str {
+        match self {
+            TomlNode::Scalar(JsonValue::Bool(_)) => "boolean",
+            TomlNode::Scalar(JsonValue::Number(number)) if number.is_f64() => "number",
+            TomlNode::Scalar(JsonValue::Number(_)) => "integer",
+            TomlNode::Scalar(_) => "string",
+            TomlNode::Array(_) => "array",
+            TomlNode::Table(_) => "object",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TomlNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TomlNodeVisitor)
+    }
+}
+
+struct TomlNodeVisitor;
+
+impl<'de> Visitor<'de> for TomlNodeVisitor {
+    type Value = TomlNode;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a TOML value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<TomlNode, E> {
+        Ok(TomlNode::Scalar(value.into()))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<TomlNode, E> {
+        Ok(TomlNode::Scalar(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<TomlNode, E> {
+        Ok(TomlNode::Scalar(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<TomlNode, E> {
+        Ok(TomlNode::Scalar(value.into()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<TomlNode, E> {
+        Ok(TomlNode::Scalar(value.into()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<TomlNode, A::Error> {
+        let mut items = vec![];
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(TomlNode::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TomlNode, A::Error> {
+        let mut entries = vec![];
+        while let Some(key) = map.next_key()? {
+            entries.push((key, map.next_value()?));
+        }
+        Ok(TomlNode::Table(entries))
+    }
+}
+
+/// Checks values against the subset of JSON schema that the config schema
+/// uses.
+struct SchemaChecker<'a> {
+    root_schema: &'a JsonValue,
+    /// Byte offset, message, and suggestion of each problem.
+    problems: Vec<(usize, String, Option<String>)>,
+}
+
+impl<'a> SchemaChecker<'a> {
+    /// Checks `node` at `key_path` against `schema`. `offset` is the position
+    /// of the key to report problems at.
+    fn check(&mut self, schema: &'a JsonValue, node: &TomlNode, key_path: &[&str], offset: usize) {
+        let schema = self.resolve_ref(schema);
+        let key = format_key_path(key_path);
+        if let Some(alternatives) = schema.get("oneOf").and_then(|v| v.as_array()) {
+            let matches_any = alternatives.iter().any(|alternative| {
+                let mut checker = SchemaChecker {
+                    root_schema: self.root_schema,
+                    problems: vec![],
+                };
+                checker.check(alternative, node, key_path, offset);
+                checker.problems.is_empty()
+            });
+            if !matches_any {
+                self.add(offset, format!(r#"Invalid value for config key "{key}""#));
+            }
+            return;
+        }
+        if let Some(types) = schema.get("type") {
+            let types = match types {
+                JsonValue::Array(types) => types.iter().filter_map(|t| t.as_str()).collect_vec(),
+                types => types.as_str().into_iter().collect_vec(),
+            };
+            let actual = node.type_name();
+            let type_matches = types.iter().any(|&expected| {
+                expected == actual || (expected == "number" && actual == "integer")
+            });
+            if !type_matches {
+                self.add(
+                    offset,
+                    format!(
+                        r#"Invalid type for config key "{key}": expected {expected}, found {actual}"#,
+                        expected = types.join(" or ")
+                    ),
+                );
+                return;
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(|v| v.as_array()) {
+            if !matches!(node, TomlNode::Scalar(value) if values.contains(value)) {
+                self.add(
+                    offset,
+                    format!(
+                        r#"Invalid value for config key "{key}": expected one of {values}"#,
+                        values = values.iter().join(", ")
+                    ),
+                );
+            }
+        }
+        if let TomlNode::Scalar(JsonValue::String(value)) = node {
+            if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+                let regex = regex::Regex::new(pattern).expect("config schema patterns are valid");
+                if !regex.is_match(value) {
+                    self.add(
+                        offset,
+                        format!(r#"Invalid value for config key "{key}": must match {pattern:?}"#),
+                    );
+                }
+            }
+        }
+        if let TomlNode::Scalar(JsonValue::Number(number)) = node {
+            let value = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(|v| v.as_f64()) {
+                if value < minimum {
+                    self.add(
+                        offset,
+                        format!(
+                            r#"Invalid value for config key "{key}": must be at least {minimum}"#
+                        ),
+                    );
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(|v| v.as_f64()) {
+                if value > maximum {
+                    self.add(
+                        offset,
+                        format!(
+                            r#"Invalid value for config key "{key}": must be at most {maximum}"#
+                        ),
+                    );
+                }
+            }
+        }
+        match node {
+            TomlNode::Scalar(_) => {}
+            TomlNode::Array(items) => {
+                if let Some(items_schema) = schema.get("items") {
+                    for item in items {
+                        self.check(items_schema, item, key_path, offset);
+                    }
+                }
+            }
+            TomlNode::Table(entries) => self.check_table(schema, entries, key_path),
+        }
+    }
+
+    fn check_table(
+        &mut self,
+        schema: &'a JsonValue,
+        entries: &[(Spanned<String>, TomlNode)],
+        key_path: &[&str],
+    ) {
+        let properties = schema.get("properties").and_then(|v| v.as_object());
+        let additional_properties = schema.get("additionalProperties");
+        for (name, value) in entries {
+            let offset = name.span().start;
+            let mut child_path = key_path.to_vec();
+            child_path.push(name.get_ref());
+            if let Some(property_schema) = properties.and_then(|p| p.get(name.get_ref())) {
+                self.check(property_schema, value, &child_path, offset);
+                continue;
+            }
+            match additional_properties {
+                Some(JsonValue::Bool(true)) => {}
+                Some(property_schema @ JsonValue::Object(_)) => {
+                    self.check(property_schema, value, &child_path, offset);
+                }
+                // Tables that list their keys are closed unless they say
+                // otherwise.
+                _ if properties.is_some() || additional_properties.is_some() => {
+                    let candidates =
+                        properties.map_or(vec![], |p| collect_similar(name.get_ref(), p.keys()));
+                    let suggestion = candidates.first().map(|candidate| {
+                        let mut suggested_path = key_path.to_vec();
+                        suggested_path.push(candidate);
+                        format_key_path(&suggested_path)
+                    });
+                    self.problems.push((
+                        offset,
+                        format!(
+                            r#"Unknown config key "{key}""#,
+                            key = format_key_path(&child_path)
+                        ),
+                        suggestion,
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Follows `$ref` pointers within the schema.
+    fn resolve_ref(&self, schema: &'a JsonValue) -> &'a JsonValue {
+        let mut schema = schema;
+        while let Some(pointer) = schema.get("$ref").and_then(|v| v.as_str()) {
+            schema = pointer
+                .strip_prefix('#')
+                .and_then(|pointer| self.root_schema.pointer(pointer))
+                .expect("config schema should only refer to itself");
+        }
+        schema
+    }
+
+    fn add(&mut self, offset: usize, message: String) {
+        self.problems.push((offset, message, None));
+    }
+}
+
+/// Returns the candidates that are similar to `name`, most similar first.
+fn collect_similar<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a String>,
+) -> Vec<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (strsim::jaro(name, candidate), candidate.as_str()))
+        // The threshold is the same as for revset function names.
+        .filter(|(similarity, _)| *similarity > 0.7)
+        .sorted_by(|(a, _), (b, _)| b.total_cmp(a))
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Formats the key path like a dotted TOML key.
+fn format_key_path(key_path: &[&str]) -> String {
+    key_path
+        .iter()
+        .map(|part| {
+            let is_bare = !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if is_bare {
+                part.to_string()
+            } else {
+                format!("{part:?}")
+            }
+        })
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Vec<String> {
+        check_config_text(Path::new("config.toml"), text)
+            .iter()
+            .map(|problem| match &problem.suggestion {
+                Some(suggestion) => format!("{problem} (did you mean {suggestion})"),
+                None => problem.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_default_config() {
+        for (name, text) in [
+            ("themes.toml", include_str!("config/themes.toml")),
+            (
+                "diff_drivers.toml",
+                include_str!("config/diff_drivers.toml"),
+            ),
+            ("merge_tools.toml", include_str!("config/merge_tools.toml")),
+            ("misc.toml", include_str!("config/misc.toml")),
+            ("revsets.toml", include_str!("config/revsets.toml")),
+            ("templates.toml", include_str!("config/templates.toml")),
+            ("unix.toml", include_str!("config/unix.toml")),
+            ("windows.toml", include_str!("config/windows.toml")),
+        ] {
+            assert_eq!(
+                check_config_text(Path::new(name), text),
+                vec![],
+                "{name} should match the schema"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_unknown_keys() {
+        assert_eq!(
+            check(
+                r#"
+                [ui]
+                difff-editor = "meld"
+                [merge-tools."my tool"]
+                program = "x"
+                [no-such-table]
+                key = 1
+                "#
+            ),
+            [
+                r#"config.toml:3: Unknown config key "ui.difff-editor" (did you mean ui.diff-editor)"#,
+                r#"config.toml:6: Unknown config key "no-such-table""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_invalid_values() {
+        assert_eq!(
+            check(
+                r##"
+                ui.color = "sometimes"
+                ui.paginate = "always"
+                ui.log-word-wrap = "yes"
+                user.name = ["a", "b"]
+                aliases.x = ["log", 1]
+                colors.commit_id = "purple"
+                colors.change_id = { fg = "#12345" }
+                "##
+            ),
+            [
+                r#"config.toml:2: Invalid value for config key "ui.color": expected one of "always", "never", "auto""#,
+                r#"config.toml:3: Invalid value for config key "ui.paginate": expected one of "never", "auto""#,
+                r#"config.toml:4: Invalid type for config key "ui.log-word-wrap": expected boolean, found string"#,
+                r#"config.toml:5: Invalid type for config key "user.name": expected string, found array"#,
+                r#"config.toml:6: Invalid type for config key "aliases.x": expected string, found integer"#,
+                r#"config.toml:7: Invalid value for config key "colors.commit_id""#,
+                r#"config.toml:8: Invalid value for config key "colors.change_id""#,
+            ]
+        );
+    }
+}
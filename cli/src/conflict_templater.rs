@@ -0,0 +1,159 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Template language for the conflicts listed by `jj resolve --list`.
+
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use jj_lib::backend::TreeValue;
+use jj_lib::file_util;
+use jj_lib::merge::MergedTreeValue;
+use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::store::Store;
+
+use crate::template_builder::{self, BuildContext, CoreTemplatePropertyKind, TemplateLanguage};
+use crate::template_parser::{
+    self, FunctionCallNode, TemplateAliasesMap, TemplateParseError, TemplateParseResult,
+};
+use crate::templater::{Template, TemplatePropertyFn};
+
+/// A conflicted path in a tree.
+#[derive(Clone, Debug)]
+pub struct ConflictEntry {
+    /// The 1-based position of the conflict among all the conflicts in the
+    /// tree, sorted by path.
+    pub number: usize,
+    pub path: RepoPathBuf,
+    pub value: MergedTreeValue,
+}
+
+struct ConflictTemplateLanguage {
+    store: Arc<Store>,
+    cwd: PathBuf,
+    workspace_root: PathBuf,
+}
+
+impl TemplateLanguage<'static> for ConflictTemplateLanguage {
+    type Context = ConflictEntry;
+    type Property = CoreTemplatePropertyKind<'static, ConflictEntry>;
+
+    template_builder::impl_core_wrap_property_fns!('static);
+
+    fn build_keyword(&self, name: &str, span: pest::Span) -> TemplateParseResult<Self::Property> {
+        build_conflict_keyword(self, name, span)
+    }
+
+    fn build_method(
+        &self,
+        build_ctx: &BuildContext<Self::Property>,
+        property: Self::Property,
+        function: &FunctionCallNode,
+    ) -> TemplateParseResult<Self::Property> {
+        template_builder::build_core_method(self, build_ctx, property, function)
+    }
+}
+
+fn build_conflict_keyword(
+    language: &ConflictTemplateLanguage,
+    name: &str,
+    span: pest::Span,
+) -> TemplateParseResult<CoreTemplatePropertyKind<'static, ConflictEntry>> {
+    fn wrap_fn<O, F: Fn(&ConflictEntry) -> O>(f: F) -> TemplatePropertyFn<F> {
+        TemplatePropertyFn(f)
+    }
+
+    let property = match name {
+        "number" => language.wrap_integer(wrap_fn(|entry| {
+            i64::try_from(entry.number).unwrap_or(i64::MAX)
+        })),
+        "path" => {
+            let cwd = language.cwd.clone();
+            let workspace_root = language.workspace_root.clone();
+            language.wrap_string(wrap_fn(move |entry| {
+                let path = entry.path.to_fs_path(&workspace_root);
+                file_util::relative_path(&cwd, &path)
+                    .to_str()
+                    .unwrap()
+                    .to_owned()
+            }))
+        }
+        "sides" => language.wrap_integer(wrap_fn(|entry| {
+            i64::try_from(entry.value.num_sides()).unwrap_or(i64::MAX)
+        })),
+        "types" => language.wrap_string_list(wrap_fn(|entry| {
+            entry
+                .value
+                .adds()
+                .map(|side| side_type(side.as_ref()).to_owned())
+                .collect()
+        })),
+        "sizes" => {
+            let store = language.store.clone();
+            language.wrap_string_list(wrap_fn(move |entry| {
+                entry
+                    .value
+                    .adds()
+                    .map(|side| side_size(&store, entry, side.as_ref()).to_string())
+                    .collect()
+            }))
+        }
+        _ => return Err(TemplateParseError::no_such_keyword(name, span)),
+    };
+    Ok(property)
+}
+
+fn side_type(side: Option<&TreeValue>) -> &'static str {
+    match side {
+        None => "absent",
+        Some(TreeValue::File {
+            executable: false, ..
+        }) => "file",
+        Some(TreeValue::File {
+            executable: true, ..
+        }) => "executable",
+        Some(TreeValue::Symlink(_)) => "symlink",
+        Some(TreeValue::Tree(_)) => "directory",
+        Some(TreeValue::GitSubmodule(_)) => "git-submodule",
+        Some(TreeValue::Conflict(_)) => "conflict",
+    }
+}
+
+fn side_size(store: &Store, entry: &ConflictEntry, side: Option<&TreeValue>) -> u64 {
+    match side {
+        Some(TreeValue::File { id, .. }) => {
+            let mut reader = store.read_file(&entry.path, id).unwrap();
+            std::io::copy(&mut reader.by_ref(), &mut std::io::sink()).unwrap()
+        }
+        Some(TreeValue::Symlink(id)) => store.read_symlink(&entry.path, id).unwrap().len() as u64,
+        _ => 0,
+    }
+}
+
+pub fn parse(
+    store: &Arc<Store>,
+    cwd: &Path,
+    workspace_root: &Path,
+    template_text: &str,
+    aliases_map: &TemplateAliasesMap,
+) -> TemplateParseResult<Box<dyn Template<ConflictEntry>>> {
+    let language = ConflictTemplateLanguage {
+        store: store.clone(),
+        cwd: cwd.to_owned(),
+        workspace_root: workspace_root.to_owned(),
+    };
+    let node = template_parser::parse(template_text, aliases_map)?;
+    template_builder::build(&language, &node)
+}
@@ -1,11 +1,20 @@
+use std::collections::HashMap;
+use std::fs;
+
 use itertools::Itertools;
+use jj_lib::backend::CommitId;
 use jj_lib::commit::Commit;
 use jj_lib::matchers::EverythingMatcher;
 use jj_lib::merged_tree::MergedTree;
 use jj_lib::repo::ReadonlyRepo;
-use jj_lib::settings::UserSettings;
+use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
+use regex::Regex;
 
-use crate::cli_util::{edit_temp_file, CommandError, WorkspaceCommandHelper};
+use crate::cli_util::{
+    edit_temp_file, user_error, user_error_with_hint, user_error_with_message, CommandError,
+    WorkspaceCommandHelper, WorkspaceCommandTransaction,
+};
 use crate::diff_util::{self, DiffFormat};
 use crate::formatter::PlainTextFormatter;
 use crate::text_util;
@@ -16,11 +25,13 @@ pub fn edit_description(
     description: &str,
     settings: &UserSettings,
 ) -> Result<String, CommandError> {
+    // Keep the instructions above the ignored part, which may be long
+    let (description, ignored) = split_ignored_part(description);
     let description = format!(
         r#"{}
 JJ: Lines starting with "JJ: " (like this one) will be removed.
-"#,
-        description
+{}"#,
+        description, ignored
     );
 
     let description = edit_temp_file(
@@ -34,11 +45,28 @@ JJ: Lines starting with "JJ: " (like this one) will be removed.
     // Normalize line ending, remove leading and trailing blank lines.
     let description = description
         .lines()
+        .take_while(|line| *line != IGNORE_REST_MARKER)
         .filter(|line| !line.starts_with("JJ: "))
         .join("\n");
     Ok(text_util::complete_newline(description.trim_matches('\n')))
 }
 
+/// Everything after this line in the editor is removed from the description.
+const IGNORE_REST_MARKER: &str = "JJ: ignore-rest";
+
+/// Splits the `text` at the line with the `IGNORE_REST_MARKER`, keeping the
+/// marker in the second part.
+fn split_ignored_part(text: &str) -> (&str, &str) {
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == IGNORE_REST_MARKER {
+            return text.split_at(pos);
+        }
+        pos += line.len();
+    }
+    (text, "")
+}
+
 pub fn combine_messages(
     repo: &ReadonlyRepo,
     source: &Commit,
@@ -46,23 +74,38 @@ pub fn combine_messages(
     settings: &UserSettings,
     abandon_source: bool,
 ) -> Result<String, CommandError> {
-    let description = if abandon_source {
-        if source.description().is_empty() {
-            destination.description().to_string()
-        } else if destination.description().is_empty() {
-            source.description().to_string()
-        } else {
-            let combined = "JJ: Enter a description for the combined commit.\n".to_string()
-                + "JJ: Description from the destination commit:\n"
-                + destination.description()
-                + "\nJJ: Description from the source commit:\n"
-                + source.description();
-            edit_description(repo, &combined, settings)?
-        }
+    if abandon_source {
+        combine_descriptions(
+            repo,
+            destination.description(),
+            source.description(),
+            settings,
+        )
     } else {
-        destination.description().to_string()
-    };
-    Ok(description)
+        Ok(destination.description().to_string())
+    }
+}
+
+/// Combines the descriptions of two commits that are being squashed, asking the
+/// user for the result if both are non-empty.
+pub fn combine_descriptions(
+    repo: &ReadonlyRepo,
+    destination: &str,
+    source: &str,
+    settings: &UserSettings,
+) -> Result<String, CommandError> {
+    if source.is_empty() {
+        Ok(destination.to_string())
+    } else if destination.is_empty() {
+        Ok(source.to_string())
+    } else {
+        let combined = "JJ: Enter a description for the combined commit.\n".to_string()
+            + "JJ: Description from the destination commit:\n"
+            + destination
+            + "\nJJ: Description from the source commit:\n"
+            + source;
+        edit_description(repo, &combined, settings)
+    }
 }
 
 pub fn description_template_for_describe(
@@ -79,17 +122,26 @@ pub fn description_template_for_describe(
         commit,
         &EverythingMatcher,
         &[DiffFormat::Summary],
+        usize::from(ui.term_width().unwrap_or(80)),
     )?;
-    let description = if commit.description().is_empty() {
-        settings.default_description()
+    let mut description = if commit.description().is_empty() {
+        default_description(settings, workspace_command)?
     } else {
         commit.description().to_owned()
     };
-    if diff_summary_bytes.is_empty() {
-        Ok(description)
-    } else {
-        Ok(description + "\n" + &diff_summary_to_description(&diff_summary_bytes))
+    if !diff_summary_bytes.is_empty() {
+        description.push('\n');
+        description.push_str(&diff_summary_to_description(&diff_summary_bytes));
+        let from_tree = merge_commit_trees(workspace_command.repo().as_ref(), &commit.parents())?;
+        description.push_str(&diff_for_editor(
+            ui,
+            settings,
+            workspace_command,
+            &from_tree,
+            &commit.tree()?,
+        )?);
     }
+    Ok(description)
 }
 
 pub fn description_template_for_commit(
@@ -110,23 +162,91 @@ pub fn description_template_for_commit(
         to_tree,
         &EverythingMatcher,
         &[DiffFormat::Summary],
+        usize::from(ui.term_width().unwrap_or(80)),
     )?;
     let mut template_chunks = Vec::new();
     if !intro.is_empty() {
         template_chunks.push(format!("JJ: {intro}\n"));
     }
     template_chunks.push(if overall_commit_description.is_empty() {
-        settings.default_description()
+        default_description(settings, workspace_command)?
     } else {
         overall_commit_description.to_owned()
     });
     if !diff_summary_bytes.is_empty() {
         template_chunks.push("\n".to_owned());
         template_chunks.push(diff_summary_to_description(&diff_summary_bytes));
+        template_chunks.push(diff_for_editor(
+            ui,
+            settings,
+            workspace_command,
+            from_tree,
+            to_tree,
+        )?);
     }
     Ok(template_chunks.concat())
 }
 
+/// Returns the diff to show below the description in the editor if
+/// `ui.description-diff` is enabled, or an empty string.
+fn diff_for_editor(
+    ui: &Ui,
+    settings: &UserSettings,
+    workspace_command: &WorkspaceCommandHelper,
+    from_tree: &MergedTree,
+    to_tree: &MergedTree,
+) -> Result<String, CommandError> {
+    let show_diff = settings
+        .config()
+        .get_bool("ui.description-diff")
+        .optional()?
+        .unwrap_or(false);
+    if !show_diff {
+        return Ok(String::new());
+    }
+    let mut diff_bytes = Vec::new();
+    diff_util::show_diff(
+        ui,
+        &mut PlainTextFormatter::new(&mut diff_bytes),
+        workspace_command,
+        from_tree,
+        to_tree,
+        &EverythingMatcher,
+        &[DiffFormat::Git(Default::default())],
+        usize::from(ui.term_width().unwrap_or(80)),
+    )?;
+    Ok(format!(
+        "{IGNORE_REST_MARKER}\nJJ: Everything below the line above will be removed.\n{}",
+        String::from_utf8_lossy(&diff_bytes)
+    ))
+}
+
+/// Returns the text to fill the editor with for an empty description, from
+/// `ui.default-description-file` or `ui.default-description`.
+fn default_description(
+    settings: &UserSettings,
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<String, CommandError> {
+    let Some(path) = settings
+        .config()
+        .get_string("ui.default-description-file")
+        .optional()?
+    else {
+        return Ok(settings.default_description());
+    };
+    // Relative paths are relative to the workspace root, so the file can be
+    // checked into the repo
+    let path = workspace_command
+        .workspace_root()
+        .join(expand_config_path(&path));
+    fs::read_to_string(&path).map_err(|err| {
+        user_error_with_message(
+            format!("Failed to read description template {}", path.display()),
+            err,
+        )
+    })
+}
+
 pub fn diff_summary_to_description(bytes: &[u8]) -> String {
     let text = std::str::from_utf8(bytes).expect(
         "Summary diffs and repo paths must always be valid UTF8.",
@@ -135,3 +255,324 @@ pub fn diff_summary_to_description(bytes: &[u8]) -> String {
     "JJ: This commit contains the following changes:\n".to_owned()
         + &textwrap::indent(text, "JJ:     ")
 }
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct DescriptionChecks {
+    max_subject_length: Option<usize>,
+    require_body: bool,
+    required_pattern: Option<String>,
+}
+
+/// Checks the `description` against the rules in `[description-checks]`.
+/// Empty descriptions are always accepted.
+pub fn check_description(settings: &UserSettings, description: &str) -> Result<(), CommandError> {
+    let checks: DescriptionChecks = settings
+        .config()
+        .get("description-checks")
+        .optional()?
+        .unwrap_or_default();
+    if description.trim().is_empty() {
+        return Ok(());
+    }
+    let mut problems = vec![];
+    let subject = description.lines().next().unwrap_or_default();
+    if let Some(max_length) = checks.max_subject_length {
+        if subject.chars().count() > max_length {
+            problems.push(format!(
+                "The subject is longer than {max_length} characters"
+            ));
+        }
+    }
+    if checks.require_body && description_body(description).trim().is_empty() {
+        problems.push("The description has no body".to_owned());
+    }
+    if let Some(pattern) = &checks.required_pattern {
+        let regex = Regex::new(pattern).map_err(|err| {
+            user_error_with_message(
+                format!("Invalid description-checks.required-pattern: {pattern}"),
+                err,
+            )
+        })?;
+        if !regex.is_match(description) {
+            problems.push(format!("The description doesn't match /{pattern}/"));
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(user_error_with_hint(
+        format!(
+            "The description doesn't pass the checks:\n{}",
+            problems
+                .iter()
+                .map(|problem| format!("  {problem}"))
+                .join("\n")
+        ),
+        "Fix the description, or use --skip-checks to skip the checks.",
+    ))
+}
+
+/// Returns the part of the `description` between the subject and the
+/// trailers.
+fn description_body(description: &str) -> &str {
+    let description = match split_trailers(description) {
+        Some((before_trailers, _)) => before_trailers,
+        None => description,
+    };
+    match description.split_once("\n\n") {
+        Some((_, body)) => body,
+        None => "",
+    }
+}
+
+/// Returns the trailer lines at the end of the `description`, like
+/// "Signed-off-by: Name <email>", or an empty list if its last paragraph isn't
+/// made of trailers. The first paragraph is never taken as trailers.
+pub fn parse_trailers(description: &str) -> Vec<&str> {
+    match split_trailers(description) {
+        Some((_, trailers)) => trailers.lines().collect(),
+        None => vec![],
+    }
+}
+
+/// Splits the `description` into the text before the trailers and the
+/// trailers paragraph, if there is one.
+fn split_trailers(description: &str) -> Option<(&str, &str)> {
+    let (body, last_paragraph) = description.trim_end().rsplit_once("\n\n")?;
+    let mut lines = last_paragraph.lines();
+    // Lines starting with whitespace continue the trailer above
+    let is_trailers = lines.next().is_some_and(is_trailer)
+        && lines.all(|line| is_trailer(line) || line.starts_with([' ', '\t']));
+    is_trailers.then_some((body, last_paragraph))
+}
+
+/// Returns whether the `line` is a "Key: value" trailer.
+pub fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(key, _)| {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// Appends the `trailers` that aren't in the `description` yet. Returns the
+/// `description` unchanged if there are none to add.
+pub fn add_trailers(description: &str, trailers: &[String]) -> String {
+    let existing = parse_trailers(description);
+    let new_trailers = trailers
+        .iter()
+        .map(|trailer| trailer.trim())
+        .filter(|trailer| !existing.contains(trailer))
+        .unique()
+        .collect_vec();
+    if new_trailers.is_empty() {
+        return description.to_owned();
+    }
+    let separator = if existing.is_empty() { "\n\n" } else { "\n" };
+    format!(
+        "{}{separator}{}\n",
+        description.trim_end(),
+        new_trailers.join("\n")
+    )
+}
+
+/// Removes the trailers that match any of the `patterns` from the
+/// `description`. A pattern is either a whole "Key: value" trailer, or a key
+/// that matches all trailers with that key, ignoring case.
+pub fn remove_trailers(description: &str, patterns: &[String]) -> String {
+    let Some((body, trailers)) = split_trailers(description) else {
+        return description.to_owned();
+    };
+    let matches = |line: &str| {
+        patterns.iter().any(|pattern| {
+            if is_trailer(pattern) {
+                line.trim_end() == pattern.trim()
+            } else {
+                line.split_once(':')
+                    .is_some_and(|(key, _)| key.eq_ignore_ascii_case(pattern.trim()))
+            }
+        })
+    };
+    let mut kept_lines = vec![];
+    let mut removing = false;
+    for line in trailers.lines() {
+        if !line.starts_with([' ', '\t']) {
+            removing = matches(line);
+        }
+        if !removing {
+            kept_lines.push(line);
+        }
+    }
+    if kept_lines.len() == trailers.lines().count() {
+        description.to_owned()
+    } else if kept_lines.is_empty() {
+        text_util::complete_newline(body)
+    } else {
+        format!("{body}\n\n{}\n", kept_lines.join("\n"))
+    }
+}
+
+/// Returns the trailers that `templates.commit_trailers` generates for the
+/// `commit`, one per line.
+pub fn commit_trailers(
+    settings: &UserSettings,
+    workspace_command: &WorkspaceCommandHelper,
+    commit: &Commit,
+) -> Result<Vec<String>, CommandError> {
+    let Some(template_text) = settings
+        .config()
+        .get_string("templates.commit_trailers")
+        .optional()?
+    else {
+        return Ok(vec![]);
+    };
+    let template = workspace_command.parse_commit_template(&template_text)?;
+    let mut output = Vec::new();
+    template.format(commit, &mut PlainTextFormatter::new(&mut output))?;
+    let output = String::from_utf8(output).expect("template output should be utf-8 bytes");
+    let mut trailers = vec![];
+    for line in output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        if !is_trailer(line) {
+            return Err(user_error(format!(
+                "Invalid trailer from templates.commit_trailers: {line}"
+            )));
+        }
+        trailers.push(line.to_owned());
+    }
+    Ok(trailers)
+}
+
+/// Adds the trailers from `templates.commit_trailers` to the new `description`
+/// of the `commit`, unless the description is empty.
+pub fn add_commit_trailers(
+    settings: &UserSettings,
+    workspace_command: &WorkspaceCommandHelper,
+    commit: &Commit,
+    description: String,
+) -> Result<String, CommandError> {
+    if description.is_empty() {
+        return Ok(description);
+    }
+    let trailers = commit_trailers(settings, workspace_command, commit)?;
+    Ok(add_trailers(&description, &trailers))
+}
+
+/// Sets the descriptions of the `commits`, which must be sorted with parents
+/// first, to the ones returned by `new_description`, and rebases the commits
+/// onto their rewritten parents. Returns the resulting commits in the same
+/// order.
+pub fn rewrite_descriptions(
+    tx: &mut WorkspaceCommandTransaction,
+    settings: &UserSettings,
+    commits: &[Commit],
+    mut new_description: impl FnMut(&Commit) -> Option<String>,
+) -> Result<Vec<Commit>, CommandError> {
+    let mut rewritten: HashMap<CommitId, CommitId> = HashMap::new();
+    let mut new_commits = vec![];
+    for commit in commits {
+        let new_parents = commit
+            .parent_ids()
+            .iter()
+            .map(|id| rewritten.get(id).unwrap_or(id).clone())
+            .collect_vec();
+        let description =
+            new_description(commit).filter(|description| description != commit.description());
+        if description.is_none() && new_parents == commit.parent_ids() {
+            new_commits.push(commit.clone());
+            continue;
+        }
+        let new_commit = tx
+            .mut_repo()
+            .rewrite_commit(settings, commit)
+            .set_parents(new_parents)
+            .set_description(description.unwrap_or_else(|| commit.description().to_owned()))
+            .write()?;
+        rewritten.insert(commit.id().clone(), new_commit.id().clone());
+        new_commits.push(new_commit);
+    }
+    Ok(new_commits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trailers() {
+        assert!(parse_trailers("").is_empty());
+        assert!(parse_trailers("fix: the bug\n").is_empty());
+        assert!(parse_trailers("Title\n\nNot: a\ntrailer block\n").is_empty());
+        assert_eq!(
+            parse_trailers("Title\n\nBody.\n\nBug: 123\nAcked-by: A\n  continued\n"),
+            vec!["Bug: 123", "Acked-by: A", "  continued"]
+        );
+    }
+
+    #[test]
+    fn test_split_ignored_part() {
+        assert_eq!(split_ignored_part("Subject\n"), ("Subject\n", ""));
+        assert_eq!(
+            split_ignored_part("Subject\nJJ: ignore-rest\ndiff\n"),
+            ("Subject\n", "JJ: ignore-rest\ndiff\n")
+        );
+        assert_eq!(
+            split_ignored_part("JJ: ignore-rest\n"),
+            ("", "JJ: ignore-rest\n")
+        );
+        // The marker must be a whole line
+        assert_eq!(
+            split_ignored_part("Subject JJ: ignore-rest\n"),
+            ("Subject JJ: ignore-rest\n", "")
+        );
+    }
+
+    #[test]
+    fn test_description_body() {
+        assert_eq!(description_body("Subject\n"), "");
+        assert_eq!(description_body("Subject\n\nBody.\n"), "Body.\n");
+        assert_eq!(description_body("Subject\n\nBug: 123\n"), "");
+        assert_eq!(
+            description_body("Subject\n\nBody.\n\nMore.\n\nBug: 123\n"),
+            "Body.\n\nMore."
+        );
+    }
+
+    #[test]
+    fn test_add_trailers() {
+        let trailers = vec!["Bug: 123".to_owned(), "Acked-by: A".to_owned()];
+        assert_eq!(
+            add_trailers("Title\n", &trailers),
+            "Title\n\nBug: 123\nAcked-by: A\n"
+        );
+        assert_eq!(
+            add_trailers("Title\n\nAcked-by: A\n", &trailers),
+            "Title\n\nAcked-by: A\nBug: 123\n"
+        );
+        let description = "Title\n\nBug: 123\nAcked-by: A";
+        assert_eq!(add_trailers(description, &trailers), description);
+    }
+
+    #[test]
+    fn test_remove_trailers() {
+        let description = "Title\n\nBody.\n\nBug: 123\nAcked-by: A\n  continued\nAcked-by: B\n";
+        assert_eq!(
+            remove_trailers(description, &["acked-by".to_owned()]),
+            "Title\n\nBody.\n\nBug: 123\n"
+        );
+        assert_eq!(
+            remove_trailers(description, &["Acked-by: B".to_owned()]),
+            "Title\n\nBody.\n\nBug: 123\nAcked-by: A\n  continued\n"
+        );
+        assert_eq!(
+            remove_trailers(description, &["Bug".to_owned(), "Acked-by".to_owned()]),
+            "Title\n\nBody.\n"
+        );
+        assert_eq!(
+            remove_trailers(description, &["Other".to_owned()]),
+            description
+        );
+    }
+}
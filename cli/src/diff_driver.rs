@@ -0,0 +1,329 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Language-specific settings for diffing files, selected by file name or by
+//! the `diff` attribute in `.gitattributes`.
+//!
+//! A driver specifies which lines start a function (or other section) of a
+//! file, so hunk headers can show the function a change is in.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use config::ConfigError;
+use jj_lib::repo_path::RepoPath;
+use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use regex::bytes::Regex;
+
+use crate::cli_util::{user_error_with_message, CommandError};
+
+/// Lines starting with a letter, `_`, or `$` are function lines for files
+/// without a driver, like in Git.
+const DEFAULT_FUNCTION_REGEX: &str = "^[[:alpha:]$_].*";
+
+/// Maximum length of the function text shown in hunk headers.
+const MAX_HEADING_LEN: usize = 80;
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct DiffDriverConfig {
+    files: Vec<String>,
+    function_regex: Option<String>,
+}
+
+/// Finds the lines that start a function.
+#[derive(Clone, Debug)]
+pub struct FunctionMatcher {
+    regex: Regex,
+}
+
+impl FunctionMatcher {
+    fn new(regex: &str) -> Result<Self, regex::Error> {
+        Ok(FunctionMatcher {
+            regex: Regex::new(regex)?,
+        })
+    }
+
+    /// Returns true if the `line` starts a function.
+    pub fn is_function_line(&self, line: &[u8]) -> bool {
+        self.regex.is_match(strip_newline(line))
+    }
+
+    /// Returns the text to show in a hunk header for the `line`, or `None` if
+    /// the line doesn't start a function. The first capture group is used if
+    /// the regex has one.
+    pub fn heading(&self, line: &[u8]) -> Option<String> {
+        let captures = self.regex.captures(strip_newline(line))?;
+        let text = captures.get(1).or_else(|| captures.get(0))?.as_bytes();
+        let mut heading = String::from_utf8_lossy(text).trim_end().to_owned();
+        if heading.len() > MAX_HEADING_LEN {
+            let mut end = MAX_HEADING_LEN;
+            while !heading.is_char_boundary(end) {
+                end -= 1;
+            }
+            heading.truncate(end);
+        }
+        Some(heading)
+    }
+}
+
+fn strip_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// File name pattern as used in `.gitattributes`. Patterns without a `/`
+/// match the file name in any directory.
+#[derive(Clone, Debug)]
+struct FilePattern {
+    pattern: glob::Pattern,
+    match_full_path: bool,
+}
+
+impl FilePattern {
+    fn new(pattern: &str) -> Result<Self, glob::PatternError> {
+        let match_full_path = pattern.contains('/');
+        Ok(FilePattern {
+            pattern: glob::Pattern::new(pattern.trim_start_matches('/'))?,
+            match_full_path,
+        })
+    }
+
+    fn matches(&self, path: &RepoPath) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        if self.match_full_path {
+            self.pattern
+                .matches_with(path.as_internal_file_string(), options)
+        } else {
+            path.split()
+                .is_some_and(|(_, name)| self.pattern.matches_with(name.as_str(), options))
+        }
+    }
+}
+
+/// Diff drivers from the config and the `diff` attributes of files.
+#[derive(Clone, Debug)]
+pub struct DiffDrivers {
+    drivers: BTreeMap<String, (Vec<FilePattern>, FunctionMatcher)>,
+    /// File patterns from `.gitattributes` and the driver they select.
+    /// Later patterns take precedence.
+    attributes: Vec<(FilePattern, Option<String>)>,
+    default: FunctionMatcher,
+}
+
+impl DiffDrivers {
+    /// Loads the drivers from the settings and the `.gitattributes` file at
+    /// the workspace root.
+    pub fn load(settings: &UserSettings, workspace_root: &Path) -> Result<Self, CommandError> {
+        let attributes_path = workspace_root.join(".gitattributes");
+        let gitattributes = match std::fs::read_to_string(&attributes_path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(user_error_with_message(
+                    format!("Failed to read {}", attributes_path.display()),
+                    err,
+                ))
+            }
+        };
+        Ok(Self::from_config(settings.config(), &gitattributes)?)
+    }
+
+    fn from_config(config: &config::Config, gitattributes: &str) -> Result<Self, ConfigError> {
+        const TABLE_KEY: &str = "diff-drivers";
+        let driver_configs: BTreeMap<String, DiffDriverConfig> =
+            config.get(TABLE_KEY).optional()?.unwrap_or_default();
+        let mut drivers = BTreeMap::new();
+        for (name, driver_config) in driver_configs {
+            let files = driver_config
+                .files
+                .iter()
+                .map(|pattern| FilePattern::new(pattern))
+                .collect::<Result<_, _>>()
+                .map_err(|err| ConfigError::Message(format!("{TABLE_KEY}.{name}.files: {err}")))?;
+            let regex = driver_config
+                .function_regex
+                .as_deref()
+                .unwrap_or(DEFAULT_FUNCTION_REGEX);
+            let matcher = FunctionMatcher::new(regex).map_err(|err| {
+                ConfigError::Message(format!("{TABLE_KEY}.{name}.function-regex: {err}"))
+            })?;
+            drivers.insert(name, (files, matcher));
+        }
+        Ok(DiffDrivers {
+            drivers,
+            attributes: parse_diff_attributes(gitattributes),
+            default: FunctionMatcher::new(DEFAULT_FUNCTION_REGEX).unwrap(),
+        })
+    }
+
+    /// Returns the function matcher of the driver for the file at `path`.
+    ///
+    /// A `diff=<name>` attribute selects the named driver. Otherwise, the
+    /// first driver (in order of name) with a matching file pattern is used.
+    pub fn function_matcher(&self, path: &RepoPath) -> &FunctionMatcher {
+        let attribute = self
+            .attributes
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(path));
+        if let Some((_, name)) = attribute {
+            if let Some(name) = name {
+                return self
+                    .drivers
+                    .get(name)
+                    .map_or(&self.default, |(_, matcher)| matcher);
+            }
+        } else if let Some((_, matcher)) = self
+            .drivers
+            .values()
+            .find(|(files, _)| files.iter().any(|pattern| pattern.matches(path)))
+        {
+            return matcher;
+        }
+        &self.default
+    }
+}
+
+/// Parses the `diff` attributes from a `.gitattributes` file. Patterns that
+/// set the attribute without a driver name (or unset it) are included with
+/// no name. Invalid patterns are ignored, like Git does.
+fn parse_diff_attributes(text: &str) -> Vec<(FilePattern, Option<String>)> {
+    let mut attributes = vec![];
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(pattern) = fields.next().filter(|pattern| !pattern.starts_with('#')) else {
+            continue;
+        };
+        let driver = fields.rev().find_map(|attribute| {
+            if let Some(name) = attribute.strip_prefix("diff=") {
+                Some(Some(name.to_owned()))
+            } else {
+                matches!(attribute, "diff" | "-diff" | "!diff").then_some(None)
+            }
+        });
+        if let (Some(driver), Ok(pattern)) = (driver, FilePattern::new(pattern)) {
+            attributes.push((pattern, driver));
+        }
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_path(value: &str) -> &RepoPath {
+        RepoPath::from_internal_string(value)
+    }
+
+    fn drivers(config_text: &str, gitattributes: &str) -> DiffDrivers {
+        let config = config::Config::builder()
+            .add_source(crate::config::default_config())
+            .add_source(config::File::from_str(
+                config_text,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        DiffDrivers::from_config(&config, gitattributes).unwrap()
+    }
+
+    #[test]
+    fn test_heading() {
+        let drivers = drivers("", "");
+        let rust = drivers.function_matcher(repo_path("src/lib.rs"));
+        assert_eq!(
+            rust.heading(b"pub(crate) async fn foo() {\n").as_deref(),
+            Some("pub(crate) async fn foo() {")
+        );
+        assert_eq!(
+            rust.heading(b"    impl<T> Foo for Bar<T> {\n").as_deref(),
+            Some("impl<T> Foo for Bar<T> {")
+        );
+        assert_eq!(rust.heading(b"    let x = 1;\n"), None);
+        assert_eq!(rust.heading(b"mod foo;\n"), None);
+
+        let python = drivers.function_matcher(repo_path("foo.py"));
+        assert_eq!(
+            python.heading(b"    def foo(self):\n").as_deref(),
+            Some("def foo(self):")
+        );
+        assert!(!python.is_function_line(b"x = 1\n"));
+
+        // The default matches lines starting with a letter
+        let default = drivers.function_matcher(repo_path("file.txt"));
+        assert_eq!(default.heading(b"foo  \r\n").as_deref(), Some("foo"));
+        assert_eq!(default.heading(b" foo\n"), None);
+        let long_line = "x".repeat(100);
+        assert_eq!(default.heading(long_line.as_bytes()).unwrap().len(), 80);
+    }
+
+    #[test]
+    fn test_function_matcher_for_path() {
+        let drivers = drivers(
+            r#"
+            diff-drivers.custom.files = ["custom/*.txt"]
+            diff-drivers.custom.function-regex = '^== (.*)'
+            "#,
+            "# comment\n*.foo diff=custom\nlegacy.py -diff\nunknown.txt diff=unknown\n",
+        );
+        let is_custom = |path| {
+            drivers
+                .function_matcher(repo_path(path))
+                .is_function_line(b"== section")
+        };
+        assert!(is_custom("custom/file.txt"));
+        assert!(!is_custom("dir/custom/file.txt"));
+        assert!(is_custom("file.foo"));
+        assert!(is_custom("dir/file.foo"));
+        assert!(!is_custom("other.txt"));
+        assert_eq!(
+            drivers
+                .function_matcher(repo_path("custom/file.txt"))
+                .heading(b"== section\n")
+                .as_deref(),
+            Some("section")
+        );
+
+        // The attribute overrides the file patterns of the drivers
+        let is_python = |path| {
+            drivers
+                .function_matcher(repo_path(path))
+                .is_function_line(b"    def foo():")
+        };
+        assert!(is_python("main.py"));
+        assert!(!is_python("legacy.py"));
+        // Unknown drivers fall back to the default
+        assert!(drivers
+            .function_matcher(repo_path("unknown.txt"))
+            .is_function_line(b"foo"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(
+                "diff-drivers.bad.function-regex = '('",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        assert!(DiffDrivers::from_config(&config, "").is_err());
+    }
+}
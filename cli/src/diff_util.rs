@@ -13,18 +13,19 @@
 // limitations under the License.
 
 use std::cmp::max;
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
 use std::ops::Range;
 
-use futures::{try_join, Stream, StreamExt};
+use futures::{stream, try_join, Stream, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use jj_lib::backend::{BackendResult, TreeValue};
 use jj_lib::commit::Commit;
 use jj_lib::conflicts::{materialize_tree_value, MaterializedTreeValue};
-use jj_lib::diff::{Diff, DiffHunk};
+use jj_lib::copies::{self, CopyOperation, CopyRecord};
+use jj_lib::diff::{Diff, DiffGranularity, DiffHunk, LineCompareMode};
 use jj_lib::files::DiffLine;
-use jj_lib::matchers::Matcher;
+use jj_lib::matchers::{EverythingMatcher, Matcher};
 use jj_lib::merge::MergedTreeValue;
 use jj_lib::merged_tree::{MergedTree, TreeDiffStream};
 use jj_lib::object_id::ObjectId;
@@ -38,14 +39,16 @@ use tracing::instrument;
 use unicode_width::UnicodeWidthStr as _;
 
 use crate::cli_util::{CommandError, WorkspaceCommandHelper};
+use crate::diff_driver::{DiffDrivers, FunctionMatcher};
 use crate::formatter::Formatter;
 use crate::merge_tools::{self, ExternalMergeTool, MergeTool};
+use crate::syntax_highlight::{SyntaxHighlighter, SyntaxSpans};
 use crate::text_util;
 use crate::ui::Ui;
 
 #[derive(clap::Args, Clone, Debug)]
 #[command(next_help_heading = "Diff Formatting Options")]
-#[command(group(clap::ArgGroup::new("short-format").args(&["summary", "stat", "types"])))]
+#[command(group(clap::ArgGroup::new("short-format").args(&["summary", "stat", "types", "name_only"])))]
 #[command(group(clap::ArgGroup::new("long-format").args(&["git", "color_words", "tool"])))]
 pub struct DiffFormatArgs {
     /// For each path, show only whether it was modified, added, or deleted
@@ -63,6 +66,12 @@ pub struct DiffFormatArgs {
     /// Git submodule.
     #[arg(long)]
     pub types: bool,
+    /// For each path, show only the path
+    ///
+    /// The file contents aren't compared, so this is faster than the other
+    /// formats for large diffs.
+    #[arg(long)]
+    pub name_only: bool,
     /// Show a Git-format diff
     #[arg(long)]
     pub git: bool,
@@ -72,18 +81,64 @@ pub struct DiffFormatArgs {
     /// Generate diff by external command
     #[arg(long)]
     pub tool: Option<String>,
+    /// Ignore whitespace when comparing lines
+    #[arg(long)]
+    pub ignore_all_space: bool,
+    /// Ignore changes in amount of whitespace when comparing lines
+    ///
+    /// Whitespace at the end of lines is ignored as well.
+    #[arg(long, conflicts_with = "ignore_all_space")]
+    pub ignore_space_change: bool,
+    /// Ignore changes whose lines are all blank
+    #[arg(long)]
+    pub ignore_blank_lines: bool,
+    /// Show the whole function around each change as context
+    ///
+    /// The lines that start a function are found by the diff driver of the
+    /// file.
+    #[arg(long)]
+    pub function_context: bool,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DiffFormat {
     Summary,
-    Stat,
+    Stat(LineDiffOptions),
     Types,
-    Git,
-    ColorWords,
+    NameOnly,
+    Git(LineDiffOptions),
+    ColorWords(LineDiffOptions),
     Tool(Box<ExternalMergeTool>),
 }
 
+/// Options for the diff formats that compare file contents line by line.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LineDiffOptions {
+    /// How lines are compared to find the matching ones.
+    pub compare_mode: LineCompareMode,
+    /// Whether changes that only add or remove blank lines are hidden.
+    pub ignore_blank_lines: bool,
+    /// Whether the whole function around each change is shown as context.
+    pub function_context: bool,
+}
+
+impl LineDiffOptions {
+    fn from_args(args: &DiffFormatArgs) -> Self {
+        let compare_mode = if args.ignore_all_space {
+            LineCompareMode::IgnoreAllSpace
+        } else if args.ignore_space_change {
+            LineCompareMode::IgnoreSpaceChange
+        } else {
+            LineCompareMode::Exact
+        };
+        LineDiffOptions {
+            compare_mode,
+            ignore_blank_lines: args.ignore_blank_lines,
+            function_context: args.function_context,
+        }
+    }
+}
+
 /// Returns a list of requested diff formats, which will never be empty.
 pub fn diff_formats_for(
     settings: &UserSettings,
@@ -91,7 +146,7 @@ pub fn diff_formats_for(
 ) -> Result<Vec<DiffFormat>, config::ConfigError> {
     let formats = diff_formats_from_args(settings, args)?;
     if formats.is_empty() {
-        Ok(vec![default_diff_format(settings)?])
+        Ok(vec![default_diff_format(settings, args)?])
     } else {
         Ok(formats)
     }
@@ -107,7 +162,7 @@ pub fn diff_formats_for_log(
     let mut formats = diff_formats_from_args(settings, args)?;
     // --patch implies default if no format other than --summary is specified
     if patch && matches!(formats.as_slice(), [] | [DiffFormat::Summary]) {
-        formats.push(default_diff_format(settings)?);
+        formats.push(default_diff_format(settings, args)?);
         formats.dedup();
     }
     Ok(formats)
@@ -117,12 +172,14 @@ fn diff_formats_from_args(
     settings: &UserSettings,
     args: &DiffFormatArgs,
 ) -> Result<Vec<DiffFormat>, config::ConfigError> {
+    let options = LineDiffOptions::from_args(args);
     let mut formats = [
         (args.summary, DiffFormat::Summary),
         (args.types, DiffFormat::Types),
-        (args.git, DiffFormat::Git),
-        (args.color_words, DiffFormat::ColorWords),
-        (args.stat, DiffFormat::Stat),
+        (args.name_only, DiffFormat::NameOnly),
+        (args.git, DiffFormat::Git(options)),
+        (args.color_words, DiffFormat::ColorWords(options)),
+        (args.stat, DiffFormat::Stat(options)),
     ]
     .into_iter()
     .filter_map(|(arg, format)| arg.then_some(format))
@@ -140,7 +197,10 @@ fn diff_formats_from_args(
     Ok(formats)
 }
 
-fn default_diff_format(settings: &UserSettings) -> Result<DiffFormat, config::ConfigError> {
+fn default_diff_format(
+    settings: &UserSettings,
+    args: &DiffFormatArgs,
+) -> Result<DiffFormat, config::ConfigError> {
     let config = settings.config();
     if let Some(args) = config.get("ui.diff.tool").optional()? {
         // External "tool" overrides the internal "format" option.
@@ -160,18 +220,71 @@ fn default_diff_format(settings: &UserSettings) -> Result<DiffFormat, config::Co
     } else {
         "color-words".to_owned()
     };
+    let options = LineDiffOptions::from_args(args);
     match name.as_ref() {
         "summary" => Ok(DiffFormat::Summary),
         "types" => Ok(DiffFormat::Types),
-        "git" => Ok(DiffFormat::Git),
-        "color-words" => Ok(DiffFormat::ColorWords),
-        "stat" => Ok(DiffFormat::Stat),
+        "name-only" => Ok(DiffFormat::NameOnly),
+        "git" => Ok(DiffFormat::Git(options)),
+        "color-words" => Ok(DiffFormat::ColorWords(options)),
+        "stat" => Ok(DiffFormat::Stat(options)),
         _ => Err(config::ConfigError::Message(format!(
             "invalid diff format: {name}"
         ))),
     }
 }
 
+fn diff_granularity(settings: &UserSettings) -> Result<DiffGranularity, config::ConfigError> {
+    let name = settings
+        .config()
+        .get_string("ui.diff.granularity")
+        .optional()?
+        .unwrap_or_else(|| "word".to_owned());
+    match name.as_ref() {
+        "line" => Ok(DiffGranularity::Line),
+        "word" => Ok(DiffGranularity::Word),
+        "char" => Ok(DiffGranularity::Char),
+        _ => Err(config::ConfigError::Message(format!(
+            "invalid diff granularity: {name}"
+        ))),
+    }
+}
+
+/// Returns the minimum similarity in percent of renamed and copied files, or
+/// `None` if they shouldn't be detected.
+fn copy_detection_similarity(settings: &UserSettings) -> Result<Option<u32>, config::ConfigError> {
+    let config = settings.config();
+    if !config
+        .get_bool("ui.diff.renames")
+        .optional()?
+        .unwrap_or(true)
+    {
+        return Ok(None);
+    }
+    rename_similarity(settings).map(Some)
+}
+
+fn rename_similarity(settings: &UserSettings) -> Result<u32, config::ConfigError> {
+    let similarity = settings
+        .config()
+        .get_int("ui.diff.rename-similarity")
+        .optional()?
+        .unwrap_or(50);
+    Ok(similarity.clamp(0, 100) as u32)
+}
+
+fn syntax_highlighter(
+    settings: &UserSettings,
+) -> Result<Option<SyntaxHighlighter>, config::ConfigError> {
+    let enabled = settings
+        .config()
+        .get_bool("ui.diff.syntax-highlight")
+        .optional()?
+        .unwrap_or(false);
+    Ok(enabled.then(SyntaxHighlighter::new))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn show_diff(
     ui: &Ui,
     formatter: &mut dyn Formatter,
@@ -180,6 +293,7 @@ pub fn show_diff(
     to_tree: &MergedTree,
     matcher: &dyn Matcher,
     formats: &[DiffFormat],
+    width: usize,
 ) -> Result<(), CommandError> {
     for format in formats {
         match format {
@@ -187,21 +301,25 @@ pub fn show_diff(
                 let tree_diff = from_tree.diff_stream(to_tree, matcher);
                 show_diff_summary(formatter, workspace_command, tree_diff)?;
             }
-            DiffFormat::Stat => {
+            DiffFormat::Stat(options) => {
                 let tree_diff = from_tree.diff_stream(to_tree, matcher);
-                show_diff_stat(ui, formatter, workspace_command, tree_diff)?;
+                show_diff_stat(formatter, workspace_command, tree_diff, options, width)?;
             }
             DiffFormat::Types => {
                 let tree_diff = from_tree.diff_stream(to_tree, matcher);
                 show_types(formatter, workspace_command, tree_diff)?;
             }
-            DiffFormat::Git => {
+            DiffFormat::NameOnly => {
+                let tree_diff = from_tree.diff_stream(to_tree, matcher);
+                show_names(formatter, workspace_command, tree_diff)?;
+            }
+            DiffFormat::Git(options) => {
                 let tree_diff = from_tree.diff_stream(to_tree, matcher);
-                show_git_diff(formatter, workspace_command, tree_diff)?;
+                show_git_diff(formatter, workspace_command, tree_diff, options)?;
             }
-            DiffFormat::ColorWords => {
+            DiffFormat::ColorWords(options) => {
                 let tree_diff = from_tree.diff_stream(to_tree, matcher);
-                show_color_words_diff(formatter, workspace_command, tree_diff)?;
+                show_color_words_diff(formatter, workspace_command, tree_diff, options)?;
             }
             DiffFormat::Tool(tool) => {
                 merge_tools::generate_diff(ui, formatter.raw(), from_tree, to_tree, matcher, tool)?;
@@ -218,6 +336,7 @@ pub fn show_patch(
     commit: &Commit,
     matcher: &dyn Matcher,
     formats: &[DiffFormat],
+    width: usize,
 ) -> Result<(), CommandError> {
     let parents = commit.parents();
     let from_tree = rewrite::merge_commit_trees(workspace_command.repo().as_ref(), &parents)?;
@@ -230,12 +349,51 @@ pub fn show_patch(
         &to_tree,
         matcher,
         formats,
+        width,
     )
 }
 
+/// Returns the path in `from_tree` that the file at `path` in `to_tree` was
+/// renamed or copied from, if it was added as a renamed or copied file.
+///
+/// Unlike in diffs, renames are detected even if `ui.diff.renames` is
+/// disabled.
+pub fn find_copy_source(
+    workspace_command: &WorkspaceCommandHelper,
+    from_tree: &MergedTree,
+    to_tree: &MergedTree,
+    path: &RepoPath,
+) -> Result<Option<RepoPathBuf>, CommandError> {
+    let entries: Vec<DiffEntry> = from_tree
+        .diff_stream(to_tree, &EverythingMatcher)
+        .map(|(path, diff)| diff.map(|(before, after)| (path, before, after)))
+        .try_collect()
+        .block_on()?;
+    let min_similarity = rename_similarity(workspace_command.settings())?;
+    let records =
+        copies::detect_copies(workspace_command.repo().store(), &entries, min_similarity)?;
+    Ok(records
+        .into_iter()
+        .find(|record| record.target.as_ref() == path)
+        .map(|record| record.source))
+}
+
+/// Returns true if the hunk consists of whitespace only.
+fn is_blank_diff_hunk(hunk: &DiffHunk) -> bool {
+    let is_blank = |text: &[u8]| text.iter().all(u8::is_ascii_whitespace);
+    match hunk {
+        DiffHunk::Matching(text) => is_blank(text),
+        DiffHunk::Different(texts) => texts.iter().all(|text| is_blank(text)),
+    }
+}
+
 fn show_color_words_diff_hunks(
     left: &[u8],
     right: &[u8],
+    granularity: DiffGranularity,
+    options: &LineDiffOptions,
+    left_syntax: Option<&SyntaxSpans>,
+    right_syntax: Option<&SyntaxSpans>,
     formatter: &mut dyn Formatter,
 ) -> io::Result<()> {
     const SKIPPED_CONTEXT_LINE: &str = "    ...\n";
@@ -245,9 +403,24 @@ fn show_color_words_diff_hunks(
     let mut skipped_context = false;
     // Are the lines in `context` to be printed before the next modified line?
     let mut context_before = true;
-    for diff_line in files::diff(left, right) {
-        if diff_line.is_unmodified() {
-            context.push_back(diff_line.clone());
+    let syntax = [left_syntax, right_syntax];
+    // Offsets of the current line in the left and right contents
+    let mut pos = [0, 0];
+    for diff_line in files::diff_with_options(left, right, granularity, options.compare_mode) {
+        let line_pos = pos;
+        for hunk in &diff_line.hunks {
+            match hunk {
+                DiffHunk::Matching(data) => pos = pos.map(|p| p + data.len()),
+                DiffHunk::Different(data) => {
+                    pos[0] += data[0].len();
+                    pos[1] += data[1].len();
+                }
+            }
+        }
+        let is_blank_change =
+            options.ignore_blank_lines && diff_line.hunks.iter().all(is_blank_diff_hunk);
+        if diff_line.is_unmodified() || is_blank_change {
+            context.push_back((diff_line, line_pos));
             let mut start_skipping_context = false;
             if context_before {
                 if skipped_context && context.len() > num_context_lines {
@@ -256,8 +429,8 @@ fn show_color_words_diff_hunks(
                     start_skipping_context = true;
                 }
             } else if context.len() > num_context_lines * 2 + 1 {
-                for line in context.drain(..num_context_lines) {
-                    show_color_words_diff_line(formatter, &line)?;
+                for (line, line_pos) in context.drain(..num_context_lines) {
+                    show_color_words_diff_line(formatter, &line, line_pos, syntax)?;
                 }
                 start_skipping_context = true;
             }
@@ -268,11 +441,11 @@ fn show_color_words_diff_hunks(
                 context_before = true;
             }
         } else {
-            for line in &context {
-                show_color_words_diff_line(formatter, line)?;
+            for (line, line_pos) in &context {
+                show_color_words_diff_line(formatter, line, *line_pos, syntax)?;
             }
             context.clear();
-            show_color_words_diff_line(formatter, &diff_line)?;
+            show_color_words_diff_line(formatter, &diff_line, line_pos, syntax)?;
             context_before = false;
             skipped_context = false;
         }
@@ -283,8 +456,8 @@ fn show_color_words_diff_hunks(
             skipped_context = true;
             context_before = true;
         }
-        for line in &context {
-            show_color_words_diff_line(formatter, line)?;
+        for (line, line_pos) in &context {
+            show_color_words_diff_line(formatter, line, *line_pos, syntax)?;
         }
         if context_before {
             formatter.write_str(SKIPPED_CONTEXT_LINE)?;
@@ -304,6 +477,8 @@ fn show_color_words_diff_hunks(
 fn show_color_words_diff_line(
     formatter: &mut dyn Formatter,
     diff_line: &DiffLine,
+    [mut left_pos, mut right_pos]: [usize; 2],
+    [left_syntax, right_syntax]: [Option<&SyntaxSpans>; 2],
 ) -> io::Result<()> {
     if diff_line.has_left_content {
         write!(
@@ -328,17 +503,21 @@ fn show_color_words_diff_line(
     for hunk in &diff_line.hunks {
         match hunk {
             DiffHunk::Matching(data) => {
-                formatter.write_all(data)?;
+                write_highlighted(formatter, data, left_pos, left_syntax)?;
+                left_pos += data.len();
+                right_pos += data.len();
             }
             DiffHunk::Different(data) => {
                 let before = data[0];
                 let after = data[1];
                 if !before.is_empty() {
-                    formatter.with_label("removed", |formatter| formatter.write_all(before))?;
+                    write_highlighted_change(formatter, before, left_pos, left_syntax, "removed")?;
                 }
                 if !after.is_empty() {
-                    formatter.with_label("added", |formatter| formatter.write_all(after))?;
+                    write_highlighted_change(formatter, after, right_pos, right_syntax, "added")?;
                 }
+                left_pos += before.len();
+                right_pos += after.len();
             }
         }
     }
@@ -346,6 +525,56 @@ fn show_color_words_diff_line(
     Ok(())
 }
 
+/// Writes the changed `text` found at `offset` in the file like
+/// `write_highlighted()`, with the `label` layered on top of the syntax labels
+/// so that its style takes precedence.
+fn write_highlighted_change(
+    formatter: &mut dyn Formatter,
+    text: &[u8],
+    offset: usize,
+    syntax: Option<&SyntaxSpans>,
+    label: &str,
+) -> io::Result<()> {
+    let Some(syntax) = syntax else {
+        return formatter.with_label(label, |formatter| formatter.write_all(text));
+    };
+    for (range, syntax_label) in syntax.split(offset..offset + text.len()) {
+        let part = &text[range.start - offset..range.end - offset];
+        match syntax_label {
+            Some(syntax_label) => formatter.with_label("syntax", |formatter| {
+                formatter.with_label(syntax_label, |formatter| {
+                    formatter.with_label(label, |formatter| formatter.write_all(part))
+                })
+            })?,
+            None => formatter.with_label(label, |formatter| formatter.write_all(part))?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the `text` found at `offset` in the file, labeling the parts
+/// highlighted by the `syntax`.
+fn write_highlighted(
+    formatter: &mut dyn Formatter,
+    text: &[u8],
+    offset: usize,
+    syntax: Option<&SyntaxSpans>,
+) -> io::Result<()> {
+    let Some(syntax) = syntax else {
+        return formatter.write_all(text);
+    };
+    for (range, label) in syntax.split(offset..offset + text.len()) {
+        let part = &text[range.start - offset..range.end - offset];
+        match label {
+            Some(label) => formatter.with_label("syntax", |formatter| {
+                formatter.with_label(label, |formatter| formatter.write_all(part))
+            })?,
+            None => formatter.write_all(part)?,
+        }
+    }
+    Ok(())
+}
+
 fn diff_content(path: &RepoPath, value: MaterializedTreeValue) -> Result<Vec<u8>, CommandError> {
     match value {
         MaterializedTreeValue::Absent => Ok(vec![]),
@@ -388,7 +617,10 @@ pub fn show_color_words_diff(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
     tree_diff: TreeDiffStream,
+    options: &LineDiffOptions,
 ) -> Result<(), CommandError> {
+    let granularity = diff_granularity(workspace_command.settings())?;
+    let highlighter = syntax_highlighter(workspace_command.settings())?;
     formatter.push_label("diff")?;
     let mut diff_stream = materialized_diff_stream(workspace_command.repo().store(), tree_diff);
     async {
@@ -405,7 +637,18 @@ pub fn show_color_words_diff(
                 if right_content.is_empty() {
                     writeln!(formatter.labeled("empty"), "    (empty)")?;
                 } else {
-                    show_color_words_diff_hunks(&[], &right_content, formatter)?;
+                    let right_syntax = highlighter
+                        .as_ref()
+                        .and_then(|highlighter| highlighter.highlight(&path, &right_content));
+                    show_color_words_diff_hunks(
+                        &[],
+                        &right_content,
+                        granularity,
+                        options,
+                        None,
+                        right_syntax.as_ref(),
+                        formatter,
+                    )?;
                 }
             } else if right_value.is_present() {
                 let description = match (&left_value, &right_value) {
@@ -458,7 +701,21 @@ pub fn show_color_words_diff(
                 let left_content = diff_content(&path, left_value)?;
                 let right_content = diff_content(&path, right_value)?;
                 writeln!(formatter.labeled("header"), "{description} {ui_path}:")?;
-                show_color_words_diff_hunks(&left_content, &right_content, formatter)?;
+                let left_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &left_content));
+                let right_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &right_content));
+                show_color_words_diff_hunks(
+                    &left_content,
+                    &right_content,
+                    granularity,
+                    options,
+                    left_syntax.as_ref(),
+                    right_syntax.as_ref(),
+                    formatter,
+                )?;
             } else {
                 let description = basic_diff_file_type(&left_value);
                 writeln!(
@@ -469,7 +726,18 @@ pub fn show_color_words_diff(
                 if left_content.is_empty() {
                     writeln!(formatter.labeled("empty"), "    (empty)")?;
                 } else {
-                    show_color_words_diff_hunks(&left_content, &[], formatter)?;
+                    let left_syntax = highlighter
+                        .as_ref()
+                        .and_then(|highlighter| highlighter.highlight(&path, &left_content));
+                    show_color_words_diff_hunks(
+                        &left_content,
+                        &[],
+                        granularity,
+                        options,
+                        left_syntax.as_ref(),
+                        None,
+                        formatter,
+                    )?;
                 }
             }
         }
@@ -542,134 +810,301 @@ fn git_diff_part(
     })
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum DiffLineType {
     Context,
     Removed,
     Added,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DiffTokenType {
+    Matching,
+    Different,
+}
+
+type DiffTokenVec<'content> = Vec<(DiffTokenType, &'content [u8])>;
+
 struct UnifiedDiffHunk<'content> {
     left_line_range: Range<usize>,
     right_line_range: Range<usize>,
-    lines: Vec<(DiffLineType, &'content [u8])>,
+    lines: Vec<(DiffLineType, DiffTokenVec<'content>)>,
+}
+
+fn unrefined_lines(content: &[u8]) -> Vec<DiffTokenVec<'_>> {
+    content
+        .split_inclusive(|b| *b == b'\n')
+        .map(|line| vec![(DiffTokenType::Matching, line)])
+        .collect()
+}
+
+/// Splits the removed and added lines of a changed hunk into tokens, marking
+/// the parts that differ between the two sides.
+fn refine_changed_lines<'content>(
+    left_content: &'content [u8],
+    right_content: &'content [u8],
+    granularity: DiffGranularity,
+) -> [Vec<DiffTokenVec<'content>>; 2] {
+    let unrefined = || {
+        [
+            unrefined_lines(left_content),
+            unrefined_lines(right_content),
+        ]
+    };
+    if left_content.is_empty() || right_content.is_empty() || granularity == DiffGranularity::Line {
+        return unrefined();
+    }
+    let hunks = Diff::with_granularity(&[left_content, right_content], granularity)
+        .hunks()
+        .collect_vec();
+    // If nothing but whitespace is in common, highlighting the differences
+    // would just highlight the whole lines.
+    let has_common_words = hunks.iter().any(|hunk| match hunk {
+        DiffHunk::Matching(content) => !content.iter().all(u8::is_ascii_whitespace),
+        DiffHunk::Different(_) => false,
+    });
+    if !has_common_words {
+        return unrefined();
+    }
+    [0, 1].map(|side| {
+        let mut lines = vec![];
+        let mut line = vec![];
+        for hunk in &hunks {
+            let (token_type, content) = match hunk {
+                DiffHunk::Matching(content) => (DiffTokenType::Matching, *content),
+                DiffHunk::Different(contents) => (DiffTokenType::Different, contents[side]),
+            };
+            for part in content.split_inclusive(|b| *b == b'\n') {
+                line.push((token_type, part));
+                if part.ends_with(b"\n") {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    })
 }
 
 fn unified_diff_hunks<'content>(
     left_content: &'content [u8],
     right_content: &'content [u8],
     num_context_lines: usize,
+    granularity: DiffGranularity,
+    options: &LineDiffOptions,
+    function_matcher: Option<&FunctionMatcher>,
 ) -> Vec<UnifiedDiffHunk<'content>> {
-    let mut hunks = vec![];
-    let mut current_hunk = UnifiedDiffHunk {
-        left_line_range: 1..1,
-        right_line_range: 1..1,
-        lines: vec![],
-    };
-    let mut show_context_after = false;
-    let diff = Diff::for_tokenizer(&[left_content, right_content], &diff::find_line_ranges);
-    for hunk in diff.hunks() {
+    // All lines of the diff, and whether each one is a change to show
+    let mut lines: Vec<(DiffLineType, DiffTokenVec<'content>)> = vec![];
+    let mut changed: Vec<bool> = vec![];
+    let diff_hunks = diff::diff_with_options(
+        left_content,
+        right_content,
+        DiffGranularity::Line,
+        options.compare_mode,
+    );
+    for hunk in diff_hunks {
+        let is_blank_change = options.ignore_blank_lines && is_blank_diff_hunk(&hunk);
         match hunk {
             DiffHunk::Matching(content) => {
-                let lines = content.split_inclusive(|b| *b == b'\n').collect_vec();
-                // Number of context lines to print after the previous non-matching hunk.
-                let num_after_lines = lines.len().min(if show_context_after {
-                    num_context_lines
-                } else {
-                    0
-                });
-                current_hunk.left_line_range.end += num_after_lines;
-                current_hunk.right_line_range.end += num_after_lines;
-                for line in lines.iter().take(num_after_lines) {
-                    current_hunk.lines.push((DiffLineType::Context, line));
-                }
-                let num_skip_lines = lines
-                    .len()
-                    .saturating_sub(num_after_lines)
-                    .saturating_sub(num_context_lines);
-                if num_skip_lines > 0 {
-                    let left_start = current_hunk.left_line_range.end + num_skip_lines;
-                    let right_start = current_hunk.right_line_range.end + num_skip_lines;
-                    if !current_hunk.lines.is_empty() {
-                        hunks.push(current_hunk);
-                    }
-                    current_hunk = UnifiedDiffHunk {
-                        left_line_range: left_start..left_start,
-                        right_line_range: right_start..right_start,
-                        lines: vec![],
-                    };
-                }
-                let num_before_lines = lines.len() - num_after_lines - num_skip_lines;
-                current_hunk.left_line_range.end += num_before_lines;
-                current_hunk.right_line_range.end += num_before_lines;
-                for line in lines.iter().skip(num_after_lines + num_skip_lines) {
-                    current_hunk.lines.push((DiffLineType::Context, line));
+                for line in content.split_inclusive(|b| *b == b'\n') {
+                    lines.push((DiffLineType::Context, vec![(DiffTokenType::Matching, line)]));
+                    changed.push(false);
                 }
             }
             DiffHunk::Different(content) => {
-                show_context_after = true;
-                let left_lines = content[0].split_inclusive(|b| *b == b'\n').collect_vec();
-                let right_lines = content[1].split_inclusive(|b| *b == b'\n').collect_vec();
-                if !left_lines.is_empty() {
-                    current_hunk.left_line_range.end += left_lines.len();
-                    for line in left_lines {
-                        current_hunk.lines.push((DiffLineType::Removed, line));
-                    }
+                let [left_lines, right_lines] =
+                    refine_changed_lines(content[0], content[1], granularity);
+                for line in left_lines {
+                    lines.push((DiffLineType::Removed, line));
+                    changed.push(!is_blank_change);
                 }
-                if !right_lines.is_empty() {
-                    current_hunk.right_line_range.end += right_lines.len();
-                    for line in right_lines {
-                        current_hunk.lines.push((DiffLineType::Added, line));
-                    }
+                for line in right_lines {
+                    lines.push((DiffLineType::Added, line));
+                    changed.push(!is_blank_change);
                 }
             }
         }
     }
-    if !current_hunk
-        .lines
-        .iter()
-        .all(|(diff_type, _line)| *diff_type == DiffLineType::Context)
-    {
-        hunks.push(current_hunk);
+
+    // Mark the context lines around the changes to be shown. With function
+    // context, the whole function containing the change is shown.
+    let mut shown = vec![false; lines.len()];
+    let function_bounds = function_matcher
+        .filter(|_| options.function_context)
+        .map(|matcher| function_bounds(&lines, matcher));
+    for i in changed.iter().positions(|changed| *changed) {
+        let mut start = i.saturating_sub(num_context_lines);
+        let mut end = (i + num_context_lines + 1).min(lines.len());
+        if let Some((function_starts, function_ends)) = &function_bounds {
+            start = start.min(function_starts[i]);
+            end = end.max(function_ends[i]);
+        }
+        shown[start..end].fill(true);
+    }
+
+    let mut hunks: Vec<UnifiedDiffHunk> = vec![];
+    let mut left_line = 1;
+    let mut right_line = 1;
+    let mut in_hunk = false;
+    for ((line_type, tokens), shown) in lines.into_iter().zip(shown) {
+        if shown {
+            if !in_hunk {
+                hunks.push(UnifiedDiffHunk {
+                    left_line_range: left_line..left_line,
+                    right_line_range: right_line..right_line,
+                    lines: vec![],
+                });
+                in_hunk = true;
+            }
+            let hunk = hunks.last_mut().unwrap();
+            if line_type != DiffLineType::Added {
+                hunk.left_line_range.end += 1;
+            }
+            if line_type != DiffLineType::Removed {
+                hunk.right_line_range.end += 1;
+            }
+            hunk.lines.push((line_type, tokens));
+        } else {
+            in_hunk = false;
+        }
+        if line_type != DiffLineType::Added {
+            left_line += 1;
+        }
+        if line_type != DiffLineType::Removed {
+            right_line += 1;
+        }
     }
     hunks
 }
 
+/// Returns the start of the function containing each line, and the end of
+/// it (the start of the next function).
+fn function_bounds(
+    lines: &[(DiffLineType, DiffTokenVec)],
+    matcher: &FunctionMatcher,
+) -> (Vec<usize>, Vec<usize>) {
+    let is_function_line = lines
+        .iter()
+        .map(|(_, tokens)| {
+            let text = tokens
+                .iter()
+                .flat_map(|(_, text)| *text)
+                .copied()
+                .collect_vec();
+            matcher.is_function_line(&text)
+        })
+        .collect_vec();
+    let mut starts = Vec::with_capacity(lines.len());
+    let mut start = 0;
+    for (i, is_function_line) in is_function_line.iter().enumerate() {
+        if *is_function_line {
+            start = i;
+        }
+        starts.push(start);
+    }
+    let mut ends = vec![0; lines.len()];
+    let mut end = lines.len();
+    for (i, is_function_line) in is_function_line.iter().enumerate().rev() {
+        ends[i] = end;
+        if *is_function_line {
+            end = i;
+        }
+    }
+    (starts, ends)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn show_unified_diff_hunks(
     formatter: &mut dyn Formatter,
     left_content: &[u8],
     right_content: &[u8],
+    granularity: DiffGranularity,
+    options: &LineDiffOptions,
+    function_matcher: Option<&FunctionMatcher>,
+    left_syntax: Option<&SyntaxSpans>,
+    right_syntax: Option<&SyntaxSpans>,
 ) -> Result<(), CommandError> {
-    for hunk in unified_diff_hunks(left_content, right_content, 3) {
-        writeln!(
-            formatter.labeled("hunk_header"),
-            "@@ -{},{} +{},{} @@",
-            hunk.left_line_range.start,
-            hunk.left_line_range.len(),
-            hunk.right_line_range.start,
-            hunk.right_line_range.len()
-        )?;
-        for (line_type, content) in hunk.lines {
+    // Lines of the left content, to find the function each hunk is in
+    let left_lines = if function_matcher.is_some() {
+        left_content.split_inclusive(|b| *b == b'\n').collect_vec()
+    } else {
+        vec![]
+    };
+    // Ranges of the lines on each side, to look up their syntax
+    let left_line_ranges = if left_syntax.is_some() {
+        line_ranges(left_content)
+    } else {
+        vec![]
+    };
+    let right_line_ranges = if right_syntax.is_some() {
+        line_ranges(right_content)
+    } else {
+        vec![]
+    };
+    let hunks = unified_diff_hunks(
+        left_content,
+        right_content,
+        3,
+        granularity,
+        options,
+        function_matcher,
+    );
+    for hunk in hunks {
+        let lines_before = &left_lines[..hunk.left_line_range.start.saturating_sub(1)];
+        let heading = function_matcher.and_then(|matcher| {
+            lines_before
+                .iter()
+                .rev()
+                .find_map(|line| matcher.heading(line))
+        });
+        formatter.with_label("hunk_header", |formatter| {
+            write!(
+                formatter,
+                "@@ -{},{} +{},{} @@",
+                hunk.left_line_range.start,
+                hunk.left_line_range.len(),
+                hunk.right_line_range.start,
+                hunk.right_line_range.len()
+            )?;
+            if let Some(heading) = &heading {
+                write!(formatter, " {heading}")?;
+            }
+            writeln!(formatter)
+        })?;
+        let mut left_line_index = hunk.left_line_range.start.saturating_sub(1);
+        let mut right_line_index = hunk.right_line_range.start.saturating_sub(1);
+        for (line_type, tokens) in &hunk.lines {
             match line_type {
                 DiffLineType::Context => {
+                    let line = left_line_ranges.get(left_line_index).cloned();
                     formatter.with_label("context", |formatter| {
                         formatter.write_str(" ")?;
-                        formatter.write_all(content)
+                        write_highlighted_diff_tokens(formatter, tokens, line, left_syntax)
                     })?;
+                    left_line_index += 1;
+                    right_line_index += 1;
                 }
                 DiffLineType::Removed => {
+                    let line = left_line_ranges.get(left_line_index).cloned();
                     formatter.with_label("removed", |formatter| {
                         formatter.write_str("-")?;
-                        formatter.write_all(content)
+                        write_highlighted_diff_tokens(formatter, tokens, line, left_syntax)
                     })?;
+                    left_line_index += 1;
                 }
                 DiffLineType::Added => {
+                    let line = right_line_ranges.get(right_line_index).cloned();
                     formatter.with_label("added", |formatter| {
                         formatter.write_str("+")?;
-                        formatter.write_all(content)
+                        write_highlighted_diff_tokens(formatter, tokens, line, right_syntax)
                     })?;
+                    right_line_index += 1;
                 }
             }
+            let (_, content) = tokens.last().expect("diff line should not be empty");
             if !content.ends_with(b"\n") {
                 formatter.write_str("\n\\ No newline at end of file\n")?;
             }
@@ -678,6 +1113,111 @@ fn show_unified_diff_hunks(
     Ok(())
 }
 
+fn write_diff_tokens(
+    formatter: &mut dyn Formatter,
+    tokens: &[(DiffTokenType, &[u8])],
+) -> io::Result<()> {
+    for (token_type, content) in tokens {
+        match token_type {
+            DiffTokenType::Matching => formatter.write_all(content)?,
+            DiffTokenType::Different => {
+                formatter.with_label("token", |formatter| formatter.write_all(content))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the `tokens` of the diff line found at `line` in the file, labeling
+/// the parts highlighted by the `syntax`. Falls back to writing the tokens
+/// without highlighting if the line isn't known.
+fn write_highlighted_diff_tokens(
+    formatter: &mut dyn Formatter,
+    tokens: &[(DiffTokenType, &[u8])],
+    line: Option<Range<usize>>,
+    syntax: Option<&SyntaxSpans>,
+) -> io::Result<()> {
+    let Some(line) = line.filter(|line| {
+        let len: usize = tokens.iter().map(|(_, content)| content.len()).sum();
+        line.len() == len
+    }) else {
+        return write_diff_tokens(formatter, tokens);
+    };
+    let mut offset = line.start;
+    for (token_type, content) in tokens {
+        match token_type {
+            DiffTokenType::Matching => write_highlighted(formatter, content, offset, syntax)?,
+            DiffTokenType::Different => formatter.with_label("token", |formatter| {
+                write_highlighted(formatter, content, offset, syntax)
+            })?,
+        }
+        offset += content.len();
+    }
+    Ok(())
+}
+
+/// Returns the ranges of the lines in the `content`, including their newlines.
+fn line_ranges(content: &[u8]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    content
+        .split_inclusive(|b| *b == b'\n')
+        .map(|line| {
+            let range = start..start + line.len();
+            start = range.end;
+            range
+        })
+        .collect()
+}
+
+type DiffEntry = (RepoPathBuf, MergedTreeValue, MergedTreeValue);
+
+/// Collects the `tree_diff`. If `ui.diff.renames` is enabled, the renamed and
+/// copied files are found, and the values of their sources become their values
+/// before. Returns the diff, and the renames and copies by target path.
+fn collect_diff_with_copies(
+    workspace_command: &WorkspaceCommandHelper,
+    tree_diff: TreeDiffStream,
+) -> Result<(Vec<DiffEntry>, HashMap<RepoPathBuf, CopyRecord>), CommandError> {
+    let entries: Vec<DiffEntry> = tree_diff
+        .map(|(path, diff)| diff.map(|(before, after)| (path, before, after)))
+        .try_collect()
+        .block_on()?;
+    let Some(min_similarity) = copy_detection_similarity(workspace_command.settings())? else {
+        return Ok((entries, HashMap::new()));
+    };
+    let records =
+        copies::detect_copies(workspace_command.repo().store(), &entries, min_similarity)?;
+    if records.is_empty() {
+        return Ok((entries, HashMap::new()));
+    }
+    let values_before: HashMap<&RepoPathBuf, &MergedTreeValue> = entries
+        .iter()
+        .map(|(path, before, _)| (path, before))
+        .collect();
+    let renamed_paths: HashSet<&RepoPathBuf> = records
+        .iter()
+        .filter(|record| record.operation == CopyOperation::Rename)
+        .map(|record| &record.source)
+        .collect();
+    let copies: HashMap<RepoPathBuf, CopyRecord> = records
+        .iter()
+        .map(|record| (record.target.clone(), record.clone()))
+        .collect();
+    let new_entries = entries
+        .iter()
+        .filter(|(path, _, _)| !renamed_paths.contains(path))
+        .map(|(path, before, after)| match copies.get(path) {
+            Some(copy) => (
+                path.clone(),
+                values_before[&copy.source].clone(),
+                after.clone(),
+            ),
+            None => (path.clone(), before.clone(), after.clone()),
+        })
+        .collect();
+    Ok((new_entries, copies))
+}
+
 fn materialized_diff_stream<'a>(
     store: &'a Store,
     tree_diff: TreeDiffStream<'a>,
@@ -706,13 +1246,29 @@ pub fn show_git_diff(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
     tree_diff: TreeDiffStream,
+    options: &LineDiffOptions,
 ) -> Result<(), CommandError> {
+    let granularity = diff_granularity(workspace_command.settings())?;
+    let highlighter = syntax_highlighter(workspace_command.settings())?;
+    let drivers = DiffDrivers::load(
+        workspace_command.settings(),
+        workspace_command.workspace_root(),
+    )?;
+    let (entries, copies) = collect_diff_with_copies(workspace_command, tree_diff)?;
+    let tree_diff = Box::pin(stream::iter(
+        entries
+            .into_iter()
+            .map(|(path, before, after)| (path, Ok((before, after)))),
+    ));
     formatter.push_label("diff")?;
 
     let mut diff_stream = materialized_diff_stream(workspace_command.repo().store(), tree_diff);
     async {
         while let Some((path, diff)) = diff_stream.next().await {
             let path_string = path.as_internal_file_string();
+            let copy = copies.get(&path);
+            let left_path_string =
+                copy.map_or(path_string, |copy| copy.source.as_internal_file_string());
             let (left_value, right_value) = diff?;
             if left_value.is_absent() {
                 let right_part = git_diff_part(&path, right_value)?;
@@ -723,12 +1279,33 @@ pub fn show_git_diff(
                     writeln!(formatter, "--- /dev/null")?;
                     writeln!(formatter, "+++ b/{path_string}")
                 })?;
-                show_unified_diff_hunks(formatter, &[], &right_part.content)?;
+                let right_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &right_part.content));
+                show_unified_diff_hunks(
+                    formatter,
+                    &[],
+                    &right_part.content,
+                    granularity,
+                    options,
+                    None,
+                    None,
+                    right_syntax.as_ref(),
+                )?;
             } else if right_value.is_present() {
                 let left_part = git_diff_part(&path, left_value)?;
                 let right_part = git_diff_part(&path, right_value)?;
                 formatter.with_label("file_header", |formatter| {
-                    writeln!(formatter, "diff --git a/{path_string} b/{path_string}")?;
+                    writeln!(formatter, "diff --git a/{left_path_string} b/{path_string}")?;
+                    if let Some(copy) = copy {
+                        let operation = match copy.operation {
+                            CopyOperation::Rename => "rename",
+                            CopyOperation::Copy => "copy",
+                        };
+                        writeln!(formatter, "similarity index {}%", copy.similarity)?;
+                        writeln!(formatter, "{operation} from {left_path_string}")?;
+                        writeln!(formatter, "{operation} to {path_string}")?;
+                    }
                     if left_part.mode != right_part.mode {
                         writeln!(formatter, "old mode {}", &left_part.mode)?;
                         writeln!(formatter, "new mode {}", &right_part.mode)?;
@@ -743,12 +1320,27 @@ pub fn show_git_diff(
                         )?;
                     }
                     if left_part.content != right_part.content {
-                        writeln!(formatter, "--- a/{path_string}")?;
+                        writeln!(formatter, "--- a/{left_path_string}")?;
                         writeln!(formatter, "+++ b/{path_string}")?;
                     }
                     Ok(())
                 })?;
-                show_unified_diff_hunks(formatter, &left_part.content, &right_part.content)?;
+                let left_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &left_part.content));
+                let right_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &right_part.content));
+                show_unified_diff_hunks(
+                    formatter,
+                    &left_part.content,
+                    &right_part.content,
+                    granularity,
+                    options,
+                    Some(drivers.function_matcher(&path)),
+                    left_syntax.as_ref(),
+                    right_syntax.as_ref(),
+                )?;
             } else {
                 let left_part = git_diff_part(&path, left_value)?;
                 formatter.with_label("file_header", |formatter| {
@@ -758,7 +1350,19 @@ pub fn show_git_diff(
                     writeln!(formatter, "--- a/{path_string}")?;
                     writeln!(formatter, "+++ /dev/null")
                 })?;
-                show_unified_diff_hunks(formatter, &left_part.content, &[])?;
+                let left_syntax = highlighter
+                    .as_ref()
+                    .and_then(|highlighter| highlighter.highlight(&path, &left_part.content));
+                show_unified_diff_hunks(
+                    formatter,
+                    &left_part.content,
+                    &[],
+                    granularity,
+                    options,
+                    None,
+                    left_syntax.as_ref(),
+                    None,
+                )?;
             }
         }
         Ok::<(), CommandError>(())
@@ -772,36 +1376,45 @@ pub fn show_git_diff(
 pub fn show_diff_summary(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
-    mut tree_diff: TreeDiffStream,
-) -> io::Result<()> {
+    tree_diff: TreeDiffStream,
+) -> Result<(), CommandError> {
+    let (entries, copies) = collect_diff_with_copies(workspace_command, tree_diff)?;
     formatter.with_label("diff", |formatter| -> io::Result<()> {
-        async {
-            while let Some((repo_path, diff)) = tree_diff.next().await {
-                let (before, after) = diff.unwrap();
-                if before.is_present() && after.is_present() {
-                    writeln!(
-                        formatter.labeled("modified"),
-                        "M {}",
-                        workspace_command.format_file_path(&repo_path)
-                    )?;
-                } else if before.is_absent() {
-                    writeln!(
-                        formatter.labeled("added"),
-                        "A {}",
-                        workspace_command.format_file_path(&repo_path)
-                    )?;
-                } else {
-                    writeln!(
-                        formatter.labeled("removed"),
-                        "D {}", // `R` could be interpreted as "renamed"
-                        workspace_command.format_file_path(&repo_path)
-                    )?;
-                }
+        for (repo_path, before, after) in &entries {
+            if let Some(copy) = copies.get(repo_path) {
+                let (label, letter) = match copy.operation {
+                    CopyOperation::Rename => ("renamed", 'R'),
+                    CopyOperation::Copy => ("copied", 'C'),
+                };
+                writeln!(
+                    formatter.labeled(label),
+                    "{letter} {} -> {}",
+                    workspace_command.format_file_path(&copy.source),
+                    workspace_command.format_file_path(repo_path)
+                )?;
+            } else if before.is_present() && after.is_present() {
+                writeln!(
+                    formatter.labeled("modified"),
+                    "M {}",
+                    workspace_command.format_file_path(repo_path)
+                )?;
+            } else if before.is_absent() {
+                writeln!(
+                    formatter.labeled("added"),
+                    "A {}",
+                    workspace_command.format_file_path(repo_path)
+                )?;
+            } else {
+                writeln!(
+                    formatter.labeled("removed"),
+                    "D {}", // `R` means "renamed"
+                    workspace_command.format_file_path(repo_path)
+                )?;
             }
-            Ok(())
         }
-        .block_on()
-    })
+        Ok(())
+    })?;
+    Ok(())
 }
 
 struct DiffStat {
@@ -810,8 +1423,20 @@ struct DiffStat {
     removed: usize,
 }
 
-fn get_diff_stat(path: String, left_content: &[u8], right_content: &[u8]) -> DiffStat {
-    let hunks = unified_diff_hunks(left_content, right_content, 0);
+fn get_diff_stat(
+    path: String,
+    left_content: &[u8],
+    right_content: &[u8],
+    options: &LineDiffOptions,
+) -> DiffStat {
+    let hunks = unified_diff_hunks(
+        left_content,
+        right_content,
+        0,
+        DiffGranularity::Line,
+        options,
+        None,
+    );
     let mut added = 0;
     let mut removed = 0;
     for hunk in hunks {
@@ -831,24 +1456,38 @@ fn get_diff_stat(path: String, left_content: &[u8], right_content: &[u8]) -> Dif
 }
 
 pub fn show_diff_stat(
-    ui: &Ui,
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
     tree_diff: TreeDiffStream,
+    options: &LineDiffOptions,
+    display_width: usize,
 ) -> Result<(), CommandError> {
     let mut stats: Vec<DiffStat> = vec![];
     let mut max_path_width = 0;
     let mut max_diffs = 0;
 
+    let (entries, copies) = collect_diff_with_copies(workspace_command, tree_diff)?;
+    let tree_diff = Box::pin(stream::iter(
+        entries
+            .into_iter()
+            .map(|(path, before, after)| (path, Ok((before, after)))),
+    ));
     let mut diff_stream = materialized_diff_stream(workspace_command.repo().store(), tree_diff);
     async {
         while let Some((repo_path, diff)) = diff_stream.next().await {
             let (left, right) = diff?;
-            let path = workspace_command.format_file_path(&repo_path);
+            let path = match copies.get(&repo_path) {
+                Some(copy) => format!(
+                    "{} => {}",
+                    workspace_command.format_file_path(&copy.source),
+                    workspace_command.format_file_path(&repo_path)
+                ),
+                None => workspace_command.format_file_path(&repo_path),
+            };
             let left_content = diff_content(&repo_path, left)?;
             let right_content = diff_content(&repo_path, right)?;
             max_path_width = max(max_path_width, path.width());
-            let stat = get_diff_stat(path, &left_content, &right_content);
+            let stat = get_diff_stat(path, &left_content, &right_content, options);
             max_diffs = max(max_diffs, stat.added + stat.removed);
             stats.push(stat);
         }
@@ -858,8 +1497,7 @@ pub fn show_diff_stat(
 
     let number_padding = max_diffs.to_string().len();
     // 4 characters padding for the graph
-    let available_width =
-        usize::from(ui.term_width().unwrap_or(80)).saturating_sub(4 + " | ".len() + number_padding);
+    let available_width = display_width.saturating_sub(4 + " | ".len() + number_padding);
     // Always give at least a tiny bit of room
     let available_width = max(available_width, 5);
     let max_path_width = max_path_width.clamp(3, (0.7 * available_width as f64) as usize);
@@ -930,6 +1568,25 @@ pub fn show_types(
     })
 }
 
+pub fn show_names(
+    formatter: &mut dyn Formatter,
+    workspace_command: &WorkspaceCommandHelper,
+    mut tree_diff: TreeDiffStream,
+) -> Result<(), CommandError> {
+    async {
+        while let Some((repo_path, diff)) = tree_diff.next().await {
+            diff?;
+            writeln!(
+                formatter,
+                "{}",
+                workspace_command.format_file_path(&repo_path)
+            )?;
+        }
+        Ok(())
+    }
+    .block_on()
+}
+
 fn diff_summary_char(value: &MergedTreeValue) -> char {
     match value.as_resolved() {
         Some(None) => '-',
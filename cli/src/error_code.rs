@@ -0,0 +1,125 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable codes of user errors, which are printed with the error and explained
+//! by `jj explain`.
+
+use std::fmt;
+
+/// The kind of a user error. The codes must not be reused for other kinds of
+/// errors, since scripts may depend on them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoRepo,
+    StaleWorkingCopy,
+    RevsetParse,
+    NoSuchRevision,
+    ImmutableCommit,
+    EmptyRevset,
+    MultipleRevisions,
+    AmbiguousIdPrefix,
+    NoSuchBranch,
+}
+
+impl ErrorCode {
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::NoRepo,
+        ErrorCode::StaleWorkingCopy,
+        ErrorCode::RevsetParse,
+        ErrorCode::NoSuchRevision,
+        ErrorCode::ImmutableCommit,
+        ErrorCode::EmptyRevset,
+        ErrorCode::MultipleRevisions,
+        ErrorCode::AmbiguousIdPrefix,
+        ErrorCode::NoSuchBranch,
+    ];
+
+    /// Looks up the error code by its name, e.g. "E1012". Case-insensitive.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|error_code| error_code.code().eq_ignore_ascii_case(code))
+            .copied()
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorCode::NoRepo => "E1001",
+            ErrorCode::StaleWorkingCopy => "E1002",
+            ErrorCode::RevsetParse => "E1010",
+            ErrorCode::NoSuchRevision => "E1011",
+            ErrorCode::ImmutableCommit => "E1012",
+            ErrorCode::EmptyRevset => "E1013",
+            ErrorCode::MultipleRevisions => "E1014",
+            ErrorCode::AmbiguousIdPrefix => "E1015",
+            ErrorCode::NoSuchBranch => "E1020",
+        }
+    }
+
+    /// One-line summary of the error.
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorCode::NoRepo => "No jj repo found",
+            ErrorCode::StaleWorkingCopy => "The working copy is stale",
+            ErrorCode::RevsetParse => "Invalid revset expression",
+            ErrorCode::NoSuchRevision => "Revision doesn't exist",
+            ErrorCode::ImmutableCommit => "Would rewrite an immutable commit",
+            ErrorCode::EmptyRevset => "Revset didn't resolve to any revisions",
+            ErrorCode::MultipleRevisions => "Revset resolved to more than one revision",
+            ErrorCode::AmbiguousIdPrefix => "Ambiguous id prefix",
+            ErrorCode::NoSuchBranch => "Branch doesn't exist",
+        }
+    }
+
+    /// The causes and remedies of the error, in Markdown.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            ErrorCode::NoRepo => include_str!("error_codes/E1001.md"),
+            ErrorCode::StaleWorkingCopy => include_str!("error_codes/E1002.md"),
+            ErrorCode::RevsetParse => include_str!("error_codes/E1010.md"),
+            ErrorCode::NoSuchRevision => include_str!("error_codes/E1011.md"),
+            ErrorCode::ImmutableCommit => include_str!("error_codes/E1012.md"),
+            ErrorCode::EmptyRevset => include_str!("error_codes/E1013.md"),
+            ErrorCode::MultipleRevisions => include_str!("error_codes/E1014.md"),
+            ErrorCode::AmbiguousIdPrefix => include_str!("error_codes/E1015.md"),
+            ErrorCode::NoSuchBranch => include_str!("error_codes/E1020.md"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools as _;
+
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique() {
+        assert!(ErrorCode::ALL.iter().map(|code| code.code()).all_unique());
+        for &error_code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(error_code.code()), Some(error_code));
+        }
+        assert_eq!(
+            ErrorCode::from_code("e1012"),
+            Some(ErrorCode::ImmutableCommit)
+        );
+        assert_eq!(ErrorCode::from_code("E9999"), None);
+    }
+}
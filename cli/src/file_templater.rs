@@ -0,0 +1,160 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Template language for the entries listed by `jj files`.
+
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use jj_lib::backend::TreeValue;
+use jj_lib::file_util;
+use jj_lib::merge::MergedTreeValue;
+use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::store::Store;
+
+use crate::template_builder::{self, BuildContext, CoreTemplatePropertyKind, TemplateLanguage};
+use crate::template_parser::{
+    self, FunctionCallNode, TemplateAliasesMap, TemplateParseError, TemplateParseResult,
+};
+use crate::templater::{Template, TemplatePropertyFn};
+
+/// A file, or a directory with `jj files --tree`, in a tree.
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    pub path: RepoPathBuf,
+    pub value: MergedTreeValue,
+}
+
+impl FileEntry {
+    fn resolved_value(&self) -> Option<&TreeValue> {
+        self.value.as_resolved()?.as_ref()
+    }
+}
+
+struct FileTemplateLanguage {
+    store: Arc<Store>,
+    cwd: PathBuf,
+    workspace_root: PathBuf,
+}
+
+impl TemplateLanguage<'static> for FileTemplateLanguage {
+    type Context = FileEntry;
+    type Property = CoreTemplatePropertyKind<'static, FileEntry>;
+
+    template_builder::impl_core_wrap_property_fns!('static);
+
+    fn build_keyword(&self, name: &str, span: pest::Span) -> TemplateParseResult<Self::Property> {
+        build_file_keyword(self, name, span)
+    }
+
+    fn build_method(
+        &self,
+        build_ctx: &BuildContext<Self::Property>,
+        property: Self::Property,
+        function: &FunctionCallNode,
+    ) -> TemplateParseResult<Self::Property> {
+        template_builder::build_core_method(self, build_ctx, property, function)
+    }
+}
+
+fn build_file_keyword(
+    language: &FileTemplateLanguage,
+    name: &str,
+    span: pest::Span,
+) -> TemplateParseResult<CoreTemplatePropertyKind<'static, FileEntry>> {
+    fn wrap_fn<O, F: Fn(&FileEntry) -> O>(f: F) -> TemplatePropertyFn<F> {
+        TemplatePropertyFn(f)
+    }
+
+    let property = match name {
+        "path" => {
+            let cwd = language.cwd.clone();
+            let workspace_root = language.workspace_root.clone();
+            language.wrap_string(wrap_fn(move |entry| {
+                format_path(&cwd, &entry.path.to_fs_path(&workspace_root))
+            }))
+        }
+        "type" => language.wrap_string(wrap_fn(|entry| {
+            let file_type = match entry.resolved_value() {
+                _ if !entry.value.is_resolved() => "conflict",
+                None => "absent",
+                Some(TreeValue::File { .. }) => "file",
+                Some(TreeValue::Symlink(_)) => "symlink",
+                Some(TreeValue::Tree(_)) => "directory",
+                Some(TreeValue::GitSubmodule(_)) => "git-submodule",
+                Some(TreeValue::Conflict(_)) => "conflict",
+            };
+            file_type.to_owned()
+        })),
+        "executable" => language.wrap_boolean(wrap_fn(|entry| {
+            matches!(
+                entry.resolved_value(),
+                Some(TreeValue::File {
+                    executable: true,
+                    ..
+                })
+            )
+        })),
+        "size" => {
+            let store = language.store.clone();
+            language.wrap_integer(wrap_fn(move |entry| {
+                let size = match entry.resolved_value() {
+                    Some(TreeValue::File { id, .. }) => {
+                        let mut reader = store.read_file(&entry.path, id).unwrap();
+                        std::io::copy(&mut reader.by_ref(), &mut std::io::sink()).unwrap()
+                    }
+                    Some(TreeValue::Symlink(id)) => {
+                        store.read_symlink(&entry.path, id).unwrap().len() as u64
+                    }
+                    _ => 0,
+                };
+                i64::try_from(size).unwrap_or(i64::MAX)
+            }))
+        }
+        "blob_id" => language.wrap_string(wrap_fn(|entry| {
+            entry
+                .resolved_value()
+                .map(|value| value.hex())
+                .unwrap_or_default()
+        })),
+        _ => return Err(TemplateParseError::no_such_keyword(name, span)),
+    };
+    Ok(property)
+}
+
+/// Formats `path` relative to `cwd`, like the paths printed by other
+/// commands.
+fn format_path(cwd: &Path, path: &Path) -> String {
+    file_util::relative_path(cwd, path)
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+pub fn parse(
+    store: &Arc<Store>,
+    cwd: &Path,
+    workspace_root: &Path,
+    template_text: &str,
+    aliases_map: &TemplateAliasesMap,
+) -> TemplateParseResult<Box<dyn Template<FileEntry>>> {
+    let language = FileTemplateLanguage {
+        store: store.clone(),
+        cwd: cwd.to_owned(),
+        workspace_root: workspace_root.to_owned(),
+    };
+    let node = template_parser::parse(template_text, aliases_map)?;
+    template_builder::build(&language, &node)
+}
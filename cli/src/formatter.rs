@@ -22,6 +22,9 @@ use std::{fmt, io, mem};
 use crossterm::queue;
 use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
 use itertools::Itertools;
+use jj_lib::settings::ConfigResultExt as _;
+
+use crate::syntax_highlight;
 
 // Lets the caller label strings and translates the labels to colors
 pub trait Formatter: Write {
@@ -322,9 +325,93 @@ impl<W: Write> ColorFormatter<W> {
     }
 }
 
+/// The variants a theme can have in addition to its base colors.
+const THEME_VARIANTS: [&str; 2] = ["dark", "light"];
+
+/// Returns the names of the themes in the config, along with the variants
+/// each of them has.
+pub fn available_themes(
+    config: &config::Config,
+) -> Result<Vec<(String, Vec<&'static str>)>, config::ConfigError> {
+    let themes = config.get_table("themes").optional()?.unwrap_or_default();
+    let mut result = vec![];
+    for (name, value) in themes {
+        let table = value.into_table()?;
+        let variants = THEME_VARIANTS
+            .into_iter()
+            .filter(|variant| table.contains_key(*variant))
+            .collect();
+        result.push((name, variants));
+    }
+    result.sort();
+    Ok(result)
+}
+
+/// Returns the colors of the `ui.theme` in its `ui.theme-variant`, applied on
+/// top of the default theme, and the `colors` table applied on top of that.
+fn colors_from_config(
+    config: &config::Config,
+) -> Result<config::Map<String, config::Value>, config::ConfigError> {
+    let theme = config
+        .get_string("ui.theme")
+        .optional()?
+        .unwrap_or_else(|| "default".to_owned());
+    let variant = config.get_string("ui.theme-variant").optional()?;
+    let themes = config.get_table("themes").optional()?.unwrap_or_default();
+    if theme != "default" && !themes.contains_key(&theme) {
+        return Err(config::ConfigError::Message(format!(
+            "Unknown theme: {theme}"
+        )));
+    }
+    let mut colors = config::Map::new();
+    for name in ["default", &theme].into_iter().dedup() {
+        let Some(theme_table) = themes.get(name) else {
+            continue;
+        };
+        let theme_table = theme_table.clone().into_table()?;
+        let variant_table = variant
+            .as_ref()
+            .and_then(|variant| theme_table.get(variant))
+            .map(|value| value.clone().into_table())
+            .transpose()?
+            .unwrap_or_default();
+        for table in [&theme_table, &variant_table] {
+            if let Some(value) = table.get("colors") {
+                merge_colors(&mut colors, value.clone().into_table()?);
+            }
+        }
+    }
+    if let Some(table) = config.get_table("colors").optional()? {
+        merge_colors(&mut colors, table);
+    }
+    Ok(colors)
+}
+
+/// Applies the `overrides` on top of the `colors`. Styles given as tables are
+/// merged, so e.g. overriding just the foreground color keeps the boldness.
+fn merge_colors(
+    colors: &mut config::Map<String, config::Value>,
+    overrides: config::Map<String, config::Value>,
+) {
+    for (key, value) in overrides {
+        match (colors.get_mut(&key), value.kind) {
+            (
+                Some(config::Value {
+                    kind: config::ValueKind::Table(style),
+                    ..
+                }),
+                config::ValueKind::Table(style_overrides),
+            ) => style.extend(style_overrides),
+            (_, kind) => {
+                colors.insert(key, config::Value::new(None, kind));
+            }
+        }
+    }
+}
+
 fn rules_from_config(config: &config::Config) -> Result<Rules, config::ConfigError> {
     let mut result = vec![];
-    let table = config.get_table("colors")?;
+    let table = colors_from_config(config)?;
     for (key, value) in table {
         let labels = key
             .split_whitespace()
@@ -367,6 +454,17 @@ fn rules_from_config(config: &config::Config) -> Result<Rules, config::ConfigErr
             _ => {}
         }
     }
+    // Theme colors take precedence over the default colors of the same labels.
+    if let Some(theme) = config.get_string("ui.diff.syntax-theme").optional()? {
+        for (label, color) in syntax_highlight::theme_colors(&theme)? {
+            let labels = ["diff", "syntax", label].map(ToOwned::to_owned).to_vec();
+            let style = Style {
+                fg_color: Some(color),
+                ..Style::default()
+            };
+            result.push((labels, style));
+        }
+    }
     Ok(result)
 }
 
@@ -389,10 +487,34 @@ fn color_for_name(color_name: &str) -> Result<Color, config::ConfigError> {
         "bright magenta" => Ok(Color::Magenta),
         "bright cyan" => Ok(Color::Cyan),
         "bright white" => Ok(Color::White),
-        _ => Err(config::ConfigError::Message(format!(
-            "invalid color: {color_name}"
-        ))),
+        _ => {
+            if let Some(hex) = color_name.strip_prefix('#') {
+                if let Some(color) = color_for_hex(hex) {
+                    return Ok(color);
+                }
+            } else if let Some(index) = color_name.strip_prefix("ansi-color-") {
+                if let Ok(index) = index.parse() {
+                    return Ok(Color::AnsiValue(index));
+                }
+            }
+            Err(config::ConfigError::Message(format!(
+                "invalid color: {color_name}"
+            )))
+        }
+    }
+}
+
+/// Parses a `rrggbb` truecolor value.
+fn color_for_hex(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
     }
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb {
+        r: component(0)?,
+        g: component(2)?,
+        b: component(4)?,
+    })
 }
 
 impl<W: Write> Write for ColorFormatter<W> {
@@ -676,6 +798,94 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn test_color_formatter_truecolor_and_256_colors() {
+        let config = config_from_string(
+            r##"
+        colors.truecolor = "#ff8000"
+        colors.palette = { fg = "ansi-color-208", bg = "#00FF80" }
+        "##,
+        );
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config).unwrap();
+        for label in ["truecolor", "palette"] {
+            formatter.push_label(label).unwrap();
+            formatter.write_str(&format!(" {label} ")).unwrap();
+            formatter.pop_label().unwrap();
+            formatter.write_str("\n").unwrap();
+        }
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r###"
+        [38;2;255;128;0m truecolor [39m
+        [38;5;208m[48;2;0;255;128m palette [39m[49m
+        "###);
+
+        for color in ["#ff80", "#gg8000", "ansi-color-256", "ansi-color-"] {
+            let config = config_from_string(&format!(r#"colors.label = "{color}""#));
+            let err = ColorFormatter::for_config(&mut vec![], &config).unwrap_err();
+            assert_eq!(err.to_string(), format!("invalid color: {color}"));
+        }
+    }
+
+    #[test]
+    fn test_color_formatter_themes() {
+        let config_text = r#"
+        [themes.default.colors]
+        "a" = "red"
+        "b" = { fg = "red", bold = true }
+        [themes.custom.colors]
+        "a" = "green"
+        [themes.custom.light.colors]
+        "c" = "blue"
+        [themes.custom.dark.colors]
+        "c" = "cyan"
+        "#;
+        let format_labels = |extra_config: &str| {
+            let config = config_from_string(&format!("{extra_config}\n{config_text}"));
+            let mut output: Vec<u8> = vec![];
+            let mut formatter = ColorFormatter::for_config(&mut output, &config).unwrap();
+            for label in ["a", "b", "c"] {
+                formatter.push_label(label).unwrap();
+                formatter.write_str(label).unwrap();
+                formatter.pop_label().unwrap();
+                formatter.write_str("\n").unwrap();
+            }
+            drop(formatter);
+            String::from_utf8(output).unwrap()
+        };
+
+        // The default theme is used if none is selected
+        insta::assert_snapshot!(format_labels(""), @r###"
+        [38;5;1ma[39m
+        [1m[38;5;1mb[0m
+        c
+        "###);
+        // The theme and its variant are applied on top of the default theme
+        insta::assert_snapshot!(format_labels(r#"ui.theme = "custom""#), @r###"
+        [38;5;2ma[39m
+        [1m[38;5;1mb[0m
+        c
+        "###);
+        insta::assert_snapshot!(
+            format_labels("ui.theme = \"custom\"\nui.theme-variant = \"dark\""), @r###"
+        [38;5;2ma[39m
+        [1m[38;5;1mb[0m
+        [38;5;6mc[39m
+        "###);
+        // The colors table overrides the theme, and styles given as tables are
+        // merged
+        insta::assert_snapshot!(
+            format_labels("colors.a = \"yellow\"\ncolors.b = { fg = \"blue\" }"), @r###"
+        [38;5;3ma[39m
+        [1m[38;5;4mb[0m
+        c
+        "###);
+
+        let config = config_from_string(r#"ui.theme = "missing""#);
+        let err = ColorFormatter::for_config(&mut vec![], &config).unwrap_err();
+        insta::assert_snapshot!(err.to_string(), @"Unknown theme: missing");
+    }
+
     #[test]
     fn test_color_formatter_single_label() {
         // Test that a single label can be colored and that the color is reset
@@ -14,18 +14,21 @@
 
 //! Git utilities shared by various commands.
 
+use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Mutex;
-use std::time::Instant;
-use std::{error, iter};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use std::{error, iter, thread};
 
+use itertools::Itertools as _;
 use jj_lib::git::{self, FailedRefExport, FailedRefExportReason, GitImportStats};
 use jj_lib::git_backend::GitBackend;
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
 use jj_lib::store::Store;
 
-use crate::cli_util::{user_error, CommandError};
+use crate::cli_util::{user_error, user_error_with_hint, CommandError};
 use crate::progress::Progress;
 use crate::ui::Ui;
 
@@ -36,71 +39,201 @@ pub fn get_git_repo(store: &Store) -> Result<git2::Repository, CommandError> {
     }
 }
 
-fn terminal_get_username(ui: &mut Ui, url: &str) -> Option<String> {
-    ui.prompt(&format!("Username for {url}")).ok()
+/// A way to ask the user for credentials, as listed in
+/// `git.credential-prompts`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CredentialPrompt {
+    /// A `pinentry` dialog. Can only ask for passphrases.
+    Pinentry,
+    /// The program in `git.askpass`, `$GIT_ASKPASS`, or `$SSH_ASKPASS`.
+    Askpass,
+    /// A prompt in the terminal.
+    Terminal,
 }
 
-fn terminal_get_pw(ui: &mut Ui, url: &str) -> Option<String> {
-    ui.prompt_password(&format!("Passphrase for {url}: ")).ok()
+/// How to ask for credentials when fetching and pushing.
+#[derive(Clone, Debug)]
+struct CredentialPromptSettings {
+    /// The prompts to try, in order, until one of them gets an answer.
+    prompts: Vec<CredentialPrompt>,
+    pinentry_program: String,
+    /// Passed to pinentry as `OPTION ttyname`, e.g. `/dev/pts/1`.
+    pinentry_ttyname: Option<String>,
+    /// Passed to pinentry as `OPTION ttytype`, e.g. `xterm-256color`.
+    pinentry_ttytype: Option<String>,
+    /// How long to wait for pinentry before giving up on it, if at all.
+    pinentry_timeout: Option<Duration>,
+    askpass_program: Option<String>,
 }
 
-fn pinentry_get_pw(url: &str) -> Option<String> {
-    // https://www.gnupg.org/documentation/manuals/assuan/Server-responses.html#Server-responses
-    fn decode_assuan_data(encoded: &str) -> Option<String> {
-        let encoded = encoded.as_bytes();
-        let mut decoded = Vec::with_capacity(encoded.len());
-        let mut i = 0;
-        while i < encoded.len() {
-            if encoded[i] != b'%' {
-                decoded.push(encoded[i]);
+impl CredentialPromptSettings {
+    fn from_settings(settings: &UserSettings) -> Result<Self, CommandError> {
+        let config = settings.config();
+        let prompts = match config
+            .get::<Vec<String>>("git.credential-prompts")
+            .optional()?
+        {
+            Some(names) => names
+                .iter()
+                .map(|name| match name.as_str() {
+                    "pinentry" => Ok(CredentialPrompt::Pinentry),
+                    "askpass" => Ok(CredentialPrompt::Askpass),
+                    "terminal" => Ok(CredentialPrompt::Terminal),
+                    _ => Err(user_error_with_hint(
+                        format!("Invalid credential prompt in git.credential-prompts: {name}"),
+                        "The prompts are `pinentry`, `askpass`, and `terminal`.",
+                    )),
+                })
+                .try_collect()?,
+            None => vec![
+                CredentialPrompt::Pinentry,
+                CredentialPrompt::Askpass,
+                CredentialPrompt::Terminal,
+            ],
+        };
+        let pinentry_program = config
+            .get_string("git.pinentry.program")
+            .optional()?
+            .map_or_else(
+                || "pinentry".to_owned(),
+                |program| expand_config_path(&program),
+            );
+        let pinentry_timeout = config
+            .get::<u64>("git.pinentry.timeout")
+            .optional()?
+            .unwrap_or(60);
+        let askpass_program = config
+            .get_string("git.askpass")
+            .optional()?
+            .map(|program| expand_config_path(&program))
+            .or_else(|| std::env::var("GIT_ASKPASS").ok())
+            .or_else(|| std::env::var("SSH_ASKPASS").ok())
+            .filter(|program| !program.is_empty());
+        Ok(CredentialPromptSettings {
+            prompts,
+            pinentry_program,
+            pinentry_ttyname: config.get_string("git.pinentry.ttyname").optional()?,
+            pinentry_ttytype: config.get_string("git.pinentry.ttytype").optional()?,
+            pinentry_timeout: (pinentry_timeout > 0).then(|| Duration::from_secs(pinentry_timeout)),
+            askpass_program,
+        })
+    }
+
+    fn get_username(&self, ui: &mut Ui, url: &str) -> Option<String> {
+        self.prompts.iter().find_map(|prompt| match prompt {
+            // pinentry can only ask for PINs and passphrases
+            CredentialPrompt::Pinentry => None,
+            CredentialPrompt::Askpass => self.askpass(&format!("Username for {url}: ")),
+            CredentialPrompt::Terminal => terminal_get_username(ui, url),
+        })
+    }
+
+    fn get_pw(&self, ui: &mut Ui, url: &str) -> Option<String> {
+        self.prompts.iter().find_map(|prompt| match prompt {
+            CredentialPrompt::Pinentry => self.pinentry_get_pw(url),
+            CredentialPrompt::Askpass => self.askpass(&format!("Passphrase for {url}: ")),
+            CredentialPrompt::Terminal => terminal_get_pw(ui, url),
+        })
+    }
+
+    fn askpass(&self, prompt: &str) -> Option<String> {
+        let program = self.askpass_program.as_ref()?;
+        let output = std::process::Command::new(program)
+            .arg(prompt)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let answer = String::from_utf8(output.stdout).ok()?;
+        Some(answer.strip_suffix('\n').unwrap_or(&answer).to_owned())
+    }
+
+    fn pinentry_get_pw(&self, url: &str) -> Option<String> {
+        // https://www.gnupg.org/documentation/manuals/assuan/Server-responses.html#Server-responses
+        fn decode_assuan_data(encoded: &str) -> Option<String> {
+            let encoded = encoded.as_bytes();
+            let mut decoded = Vec::with_capacity(encoded.len());
+            let mut i = 0;
+            while i < encoded.len() {
+                if encoded[i] != b'%' {
+                    decoded.push(encoded[i]);
+                    i += 1;
+                    continue;
+                }
                 i += 1;
-                continue;
+                let byte =
+                    u8::from_str_radix(std::str::from_utf8(encoded.get(i..i + 2)?).ok()?, 16)
+                        .ok()?;
+                decoded.push(byte);
+                i += 2;
             }
-            i += 1;
-            let byte =
-                u8::from_str_radix(std::str::from_utf8(encoded.get(i..i + 2)?).ok()?, 16).ok()?;
-            decoded.push(byte);
-            i += 2;
+            String::from_utf8(decoded).ok()
         }
-        String::from_utf8(decoded).ok()
-    }
-
-    let mut pinentry = std::process::Command::new("pinentry")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .ok()?;
-    #[rustfmt::skip]
-    pinentry
-        .stdin
-        .take()
-        .unwrap()
-        .write_all(
-            format!(
-                "SETTITLE jj passphrase\n\
-                 SETDESC Enter passphrase for {url}\n\
-                 SETPROMPT Passphrase:\n\
-                 GETPIN\n"
-            )
-            .as_bytes(),
-        )
-        .ok()?;
-    let mut out = String::new();
-    pinentry
-        .stdout
-        .take()
-        .unwrap()
-        .read_to_string(&mut out)
-        .ok()?;
-    _ = pinentry.wait();
-    for line in out.split('\n') {
-        if !line.starts_with("D ") {
-            continue;
+
+        let mut pinentry = std::process::Command::new(&self.pinentry_program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut commands = String::new();
+        if let Some(ttyname) = &self.pinentry_ttyname {
+            commands.push_str(&format!("OPTION ttyname={ttyname}\n"));
+        }
+        if let Some(ttytype) = &self.pinentry_ttytype {
+            commands.push_str(&format!("OPTION ttytype={ttytype}\n"));
         }
-        let (_, encoded) = line.split_at(2);
-        return decode_assuan_data(encoded);
+        if let Some(timeout) = self.pinentry_timeout {
+            commands.push_str(&format!("SETTIMEOUT {}\n", timeout.as_secs()));
+        }
+        commands.push_str(&format!(
+            "SETTITLE jj passphrase\nSETDESC Enter passphrase for {url}\nSETPROMPT \
+             Passphrase:\nGETPIN\n"
+        ));
+        pinentry
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(commands.as_bytes())
+            .ok()?;
+        // Read the output in another thread so that a pinentry that hangs
+        // (e.g. because it can't find a display) can be killed.
+        let mut stdout = pinentry.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut out = String::new();
+            let result = stdout.read_to_string(&mut out).map(|_| out);
+            _ = sender.send(result);
+        });
+        let timeout = self.pinentry_timeout.unwrap_or(Duration::MAX);
+        let out = match receiver.recv_timeout(timeout) {
+            Ok(result) => result.ok(),
+            Err(_) => {
+                tracing::info!("pinentry timed out");
+                _ = pinentry.kill();
+                None
+            }
+        };
+        _ = pinentry.wait();
+        for line in out?.split('\n') {
+            if !line.starts_with("D ") {
+                continue;
+            }
+            let (_, encoded) = line.split_at(2);
+            return decode_assuan_data(encoded);
+        }
+        None
     }
-    None
+}
+
+fn terminal_get_username(ui: &mut Ui, url: &str) -> Option<String> {
+    ui.prompt(&format!("Username for {url}")).ok()
+}
+
+fn terminal_get_pw(ui: &mut Ui, url: &str) -> Option<String> {
+    ui.prompt_password(&format!("Passphrase for {url}: ")).ok()
 }
 
 #[tracing::instrument]
@@ -122,34 +255,116 @@ fn get_ssh_keys(_username: &str) -> Vec<PathBuf> {
     paths
 }
 
+/// How to authenticate with a remote, from `git.remotes.<name>`.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct RemoteAuthConfig {
+    /// The username to use if the remote URL doesn't have one.
+    username: Option<String>,
+    /// SSH keys to try instead of the default ones in `~/.ssh`.
+    identity_files: Vec<String>,
+    /// The authentication methods to try, in order.
+    auth_methods: Option<Vec<git::AuthMethod>>,
+}
+
+impl RemoteAuthConfig {
+    fn from_settings(settings: &UserSettings, remote: &str) -> Result<Self, CommandError> {
+        let mut remotes: HashMap<String, RemoteAuthConfig> = settings
+            .config()
+            .get("git.remotes")
+            .optional()?
+            .unwrap_or_default();
+        Ok(remotes.remove(remote).unwrap_or_default())
+    }
+}
+
+/// Makes the Git backend of the `store`, if it has one, show progress when it
+/// fetches objects that are missing from a partial clone.
+pub fn set_up_fetch_missing_objects_progress(ui: &Ui, store: &Store) {
+    let Some(git_backend) = store.backend_impl().downcast_ref::<GitBackend>() else {
+        return;
+    };
+    let Some(output) = ui.progress_output() else {
+        return;
+    };
+    let output = Mutex::new(output);
+    git_backend.set_fetch_missing_objects_progress(Some(Box::new(move |remote, count| {
+        writeln!(
+            output.lock().unwrap(),
+            "Fetching {count} missing objects from remote {remote}..."
+        )
+        .ok();
+    })));
+}
+
 pub fn with_remote_git_callbacks<T>(
     ui: &mut Ui,
+    settings: &UserSettings,
+    remote: &str,
     f: impl FnOnce(git::RemoteCallbacks<'_>) -> T,
-) -> T {
-    let mut ui = Mutex::new(ui);
+) -> Result<T, CommandError> {
     let mut callback = None;
-    if let Some(mut output) = ui.get_mut().unwrap().progress_output() {
+    if let Some(mut output) = ui.progress_output() {
         let mut progress = Progress::new(Instant::now());
         callback = Some(move |x: &git::Progress| {
             _ = progress.update(Instant::now(), x, &mut output);
         });
     }
-    let mut callbacks = git::RemoteCallbacks::default();
-    callbacks.progress = callback
+    let progress = callback
         .as_mut()
         .map(|x| x as &mut dyn FnMut(&git::Progress));
-    let mut get_ssh_keys = get_ssh_keys; // Coerce to unit fn type
-    callbacks.get_ssh_keys = Some(&mut get_ssh_keys);
-    let mut get_pw = |url: &str, _username: &str| {
-        pinentry_get_pw(url).or_else(|| terminal_get_pw(*ui.lock().unwrap(), url))
+    with_callbacks(&Mutex::new(ui), settings, remote, progress, f)
+}
+
+/// Like [`with_remote_git_callbacks`], but for use from several threads at
+/// once. The `ui` is only locked while prompting for credentials, and no
+/// progress is shown.
+pub fn with_shared_remote_git_callbacks<T>(
+    ui: &Mutex<&mut Ui>,
+    settings: &UserSettings,
+    remote: &str,
+    f: impl FnOnce(git::RemoteCallbacks<'_>) -> T,
+) -> Result<T, CommandError> {
+    with_callbacks(ui, settings, remote, None, f)
+}
+
+fn with_callbacks<T>(
+    ui: &Mutex<&mut Ui>,
+    settings: &UserSettings,
+    remote: &str,
+    progress: Option<&mut dyn FnMut(&git::Progress)>,
+    f: impl FnOnce(git::RemoteCallbacks<'_>) -> T,
+) -> Result<T, CommandError> {
+    let prompt_settings = CredentialPromptSettings::from_settings(settings)?;
+    let auth_config = RemoteAuthConfig::from_settings(settings, remote)?;
+    let mut callbacks = git::RemoteCallbacks::default();
+    callbacks.progress = progress.map(|x| x as &mut dyn FnMut(&git::Progress));
+    let mut get_ssh_keys = |username: &str| {
+        if auth_config.identity_files.is_empty() {
+            get_ssh_keys(username)
+        } else {
+            auth_config
+                .identity_files
+                .iter()
+                .map(|path| PathBuf::from(expand_config_path(path)))
+                .collect()
+        }
     };
+    callbacks.get_ssh_keys = Some(&mut get_ssh_keys);
+    let mut get_pw = |url: &str, _username: &str| prompt_settings.get_pw(*ui.lock().unwrap(), url);
     callbacks.get_password = Some(&mut get_pw);
     let mut get_user_pw = |url: &str| {
         let ui = &mut *ui.lock().unwrap();
-        Some((terminal_get_username(ui, url)?, terminal_get_pw(ui, url)?))
+        let username = match &auth_config.username {
+            Some(username) => username.clone(),
+            None => prompt_settings.get_username(ui, url)?,
+        };
+        Some((username, prompt_settings.get_pw(ui, url)?))
     };
     callbacks.get_username_password = Some(&mut get_user_pw);
-    f(callbacks)
+    callbacks.username = auth_config.username.as_deref();
+    callbacks.auth_methods = auth_config.auth_methods.as_deref();
+    Ok(f(callbacks))
 }
 
 pub fn print_git_import_stats(ui: &mut Ui, stats: &GitImportStats) -> Result<(), CommandError> {
@@ -194,12 +409,167 @@ export or their "parent" branches."#,
     Ok(())
 }
 
-/// Expands "~/" to "$HOME/" as Git seems to do for e.g. core.excludesFile.
-pub fn expand_git_path(path_str: &str) -> PathBuf {
-    if let Some(remainder) = path_str.strip_prefix("~/") {
-        if let Ok(home_dir_str) = std::env::var("HOME") {
-            return PathBuf::from(home_dir_str).join(remainder);
-        }
+/// Splits a remote URL like `https://host/path` or `user@host:path` into the
+/// host name and the path, without a trailing `.git`. Returns `None` if the URL
+/// has no host, e.g. if it's a local path.
+pub fn split_remote_url(url: &str) -> Option<(&str, &str)> {
+    let (host, path) = if let Some((_, rest)) = url.split_once("://") {
+        // https://host/path, ssh://user@host:port/path
+        let rest = rest.split_once('@').map_or(rest, |(_, rest)| rest);
+        rest.split_once('/')?
+    } else {
+        // user@host:path
+        let (host, path) = url.split_once(':')?;
+        (host.split_once('@').map_or(host, |(_, host)| host), path)
+    };
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    if host.is_empty() {
+        return None;
+    }
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    Some((host, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt_settings(toml: &str) -> Result<CredentialPromptSettings, CommandError> {
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        CredentialPromptSettings::from_settings(&UserSettings::from_config(config))
+    }
+
+    #[cfg(unix)]
+    fn write_script(dir: &Path, name: &str, content: &str) -> String {
+        use std::os::unix::fs::PermissionsExt as _;
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{content}")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_credential_prompt_settings() {
+        let settings = prompt_settings("").unwrap();
+        assert_eq!(
+            settings.prompts,
+            [
+                CredentialPrompt::Pinentry,
+                CredentialPrompt::Askpass,
+                CredentialPrompt::Terminal
+            ]
+        );
+        assert_eq!(settings.pinentry_program, "pinentry");
+        assert_eq!(settings.pinentry_timeout, Some(Duration::from_secs(60)));
+
+        let settings = prompt_settings(
+            r#"
+            git.credential-prompts = ["terminal", "pinentry"]
+            git.pinentry.program = "pinentry-curses"
+            git.pinentry.timeout = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.prompts,
+            [CredentialPrompt::Terminal, CredentialPrompt::Pinentry]
+        );
+        assert_eq!(settings.pinentry_program, "pinentry-curses");
+        assert_eq!(settings.pinentry_timeout, None);
+
+        assert!(prompt_settings(r#"git.credential-prompts = ["gui"]"#).is_err());
+    }
+
+    #[test]
+    fn test_remote_auth_config() {
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [git.remotes.work]
+                username = "git"
+                identity-files = ["~/.ssh/id_work"]
+                auth-methods = ["key-file", "password"]
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let settings = UserSettings::from_config(config);
+
+        let work = RemoteAuthConfig::from_settings(&settings, "work").unwrap();
+        assert_eq!(work.username.as_deref(), Some("git"));
+        assert_eq!(work.identity_files, ["~/.ssh/id_work"]);
+        assert_eq!(
+            work.auth_methods,
+            Some(vec![git::AuthMethod::KeyFile, git::AuthMethod::Password])
+        );
+
+        let origin = RemoteAuthConfig::from_settings(&settings, "origin").unwrap();
+        assert_eq!(origin.username, None);
+        assert!(origin.identity_files.is_empty());
+        assert_eq!(origin.auth_methods, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pinentry_get_pw() {
+        let temp_dir = testutils::new_temp_dir();
+        let pinentry = write_script(
+            temp_dir.path(),
+            "pinentry",
+            r#"echo "OK Pleased to meet you"
+while read -r line; do
+  case "$line" in
+    "OPTION ttyname=/dev/pts/9") echo OK ;;
+    GETPIN) echo "D pass%25word"; echo OK; exit ;;
+    *) echo OK ;;
+  esac
+done
+"#,
+        );
+        let settings = prompt_settings(&format!(
+            r#"
+            git.pinentry.program = "{pinentry}"
+            git.pinentry.ttyname = "/dev/pts/9"
+            "#
+        ))
+        .unwrap();
+        assert_eq!(
+            settings.pinentry_get_pw("https://example.com"),
+            Some("pass%word".to_owned())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pinentry_timeout() {
+        let temp_dir = testutils::new_temp_dir();
+        let pinentry = write_script(temp_dir.path(), "pinentry", "exec sleep 60\n");
+        let settings = prompt_settings(&format!(
+            r#"
+            git.pinentry.program = "{pinentry}"
+            git.pinentry.timeout = 1
+            "#
+        ))
+        .unwrap();
+        let start = Instant::now();
+        assert_eq!(settings.pinentry_get_pw("https://example.com"), None);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_askpass() {
+        let temp_dir = testutils::new_temp_dir();
+        let askpass = write_script(temp_dir.path(), "askpass", "echo \"answer to $1\"\n");
+        let settings = prompt_settings(&format!(r#"git.askpass = "{askpass}""#)).unwrap();
+        assert_eq!(
+            settings.askpass("Username: "),
+            Some("answer to Username: ".to_owned())
+        );
     }
-    PathBuf::from(path_str)
 }
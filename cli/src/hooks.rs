@@ -0,0 +1,235 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks that run a command configured in `hooks.<event>` when something
+//! happens in the repo.
+//!
+//! A hook gets the name of the event in the `JJ_HOOK` environment variable.
+//! `jj` commands run by a hook don't run hooks themselves, since they see that
+//! variable.
+
+use std::io::{Read as _, Write as _};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use std::{fmt, io, thread};
+
+use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use thiserror::Error;
+
+use crate::cli_util::{user_error_with_hint, CommandError};
+use crate::config::CommandNameAndArgs;
+use crate::ui::Ui;
+
+/// The environment variable that's set while a hook runs.
+const HOOK_ENV: &str = "JJ_HOOK";
+
+/// An event that can run a hook.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HookEvent {
+    /// Before the working copy is snapshotted. The hook may change files in
+    /// the working copy, e.g. to format them.
+    PreSnapshot,
+    /// After a snapshot recorded changes in the working copy.
+    PostSnapshot,
+    /// Before branches are pushed to a remote.
+    PrePush,
+    /// After an operation rewrote commits.
+    PostRewrite,
+    /// After an operation was recorded.
+    PostOperation,
+}
+
+impl HookEvent {
+    /// The name of the event, as used in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreSnapshot => "pre-snapshot",
+            HookEvent::PostSnapshot => "post-snapshot",
+            HookEvent::PrePush => "pre-push",
+            HookEvent::PostRewrite => "post-rewrite",
+            HookEvent::PostOperation => "post-operation",
+        }
+    }
+
+    /// Whether a failure of the hook aborts the command.
+    fn is_blocking(self) -> bool {
+        matches!(self, HookEvent::PreSnapshot | HookEvent::PrePush)
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Error)]
+enum HookError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    ExitStatus(ExitStatus),
+    #[error("Timed out after {} seconds", .0.as_secs())]
+    Timeout(Duration),
+    #[error("Failed to read the output")]
+    Output,
+}
+
+/// The hooks in the `hooks` config.
+#[derive(Clone, Debug)]
+pub struct Hooks {
+    settings: UserSettings,
+    timeout: Option<Duration>,
+    enabled: bool,
+}
+
+impl Hooks {
+    /// Loads the hooks from the `settings`. If `enabled` is false, or this is
+    /// running inside a hook, no hooks run.
+    pub fn from_settings(settings: &UserSettings, enabled: bool) -> Result<Self, CommandError> {
+        let timeout = settings
+            .config()
+            .get::<u64>("hooks.timeout")
+            .optional()?
+            .unwrap_or(0);
+        Ok(Hooks {
+            settings: settings.clone(),
+            timeout: (timeout > 0).then(|| Duration::from_secs(timeout)),
+            enabled: enabled && std::env::var_os(HOOK_ENV).is_none(),
+        })
+    }
+
+    /// Returns the command configured for the `event`, if any.
+    fn command(&self, event: HookEvent) -> Result<Option<CommandNameAndArgs>, CommandError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let key = format!("hooks.{}", event.name());
+        Ok(self.settings.config().get(&key).optional()?)
+    }
+
+    /// Whether a hook is configured for the `event`. Lets callers skip
+    /// preparing the input of a hook that won't run.
+    pub fn has_hook(&self, event: HookEvent) -> Result<bool, CommandError> {
+        Ok(self.command(event)?.is_some())
+    }
+
+    /// Runs the hook for the `event` in the directory `cwd`, with the
+    /// environment variables `envs` and `input` on stdin.
+    ///
+    /// What the hook prints is shown on stderr. If a hook for a `pre-` event
+    /// fails, the command is aborted with an error. Failures of other hooks
+    /// are reported as warnings.
+    pub fn run(
+        &self,
+        ui: &Ui,
+        event: HookEvent,
+        cwd: &Path,
+        envs: &[(&str, String)],
+        input: &[u8],
+    ) -> Result<(), CommandError> {
+        let Some(command) = self.command(event)? else {
+            return Ok(());
+        };
+        tracing::info!(?command, %event, "running hook");
+        let result = run_command(ui, &command, event, cwd, envs, input, self.timeout);
+        match result {
+            Ok(()) => Ok(()),
+            Err(err) if event.is_blocking() => Err(user_error_with_hint(
+                format!("The {event} hook failed: {err}"),
+                "Pass `--no-hooks` to skip hooks.",
+            )),
+            Err(err) => {
+                writeln!(ui.warning(), "The {event} hook failed: {err}")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn run_command(
+    ui: &Ui,
+    command: &CommandNameAndArgs,
+    event: HookEvent,
+    cwd: &Path,
+    envs: &[(&str, String)],
+    input: &[u8],
+    timeout: Option<Duration>,
+) -> Result<(), HookError> {
+    let mut child = command
+        .to_command()
+        .current_dir(cwd)
+        .env(HOOK_ENV, event.name())
+        .envs(envs.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    // Write the input and read the output in other threads so that a hook
+    // that doesn't read all of its input, or that hangs, can't block us.
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    thread::spawn(move || {
+        _ = stdin.write_all(&input);
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = vec![];
+        let result = stdout.read_to_end(&mut output).map(|_| output);
+        _ = sender.send(result);
+    });
+    // The hook may keep running after closing its stdout, so the timeout
+    // applies until it exits.
+    let deadline = timeout.map(|timeout| (timeout, Instant::now() + timeout));
+    let result = match deadline {
+        Some((timeout, deadline)) => receiver
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .map_err(|err| match err {
+                RecvTimeoutError::Timeout => HookError::Timeout(timeout),
+                RecvTimeoutError::Disconnected => HookError::Output,
+            }),
+        None => receiver.recv().map_err(|_| HookError::Output),
+    };
+    let output = match result {
+        Ok(output) => output?,
+        Err(err) => {
+            _ = child.kill();
+            _ = child.wait();
+            return Err(err);
+        }
+    };
+    let status = match deadline {
+        Some((timeout, deadline)) => loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                _ = child.kill();
+                _ = child.wait();
+                return Err(HookError::Timeout(timeout));
+            }
+            thread::sleep(Duration::from_millis(10));
+        },
+        None => child.wait()?,
+    };
+    // Keep the stdout of jj for the output of the command itself.
+    ui.stderr().write_all(&output)?;
+    if !status.success() {
+        return Err(HookError::ExitStatus(status));
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translations of user-facing messages.
+//!
+//! Messages are looked up by id in the catalog of the language selected by
+//! `ui.language`, falling back to the English catalog. Message templates refer
+//! to their arguments as `{name}`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The built-in message catalogs, by language code. The English catalog
+/// defines every message.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("i18n/en.toml")),
+    ("fr", include_str!("i18n/fr.toml")),
+];
+
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Codes of the languages that have a built-in catalog.
+pub fn available_languages() -> impl Iterator<Item = &'static str> {
+    CATALOGS.iter().map(|(language, _)| *language)
+}
+
+/// The messages of the selected language.
+#[derive(Clone, Debug)]
+pub struct Messages {
+    language: String,
+    catalog: Arc<HashMap<String, String>>,
+    fallback: Arc<HashMap<String, String>>,
+}
+
+impl Messages {
+    pub fn new(language: &str) -> Self {
+        let fallback = Arc::new(load_catalog(FALLBACK_LANGUAGE).unwrap());
+        let (language, catalog) = match load_catalog(language) {
+            Some(catalog) => (language, Arc::new(catalog)),
+            None => (FALLBACK_LANGUAGE, fallback.clone()),
+        };
+        Messages {
+            language: language.to_owned(),
+            catalog,
+            fallback,
+        }
+    }
+
+    pub fn from_config(config: &config::Config) -> Self {
+        // The locale isn't used, so that messages (and output that scripts
+        // parse) don't change unless the user asks for another language
+        let language = config.get_string("ui.language").unwrap_or_default();
+        Self::new(&language)
+    }
+
+    /// The code of the language in use.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Formats the message `id` with the given arguments. If the message
+    /// isn't defined, the `id` itself is returned.
+    pub fn format(&self, id: &str, args: &[(&str, String)]) -> String {
+        match self.catalog.get(id).or_else(|| self.fallback.get(id)) {
+            Some(template) => format_template(template, args),
+            None => id.to_owned(),
+        }
+    }
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self::new(FALLBACK_LANGUAGE)
+    }
+}
+
+fn load_catalog(language: &str) -> Option<HashMap<String, String>> {
+    let (_, text) = CATALOGS.iter().find(|(name, _)| *name == language)?;
+    Some(toml_edit::de::from_str(text).expect("built-in message catalog should be valid"))
+}
+
+/// Replaces `{name}` in the `template` with the value of the argument `name`.
+/// `{{` and `}}` are literal braces.
+fn format_template(template: &str, args: &[(&str, String)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        output.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(stripped) = rest.strip_prefix("{{") {
+            output.push('{');
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("}}") {
+            output.push('}');
+            rest = stripped;
+        } else if let Some((name, stripped)) = rest[1..].split_once('}') {
+            match args.iter().find(|(arg_name, _)| *arg_name == name) {
+                Some((_, value)) => output.push_str(value),
+                None => output.push_str(&rest[..name.len() + 2]),
+            }
+            rest = stripped;
+        } else {
+            output.push_str(rest);
+            rest = "";
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Formats a message from the catalog of the `Ui`, e.g.
+/// `tr!(ui, "rebased-descendants", count = num_rebased)`.
+#[macro_export]
+macro_rules! tr {
+    ($ui:expr, $id:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $ui.messages()
+            .format($id, &[$((stringify!($name), $value.to_string())),*])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_format_template() {
+        let args = [("name", "main".to_owned()), ("count", "2".to_owned())];
+        assert_eq!(
+            format_template("Moved {count} commits to {name}", &args),
+            "Moved 2 commits to main"
+        );
+        assert_eq!(
+            format_template("{{name}} {unknown}", &args),
+            "{name} {unknown}"
+        );
+        assert_eq!(format_template("unclosed {name", &args), "unclosed {name");
+        assert_eq!(format_template("}} {", &args), "} {");
+    }
+
+    #[test]
+    fn test_catalogs() {
+        let english = load_catalog("en").unwrap();
+        for language in available_languages() {
+            let catalog = load_catalog(language).unwrap();
+            for id in catalog.keys() {
+                assert!(
+                    english.contains_key(id),
+                    "message {id} of {language} is not defined in English"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let messages = Messages::new("xx");
+        assert_eq!(messages.language(), "en");
+        assert_eq!(messages.format("nothing-changed", &[]), "Nothing changed.");
+        let messages = Messages::new("fr");
+        assert_eq!(messages.language(), "fr");
+        assert_eq!(messages.format("nothing-changed", &[]), "Rien n'a changé.");
+        assert_eq!(messages.format("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test]
+    fn test_messages_used_are_defined() {
+        fn visit(dir: &Path, ids: &mut Vec<String>) {
+            let id_regex = Regex::new(r#"tr!\(\s*[^,]+,\s*"([^"]+)""#).unwrap();
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    visit(&path, ids);
+                } else if path.extension().is_some_and(|ext| ext == "rs") {
+                    let text = fs::read_to_string(&path).unwrap();
+                    ids.extend(id_regex.captures_iter(&text).map(|c| c[1].to_owned()));
+                }
+            }
+        }
+
+        let mut ids = vec![];
+        visit(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut ids);
+        assert!(!ids.is_empty());
+        let english = load_catalog("en").unwrap();
+        for id in ids {
+            assert!(
+                english.contains_key(&id),
+                "message {id} is not defined in English"
+            );
+        }
+    }
+}
@@ -18,15 +18,28 @@ pub mod cleanup_guard;
 pub mod cli_util;
 pub mod commands;
 pub mod commit_templater;
+pub mod complete;
 pub mod config;
+pub mod config_schema;
+pub mod conflict_templater;
 pub mod description_util;
+pub mod diff_driver;
 pub mod diff_util;
+pub mod error_code;
+pub mod file_templater;
 pub mod formatter;
 pub mod git_util;
 pub mod graphlog;
+pub mod hooks;
+pub mod i18n;
+pub mod maintenance;
 pub mod merge_tools;
 pub mod operation_templater;
+pub mod pager;
+pub mod patch_util;
+pub mod plugins;
 mod progress;
+pub mod syntax_highlight;
 pub mod template_builder;
 pub mod template_parser;
 pub mod templater;
@@ -0,0 +1,150 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scheduling of repo maintenance, which can run automatically in a detached
+//! background process.
+//!
+//! The operation at which maintenance last ran is recorded in
+//! `.jj/repo/maintenance/last-run`. Maintenance is due when enough operations
+//! were added since then, or enough time has passed since the file was
+//! written.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
+
+use jj_lib::lock::{FileLock, LockBackend};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::op_walk;
+use jj_lib::repo::ReadonlyRepo;
+use jj_lib::settings::UserSettings;
+use tracing::instrument;
+
+/// When maintenance should run automatically.
+#[derive(Clone, Debug)]
+pub struct AutoMaintenanceSettings {
+    /// Whether maintenance runs automatically at all.
+    pub enabled: bool,
+    /// Run after this many operations since the last run.
+    pub after_operations: usize,
+    /// Run after this much time since the last run.
+    pub after_duration: Duration,
+}
+
+impl AutoMaintenanceSettings {
+    pub fn from_settings(settings: &UserSettings) -> Self {
+        let config = settings.config();
+        let after_days = config
+            .get_int("maintenance.auto-after-days")
+            .unwrap_or(7)
+            .max(0);
+        AutoMaintenanceSettings {
+            enabled: config.get_bool("maintenance.auto").unwrap_or(false),
+            after_operations: config
+                .get_int("maintenance.auto-after-operations")
+                .unwrap_or(100)
+                .max(1) as usize,
+            after_duration: Duration::from_secs(after_days as u64 * 86400),
+        }
+    }
+}
+
+fn state_dir(repo: &ReadonlyRepo) -> PathBuf {
+    repo.repo_path().join("maintenance")
+}
+
+/// Takes the lock that prevents concurrent maintenance runs. Returns `None`
+/// if maintenance is already running.
+pub fn try_lock(repo: &ReadonlyRepo, lock_backend: LockBackend) -> io::Result<Option<FileLock>> {
+    let dir = state_dir(repo);
+    fs::create_dir_all(&dir)?;
+    FileLock::try_lock(dir.join("lock"), lock_backend)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+/// Records that maintenance ran at the current operation of the `repo`.
+pub fn record_run(repo: &ReadonlyRepo) -> io::Result<()> {
+    let dir = state_dir(repo);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("last-run"), repo.op_id().hex())
+}
+
+/// Checks if maintenance is due. If maintenance has never run, the current
+/// operation is recorded as the last run, so enabling automatic maintenance
+/// doesn't immediately start it.
+#[instrument(skip_all)]
+pub fn is_due(repo: &ReadonlyRepo, settings: &AutoMaintenanceSettings) -> io::Result<bool> {
+    let path = state_dir(repo).join("last-run");
+    let (contents, mtime) = match fs::read_to_string(&path) {
+        Ok(contents) => (contents, fs::metadata(&path)?.modified()?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            record_run(repo)?;
+            return Ok(false);
+        }
+        Err(err) => return Err(err),
+    };
+    if mtime + settings.after_duration <= SystemTime::now() {
+        return Ok(true);
+    }
+    let Ok(last_op_id) = OperationId::try_from_hex(contents.trim()) else {
+        return Ok(true);
+    };
+    // If the last operation was abandoned, maintenance is only run after
+    // enough time has passed.
+    let new_op_count = op_walk::walk_ancestors(std::slice::from_ref(repo.operation()))
+        .take(settings.after_operations)
+        .take_while(|op| op.as_ref().map_or(true, |op| *op.id() != last_op_id))
+        .count();
+    Ok(new_op_count >= settings.after_operations)
+}
+
+/// Starts `jj util maintenance --auto` in a detached background process if
+/// automatic maintenance is enabled and due. Errors are only logged, since
+/// they shouldn't fail the command that triggered the maintenance.
+#[instrument(skip_all)]
+pub fn maybe_start_in_background(
+    settings: &UserSettings,
+    workspace_root: &Path,
+    repo: &ReadonlyRepo,
+) {
+    let auto_settings = AutoMaintenanceSettings::from_settings(settings);
+    if !auto_settings.enabled {
+        return;
+    }
+    match is_due(repo, &auto_settings) {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(err) => {
+            tracing::warn!(?err, "failed to check if maintenance is due");
+            return;
+        }
+    }
+    let result = std::env::current_exe().and_then(|jj| {
+        Command::new(jj)
+            .args(["util", "maintenance", "--auto", "--ignore-working-copy"])
+            .arg("--repository")
+            .arg(workspace_root)
+            .current_dir(workspace_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    match result {
+        Ok(child) => tracing::info!(pid = child.id(), "started background maintenance"),
+        Err(err) => tracing::warn!(?err, "failed to start background maintenance"),
+    }
+}
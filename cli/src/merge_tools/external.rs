@@ -1,24 +1,26 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
 use std::sync::Arc;
+use std::thread;
 
 use config::ConfigError;
 use futures::StreamExt;
 use itertools::Itertools;
 use jj_lib::backend::{FileId, MergedTreeId, TreeValue};
 use jj_lib::conflicts::{self, materialize_merge_result};
+use jj_lib::fsync::Fsync;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::local_working_copy::{TreeState, TreeStateError};
 use jj_lib::matchers::Matcher;
 use jj_lib::merge::{Merge, MergedTreeValue};
 use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
 use jj_lib::repo_path::{RepoPath, RepoPathBuf};
-use jj_lib::settings::UserSettings;
+use jj_lib::settings::{expand_config_path, UserSettings};
 use jj_lib::store::Store;
-use jj_lib::working_copy::{CheckoutError, SnapshotOptions};
+use jj_lib::working_copy::{CheckoutError, CheckoutOptions, SnapshotOptions};
 use pollster::FutureExt;
 use regex::{Captures, Regex};
 use tempfile::TempDir;
@@ -95,7 +97,7 @@ impl ExternalMergeTool {
     ) -> Self {
         let (name, args) = command_args.split_name_and_args();
         let mut tool = Self {
-            program: name.into_owned(),
+            program: expand_config_path(&name),
             ..Default::default()
         };
         if !args.is_empty() {
@@ -190,9 +192,10 @@ fn check_out(
 ) -> Result<TreeState, DiffCheckoutError> {
     std::fs::create_dir(&wc_dir).map_err(DiffCheckoutError::SetUpDir)?;
     std::fs::create_dir(&state_dir).map_err(DiffCheckoutError::SetUpDir)?;
-    let mut tree_state = TreeState::init(store, wc_dir, state_dir)?;
-    tree_state.set_sparse_patterns(sparse_patterns)?;
-    tree_state.check_out(tree)?;
+    // The checkout is temporary, so there's no need to flush it.
+    let mut tree_state = TreeState::init(store, wc_dir, state_dir, Fsync::default())?;
+    tree_state.set_sparse_patterns(sparse_patterns, CheckoutOptions { progress: None })?;
+    tree_state.check_out(tree, CheckoutOptions { progress: None })?;
     Ok(tree_state)
 }
 
@@ -558,7 +561,24 @@ pub fn generate_diff(
             tool_binary: tool.program.clone(),
             source,
         })?;
+    // Read stderr, if it's piped, on another thread so that neither pipe fills
+    // up while the other is being read.
+    let stderr_thread = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut buf = vec![];
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        })
+    });
     io::copy(&mut child.stdout.take().unwrap(), writer).map_err(ExternalToolError::Io)?;
+    if let Some(stderr_thread) = stderr_thread {
+        let stderr = stderr_thread
+            .join()
+            .expect("stderr reader thread panicked")
+            .map_err(ExternalToolError::Io)?;
+        ui.stderr()
+            .write_all(&stderr)
+            .map_err(ExternalToolError::Io)?;
+    }
     // Non-zero exit code isn't an error. For example, the traditional diff command
     // will exit with 1 if inputs are different.
     let exit_status = child.wait().map_err(ExternalToolError::Io)?;
@@ -24,7 +24,7 @@ use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::matchers::Matcher;
 use jj_lib::merged_tree::MergedTree;
 use jj_lib::repo_path::{RepoPath, RepoPathBuf};
-use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use jj_lib::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
 use jj_lib::working_copy::SnapshotError;
 use pollster::FutureExt;
 use thiserror::Error;
@@ -203,6 +203,7 @@ pub fn get_tool_config(
         if result.program.is_empty() {
             result.program.clone_from(&name.to_string());
         };
+        result.program = expand_config_path(&result.program);
         Ok(Some(MergeTool::External(result)))
     } else {
         Ok(None)
@@ -410,6 +411,40 @@ mod tests {
         assert!(get(r#"ui.diff-editor.k = 0"#).is_err());
     }
 
+    #[test]
+    fn test_get_diff_tool() {
+        let get = |text, name| {
+            let config = config_from_string(text);
+            let settings = UserSettings::from_config(config);
+            get_tool_config(&settings, name)
+        };
+
+        // Built-in tool configuration
+        insta::assert_debug_snapshot!(get("", "difft").unwrap(), @r###"
+        Some(
+            External(
+                ExternalMergeTool {
+                    program: "difft",
+                    diff_args: [
+                        "--color=always",
+                        "$left",
+                        "$right",
+                    ],
+                    edit_args: [
+                        "$left",
+                        "$right",
+                    ],
+                    merge_args: [],
+                    merge_tool_edits_conflict_markers: false,
+                },
+            ),
+        )
+        "###);
+
+        // Unknown tool
+        insta::assert_debug_snapshot!(get("", "my-diff").unwrap(), @"None");
+    }
+
     #[test]
     fn test_get_merge_tool() {
         let get = |text| {
@@ -133,6 +133,7 @@ fn build_operation_keyword(
             language.wrap_string(wrap_metadata_fn(|metadata| metadata.description.clone()))
         }
         "id" => language.wrap_operation_id(wrap_fn(|op| op.id().clone())),
+        "merge" => language.wrap_boolean(wrap_fn(|op| op.parent_ids().len() > 1)),
         "tags" => language.wrap_string(wrap_metadata_fn(|metadata| {
             // TODO: introduce map type
             metadata
@@ -0,0 +1,693 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Built-in pager, used when `ui.pager` is set to `:builtin`.
+//!
+//! The output is received on a separate thread. If it fits in the terminal,
+//! it's printed as is. Otherwise it's shown on the alternate screen without
+//! wrapping long lines, which can be scrolled horizontally instead. Color
+//! escape sequences are passed through.
+
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{cmp, mem};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use unicode_width::UnicodeWidthChar as _;
+
+/// Value of `ui.pager` selecting the built-in pager.
+pub const BUILTIN_PAGER_NAME: &str = ":builtin";
+
+const TAB_WIDTH: usize = 8;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Handle to the pager thread. Output written to `&BuiltinPager` is sent to
+/// the pager. Writes fail with `BrokenPipe` once the user has quit.
+#[derive(Debug)]
+pub struct BuiltinPager {
+    sender: Sender<Vec<u8>>,
+    thread: JoinHandle<io::Result<()>>,
+}
+
+impl BuiltinPager {
+    pub fn spawn() -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("pager".to_owned())
+            .spawn(move || run_pager(receiver))?;
+        Ok(BuiltinPager { sender, thread })
+    }
+
+    /// Signals the end of the output and waits for the user to quit.
+    pub fn wait(self) -> io::Result<()> {
+        drop(self.sender);
+        self.thread.join().unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "pager thread panicked",
+            ))
+        })
+    }
+}
+
+impl Write for &BuiltinPager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run_pager(receiver: Receiver<Vec<u8>>) -> io::Result<()> {
+    let (width, height) = match terminal::size() {
+        Ok((width, height)) if width > 0 && height > 0 => (width, height),
+        _ => return copy_to_stdout(&[], &receiver),
+    };
+    // Buffer the output until it no longer fits in the terminal, so short
+    // output is printed without entering the alternate screen.
+    let mut buffered = vec![];
+    while buffered.iter().filter(|&&b| b == b'\n').count() < usize::from(height) {
+        match receiver.recv() {
+            Ok(data) => buffered.extend(data),
+            Err(_) => return copy_to_stdout(&buffered, &receiver),
+        }
+    }
+
+    let mut content = Content::default();
+    content.push(&buffered);
+    let mut state = PagerState::new(content, width, height);
+    let mut stdout = io::stdout();
+    let _screen = AlternateScreen::enter()?;
+    let mut receiver = Some(receiver);
+    let mut dirty = true;
+    loop {
+        if let Some(rx) = &receiver {
+            loop {
+                match rx.try_recv() {
+                    Ok(data) => {
+                        state.content.push(&data);
+                        dirty = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        state.content.finish();
+                        receiver = None;
+                        dirty = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if dirty {
+            state.draw(&mut stdout, receiver.is_none())?;
+            dirty = false;
+        }
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                if state.handle_key(key) == KeyAction::Quit {
+                    return Ok(());
+                }
+                dirty = true;
+            }
+            Event::Resize(width, height) => {
+                state.resize(width, height);
+                dirty = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn copy_to_stdout(buffered: &[u8], receiver: &Receiver<Vec<u8>>) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(buffered)?;
+    for data in receiver {
+        stdout.write_all(&data)?;
+    }
+    stdout.flush()
+}
+
+/// Restores the terminal state when dropped.
+pub(crate) struct AlternateScreen;
+
+impl AlternateScreen {
+    pub(crate) fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = AlternateScreen;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        execute!(io::stdout(), cursor::Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/// Output received so far, split into lines.
+#[derive(Debug, Default)]
+pub(crate) struct Content {
+    pub(crate) lines: Vec<String>,
+    partial: Vec<u8>,
+    /// Graphic rendition in effect at the end of the last line.
+    sgr_state: SgrState,
+}
+
+impl Content {
+    pub(crate) fn push(&mut self, data: &[u8]) {
+        self.partial.extend_from_slice(data);
+        let Some(pos) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return;
+        };
+        let rest = self.partial.split_off(pos + 1);
+        let complete = mem::replace(&mut self.partial, rest);
+        for line in complete[..pos].split(|&b| b == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            self.push_line(&String::from_utf8_lossy(line));
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        if !self.partial.is_empty() {
+            let line = mem::take(&mut self.partial);
+            self.push_line(&String::from_utf8_lossy(&line));
+        }
+    }
+
+    /// Adds a line, prefixed by the colors carried over from previous lines
+    /// so each line can be rendered on its own.
+    fn push_line(&mut self, line: &str) {
+        let stored = format!("{}{line}", self.sgr_state.to_sequence());
+        for seq in sgr_sequences(line) {
+            self.sgr_state.apply(seq);
+        }
+        self.lines.push(stored);
+    }
+}
+
+/// Effective colors and attributes set by SGR sequences. Only the resulting
+/// state is kept so that the prefix carried over to the next line stays short
+/// no matter how many sequences the output contains.
+#[derive(Debug, Default)]
+struct SgrState {
+    /// Whether the attributes 1 (bold) to 9 (crossed-out) are enabled.
+    attributes: [bool; 10],
+    /// Parameters setting the foreground color, e.g. `31` or `38;5;1`.
+    foreground: Option<String>,
+    /// Parameters setting the background color.
+    background: Option<String>,
+}
+
+impl SgrState {
+    fn apply(&mut self, seq: &str) {
+        let params = &seq[2..seq.len() - 1];
+        let params: Vec<&str> = params.split(';').collect();
+        let mut i = 0;
+        while i < params.len() {
+            let code: u8 = match params[i] {
+                "" => 0,
+                param => param.parse().unwrap_or(u8::MAX),
+            };
+            // Extended colors: 38;5;n or 38;2;r;g;b
+            let color_len = match params.get(i + 1) {
+                Some(&"5") => 3,
+                Some(&"2") => 5,
+                _ => 1,
+            };
+            match code {
+                0 => *self = SgrState::default(),
+                1..=9 => self.attributes[usize::from(code)] = true,
+                // 22 turns off both bold and faint
+                22 => self.attributes[1..=2].fill(false),
+                23..=29 => self.attributes[usize::from(code - 20)] = false,
+                30..=37 | 90..=97 => self.foreground = Some(params[i].to_owned()),
+                38 => {
+                    let end = cmp::min(i + color_len, params.len());
+                    self.foreground = Some(params[i..end].join(";"));
+                    i = end - 1;
+                }
+                39 => self.foreground = None,
+                40..=47 | 100..=107 => self.background = Some(params[i].to_owned()),
+                48 => {
+                    let end = cmp::min(i + color_len, params.len());
+                    self.background = Some(params[i..end].join(";"));
+                    i = end - 1;
+                }
+                49 => self.background = None,
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Returns a sequence restoring this state, or an empty string if nothing
+    /// is set.
+    fn to_sequence(&self) -> String {
+        let params: Vec<String> = (1..self.attributes.len())
+            .filter(|&code| self.attributes[code])
+            .map(|code| code.to_string())
+            .chain(self.foreground.clone())
+            .chain(self.background.clone())
+            .collect();
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", params.join(";"))
+        }
+    }
+}
+
+/// Splits the line into escape sequences and other characters.
+fn tokenize(line: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        if c != '\x1b' {
+            rest = &rest[c.len_utf8()..];
+            return Some(Token::Char(c));
+        }
+        let len = if let Some(params) = rest.strip_prefix("\x1b[") {
+            // CSI sequence: parameters followed by a final byte
+            params
+                .find(|c| ('\x40'..='\x7e').contains(&c))
+                .map_or(rest.len(), |i| i + 3)
+        } else {
+            rest[1..].chars().next().map_or(1, |c| 1 + c.len_utf8())
+        };
+        let (seq, tail) = rest.split_at(len);
+        rest = tail;
+        Some(Token::Escape(seq))
+    })
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token<'a> {
+    Char(char),
+    Escape(&'a str),
+}
+
+fn sgr_sequences(line: &str) -> impl Iterator<Item = &str> {
+    tokenize(line).filter_map(|token| match token {
+        Token::Escape(seq) if seq.starts_with("\x1b[") && seq.ends_with('m') => Some(seq),
+        _ => None,
+    })
+}
+
+/// Returns the line without escape sequences.
+fn plain_text(line: &str) -> String {
+    tokenize(line)
+        .filter_map(|token| match token {
+            Token::Char(c) => Some(c),
+            Token::Escape(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the part of the line visible in the columns `start_col` to
+/// `start_col + width`. Color sequences are kept (including the ones before
+/// `start_col`), but other escape sequences and control characters are
+/// dropped so they can't mess up the screen.
+pub(crate) fn render_line(line: &str, start_col: usize, width: usize) -> String {
+    let end_col = start_col + width;
+    let mut out = String::new();
+    let mut col = 0;
+    for token in tokenize(line) {
+        match token {
+            Token::Escape(seq) => {
+                if seq.starts_with("\x1b[") && seq.ends_with('m') {
+                    out.push_str(seq);
+                }
+            }
+            Token::Char('\t') => {
+                let next_col = (col / TAB_WIDTH + 1) * TAB_WIDTH;
+                let visible = cmp::max(col, start_col)..cmp::min(next_col, end_col);
+                out.extend(visible.map(|_| ' '));
+                col = next_col;
+            }
+            Token::Char(c) => {
+                let Some(char_width) = c.width() else {
+                    continue;
+                };
+                if col >= start_col && col + char_width <= end_col {
+                    out.push(c);
+                }
+                col += char_width;
+            }
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum KeyAction {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug)]
+pub(crate) struct PagerState {
+    content: Content,
+    /// Index of the first line on the screen.
+    top: usize,
+    /// Number of columns scrolled to the right.
+    left: usize,
+    width: u16,
+    height: u16,
+    /// Search pattern being typed.
+    input: Option<(SearchDirection, String)>,
+    /// Last search pattern.
+    pattern: Option<(SearchDirection, String)>,
+    message: Option<String>,
+}
+
+impl PagerState {
+    pub(crate) fn new(content: Content, width: u16, height: u16) -> Self {
+        PagerState {
+            content,
+            top: 0,
+            left: 0,
+            width,
+            height,
+            input: None,
+            pattern: None,
+            message: None,
+        }
+    }
+
+    pub(crate) fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Number of lines of content that fit above the status line.
+    fn page_height(&self) -> usize {
+        usize::from(self.height).saturating_sub(1).max(1)
+    }
+
+    fn max_top(&self) -> usize {
+        self.content.lines.len().saturating_sub(self.page_height())
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        // The top may be past the max after jumping to a search match
+        let max_top = cmp::max(self.max_top(), self.top);
+        self.top = cmp::min(self.top + n, max_top);
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        self.top = self.top.saturating_sub(n);
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> KeyAction {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return KeyAction::Quit;
+        }
+        if let Some((direction, text)) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let (direction, text) = (*direction, mem::take(text));
+                    self.input = None;
+                    if !text.is_empty() {
+                        self.pattern = Some((direction, text));
+                    }
+                    self.search(direction);
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace if text.is_empty() => self.input = None,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            }
+            return KeyAction::Continue;
+        }
+
+        self.message = None;
+        let page = self.page_height();
+        let half_width = usize::from(self.width / 2).max(1);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return KeyAction::Quit,
+            KeyCode::Char('j') | KeyCode::Char('e') | KeyCode::Down | KeyCode::Enter => {
+                self.scroll_down(1);
+            }
+            KeyCode::Char('k') | KeyCode::Char('y') | KeyCode::Up => self.scroll_up(1),
+            KeyCode::Char('f') if ctrl => self.scroll_down(page),
+            KeyCode::Char('b') if ctrl => self.scroll_up(page),
+            KeyCode::Char('d') if ctrl => self.scroll_down(page / 2),
+            KeyCode::Char('u') if ctrl => self.scroll_up(page / 2),
+            KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => {
+                self.scroll_down(page);
+            }
+            KeyCode::Char('b') | KeyCode::PageUp => self.scroll_up(page),
+            KeyCode::Char('d') => self.scroll_down(page / 2),
+            KeyCode::Char('u') => self.scroll_up(page / 2),
+            KeyCode::Char('g') | KeyCode::Char('<') | KeyCode::Home => self.top = 0,
+            KeyCode::Char('G') | KeyCode::Char('>') | KeyCode::End => self.top = self.max_top(),
+            KeyCode::Right => self.left += half_width,
+            KeyCode::Left => self.left = self.left.saturating_sub(half_width),
+            KeyCode::Char('/') => self.input = Some((SearchDirection::Forward, String::new())),
+            KeyCode::Char('?') => self.input = Some((SearchDirection::Backward, String::new())),
+            KeyCode::Char('n') => {
+                let direction = self.pattern.as_ref().map(|(direction, _)| *direction);
+                self.search(direction.unwrap_or(SearchDirection::Forward));
+            }
+            KeyCode::Char('N') => {
+                let direction = self.pattern.as_ref().map(|(direction, _)| *direction);
+                self.search(match direction.unwrap_or(SearchDirection::Forward) {
+                    SearchDirection::Forward => SearchDirection::Backward,
+                    SearchDirection::Backward => SearchDirection::Forward,
+                });
+            }
+            _ => {}
+        }
+        KeyAction::Continue
+    }
+
+    /// Moves the next line matching the last pattern to the top of the
+    /// screen.
+    fn search(&mut self, direction: SearchDirection) {
+        let Some((_, pattern)) = &self.pattern else {
+            self.message = Some("No previous search pattern".to_owned());
+            return;
+        };
+        let lines = &self.content.lines;
+        let is_match = |i: &usize| plain_text(&lines[*i]).contains(pattern.as_str());
+        let found = match direction {
+            SearchDirection::Forward => (self.top + 1..lines.len()).find(is_match),
+            SearchDirection::Backward => (0..self.top).rev().find(is_match),
+        };
+        match found {
+            Some(i) => self.top = i,
+            None => self.message = Some("Pattern not found".to_owned()),
+        }
+    }
+
+    fn status_line(&self, complete: bool) -> String {
+        if let Some((direction, text)) = &self.input {
+            let prompt = match direction {
+                SearchDirection::Forward => '/',
+                SearchDirection::Backward => '?',
+            };
+            format!("{prompt}{text}")
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else if complete && self.top >= self.max_top() {
+            "(END)".to_owned()
+        } else {
+            ":".to_owned()
+        }
+    }
+
+    pub(crate) fn draw(&self, out: &mut impl Write, complete: bool) -> io::Result<()> {
+        let width = usize::from(self.width);
+        for row in 0..self.page_height() {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            if let Some(line) = self.content.lines.get(self.top + row) {
+                out.write_all(render_line(line, self.left, width).as_bytes())?;
+                queue!(out, SetAttribute(Attribute::Reset))?;
+            }
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+        let status = render_line(&self.status_line(complete), 0, width);
+        queue!(
+            out,
+            cursor::MoveTo(0, self.height.saturating_sub(1)),
+            SetAttribute(Attribute::Reverse),
+        )?;
+        out.write_all(status.as_bytes())?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Reset),
+            Clear(ClearType::UntilNewLine),
+        )?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn content_of(text: &str) -> Content {
+        let mut content = Content::default();
+        content.push(text.as_bytes());
+        content.finish();
+        content
+    }
+
+    #[test]
+    fn test_content_lines() {
+        let mut content = Content::default();
+        content.push(b"first\nsec");
+        assert_eq!(content.lines, ["first"]);
+        content.push(b"ond\r\n\nthird");
+        assert_eq!(content.lines, ["first", "second", ""]);
+        content.finish();
+        assert_eq!(content.lines, ["first", "second", "", "third"]);
+    }
+
+    #[test]
+    fn test_content_carries_colors_across_lines() {
+        let content = content_of("\x1b[31mred\nstill red\x1b[0m\nplain\n");
+        assert_eq!(
+            content.lines,
+            ["\x1b[31mred", "\x1b[31mstill red\x1b[0m", "plain"]
+        );
+        let content = content_of("\x1b[1;38;5;2mgreen\x1b[3m\nboth\x1b[22;39m\nitalic\n");
+        assert_eq!(
+            content.lines,
+            [
+                "\x1b[1;38;5;2mgreen\x1b[3m",
+                "\x1b[1;3;38;5;2mboth\x1b[22;39m",
+                "\x1b[3mitalic"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_content_color_prefix_is_bounded() {
+        // The formatter resets colors with e.g. `\x1b[39m` rather than
+        // `\x1b[0m`, which must not make the carried-over prefix grow.
+        let line = "\x1b[38;5;1mred\x1b[39m \x1b[1mbold\x1b[22m \x1b[48;5;4mbg\x1b[49m";
+        let text = format!("\x1b[4m{}", format!("{line}\n").repeat(1000));
+        let content = content_of(&text);
+        assert_eq!(content.lines.len(), 1000);
+        assert_eq!(content.lines[999], format!("\x1b[4m{line}"));
+    }
+
+    #[test]
+    fn test_render_line() {
+        assert_eq!(render_line("hello world", 0, 5), "hello");
+        assert_eq!(render_line("hello world", 6, 10), "world");
+        // Colors before the visible part are kept
+        assert_eq!(
+            render_line("\x1b[1mbold\x1b[0m text", 5, 10),
+            "\x1b[1m\x1b[0mtext"
+        );
+        // Other escape sequences and control characters are dropped
+        assert_eq!(render_line("a\x1b[2Jb\x07c", 0, 10), "abc");
+        // Wide characters cut by the edges are hidden
+        assert_eq!(render_line("a\u{4e00}b", 0, 2), "a");
+        assert_eq!(render_line("a\u{4e00}b", 2, 2), "b");
+        // Tabs are expanded
+        assert_eq!(render_line("a\tb", 0, 10), "a       b");
+        assert_eq!(render_line("a\tb", 4, 10), "    b");
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(plain_text("\x1b[38;5;1mfoo\x1b[39m bar"), "foo bar");
+        assert_eq!(plain_text("trailing\x1b["), "trailing");
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let text = (0..10).map(|i| format!("line {i}\n")).collect::<String>();
+        let mut state = PagerState::new(content_of(&text), 20, 5);
+        assert_eq!(state.max_top(), 6);
+        state.handle_key(key(KeyCode::Char(' ')));
+        assert_eq!(state.top, 4);
+        state.handle_key(key(KeyCode::Char(' ')));
+        assert_eq!(state.top, 6);
+        assert_eq!(state.status_line(true), "(END)");
+        assert_eq!(state.status_line(false), ":");
+        state.handle_key(key(KeyCode::Up));
+        assert_eq!(state.top, 5);
+        state.handle_key(key(KeyCode::Char('g')));
+        assert_eq!(state.top, 0);
+        state.handle_key(key(KeyCode::Right));
+        assert_eq!(state.left, 10);
+        state.handle_key(key(KeyCode::Left));
+        state.handle_key(key(KeyCode::Left));
+        assert_eq!(state.left, 0);
+        assert_eq!(state.handle_key(key(KeyCode::Char('q'))), KeyAction::Quit);
+    }
+
+    #[test]
+    fn test_search() {
+        let text = "foo\nbar\n\x1b[1mba\x1b[0mz\nbar\n";
+        let mut state = PagerState::new(content_of(text), 20, 3);
+        state.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(state.status_line(false), "No previous search pattern");
+
+        state.handle_key(key(KeyCode::Char('/')));
+        state.handle_key(key(KeyCode::Char('b')));
+        state.handle_key(key(KeyCode::Char('a')));
+        assert_eq!(state.status_line(false), "/ba");
+        state.handle_key(key(KeyCode::Enter));
+        assert_eq!(state.top, 1);
+        // Matches ignore colors
+        state.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(state.top, 2);
+        state.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(state.top, 3);
+        state.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(state.top, 3);
+        assert_eq!(state.status_line(false), "Pattern not found");
+        state.handle_key(key(KeyCode::Char('N')));
+        assert_eq!(state.top, 2);
+
+        state.handle_key(key(KeyCode::Char('?')));
+        state.handle_key(key(KeyCode::Char('f')));
+        state.handle_key(key(KeyCode::Enter));
+        assert_eq!(state.top, 0);
+    }
+}
@@ -0,0 +1,1163 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of patches in the unified diff format (including Git's extended
+//! headers), and applying them to trees. Also selecting changes by line
+//! numbers.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read as _};
+use std::ops::RangeInclusive;
+use std::str::{self, FromStr};
+use std::sync::Arc;
+
+use jj_lib::backend::{BackendError, BackendResult, MergedTreeId, TreeValue};
+use jj_lib::diff::{find_line_ranges, Diff, DiffHunk};
+use jj_lib::merge::Merge;
+use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
+use jj_lib::repo_path::{RepoPath, RepoPathBuf};
+use jj_lib::store::Store;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("line {line}: {message}")]
+pub struct PatchParseError {
+    line: usize,
+    message: String,
+}
+
+/// Type of a file as given by the modes in Git's extended headers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileMode {
+    Normal,
+    Executable,
+    Symlink,
+}
+
+impl FileMode {
+    fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "100644" | "100664" => Some(FileMode::Normal),
+            "100755" => Some(FileMode::Executable),
+            "120000" => Some(FileMode::Symlink),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HunkLineKind {
+    Context,
+    Removed,
+    Added,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HunkLine {
+    pub kind: HunkLineKind,
+    /// The text of the line, including the newline unless the line is at the
+    /// end of a file without one.
+    pub text: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line number of the first line of the hunk in the old file.
+    pub old_start: usize,
+    /// 1-based line number of the first line of the hunk in the new file.
+    pub new_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&[u8]> {
+        self.lines
+            .iter()
+            .filter(|line| line.kind != HunkLineKind::Added)
+            .map(|line| line.text.as_slice())
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&[u8]> {
+        self.lines
+            .iter()
+            .filter(|line| line.kind != HunkLineKind::Removed)
+            .map(|line| line.text.as_slice())
+            .collect()
+    }
+
+    fn count(&self, kind: HunkLineKind) -> usize {
+        self.lines.iter().filter(|line| line.kind == kind).count()
+    }
+
+    /// Number of context lines at the start and at the end of the hunk.
+    fn context_len(&self) -> (usize, usize) {
+        let is_context = |line: &&HunkLine| line.kind == HunkLineKind::Context;
+        let leading = self.lines.iter().take_while(is_context).count();
+        let trailing = self.lines.iter().rev().take_while(is_context).count();
+        (leading, trailing.min(self.lines.len() - leading))
+    }
+
+    fn reversed(&self) -> Self {
+        let mut lines: Vec<HunkLine> = self
+            .lines
+            .iter()
+            .map(|line| HunkLine {
+                kind: match line.kind {
+                    HunkLineKind::Context => HunkLineKind::Context,
+                    HunkLineKind::Removed => HunkLineKind::Added,
+                    HunkLineKind::Added => HunkLineKind::Removed,
+                },
+                text: line.text.clone(),
+            })
+            .collect();
+        // Keep the removed lines of each change before the added lines
+        for change in lines.split_mut(|line| line.kind == HunkLineKind::Context) {
+            change.sort_by_key(|line| line.kind == HunkLineKind::Added);
+        }
+        Hunk {
+            old_start: self.new_start,
+            new_start: self.old_start,
+            lines,
+        }
+    }
+
+    /// Writes the hunk in the unified diff format.
+    pub fn write_to(&self, output: &mut Vec<u8>) {
+        let context = self.count(HunkLineKind::Context);
+        let old_len = context + self.count(HunkLineKind::Removed);
+        let new_len = context + self.count(HunkLineKind::Added);
+        output.extend_from_slice(
+            format!(
+                "@@ -{},{old_len} +{},{new_len} @@\n",
+                self.old_start, self.new_start
+            )
+            .as_bytes(),
+        );
+        for line in &self.lines {
+            output.push(match line.kind {
+                HunkLineKind::Context => b' ',
+                HunkLineKind::Removed => b'-',
+                HunkLineKind::Added => b'+',
+            });
+            output.extend_from_slice(&line.text);
+            if !line.text.ends_with(b"\n") {
+                output.extend_from_slice(b"\n\\ No newline at end of file\n");
+            }
+        }
+    }
+}
+
+/// The changes to a single file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FilePatch {
+    /// Path of the file before the change, or `None` if the file is added.
+    pub old_path: Option<RepoPathBuf>,
+    /// Path of the file after the change, or `None` if the file is deleted.
+    pub new_path: Option<RepoPathBuf>,
+    pub old_mode: Option<FileMode>,
+    pub new_mode: Option<FileMode>,
+    /// Whether the old file is kept when it's renamed.
+    pub is_copy: bool,
+    /// Whether the change is to a binary file, which can't be applied.
+    pub is_binary: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// Returns the patch undoing this one.
+    pub fn reversed(&self) -> Self {
+        FilePatch {
+            old_path: self.new_path.clone(),
+            new_path: self.old_path.clone(),
+            old_mode: self.new_mode,
+            new_mode: self.old_mode,
+            is_copy: self.is_copy,
+            is_binary: self.is_binary,
+            hunks: self.hunks.iter().map(Hunk::reversed).collect(),
+        }
+    }
+
+    /// Returns the path to show for the patch.
+    pub fn path(&self) -> &RepoPath {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or(RepoPath::root())
+    }
+
+    /// Writes the patch in the unified diff format, with only the given
+    /// `hunks`.
+    pub fn write_to(&self, hunks: &[Hunk], output: &mut Vec<u8>) {
+        let format_path = |prefix: &str, path: &Option<RepoPathBuf>| match path {
+            Some(path) => format!("{prefix}{}", path.as_internal_file_string()),
+            None => "/dev/null".to_owned(),
+        };
+        output.extend_from_slice(
+            format!(
+                "--- {}\n+++ {}\n",
+                format_path("a/", &self.old_path),
+                format_path("b/", &self.new_path)
+            )
+            .as_bytes(),
+        );
+        for hunk in hunks {
+            hunk.write_to(output);
+        }
+    }
+}
+
+/// Parses the file patches in `text`. Text that isn't part of a patch, like a
+/// commit message, is skipped. `strip` leading components are removed from
+/// the paths in the patch, like by `patch -p`.
+pub fn parse_patch(text: &[u8], strip: usize) -> Result<Vec<FilePatch>, PatchParseError> {
+    let lines: Vec<&[u8]> = text.split_inclusive(|b| *b == b'\n').collect();
+    let mut parser = PatchParser {
+        lines,
+        pos: 0,
+        strip,
+    };
+    let mut patches = vec![];
+    while let Some(line) = parser.peek() {
+        if line.starts_with(b"diff --git ") {
+            patches.push(parser.parse_git_file_patch()?);
+        } else if line.starts_with(b"--- ")
+            && parser
+                .lines
+                .get(parser.pos + 1)
+                .is_some_and(|next| next.starts_with(b"+++ "))
+        {
+            let mut patch = FilePatch::default();
+            parser.parse_file_header(&mut patch)?;
+            // There are no renames in plain patches. The names usually differ
+            // by a backup suffix or a directory, so pick the one that's most
+            // likely the file's, as `patch` does.
+            if let (Some(old_path), Some(new_path)) = (&patch.old_path, &patch.new_path) {
+                let path = [old_path, new_path]
+                    .into_iter()
+                    .min_by_key(|path| {
+                        let file_name = path.components().next_back().map(|name| name.as_str());
+                        (
+                            path.components().count(),
+                            file_name.map_or(0, str::len),
+                            path.as_internal_file_string().len(),
+                        )
+                    })
+                    .unwrap()
+                    .clone();
+                patch.old_path = Some(path.clone());
+                patch.new_path = Some(path);
+            }
+            parser.parse_hunks(&mut patch)?;
+            patches.push(patch);
+        } else {
+            parser.pos += 1;
+        }
+    }
+    Ok(patches)
+}
+
+struct PatchParser<'a> {
+    lines: Vec<&'a [u8]>,
+    pos: usize,
+    strip: usize,
+}
+
+impl<'a> PatchParser<'a> {
+    fn peek(&self) -> Option<&'a [u8]> {
+        self.lines.get(self.pos).copied()
+    }
+
+    fn error(&self, message: impl Into<String>) -> PatchParseError {
+        PatchParseError {
+            line: self.pos + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the current line without the line ending, as text.
+    fn current_text(&self) -> Result<&'a str, PatchParseError> {
+        let line = self.peek().unwrap_or_default();
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        str::from_utf8(line).map_err(|_| self.error("invalid UTF-8 in header"))
+    }
+
+    fn parse_path(&self, text: &str, strip: usize) -> Result<RepoPathBuf, PatchParseError> {
+        let path = unquote_path(text).ok_or_else(|| self.error("invalid quoted path"))?;
+        let components: Vec<_> = path
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+        if strip > 0 && components.len() <= strip {
+            return Err(self.error(format!("cannot strip {strip} components from {path}")));
+        }
+        let components = &components[strip..];
+        if components.is_empty() || components.contains(&"..") {
+            return Err(self.error(format!("invalid path: {text}")));
+        }
+        Ok(RepoPathBuf::from_internal_string(components.join("/")))
+    }
+
+    /// Parses the path in a `---` or `+++` line, which may be followed by a
+    /// timestamp.
+    fn parse_header_path(&self, text: &str) -> Result<Option<RepoPathBuf>, PatchParseError> {
+        let text = if text.starts_with('"') {
+            text
+        } else {
+            text.split('\t').next().unwrap()
+        };
+        if text.trim_end() == "/dev/null" {
+            Ok(None)
+        } else {
+            self.parse_path(text.trim_end(), self.strip).map(Some)
+        }
+    }
+
+    fn parse_file_header(&mut self, patch: &mut FilePatch) -> Result<(), PatchParseError> {
+        let old = self.current_text()?.strip_prefix("--- ").unwrap();
+        let old_path = self.parse_header_path(old)?;
+        self.pos += 1;
+        let new = self.current_text()?.strip_prefix("+++ ").unwrap();
+        let new_path = self.parse_header_path(new)?;
+        self.pos += 1;
+        // Renames in Git patches are given by the extended headers
+        if patch.old_path.is_none() || old_path.is_none() {
+            patch.old_path = old_path;
+        }
+        if patch.new_path.is_none() || new_path.is_none() {
+            patch.new_path = new_path;
+        }
+        Ok(())
+    }
+
+    fn parse_git_file_patch(&mut self) -> Result<FilePatch, PatchParseError> {
+        let header = self.current_text()?.strip_prefix("diff --git ").unwrap();
+        let (old_path, new_path) = self.parse_git_header_paths(header)?;
+        let mut patch = FilePatch {
+            old_path: Some(old_path),
+            new_path: Some(new_path),
+            ..Default::default()
+        };
+        self.pos += 1;
+        let parse_mode = |parser: &Self, mode: &str| {
+            FileMode::parse(mode.trim())
+                .ok_or_else(|| parser.error(format!("unsupported file mode {mode}")))
+        };
+        while self.peek().is_some() {
+            let line = self.current_text()?;
+            if let Some(mode) = line.strip_prefix("old mode ") {
+                patch.old_mode = Some(parse_mode(self, mode)?);
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                patch.new_mode = Some(parse_mode(self, mode)?);
+            } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+                patch.old_mode = Some(parse_mode(self, mode)?);
+                patch.new_path = None;
+            } else if let Some(mode) = line.strip_prefix("new file mode ") {
+                patch.new_mode = Some(parse_mode(self, mode)?);
+                patch.old_path = None;
+            } else if let Some(path) = line
+                .strip_prefix("rename from ")
+                .or_else(|| line.strip_prefix("copy from "))
+            {
+                patch.old_path = Some(self.parse_path(path, 0)?);
+                patch.is_copy = line.starts_with("copy");
+            } else if let Some(path) = line
+                .strip_prefix("rename to ")
+                .or_else(|| line.strip_prefix("copy to "))
+            {
+                patch.new_path = Some(self.parse_path(path, 0)?);
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                patch.is_binary = true;
+            } else if line.starts_with("--- ") {
+                self.parse_file_header(&mut patch)?;
+                self.parse_hunks(&mut patch)?;
+                break;
+            } else if !(line.starts_with("index ")
+                || line.starts_with("similarity index ")
+                || line.starts_with("dissimilarity index "))
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(patch)
+    }
+
+    /// Parses the `a/<path> b/<path>` part of a `diff --git` line. The paths
+    /// are only ambiguous if they contain spaces, in which case they're the
+    /// same unless the file is renamed, and the extended headers give them.
+    fn parse_git_header_paths(
+        &self,
+        header: &str,
+    ) -> Result<(RepoPathBuf, RepoPathBuf), PatchParseError> {
+        let (old, new) = if header.starts_with('"') {
+            let end = quoted_len(header).ok_or_else(|| self.error("invalid quoted path"))?;
+            (&header[..end], header[end..].trim_start())
+        } else if let Some((old, new)) = header
+            .len()
+            .checked_sub(1)
+            .map(|len| header.split_at(len / 2))
+            .filter(|(old, new)| {
+                new.starts_with(' ') && old.get(2..) == new.get(3..) && old.get(2..).is_some()
+            })
+        {
+            (old, &new[1..])
+        } else {
+            header
+                .split_once(' ')
+                .ok_or_else(|| self.error("invalid diff header"))?
+        };
+        Ok((
+            self.parse_path(old, self.strip)?,
+            self.parse_path(new, self.strip)?,
+        ))
+    }
+
+    fn parse_hunks(&mut self, patch: &mut FilePatch) -> Result<(), PatchParseError> {
+        while let Some(line) = self.peek() {
+            if !line.starts_with(b"@@ ") {
+                break;
+            }
+            let header = self.current_text()?;
+            let (old_start, mut old_len, new_start, mut new_len) =
+                parse_hunk_header(header).ok_or_else(|| self.error("invalid hunk header"))?;
+            self.pos += 1;
+            let mut lines: Vec<HunkLine> = vec![];
+            while old_len > 0 || new_len > 0 {
+                let Some(line) = self.peek() else {
+                    return Err(self.error("unexpected end of hunk"));
+                };
+                let (kind, text) = match line.first() {
+                    Some(b' ') => (HunkLineKind::Context, &line[1..]),
+                    Some(b'-') => (HunkLineKind::Removed, &line[1..]),
+                    Some(b'+') => (HunkLineKind::Added, &line[1..]),
+                    // Some mail clients strip the space of empty context lines
+                    Some(b'\n' | b'\r') => (HunkLineKind::Context, line),
+                    Some(b'\\') => {
+                        self.strip_last_newline(&mut lines);
+                        self.pos += 1;
+                        continue;
+                    }
+                    _ => return Err(self.error("unexpected end of hunk")),
+                };
+                let (old, new) = match kind {
+                    HunkLineKind::Context => (1, 1),
+                    HunkLineKind::Removed => (1, 0),
+                    HunkLineKind::Added => (0, 1),
+                };
+                if old > old_len || new > new_len {
+                    return Err(self.error("hunk is longer than its header says"));
+                }
+                old_len -= old;
+                new_len -= new;
+                lines.push(HunkLine {
+                    kind,
+                    text: text.to_vec(),
+                });
+                self.pos += 1;
+            }
+            if self.peek().is_some_and(|line| line.starts_with(b"\\")) {
+                self.strip_last_newline(&mut lines);
+                self.pos += 1;
+            }
+            patch.hunks.push(Hunk {
+                old_start,
+                new_start,
+                lines,
+            });
+        }
+        Ok(())
+    }
+
+    /// Handles a `\ No newline at end of file` line.
+    fn strip_last_newline(&self, lines: &mut [HunkLine]) {
+        if let Some(line) = lines.last_mut() {
+            if line.text.ends_with(b"\r\n") {
+                line.text.truncate(line.text.len() - 2);
+            } else if line.text.ends_with(b"\n") {
+                line.text.pop();
+            }
+        }
+    }
+}
+
+/// Parses `@@ -<start>[,<len>] +<start>[,<len>] @@`.
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize, usize)> {
+    let ranges = header.strip_prefix("@@ -")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = parse_range(old)?;
+    let (new_start, new_len) = parse_range(new)?;
+    Some((old_start, old_len, new_start, new_len))
+}
+
+/// Returns the length of the quoted string at the start of `text`.
+fn quoted_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Removes the quotes of a path quoted by Git, which escapes special
+/// characters like in C.
+fn unquote_path(text: &str) -> Option<String> {
+    let Some(quoted) = text.strip_prefix('"') else {
+        return Some(text.to_owned());
+    };
+    let quoted = quoted.strip_suffix('"')?;
+    let mut bytes = vec![];
+    let mut chars = quoted.bytes();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        let escaped = chars.next()?;
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'0'..=b'7' => {
+                let mut value = u32::from(escaped - b'0');
+                for _ in 0..2 {
+                    let digit = chars.next().filter(u8::is_ascii_digit)?;
+                    value = value * 8 + u32::from(digit - b'0');
+                }
+                u8::try_from(value).ok()?
+            }
+            other => other,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Applies the `hunks` to the `content`. Hunks that don't match the content
+/// are returned instead of applied.
+///
+/// Hunks are matched at the line numbers given in the hunk headers, or at the
+/// nearest lines where the context matches. With a nonzero `fuzz`, up to that
+/// many lines of context at the start and at the end of a hunk are ignored if
+/// the hunk doesn't match otherwise.
+pub fn apply_hunks<'a>(content: &[u8], hunks: &'a [Hunk], fuzz: usize) -> (Vec<u8>, Vec<&'a Hunk>) {
+    let lines: Vec<&[u8]> = content.split_inclusive(|b| *b == b'\n').collect();
+    let mut output = vec![];
+    let mut rejected = vec![];
+    // Lines before `pos` are already written to the output
+    let mut pos = 0;
+    // Difference between the line numbers in the hunk headers and the lines
+    // where the previous hunk was applied
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let old_lines = hunk.old_lines();
+        let new_lines = hunk.new_lines();
+        let (leading_context, trailing_context) = hunk.context_len();
+        let found = (0..=fuzz).find_map(|fuzz| {
+            let leading = fuzz.min(leading_context);
+            let trailing = fuzz.min(trailing_context);
+            if leading + trailing > old_lines.len() {
+                return None;
+            }
+            let old = &old_lines[leading..old_lines.len() - trailing];
+            // A hunk without old lines inserts after the line in its header
+            let expected = if old_lines.is_empty() {
+                hunk.old_start
+            } else {
+                hunk.old_start.saturating_sub(1) + leading
+            };
+            let expected = (expected as isize + offset).clamp(0, lines.len() as isize) as usize;
+            let start = find_lines(&lines, old, pos, expected)?;
+            Some((start, leading, trailing, expected))
+        });
+        let Some((start, leading, trailing, expected)) = found else {
+            rejected.push(hunk);
+            continue;
+        };
+        let old_len = old_lines.len() - leading - trailing;
+        output.extend(lines[pos..start].iter().copied().flatten());
+        output.extend(
+            new_lines[leading..new_lines.len() - trailing]
+                .iter()
+                .copied()
+                .flatten(),
+        );
+        pos = start + old_len;
+        offset += start as isize - expected as isize;
+    }
+    output.extend(lines[pos..].iter().copied().flatten());
+    (output, rejected)
+}
+
+/// Finds the `needle` lines in `lines[min_pos..]`, starting at `expected` and
+/// searching outwards.
+fn find_lines(lines: &[&[u8]], needle: &[&[u8]], min_pos: usize, expected: usize) -> Option<usize> {
+    let max_pos = lines.len().checked_sub(needle.len())?;
+    if min_pos > max_pos {
+        return None;
+    }
+    let expected = expected.clamp(min_pos, max_pos);
+    let matches_at = |pos: usize| lines[pos..pos + needle.len()] == *needle;
+    for distance in 0..=(max_pos - min_pos) {
+        if let Some(pos) = expected.checked_add(distance).filter(|pos| *pos <= max_pos) {
+            if matches_at(pos) {
+                return Some(pos);
+            }
+        }
+        if let Some(pos) = expected
+            .checked_sub(distance)
+            .filter(|pos| distance > 0 && *pos >= min_pos)
+        {
+            if matches_at(pos) {
+                return Some(pos);
+            }
+        }
+    }
+    None
+}
+
+/// Changes to a file that couldn't be applied.
+#[derive(Clone, Debug)]
+pub struct RejectedPatch {
+    pub patch: FilePatch,
+    /// Why the patch was rejected.
+    pub reason: String,
+    /// The hunks that weren't applied.
+    pub hunks: Vec<Hunk>,
+}
+
+/// Result of applying a patch to a tree.
+#[derive(Clone, Debug)]
+pub struct AppliedPatch {
+    pub tree_id: MergedTreeId,
+    pub rejected: Vec<RejectedPatch>,
+}
+
+/// Applies the file `patches` to the `tree`. Changes to files that can't be
+/// applied are returned instead of applied. The hunks of a file that do apply
+/// are applied even if others don't.
+pub fn apply_patches(
+    store: &Arc<Store>,
+    tree: &MergedTree,
+    patches: &[FilePatch],
+    fuzz: usize,
+) -> BackendResult<AppliedPatch> {
+    // New contents of the changed files, or `None` if they're deleted
+    let mut files: BTreeMap<RepoPathBuf, Option<(Vec<u8>, FileMode)>> = BTreeMap::new();
+    let mut rejected = vec![];
+    for patch in patches {
+        let reject = |reason: &str| RejectedPatch {
+            patch: patch.clone(),
+            reason: reason.to_owned(),
+            hunks: patch.hunks.clone(),
+        };
+        if patch.is_binary {
+            rejected.push(reject("binary files can't be patched"));
+            continue;
+        }
+        let old_file = match &patch.old_path {
+            Some(path) => match files.get(path) {
+                Some(file) => file.clone().map(Ok),
+                None => read_file(store, tree, path)?,
+            },
+            None => None,
+        };
+        let old_file = match (&patch.old_path, old_file) {
+            (Some(_), Some(Ok(file))) => Some(file),
+            (Some(_), Some(Err(reason))) => {
+                rejected.push(reject(reason));
+                continue;
+            }
+            (Some(_), None) => {
+                rejected.push(reject("file doesn't exist"));
+                continue;
+            }
+            (None, _) => None,
+        };
+        if let Some(new_path) = &patch.new_path {
+            if patch.old_path.as_ref() != Some(new_path) {
+                let exists = match files.get(new_path) {
+                    Some(file) => file.is_some(),
+                    None => tree.path_value(new_path).is_present(),
+                };
+                if exists {
+                    rejected.push(reject("file already exists"));
+                    continue;
+                }
+            }
+        }
+
+        let (old_content, old_mode) = old_file.unwrap_or((vec![], FileMode::Normal));
+        let (new_content, rejected_hunks) = apply_hunks(&old_content, &patch.hunks, fuzz);
+        if !rejected_hunks.is_empty() {
+            rejected.push(RejectedPatch {
+                patch: patch.clone(),
+                reason: format!(
+                    "{} of {} hunks don't apply",
+                    rejected_hunks.len(),
+                    patch.hunks.len()
+                ),
+                hunks: rejected_hunks.into_iter().cloned().collect(),
+            });
+        }
+        if let Some(old_path) = &patch.old_path {
+            if !patch.is_copy && patch.new_path.as_ref() != Some(old_path) {
+                files.insert(old_path.clone(), None);
+            }
+        }
+        if let Some(new_path) = &patch.new_path {
+            let mode = patch.new_mode.unwrap_or(old_mode);
+            files.insert(new_path.clone(), Some((new_content, mode)));
+        } else if !new_content.is_empty() {
+            // The file is only deleted if its whole content is removed
+            let old_path = patch.old_path.clone().unwrap();
+            files.insert(old_path, Some((new_content, old_mode)));
+        }
+    }
+
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    for (path, file) in files {
+        let value = match file {
+            None => Merge::absent(),
+            Some((content, FileMode::Symlink)) => {
+                let target = String::from_utf8(content).map_err(|err| {
+                    BackendError::Other(format!("Invalid symlink target: {err}").into())
+                })?;
+                let id = store.write_symlink(&path, &target)?;
+                Merge::normal(TreeValue::Symlink(id))
+            }
+            Some((content, mode)) => {
+                let id = store.write_file(&path, &mut content.as_slice())?;
+                Merge::normal(TreeValue::File {
+                    id,
+                    executable: mode == FileMode::Executable,
+                })
+            }
+        };
+        tree_builder.set_or_remove(path, value);
+    }
+    Ok(AppliedPatch {
+        tree_id: tree_builder.write_tree(store)?,
+        rejected,
+    })
+}
+
+/// Lines to select in a file, given as `PATH:LINES`, where `LINES` is a
+/// comma-separated list of line numbers and `START-END` ranges. The path is
+/// a `String` until it's resolved to a `RepoPathBuf`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineSelection<P = String> {
+    pub path: P,
+    /// 1-based line numbers.
+    pub ranges: Vec<RangeInclusive<usize>>,
+}
+
+impl FromStr for LineSelection {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!(r#"expected PATH:LINES, like "src/main.rs:10-25", got "{text}""#);
+        let (path, lines) = text.rsplit_once(':').ok_or_else(invalid)?;
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        let parse_line = |line: &str| match line.trim().parse() {
+            Ok(0) | Err(_) => Err(invalid()),
+            Ok(line) => Ok(line),
+        };
+        let ranges = lines
+            .split(',')
+            .map(|range| {
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (parse_line(start)?, parse_line(end)?),
+                    None => (parse_line(range)?, parse_line(range)?),
+                };
+                if start > end {
+                    return Err(invalid());
+                }
+                Ok(start..=end)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(LineSelection {
+            path: path.to_owned(),
+            ranges,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SelectLinesError {
+    #[error("Can't select lines in {path}: {reason}")]
+    Unsupported { path: String, reason: &'static str },
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Returns the `left_tree` with the changes to the selected lines from the
+/// `right_tree`. The lines are numbered as in the `right_tree`. A changed hunk
+/// is selected if it touches any of the lines, where deleted lines touch the
+/// lines around them. Several selections of the same file add up.
+pub fn select_lines(
+    store: &Arc<Store>,
+    left_tree: &MergedTree,
+    right_tree: &MergedTree,
+    selections: &[LineSelection<RepoPathBuf>],
+) -> Result<MergedTreeId, SelectLinesError> {
+    let mut ranges_by_path: BTreeMap<&RepoPath, Vec<RangeInclusive<usize>>> = BTreeMap::new();
+    for LineSelection { path, ranges } in selections {
+        ranges_by_path
+            .entry(path)
+            .or_default()
+            .extend(ranges.iter().cloned());
+    }
+    let mut tree_builder = MergedTreeBuilder::new(left_tree.id());
+    for (path, ranges) in ranges_by_path {
+        let unsupported = |reason| SelectLinesError::Unsupported {
+            path: path.as_internal_file_string().to_owned(),
+            reason,
+        };
+        let (right_content, right_mode) = match read_file(store, right_tree, path)? {
+            Some(Ok(file)) => file,
+            Some(Err(reason)) => return Err(unsupported(reason)),
+            None => return Err(unsupported("file doesn't exist")),
+        };
+        let (left_content, left_mode) = match read_file(store, left_tree, path)? {
+            Some(Ok((content, mode))) => (content, Some(mode)),
+            Some(Err(reason)) => return Err(unsupported(reason)),
+            None => (vec![], None),
+        };
+        if right_mode == FileMode::Symlink || left_mode == Some(FileMode::Symlink) {
+            return Err(unsupported("symlinks don't have lines"));
+        }
+        let content = select_changed_lines(&left_content, &right_content, &ranges);
+        if content == left_content && (left_mode.is_some() || content.is_empty()) {
+            continue;
+        }
+        let id = store.write_file(path, &mut content.as_slice())?;
+        let value = TreeValue::File {
+            id,
+            executable: left_mode.unwrap_or(right_mode) == FileMode::Executable,
+        };
+        tree_builder.set_or_remove(path.to_owned(), Merge::normal(value));
+    }
+    Ok(tree_builder.write_tree(store)?)
+}
+
+/// Returns the `left` content with the hunks from `right` that touch the
+/// `ranges` of lines in `right`.
+fn select_changed_lines(left: &[u8], right: &[u8], ranges: &[RangeInclusive<usize>]) -> Vec<u8> {
+    let count_lines = |text: &[u8]| text.split_inclusive(|b| *b == b'\n').count();
+    let is_selected = |first: usize, last: usize| {
+        ranges
+            .iter()
+            .any(|range| *range.start() <= last && first <= *range.end())
+    };
+    let diff = Diff::for_tokenizer(&[left, right], &find_line_ranges);
+    let mut content = vec![];
+    // The number of the next line in `right`
+    let mut line = 1;
+    for hunk in diff.hunks() {
+        match hunk {
+            DiffHunk::Matching(text) => {
+                content.extend_from_slice(text);
+                line += count_lines(text);
+            }
+            DiffHunk::Different(texts) => {
+                let num_lines = count_lines(texts[1]);
+                let selected = if num_lines == 0 {
+                    is_selected(line - 1, line)
+                } else {
+                    is_selected(line, line + num_lines - 1)
+                };
+                content.extend_from_slice(if selected { texts[1] } else { texts[0] });
+                line += num_lines;
+            }
+        }
+    }
+    content
+}
+
+type FileContent = Result<(Vec<u8>, FileMode), &'static str>;
+
+/// Reads the file at `path` in the `tree`. Returns `None` if there's no file,
+/// or the reason why the file can't be patched.
+fn read_file(
+    store: &Arc<Store>,
+    tree: &MergedTree,
+    path: &RepoPath,
+) -> BackendResult<Option<FileContent>> {
+    let value = tree.path_value(path);
+    let Some(value) = value.as_resolved() else {
+        return Ok(Some(Err("file has conflicts")));
+    };
+    let read_error = |err: io::Error| BackendError::ReadObject {
+        object_type: "file".to_owned(),
+        hash: path.as_internal_file_string().to_owned(),
+        source: err.into(),
+    };
+    match value {
+        None => Ok(None),
+        Some(TreeValue::File { id, executable }) => {
+            let mut content = vec![];
+            store
+                .read_file(path, id)?
+                .read_to_end(&mut content)
+                .map_err(read_error)?;
+            let mode = if *executable {
+                FileMode::Executable
+            } else {
+                FileMode::Normal
+            };
+            Ok(Some(Ok((content, mode))))
+        }
+        Some(TreeValue::Symlink(id)) => {
+            let target = store.read_symlink(path, id)?;
+            Ok(Some(Ok((target.into_bytes(), FileMode::Symlink))))
+        }
+        Some(_) => Ok(Some(Err("not a file"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_path(value: &str) -> RepoPathBuf {
+        RepoPathBuf::from_internal_string(value)
+    }
+
+    fn apply(content: &str, patch: &str, fuzz: usize) -> (String, usize) {
+        let patches = parse_patch(patch.as_bytes(), 1).unwrap();
+        assert_eq!(patches.len(), 1);
+        let (output, rejected) = apply_hunks(content.as_bytes(), &patches[0].hunks, fuzz);
+        (String::from_utf8(output).unwrap(), rejected.len())
+    }
+
+    #[test]
+    fn test_parse_line_selection() {
+        assert_eq!(
+            "dir/file:3".parse(),
+            Ok(LineSelection {
+                path: "dir/file".to_owned(),
+                ranges: vec![3..=3],
+            })
+        );
+        assert_eq!(
+            "a:b:10-25,30".parse(),
+            Ok(LineSelection {
+                path: "a:b".to_owned(),
+                ranges: vec![10..=25, 30..=30],
+            })
+        );
+        assert!("file".parse::<LineSelection>().is_err());
+        assert!(":1".parse::<LineSelection>().is_err());
+        assert!("file:0".parse::<LineSelection>().is_err());
+        assert!("file:5-3".parse::<LineSelection>().is_err());
+        assert!("file:1-".parse::<LineSelection>().is_err());
+    }
+
+    #[test]
+    fn test_select_changed_lines() {
+        let select = |left: &str, right: &str, ranges: &[RangeInclusive<usize>]| {
+            String::from_utf8(select_changed_lines(
+                left.as_bytes(),
+                right.as_bytes(),
+                ranges,
+            ))
+            .unwrap()
+        };
+        let left = "a\nb\nc\nd\ne\n";
+        let right = "a\nB\nc\nd\nE\nF\n";
+        assert_eq!(select(left, right, &[]), left);
+        assert_eq!(select(left, right, &[2..=2]), "a\nB\nc\nd\ne\n");
+        assert_eq!(select(left, right, &[3..=5]), "a\nb\nc\nd\nE\nF\n");
+        assert_eq!(select(left, right, &[1..=10]), right);
+        // Deleted lines are selected by the lines around them
+        assert_eq!(select(left, "a\nb\nd\ne\n", &[1..=1]), left);
+        assert_eq!(select(left, "a\nb\nd\ne\n", &[2..=2]), "a\nb\nd\ne\n");
+        assert_eq!(select(left, "a\nb\nd\ne\n", &[3..=3]), "a\nb\nd\ne\n");
+        // Lines without a newline at the end
+        assert_eq!(select("a\nb", "a\nc", &[2..=2]), "a\nc");
+        assert_eq!(select("", "a\nb\n", &[1..=1]), "a\nb\n");
+    }
+
+    #[test]
+    fn test_parse_git_patch() {
+        let patches = parse_patch(
+            b"Commit message\n\
+              ---\n\
+              diff --git a/file b/file\n\
+              index 1234567..89abcde 100644\n\
+              --- a/file\n\
+              +++ b/file\n\
+              @@ -1,2 +1,2 @@\n\
+              \x20a\n\
+              -b\n\
+              +c\n\
+              \\ No newline at end of file\n\
+              diff --git a/dir/old name b/dir/new name\n\
+              similarity index 100%\n\
+              rename from dir/old name\n\
+              rename to dir/new name\n\
+              diff --git a/script b/script\n\
+              old mode 100644\n\
+              new mode 100755\n\
+              diff --git a/added b/added\n\
+              new file mode 100644\n\
+              index 0000000..7898192\n\
+              --- /dev/null\n\
+              +++ b/added\n\
+              @@ -0,0 +1 @@\n\
+              +a\n\
+              diff --git a/image b/image\n\
+              deleted file mode 100644\n\
+              Binary files a/image and /dev/null differ\n\
+              -- \n\
+              2.43.0\n",
+            1,
+        )
+        .unwrap();
+        assert_eq!(patches.len(), 5);
+        assert_eq!(
+            patches[0],
+            FilePatch {
+                old_path: Some(repo_path("file")),
+                new_path: Some(repo_path("file")),
+                hunks: vec![Hunk {
+                    old_start: 1,
+                    new_start: 1,
+                    lines: vec![
+                        HunkLine {
+                            kind: HunkLineKind::Context,
+                            text: b"a\n".to_vec()
+                        },
+                        HunkLine {
+                            kind: HunkLineKind::Removed,
+                            text: b"b\n".to_vec()
+                        },
+                        HunkLine {
+                            kind: HunkLineKind::Added,
+                            text: b"c".to_vec()
+                        },
+                    ],
+                }],
+                ..Default::default()
+            }
+        );
+        assert_eq!(patches[1].old_path, Some(repo_path("dir/old name")));
+        assert_eq!(patches[1].new_path, Some(repo_path("dir/new name")));
+        assert!(patches[1].hunks.is_empty());
+        assert_eq!(patches[2].old_mode, Some(FileMode::Normal));
+        assert_eq!(patches[2].new_mode, Some(FileMode::Executable));
+        assert_eq!(patches[3].old_path, None);
+        assert_eq!(patches[3].new_path, Some(repo_path("added")));
+        assert_eq!(patches[3].hunks[0].lines.len(), 1);
+        assert_eq!(patches[4].new_path, None);
+        assert!(patches[4].is_binary);
+    }
+
+    #[test]
+    fn test_parse_plain_patch() {
+        let patches = parse_patch(
+            b"--- old/dir/file\t2024-01-01 00:00:00\n\
+              +++ new/dir/file\t2024-01-01 00:00:00\n\
+              @@ -1 +1 @@\n\
+              -a\n\
+              +b\n",
+            1,
+        )
+        .unwrap();
+        assert_eq!(patches[0].old_path, Some(repo_path("dir/file")));
+        assert_eq!(patches[0].new_path, Some(repo_path("dir/file")));
+
+        let patches = parse_patch(b"--- dir/file.orig\n+++ dir/file\n", 0).unwrap();
+        assert_eq!(patches[0].old_path, Some(repo_path("dir/file")));
+        assert_eq!(patches[0].new_path, Some(repo_path("dir/file")));
+
+        let patches = parse_patch(b"--- \"a/tab\\there\"\n+++ \"b/tab\\there\"\n", 1).unwrap();
+        assert_eq!(patches[0].new_path, Some(repo_path("tab\there")));
+
+        let err = parse_patch(b"--- a/file\n+++ b/file\n@@ -1,2 +1,2 @@\n a\n", 1).unwrap_err();
+        assert_eq!(err.to_string(), "line 5: unexpected end of hunk");
+        let err = parse_patch(b"--- a/file\n+++ b/file\n", 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: cannot strip 2 components from a/file"
+        );
+    }
+
+    #[test]
+    fn test_apply_hunks() {
+        let content = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let patch = "--- a/f\n+++ b/f\n@@ -3,3 +3,3 @@\n 3\n-4\n+four\n 5\n";
+        assert_eq!(
+            apply(content, patch, 0),
+            ("1\n2\n3\nfour\n5\n6\n7\n8\n9\n".to_owned(), 0)
+        );
+        // The hunk is found at a different line
+        assert_eq!(
+            apply(&format!("0\n0\n{content}"), patch, 0),
+            ("0\n0\n1\n2\n3\nfour\n5\n6\n7\n8\n9\n".to_owned(), 0)
+        );
+        // The context doesn't match
+        let mismatch = "1\n2\nx\n4\n5\n";
+        assert_eq!(apply(mismatch, patch, 0), (mismatch.to_owned(), 1));
+        assert_eq!(
+            apply(mismatch, patch, 1),
+            ("1\n2\nx\nfour\n5\n".to_owned(), 0)
+        );
+        // The removed line doesn't match
+        assert_eq!(apply("3\nx\n5\n", patch, 1), ("3\nx\n5\n".to_owned(), 1));
+
+        // Insertion into an empty file
+        let patch = "--- /dev/null\n+++ b/f\n@@ -0,0 +1,2 @@\n+a\n+b\n";
+        assert_eq!(apply("", patch, 0), ("a\nb\n".to_owned(), 0));
+        // Missing newline at the end of the file
+        let patch = "--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+b\n";
+        assert_eq!(apply("a", patch, 0), ("b\n".to_owned(), 0));
+        assert_eq!(apply("a\n", patch, 0), ("a\n".to_owned(), 1));
+    }
+
+    #[test]
+    fn test_reversed() {
+        let patch = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n";
+        let patches = parse_patch(patch.as_bytes(), 1).unwrap();
+        let reversed = patches[0].reversed();
+        let (output, rejected) = apply_hunks(b"a\nc\n", &reversed.hunks, 0);
+        assert_eq!(output, b"a\nb\n");
+        assert!(rejected.is_empty());
+        let mut text = vec![];
+        reversed.write_to(&reversed.hunks, &mut text);
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-c\n+b\n"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External subcommands, which are executables called `jj-<name>` on `PATH`.
+//!
+//! `jj <name>` runs `jj-<name>` if `<name>` isn't a built-in command. A plugin
+//! can describe itself in a `jj-<name>.toml` file next to the executable, so
+//! that it's listed in `jj --help`:
+//!
+//! ```toml
+//! about = "Does something useful"
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ArgMatches;
+use jj_lib::object_id::ObjectId as _;
+
+use crate::cli_util::{user_error, user_error_with_message, CommandError, CommandHelper};
+use crate::ui::Ui;
+
+/// The prefix of the executables that provide external subcommands.
+const PLUGIN_PREFIX: &str = "jj-";
+
+/// The id of the argument that collects the arguments of a plugin.
+const PLUGIN_ARGS_ID: &str = "plugin-args";
+
+/// An external subcommand found on `PATH`.
+#[derive(Clone, Debug)]
+pub struct Plugin {
+    /// The name of the subcommand, without the `jj-` prefix.
+    pub name: String,
+    pub path: PathBuf,
+    /// A short description from the plugin's manifest, if any.
+    pub about: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct PluginManifest {
+    about: Option<String>,
+}
+
+/// Returns the plugins on `PATH` by name. If several directories contain a
+/// plugin with the same name, the first one wins, like the shell would pick.
+pub fn list_plugins() -> BTreeMap<String, Plugin> {
+    let mut plugins = BTreeMap::new();
+    let Some(paths) = env::var_os("PATH") else {
+        return plugins;
+    };
+    for dir in env::split_paths(&paths) {
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = plugin_name(&path) else {
+                continue;
+            };
+            if plugins.contains_key(name) || !is_executable(&path) {
+                continue;
+            }
+            let about = read_manifest(&path).about;
+            plugins.insert(
+                name.to_owned(),
+                Plugin {
+                    name: name.to_owned(),
+                    path,
+                    about,
+                },
+            );
+        }
+    }
+    plugins
+}
+
+fn plugin_name(path: &Path) -> Option<&str> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name
+        .strip_prefix(PLUGIN_PREFIX)?
+        .strip_suffix(env::consts::EXE_SUFFIX)?;
+    (!name.is_empty() && !name.contains('.')).then_some(name)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt as _;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn read_manifest(plugin_path: &Path) -> PluginManifest {
+    let manifest_path = plugin_path.with_extension("toml");
+    let Ok(text) = std::fs::read_to_string(&manifest_path) else {
+        return PluginManifest::default();
+    };
+    toml_edit::de::from_str(&text).unwrap_or_else(|err| {
+        tracing::warn!(?manifest_path, %err, "ignoring invalid plugin manifest");
+        PluginManifest::default()
+    })
+}
+
+/// Adds a subcommand to `app` for each plugin that doesn't have the same name
+/// as an existing subcommand.
+pub fn add_plugin_subcommands(mut app: clap::Command) -> clap::Command {
+    for plugin in list_plugins().into_values() {
+        if app.find_subcommand(&plugin.name).is_some() {
+            continue;
+        }
+        let about = plugin
+            .about
+            .unwrap_or_else(|| format!("External subcommand ({})", plugin.path.display()));
+        let subcommand = clap::Command::new(plugin.name)
+            .about(about)
+            .disable_help_flag(true)
+            .arg(
+                clap::Arg::new(PLUGIN_ARGS_ID)
+                    .num_args(0..)
+                    .trailing_var_arg(true)
+                    .allow_hyphen_values(true),
+            );
+        app = app.subcommand(subcommand);
+    }
+    app
+}
+
+/// Returns whether the help requested by `string_args` lists the subcommands
+/// of `app`, and so the plugins. The help of a built-in subcommand doesn't, so
+/// `PATH` isn't scanned for it.
+pub fn help_lists_plugins(app: &clap::Command, string_args: &[String]) -> bool {
+    // ignore_errors() bypasses errors like the unknown help flag
+    let Ok(matches) = app
+        .clone()
+        .disable_help_flag(true)
+        .disable_help_subcommand(true)
+        .allow_external_subcommands(true)
+        .ignore_errors(true)
+        .try_get_matches_from(string_args)
+    else {
+        return true;
+    };
+    let name = match matches.subcommand() {
+        None => return true,
+        // `jj help <command>`
+        Some(("help", sub_matches)) => {
+            let Some(name) = sub_matches
+                .get_many::<OsString>("")
+                .and_then(|mut args| args.next())
+            else {
+                return true;
+            };
+            name.to_string_lossy()
+        }
+        Some((name, _)) => name.into(),
+    };
+    app.find_subcommand(name.as_ref()).is_none()
+}
+
+/// Returns the name and arguments of the plugin if `matches` is for one.
+pub fn plugin_invocation(matches: &ArgMatches) -> Option<(&str, Vec<&str>)> {
+    let (name, sub_matches) = matches.subcommand()?;
+    let args = sub_matches.try_get_many::<String>(PLUGIN_ARGS_ID).ok()?;
+    Some((
+        name,
+        args.into_iter().flatten().map(|s| s.as_str()).collect(),
+    ))
+}
+
+/// Runs the plugin `name` with `args`.
+///
+/// If the command runs in a workspace, the plugin gets its root in
+/// `JJ_WORKSPACE_ROOT` and the id of the operation it was loaded at in
+/// `JJ_OPERATION_ID`. The working copy is snapshotted first so that the
+/// plugin sees its current content.
+pub fn run_plugin(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    name: &str,
+    args: &[&str],
+) -> Result<(), CommandError> {
+    let Some(plugin) = list_plugins().remove(name) else {
+        return Err(user_error(format!(
+            "External subcommand `{name}` is no longer on PATH"
+        )));
+    };
+    let mut cmd = Command::new(&plugin.path);
+    cmd.args(args.iter().map(OsStr::new));
+    if command.workspace_loader().is_ok() {
+        let workspace_command = command.workspace_helper(ui)?;
+        cmd.env("JJ_WORKSPACE_ROOT", workspace_command.workspace_root())
+            .env("JJ_OPERATION_ID", workspace_command.repo().op_id().hex());
+    }
+    tracing::info!(?cmd, "running external subcommand");
+    let status = cmd.status().map_err(|err| {
+        user_error_with_message(format!("Failed to run external subcommand `{name}`"), err)
+    })?;
+    if status.success() {
+        Ok(())
+    } else {
+        // The plugin reported its own error, so only forward the exit code.
+        let code = status.code().map_or(1, |code| code.clamp(1, 255) as u8);
+        Err(CommandError::ExitCode(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn help_lists_plugins_for(args: &[&str]) -> bool {
+        let app = clap::Command::new("jj")
+            .arg(clap::Arg::new("repository").short('R').long("repository"))
+            .subcommand(clap::Command::new("log").alias("l"))
+            .subcommand(clap::Command::new("branch").subcommand(clap::Command::new("list")));
+        let string_args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        help_lists_plugins(&app, &string_args)
+    }
+
+    #[test]
+    fn test_help_lists_plugins() {
+        assert!(help_lists_plugins_for(&["jj", "--help"]));
+        assert!(help_lists_plugins_for(&["jj", "-R", "repo", "-h"]));
+        assert!(help_lists_plugins_for(&["jj", "help"]));
+        assert!(help_lists_plugins_for(&["jj", "plugin", "--help"]));
+        assert!(help_lists_plugins_for(&["jj", "help", "plugin"]));
+
+        assert!(!help_lists_plugins_for(&["jj", "log", "--help"]));
+        assert!(!help_lists_plugins_for(&["jj", "-R", "repo", "l", "-h"]));
+        assert!(!help_lists_plugins_for(&["jj", "help", "log"]));
+        assert!(!help_lists_plugins_for(&["jj", "branch"]));
+        assert!(!help_lists_plugins_for(&["jj", "branch", "list", "--help"]));
+    }
+}
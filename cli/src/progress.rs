@@ -6,7 +6,7 @@ use std::time::{Duration, Instant};
 use crossterm::terminal::{Clear, ClearType};
 use jj_lib::fmt_util::binary_prefix;
 use jj_lib::git;
-use jj_lib::repo_path::RepoPath;
+use jj_lib::working_copy::WorkingCopyProgress;
 
 use crate::cleanup_guard::CleanupGuard;
 use crate::text_util;
@@ -44,7 +44,7 @@ impl Progress {
         }
 
         let rate = progress
-            .bytes_downloaded
+            .bytes_transferred
             .and_then(|x| self.rate.update(now, x));
         if now < self.next_print {
             return Ok(());
@@ -63,7 +63,7 @@ impl Progress {
         write!(self.buffer, "\r{}", Clear(ClearType::CurrentLine)).unwrap();
         let control_chars = self.buffer.len();
         write!(self.buffer, "{: >3.0}% ", 100.0 * progress.overall).unwrap();
-        if let Some(total) = progress.bytes_downloaded {
+        if let Some(total) = progress.bytes_transferred {
             let (scaled, prefix) = binary_prefix(total as f32);
             write!(self.buffer, "{scaled: >5.1} {prefix}B ").unwrap();
         }
@@ -158,7 +158,18 @@ impl RateEstimateState {
     }
 }
 
-pub fn snapshot_progress(ui: &Ui) -> Option<impl Fn(&RepoPath) + '_> {
+pub fn snapshot_progress(ui: &Ui) -> Option<impl Fn(WorkingCopyProgress<'_>) + Sync + '_> {
+    working_copy_progress(ui, "Snapshotting")
+}
+
+pub fn checkout_progress(ui: &Ui) -> Option<impl Fn(WorkingCopyProgress<'_>) + Sync + '_> {
+    working_copy_progress(ui, "Updating")
+}
+
+fn working_copy_progress<'a>(
+    ui: &'a Ui,
+    action: &'static str,
+) -> Option<impl Fn(WorkingCopyProgress<'_>) + Sync + 'a> {
     struct State {
         guard: Option<OutputGuard>,
         output: ProgressOutput,
@@ -175,7 +186,7 @@ pub fn snapshot_progress(ui: &Ui) -> Option<impl Fn(&RepoPath) + '_> {
         next_display_time,
     });
 
-    Some(move |path: &RepoPath| {
+    Some(move |progress: WorkingCopyProgress<'_>| {
         let mut state = state.lock().unwrap();
         let now = Instant::now();
         if now < state.next_display_time {
@@ -193,23 +204,37 @@ pub fn snapshot_progress(ui: &Ui) -> Option<impl Fn(&RepoPath) + '_> {
             );
         }
 
+        let prefix = format!("{action} {} ", format_count(&progress));
         let line_width = state.output.term_width().map(usize::from).unwrap_or(80);
-        let max_path_width = line_width.saturating_sub(13); // Account for "Snapshotting "
-        let fs_path = path.to_fs_path(Path::new(""));
+        let max_path_width = line_width.saturating_sub(prefix.len());
+        let fs_path = progress.path.to_fs_path(Path::new(""));
         let (display_path, _) =
             text_util::elide_start(fs_path.to_str().unwrap(), "...", max_path_width);
 
         _ = write!(
             state.output,
-            "\r{}Snapshotting {display_path}",
+            "\r{}{prefix}{display_path}",
             Clear(ClearType::CurrentLine),
         );
         _ = state.output.flush();
     })
 }
 
+/// Formats the number of processed files, and the total if it hasn't been
+/// exceeded.
+fn format_count(progress: &WorkingCopyProgress) -> String {
+    match progress.total {
+        Some(total) if progress.processed <= total => {
+            format!("[{}/{total}]", progress.processed)
+        }
+        _ => format!("[{}]", progress.processed),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use jj_lib::repo_path::RepoPath;
+
     use super::*;
 
     #[test]
@@ -228,4 +253,19 @@ mod tests {
         assert_eq!(buf, "█████▍    ");
         buf.clear();
     }
+
+    #[test]
+    fn test_format_count() {
+        let path = RepoPath::from_internal_string("file");
+        let progress = |processed, total| WorkingCopyProgress {
+            path,
+            processed,
+            total,
+        };
+        assert_eq!(format_count(&progress(3, Some(10))), "[3/10]");
+        assert_eq!(format_count(&progress(10, Some(10))), "[10/10]");
+        // New files may exceed the estimate
+        assert_eq!(format_count(&progress(11, Some(10))), "[11]");
+        assert_eq!(format_count(&progress(3, None)), "[3]");
+    }
 }
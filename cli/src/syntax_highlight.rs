@@ -0,0 +1,290 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Syntax highlighting of file contents shown in diffs.
+//!
+//! Highlighted text is written with a label per syntax category (e.g.
+//! `keyword`), so the colors can be configured like any other label.
+
+use std::ops::Range;
+use std::str;
+
+use crossterm::style::Color;
+use itertools::Itertools as _;
+use jj_lib::repo_path::RepoPath;
+use syntect::highlighting::{Highlighter, ThemeSet};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+/// Scopes to highlight and the labels they're written with. Scopes are
+/// listed before any scope they're nested in, and the first scope of each
+/// label is the one used to look up the label's color in a theme.
+const SCOPE_LABELS: &[(&str, &str)] = &[
+    ("comment", "comment"),
+    ("string", "string"),
+    ("constant", "constant"),
+    ("keyword", "keyword"),
+    ("storage", "keyword"),
+    ("entity.name.function", "function"),
+    ("support.function", "function"),
+    ("variable.function", "function"),
+    ("entity.name.type", "type"),
+    ("support.type", "type"),
+    ("support.class", "type"),
+    ("entity.name", "type"),
+];
+
+fn scope_labels() -> Vec<(Scope, &'static str)> {
+    SCOPE_LABELS
+        .iter()
+        .map(|&(scope, label)| (Scope::new(scope).unwrap(), label))
+        .collect()
+}
+
+pub struct SyntaxHighlighter {
+    syntax_set: SyntaxSet,
+    scope_labels: Vec<(Scope, &'static str)>,
+}
+
+impl SyntaxHighlighter {
+    pub fn new() -> Self {
+        SyntaxHighlighter {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            scope_labels: scope_labels(),
+        }
+    }
+
+    fn find_syntax(&self, path: &RepoPath, content: &[u8]) -> Option<&SyntaxReference> {
+        let (_, file_name) = path.split()?;
+        let file_name = file_name.as_str();
+        file_name
+            .rsplit_once('.')
+            .and_then(|(_, extension)| self.syntax_set.find_syntax_by_extension(extension))
+            // Some syntaxes list full file names like "Makefile" as extensions
+            .or_else(|| self.syntax_set.find_syntax_by_extension(file_name))
+            .or_else(|| {
+                let first_line = content.split(|b| *b == b'\n').next()?;
+                let first_line = str::from_utf8(first_line).ok()?;
+                self.syntax_set.find_syntax_by_first_line(first_line)
+            })
+    }
+
+    fn label_for(&self, scopes: &[Scope]) -> Option<&'static str> {
+        scopes.iter().rev().find_map(|scope| {
+            self.scope_labels
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .map(|(_, label)| *label)
+        })
+    }
+
+    /// Parses the `content` of the file at `path`. Returns `None` if the
+    /// language of the file isn't known, or if the content isn't valid UTF-8.
+    pub fn highlight(&self, path: &RepoPath, content: &[u8]) -> Option<SyntaxSpans> {
+        let syntax = self.find_syntax(path, content)?;
+        let mut parse_state = ParseState::new(syntax);
+        let mut scope_stack = ScopeStack::new();
+        let mut spans: Vec<(Range<usize>, &'static str)> = vec![];
+        let mut line_start = 0;
+        for line in content.split_inclusive(|b| *b == b'\n') {
+            let line_text = str::from_utf8(line).ok()?;
+            let ops = parse_state.parse_line(line_text, &self.syntax_set).ok()?;
+            let mut pos = 0;
+            let op_positions = ops.iter().map(|(pos, op)| (*pos, Some(op)));
+            for (op_pos, op) in op_positions.chain([(line.len(), None)]) {
+                if op_pos > pos {
+                    if let Some(label) = self.label_for(scope_stack.as_slice()) {
+                        let range = line_start + pos..line_start + op_pos;
+                        match spans.last_mut() {
+                            Some((last, last_label))
+                                if last.end == range.start && *last_label == label =>
+                            {
+                                last.end = range.end;
+                            }
+                            _ => spans.push((range, label)),
+                        }
+                    }
+                    pos = op_pos;
+                }
+                if let Some(op) = op {
+                    scope_stack.apply(op).ok()?;
+                }
+            }
+            line_start += line.len();
+        }
+        Some(SyntaxSpans { spans })
+    }
+}
+
+impl Default for SyntaxHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Highlighted byte ranges of a file.
+#[derive(Clone, Debug, Default)]
+pub struct SyntaxSpans {
+    spans: Vec<(Range<usize>, &'static str)>,
+}
+
+impl SyntaxSpans {
+    /// Splits the `range` of the file into parts, and returns them along with
+    /// the label of the highlighted ones.
+    pub fn split(&self, range: Range<usize>) -> Vec<(Range<usize>, Option<&'static str>)> {
+        let mut parts = vec![];
+        let mut pos = range.start;
+        let first = self.spans.partition_point(|(span, _)| span.end <= pos);
+        for (span, label) in &self.spans[first..] {
+            if span.start >= range.end {
+                break;
+            }
+            if span.start > pos {
+                parts.push((pos..span.start, None));
+            }
+            let end = span.end.min(range.end);
+            parts.push((span.start.max(pos)..end, Some(*label)));
+            pos = end;
+        }
+        if pos < range.end {
+            parts.push((pos..range.end, None));
+        }
+        parts
+    }
+}
+
+/// Returns the colors of the syntax labels in the named built-in theme.
+/// Labels the theme doesn't assign a color to are omitted.
+pub fn theme_colors(name: &str) -> Result<Vec<(&'static str, Color)>, config::ConfigError> {
+    let mut theme_set = ThemeSet::load_defaults();
+    let theme = theme_set.themes.remove(name).ok_or_else(|| {
+        config::ConfigError::Message(format!(
+            "invalid syntax theme: {name} (available themes: {})",
+            theme_set.themes.keys().join(", ")
+        ))
+    })?;
+    let highlighter = Highlighter::new(&theme);
+    let default_color = highlighter.get_default().foreground;
+    let colors = scope_labels()
+        .into_iter()
+        .unique_by(|(_, label)| *label)
+        .filter_map(|(scope, label)| {
+            let color = highlighter.style_for_stack(&[scope]).foreground;
+            (color != default_color).then_some((
+                label,
+                Color::Rgb {
+                    r: color.r,
+                    g: color.g,
+                    b: color.b,
+                },
+            ))
+        })
+        .collect();
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(path: &str, content: &str) -> Option<Vec<(&'static str, String)>> {
+        let highlighter = SyntaxHighlighter::new();
+        let spans =
+            highlighter.highlight(RepoPath::from_internal_string(path), content.as_bytes())?;
+        Some(
+            spans
+                .spans
+                .iter()
+                .map(|(range, label)| (*label, content[range.clone()].to_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_highlight() {
+        insta::assert_debug_snapshot!(
+            highlight("src/main.rs", "// hello\nfn main() { let s = \"x\"; }\n").unwrap(), @r###"
+        [
+            (
+                "comment",
+                "// hello\n",
+            ),
+            (
+                "keyword",
+                "fn",
+            ),
+            (
+                "function",
+                "main",
+            ),
+            (
+                "keyword",
+                "let",
+            ),
+            (
+                "keyword",
+                "=",
+            ),
+            (
+                "string",
+                "\"x\"",
+            ),
+        ]
+        "###);
+
+        // Syntax detected by file name
+        assert!(highlight("Makefile", "all:\n\techo hi\n").is_some());
+        // Syntax detected by shebang line
+        assert!(highlight("script", "#!/bin/sh\necho hi\n").is_some());
+        // Unknown syntax
+        assert_eq!(highlight("file.unknown-extension", "foo\n"), None);
+        // Not UTF-8
+        let highlighter = SyntaxHighlighter::new();
+        let path = RepoPath::from_internal_string("file.rs");
+        assert!(highlighter.highlight(path, b"\xff\n").is_none());
+    }
+
+    #[test]
+    fn test_split_spans() {
+        let spans = SyntaxSpans {
+            spans: vec![(2..4, "keyword"), (6..8, "string")],
+        };
+        assert_eq!(
+            spans.split(0..10),
+            vec![
+                (0..2, None),
+                (2..4, Some("keyword")),
+                (4..6, None),
+                (6..8, Some("string")),
+                (8..10, None),
+            ]
+        );
+        assert_eq!(
+            spans.split(3..7),
+            vec![
+                (3..4, Some("keyword")),
+                (4..6, None),
+                (6..7, Some("string"))
+            ]
+        );
+        assert_eq!(spans.split(4..6), vec![(4..6, None)]);
+        assert_eq!(spans.split(5..5), vec![]);
+    }
+
+    #[test]
+    fn test_theme_colors() {
+        let colors = theme_colors("InspiredGitHub").unwrap();
+        assert!(colors.iter().any(|(label, _)| *label == "comment"));
+        assert!(theme_colors("no-such-theme").is_err());
+    }
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
 use std::io::{IsTerminal as _, Stderr, StderrLock, Stdout, StdoutLock, Write};
 use std::process::{Child, ChildStdin, Stdio};
 use std::str::FromStr;
@@ -22,6 +23,8 @@ use tracing::instrument;
 use crate::cli_util::CommandError;
 use crate::config::CommandNameAndArgs;
 use crate::formatter::{Formatter, FormatterFactory, LabeledWriter};
+use crate::i18n::Messages;
+use crate::pager::{BuiltinPager, BUILTIN_PAGER_NAME};
 
 enum UiOutput {
     Terminal {
@@ -32,6 +35,9 @@ enum UiOutput {
         child: Child,
         child_stdin: ChildStdin,
     },
+    BuiltinPaged {
+        pager: BuiltinPager,
+    },
 }
 
 impl UiOutput {
@@ -47,18 +53,25 @@ impl UiOutput {
         let child_stdin = child.stdin.take().unwrap();
         Ok(UiOutput::Paged { child, child_stdin })
     }
+
+    fn new_builtin_paged() -> io::Result<UiOutput> {
+        let pager = BuiltinPager::spawn()?;
+        Ok(UiOutput::BuiltinPaged { pager })
+    }
 }
 
 #[derive(Debug)]
 pub enum UiStdout<'a> {
     Terminal(StdoutLock<'static>),
     Paged(&'a ChildStdin),
+    BuiltinPaged(&'a BuiltinPager),
 }
 
 #[derive(Debug)]
 pub enum UiStderr<'a> {
     Terminal(StderrLock<'static>),
     Paged(&'a ChildStdin),
+    BuiltinPaged(&'a BuiltinPager),
 }
 
 macro_rules! for_outputs {
@@ -66,6 +79,7 @@ macro_rules! for_outputs {
         match $output {
             $ty::Terminal($pat) => $expr,
             $ty::Paged($pat) => $expr,
+            $ty::BuiltinPaged($pat) => $expr,
         }
     };
 }
@@ -100,10 +114,11 @@ impl Write for UiStderr<'_> {
 
 pub struct Ui {
     color: bool,
-    pager_cmd: CommandNameAndArgs,
+    pager_cmd: PagerCommand,
     paginate: PaginationChoice,
     progress_indicator: bool,
     formatter_factory: FormatterFactory,
+    messages: Messages,
     output: UiOutput,
 }
 
@@ -173,10 +188,30 @@ fn pagination_setting(config: &config::Config) -> Result<PaginationChoice, Comma
         .map_err(|err| CommandError::ConfigError(format!("Invalid `ui.paginate`: {err}")))
 }
 
-fn pager_setting(config: &config::Config) -> Result<CommandNameAndArgs, CommandError> {
-    config
+/// Pager selected by `ui.pager`.
+#[derive(Clone, Debug)]
+enum PagerCommand {
+    Builtin,
+    External(CommandNameAndArgs),
+}
+
+impl PagerCommand {
+    fn name(&self) -> Cow<'_, str> {
+        match self {
+            PagerCommand::Builtin => BUILTIN_PAGER_NAME.into(),
+            PagerCommand::External(cmd) => cmd.split_name(),
+        }
+    }
+}
+
+fn pager_setting(config: &config::Config) -> Result<PagerCommand, CommandError> {
+    let cmd = config
         .get::<CommandNameAndArgs>("ui.pager")
-        .map_err(|err| CommandError::ConfigError(format!("Invalid `ui.pager`: {err}")))
+        .map_err(|err| CommandError::ConfigError(format!("Invalid `ui.pager`: {err}")))?;
+    match cmd {
+        CommandNameAndArgs::String(name) if name == BUILTIN_PAGER_NAME => Ok(PagerCommand::Builtin),
+        _ => Ok(PagerCommand::External(cmd)),
+    }
 }
 
 impl Ui {
@@ -193,6 +228,7 @@ impl Ui {
             pager_cmd: pager_setting(config)?,
             paginate: pagination_setting(config)?,
             progress_indicator,
+            messages: Messages::from_config(config),
             output: UiOutput::new_terminal(),
         })
     }
@@ -204,6 +240,7 @@ impl Ui {
         self.progress_indicator = progress_indicator_setting(config);
         let sanitize = io::stdout().is_terminal();
         self.formatter_factory = FormatterFactory::prepare(config, self.color, sanitize)?;
+        self.messages = Messages::from_config(config);
         Ok(())
     }
 
@@ -217,7 +254,11 @@ impl Ui {
 
         match self.output {
             UiOutput::Terminal { .. } if io::stdout().is_terminal() => {
-                match UiOutput::new_paged(&self.pager_cmd) {
+                let pager_output = match &self.pager_cmd {
+                    PagerCommand::Builtin => UiOutput::new_builtin_paged(),
+                    PagerCommand::External(cmd) => UiOutput::new_paged(cmd),
+                };
+                match pager_output {
                     Ok(pager_output) => {
                         self.output = pager_output;
                     }
@@ -226,16 +267,22 @@ impl Ui {
                         writeln!(
                             self.warning(),
                             "Failed to spawn pager '{name}': {e}",
-                            name = self.pager_cmd.split_name(),
+                            name = self.pager_cmd.name(),
                         )
                         .ok();
                     }
                 }
             }
-            UiOutput::Terminal { .. } | UiOutput::Paged { .. } => {}
+            UiOutput::Terminal { .. } | UiOutput::Paged { .. } | UiOutput::BuiltinPaged { .. } => {}
         }
     }
 
+    /// The messages in the user's language. Use the `tr!` macro to format
+    /// them.
+    pub fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
     pub fn color(&self) -> bool {
         self.color
     }
@@ -252,6 +299,7 @@ impl Ui {
         match &self.output {
             UiOutput::Terminal { stdout, .. } => UiStdout::Terminal(stdout.lock()),
             UiOutput::Paged { child_stdin, .. } => UiStdout::Paged(child_stdin),
+            UiOutput::BuiltinPaged { pager } => UiStdout::BuiltinPaged(pager),
         }
     }
 
@@ -268,6 +316,7 @@ impl Ui {
         match &self.output {
             UiOutput::Terminal { stderr, .. } => UiStderr::Terminal(stderr.lock()),
             UiOutput::Paged { child_stdin, .. } => UiStderr::Paged(child_stdin),
+            UiOutput::BuiltinPaged { pager } => UiStderr::BuiltinPaged(pager),
         }
     }
 
@@ -277,10 +326,16 @@ impl Ui {
     }
 
     /// Stderr stream to be attached to a child process.
+    ///
+    /// If this is a pipe, the caller should read the child's stderr and write
+    /// it to `stderr()`.
     pub fn stderr_for_child(&self) -> io::Result<Stdio> {
         match &self.output {
             UiOutput::Terminal { .. } => Ok(Stdio::inherit()),
             UiOutput::Paged { child_stdin, .. } => Ok(duplicate_child_stdin(child_stdin)?.into()),
+            // The built-in pager owns the terminal, and can't be written to
+            // by another process.
+            UiOutput::BuiltinPaged { .. } => Ok(Stdio::piped()),
         }
     }
 
@@ -289,7 +344,7 @@ impl Ui {
     pub fn use_progress_indicator(&self) -> bool {
         match &self.output {
             UiOutput::Terminal { stderr, .. } => self.progress_indicator && stderr.is_terminal(),
-            UiOutput::Paged { .. } => false,
+            UiOutput::Paged { .. } | UiOutput::BuiltinPaged { .. } => false,
         }
     }
 
@@ -314,18 +369,22 @@ impl Ui {
     /// Waits for the pager exits.
     #[instrument(skip_all)]
     pub fn finalize_pager(&mut self) {
-        if let UiOutput::Paged {
-            mut child,
-            child_stdin,
-        } = mem::replace(&mut self.output, UiOutput::new_terminal())
-        {
-            drop(child_stdin);
-            if let Err(e) = child.wait() {
-                // It's possible (though unlikely) that this write fails, but
-                // this function gets called so late that there's not much we
-                // can do about it.
-                writeln!(self.error(), "Failed to wait on pager: {e}").ok();
+        let result = match mem::replace(&mut self.output, UiOutput::new_terminal()) {
+            UiOutput::Terminal { .. } => Ok(()),
+            UiOutput::Paged {
+                mut child,
+                child_stdin,
+            } => {
+                drop(child_stdin);
+                child.wait().map(|_| ())
             }
+            UiOutput::BuiltinPaged { pager } => pager.wait(),
+        };
+        if let Err(e) = result {
+            // It's possible (though unlikely) that this write fails, but
+            // this function gets called so late that there's not much we
+            // can do about it.
+            writeln!(self.error(), "Failed to wait on pager: {e}").ok();
         }
     }
 
@@ -0,0 +1,81 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::exit;
+
+use clap::Parser;
+
+/// A fake code formatter, useful for testing
+///
+/// Reads the file content from stdin and writes the formatted content to
+/// stdout.
+#[derive(Parser, Debug)]
+#[clap()]
+struct Args {
+    /// Convert the content to uppercase
+    #[arg(long)]
+    uppercase: bool,
+
+    /// Convert the content to lowercase
+    #[arg(long)]
+    lowercase: bool,
+
+    /// Append this line to the content
+    #[arg(long)]
+    append: Option<String>,
+
+    /// Print this message to stderr and exit with an error
+    #[arg(long)]
+    fail: Option<String>,
+
+    /// Append the name of the file being formatted to this file, to count
+    /// the invocations
+    #[arg(long)]
+    log: Option<PathBuf>,
+
+    /// The name of the file being formatted
+    #[arg(long)]
+    path: Option<String>,
+}
+
+fn main() {
+    let args: Args = Args::parse();
+    if let Some(log) = &args.log {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log)
+            .unwrap();
+        writeln!(file, "{}", args.path.as_deref().unwrap_or("-")).unwrap();
+    }
+    if let Some(message) = &args.fail {
+        eprintln!("{message}");
+        exit(1);
+    }
+    let mut content = String::new();
+    std::io::stdin().read_to_string(&mut content).unwrap();
+    if args.uppercase {
+        content = content.to_uppercase();
+    }
+    if args.lowercase {
+        content = content.to_lowercase();
+    }
+    if let Some(line) = &args.append {
+        content.push_str(line);
+        content.push('\n');
+    }
+    std::io::stdout().write_all(content.as_bytes()).unwrap();
+}
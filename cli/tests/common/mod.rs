@@ -84,7 +84,13 @@ impl Default for TestEnvironment {
 
 impl TestEnvironment {
     pub fn jj_cmd(&self, current_dir: &Path, args: &[&str]) -> assert_cmd::Command {
-        let mut cmd = assert_cmd::Command::cargo_bin("jj").unwrap();
+        assert_cmd::Command::from_std(self.jj_std_cmd(current_dir, args))
+    }
+
+    /// Like `jj_cmd()`, but the command can be spawned to interact with it
+    /// while it's running.
+    pub fn jj_std_cmd(&self, current_dir: &Path, args: &[&str]) -> std::process::Command {
+        let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("jj"));
         cmd.current_dir(current_dir);
         cmd.args(args);
         cmd.env_clear();
@@ -0,0 +1,226 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"separate(" ", change_id.short(), author.name(), author.timestamp(), description.first_line())"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
+
+#[test]
+fn test_am() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Add file"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "Update file\n\nWith a body.\n"]);
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    std::fs::write(repo_path.join("new-file"), "x\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["format-patch", "-r", "root()..@-", "-o", "../patches"],
+    );
+
+    // The patches are inserted before the working-copy commit, which keeps
+    // its changes
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()"]);
+    std::fs::write(repo_path.join("other"), "x\n").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "am",
+            "../patches/0001-Add-file.patch",
+            "../patches/0002-Update-file.patch",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Applied yqosqzyt 0eac83a6 Add file
+    Applied spxsnpux 4c179073 Update file
+    Working copy now at: royxmykx 795f0c21 (no description set)
+    Parent commit      : spxsnpux 4c179073 Update file
+    Added 2 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  royxmykxtrkr Test User 2001-02-03 04:05:12.000 +07:00
+    ◉  spxsnpuxtvxq Test User 2001-02-03 04:05:09.000 +07:00 Update file
+    ◉  yqosqzytrlsw Test User 2001-02-03 04:05:07.000 +07:00 Add file
+    │ ◉  kkmpptxzrspx Test User 2001-02-03 04:05:09.000 +07:00 Update file
+    │ ◉  qpvuntsmwlqt Test User 2001-02-03 04:05:07.000 +07:00 Add file
+    ├─╯
+    ◉  zzzzzzzzzzzz 1970-01-01 00:00:00.000 +00:00
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r", "@-", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 422c2b7ab3...0f7bc76605 100644
+    --- a/file
+    +++ b/file
+    @@ -1,2 +1,2 @@
+     a
+    -b
+    +c
+    diff --git a/new-file b/new-file
+    new file mode 100644
+    index 0000000000..587be6b4c3
+    --- /dev/null
+    +++ b/new-file
+    @@ -1,0 +1,1 @@
+    +x
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r", "@-", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  Update file
+    │
+    ~  With a body.
+    "###);
+}
+
+#[test]
+fn test_am_continue() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Add file"]);
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Update file"]);
+    std::fs::write(repo_path.join("other"), "x\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Add other"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["format-patch", "-r", "@---..@-", "-o", "../patches"],
+    );
+
+    // The first patch doesn't apply to a different version of the file
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()"]);
+    std::fs::write(repo_path.join("file"), "a\nB\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "am",
+            "../patches/0001-Update-file.patch",
+            "../patches/0002-Add-other.patch",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: vruxwmqv ab807e26 (empty) Update file
+    Parent commit      : royxmykx 9486b3b3 (no description set)
+    file: 1 of 1 hunks don't apply
+    Error: Failed to apply patch: Update file
+    Hint: The changes that don't apply are saved in $TEST_ENV/repo/.jj/am/rejected.diff. Make them in the working copy, and then run `jj am --continue`.
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  vruxwmqvtpmx Test User 2001-02-03 04:05:08.000 +07:00 Update file
+    ◉  royxmykxtrkr Test User 2001-02-03 04:05:12.000 +07:00
+    │ ◉  kkmpptxzrspx Test User 2001-02-03 04:05:09.000 +07:00 Add other
+    │ ◉  rlvkpnrzqnoo Test User 2001-02-03 04:05:08.000 +07:00 Update file
+    │ ◉  qpvuntsmwlqt Test User 2001-02-03 04:05:07.000 +07:00 Add file
+    ├─╯
+    ◉  zzzzzzzzzzzz 1970-01-01 00:00:00.000 +00:00
+    "###);
+    let rejected =
+        std::fs::read_to_string(repo_path.join(".jj").join("am").join("rejected.diff")).unwrap();
+    insta::assert_snapshot!(rejected, @r###"
+    --- a/file
+    +++ b/file
+    @@ -1,2 +1,2 @@
+     a
+    -b
+    +c
+    "###);
+
+    // Another series can't be started before the first one is done
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["am", "../patches/0002-Add-other.patch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Patches from a previous `jj am` are still being applied
+    Hint: Run `jj am --continue` to apply them, or `jj am --abort` to forget them.
+    "###);
+
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["am", "--continue"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Applied kpqxywon cc83d1c2 Add other
+    Working copy now at: vzqnnsmr 45862e36 (empty) (no description set)
+    Parent commit      : kpqxywon cc83d1c2 Add other
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  vzqnnsmrxxkw Test User 2001-02-03 04:05:17.000 +07:00
+    ◉  kpqxywonksrl Test User 2001-02-03 04:05:09.000 +07:00 Add other
+    ◉  vruxwmqvtpmx Test User 2001-02-03 04:05:08.000 +07:00 Update file
+    ◉  royxmykxtrkr Test User 2001-02-03 04:05:12.000 +07:00
+    │ ◉  kkmpptxzrspx Test User 2001-02-03 04:05:09.000 +07:00 Add other
+    │ ◉  rlvkpnrzqnoo Test User 2001-02-03 04:05:08.000 +07:00 Update file
+    │ ◉  qpvuntsmwlqt Test User 2001-02-03 04:05:07.000 +07:00 Add file
+    ├─╯
+    ◉  zzzzzzzzzzzz 1970-01-01 00:00:00.000 +00:00
+    "###);
+    assert!(!repo_path.join(".jj").join("am").exists());
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["am", "--continue"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No patches are being applied
+    "###);
+}
+
+#[test]
+fn test_am_abort() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Add file"]);
+    std::fs::write(repo_path.join("other"), "x\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Add other"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["format-patch", "-r", "root()..@-", "-o", "../patches"],
+    );
+
+    // The file already exists
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "am",
+            "../patches/0001-Add-file.patch",
+            "../patches/0002-Add-other.patch",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: mzvwutvl 8d311a42 (empty) Add file
+    Parent commit      : rlvkpnrz 60a3216e Add other
+    file: file already exists
+    Error: Failed to apply patch: Add file
+    Hint: The changes that don't apply are saved in $TEST_ENV/repo/.jj/am/rejected.diff. Make them in the working copy, and then run `jj am --continue`.
+    "###);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["am", "--abort"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Forgot 1 remaining patches
+    "###);
+    assert!(!repo_path.join(".jj").join("am").exists());
+}
@@ -0,0 +1,184 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Creates commits "1" to "count", where the first bad one has "bug" in the
+/// file "state", and returns the path to the repo.
+fn create_history(
+    test_env: &TestEnvironment,
+    count: usize,
+    first_bad: usize,
+) -> std::path::PathBuf {
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    for i in 1..=count {
+        let state = if i >= first_bad { "bug" } else { "ok" };
+        std::fs::write(repo_path.join("state"), format!("{state} {i}\n")).unwrap();
+        test_env.jj_cmd_ok(&repo_path, &["commit", "-m", &i.to_string()]);
+    }
+    repo_path
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    test_env.jj_cmd_success(repo_path, &["log", "-T", "description"])
+}
+
+#[test]
+fn test_bisect_run() {
+    let test_env = TestEnvironment::default();
+    let repo_path = create_history(&test_env, 8, 6);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "bisect",
+            "run",
+            "--range=description(1)::description(8)",
+            "--",
+            "sh",
+            "-c",
+            "! grep -q bug state",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    The first bad revision is:
+      royxmykx 5190b410 6
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Now evaluating: zsuskuln eb2b0d87 4
+    Working copy now at: znkkpsqq de842f30 (empty) (no description set)
+    Parent commit      : zsuskuln eb2b0d87 4
+    Added 0 files, modified 1 files, removed 0 files
+    The revision is good.
+
+    Now evaluating: royxmykx 5190b410 6
+    Working copy now at: uuzqqzqu 8e88c476 (empty) (no description set)
+    Parent commit      : royxmykx 5190b410 6
+    Added 0 files, modified 1 files, removed 0 files
+    The revision is bad.
+
+    Now evaluating: mzvwutvl 6b5f18f8 5
+    Working copy now at: lpnsqqnl f2d0816a (empty) (no description set)
+    Parent commit      : mzvwutvl 6b5f18f8 5
+    Added 0 files, modified 1 files, removed 0 files
+    The revision is good.
+
+    Working copy now at: yostqsxw b53610ab (empty) (no description set)
+    Parent commit      : vruxwmqv fde757ec 8
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+
+    // The original working copy is checked out again, and the temporary
+    // working-copy commits are gone
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  8
+    ◉  7
+    ◉  6
+    ◉  5
+    ◉  4
+    ◉  3
+    ◉  2
+    ◉  1
+    ◉
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("state")).unwrap(), @r###"
+    bug 8
+    "###);
+}
+
+#[test]
+fn test_bisect_run_skip() {
+    let test_env = TestEnvironment::default();
+    let repo_path = create_history(&test_env, 4, 4);
+
+    // Revision 3 can't be evaluated
+    let (stdout, _stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "bisect",
+            "run",
+            "--range=description(1)::description(4)",
+            "--",
+            "sh",
+            "-c",
+            "grep -q 3 state && exit 125; ! grep -q bug state",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    The first bad revision is one of these, but some of them were skipped:
+      zsuskuln ec1a06b0 4
+      kkmpptxz 1d74179a 3
+    "###);
+
+    // No bad revisions
+    let (stdout, _stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "bisect",
+            "run",
+            "--range=description(1)::description(4)",
+            "--",
+            "true",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    None of the revisions in the range is bad
+    "###);
+}
+
+#[test]
+fn test_bisect_run_abort() {
+    let test_env = TestEnvironment::default();
+    let repo_path = create_history(&test_env, 4, 2);
+
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "bisect",
+            "run",
+            "--range=description(1)::description(4)",
+            "--",
+            "sh",
+            "-c",
+            "exit 200",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Now evaluating: rlvkpnrz 745c78e9 2
+    Working copy now at: royxmykx dec8fa66 (empty) (no description set)
+    Parent commit      : rlvkpnrz 745c78e9 2
+    Added 0 files, modified 1 files, removed 0 files
+    Working copy now at: mzvwutvl 1498f189 (empty) (no description set)
+    Parent commit      : zsuskuln dad5833b 4
+    Added 0 files, modified 1 files, removed 0 files
+    Error: sh exited with exit status: 200; aborting the bisection
+    "###);
+    // The working copy is restored
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  4
+    ◉  3
+    ◉  2
+    ◉  1
+    ◉
+    "###);
+}
@@ -100,7 +100,7 @@ fn test_branch_move() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "set", "foo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: No such branch: foo
+    Error[E1020]: No such branch: foo
     Hint: Use `jj branch create` to create it.
     "###);
 
@@ -205,7 +205,7 @@ fn test_branch_rename() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "rename", "bnoexist", "blocal"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: No such branch: bnoexist
+    Error[E1020]: No such branch: bnoexist
     "###);
 
     test_env.jj_cmd_ok(&repo_path, &["describe", "-m=commit-0"]);
@@ -453,7 +453,7 @@ fn test_branch_forget_export() {
     insta::assert_snapshot!(stdout, @"");
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r=foo", "--no-graph"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revision "foo" doesn't exist
+    Error[E1011]: Revision "foo" doesn't exist
     "###);
 
     // `jj git export` will delete the branch from git. In a colocated repo,
@@ -638,7 +638,7 @@ fn test_branch_forget_deleted_or_nonexistent_branch() {
     // Can't forget a non-existent branch
     let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "forget", "i_do_not_exist"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: No such branch: i_do_not_exist
+    Error[E1020]: No such branch: i_do_not_exist
     "###);
 }
 
@@ -1113,7 +1113,7 @@ fn test_branch_list_filtered() {
     insta::assert_snapshot!(query(&["-rbranches(remote-delete)"]), @r###"
     "###);
     insta::assert_snapshot!(query_error(&["-rremote-delete"]), @r###"
-    Error: Revision "remote-delete" doesn't exist
+    Error[E1011]: Revision "remote-delete" doesn't exist
     Hint: Did you mean "remote-delete@origin", "remote-keep", "remote-rewrite", "remote-rewrite@origin"?
     "###);
 
@@ -1139,6 +1139,115 @@ fn test_branch_list_filtered() {
     "###);
 }
 
+#[test]
+fn test_branch_list_tracked_and_conflicted() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config("git.auto-local-branch = true");
+
+    // Initialize remote refs
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "remote", "--git"]);
+    let remote_path = test_env.env_root().join("remote");
+    for branch in ["remote-sync", "remote-unsync", "remote-untrack"] {
+        test_env.jj_cmd_ok(&remote_path, &["new", "root()", "-m", branch]);
+        test_env.jj_cmd_ok(&remote_path, &["branch", "create", branch]);
+    }
+    test_env.jj_cmd_ok(&remote_path, &["new"]);
+    test_env.jj_cmd_ok(&remote_path, &["git", "export"]);
+
+    // Initialize local refs
+    let mut remote_git_path = remote_path;
+    remote_git_path.extend([".jj", "repo", "store", "git"]);
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", remote_git_path.to_str().unwrap(), "local"],
+    );
+    let local_path = test_env.env_root().join("local");
+    test_env.jj_cmd_ok(&local_path, &["branch", "untrack", "remote-untrack@origin"]);
+    test_env.jj_cmd_ok(&local_path, &["new", "remote-unsync", "-m", "local change"]);
+    test_env.jj_cmd_ok(&local_path, &["branch", "set", "remote-unsync"]);
+    test_env.jj_cmd_ok(&local_path, &["new", "root()", "-m", "local-only"]);
+    test_env.jj_cmd_ok(&local_path, &["branch", "create", "local-only"]);
+
+    // Make a conflicted local branch by moving it concurrently
+    test_env.jj_cmd_ok(&local_path, &["branch", "create", "local-conflict"]);
+    test_env.jj_cmd_ok(
+        &local_path,
+        &[
+            "branch",
+            "set",
+            "--allow-backwards",
+            "local-conflict",
+            "-r",
+            "remote-sync",
+        ],
+    );
+    test_env.jj_cmd_ok(
+        &local_path,
+        &[
+            "branch",
+            "set",
+            "--allow-backwards",
+            "local-conflict",
+            "-r",
+            "remote-unsync",
+            "--at-op",
+            "@-",
+        ],
+    );
+    // Resolve the concurrent operations
+    test_env.jj_cmd_ok(&local_path, &["status"]);
+
+    let query =
+        |args: &[&str]| test_env.jj_cmd_success(&local_path, &[&["branch", "list"], args].concat());
+
+    insta::assert_snapshot!(query(&[]), @r###"
+    local-conflict (conflicted):
+      - lylxulpl e4f36c55 (empty) local-only
+      + zwtyzrop c761c7ea (empty) remote-sync
+      + kmkuslsw e166a7b4 (empty) local change
+    local-only: lylxulpl e4f36c55 (empty) local-only
+    remote-sync: zwtyzrop c761c7ea (empty) remote-sync
+    remote-unsync: kmkuslsw e166a7b4 (empty) local change
+      @origin (behind by 1 commits): qpsqxpyq 38ef8af7 (empty) remote-unsync
+    remote-untrack: vmortlor 71a16b05 (empty) remote-untrack
+    "###);
+
+    // Branches that only exist locally or whose remotes aren't tracked are
+    // omitted. The out-of-sync remote shows what there is to push.
+    insta::assert_snapshot!(query(&["--tracked"]), @r###"
+    remote-sync: zwtyzrop c761c7ea (empty) remote-sync
+    remote-unsync: kmkuslsw e166a7b4 (empty) local change
+      @origin (behind by 1 commits): qpsqxpyq 38ef8af7 (empty) remote-unsync
+    "###);
+
+    insta::assert_snapshot!(query(&["--conflicted"]), @r###"
+    local-conflict (conflicted):
+      - lylxulpl e4f36c55 (empty) local-only
+      + zwtyzrop c761c7ea (empty) remote-sync
+      + kmkuslsw e166a7b4 (empty) local change
+    "###);
+
+    // The filters are AND-ed with names and revisions.
+    insta::assert_snapshot!(query(&["--tracked", "glob:*-sync"]), @r###"
+    remote-sync: zwtyzrop c761c7ea (empty) remote-sync
+    "###);
+    insta::assert_snapshot!(query(&["--tracked", "-rremote-sync"]), @r###"
+    remote-sync: zwtyzrop c761c7ea (empty) remote-sync
+    "###);
+    insta::assert_snapshot!(query(&["--tracked", "local-only"]), @"");
+    insta::assert_snapshot!(query(&["--tracked", "--conflicted"]), @"");
+
+    insta::assert_snapshot!(
+        test_env.jj_cmd_cli_error(&local_path, &["branch", "list", "--tracked", "--all"]),
+        @r###"
+    error: the argument '--tracked' cannot be used with '--all'
+
+    Usage: jj branch list --tracked [NAMES]...
+
+    For more information, try '--help'.
+    "###);
+}
+
 #[test]
 fn test_branch_list_much_remote_divergence() {
     let test_env = TestEnvironment::default();
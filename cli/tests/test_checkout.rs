@@ -70,7 +70,7 @@ fn test_checkout_not_single_rev() {
     insta::assert_snapshot!(stderr, @r###"
     warning: `jj checkout` is deprecated; use `jj new` instead, which is equivalent
     warning: `jj checkout` will be removed in a future version, and this will be a hard error
-    Error: Revset "root()..@" resolved to more than one revision
+    Error[E1014]: Revset "root()..@" resolved to more than one revision
     Hint: The revset "root()..@" resolved to these revisions:
     royxmykx 2f859371 (empty) (no description set)
     mzvwutvl 5c1afd8b (empty) fifth
@@ -84,7 +84,7 @@ fn test_checkout_not_single_rev() {
     insta::assert_snapshot!(stderr, @r###"
     warning: `jj checkout` is deprecated; use `jj new` instead, which is equivalent
     warning: `jj checkout` will be removed in a future version, and this will be a hard error
-    Error: Revset "root()..@-" resolved to more than one revision
+    Error[E1014]: Revset "root()..@-" resolved to more than one revision
     Hint: The revset "root()..@-" resolved to these revisions:
     mzvwutvl 5c1afd8b (empty) fifth
     zsuskuln 009f88bf (empty) fourth
@@ -97,7 +97,7 @@ fn test_checkout_not_single_rev() {
     insta::assert_snapshot!(stderr, @r###"
     warning: `jj checkout` is deprecated; use `jj new` instead, which is equivalent
     warning: `jj checkout` will be removed in a future version, and this will be a hard error
-    Error: Revset "@-|@--" resolved to more than one revision
+    Error[E1014]: Revset "@-|@--" resolved to more than one revision
     Hint: The revset "@-|@--" resolved to these revisions:
     mzvwutvl 5c1afd8b (empty) fifth
     zsuskuln 009f88bf (empty) fourth
@@ -107,7 +107,7 @@ fn test_checkout_not_single_rev() {
     insta::assert_snapshot!(stderr, @r###"
     warning: `jj checkout` is deprecated; use `jj new` instead, which is equivalent
     warning: `jj checkout` will be removed in a future version, and this will be a hard error
-    Error: Revset "none()" didn't resolve to any revisions
+    Error[E1013]: Revset "none()" didn't resolve to any revisions
     "###);
 }
 
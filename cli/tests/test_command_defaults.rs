@@ -0,0 +1,107 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_command_defaults_basic() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "my-branch"]);
+
+    test_env.add_config(r#"command-defaults.log = ["-r", "@", "-T", "branches"]"#);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  my-branch
+    │
+    ~
+    "###);
+
+    // Arguments on the command line override the defaults
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    │
+    ~
+    "###);
+
+    // Global arguments before the command name are kept in place
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["-R", "repo", "log"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  my-branch
+    │
+    ~
+    "###);
+
+    // Also applies to the default command and to aliases
+    test_env.add_config(
+        r#"ui.default-command = "log"
+    aliases.l = ["log", "--no-graph"]
+    "#,
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &[]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  my-branch
+    │
+    ~
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["l"]);
+    insta::assert_snapshot!(stdout, @"my-branch");
+}
+
+#[test]
+fn test_command_defaults_subcommand() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "my-branch"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "other"]);
+
+    test_env.add_config(
+        r#"[command-defaults]
+    branch.list = ["glob:my-*"]
+    "#,
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["branch", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    my-branch: qpvuntsm 230dd059 (empty) (no description set)
+    "###);
+    // Visible aliases of subcommands resolve to the same defaults
+    let stdout = test_env.jj_cmd_success(&repo_path, &["branch", "l"]);
+    insta::assert_snapshot!(stdout, @r###"
+    my-branch: qpvuntsm 230dd059 (empty) (no description set)
+    "###);
+    // Arguments that can be repeated are added to the defaults
+    let stdout = test_env.jj_cmd_success(&repo_path, &["branch", "list", "other"]);
+    insta::assert_snapshot!(stdout, @r###"
+    my-branch: qpvuntsm 230dd059 (empty) (no description set)
+    other: qpvuntsm 230dd059 (empty) (no description set)
+    "###);
+    // Other subcommands aren't affected
+    let stdout = test_env.jj_cmd_success(&repo_path, &["branch", "delete", "other"]);
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_command_defaults_invalid_definition() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(r#"command-defaults.version = 5"#);
+    let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["version"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Default arguments for "version" must be a string list
+    "###);
+}
@@ -75,6 +75,23 @@ fn test_commit_with_editor() {
     "###);
 }
 
+#[test]
+fn test_commit_with_trailers() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"templates.commit_trailers = '"Signed-off-by: " ++ committer'"#);
+
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m=first"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @  9f08ec2bf0af
+    ◉  4ab806755d19 first
+    │
+    │  Signed-off-by: Test User <test.user@example.com>
+    ◉  000000000000
+    "###);
+}
+
 #[test]
 fn test_commit_interactive() {
     let mut test_env = TestEnvironment::default();
@@ -136,6 +153,66 @@ JJ: Lines starting with "JJ: " (like this one) will be removed.
     );
 }
 
+#[test]
+fn test_commit_with_description_diff() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config("ui.description-diff = true");
+    std::fs::write(workspace_path.join("file1"), "foo\n").unwrap();
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(edit_script, ["dump editor", "write\nSubject"].join("\0")).unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit"]);
+    assert_eq!(
+        std::fs::read_to_string(test_env.env_root().join("editor")).unwrap(),
+        r#"
+JJ: This commit contains the following changes:
+JJ:     A file1
+
+JJ: Lines starting with "JJ: " (like this one) will be removed.
+JJ: ignore-rest
+JJ: Everything below the line above will be removed.
+diff --git a/file1 b/file1
+new file mode 100644
+index 0000000000..257cc5642c
+--- /dev/null
++++ b/file1
+@@ -1,0 +1,1 @@
++foo
+"#
+    );
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @  049f23eabeb4
+    ◉  44817e373876 Subject
+    ◉  000000000000
+    "###);
+}
+
+#[test]
+fn test_commit_description_checks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"description-checks.max-subject-length = 10"#);
+
+    let stderr = test_env.jj_cmd_failure(&workspace_path, &["commit", "-m", "A long subject"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The description doesn't pass the checks:
+      The subject is longer than 10 characters
+    Hint: Fix the description, or use --skip-checks to skip the checks.
+    "###);
+    test_env.jj_cmd_ok(
+        &workspace_path,
+        &["commit", "-m", "A long subject", "--skip-checks"],
+    );
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @  4afc72b3c24c
+    ◉  ee6e666c19d4 A long subject
+    ◉  000000000000
+    "###);
+}
+
 #[test]
 fn test_commit_without_working_copy() {
     let test_env = TestEnvironment::default();
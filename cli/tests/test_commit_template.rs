@@ -517,3 +517,85 @@ fn test_log_customize_short_id() {
     ◉  ZZZZZZZZ root() 00000000
     "###);
 }
+
+#[cfg(unix)]
+#[test]
+fn test_log_signature() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let test_env = TestEnvironment::default();
+    // A stand-in for `gpgsm` that "signs" data with its checksum.
+    let program = test_env.env_root().join("fake-gpgsm");
+    std::fs::write(
+        &program,
+        r#"#!/bin/sh
+case "$2" in
+  -bsau)
+    sum=$(cksum)
+    printf -- '-----BEGIN SIGNED MESSAGE-----\n%s\n%s\n-----END SIGNED MESSAGE-----\n' "$3" "$sum"
+    ;;
+  --verify)
+    sum=$(cksum)
+    key=$(sed -n 2p "$3")
+    if [ "$(sed -n 3p "$3")" = "$sum" ]; then
+      echo "[GNUPG:] GOODSIG $key /CN=Test User"
+    else
+      echo "[GNUPG:] BADSIG $key /CN=Test User"
+      exit 1
+    fi
+    ;;
+esac
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "unsigned"]);
+    test_env.add_config(&format!(
+        r#"
+        signing.backend = "x509"
+        signing.key = "0x1234"
+        signing.sign-all = true
+        signing.backends.x509.program = "{}"
+        "#,
+        program.display()
+    ));
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "signed"]);
+
+    let template = r#"description.first_line() ++ " " ++ if(signature,
+      signature.status() ++ " " ++ signature.key() ++ " " ++ signature.display(),
+      "unsigned",
+    ) ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", template, "-r", "..@"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  signed good 0x1234 /CN=Test User
+    ◉  unsigned unsigned
+    │
+    ~
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "--no-graph", "-r", "..@", "--show-signatures"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    kkmpptxz test.user@example.com 2001-02-03 04:05:09.000 +07:00 6117c4cd
+    (empty) signed
+    Signature: good /CN=Test User (0x1234)
+    qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 9333b7aa
+    (empty) unsigned
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["show", "--show-signatures"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Commit ID: 6117c4cde1b96a6f4355899ed2fc951feb8812b8
+    Change ID: kkmpptxzrspxrzommnulwmwkkqwworpl
+    Author: Test User <test.user@example.com> (2001-02-03 04:05:09.000 +07:00)
+    Committer: Test User <test.user@example.com> (2001-02-03 04:05:09.000 +07:00)
+
+        signed
+
+    Signature: good /CN=Test User (0x1234)
+    "###);
+}
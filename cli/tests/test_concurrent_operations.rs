@@ -15,6 +15,7 @@
 use std::path::Path;
 
 use itertools::Itertools as _;
+use jj_lib::lock::{FileLock, LockBackend};
 
 use crate::common::TestEnvironment;
 
@@ -33,7 +34,7 @@ fn test_concurrent_operation_divergence() {
     );
 
     // "op log" doesn't merge the concurrent operations
-    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "log"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["op", "log"]);
     insta::assert_snapshot!(stdout, @r###"
     ◉  31e3dc1f7c87 test-username@host.example.com 2001-02-03 04:05:09.000 +07:00 - 2001-02-03 04:05:09.000 +07:00
     │  describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
@@ -47,6 +48,9 @@ fn test_concurrent_operation_divergence() {
     │  initialize repo
     ◉  000000000000 root()
     "###);
+    insta::assert_snapshot!(stderr, @r###"
+    The operation log has 2 heads from concurrent operations. They will be merged by the next command that loads the repo.
+    "###);
 
     // We should be informed about the concurrent modification
     let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["log", "-T", "description"]);
@@ -59,6 +63,22 @@ fn test_concurrent_operation_divergence() {
     insta::assert_snapshot!(stderr, @r###"
     Concurrent modification detected, resolving automatically.
     "###);
+
+    // The merge operation is marked
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "log", "--limit=4"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @    b3a1d1eb76dc test-username@host.example.com 2001-02-03 04:05:11.000 +07:00 - 2001-02-03 04:05:11.000 +07:00 (merge)
+    ├─╮  resolve concurrent operations
+    │ │  args: jj log -T description
+    ◉ │  e914ad151dae test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
+    │ │  describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    │ │  args: jj describe -m 'message 1'
+    │ ◉  31e3dc1f7c87 test-username@host.example.com 2001-02-03 04:05:09.000 +07:00 - 2001-02-03 04:05:09.000 +07:00
+    ├─╯  describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    │    args: jj describe -m 'message 2' --at-op @-
+    ◉  27143b59c690 test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+    │  add workspace 'default'
+    "###);
 }
 
 #[test]
@@ -239,6 +259,73 @@ fn test_concurrent_snapshot_wc_reloadable() {
     "###);
 }
 
+#[test]
+fn test_read_only_command_with_locked_working_copy() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "contents\n").unwrap();
+
+    // Simulate another process that is snapshotting the working copy
+    let lock = FileLock::lock(
+        repo_path.join(".jj/working_copy/working_copy.lock"),
+        LockBackend::default(),
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["log", "-T=description", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    ◉
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    The working copy is being updated by another process, so it wasn't snapshotted.
+    "###);
+    drop(lock);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["log", "-T=description", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    │  A file
+    ◉
+    "###);
+    insta::assert_snapshot!(stderr, @"");
+}
+
+#[test]
+fn test_read_only_command_with_locked_op_heads() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "message 1"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "-m", "message 2", "--at-op", "@-"],
+    );
+
+    // Simulate another process that is merging the concurrent operations
+    let lock = FileLock::lock(
+        repo_path.join(".jj/repo/op_heads/heads/lock"),
+        LockBackend::default(),
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  message 2
+    │ @  message 1
+    ├─╯
+    ◉
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Concurrent modification detected, resolving automatically.
+    "###);
+    // The merged operation isn't recorded
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["op", "log"]);
+    insta::assert_snapshot!(stderr, @r###"
+    The operation log has 2 heads from concurrent operations. They will be merged by the next command that loads the repo.
+    "###);
+    drop(lock);
+}
+
 fn get_log_output_with_stderr(test_env: &TestEnvironment, cwd: &Path) -> (String, String) {
     let template = r#"commit_id ++ " " ++ description"#;
     test_env.jj_cmd_ok(cwd, &["log", "-T", template])
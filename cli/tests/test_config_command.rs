@@ -12,11 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::Path;
+
 use insta::assert_snapshot;
 use itertools::Itertools;
 use regex::Regex;
 
-use crate::common::TestEnvironment;
+use crate::common::{get_stderr_string, get_stdout_string, TestEnvironment};
 
 pub mod common;
 
@@ -181,6 +183,117 @@ fn test_config_list_layer() {
     "###);
 }
 
+#[test]
+fn test_config_list_include_origin() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let user_config_path = test_env.config_path().join("config.toml");
+    test_env.set_config_path(user_config_path.to_owned());
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["config", "set", "--user", "test-table.user-key", "a"],
+    );
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["config", "set", "--user", "test-table.layered-key", "b"],
+    );
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["config", "set", "--repo", "test-table.layered-key", "c"],
+    );
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "test-table",
+            "--include-origin",
+            "--include-overridden",
+            "--config-toml=test-table.arg-key=1",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    # test-table.layered-key="b" # user
+    test-table.user-key="a" # user
+    test-table.layered-key="c" # repo
+    test-table.arg-key=1 # cli
+    "###);
+}
+
+#[test]
+fn test_config_conditional() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "work", "--git"]);
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "personal", "--git"]);
+    let work_path = test_env.env_root().join("work");
+    let personal_path = test_env.env_root().join("personal");
+    test_env.jj_cmd_ok(
+        &personal_path,
+        &[
+            "git",
+            "remote",
+            "add",
+            "origin",
+            "https://example.com/me/personal.git",
+        ],
+    );
+    test_env.add_config(&format!(
+        r#"
+        test-table.key = "default"
+
+        [[conditional]]
+        when.repositories = ['{}']
+        config.test-table.key = "work"
+
+        [[conditional]]
+        when.remote-urls = ["https://example.com/me/*"]
+        config.test-table.key = "personal"
+
+        [[conditional]]
+        when.remote-urls = ["https://example.com/me/*"]
+        when.hostnames = ["no-such-host.invalid"]
+        config.test-table.key = "other host"
+        "#,
+        work_path.display()
+    ));
+
+    let get_key = |path: &Path| test_env.jj_cmd_success(path, &["config", "get", "test-table.key"]);
+    insta::assert_snapshot!(get_key(test_env.env_root()), @r###"
+    default
+    "###);
+    insta::assert_snapshot!(get_key(&work_path), @r###"
+    work
+    "###);
+    insta::assert_snapshot!(get_key(&personal_path), @r###"
+    personal
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        test_env.env_root(),
+        &["config", "get", "test-table.key", "-R", "work"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    work
+    "###);
+
+    // The conditional sections themselves aren't listed
+    let stdout = test_env.jj_cmd_success(&work_path, &["config", "list", "--user"]);
+    insta::assert_snapshot!(find_stdout_lines(r"(conditional|test-table)[^=]*", &stdout), @r###"test-table.key="work""###);
+
+    test_env.add_config(
+        r#"
+        [[conditional]]
+        when.hostnames = ["[invalid"]
+        "#,
+    );
+    let stderr = test_env.jj_cmd_failure(&work_path, &["status"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: Invalid pattern in conditional config: Pattern syntax error near position 0: invalid range pattern
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
 #[test]
 fn test_config_layer_override_default() {
     let test_env = TestEnvironment::default();
@@ -355,6 +468,137 @@ fn test_config_layer_workspace() {
     "###);
 }
 
+#[test]
+fn test_config_project() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "--git", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["config", "trust"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: There is no .jjconfig.toml in the workspace root
+    "###);
+
+    std::fs::write(
+        repo_path.join(".jjconfig.toml"),
+        r#"
+ui.editor = "rm -rf"
+template-aliases.greeting = '"hello"'
+"#,
+    )
+    .unwrap();
+
+    // Not used until it's trusted
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Ignoring the untrusted project config in .jjconfig.toml
+    Hint: Review the file and run `jj config trust` to use it.
+    No matching config key for template-aliases.greeting
+    "###);
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+        "n\n",
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Trust .jjconfig.toml? (yN): 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    This repo has a project config in .jjconfig.toml. It can set revset and template aliases, immutable heads, and fix tools to run on your files.
+    Ignoring the untrusted project config in .jjconfig.toml
+    Hint: Review the file and run `jj config trust` to use it.
+    No matching config key for template-aliases.greeting
+    "###);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["config", "trust"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Ignoring the untrusted project config in .jjconfig.toml
+    Hint: Review the file and run `jj config trust` to use it.
+    Trusted the project config in .jjconfig.toml
+    "###);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    template-aliases.greeting="\"hello\"" # project
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Ignoring `ui` in .jjconfig.toml, which can't be set by a project config
+    "###);
+
+    // Changes need to be trusted again
+    std::fs::write(
+        repo_path.join(".jjconfig.toml"),
+        "template-aliases.greeting = '\"bye\"'\n",
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+        "y\n",
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Trust .jjconfig.toml? (yN): template-aliases.greeting="\"bye\"" # project
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    The project config in .jjconfig.toml has changed since you trusted it.
+    "###);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    template-aliases.greeting="\"bye\"" # project
+    "###);
+    insta::assert_snapshot!(stderr, @"");
+
+    // User config takes precedence
+    test_env.add_config(r#"template-aliases.greeting = '"hi"'"#);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "config",
+            "list",
+            "--include-origin",
+            "template-aliases.greeting",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    template-aliases.greeting="\"hi\"" # user
+    "###);
+}
+
 #[test]
 fn test_config_set_missing_opts() {
     let test_env = TestEnvironment::default();
@@ -565,6 +809,170 @@ fn test_config_edit_repo() {
     test_env.jj_cmd_ok(&repo_path, &["config", "edit", "--repo"]);
 }
 
+#[test]
+fn test_config_edit_invalid() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let edit_script = test_env.set_up_fake_editor();
+
+    std::fs::write(edit_script, "write\nui.editor = [").unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["config", "edit", "--repo"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse file $TEST_ENV/repo/.jj/repo/config.toml
+    Caused by: TOML parse error at line 1, column 14
+      |
+    1 | ui.editor = [
+      |              ^
+    invalid array
+    expected `]`
+
+    Hint: Run `jj config edit` again to fix the syntax error.
+    "###);
+}
+
+#[test]
+fn test_config_theme() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let user_config_path = test_env.config_path().join("config.toml");
+    test_env.set_config_path(user_config_path.to_owned());
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(
+        &user_config_path,
+        r##"
+        [themes.mine.colors]
+        "change_id" = "#ff0000"
+        [themes.mine.light.colors]
+        "commit_id" = "ansi-color-19"
+        "##,
+    )
+    .unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["config", "theme", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    * default
+      gruvbox (dark, light)
+      mine (light)
+      solarized (dark, light)
+    "###);
+
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "config",
+            "theme",
+            "set",
+            "--user",
+            "mine",
+            "--variant",
+            "light",
+        ],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["config", "theme", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+      default
+      gruvbox (dark, light)
+    * mine (light)
+      solarized (dark, light)
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-r@",
+            "--color=always",
+            "-T",
+            "change_id.short() ++ commit_id.short()",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    @  [38;2;255;0;0mqpvuntsmwlqt[39m[38;5;19m230dd059e1b0[39m
+    │
+    ~
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["config", "theme", "set", "--user", "nope"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No such theme: nope
+    Hint: Use `jj config theme list` to see the available themes.
+    "###);
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "config",
+            "theme",
+            "set",
+            "--user",
+            "mine",
+            "--variant",
+            "dark",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Theme mine has no dark variant
+    "###);
+
+    // An unknown theme is a config error
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["log", "--color=always", "--config-toml=ui.theme='nope'"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: Unknown theme: nope
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
+#[test]
+fn test_config_check() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["config", "check"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    No problems found in the config files.
+    "###);
+
+    test_env.add_config(
+        r#"
+        [ui]
+        difff-editor = "meld"
+        [merge-tools.x]
+        diff-args = "$left $right"
+        "#,
+    );
+    std::fs::write(
+        repo_path.join(".jj/repo/config.toml"),
+        "no-such-table.key = 1\n",
+    )
+    .unwrap();
+
+    // Likely misspellings are reported by every command
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["status"]);
+    insta::assert_snapshot!(stderr, @r###"
+    $TEST_ENV/config/config0002.toml:3: Unknown config key "ui.difff-editor"
+    Hint: Did you mean "ui.diff-editor"?
+    "###);
+
+    let assert = test_env
+        .jj_cmd(&repo_path, &["config", "check"])
+        .assert()
+        .code(1);
+    insta::assert_snapshot!(test_env.normalize_output(&get_stdout_string(&assert)), @r###"
+    $TEST_ENV/config/config0002.toml:3: Unknown config key "ui.difff-editor" (did you mean "ui.diff-editor"?)
+    $TEST_ENV/config/config0002.toml:5: Invalid type for config key "merge-tools.x.diff-args": expected array, found string
+    $TEST_ENV/repo/.jj/repo/config.toml:1: Unknown config key "no-such-table"
+    "###);
+    insta::assert_snapshot!(test_env.normalize_output(&get_stderr_string(&assert)), @r###"
+    $TEST_ENV/config/config0002.toml:3: Unknown config key "ui.difff-editor"
+    Hint: Did you mean "ui.diff-editor"?
+    Error: Found 3 problems in the config files
+    "###);
+}
+
 #[test]
 fn test_config_path() {
     let test_env = TestEnvironment::default();
@@ -590,7 +998,7 @@ fn test_config_edit_repo_outside_repo() {
     let test_env = TestEnvironment::default();
     let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["config", "edit", "--repo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 }
 
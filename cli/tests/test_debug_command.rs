@@ -56,6 +56,46 @@ fn test_debug_revset() {
     });
 }
 
+#[test]
+fn test_debug_revset_explain() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&workspace_path, &["new", "-m", "child"]);
+
+    let stdout = test_env.jj_cmd_success(
+        &workspace_path,
+        &[
+            "debug",
+            "revset",
+            "--explain",
+            "(::@ | root()..) & ~description(child)",
+        ],
+    );
+    // Only show the profile, without the timings
+    let profile = stdout
+        .split("-- Profile:\n")
+        .nth(1)
+        .unwrap()
+        .split("\n\n")
+        .next()
+        .unwrap();
+    let profile = Regex::new(r" in [0-9.]+.?s")
+        .unwrap()
+        .replace_all(profile, " in <time>");
+    assert_snapshot!(profile, @r###"
+    FilterWithin: 2 commits in <time>
+      Union: 3 commits in <time>
+        Ancestors: 3 commits in <time>
+          Commits (1 ids): 1 commits in <time>
+        Range: 2 commits in <time>
+          Commits (1 ids): 1 commits in <time>
+          Commits (1 ids): 1 commits in <time>
+      Predicate NotIn
+        Predicate Description(Substring("child"))
+    "###);
+}
+
 #[test]
 fn test_debug_index() {
     let test_env = TestEnvironment::default();
@@ -119,6 +159,59 @@ fn test_debug_reindex() {
     );
 }
 
+#[test]
+fn test_debug_index_changed_paths() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    std::fs::write(workspace_path.join("file1"), "a").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "add file1"]);
+    std::fs::write(workspace_path.join("file2"), "b").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "add file2"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "index-changed-paths"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Built changed-path filters for 8 commits.
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "index"]);
+    assert!(stdout.contains("Number of commits with changed-path filters: 8"));
+
+    // Path-limited revsets give the same results with the filters
+    let template = r#"description.first_line() ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(
+        &workspace_path,
+        &["log", "--no-graph", "-T", template, "-r", "file(file1)"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    add file1
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &workspace_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            template,
+            "-r",
+            "file(file1) | file(file2)",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    add file2
+    add file1
+    "###);
+
+    // New commits get filters when they're indexed
+    test_env.jj_cmd_ok(&workspace_path, &["new"]);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "index"]);
+    assert!(stdout.contains("Number of commits with changed-path filters: 9"));
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "index-changed-paths"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Built changed-path filters for 0 commits.
+    "###);
+}
+
 #[test]
 fn test_debug_operation_id() {
     let test_env = TestEnvironment::default();
@@ -132,6 +225,230 @@ fn test_debug_operation_id() {
     );
 }
 
+#[test]
+fn test_debug_fsck() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    std::fs::write(workspace_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "add file"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "fsck"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Checked 5 operations, 5 views, 5 commits, 2 trees, 1 files, 0 symlinks, 0 conflicts.
+    "###);
+}
+
+#[test]
+fn test_debug_fsck_git_objects() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    std::fs::write(workspace_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "add file"]);
+
+    std::fs::write(workspace_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "modify file"]);
+
+    // Replace the loose object of the first version of the file with the second
+    // version
+    let objects_dir = workspace_path.join(".jj/repo/store/git/objects");
+    let object_path = objects_dir.join("78/981922613b2afb6025042ff6bd878ac1994e85");
+    std::fs::remove_file(&object_path).unwrap();
+    std::fs::copy(
+        objects_dir.join("61/780798228d17af2d34fce4cfbdf35556832472"),
+        &object_path,
+    )
+    .unwrap();
+    let assert = test_env
+        .jj_cmd(&workspace_path, &["debug", "fsck", "--quarantine"])
+        .assert()
+        .code(1);
+    let stdout = test_env.normalize_output(&common::get_stdout_string(&assert));
+    let stderr = test_env.normalize_output(&common::get_stderr_string(&assert));
+    insta::assert_snapshot!(stdout, @r###"
+    Corrupt file 78981922613b2afb6025042ff6bd878ac1994e85 at file: The contents hash to 61780798228d17af2d34fce4cfbdf35556832472
+      Moved $TEST_ENV/repo/.jj/repo/store/git/objects/78/981922613b2afb6025042ff6bd878ac1994e85 to $TEST_ENV/repo/.jj/repo/quarantine/files/78981922613b2afb6025042ff6bd878ac1994e85
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Checked 7 operations, 7 views, 8 commits, 3 trees, 2 files, 0 symlinks, 0 conflicts.
+    Error: Found 1 problem
+    "###);
+    assert!(!object_path.exists());
+    assert!(workspace_path
+        .join(".jj/repo/quarantine/files/78981922613b2afb6025042ff6bd878ac1994e85")
+        .exists());
+
+    // The object is now missing
+    let assert = test_env
+        .jj_cmd(&workspace_path, &["debug", "fsck"])
+        .assert()
+        .code(1);
+    let stdout = test_env.normalize_output(&common::get_stdout_string(&assert));
+    insta::assert_snapshot!(stdout, @r###"
+    Missing file 78981922613b2afb6025042ff6bd878ac1994e85 at file
+    "###);
+}
+
+#[test]
+fn test_debug_fsck_native() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["init", "repo", "--config-toml=ui.allow-init-native=true"],
+    );
+    let workspace_path = test_env.env_root().join("repo");
+    std::fs::write(workspace_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "add file"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "fsck"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Checked 5 operations, 5 views, 5 commits, 2 trees, 1 files, 0 symlinks, 0 conflicts.
+    "###);
+
+    // Replace a commit with another one, and delete an operation
+    let commit_id = |revision: &str| {
+        test_env.jj_cmd_success(
+            &workspace_path,
+            &["log", "--no-graph", "-r", revision, "-T", "commit_id"],
+        )
+    };
+    let commits_dir = workspace_path.join(".jj/repo/store/commits");
+    std::fs::copy(
+        commits_dir.join(commit_id("@")),
+        commits_dir.join(commit_id("@-")),
+    )
+    .unwrap();
+    let operation_ids = test_env.jj_cmd_success(
+        &workspace_path,
+        &["op", "log", "--no-graph", "-T", r#"id ++ "\n""#],
+    );
+    let operation_id = operation_ids.lines().nth(2).unwrap();
+    std::fs::remove_file(
+        workspace_path
+            .join(".jj/repo/op_store/operations")
+            .join(operation_id),
+    )
+    .unwrap();
+    let assert = test_env
+        .jj_cmd(&workspace_path, &["debug", "fsck"])
+        .assert()
+        .code(1);
+    let stdout = test_env.normalize_output(&common::get_stdout_string(&assert));
+    let stderr = test_env.normalize_output(&common::get_stderr_string(&assert));
+    insta::assert_snapshot!(stdout, @r###"
+    Missing operation c5295044c865da96a4e11af35cc601c20c5963448bc8f3137f605d7e6bd1a37f7f041fb4a057ea9200895ce61ccfff9fbf2dab70fd9e61def9c091673a2d332e
+    Corrupt commit e690cd8e884ac9422c4ed42631d2d9861e8db8f98a877b35a9327ad7a601b1a4e0f478980177ce9bfff86a2277575fe46ab231580079dadaf59ffba42548711c: The contents hash to 14c6b039ea1957c9791430edc40bde063cb330d36f164811eca088ed2eb26ee6bd7d7d7b5e9663624fa7065a5610dc112b659404f6e5189dede2c67e2f8e9160
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Checked 3 operations, 2 views, 5 commits, 2 trees, 1 files, 0 symlinks, 0 conflicts.
+    Error: Found 2 problems
+    "###);
+}
+
+#[test]
+fn test_debug_export_import_repo() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main", "-r", "@-"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    std::fs::write(repo_path.join("other"), "c\n").unwrap();
+
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["debug", "export-repo", "../backup.jjar"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(filter_bytes(&stderr), @r###"
+    Exported 58 files ([N] bytes) to ../backup.jjar
+    "###);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["debug", "import-repo", "backup.jjar", "imported"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Imported repo to "imported"
+    Working copy now at: rlvkpnrz 0fe1425c (no description set)
+    Added 2 files, modified 0 files, removed 0 files
+    "###);
+    let imported_path = test_env.env_root().join("imported");
+    assert_eq!(
+        std::fs::read_to_string(imported_path.join("file")).unwrap(),
+        "b\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(imported_path.join("other")).unwrap(),
+        "c\n"
+    );
+
+    // The commits, branches, and the operation log are the same
+    for args in [&["log"][..], &["op", "log"], &["status"]] {
+        assert_eq!(
+            test_env.jj_cmd_success(&imported_path, args),
+            test_env.jj_cmd_success(&repo_path, args),
+        );
+    }
+
+    // The destination must be empty
+    let stderr = test_env.jj_cmd_failure(
+        test_env.env_root(),
+        &["debug", "import-repo", "backup.jjar", "imported"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Destination "imported" is not empty
+    "###);
+
+    // Other files are rejected
+    std::fs::write(test_env.env_root().join("bad.jjar"), "not an archive").unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        test_env.env_root(),
+        &["debug", "import-repo", "bad.jjar", "bad"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid repo archive: Unknown file format
+    "###);
+}
+
+#[test]
+fn test_debug_export_import_repo_colocated() {
+    let test_env = TestEnvironment::default();
+    let repo_path = test_env.env_root().join("repo");
+    git2::Repository::init(&repo_path).unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["init", "--git-repo", "."]);
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+
+    test_env.jj_cmd_ok(&repo_path, &["debug", "export-repo", "../backup.jjar"]);
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["debug", "import-repo", "backup.jjar", "imported"],
+    );
+    // The Git repo is imported as an internal Git repo
+    let imported_path = test_env.env_root().join("imported");
+    assert!(!imported_path.join(".git").exists());
+    assert_eq!(
+        std::fs::read_to_string(imported_path.join(".jj/repo/store/git_target")).unwrap(),
+        "git"
+    );
+    let stdout = test_env.jj_cmd_success(&imported_path, &["log", "-r", "all()"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  rlvkpnrz test.user@example.com 2001-02-03 04:05:08.000 +07:00 e60f4632
+    │  (empty) (no description set)
+    ◉  qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 HEAD@git 8b754178
+    │  first
+    ◉  zzzzzzzz root() 00000000
+    "###);
+}
+
+fn filter_bytes(text: &str) -> String {
+    let regex = Regex::new(r"\d+ bytes").unwrap();
+    regex.replace_all(text, "[N] bytes").to_string()
+}
+
 fn filter_index_stats(text: &str) -> String {
     let regex = Regex::new(r"    Name: [0-9a-z]+").unwrap();
     regex.replace_all(text, "    Name: [hash]").to_string()
@@ -280,6 +280,211 @@ JJ: Lines starting with "JJ: " (like this one) will be removed.
     );
 }
 
+#[test]
+fn test_describe_commit_trailers() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(
+        r#"templates.commit_trailers = '"Signed-off-by: " ++ committer ++ "\nBug: 123"'"#,
+    );
+    let get_description = || {
+        test_env.jj_cmd_success(
+            &repo_path,
+            &["log", "--no-graph", "-r@", "-T", "description"],
+        )
+    };
+
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Title"]);
+    insta::assert_snapshot!(get_description(), @r###"
+    Title
+
+    Signed-off-by: Test User <test.user@example.com>
+    Bug: 123
+    "###);
+
+    // Trailers that are already there aren't added again
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Title\n\nBody\n\nBug: 123"]);
+    insta::assert_snapshot!(get_description(), @r###"
+    Title
+
+    Body
+
+    Bug: 123
+    Signed-off-by: Test User <test.user@example.com>
+    "###);
+
+    // An empty description stays empty
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", ""]);
+    insta::assert_snapshot!(get_description(), @"");
+
+    test_env.add_config(r#"templates.commit_trailers = '"Not a trailer"'"#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m", "Title"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid trailer from templates.commit_trailers: Not a trailer
+    "###);
+}
+
+#[test]
+fn test_describe_default_description_file() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    std::fs::write(
+        workspace_path.join(".commit-template"),
+        "Component:\n\nTESTED=TODO\n",
+    )
+    .unwrap();
+    test_env.add_config(r#"ui.default-description-file = ".commit-template""#);
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(edit_script, ["dump editor"].join("\0")).unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["describe"]);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("editor")).unwrap(), @r###"
+    Component:
+
+    TESTED=TODO
+
+    JJ: This commit contains the following changes:
+    JJ:     A .commit-template
+
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+
+    test_env.add_config(r#"ui.default-description-file = "missing""#);
+    test_env.jj_cmd_ok(&workspace_path, &["new"]);
+    let stderr = test_env.jj_cmd_failure(&workspace_path, &["describe"]);
+    insta::assert_snapshot!(stderr.replace(test_env.env_root().to_str().unwrap(), "$TEST_ENV"), @r###"
+    Error: Failed to read description template $TEST_ENV/repo/missing
+    Caused by: No such file or directory (os error 2)
+    "###);
+
+    // Environment variables are expanded
+    test_env.add_env_var("TEMPLATE_DIR", workspace_path.to_str().unwrap());
+    test_env.add_config(
+        r#"ui.default-description-file = "${TEMPLATE_DIR}/${TEMPLATE_NAME:-.commit-template}""#,
+    );
+    test_env.jj_cmd_ok(&workspace_path, &["describe"]);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("editor")).unwrap(), @r###"
+    Component:
+
+    TESTED=TODO
+
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+}
+
+#[test]
+fn test_describe_description_diff() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+    test_env.add_config("ui.description-diff = true");
+    std::fs::write(workspace_path.join("file1"), "foo\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["describe", "-m", "Subject"]);
+
+    // The diff is shown below the instructions, and isn't kept
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(&edit_script, "dump editor").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["describe"]);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("editor")).unwrap(), @r###"
+    Subject
+
+    JJ: This commit contains the following changes:
+    JJ:     A file1
+
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    JJ: ignore-rest
+    JJ: Everything below the line above will be removed.
+    diff --git a/file1 b/file1
+    new file mode 100644
+    index 0000000000..257cc5642c
+    --- /dev/null
+    +++ b/file1
+    @@ -1,0 +1,1 @@
+    +foo
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &workspace_path,
+        &["log", "--no-graph", "-r@", "-T", "description"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Subject
+    "###);
+
+    // Everything after the marker is removed
+    std::fs::write(
+        &edit_script,
+        "write\nNew subject\nJJ: ignore-rest\nNot in the description",
+    )
+    .unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["describe"]);
+    let stdout = test_env.jj_cmd_success(
+        &workspace_path,
+        &["log", "--no-graph", "-r@", "-T", "description"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    New subject
+    "###);
+}
+
+#[test]
+fn test_describe_description_checks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(
+        r#"
+        [description-checks]
+        max-subject-length = 10
+        require-body = true
+        required-pattern = '(?m)^Bug: \d+$'
+        "#,
+    );
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m", "A long subject"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The description doesn't pass the checks:
+      The subject is longer than 10 characters
+      The description has no body
+      The description doesn't match /(?m)^Bug: \d+$/
+    Hint: Fix the description, or use --skip-checks to skip the checks.
+    "###);
+
+    // Trailers don't count as a body
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m", "Subject\n\nBug: 123"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The description doesn't pass the checks:
+      The description has no body
+    Hint: Fix the description, or use --skip-checks to skip the checks.
+    "###);
+
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "-m", "Subject\n\nThe body.\n\nBug: 123"],
+    );
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "-m", "A long subject", "--skip-checks"],
+    );
+    // Empty descriptions and unchanged descriptions aren't checked
+    test_env.jj_cmd_ok(&repo_path, &["describe", "--no-edit", "--reset-author"]);
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", ""]);
+
+    test_env.add_config(r#"description-checks.required-pattern = "(""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m", "Subject"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid description-checks.required-pattern: (
+    Caused by: regex parse error:
+        (
+        ^
+    error: unclosed group
+    "###);
+}
+
 #[test]
 fn test_describe_author() {
     let test_env = TestEnvironment::default();
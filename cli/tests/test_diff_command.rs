@@ -43,9 +43,8 @@ fn test_diff_basic() {
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    D file1
     M file2
-    A file3
+    R file1 -> file3
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--types"]);
@@ -57,13 +56,6 @@ fn test_diff_basic() {
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
     insta::assert_snapshot!(stdout, @r###"
-    diff --git a/file1 b/file1
-    deleted file mode 100644
-    index 257cc5642c..0000000000
-    --- a/file1
-    +++ /dev/null
-    @@ -1,1 +1,0 @@
-    -foo
     diff --git a/file2 b/file2
     index 257cc5642c...3bd1f0e297 100644
     --- a/file2
@@ -71,27 +63,16 @@ fn test_diff_basic() {
     @@ -1,1 +1,2 @@
      foo
     +bar
-    diff --git a/file3 b/file3
-    new file mode 100644
-    index 0000000000..257cc5642c
-    --- /dev/null
-    +++ b/file3
-    @@ -1,0 +1,1 @@
-    +foo
+    diff --git a/file1 b/file3
+    similarity index 100%
+    rename from file1
+    rename to file3
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "--git"]);
     insta::assert_snapshot!(stdout, @r###"
-    D file1
     M file2
-    A file3
-    diff --git a/file1 b/file1
-    deleted file mode 100644
-    index 257cc5642c..0000000000
-    --- a/file1
-    +++ /dev/null
-    @@ -1,1 +1,0 @@
-    -foo
+    R file1 -> file3
     diff --git a/file2 b/file2
     index 257cc5642c...3bd1f0e297 100644
     --- a/file2
@@ -99,21 +80,128 @@ fn test_diff_basic() {
     @@ -1,1 +1,2 @@
      foo
     +bar
-    diff --git a/file3 b/file3
+    diff --git a/file1 b/file3
+    similarity index 100%
+    rename from file1
+    rename to file3
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--stat"]);
+    insta::assert_snapshot!(stdout, @r###"
+    file2          | 1 +
+    file1 => file3 | 0
+    2 files changed, 1 insertion(+), 0 deletions(-)
+    "###);
+}
+
+#[test]
+fn test_diff_renames() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "1\n2\n3\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::remove_file(repo_path.join("file1")).unwrap();
+    std::fs::write(repo_path.join("renamed"), "a\nb\nc\nx\n").unwrap();
+    // Only modified files are sources of copies
+    std::fs::write(repo_path.join("file2"), "1\n2\n3\n4\n").unwrap();
+    std::fs::write(repo_path.join("copied"), "1\n2\n3\n").unwrap();
+    std::fs::write(repo_path.join("other"), "x\ny\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    C file2 -> copied
+    M file2
+    A other
+    R file1 -> renamed
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy changes:
+    C file2 -> copied
+    M file2
+    A other
+    R file1 -> renamed
+    Working copy : rlvkpnrz d53fd843 (no description set)
+    Parent commit: qpvuntsm 0f42223f (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file2 b/copied
+    similarity index 100%
+    copy from file2
+    copy to copied
+    diff --git a/file2 b/file2
+    index 01e79c32a8...94ebaf9001 100644
+    --- a/file2
+    +++ b/file2
+    @@ -1,3 +1,4 @@
+     1
+     2
+     3
+    +4
+    diff --git a/other b/other
     new file mode 100644
-    index 0000000000..257cc5642c
+    index 0000000000..b77b4eb1d9
     --- /dev/null
-    +++ b/file3
-    @@ -1,0 +1,1 @@
-    +foo
+    +++ b/other
+    @@ -1,0 +1,2 @@
+    +x
+    +y
+    diff --git a/file1 b/renamed
+    similarity index 75%
+    rename from file1
+    rename to renamed
+    index d68dd4031d...2155939615 100644
+    --- a/file1
+    +++ b/renamed
+    @@ -1,4 +1,4 @@
+     a
+     b
+     c
+    -d
+    +x
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--stat"]);
     insta::assert_snapshot!(stdout, @r###"
-    file1 | 1 -
-    file2 | 1 +
-    file3 | 1 +
-    3 files changed, 2 insertions(+), 1 deletion(-)
+    file2 => copied  | 0
+    file2            | 1 +
+    other            | 2 ++
+    file1 => renamed | 2 +-
+    4 files changed, 4 insertions(+), 1 deletion(-)
+    "###);
+
+    // Files that are less similar than the threshold aren't paired up
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "-s",
+            "--config-toml",
+            "ui.diff.rename-similarity = 80",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    C file2 -> copied
+    D file1
+    M file2
+    A other
+    A renamed
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["diff", "-s", "--config-toml", "ui.diff.renames = false"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    A copied
+    D file1
+    M file2
+    A other
+    A renamed
     "###);
 }
 
@@ -214,6 +302,75 @@ fn test_diff_types() {
     }
 }
 
+#[test]
+fn test_diff_name_only() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("deleted"), "d").unwrap();
+    std::fs::write(repo_path.join("modified"), "m").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m=first"]);
+    std::fs::remove_file(repo_path.join("deleted")).unwrap();
+    std::fs::write(repo_path.join("modified"), "mod").unwrap();
+    std::fs::create_dir(repo_path.join("sub")).unwrap();
+    std::fs::write(repo_path.join("sub/added"), "a").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m=second"]);
+    std::fs::write(repo_path.join("third"), "t").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m=third"]);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--name-only"]);
+    insta::assert_snapshot!(stdout, @r###"
+    third
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--name-only",
+            "--from=description(first)",
+            "--to=description(third)",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    deleted
+    modified
+    sub/added
+    third
+    "###);
+
+    // The paths changed in any of the revisions
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--name-only",
+            "-r=description(second)|description(third)",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    deleted
+    modified
+    sub/added
+    third
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--name-only", "-r=all()", "sub"]);
+    insta::assert_snapshot!(stdout, @r###"
+    sub/added
+    "###);
+
+    // Other formats still need a single revision
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["diff", "--name-only", "--git", "-r=all()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1014]: Revset "all()" resolved to more than one revision
+    Hint: The revset "all()" resolved to these revisions:
+    kkmpptxz 49d64529 third
+    rlvkpnrz 6384d451 second
+    qpvuntsm d7407499 first
+    zzzzzzzz 00000000 (empty) (no description set)
+    "###);
+}
+
 #[test]
 fn test_diff_bad_args() {
     let test_env = TestEnvironment::default();
@@ -426,6 +583,492 @@ fn test_diff_missing_newline() {
     "###);
 }
 
+#[test]
+fn test_diff_granularity() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "the quick brown fox\nsame\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file"), "the quick red fox\nsame\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "--color=always"]);
+    insta::assert_snapshot!(stdout, @r###"
+    [1mdiff --git a/file b/file[0m
+    [1mindex 058ab4b200...367dab737b 100644[0m
+    [1m--- a/file[0m
+    [1m+++ b/file[0m
+    [38;5;6m@@ -1,2 +1,2 @@[39m
+    [38;5;1m-the quick [4mbrown[24m fox[39m
+    [38;5;2m+the quick [4mred[24m fox[39m
+     same
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--git",
+            "--color=always",
+            "--config-toml=ui.diff.granularity='char'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [1mdiff --git a/file b/file[0m
+    [1mindex 058ab4b200...367dab737b 100644[0m
+    [1m--- a/file[0m
+    [1m+++ b/file[0m
+    [38;5;6m@@ -1,2 +1,2 @@[39m
+    [38;5;1m-the quick [4mb[24mr[4mown[24m fox[39m
+    [38;5;2m+the quick r[4med[24m fox[39m
+     same
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--color=always",
+            "--config-toml=ui.diff.granularity='char'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [38;5;3mModified regular file file:[39m
+    [38;5;1m   1[39m [38;5;2m   1[39m: the quick [38;5;1mb[39mr[38;5;1mown[38;5;2med[39m fox
+    [38;5;1m   2[39m [38;5;2m   2[39m: same
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--git",
+            "--color=always",
+            "--config-toml=ui.diff.granularity='line'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [1mdiff --git a/file b/file[0m
+    [1mindex 058ab4b200...367dab737b 100644[0m
+    [1m--- a/file[0m
+    [1m+++ b/file[0m
+    [38;5;6m@@ -1,2 +1,2 @@[39m
+    [38;5;1m-the quick brown fox[39m
+    [38;5;2m+the quick red fox[39m
+     same
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["diff", "--config-toml=ui.diff.granularity='bad'"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: invalid diff granularity: bad
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
+#[test]
+fn test_diff_ignore_whitespace() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(
+        repo_path.join("file"),
+        "foo {\n    bar;\n}\nbaz {}\n1\n2\n3\n4\n5\n6\nqux\n",
+    )
+    .unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::write(
+        repo_path.join("file"),
+        "foo {\n  bar;\n}\n\nbaz  {  }\n1\n2\n3\n4\n5\n6\nquux\n",
+    )
+    .unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 58951ee43f...39284b0786 100644
+    --- a/file
+    +++ b/file
+    @@ -1,11 +1,12 @@
+     foo {
+    -    bar;
+    +  bar;
+     }
+    -baz {}
+    +
+    +baz  {  }
+     1
+     2
+     3
+     4
+     5
+     6
+    -qux
+    +quux
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "--ignore-all-space"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 58951ee43f...39284b0786 100644
+    --- a/file
+    +++ b/file
+    @@ -1,6 +1,7 @@
+     foo {
+         bar;
+     }
+    +
+     baz {}
+     1
+     2
+    @@ -8,4 +9,4 @@ baz {}
+     4
+     5
+     6
+    -qux
+    +quux
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "--ignore-space-change"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 58951ee43f...39284b0786 100644
+    --- a/file
+    +++ b/file
+    @@ -1,11 +1,12 @@
+     foo {
+         bar;
+     }
+    -baz {}
+    +
+    +baz  {  }
+     1
+     2
+     3
+     4
+     5
+     6
+    -qux
+    +quux
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--git",
+            "--ignore-all-space",
+            "--ignore-blank-lines",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 58951ee43f...39284b0786 100644
+    --- a/file
+    +++ b/file
+    @@ -8,4 +9,4 @@ baz {}
+     4
+     5
+     6
+    -qux
+    +quux
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--color-words",
+            "--ignore-all-space",
+            "--ignore-blank-lines",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Modified regular file file:
+        ...
+       8    9: 4
+       9   10: 5
+      10   11: 6
+      11   12: quxquux
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--stat",
+            "--ignore-all-space",
+            "--ignore-blank-lines",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    file | 2 +-
+    1 file changed, 1 insertion(+), 1 deletion(-)
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-r@",
+            "--no-graph",
+            "-Tdescription",
+            "--stat",
+            "--ignore-all-space",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    file | 3 ++-
+    1 file changed, 2 insertions(+), 1 deletion(-)
+    "###);
+
+    // The whitespace options can't be combined
+    let stderr = test_env.jj_cmd_cli_error(
+        &repo_path,
+        &["diff", "--ignore-all-space", "--ignore-space-change"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--ignore-all-space' cannot be used with '--ignore-space-change'
+
+    Usage: jj diff --ignore-all-space [PATHS]...
+
+    For more information, try '--help'.
+    "###);
+}
+
+#[test]
+fn test_diff_function_context() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let content = "\
+fn foo() {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+    let d = 4;
+    let e = 5;
+}
+
+fn bar() {
+    let f = 6;
+}
+";
+    std::fs::write(repo_path.join("file.rs"), content).unwrap();
+    std::fs::write(
+        repo_path.join("file.tex"),
+        "\\section{Intro}\n1\n2\n3\n4\n5\n",
+    )
+    .unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::write(
+        repo_path.join("file.rs"),
+        content.replace("let e = 5", "let e = 50"),
+    )
+    .unwrap();
+    std::fs::write(
+        repo_path.join("file.tex"),
+        "\\section{Intro}\n1\n2\n3\n4\nfive\n",
+    )
+    .unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file.rs b/file.rs
+    index 1d6eddf056...8068a2de46 100644
+    --- a/file.rs
+    +++ b/file.rs
+    @@ -3,7 +3,7 @@ fn foo() {
+         let b = 2;
+         let c = 3;
+         let d = 4;
+    -    let e = 5;
+    +    let e = 50;
+     }
+     
+     fn bar() {
+    diff --git a/file.tex b/file.tex
+    index 19ed3adb16...e5c427889e 100644
+    --- a/file.tex
+    +++ b/file.tex
+    @@ -3,4 +3,4 @@
+     2
+     3
+     4
+    -5
+    +five
+    "###);
+
+    // Function context
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["diff", "--git", "--function-context", "file.rs"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file.rs b/file.rs
+    index 1d6eddf056...8068a2de46 100644
+    --- a/file.rs
+    +++ b/file.rs
+    @@ -1,9 +1,9 @@
+     fn foo() {
+         let a = 1;
+         let b = 2;
+         let c = 3;
+         let d = 4;
+    -    let e = 5;
+    +    let e = 50;
+     }
+     
+     fn bar() {
+    "###);
+
+    // Custom driver selected by .gitattributes
+    test_env.add_config(
+        r#"
+        [diff-drivers.tex]
+        function-regex = '^(\\(sub)*section\{.*)$'
+        "#,
+    );
+    std::fs::write(repo_path.join(".gitattributes"), "*.tex diff=tex\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "file.tex"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file.tex b/file.tex
+    index 19ed3adb16...e5c427889e 100644
+    --- a/file.tex
+    +++ b/file.tex
+    @@ -3,4 +3,4 @@ \section{Intro}
+     2
+     3
+     4
+    -5
+    +five
+    "###);
+}
+
+#[test]
+fn test_diff_syntax_highlight() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(
+        repo_path.join("file.rs"),
+        "// comment\nfn main() {\n    let s = \"a\";\n}\n",
+    )
+    .unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::write(
+        repo_path.join("file.rs"),
+        "// comment\nfn main() {\n    let s = \"b\";\n}\n",
+    )
+    .unwrap();
+
+    // The removed and added lines are highlighted too, and the changed tokens
+    // stay underlined
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--git",
+            "--color=always",
+            "--config-toml=ui.diff.syntax-highlight=true",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [1mdiff --git a/file.rs b/file.rs[0m
+    [1mindex cbe58577c8...c5090d0746 100644[0m
+    [1m--- a/file.rs[0m
+    [1m+++ b/file.rs[0m
+    [38;5;6m@@ -1,4 +1,4 @@[39m
+     [38;5;8m// comment[39m
+     [38;5;5mfn[39m [38;5;4mmain[39m() {
+    [38;5;1m-    [38;5;5mlet[38;5;1m s [38;5;5m=[38;5;1m [38;5;3m"[4ma[24m"[38;5;1m;[39m
+    [38;5;2m+    [38;5;5mlet[38;5;2m s [38;5;5m=[38;5;2m [38;5;3m"[4mb[24m"[38;5;2m;[39m
+     }
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--color=always",
+            "--config-toml=ui.diff.syntax-highlight=true",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [38;5;3mModified regular file file.rs:[39m
+    [38;5;1m   1[39m [38;5;2m   1[39m: [38;5;8m// comment[39m
+    [38;5;1m   2[39m [38;5;2m   2[39m: [38;5;5mfn[39m [38;5;4mmain[39m() {
+    [38;5;1m   3[39m [38;5;2m   3[39m:     [38;5;5mlet[39m s [38;5;5m=[39m [38;5;3m"[38;5;1ma[38;5;2mb[38;5;3m"[39m;
+    [38;5;1m   4[39m [38;5;2m   4[39m: }
+    "###);
+
+    // The removed and added words are highlighted under the removed and added
+    // styles, which shows when those don't set a foreground color
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--color=always",
+            "--config-toml=ui.diff.syntax-highlight=true",
+            r#"--config-toml=colors."diff removed"={bg="red"}"#,
+            r#"--config-toml=colors."diff added"={bg="green"}"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [38;5;3mModified regular file file.rs:[39m
+    [48;5;1m   1[49m [48;5;2m   1[49m: [38;5;8m// comment[39m
+    [48;5;1m   2[49m [48;5;2m   2[49m: [38;5;5mfn[39m [38;5;4mmain[39m() {
+    [48;5;1m   3[49m [48;5;2m   3[49m:     [38;5;5mlet[39m s [38;5;5m=[39m [38;5;3m"[48;5;1ma[48;5;2mb[49m"[39m;
+    [48;5;1m   4[49m [48;5;2m   4[49m: }
+    "###);
+
+    // Colors from a built-in theme
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--color=always",
+            "--config-toml=ui.diff.syntax-highlight=true",
+            "--config-toml=ui.diff.syntax-theme='base16-ocean.dark'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    [38;5;3mModified regular file file.rs:[39m
+    [38;5;1m   1[39m [38;5;2m   1[39m: [38;2;101;115;126m// comment[39m
+    [38;5;1m   2[39m [38;5;2m   2[39m: [38;2;180;142;173mfn[39m [38;2;143;161;179mmain[39m() {
+    [38;5;1m   3[39m [38;5;2m   3[39m:     [38;2;180;142;173mlet[39m s [38;2;180;142;173m=[39m [38;2;163;190;140m"[38;5;1ma[38;5;2mb[38;2;163;190;140m"[39m;
+    [38;5;1m   4[39m [38;5;2m   4[39m: }
+    "###);
+
+    // Highlighting is off by default
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "--color=always"]);
+    insta::assert_snapshot!(stdout, @r###"
+    [1mdiff --git a/file.rs b/file.rs[0m
+    [1mindex cbe58577c8...c5090d0746 100644[0m
+    [1m--- a/file.rs[0m
+    [1m+++ b/file.rs[0m
+    [38;5;6m@@ -1,4 +1,4 @@[39m
+     // comment
+     fn main() {
+    [38;5;1m-    let s = "[4ma[24m";[39m
+    [38;5;2m+    let s = "[4mb[24m";[39m
+     }
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "diff",
+            "--color=always",
+            "--config-toml=ui.diff.syntax-theme='bad'",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: invalid syntax theme: bad (available themes: InspiredGitHub, Solarized (dark), Solarized (light), base16-eighties.dark, base16-mocha.dark, base16-ocean.dark, base16-ocean.light)
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
 #[test]
 fn test_color_words_diff_missing_newline() {
     let test_env = TestEnvironment::default();
@@ -941,3 +1584,83 @@ fn test_diff_stat_long_name_or_stat() {
     2 files changed, 20 insertions(+), 0 deletions(-)
     "###);
 }
+
+#[test]
+fn test_diff_filesets() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::create_dir(repo_path.join("sub")).unwrap();
+    for path in ["a.rs", "b.txt", "sub/c.rs", "sub/d.txt", "script"] {
+        std::fs::write(repo_path.join(path), "contents\n").unwrap();
+    }
+    test_env.jj_cmd_ok(&repo_path, &["chmod", "x", "script"]);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "glob:*.rs"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A a.rs
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", r#"glob:"**/*.rs""#]);
+    insta::assert_snapshot!(stdout, @r###"
+    A a.rs
+    A sub/c.rs
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "~sub ~ executable()"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A a.rs
+    A b.txt
+    "###);
+    let stdout =
+        test_env.jj_cmd_success(&repo_path, &["diff", "-s", "sub & glob:**/*.txt | b.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A b.txt
+    A sub/d.txt
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "file:sub", "executable()"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A script
+    "###);
+
+    // Paths relative to the current directory
+    let stdout = test_env.jj_cmd_success(
+        &repo_path.join("sub"),
+        &["diff", "-s", "glob:*.rs", "root:b.txt"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    A ../b.txt
+    A c.rs
+    "###);
+
+    // Values that aren't valid fileset expressions are literal paths
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "sub &", "globs:*.rs"]);
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_diff_literal_file_names() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    for path in ["foo.txt", "foo.txt~", "a:b", "x (1).png"] {
+        std::fs::write(repo_path.join(path), "contents\n").unwrap();
+    }
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "foo.txt~"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A foo.txt~
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "a:b", "x (1).png"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A a:b
+    A x (1).png
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["restore", "foo.txt~"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A a:b
+    A foo.txt
+    A x (1).png
+    "###);
+}
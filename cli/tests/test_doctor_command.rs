@@ -0,0 +1,166 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::{get_stderr_string, get_stdout_string, TestEnvironment};
+
+pub mod common;
+
+#[test]
+fn test_doctor_no_problems() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["doctor"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    No problems found.
+    "###);
+}
+
+#[test]
+fn test_doctor_stale_lock() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join(".jj/working_copy/working_copy.lock"), "").unwrap();
+    let (stdout, stderr) = doctor_failure(&test_env, &repo_path);
+    insta::assert_snapshot!(stdout, @r###"
+    Found a lock file that may be stale: $TEST_ENV/repo/.jj/working_copy/working_copy.lock
+    Fix: If no other jj process is running, an earlier one was probably interrupted. Delete the lock file.
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Found 1 problem
+    "###);
+}
+
+#[test]
+fn test_doctor_config_problems() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.colour = "always""#);
+
+    let (stdout, stderr) = doctor_failure(&test_env, &repo_path);
+    insta::assert_snapshot!(stdout, @r###"
+    Config problem: $TEST_ENV/config/config0002.toml:1: Unknown config key "ui.colour"
+    Fix: Did you mean "ui.color"?
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    $TEST_ENV/config/config0002.toml:1: Unknown config key "ui.colour"
+    Hint: Did you mean "ui.color"?
+    Error: Found 1 problem
+    "###);
+}
+
+#[test]
+fn test_doctor_large_files() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"snapshot.max-new-file-size = "10""#);
+
+    std::fs::write(repo_path.join(".gitignore"), "ignored\n").unwrap();
+    std::fs::write(repo_path.join("small"), "small").unwrap();
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir").join("large"), "a".repeat(100)).unwrap();
+    std::fs::create_dir(repo_path.join("ignored")).unwrap();
+    std::fs::write(repo_path.join("ignored").join("large"), "a".repeat(100)).unwrap();
+    let (stdout, stderr) = doctor_failure(&test_env, &repo_path);
+    insta::assert_snapshot!(stdout, @r###"
+    Untracked file dir/large won't be snapshotted because it's 100 bytes, more than `snapshot.max-new-file-size` (10 bytes)
+    Fix: Add it to your `.gitignore` file, or increase `snapshot.max-new-file-size` if you want it to be snapshotted.
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Found 1 problem
+    "###);
+}
+
+#[test]
+fn test_doctor_colocated_git() {
+    let test_env = TestEnvironment::default();
+    let repo_path = test_env.env_root().join("repo");
+    let git_repo = git2::Repository::init(&repo_path).unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["init", "--git-repo", "."]);
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "second"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main", "-r", "@-"]);
+    let (stdout, _stderr) = test_env.jj_cmd_ok(&repo_path, &["doctor"]);
+    insta::assert_snapshot!(stdout, @"");
+
+    // Move HEAD and create a branch behind jj's back
+    let head_commit = git_repo.head().unwrap().peel_to_commit().unwrap();
+    git_repo
+        .branch("feature", &head_commit.parent(0).unwrap(), false)
+        .unwrap();
+    git_repo
+        .set_head_detached(head_commit.parent(0).unwrap().id())
+        .unwrap();
+    let (stdout, stderr) = doctor_failure(&test_env, &repo_path);
+    insta::assert_snapshot!(stdout, @r###"
+    The HEAD of the colocated Git repo was moved, but hasn't been imported yet
+    Fix: Run a command that snapshots the working copy, such as `jj status`, to import it.
+    Git refs were changed in the colocated Git repo, but haven't been imported yet: refs/heads/feature
+    Fix: Run `jj git import`, or a command that snapshots the working copy, such as `jj status`.
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Found 2 problems
+    "###);
+
+    // Once imported, there are no problems
+    test_env.jj_cmd_ok(&repo_path, &["status"]);
+    let (stdout, _stderr) = test_env.jj_cmd_ok(&repo_path, &["doctor"]);
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_doctor_missing_objects() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "contents").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    let commit_id = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "--no-graph", "-r", "@-", "-T", "commit_id"],
+    );
+
+    let object_path = repo_path
+        .join(".jj/repo/store/git/objects")
+        .join(&commit_id[..2])
+        .join(&commit_id[2..]);
+    std::fs::remove_file(object_path).unwrap();
+    let (stdout, stderr) = doctor_failure(&test_env, &repo_path);
+    insta::assert_snapshot!(stdout, @r###"
+    Some commits can't be read from the store:
+      c819894241f5e6d015d1b25e56a8466bd295da70: Object c819894241f5e6d015d1b25e56a8466bd295da70 of type commit not found
+    Fix: The object files may have been deleted. Restore them from a backup or another clone of the repo.
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Found 1 problem
+    "###);
+}
+
+/// Runs `jj doctor`, checks that it found problems, and returns its stdout and
+/// stderr.
+fn doctor_failure(test_env: &TestEnvironment, repo_path: &Path) -> (String, String) {
+    let assert = test_env.jj_cmd(repo_path, &["doctor"]).assert().code(1);
+    (
+        test_env.normalize_output(&get_stdout_string(&assert)),
+        test_env.normalize_output(&get_stderr_string(&assert)),
+    )
+}
@@ -248,6 +248,100 @@ fn test_duplicate_many() {
 }
 
 // https://github.com/martinvonz/jj/issues/1050
+#[test]
+fn test_duplicate_destination() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    create_commit(&test_env, &repo_path, "c", &[]);
+    create_commit(&test_env, &repo_path, "d", &["c"]);
+
+    // Copy b and its parent onto d
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["duplicate", "a::b", "-d", "d"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Duplicated 2443ea76b0b1 as znkkpsqq 85119bda a
+    Duplicated 1394f625cbbd as uuzqqzqu 78cdf81f b
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    ◉  78cdf81f0cc6   b
+    ◉  85119bdac03f   a
+    @  b7c62f28ed10   d
+    ◉  fe2e8e8b50b3   c
+    │ ◉  1394f625cbbd   b
+    │ ◉  2443ea76b0b1   a
+    ├─╯
+    ◉  000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["diff", "--from", "d", "--to", "heads(d::)", "-s"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    A a
+    A b
+    "###);
+
+    // Copy b alone, without its parent
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["duplicate", "b", "-d", "d"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Duplicated 1394f625cbbd as lylxulpl 132504cf b
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    ◉  132504cfbfb3   b
+    @  b7c62f28ed10   d
+    ◉  fe2e8e8b50b3   c
+    │ ◉  1394f625cbbd   b
+    │ ◉  2443ea76b0b1   a
+    ├─╯
+    ◉  000000000000
+    "###);
+}
+
+#[test]
+fn test_duplicate_insert_after() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    create_commit(&test_env, &repo_path, "c", &[]);
+    create_commit(&test_env, &repo_path, "d", &["c"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["duplicate", "b", "-A", "c"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Duplicated 1394f625cbbd as znkkpsqq f342cf8b b
+    Rebased 1 commits
+    Working copy now at: vruxwmqv 91c5f1a8 d | d
+    Parent commit      : znkkpsqq f342cf8b b
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  91c5f1a8b303   d
+    ◉  f342cf8bd434   b
+    ◉  fe2e8e8b50b3   c
+    │ ◉  1394f625cbbd   b
+    │ ◉  2443ea76b0b1   a
+    ├─╯
+    ◉  000000000000
+    "###);
+
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["duplicate", "b", "-A", "c", "-d", "a"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--insert-after <INSERT_AFTER>' cannot be used with '--destination <DESTINATION>'
+
+    Usage: jj duplicate --insert-after <INSERT_AFTER> <REVISIONS>...
+
+    For more information, try '--help'.
+    "###);
+}
+
 #[test]
 fn test_undo_after_duplicate() {
     let test_env = TestEnvironment::default();
@@ -0,0 +1,91 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_eval() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "first"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "second"]);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["eval", "::@ ~ root()"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bc28fa9df1e531145bb9b91f6afc7c5ddf7f5861
+    69542c1984c1f9d91f7c6c9c9e6941782c944bd9
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["eval", "--change-id", "@", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    kkmpptxzrspxrzommnulwmwkkqwworpl
+    qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["eval", "-0", "::@ ~ root()"]);
+    insta::assert_snapshot!(stdout.replace('\0', "<NUL>"), @"bc28fa9df1e531145bb9b91f6afc7c5ddf7f5861<NUL>69542c1984c1f9d91f7c6c9c9e6941782c944bd9<NUL>");
+
+    // Templates are rendered without colors
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "eval",
+            "--color=always",
+            "-T",
+            "description",
+            "::@ ~ root()",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    second
+
+    first
+
+    "###);
+
+    // The log template doesn't matter
+    test_env.add_config(r#"templates.log = "'custom'""#);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["eval", "@"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bc28fa9df1e531145bb9b91f6afc7c5ddf7f5861
+    "###);
+
+    // An empty revset prints nothing
+    let stdout = test_env.jj_cmd_success(&repo_path, &["eval", "none()"]);
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_eval_errors() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["eval", "nonexistent"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1011]: Revision "nonexistent" doesn't exist
+    "###);
+
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["eval", "--change-id", "-T", "''", "@"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--change-id' cannot be used with '--template <TEMPLATE>'
+
+    Usage: jj eval --change-id <REVISIONS>...
+
+    For more information, try '--help'.
+    "###);
+}
@@ -0,0 +1,110 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_explain() {
+    let test_env = TestEnvironment::default();
+
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["explain"]);
+    insta::assert_snapshot!(stdout, @r###"
+    E1001: No jj repo found
+    E1002: The working copy is stale
+    E1010: Invalid revset expression
+    E1011: Revision doesn't exist
+    E1012: Would rewrite an immutable commit
+    E1013: Revset didn't resolve to any revisions
+    E1014: Revset resolved to more than one revision
+    E1015: Ambiguous id prefix
+    E1020: Branch doesn't exist
+    "###);
+
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["explain", "e1014"]);
+    insta::assert_snapshot!(stdout, @r###"
+    E1014: Revset resolved to more than one revision
+
+    The command needs a single revision, but the revset resolved to more than one.
+
+    Causes:
+
+    * The revset is a range or a filter, e.g. `main..@`.
+    * A branch is conflicted, so it points to several revisions.
+    * A change is divergent, so its change id refers to several commits.
+
+    Remedies:
+
+    * Pick one of the revisions, e.g. by its commit id.
+    * Resolve the conflicted branch with `jj branch set <NAME> -r <REVISION>`.
+    * Abandon all but one of the divergent commits with `jj abandon`.
+    * For commands that take several revisions, prefix the revset with `all:` to
+      allow any number of them.
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["explain", "E9999"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Unknown error code: E9999
+    Hint: Run `jj explain` to list the error codes.
+    "###);
+}
+
+#[test]
+fn test_error_code() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    // The code of the error is printed so it can be looked up
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1012]: The root commit 000000000000 is immutable
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["explain", "--color=always", "E1012"]);
+    insta::assert_snapshot!(stdout, @r###"
+    [1m[38;5;1mE1012[0m: Would rewrite an immutable commit
+
+    The command would rewrite a commit that is immutable. The root commit is always
+    immutable, as are the ancestors of the `immutable_heads()` revset, which by
+    default includes the trunk and tagged commits.
+
+    Causes:
+
+    * The revision passed to the command is immutable, e.g. a commit that has been
+      pushed to the trunk.
+    * The command rebases descendants of the revision, and some of them are
+      immutable.
+
+    Remedies:
+
+    * Make a new commit on top of the immutable one with `jj new` instead.
+    * Change the set of immutable commits with `revset-aliases.immutable_heads()`.
+    * Pass `--ignore-immutable` to rewrite the commit anyway.
+
+    See https://github.com/martinvonz/jj/blob/main/docs/config.md#set-of-immutable-commits.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()", "--color=always"]);
+    insta::assert_snapshot!(stderr, @r###"
+    [38;5;1mError[E1012]: The root commit 000000000000 is immutable[39m
+    "###);
+
+    // Errors without a code are printed as before
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "foo"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "create", "foo"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Branch already exists: foo
+    Hint: Use `jj branch set` to update it.
+    "###);
+}
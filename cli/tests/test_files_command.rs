@@ -0,0 +1,92 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_files() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::create_dir_all(repo_path.join("dir/sub")).unwrap();
+    std::fs::write(repo_path.join("file"), "content\n").unwrap();
+    std::fs::write(repo_path.join("dir/a"), "a\n").unwrap();
+    std::fs::write(repo_path.join("dir/sub/b"), "bb\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files"]);
+    insta::assert_snapshot!(stdout, @r###"
+    dir/a
+    dir/sub/b
+    file
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "--tree"]);
+    insta::assert_snapshot!(stdout, @r###"
+    dir/
+    dir/a
+    dir/sub/
+    dir/sub/b
+    file
+    "###);
+
+    // Paths are relative to the current directory
+    let stdout = test_env.jj_cmd_success(&repo_path.join("dir"), &["files", "--tree", "sub"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ./
+    sub/
+    sub/b
+    "###);
+}
+
+#[test]
+fn test_files_template() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("file"), "content\n").unwrap();
+    std::fs::write(repo_path.join("dir/a"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["chmod", "x", "dir/a"]);
+
+    let template = r#"separate(" ", path, type, executable, size, blob_id) ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "--tree", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    dir directory false 0 5159024df6da88f428feb8652b261de7eecc5522
+    dir/a file true 2 78981922613b2afb6025042ff6bd878ac1994e85
+    file file false 8 d95f3ad14dee633a758d2e331151e950dd13e4ed
+    "###);
+
+    // Conflicted files have no blob id
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "left"]);
+    std::fs::write(repo_path.join("file"), "left\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "right", "@-"]);
+    std::fs::write(repo_path.join("file"), "right\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new", "description(left)", "@"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "-T", template, "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    file conflict false 0
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["files", "-T", "description"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse template:  --> 1:1
+      |
+    1 | description
+      | ^---------^
+      |
+      = Keyword "description" doesn't exist
+    "###);
+}
@@ -0,0 +1,371 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Configures the fake formatter as the fix tool `name`, running with `args`
+/// on the files matching `patterns`.
+fn add_fake_formatter(test_env: &TestEnvironment, name: &str, args: &[&str], patterns: &[&str]) {
+    let formatter_path = assert_cmd::cargo::cargo_bin("fake-formatter");
+    assert!(formatter_path.is_file());
+    let command = [formatter_path.to_str().unwrap()]
+        .iter()
+        .chain(args)
+        .map(|arg| format!("{:?}", arg))
+        .join(", ");
+    let patterns = patterns
+        .iter()
+        .map(|pattern| format!("{:?}", pattern))
+        .join(", ");
+    test_env.add_config(&format!(
+        r#"
+        fix.tools.{name}.command = [{command}]
+        fix.tools.{name}.patterns = [{patterns}]
+        "#
+    ));
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    test_env.jj_cmd_success(repo_path, &["log", "-T", "description"])
+}
+
+#[test]
+fn test_fix_no_tools() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No fix tools are configured. Add one in `fix.tools.<name>`.
+    "###);
+}
+
+#[test]
+fn test_fix_stack() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    add_fake_formatter(&test_env, "upper", &["--uppercase"], &["*.txt"]);
+
+    std::fs::write(repo_path.join("a.txt"), "a\n").unwrap();
+    std::fs::write(repo_path.join("skipped.md"), "skipped\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "a"]);
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir/b.txt"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "b"]);
+    std::fs::write(repo_path.join("a.txt"), "a\nc\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "c"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["fix", "-s", "description(a)"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Fixed 3 commits of 3 checked.
+    Working copy now at: kkmpptxz d7c643db c
+    Parent commit      : rlvkpnrz 9317a590 b
+    Added 0 files, modified 2 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  c
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+
+    // The fixes of the parents are kept in the descendants
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "description(a)", "a.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "description(b)", "a.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "description(b)", "dir/b.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    B
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "a.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A
+    C
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "skipped.md"]);
+    insta::assert_snapshot!(stdout, @r###"
+    skipped
+    "###);
+    // The working copy was updated
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("a.txt")).unwrap(), @r###"
+    A
+    C
+    "###);
+
+    // Nothing left to fix
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["fix", "-s", "description(a)"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Fixed 0 commits of 3 checked.
+    Nothing changed.
+    "###);
+}
+
+#[test]
+fn test_fix_runs_each_input_once() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let log_path = test_env.env_root().join("invocations");
+    add_fake_formatter(
+        &test_env,
+        "logged",
+        &[
+            "--append",
+            "fixed",
+            "--path",
+            "$path",
+            "--log",
+            log_path.to_str().unwrap(),
+        ],
+        &["*"],
+    );
+
+    // The same content of "shared" is in every commit, and "copy" has the same
+    // content at another path
+    std::fs::write(repo_path.join("shared"), "shared\n").unwrap();
+    std::fs::write(repo_path.join("copy"), "shared\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "1"]);
+    for i in 2..=5 {
+        std::fs::write(repo_path.join(format!("file{i}")), "content\n").unwrap();
+        test_env.jj_cmd_ok(&repo_path, &["commit", "-m", &i.to_string()]);
+    }
+
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["fix", "-s", "description(1)", "--jobs", "2"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Fixed 6 commits of 6 checked.
+    Working copy now at: royxmykx dc0bcc82 (empty) (no description set)
+    Parent commit      : mzvwutvl 500d6a93 5
+    Added 0 files, modified 6 files, removed 0 files
+    "###);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations.lines().sorted().join("\n"), @r###"
+    copy
+    file2
+    file3
+    file4
+    file5
+    shared
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "description(5)", "shared"]);
+    insta::assert_snapshot!(stdout, @r###"
+    shared
+    fixed
+    "###);
+}
+
+#[test]
+fn test_fix_caches_results() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let log_path = test_env.env_root().join("invocations");
+    let log_path_str = log_path.to_str().unwrap();
+    add_fake_formatter(
+        &test_env,
+        "logged",
+        &[
+            "--append",
+            "fixed",
+            "--path",
+            "$path",
+            "--log",
+            log_path_str,
+        ],
+        &["*"],
+    );
+
+    std::fs::write(repo_path.join("file"), "content\n").unwrap();
+    // Without a cache key, the tool runs every time
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations, @r###"
+    file
+    file
+    "###);
+
+    // With a cache key, the same content is fixed again from the cache, without
+    // running the tool
+    test_env.add_config(r#"fix.tools.logged.cache-key = "v1""#);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Fixed 1 commits of 1 checked.
+    Working copy now at: qpvuntsm afd2dea6 (no description set)
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations, @r###"
+    file
+    file
+    file
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    content
+    fixed
+    "###);
+
+    // Changing the cache key invalidates the cache
+    test_env.add_config(r#"fix.tools.logged.cache-key = "v2""#);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations, @r###"
+    file
+    file
+    file
+    file
+    "###);
+
+    // So does changing the tool
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    add_fake_formatter(
+        &test_env,
+        "logged",
+        &[
+            "--append",
+            "other",
+            "--path",
+            "$path",
+            "--log",
+            log_path_str,
+        ],
+        &["*"],
+    );
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations, @r###"
+    file
+    file
+    file
+    file
+    file
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    content
+    other
+    "###);
+
+    // `jj util gc` clears the cache
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "gc", "--expire=now"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Removed unreachable objects: 7 index files, 3 cached fix results
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    let invocations = std::fs::read_to_string(&log_path).unwrap();
+    insta::assert_snapshot!(invocations, @r###"
+    file
+    file
+    file
+    file
+    file
+    file
+    "###);
+}
+
+#[test]
+fn test_fix_tool_failure() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    add_fake_formatter(&test_env, "a-upper", &["--uppercase"], &["*"]);
+    add_fake_formatter(
+        &test_env,
+        "b-broken",
+        &["--fail", "can't parse"],
+        &["*.bad"],
+    );
+    add_fake_formatter(&test_env, "c-append", &["--append", "end"], &["*"]);
+
+    std::fs::write(repo_path.join("file.bad"), "bad\n").unwrap();
+    std::fs::write(repo_path.join("file.good"), "good\n").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Fix tool `b-broken` failed on file.bad:
+    can't parse
+    Fixed 1 commits of 1 checked.
+    Working copy now at: qpvuntsm d8db4553 (no description set)
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    Added 0 files, modified 2 files, removed 0 files
+    "###);
+    // The tools after the failed one still run
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file.bad"]);
+    insta::assert_snapshot!(stdout, @r###"
+    BAD
+    end
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file.good"]);
+    insta::assert_snapshot!(stdout, @r###"
+    GOOD
+    end
+    "###);
+}
+
+#[test]
+fn test_fix_paths() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    add_fake_formatter(&test_env, "upper", &["--uppercase"], &["*"]);
+
+    std::fs::write(repo_path.join("file1"), "one\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "two\n").unwrap();
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["fix", "file1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Fixed 1 commits of 1 checked.
+    Working copy now at: qpvuntsm 0ad92dd4 (no description set)
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--summary"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file1
+    A file2
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file1")).unwrap(), @r###"
+    ONE
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file2")).unwrap(), @r###"
+    two
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["fix", "--jobs", "0"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: must pass at least one job
+    "###);
+}
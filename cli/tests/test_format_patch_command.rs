@@ -0,0 +1,116 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_format_patch() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Add file"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["new", "-m", "Update file\n\nThe body of the café.\n"],
+    );
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["format-patch", "-r", "root()..@-", "-o", "patches"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    patches/0001-Add-file.patch
+    patches/0002-Update-file.patch
+    "###);
+    let read_patch =
+        |name: &str| std::fs::read_to_string(repo_path.join("patches").join(name)).unwrap();
+    insta::assert_snapshot!(read_patch("0001-Add-file.patch"), @r###"
+    From 8848c00371a49c4c430ef6cb3fb6778a8bdc8fea Mon Sep 17 00:00:00 2001
+    From: Test User <test.user@example.com>
+    Date: Sat, 3 Feb 2001 04:05:07 +0700
+    Subject: [PATCH 1/2] Add file
+
+    Change-Id: qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    ---
+    file | 2 ++
+    1 file changed, 2 insertions(+), 0 deletions(-)
+
+    diff --git a/file b/file
+    new file mode 100644
+    index 0000000000..422c2b7ab3
+    --- /dev/null
+    +++ b/file
+    @@ -1,0 +1,2 @@
+    +a
+    +b
+    "###);
+    insta::assert_snapshot!(read_patch("0002-Update-file.patch"), @r###"
+    From 57e9098f72056d0d976cfc40b1588db9177a45ab Mon Sep 17 00:00:00 2001
+    From: Test User <test.user@example.com>
+    Date: Sat, 3 Feb 2001 04:05:09 +0700
+    Subject: [PATCH 2/2] Update file
+    MIME-Version: 1.0
+    Content-Type: text/plain; charset=UTF-8
+    Content-Transfer-Encoding: 8bit
+
+    The body of the café.
+
+    Change-Id: kkmpptxzrspxrzommnulwmwkkqwworpl
+    ---
+    file | 2 +-
+    1 file changed, 1 insertion(+), 1 deletion(-)
+
+    diff --git a/file b/file
+    index 422c2b7ab3...0f7bc76605 100644
+    --- a/file
+    +++ b/file
+    @@ -1,2 +1,2 @@
+     a
+    -b
+    +c
+    "###);
+
+    // A single patch is written to the current directory by default
+    let stdout = test_env.jj_cmd_success(&repo_path, &["format-patch", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    0001-Update-file.patch
+    "###);
+    let patch = std::fs::read_to_string(repo_path.join("0001-Update-file.patch")).unwrap();
+    insta::assert_snapshot!(patch.lines().nth(3).unwrap(), @"Subject: [PATCH] Update file");
+
+    // Merge commits are skipped
+    test_env.jj_cmd_ok(&repo_path, &["new", "@--", "@-", "-m", "merge"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["format-patch", "-r", "@ | @-", "-o", "patches"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    patches/0001-Add-file.patch
+    patches/0002-Update-file.patch
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Skipping merge commit 244447b7802f
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["format-patch", "-r", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot format the root commit as a patch
+    "###);
+}
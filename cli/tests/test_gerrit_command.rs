@@ -0,0 +1,164 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn set_up() -> (TestEnvironment, PathBuf) {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("origin.git");
+    git2::Repository::init_bare(&git_repo_path).unwrap();
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", git_repo_path.to_str().unwrap(), "repo"],
+    );
+    test_env.add_config(r#"gerrit.default-branch = "main""#);
+    let repo_path = test_env.env_root().join("repo");
+    (test_env, repo_path)
+}
+
+/// Lists the refs in the bare repo that the changes were pushed to, with the
+/// subjects of the commits they point to.
+fn get_review_refs(test_env: &TestEnvironment) -> String {
+    let git_repo = git2::Repository::open(test_env.env_root().join("origin.git")).unwrap();
+    let mut lines = vec![];
+    for reference in git_repo.references_glob("refs/for/*").unwrap() {
+        let reference = reference.unwrap();
+        let commit = reference.peel_to_commit().unwrap();
+        lines.push(format!(
+            "{} {}",
+            reference.name().unwrap(),
+            commit.summary().unwrap()
+        ));
+    }
+    lines.sort();
+    lines.join("\n")
+}
+
+#[test]
+fn test_gerrit_send() {
+    let (test_env, repo_path) = set_up();
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "First"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Second\n\nBug: 123"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "gerrit",
+            "send",
+            "-r",
+            "@-",
+            "--topic",
+            "feature",
+            "--reviewer",
+            "a@example.com",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Added Change-Id to 2 commits
+    Rebased 1 descendant commits
+    Changes to send to origin for review on main:
+      rlvkpnrz e0a79a7c Second
+      qpvuntsm 0c4abb1a First
+    Working copy now at: kkmpptxz 63b823e0 (empty) (no description set)
+    Parent commit      : rlvkpnrz e0a79a7c Second
+    "###);
+    insta::assert_snapshot!(get_review_refs(&test_env), @"refs/for/main%topic=feature,r=a@example.com Second");
+    let template = r#"separate(" ", change_id.short(), description) ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  kkmpptxzrspx
+    ◉  rlvkpnrzqnoo Second
+    │
+    │  Bug: 123
+    │  Change-Id: I6a6a69648e4fac809cbb3b162c953458183c8dea
+    │
+    ◉  qpvuntsmwlqt First
+    │
+    │  Change-Id: I6a6a69649a45c67d3e96a7e5007c110ede34dec5
+    │
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    // An existing Change-Id is kept
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "@--", "-m", "First\n\nChange-Id: I1234"],
+    );
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["gerrit", "send", "-r", "@-", "-b", "other"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Changes to send to origin for review on other:
+      rlvkpnrz 55d41eb1 Second
+      qpvuntsm 0a6e267a First
+    "###);
+    insta::assert_snapshot!(get_review_refs(&test_env), @r###"
+    refs/for/main%topic=feature,r=a@example.com Second
+    refs/for/other Second
+    "###);
+}
+
+#[test]
+fn test_gerrit_send_errors() {
+    let (test_env, repo_path) = set_up();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["gerrit", "send"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Won't push commits without a description:
+      qpvuntsm 230dd059 (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, or set `git.push-guard.undescribed` to false.
+    "###);
+
+    // Nothing is pushed or rewritten in a dry run
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "First"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["gerrit", "send", "--dry-run"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Added Change-Id to 1 commits
+    Changes to send to origin for review on main:
+      qpvuntsm 0c4abb1a First
+    Dry-run requested, not pushing.
+    "###);
+    insta::assert_snapshot!(get_review_refs(&test_env), @"");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r", "@", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  First
+    │
+    ~
+    "###);
+
+    // Nothing is pushed in read-only mode
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["gerrit", "send", "--read-only"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot modify the repo in read-only mode.
+    Hint: Don't use --read-only.
+    "###);
+    insta::assert_snapshot!(get_review_refs(&test_env), @"");
+
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["gerrit", "send"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No branch to send the changes for
+    Hint: Give the branch on the command line, or set gerrit.default-branch.
+    "###);
+}
@@ -361,6 +361,121 @@ fn test_git_clone_remote_default_branch() {
     "###);
 }
 
+#[test]
+fn test_git_clone_branch() {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("source");
+    let git_repo = git2::Repository::init(git_repo_path).unwrap();
+    set_up_non_empty_git_repo(&git_repo);
+    // Create a non-default branch with its own commit
+    let signature =
+        git2::Signature::new("Some One", "some.one@example.com", &git2::Time::new(0, 0)).unwrap();
+    let main_commit = git_repo
+        .find_reference("refs/heads/main")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    git_repo
+        .commit(
+            Some("refs/heads/feature1"),
+            &signature,
+            &signature,
+            "feature",
+            &main_commit.tree().unwrap(),
+            &[&main_commit],
+        )
+        .unwrap();
+
+    // The requested branch is checked out, and all branches are fetched
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", "source", "clone1", "--branch", "feature1"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Fetching into new repo in "$TEST_ENV/clone1"
+    Working copy now at: sqpuoqvx e349bafa (empty) (no description set)
+    Parent commit      : pplwkrsk 8b94e68d feature1 | (empty) feature
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(
+        get_branch_output(&test_env, &test_env.env_root().join("clone1")), @r###"
+    feature1: pplwkrsk 8b94e68d (empty) feature
+      @origin: pplwkrsk 8b94e68d (empty) feature
+    main@origin: mzyxwzks 9f01a0e0 message
+    "###);
+
+    // Only the requested branch is fetched
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &[
+            "git",
+            "clone",
+            "source",
+            "clone2",
+            "--branch",
+            "feature1",
+            "--single-branch",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Fetching into new repo in "$TEST_ENV/clone2"
+    Working copy now at: pmmvwywv 70dc62d5 (empty) (no description set)
+    Parent commit      : pplwkrsk 8b94e68d feature1 | (empty) feature
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(
+        get_branch_output(&test_env, &test_env.env_root().join("clone2")), @r###"
+    feature1: pplwkrsk 8b94e68d (empty) feature
+      @origin: pplwkrsk 8b94e68d (empty) feature
+    "###);
+    let clone_git_repo =
+        git2::Repository::open(test_env.env_root().join("clone2/.jj/repo/store/git")).unwrap();
+    let refspecs = clone_git_repo
+        .find_remote("origin")
+        .unwrap()
+        .fetch_refspecs()
+        .unwrap()
+        .iter()
+        .map(|refspec| refspec.unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        refspecs,
+        ["+refs/heads/feature1:refs/remotes/origin/feature1"]
+    );
+
+    // A branch that doesn't exist on the remote
+    let stderr = test_env.jj_cmd_failure(
+        test_env.env_root(),
+        &[
+            "git",
+            "clone",
+            "source",
+            "clone3",
+            "--branch",
+            "nonexistent",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Fetching into new repo in "$TEST_ENV/clone3"
+    Error: Branch nonexistent doesn't exist on the remote
+    "###);
+    assert!(!test_env.env_root().join("clone3").exists());
+
+    // --single-branch needs --branch
+    let stderr = test_env.jj_cmd_cli_error(
+        test_env.env_root(),
+        &["git", "clone", "source", "clone4", "--single-branch"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    error: the following required arguments were not provided:
+      --branch <BRANCH>
+
+    Usage: jj git clone --branch <BRANCH> --single-branch <SOURCE> <DESTINATION>
+
+    For more information, try '--help'.
+    "###);
+}
+
 fn get_branch_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     test_env.jj_cmd_success(repo_path, &["branch", "list", "--all"])
 }
@@ -208,6 +208,43 @@ fn test_git_fetch_all_remotes() {
     "###);
 }
 
+#[test]
+fn test_git_fetch_deepen() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config("git.auto-local-branch = true");
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    add_git_remote(&test_env, &repo_path, "rem1");
+
+    let stderr = test_env.jj_cmd_cli_error(
+        &repo_path,
+        &["git", "fetch", "--depth", "1", "--deepen", "1"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--depth <DEPTH>' cannot be used with '--deepen <DEEPEN>'
+
+    Usage: jj git fetch --depth <DEPTH>
+
+    For more information, try '--help'.
+    "###);
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["git", "fetch", "--depth", "0"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value '0' for '--depth <DEPTH>': number would be zero for non-zero type
+
+    For more information, try '--help'.
+    "###);
+
+    // Deepening a repo that isn't shallow fetches everything
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["git", "fetch", "--remote", "rem1", "--deepen", "1"],
+    );
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
+    rem1: qxosxrvv 6a211027 message
+      @rem1: qxosxrvv 6a211027 message
+    "###);
+}
+
 #[test]
 fn test_git_fetch_multiple_remotes_from_config() {
     let test_env = TestEnvironment::default();
@@ -1105,6 +1142,48 @@ fn test_git_fetch_removed_branch() {
     "###);
 }
 
+#[test]
+fn test_git_fetch_prune_merged_branches() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config("git.auto-local-branch = true");
+    test_env.add_config("git.prune-merged-branches = true");
+    let source_git_repo_path = test_env.env_root().join("source");
+    let _git_repo = git2::Repository::init(source_git_repo_path.clone()).unwrap();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "clone", "source", "target"]);
+    let target_jj_repo_path = test_env.env_root().join("target");
+    create_colocated_repo_and_branches_from_trunk1(&test_env, &source_git_repo_path);
+    test_env.jj_cmd_ok(&target_jj_repo_path, &["git", "fetch"]);
+
+    // Move a1 to a merged commit, and b to a commit that isn't on the remote
+    test_env.jj_cmd_ok(
+        &target_jj_repo_path,
+        &["branch", "set", "--allow-backwards", "a1", "-r", "trunk1"],
+    );
+    test_env.jj_cmd_ok(&target_jj_repo_path, &["new", "b", "-m", "local b"]);
+    test_env.jj_cmd_ok(&target_jj_repo_path, &["branch", "set", "b"]);
+
+    // Remove all but trunk1 in origin
+    for branch in ["a1", "a2", "b"] {
+        test_env.jj_cmd_ok(&source_git_repo_path, &["branch", "forget", branch]);
+    }
+
+    // a2 is deleted because it was unchanged, a1 because its commits are
+    // merged, and b is kept
+    let (stdout, stderr) = test_env.jj_cmd_ok(&target_jj_repo_path, &["git", "fetch"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Abandoned 2 commits that are no longer reachable.
+    Deleted branch a1: a1@origin was deleted and its commits are merged
+    "###);
+    insta::assert_snapshot!(get_branch_output(&test_env, &target_jj_repo_path), @r###"
+    b (conflicted):
+      - vpupmnsl c7d4bdcb descr_for_b
+      + lylxulpl e09ff18e (empty) local b
+    trunk1: zowqyktl ff36dc55 descr_for_trunk1
+      @origin: zowqyktl ff36dc55 descr_for_trunk1
+    "###);
+}
+
 #[test]
 fn test_git_fetch_removed_parent_branch() {
     let test_env = TestEnvironment::default();
@@ -114,7 +114,7 @@ fn test_git_export_undo() {
     ]
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_failure(&repo_path, &["log", "-ra@git"]), @r###"
-    Error: Revision "a@git" doesn't exist
+    Error[E1011]: Revision "a@git" doesn't exist
     Hint: Did you mean "a"?
     "###);
 
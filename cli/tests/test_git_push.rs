@@ -97,6 +97,9 @@ fn test_git_push_current_branch() {
     Branch changes to push to origin:
       Move branch branch2 from 8476341eb395 to 10ee3363b259
       Add branch my-branch to 10ee3363b259
+    Pushed to origin:
+      branch2: 8476341eb395 -> 10ee3363b259
+      my-branch: (new) -> 10ee3363b259
     "###);
     let stdout = test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]);
     insta::assert_snapshot!(stdout, @r###"
@@ -125,6 +128,8 @@ fn test_git_push_parent_branch() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Force branch branch1 from 45a3aa29e907 to d47326d59ee1
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> d47326d59ee1 (forced)
     "###);
 }
 
@@ -185,6 +190,8 @@ fn test_git_push_other_remote_has_branch() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Force branch branch1 from 45a3aa29e907 to 50421a29358a
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> 50421a29358a (forced)
     "###);
     // Since it's already pushed to origin, nothing will happen if push again
     let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_root, &["git", "push"]);
@@ -199,6 +206,8 @@ fn test_git_push_other_remote_has_branch() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to other:
       Add branch branch1 to 50421a29358a
+    Pushed to other:
+      branch1: (new) -> 50421a29358a
     "###);
 }
 
@@ -245,6 +254,8 @@ fn test_git_push_locally_created_and_rewritten() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Add branch my to fcc999921ce9
+    Pushed to origin:
+      my: (new) -> fcc999921ce9
     "###);
 
     // Rewrite it and push again, which would fail if the pushed branch weren't
@@ -263,6 +274,8 @@ fn test_git_push_locally_created_and_rewritten() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Force branch my from fcc999921ce9 to bde1d2e44b2a
+    Pushed to origin:
+      my: fcc999921ce9 -> bde1d2e44b2a (forced)
     "###);
 }
 
@@ -345,7 +358,7 @@ fn test_git_push_multiple() {
     // Unmatched branch name is error
     let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "-b=foo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: No such branch: foo
+    Error[E1020]: No such branch: foo
     "###);
     let stderr = test_env.jj_cmd_failure(
         &workspace_root,
@@ -362,6 +375,10 @@ fn test_git_push_multiple() {
       Delete branch branch1 from 45a3aa29e907
       Force branch branch2 from 8476341eb395 to 15dcdaa4f12f
       Add branch my-branch to 15dcdaa4f12f
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> (deleted)
+      branch2: 8476341eb395 -> 15dcdaa4f12f (forced)
+      my-branch: (new) -> 15dcdaa4f12f
     "###);
     let stdout = test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]);
     insta::assert_snapshot!(stdout, @r###"
@@ -396,6 +413,8 @@ fn test_git_push_changes() {
     Creating branch push-yostqsxwqrlt for revision @
     Branch changes to push to origin:
       Add branch push-yostqsxwqrlt to 28d7620ea63a
+    Pushed to origin:
+      push-yostqsxwqrlt: (new) -> 28d7620ea63a
     "###);
     // test pushing two changes at once
     std::fs::write(workspace_root.join("file"), "modified2").unwrap();
@@ -406,6 +425,9 @@ fn test_git_push_changes() {
     Branch changes to push to origin:
       Force branch push-yostqsxwqrlt from 28d7620ea63a to 48d8c7948133
       Add branch push-yqosqzytrlsw to fa16a14170fb
+    Pushed to origin:
+      push-yostqsxwqrlt: 28d7620ea63a -> 48d8c7948133 (forced)
+      push-yqosqzytrlsw: (new) -> fa16a14170fb
     "###);
     // specifying the same change twice doesn't break things
     std::fs::write(workspace_root.join("file"), "modified3").unwrap();
@@ -414,6 +436,8 @@ fn test_git_push_changes() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Force branch push-yostqsxwqrlt from 48d8c7948133 to b5f030322b1d
+    Pushed to origin:
+      push-yostqsxwqrlt: 48d8c7948133 -> b5f030322b1d (forced)
     "###);
     // Test changing `git.push-branch-prefix`. It causes us to push again.
     let (stdout, stderr) = test_env.jj_cmd_ok(
@@ -431,6 +455,64 @@ fn test_git_push_changes() {
     Creating branch test-yostqsxwqrlt for revision @
     Branch changes to push to origin:
       Add branch test-yostqsxwqrlt to b5f030322b1d
+    Pushed to origin:
+      test-yostqsxwqrlt: (new) -> b5f030322b1d
+    "###);
+}
+
+#[test]
+fn test_git_push_changes_with_branch_template() {
+    let (test_env, workspace_root) = set_up();
+    test_env.jj_cmd_ok(&workspace_root, &["describe", "-m", "foo"]);
+    std::fs::write(workspace_root.join("file"), "contents").unwrap();
+    test_env.add_config(
+        r#"git.push-branch-template = '"users/" ++ author.username() ++ "/" ++ change_id.short(8)'"#,
+    );
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_root, &["git", "push", "--change=@"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch users/test.user/yqosqzyt for revision @
+    Branch changes to push to origin:
+      Add branch users/test.user/yqosqzyt to fa16a14170fb
+    Pushed to origin:
+      users/test.user/yqosqzyt: (new) -> fa16a14170fb
+    "###);
+
+    // A template set for the remote takes precedence
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--config-toml",
+            r#"git.remotes.origin.push-branch-template = '"review/" ++ change_id.short()'"#,
+            "--change=@",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch review/yqosqzytrlsw for revision @
+    Branch changes to push to origin:
+      Add branch review/yqosqzytrlsw to fa16a14170fb
+    Pushed to origin:
+      review/yqosqzytrlsw: (new) -> fa16a14170fb
+    "###);
+
+    // The generated name must be usable as a branch name
+    let stderr = test_env.jj_cmd_failure(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--config-toml",
+            r#"git.push-branch-template = '"my " ++ description'"#,
+            "--change=@",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid branch name "my foo" generated for commit fa16a14170fb
+    Hint: Check the template in `git.push-branch-template`.
     "###);
 }
 
@@ -527,6 +609,11 @@ fn test_git_push_mixed() {
       Add branch push-yqosqzytrlsw to fa16a14170fb
       Add branch branch-2a to 1b45449e18d0
       Add branch branch-2b to 1b45449e18d0
+    Pushed to origin:
+      branch-1: (new) -> 7decc7932d9c
+      push-yqosqzytrlsw: (new) -> fa16a14170fb
+      branch-2a: (new) -> 1b45449e18d0
+      branch-2b: (new) -> 1b45449e18d0
     "###);
 }
 
@@ -545,6 +632,8 @@ fn test_git_push_existing_long_branch() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Add branch push-19b790168e73f7a73a98deae21e807c0 to fa16a14170fb
+    Pushed to origin:
+      push-19b790168e73f7a73a98deae21e807c0: (new) -> fa16a14170fb
     "###);
 }
 
@@ -571,7 +660,19 @@ fn test_git_push_conflict() {
     test_env.jj_cmd_ok(&workspace_root, &["describe", "-m", "third"]);
     let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--all"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Won't push commit 1973d389875c since it has conflicts
+    Won't push commits with conflicts:
+      yostqsxw 1973d389 my-branch | (conflict) third
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, pass `--allow-conflicts`, or set `git.push-guard.conflicted` to false.
+    "###);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "push", "--all", "--allow-conflicts", "--dry-run"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch my-branch to 1973d389875c
+    Dry-run requested, not pushing.
     "###);
 }
 
@@ -583,7 +684,109 @@ fn test_git_push_no_description() {
     let stderr =
         test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch", "my-branch"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Won't push commit 5b36783cd11c since it has no description
+    Won't push commits without a description:
+      yqosqzyt 5b36783c my-branch | (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, pass `--allow-empty-description`, or set `git.push-guard.undescribed` to false.
+    "###);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--branch",
+            "my-branch",
+            "--allow-empty-description",
+            "--dry-run",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch my-branch to 5b36783cd11c
+    Dry-run requested, not pushing.
+    "###);
+
+    // The check can be turned off in the config
+    test_env.add_config("git.push-guard.undescribed = false");
+    test_env.jj_cmd_ok(&workspace_root, &["git", "push", "--branch", "my-branch"]);
+}
+
+#[test]
+fn test_git_push_empty() {
+    let (test_env, workspace_root) = set_up();
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1", "-m=empty"]);
+    test_env.jj_cmd_ok(&workspace_root, &["new", "-m="]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "my-branch"]);
+    // Empty commits can be pushed by default
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--branch",
+            "my-branch",
+            "--allow-empty-description",
+            "--dry-run",
+        ],
+    );
+
+    // All the commits that are refused are listed
+    test_env.add_config("git.push-guard.empty = true");
+    let stderr =
+        test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch", "my-branch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Won't push empty commits:
+      yostqsxw e54a5ba4 my-branch | (empty) (no description set)
+      vruxwmqv c1073574 (empty) empty
+    Won't push commits without a description:
+      yostqsxw e54a5ba4 my-branch | (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, pass `--allow-empty --allow-empty-description`, or set `git.push-guard.empty` and `git.push-guard.undescribed` to false.
+    "###);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--branch",
+            "my-branch",
+            "--allow-empty",
+            "--allow-empty-description",
+            "--dry-run",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch my-branch to e54a5ba42f3d
+    Dry-run requested, not pushing.
+    "###);
+}
+
+#[test]
+fn test_git_push_block_revset() {
+    let (test_env, workspace_root) = set_up();
+    test_env.add_config(r#"git.push-block-revset = 'description(glob:"wip:*")'"#);
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1", "-m=wip: first"]);
+    test_env.jj_cmd_ok(&workspace_root, &["new", "-m=second"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "my-branch"]);
+    let stderr =
+        test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch", "my-branch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    These commits match `git.push-block-revset`:
+      vruxwmqv c3500bfa (empty) wip: first
+    Error: Won't push commits that match `git.push-block-revset`
+    Hint: Rewrite the commits so that they don't match, or change the config.
+    "###);
+
+    // Once the commit no longer matches, it can be pushed
+    test_env.jj_cmd_ok(&workspace_root, &["describe", "@-", "-m=first"]);
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&workspace_root, &["git", "push", "--branch", "my-branch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch my-branch to d0749b69332e
+    Pushed to origin:
+      my-branch: (new) -> d0749b69332e
     "###);
 }
 
@@ -641,13 +844,12 @@ fn test_git_push_missing_committer() {
     Error: Won't push commit eab97428a6ec since it has no author and/or committer set
     "###);
 
-    // Test message when there are multiple reasons (missing committer and
-    // description)
+    // The missing committer is reported before the missing description
     run_without_var("JJ_EMAIL", &["describe", "-m=", "missing-email"]);
     let stderr =
         test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch=missing-email"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Won't push commit 1143ed607f54 since it has no description and it has no author and/or committer set
+    Error: Won't push commit 1143ed607f54 since it has no author and/or committer set
     "###);
 }
 
@@ -661,6 +863,8 @@ fn test_git_push_deleted() {
     insta::assert_snapshot!(stderr, @r###"
     Branch changes to push to origin:
       Delete branch branch1 from 45a3aa29e907
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> (deleted)
     "###);
     let stdout = test_env.jj_cmd_success(&workspace_root, &["log", "-rall()"]);
     insta::assert_snapshot!(stdout, @r###"
@@ -739,6 +943,8 @@ fn test_git_push_conflicting_branches() {
     Hint: Run `jj branch list` to inspect, and use `jj branch set` to fix it up.
     Branch changes to push to origin:
       Move branch branch1 from 45a3aa29e907 to fd1d63e031ea
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> fd1d63e031ea
     "###);
 
     // --revisions shouldn't be blocked by conflicting branch
@@ -750,6 +956,78 @@ fn test_git_push_conflicting_branches() {
     Hint: Run `jj branch list` to inspect, and use `jj branch set` to fix it up.
     Branch changes to push to origin:
       Move branch branch1 from fd1d63e031ea to 8263cf992d33
+    Pushed to origin:
+      branch1: fd1d63e031ea -> 8263cf992d33
+    "###);
+}
+
+#[test]
+fn test_git_push_allow_deny() {
+    let (test_env, workspace_root) = set_up();
+    test_env.add_config(
+        r#"
+        git.push-allow = ["glob:branch*", "glob:push-*"]
+        git.push-deny = ["branch2"]
+        "#,
+    );
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1", "-m=bump"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "set", "branch1"]);
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &["branch", "set", "--allow-backwards", "branch2"],
+    );
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "other"]);
+
+    // --branch should be blocked by a denied branch
+    let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch", "branch2"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Branch branch2 is not allowed to be pushed
+    Hint: It matches a pattern in `git.push-deny`.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch", "other"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Branch other is not allowed to be pushed
+    Hint: It doesn't match any pattern in `git.push-allow`.
+    "###);
+
+    // --all skips the branches that aren't allowed
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_root, &["git", "push", "--all"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Branch branch2 is not allowed to be pushed
+    Hint: It matches a pattern in `git.push-deny`.
+    Branch other is not allowed to be pushed
+    Hint: It doesn't match any pattern in `git.push-allow`.
+    Branch changes to push to origin:
+      Move branch branch1 from 45a3aa29e907 to fb2cfb9fd6fe
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> fb2cfb9fd6fe
+    "###);
+
+    // Branches created by --change are checked too
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_root, &["git", "push", "--change", "@"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-vruxwmqvtpmx for revision @
+    Branch changes to push to origin:
+      Add branch push-vruxwmqvtpmx to fb2cfb9fd6fe
+    Pushed to origin:
+      push-vruxwmqvtpmx: (new) -> fb2cfb9fd6fe
+    "###);
+
+    // An invalid pattern is reported
+    let stderr = test_env.jj_cmd_failure(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--all",
+            "--config-toml=git.push-deny=['bad:x']",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid pattern in git.push-deny: bad:x
+    Caused by: Invalid string pattern kind "bad"
     "###);
 }
 
@@ -767,7 +1045,7 @@ fn test_git_push_deleted_untracked() {
     "###);
     let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--branch=branch1"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: No such branch: branch1
+    Error[E1020]: No such branch: branch1
     "###);
 }
 
@@ -823,3 +1101,112 @@ fn test_git_push_to_remote_named_git() {
     Error: Git remote named 'git' is reserved for local Git repository
     "###);
 }
+
+#[test]
+fn test_git_push_multiple_remotes() {
+    let (test_env, workspace_root) = set_up();
+    let backup_path = test_env.env_root().join("backup");
+    git2::Repository::init_bare(&backup_path).unwrap();
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "remote",
+            "add",
+            "backup",
+            backup_path.to_str().unwrap(),
+        ],
+    );
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "remote", "add", "broken", "nonexistent"],
+    );
+    test_env.add_config(r#"git.remote-groups.mirrors = ["origin", "backup"]"#);
+
+    // The branch is pushed to each of the remotes, and the results are reported
+    // per remote
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1", "-m=modified"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "set", "branch1"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "git",
+            "push",
+            "--remote=origin",
+            "--remote=backup",
+            "-b=branch1",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Move branch branch1 from 45a3aa29e907 to 3a18cb40265c
+    Branch changes to push to backup:
+      Add branch branch1 to 3a18cb40265c
+    Pushed to origin:
+      branch1: 45a3aa29e907 -> 3a18cb40265c
+    Pushed to backup:
+      branch1: (new) -> 3a18cb40265c
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]);
+    insta::assert_snapshot!(stdout, @r###"
+    branch1: znkkpsqq 3a18cb40 (empty) modified
+      @backup: znkkpsqq 3a18cb40 (empty) modified
+      @origin: znkkpsqq 3a18cb40 (empty) modified
+    branch2: rlzusymt 8476341e (empty) description 2
+      @origin: rlzusymt 8476341e (empty) description 2
+    "###);
+
+    // A remote group pushes to each of the remotes in it
+    test_env.jj_cmd_ok(&workspace_root, &["describe", "-m=modified again"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "push", "--remote=mirrors", "-b=branch1", "--dry-run"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Force branch branch1 from 3a18cb40265c to 1ae852c63acb
+    Branch changes to push to backup:
+      Force branch branch1 from 3a18cb40265c to 1ae852c63acb
+    Dry-run requested, not pushing.
+    "###);
+
+    // A failure to push to one remote doesn't keep the others from being updated
+    let assert = test_env
+        .jj_cmd(
+            &workspace_root,
+            &[
+                "git",
+                "push",
+                "--remote=mirrors",
+                "--remote=broken",
+                "-b=branch1",
+            ],
+        )
+        .assert()
+        .code(1);
+    insta::assert_snapshot!(get_stdout_string(&assert), @"");
+    insta::assert_snapshot!(get_stderr_string(&assert), @r###"
+    Branch changes to push to origin:
+      Force branch branch1 from 3a18cb40265c to 1ae852c63acb
+    Branch changes to push to backup:
+      Force branch branch1 from 3a18cb40265c to 1ae852c63acb
+    Branch changes to push to broken:
+      Add branch branch1 to 1ae852c63acb
+    Pushed to origin:
+      branch1: 3a18cb40265c -> 1ae852c63acb (forced)
+    Pushed to backup:
+      branch1: 3a18cb40265c -> 1ae852c63acb (forced)
+    Failed to push to broken: unsupported URL protocol
+    Error: Failed to push to broken
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]);
+    insta::assert_snapshot!(stdout, @r###"
+    branch1: znkkpsqq 1ae852c6 (empty) modified again
+      @backup: znkkpsqq 1ae852c6 (empty) modified again
+      @origin: znkkpsqq 1ae852c6 (empty) modified again
+    branch2: rlzusymt 8476341e (empty) description 2
+      @origin: rlzusymt 8476341e (empty) description 2
+    "###);
+}
@@ -0,0 +1,275 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Path, PathBuf};
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn set_up() -> (TestEnvironment, PathBuf, PathBuf) {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "--git", "origin"]);
+    let origin_path = test_env.env_root().join("origin");
+    let origin_git_repo_path = origin_path
+        .join(".jj")
+        .join("repo")
+        .join("store")
+        .join("git");
+
+    test_env.jj_cmd_ok(&origin_path, &["describe", "-m=description 1"]);
+    test_env.jj_cmd_ok(&origin_path, &["branch", "create", "branch1"]);
+    test_env.jj_cmd_ok(&origin_path, &["new", "root()", "-m=description 2"]);
+    test_env.jj_cmd_ok(&origin_path, &["branch", "create", "branch2"]);
+    test_env.jj_cmd_ok(&origin_path, &["git", "export"]);
+
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &[
+            "git",
+            "clone",
+            "--config-toml=git.auto-local-branch=true",
+            origin_git_repo_path.to_str().unwrap(),
+            "local",
+        ],
+    );
+    let workspace_root = test_env.env_root().join("local");
+    (test_env, workspace_root, origin_git_repo_path)
+}
+
+fn get_git_refs(git_repo_path: &Path) -> String {
+    let git_repo = git2::Repository::open(git_repo_path).unwrap();
+    let mut refs: Vec<_> = git_repo
+        .references()
+        .unwrap()
+        .map(|git_ref| git_ref.unwrap())
+        .filter(|git_ref| git_ref.is_branch() || git_ref.is_tag())
+        .map(|git_ref| {
+            let commit = git_ref.peel_to_commit().unwrap();
+            format!(
+                "{} {}",
+                git_ref.name().unwrap(),
+                &commit.id().to_string()[..12]
+            )
+        })
+        .collect();
+    refs.sort();
+    refs.join("\n")
+}
+
+#[test]
+fn test_git_sync_mirror_to_remote() {
+    let (test_env, workspace_root, origin_git_repo_path) = set_up();
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1", "-m=modified"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "set", "branch1"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "delete", "branch2"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "-r=@-", "branch3"]);
+    let local_git_repo = git2::Repository::open(workspace_root.join(".jj/repo/store/git")).unwrap();
+    let head_oid = local_git_repo
+        .find_reference("refs/remotes/origin/branch2")
+        .unwrap()
+        .target()
+        .unwrap();
+    local_git_repo
+        .reference("refs/tags/v1", head_oid, false, "")
+        .unwrap();
+    test_env.jj_cmd_ok(&workspace_root, &["git", "import"]);
+    insta::assert_snapshot!(get_git_refs(&origin_git_repo_path), @r###"
+    refs/heads/branch1 45a3aa29e907
+    refs/heads/branch2 8476341eb395
+    "###);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "sync", "--mirror", "origin", "--dry-run"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Move branch branch1 from 45a3aa29e907 to 74c0d21cf354
+      Delete branch branch2 from 8476341eb395
+      Add branch branch3 to 45a3aa29e907
+      Add tag v1 to 8476341eb395
+    Dry-run requested, not pushing.
+    "###);
+
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&workspace_root, &["git", "sync", "--mirror", "origin"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Move branch branch1 from 45a3aa29e907 to 74c0d21cf354
+      Delete branch branch2 from 8476341eb395
+      Add branch branch3 to 45a3aa29e907
+      Add tag v1 to 8476341eb395
+    "###);
+    insta::assert_snapshot!(get_git_refs(&origin_git_repo_path), @r###"
+    refs/heads/branch1 74c0d21cf354
+    refs/heads/branch3 45a3aa29e907
+    refs/tags/v1 8476341eb395
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]), @r###"
+    branch1: vruxwmqv 74c0d21c (empty) modified
+      @origin: vruxwmqv 74c0d21c (empty) modified
+    branch3: lzmmnrxq 45a3aa29 (empty) description 1
+      @origin: lzmmnrxq 45a3aa29 (empty) description 1
+    "###);
+
+    // Mirroring again changes nothing
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&workspace_root, &["git", "sync", "--mirror", "origin"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+}
+
+#[test]
+fn test_git_sync_mirror_from_remote() {
+    let (test_env, workspace_root, origin_git_repo_path) = set_up();
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "local-only"]);
+    // Change the remote's branches and add a tag
+    let origin_git_repo = git2::Repository::open(&origin_git_repo_path).unwrap();
+    let branch1_oid = origin_git_repo
+        .find_reference("refs/heads/branch1")
+        .unwrap()
+        .target()
+        .unwrap();
+    origin_git_repo
+        .reference("refs/heads/branch3", branch1_oid, false, "")
+        .unwrap();
+    origin_git_repo
+        .reference("refs/tags/v1", branch1_oid, false, "")
+        .unwrap();
+    origin_git_repo
+        .find_reference("refs/heads/branch2")
+        .unwrap()
+        .delete()
+        .unwrap();
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "sync", "--mirror", "origin", "--from-remote"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Abandoned 1 commits that are no longer reachable.
+    Local changes to mirror origin:
+      Delete branch branch2 from 8476341eb395
+      Add branch branch3 to 45a3aa29e907
+      Delete branch local-only from 5b36783cd11c
+      Add tag v1 to 45a3aa29e907
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&workspace_root, &["branch", "list", "--all"]), @r###"
+    branch1: lzmmnrxq 45a3aa29 (empty) description 1
+      @origin: lzmmnrxq 45a3aa29 (empty) description 1
+    branch3: lzmmnrxq 45a3aa29 (empty) description 1
+      @origin: lzmmnrxq 45a3aa29 (empty) description 1
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&workspace_root, &["tag", "list"]), @r###"
+    v1
+    "###);
+
+    // Tags that were deleted on the remote are deleted locally
+    origin_git_repo
+        .find_reference("refs/tags/v1")
+        .unwrap()
+        .delete()
+        .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_root,
+        &["git", "sync", "--mirror", "origin", "--from-remote"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Local changes to mirror origin:
+      Delete tag v1 from 45a3aa29e907
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&workspace_root, &["tag", "list"]), @"");
+}
+
+#[test]
+fn test_git_sync_mirror_conflicted_branch() {
+    let (test_env, workspace_root, _origin_git_repo_path) = set_up();
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &["branch", "create", "-r=branch2", "branch3"],
+    );
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &["branch", "create", "--at-op=@-", "-r=branch1", "branch3"],
+    );
+    let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "sync", "--mirror", "origin"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Concurrent modification detected, resolving automatically.
+    Error: Branch branch3 is conflicted
+    Hint: Run `jj branch list` to inspect, and use `jj branch set` to fix it up.
+    "###);
+}
+
+#[test]
+fn test_git_sync_mirror_refuses_unready_commits() {
+    let (test_env, workspace_root, origin_git_repo_path) = set_up();
+    // Like `jj git push`, commits without a description aren't pushed, even in
+    // a dry run
+    test_env.jj_cmd_ok(&workspace_root, &["new", "branch1"]);
+    test_env.jj_cmd_ok(&workspace_root, &["branch", "create", "branch3"]);
+    let stderr = test_env.jj_cmd_failure(
+        &workspace_root,
+        &["git", "sync", "--mirror", "origin", "--dry-run"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Add branch branch3 to d72edeef7921
+    Won't push commits without a description:
+      vruxwmqv d72edeef branch3 | (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, or set `git.push-guard.undescribed` to false.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "sync", "--mirror", "origin"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Add branch branch3 to d72edeef7921
+    Won't push commits without a description:
+      vruxwmqv d72edeef branch3 | (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, or set `git.push-guard.undescribed` to false.
+    "###);
+    let origin_git_repo = git2::Repository::open(origin_git_repo_path).unwrap();
+    assert!(origin_git_repo
+        .find_reference("refs/heads/branch3")
+        .is_err());
+
+    // Commits that are already on the remote aren't checked
+    test_env.jj_cmd_ok(
+        &workspace_root,
+        &[
+            "branch",
+            "set",
+            "branch3",
+            "-r=branch1",
+            "--allow-backwards",
+        ],
+    );
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&workspace_root, &["git", "sync", "--mirror", "origin"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Add branch branch3 to 45a3aa29e907
+    "###);
+}
@@ -0,0 +1,204 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Sets up a repo with a fake GitHub CLI that logs its arguments, and pretends
+/// that the "origin" remote is on GitHub.
+fn set_up() -> (TestEnvironment, PathBuf) {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("origin.git");
+    git2::Repository::init_bare(&git_repo_path).unwrap();
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", git_repo_path.to_str().unwrap(), "repo"],
+    );
+    let repo_path = test_env.env_root().join("repo");
+
+    let state_dir = test_env.env_root().join("gh");
+    std::fs::create_dir(&state_dir).unwrap();
+    let gh_path = test_env.env_root().join("fake-gh");
+    std::fs::write(
+        &gh_path,
+        format!(
+            r#"#!/bin/sh
+state={state}
+echo "gh $*" >> $state/log
+case "$1 $2" in
+"pr list")
+    cat "$state/pr-$4" 2>/dev/null || echo "[]" ;;
+"pr create")
+    n=$(( $(cat $state/count 2>/dev/null || echo 0) + 1 ))
+    echo $n > $state/count
+    echo "[{{\"number\": $n, \"url\": \"https://github.com/owner/repo/pull/$n\"}}]" > "$state/pr-$4"
+    echo "https://github.com/owner/repo/pull/$n" ;;
+"pr edit")
+    echo "https://github.com/owner/repo/pull/$3" ;;
+esac
+"#,
+            state = state_dir.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&gh_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    test_env.add_config(&format!(
+        r#"
+        github.program = '{}'
+        github.repository = "owner/repo"
+        "#,
+        gh_path.display()
+    ));
+    (test_env, repo_path)
+}
+
+fn take_gh_log(test_env: &TestEnvironment) -> String {
+    let log_path = test_env.env_root().join("gh").join("log");
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    std::fs::remove_file(&log_path).ok();
+    log
+}
+
+#[test]
+fn test_github_pr_create() {
+    let (test_env, repo_path) = set_up();
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "First\n\nThe body."]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "-r", "@-", "first"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Second"]);
+
+    // The second pull request is stacked on the first
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["github", "pr", "create", "-r", "@--::@-", "--draft"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-rlvkpnrzqnoo for revision 08ca503a0069
+    Branch changes to push to origin:
+      Add branch first to a15c47b5a2c3
+      Add branch push-rlvkpnrzqnoo to 08ca503a0069
+    Pushed to origin:
+      first: (new) -> a15c47b5a2c3
+      push-rlvkpnrzqnoo: (new) -> 08ca503a0069
+    Created pull request https://github.com/owner/repo/pull/1
+    Created pull request https://github.com/owner/repo/pull/2
+    "###);
+    insta::assert_snapshot!(take_gh_log(&test_env), @r###"
+    gh pr list --head first --state open --json number,url --repo owner/repo
+    gh pr list --head push-rlvkpnrzqnoo --state open --json number,url --repo owner/repo
+    gh pr create --head first --title First --body The body. --draft --repo owner/repo
+    gh pr create --head push-rlvkpnrzqnoo --title Second --body  --base first --draft --repo owner/repo
+    "###);
+
+    // Existing pull requests aren't created again
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["github", "pr", "create", "-r", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 08ca503a0069 already has pull request https://github.com/owner/repo/pull/2
+    Hint: Use `jj github pr update` to update it.
+    "###);
+    take_gh_log(&test_env);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["github", "pr", "create"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-zsuskulnrvyr for revision 4a2e611df9e8
+    Won't push commits without a description:
+      zsuskuln 4a2e611d (empty) (no description set)
+    Error: Won't push the commits listed above
+    Hint: Rewrite the commits, or set `git.push-guard.undescribed` to false.
+    "###);
+    take_gh_log(&test_env);
+}
+
+#[test]
+fn test_github_pr_update() {
+    let (test_env, repo_path) = set_up();
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "First"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Second"]);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["github", "pr", "update", "-r", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit c76c69658bf0 doesn't have a pull request
+    Hint: Use `jj github pr create` to create one.
+    "###);
+    take_gh_log(&test_env);
+
+    test_env.jj_cmd_ok(&repo_path, &["github", "pr", "create", "-r", "@--::@-"]);
+    take_gh_log(&test_env);
+
+    // The updated commits and descriptions are pushed
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "@--", "-m", "First\n\nWith a body."],
+    );
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["github", "pr", "update", "-r", "@--::@-", "--base", "main"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Force branch push-qpvuntsmwlqt from 144d0742af09 to d3443dd1f240
+      Force branch push-rlvkpnrzqnoo from c76c69658bf0 to 8d90cdfabeb9
+    Pushed to origin:
+      push-qpvuntsmwlqt: 144d0742af09 -> d3443dd1f240 (forced)
+      push-rlvkpnrzqnoo: c76c69658bf0 -> 8d90cdfabeb9 (forced)
+    Updated pull request https://github.com/owner/repo/pull/1
+    Updated pull request https://github.com/owner/repo/pull/2
+    "###);
+    insta::assert_snapshot!(take_gh_log(&test_env), @r###"
+    gh pr list --head push-qpvuntsmwlqt --state open --json number,url --repo owner/repo
+    gh pr list --head push-rlvkpnrzqnoo --state open --json number,url --repo owner/repo
+    gh pr edit 1 --title First --body With a body. --base main --repo owner/repo
+    gh pr edit 2 --title Second --body  --base push-qpvuntsmwlqt --repo owner/repo
+    "###);
+}
+
+#[test]
+fn test_github_pr_not_github() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "git",
+            "remote",
+            "add",
+            "origin",
+            "https://example.com/repo.git",
+        ],
+    );
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["github", "pr", "create"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Remote origin is not a GitHub repository: https://example.com/repo.git
+    Hint: If it is, set github.repository to its [HOST/]OWNER/REPO name.
+    "###);
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["github", "pr", "create", "--remote", "missing"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No git remote named 'missing'
+    "###);
+}
@@ -14,6 +14,8 @@
 
 use std::ffi::OsString;
 
+use test_case::test_case;
+
 use crate::common::{get_stderr_string, TestEnvironment};
 
 pub mod common;
@@ -69,13 +71,13 @@ fn test_no_subcommand() {
     insta::assert_snapshot!(stderr, @r###"
     Hint: Use `jj -h` for a list of available commands.
     Set the config `ui.default-command = "log"` to disable this message.
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 
     test_env.add_config(r#"ui.default-command="log""#);
     let stderr = test_env.jj_cmd_failure(test_env.env_root(), &[]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 
     let stdout = test_env.jj_cmd_success(test_env.env_root(), &["--help"]);
@@ -136,12 +138,117 @@ fn test_ignore_working_copy() {
     "###);
 }
 
+#[test]
+fn test_read_only() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let op_heads_dir = repo_path.join(".jj/repo/op_heads/heads");
+    let count_op_heads = || std::fs::read_dir(&op_heads_dir).unwrap().count();
+    let count_files = |dir: &str| std::fs::read_dir(repo_path.join(dir)).unwrap().count();
+
+    // The working copy isn't snapshotted
+    std::fs::write(repo_path.join("file"), "initial").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "commit_id", "--read-only"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    ◉  0000000000000000000000000000000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-T",
+            "commit_id",
+            "--config-toml=ui.default-command-mode='readonly'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    @  230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    ◉  0000000000000000000000000000000000000000
+    "###);
+
+    // Commands that modify the repo fail
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m", "foo", "--read-only"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot modify the repo in read-only mode.
+    Hint: Don't use --read-only.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["op", "abandon", "@-", "--read-only"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot modify the repo in read-only mode.
+    Hint: Don't use --read-only.
+    "###);
+    // Including the ones that modify the repo outside of a transaction
+    let git_config = std::fs::read(repo_path.join(".jj/repo/store/git/config")).unwrap();
+    for args in [
+        &["git", "remote", "add", "origin", "http://example.com"][..],
+        &["git", "remote", "remove", "origin"],
+        &["git", "remote", "rename", "origin", "upstream"],
+        &["git", "fetch"],
+        &["git", "push", "--all"],
+        &["git", "import"],
+        &["git", "export"],
+        &["sparse", "set", "--clear"],
+        &["workspace", "add", "../secondary"],
+        &["workspace", "forget"],
+        &["workspace", "update-stale"],
+    ] {
+        let stderr = test_env.jj_cmd_failure(&repo_path, &[args, &["--read-only"]].concat());
+        assert_eq!(
+            stderr,
+            "Error: Cannot modify the repo in read-only mode.\nHint: Don't use --read-only.\n",
+            "{args:?}"
+        );
+    }
+    assert_eq!(
+        std::fs::read(repo_path.join(".jj/repo/store/git/config")).unwrap(),
+        git_config
+    );
+    assert!(!test_env.env_root().join("secondary").exists());
+    // The config doesn't affect them
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "describe",
+            "-m",
+            "foo",
+            "--config-toml=ui.default-command-mode='readonly'",
+        ],
+    );
+
+    // Concurrent operations are merged, but the merge isn't recorded
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "bar", "--at-op", "@-"]);
+    assert_eq!(count_op_heads(), 2);
+    let store_dirs = [
+        ".jj/repo/op_store/operations",
+        ".jj/repo/op_store/views",
+        ".jj/repo/index/operations",
+        ".jj/repo/store/extra",
+    ];
+    let file_counts = store_dirs.map(count_files);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["log", "-T", "description", "--no-graph", "--read-only"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    bar
+    foo
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Concurrent modification detected, resolving automatically.
+    "###);
+    assert_eq!(count_op_heads(), 2);
+    // Nothing is written to the stores
+    assert_eq!(store_dirs.map(count_files), file_counts);
+}
+
 #[test]
 fn test_repo_arg_with_init() {
     let test_env = TestEnvironment::default();
     let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["init", "-R=.", "repo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 }
 
@@ -150,7 +257,7 @@ fn test_repo_arg_with_git_clone() {
     let test_env = TestEnvironment::default();
     let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["git", "clone", "-R=.", "remote"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 }
 
@@ -173,7 +280,7 @@ fn test_resolve_workspace_directory() {
     // Explicit subdirectory path
     let stderr = test_env.jj_cmd_failure(&subdir, &["status", "-R", "."]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 
     // Valid explicit path
@@ -187,7 +294,7 @@ fn test_resolve_workspace_directory() {
     // "../../..".ancestors() contains "../..", but it should never be looked up.
     let stderr = test_env.jj_cmd_failure(&subdir, &["status", "-R", "../../.."]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "../../.."
+    Error[E1001]: There is no jj repo in "../../.."
     "###);
 }
 
@@ -199,18 +306,18 @@ fn test_no_workspace_directory() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["status"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     "###);
 
     let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["status", "-R", "repo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "repo"
+    Error[E1001]: There is no jj repo in "repo"
     "###);
 
     std::fs::create_dir(repo_path.join(".git")).unwrap();
     let stderr = test_env.jj_cmd_failure(&repo_path, &["status"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: There is no jj repo in "."
+    Error[E1001]: There is no jj repo in "."
     Hint: It looks like this is a git repo. You can create a jj repo backed by it by running this:
     jj init --git-repo=.
     "###);
@@ -393,6 +500,67 @@ fn test_invalid_config() {
     "###);
 }
 
+#[test]
+fn test_lock_backend_config() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(r#"core.lock-backend = "lockfile""#);
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "contents").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    ◉  first
+    ◉
+    "###);
+    // No lock files are left behind
+    assert!(!repo_path.join(".jj/repo/op_heads/lock").exists());
+    assert!(!repo_path
+        .join(".jj/working_copy/working_copy.lock")
+        .exists());
+
+    test_env.add_config(r#"core.lock-backend = "flock""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Config error: unknown lock backend: flock
+    For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
+#[test_case("immediate" ; "immediate")]
+#[test_case("batch" ; "batch")]
+fn test_fsync_config(method: &str) {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(&format!(
+        r#"
+        core.fsync = "all"
+        core.fsync-method = "{method}"
+        "#
+    ));
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "contents").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description"]);
+    insta::allow_duplicates! {
+        insta::assert_snapshot!(stdout, @r###"
+        @
+        ◉  first
+        ◉
+        "###);
+    }
+
+    test_env.add_config(r#"core.fsync = ["objects", "index"]"#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log"]);
+    insta::allow_duplicates! {
+        insta::assert_snapshot!(stderr, @r###"
+        Config error: unknown fsync target: index
+        For help, see https://github.com/martinvonz/jj/blob/main/docs/config.md.
+        "###);
+    }
+}
+
 #[test]
 fn test_no_user_configured() {
     // Test that the user is reminded if they haven't configured their name or email
@@ -447,8 +615,12 @@ fn test_help() {
     Global Options:
       -R, --repository <REPOSITORY>      Path to repository to operate on
           --ignore-working-copy          Don't snapshot the working copy, and don't update it
+          --read-only                    Don't modify the repo or the working copy
+          --ignore-immutable             Allow rewriting immutable commits
+          --no-hooks                     Don't run hooks
           --at-operation <AT_OPERATION>  Operation to load the repo at [default: @] [aliases: at-op]
       -v, --verbose                      Enable verbose logging
+          --profile <FILE>               Write a trace of the command's instrumented spans to this file
           --color <WHEN>                 When to colorize output (always, never, auto)
           --no-pager                     Disable the pager
           --config-toml <TOML>           Additional configuration options (can be repeated)
@@ -474,6 +646,34 @@ fn test_verbose_logging_enabled() {
     insta::assert_snapshot!(log_line, @"[32m INFO[0m [2mjj_cli::cli_util[0m[2m:[0m verbose logging enabled");
 }
 
+#[test]
+fn test_profile() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let trace_path = test_env.env_root().join("trace.json");
+
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["log", "--profile", trace_path.to_str().unwrap()],
+    );
+    // The trace is a JSON array of events, including spans of the command
+    let trace = std::fs::read_to_string(&trace_path).unwrap();
+    assert!(trace.starts_with('['), "{trace}");
+    assert!(trace.contains(r#""name":"cmd_log""#), "{trace}");
+
+    // A trace file that can't be created is an error
+    let trace_path = test_env.env_root().join("nonexistent").join("trace.json");
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["log", "--profile", trace_path.to_str().unwrap()],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to create trace file $TEST_ENV/nonexistent/trace.json
+    Caused by: No such file or directory (os error 2)
+    "###);
+}
+
 fn strip_last_line(s: &str) -> &str {
     s.trim_end_matches('\n')
         .rsplit_once('\n')
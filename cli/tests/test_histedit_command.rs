@@ -0,0 +1,294 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Creates commits "a", "b" and "c" that each add a file with their name, and
+/// an empty working-copy commit on top.
+fn set_up_stack(test_env: &TestEnvironment, repo_path: &Path) {
+    for name in ["a", "b", "c"] {
+        test_env.jj_cmd_ok(repo_path, &["describe", "-m", name]);
+        std::fs::write(repo_path.join(name), format!("{name}\n")).unwrap();
+        test_env.jj_cmd_ok(repo_path, &["new"]);
+    }
+}
+
+fn commit_id(test_env: &TestEnvironment, repo_path: &Path, description: &str) -> String {
+    let revset = format!("description(exact:\"{description}\n\")");
+    test_env.jj_cmd_success(
+        repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            &revset,
+            "-T",
+            "commit_id.short()",
+        ],
+    )
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"description.first_line() ++ " " ++ branches"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
+
+#[test]
+fn test_histedit_reorder() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    set_up_stack(&test_env, &repo_path);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["branch", "create", "-r", "description(c)", "top"],
+    );
+    let [a, b, c] = ["a", "b", "c"].map(|name| commit_id(&test_env, &repo_path, name));
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(
+        &edit_script,
+        format!("dump plan\0write\npick {c}\npick {a}\npick {b}\n"),
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    Working copy now at: yqosqzyt f959bce7 (empty) (no description set)
+    Parent commit      : kkmpptxz b5e3fd67 top | b
+    "###);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("plan")).unwrap(), @r###"
+    pick 3df52ee1f8a9 a
+    pick b2e8585f77c4 b
+    pick db142824afeb c
+    JJ: Commits are listed oldest first. Reorder the lines to reorder the commits,
+    JJ: and change `pick` to one of these to change what happens to the commit:
+    JJ:   reword: edit the description of the commit
+    JJ:   squash: combine the commit with the one above it
+    JJ:   drop:   abandon the commit
+    JJ: Remove all lines to leave the commits unchanged.
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+    // The working copy and the branch stay at the top of the stack
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  b top
+    ◉  a
+    ◉  c
+    ◉
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "-r", "description(c)"]);
+    insta::assert_snapshot!(stdout, @r###"
+    c
+    "###);
+
+    // All changes are undone at once
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  c top
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+}
+
+#[test]
+fn test_histedit_squash_drop_reword() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    set_up_stack(&test_env, &repo_path);
+    let [a, b, c] = ["a", "b", "c"].map(|name| commit_id(&test_env, &repo_path, name));
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(
+        &edit_script,
+        [
+            &format!("write\ns {b}\ndrop {c}\nreword {a}\n"),
+            "next invocation\n",
+            "write\nfirst\n",
+        ]
+        .join("\0"),
+    )
+    .unwrap();
+    // The commit to squash must come after another one
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot squash commit b2e8585f77c4 because there's no commit above it
+    "###);
+
+    std::fs::write(
+        &edit_script,
+        [
+            &format!("write\nreword {a}\nsquash {b}\ndrop {c}\n"),
+            "next invocation\n",
+            "write\nfirst\n",
+            "next invocation\n",
+            "dump combined\0write\nfirst and second\n",
+        ]
+        .join("\0"),
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    Working copy now at: yqosqzyt 4a893987 (empty) (no description set)
+    Parent commit      : qpvuntsm 24781f16 first and second
+    Added 0 files, modified 0 files, removed 1 files
+    "###);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("combined")).unwrap(), @r###"
+    JJ: Enter a description for the combined commit.
+    JJ: Description from the destination commit:
+    first
+
+    JJ: Description from the source commit:
+    b
+
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  first and second
+    ◉
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    a
+    b
+    "###);
+}
+
+#[test]
+fn test_histedit_conflict() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "a"]);
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "b"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    let [a, b] = ["a", "b"].map(|name| commit_id(&test_env, &repo_path, name));
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(&edit_script, format!("write\npick {b}\npick {a}\n")).unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(b)"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    New conflicts appeared in these commits:
+      kkmpptxz 0daa4c85 (conflict) b
+    To resolve the conflicts, start by updating to it:
+      jj new kkmpptxzrspx
+    Then use `jj resolve`, or edit the conflict markers in the file directly.
+    Once the conflicts are resolved, you may want inspect the result with `jj diff`.
+    Then run `jj squash` to move the resolution into the conflicted commit.
+    Working copy now at: qpvuntsm 89109d7b a
+    Parent commit      : kkmpptxz 0daa4c85 (conflict) b
+    "###);
+}
+
+#[test]
+fn test_histedit_errors() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    set_up_stack(&test_env, &repo_path);
+    let [a, b, c] = ["a", "b", "c"].map(|name| commit_id(&test_env, &repo_path, name));
+    let edit_script = test_env.set_up_fake_editor();
+
+    // The commits must form a linear chain
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["histedit", "-r", "description(a) | description(c)"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The commits to edit must form a linear chain
+    Hint: Each commit except the first must be the only child of the one before it in the revset.
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["new", "description(a)", "description(c)"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["histedit", "-r", "@"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot edit merge commit 57024b4dcb44
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+
+    // Every commit must be listed once
+    std::fs::write(&edit_script, format!("write\npick {a}\npick {b}\n")).unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit db142824afeb is missing from the plan
+    Hint: Use `drop` to abandon a commit.
+    "###);
+    std::fs::write(
+        &edit_script,
+        format!("write\npick {a}\npick {b}\npick {c}\npick {a}\n"),
+    )
+    .unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 3df52ee1f8a9 is listed more than once
+    "###);
+    std::fs::write(&edit_script, format!("write\npick {a}\nfixup {b}\n")).unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(b)"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Unknown histedit action: fixup
+    "###);
+
+    // Removing all lines leaves the commits unchanged
+    std::fs::write(&edit_script, "write\nJJ: nothing\n").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["histedit", "-r", "description(a)::description(c)"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @
+    ◉  c
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+}
@@ -0,0 +1,251 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Configures the hook for the `event` to run the shell `script`, which can
+/// append to the file in `$log`.
+fn set_hook(test_env: &TestEnvironment, event: &str, script: &str) {
+    let log_path = test_env.env_root().join("hook-log");
+    let script = format!("log={}\n{script}", log_path.display());
+    test_env.add_config(&format!(
+        "hooks.{event} = [\"sh\", \"-c\", {}]",
+        toml_edit::Value::from(script)
+    ));
+}
+
+fn take_log(test_env: &TestEnvironment) -> String {
+    let log_path = test_env.env_root().join("hook-log");
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    std::fs::remove_file(&log_path).ok();
+    log
+}
+
+fn set_up() -> (TestEnvironment, PathBuf) {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "--git", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    (test_env, repo_path)
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    test_env.jj_cmd_success(repo_path, &["log", "-T", "description"])
+}
+
+#[test]
+fn test_snapshot_hooks() {
+    let (test_env, repo_path) = set_up();
+    // The pre-snapshot hook can change the working copy before it's snapshotted
+    set_hook(
+        &test_env,
+        "pre-snapshot",
+        r#"echo "$JJ_HOOK" >> $log; echo formatted > file"#,
+    );
+    set_hook(
+        &test_env,
+        "post-snapshot",
+        r#"echo "$JJ_HOOK $JJ_COMMIT_ID" >> $log"#,
+    );
+    std::fs::write(repo_path.join("file"), "unformatted").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    formatted
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    pre-snapshot
+    post-snapshot 166626cf2a30f3040f0d6d7f9ffebaeeaafe69ec
+    "###);
+
+    // Without changes in the working copy, only the pre-snapshot hook runs
+    test_env.jj_cmd_ok(&repo_path, &["status"]);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    pre-snapshot
+    "###);
+
+    // Hooks can be skipped
+    std::fs::write(repo_path.join("file"), "unformatted").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file", "--no-hooks"]);
+    insta::assert_snapshot!(stdout, @"unformatted");
+    insta::assert_snapshot!(take_log(&test_env), @"");
+}
+
+#[test]
+fn test_operation_hooks() {
+    let (test_env, repo_path) = set_up();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m=first"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m=second"]);
+    set_hook(
+        &test_env,
+        "post-rewrite",
+        r#"echo "$JJ_HOOK $JJ_OPERATION_ID" >> $log; cat >> $log"#,
+    );
+    set_hook(
+        &test_env,
+        "post-operation",
+        r#"echo "$JJ_HOOK $JJ_OPERATION_ID" >> $log; echo "hook output""#,
+    );
+
+    // Rewriting a commit gets the old and new commit IDs of it and its
+    // descendants
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["describe", "@-", "-m=first!"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    Working copy now at: kkmpptxz ce64dc6f (empty) second
+    Parent commit      : qpvuntsm acb13f2d (empty) first!
+    hook output
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    post-rewrite 30c77939fe63a439d947223fd02d911184d62c27a0a6ec8bee06fe13fa4a2c0dd865e1d7bfee5a4f03dd944f97c28e5a830a32a1e95f5134b08ab96a5c9afe1f
+    69542c1984c1f9d91f7c6c9c9e6941782c944bd9 acb13f2de17b2a31679409412c1bbb057281573a
+    bc28fa9df1e531145bb9b91f6afc7c5ddf7f5861 ce64dc6f54dbe7dcdda9ab85df7180b8b27ff66f
+    post-operation 30c77939fe63a439d947223fd02d911184d62c27a0a6ec8bee06fe13fa4a2c0dd865e1d7bfee5a4f03dd944f97c28e5a830a32a1e95f5134b08ab96a5c9afe1f
+    "###);
+
+    // An operation that doesn't rewrite commits only runs the post-operation
+    // hook
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main"]);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    post-operation 5448deba3c3e4b8f92646f2eb41957dba1c6822b1b1287376868dd58239cc908a302da419ea2c5210b48217d542d1691ff44309ebb8aef38fbcce9ab0b9b3e26
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  second
+    ◉  first!
+    ◉
+    "###);
+}
+
+#[test]
+fn test_hook_failures() {
+    let (test_env, repo_path) = set_up();
+    // A failing post- hook is only a warning
+    set_hook(&test_env, "post-operation", "exit 1");
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["describe", "-m=first"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: qpvuntsm 69542c19 (empty) first
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    The post-operation hook failed: exit status: 1
+    "###);
+
+    // A failing pre- hook aborts the command
+    set_hook(&test_env, "pre-snapshot", "echo checking; exit 2");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r###"
+    checking
+    Error: The pre-snapshot hook failed: exit status: 2
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+
+    // A hook that takes too long is killed
+    test_env.add_config("hooks.timeout = 1");
+    set_hook(&test_env, "pre-snapshot", "sleep 10");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The pre-snapshot hook failed: Timed out after 1 seconds
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+
+    // Even if it closes its stdout first
+    set_hook(&test_env, "pre-snapshot", "exec >&-; sleep 10");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The pre-snapshot hook failed: Timed out after 1 seconds
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+
+    // jj commands run by hooks don't run hooks
+    let jj_status = format!("{} status > /dev/null", env!("CARGO_BIN_EXE_jj"));
+    set_hook(&test_env, "pre-snapshot", &jj_status);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["describe", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: qpvuntsm 8c6c8886 (empty) second
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    The post-operation hook failed: exit status: 1
+    "###);
+}
+
+#[test]
+fn test_pre_push_hook() {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("origin.git");
+    git2::Repository::init_bare(&git_repo_path).unwrap();
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", git_repo_path.to_str().unwrap(), "repo"],
+    );
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m=first"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main"]);
+    set_hook(
+        &test_env,
+        "pre-push",
+        r#"echo "$JJ_HOOK $JJ_REMOTE" >> $log; cat >> $log; exit 1"#,
+    );
+
+    // The hook gets the refs to push, and can refuse the push
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["git", "push", "-b=main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch main to 69542c1984c1
+    Error: The pre-push hook failed: exit status: 1
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    pre-push origin
+    refs/heads/main 69542c1984c1f9d91f7c6c9c9e6941782c944bd9 refs/heads/main 0000000000000000000000000000000000000000
+    "###);
+
+    // The hook can be bypassed
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["git", "push", "-b=main", "--no-hooks"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Add branch main to 69542c1984c1
+    Pushed to origin:
+      main: (new) -> 69542c1984c1
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @"");
+
+    // The hook also runs for the other commands that push
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m=second"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["gerrit", "send", "-r=@", "--branch=main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Added Change-Id to 1 commits
+    Changes to send to origin for review on main:
+      royxmykx 139cfe16 (empty) second
+    Error: The pre-push hook failed: exit status: 1
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    pre-push origin
+    refs/for/main 139cfe16768d02f0c2b3ea899f0c622e4a2646b5 refs/for/main 0000000000000000000000000000000000000000
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "other", "-r=main"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["git", "sync", "--mirror=origin"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Ref changes to mirror to origin:
+      Add branch other to 69542c1984c1
+    Error: The pre-push hook failed: exit status: 1
+    Hint: Pass `--no-hooks` to skip hooks.
+    "###);
+    insta::assert_snapshot!(take_log(&test_env), @r###"
+    pre-push origin
+    refs/heads/other 69542c1984c1f9d91f7c6c9c9e6941782c944bd9 refs/heads/other 0000000000000000000000000000000000000000
+    "###);
+}
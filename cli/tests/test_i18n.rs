@@ -0,0 +1,67 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_language_from_config() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.language = "fr""#);
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["new"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Copie de travail maintenant à : rlvkpnrz 65b6b74e (empty) (no description set)
+    Commit parent                 : qpvuntsm 230dd059 (empty) (no description set)
+    "###);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["describe", "-m", ""]);
+    insta::assert_snapshot!(stderr, @r###"
+    Rien n'a changé.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Erreur[E1012] : The root commit 000000000000 is immutable
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "--config-toml=ui.paginate=1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Erreur de configuration : Invalid `ui.paginate`: value of enum PaginationChoice should be represented by either string or table with exactly one key
+    Pour de l'aide, voir https://github.com/martinvonz/jj/blob/main/docs/config.md.
+    "###);
+}
+
+#[test]
+fn test_language_not_from_env() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    // The locale doesn't change the language of the messages
+    test_env.add_env_var("LANG", "fr_FR.UTF-8");
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["describe", "-m", ""]);
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+
+    // Only the config does
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "-m", "", "--config-toml=ui.language='fr'"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Rien n'a changé.
+    "###);
+}
@@ -43,20 +43,20 @@ fn test_rewrite_immutable_generic() {
     test_env.add_config(r#"revset-aliases."immutable_heads()" = "main""#);
     let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit c8d4c7ca95d0 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit c8d4c7ca95d0 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // Cannot rewrite an ancestor of the configured set
     let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main-"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 46a8dc5175be is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 46a8dc5175be is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // Cannot rewrite the root commit even with an empty set of immutable commits
     test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
     let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The root commit 000000000000 is immutable
+    Error[E1012]: The root commit 000000000000 is immutable
     "###);
     // Error if we redefine immutable_heads() with an argument
     // TODO: This error comes from the built-in definition of
@@ -80,6 +80,41 @@ fn test_rewrite_immutable_generic() {
     "###);
 }
 
+#[test]
+fn test_rewrite_immutable_ignore_immutable() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m=a"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m=b"]);
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "main""#);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "main", "-m=a2"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1012]: Commit 4c5b3042d9e0 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+
+    // The check can be overridden
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["describe", "main", "-m=a2", "--ignore-immutable"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    Working copy now at: zsuskuln dd0efe4c (empty) b
+    Parent commit      : qpvuntsm 42a3e3f4 main | (empty) a2
+    "###);
+
+    // ... but not for the root commit
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()", "--ignore-immutable"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1012]: The root commit 000000000000 is immutable
+    "###);
+}
+
 #[test]
 fn test_rewrite_immutable_commands() {
     let test_env = TestEnvironment::default();
@@ -114,109 +149,109 @@ fn test_rewrite_immutable_commands() {
     // abandon
     let stderr = test_env.jj_cmd_failure(&repo_path, &["abandon", "main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // chmod
     let stderr = test_env.jj_cmd_failure(&repo_path, &["chmod", "-r=main", "x", "file"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // describe
     let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // diffedit
     let stderr = test_env.jj_cmd_failure(&repo_path, &["diffedit", "-r=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // edit
     let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // move --from
     let stderr = test_env.jj_cmd_failure(&repo_path, &["move", "--from=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // move --to
     let stderr = test_env.jj_cmd_failure(&repo_path, &["move", "--to=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // new --insert-before
     let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "--insert-before", "main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // new --insert-after parent_of_main
     let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "--insert-after", "description(b)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // rebase -s
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s=main", "-d=@"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // rebase -b
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-b=main", "-d=@"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 6e11f430f297 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 6e11f430f297 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // rebase -r
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-r=main", "-d=@"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // resolve
     let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve", "-r=description(merge)", "file"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // restore -c
     let stderr = test_env.jj_cmd_failure(&repo_path, &["restore", "-c=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // restore --to
     let stderr = test_env.jj_cmd_failure(&repo_path, &["restore", "--to=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // split
     let stderr = test_env.jj_cmd_failure(&repo_path, &["split", "-r=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // squash
     let stderr = test_env.jj_cmd_failure(&repo_path, &["squash", "-r=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
     // unsquash
     let stderr = test_env.jj_cmd_failure(&repo_path, &["unsquash", "-r=main"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Commit 16ca9d800b08 is immutable
-    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`.
+    Error[E1012]: Commit 16ca9d800b08 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
     "###);
 }
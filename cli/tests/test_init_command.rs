@@ -527,3 +527,21 @@ fn test_init_local() {
     assert!(store_path.join("symlinks").is_dir());
     assert!(store_path.join("conflicts").is_dir());
 }
+
+#[test]
+fn test_init_working_copy_type() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(r#"core.working-copy = "local""#);
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "--git", "repo"]);
+    let working_copy_type =
+        std::fs::read_to_string(test_env.env_root().join("repo/.jj/working_copy/type")).unwrap();
+    assert_eq!(working_copy_type, "local");
+
+    test_env.add_config(r#"core.working-copy = "virtual""#);
+    let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["init", "--git", "repo2"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Unknown working-copy type "virtual"
+    Hint: Available types are: local
+    "###);
+    assert!(!test_env.env_root().join("repo2/.jj").exists());
+}
@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::{BufRead as _, BufReader};
+use std::process::Stdio;
+
 use common::{get_stdout_string, TestEnvironment};
 
 pub mod common;
@@ -314,6 +317,33 @@ fn test_log_with_or_without_diff() {
     "###);
 }
 
+#[test]
+fn test_log_stat_width() {
+    let mut test_env = TestEnvironment::default();
+    test_env.add_env_var("COLUMNS", "30");
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "content line\n".repeat(100)).unwrap();
+
+    // The bar is narrowed to fit next to the graph
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r@", "-Tdescription", "--stat"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    │  file | 100 +++++++++++++
+    ~  1 file changed, 100 insertions(+), 0 deletions(-)
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "-r@", "-Tdescription", "--stat", "--no-graph"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    file | 100 ++++++++++++++++
+    1 file changed, 100 insertions(+), 0 deletions(-)
+    "###);
+}
+
 #[test]
 fn test_log_null_terminate_multiline_descriptions() {
     let test_env = TestEnvironment::default();
@@ -664,7 +694,7 @@ fn test_log_prefix_highlight_counts_hidden_commits() {
     insta::assert_snapshot!(
         test_env.jj_cmd_failure(&repo_path, &["log", "-r", "4", "-T", prefix_format]),
         @r###"
-    Error: Commit ID prefix "4" is ambiguous
+    Error[E1015]: Commit ID prefix "4" is ambiguous
     "###
     );
     insta::assert_snapshot!(
@@ -863,6 +893,152 @@ fn test_log_filtered_by_path() {
     "###);
 }
 
+#[test]
+fn test_log_filtered_by_fileset() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file.rs"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "add file.rs"]);
+    std::fs::write(repo_path.join("file.txt"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "add file.txt"]);
+    test_env.jj_cmd_ok(&repo_path, &["chmod", "x", "file.rs"]);
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "make file.rs executable"]);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "-T", "description", "--no-graph", "-s", "glob:*.rs"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    make file.rs executable
+    M file.rs
+    add file.rs
+    A file.rs
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-T",
+            "description",
+            "--no-graph",
+            "-s",
+            "executable()",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    make file.rs executable
+    M file.rs
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-T",
+            "description",
+            "--no-graph",
+            "-r",
+            r#"file("~glob:*.rs")"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    add file.txt
+    "###);
+}
+
+#[test]
+fn test_log_follow() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "add file1"]);
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    std::fs::write(repo_path.join("other"), "x\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "modify file1"]);
+    std::fs::rename(repo_path.join("file1"), repo_path.join("file2")).unwrap();
+    std::fs::write(repo_path.join("file2"), "a\nb\nc\nd\nf\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "rename to file2"]);
+    std::fs::write(repo_path.join("other"), "y\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "modify other"]);
+    std::fs::write(repo_path.join("file2"), "a\nb\nc\nd\ng\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "modify file2"]);
+
+    // Without --follow, the history stops at the rename
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "-r", "::", "-T", "description", "file2"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    @  modify file2
+    ◉  rename to file2
+    │
+    ~
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-r",
+            "::",
+            "-T",
+            "description",
+            "--follow",
+            "file2",
+            "-s",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    @  modify file2
+    ╷  M file2
+    ◉  rename to file2
+    │  R file1 -> file2
+    ◉  modify file1
+    │  M file1
+    ◉  add file1
+    │  A file1
+    ~
+    "###);
+
+    // Renames are detected with the configured similarity
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "-r",
+            "::",
+            "-T",
+            "description",
+            "--follow",
+            "file2",
+            "--no-graph",
+            "--config-toml",
+            "ui.diff.rename-similarity = 100",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    modify file2
+    rename to file2
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "--follow", "file1", "file2"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: --follow requires a single path
+    "###);
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["log", "--follow"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the following required arguments were not provided:
+      <PATHS>...
+
+    Usage: jj log --follow <PATHS>...
+
+    For more information, try '--help'.
+    "###);
+}
+
 #[test]
 fn test_log_limit() {
     let test_env = TestEnvironment::default();
@@ -1357,3 +1533,64 @@ fn test_log_word_wrap() {
        merge
     "###);
 }
+
+#[test]
+fn test_log_watch() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "--watch", "--at-op=@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: --watch cannot be used with --at-op
+    "###);
+
+    // The log is shown again after the working copy is modified. Nothing is
+    // shown while the repo doesn't change.
+    let mut child = test_env
+        .jj_std_cmd(&repo_path, &["log", "--watch", "-T=description", "-s"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut read_log = || {
+        let mut log = String::new();
+        loop {
+            let mut line = String::new();
+            assert_ne!(stdout.read_line(&mut line).unwrap(), 0, "jj exited");
+            log.push_str(&line);
+            // The root commit is shown last
+            if line.trim_end() == "◉" {
+                return log;
+            }
+        }
+    };
+    let first_log = read_log();
+    std::fs::write(repo_path.join("file"), "contents").unwrap();
+    let second_log = read_log();
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    insta::assert_snapshot!(first_log, @r###"
+    @
+    ◉
+    "###);
+    insta::assert_snapshot!(second_log, @r###"
+    @
+    │  A file
+    ◉
+    "###);
+    insta::assert_snapshot!(String::from_utf8(output.stderr).unwrap(), @"");
+}
+
+#[test]
+fn test_browse_requires_terminal() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["browse"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: jj browse must be run in a terminal
+    "###);
+}
@@ -342,6 +342,53 @@ fn test_move_partial() {
     "###);
 }
 
+#[test]
+fn test_move_lines() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "base"]);
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "destination"]);
+    std::fs::write(repo_path.join("file"), "a\nc\nd\nE\n").unwrap();
+
+    // The deletion of line 2 touches line 2 of the source
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["move", "--to", "@-", "--lines", "file:2"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    Working copy now at: kkmpptxz c107bd53 (no description set)
+    Parent commit      : rlvkpnrz e1f64341 destination
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 9405325339...3d9570c8e5 100644
+    --- a/file
+    +++ b/file
+    @@ -1,5 +1,4 @@
+     a
+    -b
+     c
+     d
+     e
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index 3d9570c8e5...687f74438d 100644
+    --- a/file
+    +++ b/file
+    @@ -1,4 +1,4 @@
+     a
+     c
+     d
+    -e
+    +E
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, cwd: &Path) -> String {
     let template = r#"commit_id.short() ++ " " ++ branches"#;
     test_env.jj_cmd_success(cwd, &["log", "-T", template])
@@ -147,6 +147,48 @@ fn test_new_merge() {
     "###);
 }
 
+#[test]
+fn test_new_merge_strategy() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main"]);
+    std::fs::write(repo_path.join("file1"), "a").unwrap();
+    std::fs::write(repo_path.join("file2"), "a").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()"]);
+    std::fs::write(repo_path.join("file1"), "b").unwrap();
+    std::fs::write(repo_path.join("file3"), "b").unwrap();
+
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["new", "main", "@", "--strategy", "theirs"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolved conflicts in zsuskuln 5eb0faf3 (no description set):
+      file1
+    Working copy now at: zsuskuln 5eb0faf3 (no description set)
+    Parent commit      : qpvuntsm 24fbfb3a main | (no description set)
+    Parent commit      : kkmpptxz 8e46e655 (no description set)
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "file1"]);
+    insta::assert_snapshot!(stdout, @"b");
+
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["new", "main", "@", "--strategy", "ours"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Resolved conflicts in yqosqzyt 542e6fea (no description set):
+      file1
+    Working copy now at: yqosqzyt 542e6fea (no description set)
+    Parent commit      : qpvuntsm 24fbfb3a main | (no description set)
+    Parent commit      : kkmpptxz 8e46e655 (no description set)
+    Added 1 files, modified 1 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "file1"]);
+    insta::assert_snapshot!(stdout, @"a");
+}
+
 #[test]
 fn test_new_insert_after() {
     let test_env = TestEnvironment::default();
@@ -464,7 +506,7 @@ fn test_new_insert_before_root() {
     let stderr =
         test_env.jj_cmd_failure(&repo_path, &["new", "--insert-before", "-m", "G", "root()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The root commit 000000000000 is immutable
+    Error[E1012]: The root commit 000000000000 is immutable
     "###);
 }
 
@@ -494,7 +536,7 @@ fn test_new_conflicting_branches() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "foo"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revset "foo" resolved to more than one revision
+    Error[E1014]: Revset "foo" resolved to more than one revision
     Hint: Branch foo resolved to multiple revisions because it's conflicted.
     It resolved to these revisions:
     kkmpptxz 66c6502d foo?? | (empty) two
@@ -518,11 +560,11 @@ fn test_new_conflicting_change_ids() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "qpvuntsm"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revset "qpvuntsm" resolved to more than one revision
+    Error[E1014]: Revset "qpvuntsm" resolved to more than one revision
     Hint: The revset "qpvuntsm" resolved to these revisions:
     qpvuntsm?? d2ae6806 (empty) two
     qpvuntsm?? a9330854 (empty) one
-    Some of these commits have the same change id. Abandon one of them with `jj abandon -r <REVISION>`.
+    Some of these commits have the same change id. Abandon one of them with `jj abandon -r <REVISION>`, or combine them with `jj resolve-divergence`.
     Prefix the expression with 'all' to allow any number of revisions (i.e. 'all:qpvuntsm').
     "###);
 }
@@ -132,13 +132,10 @@ fn test_obslog_with_or_without_diff() {
     @@ -1,1 +1,2 @@
      foo
     +bar
-    diff --git a/file2 b/file2
-    new file mode 100644
-    index 0000000000..257cc5642c
-    --- /dev/null
-    +++ b/file2
-    @@ -1,0 +1,1 @@
-    +foo
+    diff --git a/file1 b/file2
+    similarity index 100%
+    copy from file1
+    copy to file2
     rlvkpnrz hidden test.user@example.com 2001-02-03 04:05:08.000 +07:00 eac0d0da
     (empty) my description
     "###);
@@ -237,3 +234,87 @@ fn test_obslog_squash() {
          (empty) second
     "###);
 }
+
+#[test]
+fn test_obslog_interdiff() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "foo\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "my description"]);
+    std::fs::write(repo_path.join("file1"), "foo\nbar\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "foo\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["rebase", "-r", "@", "-d", "root()"]);
+    std::fs::write(repo_path.join("file1"), "resolved\n").unwrap();
+
+    // By default, the revision is compared to its previous version
+    let stdout = test_env.jj_cmd_success(&repo_path, &["obslog", "--interdiff", "--summary"]);
+    insta::assert_snapshot!(stdout, @r###"
+    M file1
+    "###);
+
+    // Compare with an older version. The rebase doesn't show up in the diff.
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["obslog", "--interdiff", "--from", "eac0d0da", "--git"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    new file mode 100644
+    index 0000000000..2ab19ae607
+    --- /dev/null
+    +++ b/file1
+    @@ -1,0 +1,1 @@
+    +resolved
+    diff --git a/file2 b/file2
+    new file mode 100644
+    index 0000000000..257cc5642c
+    --- /dev/null
+    +++ b/file2
+    @@ -1,0 +1,1 @@
+    +foo
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "obslog",
+            "--interdiff",
+            "--from",
+            "eac0d0da",
+            "--to",
+            "6fbba7bc",
+            "-s",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    M file1
+    C file1 -> file2
+    "###);
+
+    // The versions must belong to the change
+    let stderr =
+        test_env.jj_cmd_failure(&repo_path, &["obslog", "--interdiff", "--from", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 000000000000 is not a version of the change in commit 66b42ad36073
+    "###);
+
+    // The first version has nothing to compare with
+    let stderr =
+        test_env.jj_cmd_failure(&repo_path, &["obslog", "--interdiff", "--to", "eac0d0da"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit eac0d0dae082 has no previous version
+    "###);
+
+    // `--from` only applies to `--interdiff`
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["obslog", "--from", "eac0d0da"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the following required arguments were not provided:
+      --interdiff
+
+    Usage: jj obslog --interdiff --from <FROM>
+
+    For more information, try '--help'.
+    "###);
+}
@@ -449,3 +449,116 @@ fn get_log_output(test_env: &TestEnvironment, repo_path: &Path, op_id: &str) ->
         &["log", "-T", "commit_id", "--at-op", op_id, "-r", "all()"],
     )
 }
+
+#[test]
+fn test_op_sync() {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("git-repo");
+    git2::Repository::init_bare(git_repo_path).unwrap();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "clone", "git-repo", "a"]);
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "clone", "git-repo", "b"]);
+    let a_path = test_env.env_root().join("a");
+    let b_path = test_env.env_root().join("b");
+    let get_log = |repo_path: &Path| {
+        test_env.jj_cmd_success(repo_path, &["log", "-T", "description", "-r", "all()"])
+    };
+
+    std::fs::write(a_path.join("file"), "contents").unwrap();
+    test_env.jj_cmd_ok(&a_path, &["describe", "-m", "from a"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&a_path, &["op", "sync"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Exported 4 operations to origin
+    "###);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&a_path, &["op", "sync"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+
+    // The operations and commits from the other repo are merged
+    let git_repo = git2::Repository::open(test_env.env_root().join("git-repo")).unwrap();
+    let get_sync_commit = || {
+        git_repo
+            .find_reference("refs/jj/sync/op-log")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap()
+    };
+    let first_sync_commit = get_sync_commit();
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&b_path, &["op", "sync"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Concurrent modification detected, resolving automatically.
+    Imported 4 operations from origin
+    Exported 3 operations to origin
+    "###);
+    // Only the commits that the previous sync commit doesn't reach are added
+    // as parents
+    let sync_commit = get_sync_commit();
+    let parent_ids = sync_commit.parent_ids().collect_vec();
+    assert_eq!(parent_ids[0], first_sync_commit.id());
+    assert!(parent_ids.len() > 1);
+    for parent_id in &parent_ids[1..] {
+        assert!(!git_repo
+            .graph_descendant_of(first_sync_commit.id(), *parent_id)
+            .unwrap());
+    }
+    insta::assert_snapshot!(get_log(&b_path), @r###"
+    ◉  from a
+    │ @
+    ├─╯
+    ◉
+    "###);
+
+    // The working copy is updated if the working-copy commit was changed in
+    // the other repo
+    test_env.jj_cmd_ok(&b_path, &["describe", "-m", "from b"]);
+    test_env.jj_cmd_ok(&b_path, &["op", "sync"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&a_path, &["op", "sync"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Imported 4 operations from origin
+    Working copy now at: rlvkpnrz 34547f31 (empty) from b
+    Added 0 files, modified 0 files, removed 1 files
+    "###);
+    insta::assert_snapshot!(get_log(&a_path), @r###"
+    @  from b
+    │ ◉  from a
+    ├─╯
+    ◉
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&a_path, &["op", "log", "-T", "description ++ \"\\n\""]),
+        @r###"
+    @  describe commit 6acbecf3f4d4eeaa4b8e312d37aa764c4448156e
+    ◉    resolve concurrent operations
+    ├─╮
+    ◉ │  add workspace 'default'
+    ◉ │  initialize repo
+    │ ◉  describe commit feb7edf79ad12f60f02fa160ee6c7ebf34fca994
+    │ ◉  snapshot working copy
+    │ ◉  add workspace 'default'
+    │ ◉  initialize repo
+    ├─╯
+    ◉
+    "###);
+
+    // Operations from the other repo can be undone
+    test_env.jj_cmd_ok(&a_path, &["undo"]);
+    insta::assert_snapshot!(get_log(&a_path), @r###"
+    @
+    │ ◉  from a
+    ├─╯
+    ◉
+    "###);
+}
+
+#[test]
+fn test_op_sync_no_such_remote() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["op", "sync", "nonexistent"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No git remote named 'nonexistent'
+    "###);
+}
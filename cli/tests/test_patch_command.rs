@@ -0,0 +1,160 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_patch_apply() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\nc\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file"), "a\nB\nc\n").unwrap();
+    std::fs::write(repo_path.join("added"), "new\n").unwrap();
+    let patch = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    std::fs::write(test_env.env_root().join("git.patch"), &patch).unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["restore"]);
+
+    // Apply to the working-copy commit from a file
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["patch", "apply", "../git.patch"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: rlvkpnrz 4f822e55 (no description set)
+    Parent commit      : qpvuntsm bf8e1c93 (no description set)
+    Added 1 files, modified 1 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    assert_eq!(stdout, patch);
+
+    // Apply the reverse from stdin
+    let (stdout, stderr) =
+        test_env.jj_cmd_stdin_ok(&repo_path, &["patch", "apply", "--reverse"], &patch);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: rlvkpnrz 2115cd9d (empty) (no description set)
+    Parent commit      : qpvuntsm bf8e1c93 (no description set)
+    Added 0 files, modified 1 files, removed 1 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @"");
+
+    // A plain patch can be applied as a new commit on another revision
+    let plain_patch = "\
+--- file.orig
++++ file
+@@ -2 +2 @@
+-b
++X
+";
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(
+        &repo_path,
+        &["patch", "apply", "-p0", "-r", "@-", "-m", "Change b"],
+        plain_patch,
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Created new commit yostqsxw b126cde5 Change b
+    "###);
+    let template = r#"separate(" ", change_id.short(), description.first_line())"#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  yostqsxwqrlt Change b
+    │ @  rlvkpnrzqnoo
+    ├─╯
+    ◉  qpvuntsmwlqt
+    ◉  zzzzzzzzzzzz
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r", "description(b)", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index de980441c3...0c02cccd4c 100644
+    --- a/file
+    +++ b/file
+    @@ -1,3 +1,3 @@
+     a
+    -b
+    +X
+     c
+    "###);
+}
+
+#[test]
+fn test_patch_apply_fuzz() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "1\n2\n3\n4\n5\n6\n7\n").unwrap();
+    let patch = "\
+diff --git a/file b/file
+--- a/file
++++ b/file
+@@ -2,5 +2,5 @@
+ 2
+ 3
+-4
++four
+ 5
+ six
+";
+    std::fs::write(test_env.env_root().join("fuzz.patch"), patch).unwrap();
+    std::fs::write(test_env.env_root().join("invalid.patch"), "not a patch\n").unwrap();
+
+    // Nothing is changed if a hunk doesn't apply
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["patch", "apply", "../fuzz.patch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    file: 1 of 1 hunks don't apply
+    Error: Failed to apply the patch
+    Hint: Check that the patch is for this revision. Use --fuzz to allow context lines to differ.
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file
+    "###);
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["patch", "apply", "--fuzz", "1", "../fuzz.patch"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: qpvuntsm 8b54f219 (no description set)
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    let stdout = std::fs::read_to_string(repo_path.join("file")).unwrap();
+    insta::assert_snapshot!(stdout, @r###"
+    1
+    2
+    3
+    four
+    5
+    6
+    7
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["patch", "apply", "../invalid.patch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No changes found in the patch
+    "###);
+    let stderr =
+        test_env.jj_cmd_failure(&repo_path, &["patch", "apply", "-p", "2", "../fuzz.patch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse patch
+    Caused by: line 1: cannot strip 2 components from a/file
+    "###);
+}
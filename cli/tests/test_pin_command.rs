@@ -0,0 +1,99 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn get_git_ref_names(repo_path: &Path) -> String {
+    let git_repo = git2::Repository::open(repo_path).unwrap();
+    let names = git_repo
+        .references()
+        .unwrap()
+        .map(|reference| reference.unwrap().name().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    names.join("\n")
+}
+
+#[test]
+fn test_pin() {
+    let test_env = TestEnvironment::default();
+    let repo_path = test_env.env_root().join("repo");
+    git2::Repository::init(&repo_path).unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["init", "--git-repo=."]);
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m=first"]);
+    test_env.jj_cmd_ok(&repo_path, &["pin", "add", "bug"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m=second"]);
+    test_env.jj_cmd_ok(&repo_path, &["pin", "add", "good", "-r", "@"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m=third"]);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["pin", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bug: qpvuntsm 69542c19 (empty) first
+    good: zsuskuln 8348d73c (empty) second
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["pin", "list", "glob:b*"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bug: qpvuntsm 69542c19 (empty) first
+    "###);
+
+    // Pins can be used in revsets
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "-r", "pin(bug)::pin(good)", "-T", "description"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    ◉  second
+    ◉  first
+    │
+    ~
+    "###);
+
+    // Pins aren't exported to Git
+    insta::assert_snapshot!(get_git_ref_names(&repo_path), @r###"
+    refs/jj/keep/230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    refs/jj/keep/69542c1984c1f9d91f7c6c9c9e6941782c944bd9
+    refs/jj/keep/8348d73c39c86445646b39de3ec67511e0ad0cee
+    refs/jj/keep/b5d894a91da783a6a97f54f31e3cde9f97a11fd8
+    "###);
+
+    // Adding a pin with an existing name moves it
+    test_env.jj_cmd_ok(&repo_path, &["pin", "add", "bug", "-r", "@"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["pin", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bug: royxmykx b5d894a9 (empty) third
+    good: zsuskuln 8348d73c (empty) second
+    "###);
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["pin", "remove", "glob:*"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Removed 2 pins.
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["pin", "list"]);
+    insta::assert_snapshot!(stdout, @"");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["pin", "remove", "bug"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No such pin: bug
+    "###);
+
+    // Removing pins can be undone
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["pin", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    bug: royxmykx b5d894a9 (empty) third
+    good: zsuskuln 8348d73c (empty) second
+    "###);
+}
@@ -0,0 +1,136 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt as _;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Puts a `jj-<name>` shell script on `PATH`.
+fn add_plugin(test_env: &mut TestEnvironment, name: &str, script: &str) {
+    let plugin_dir = test_env.env_root().join("plugins");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    let path = plugin_dir.join(format!("jj-{name}"));
+    std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let paths = std::env::var("PATH").unwrap_or_default();
+    test_env.add_env_var("PATH", &format!("{}:{paths}", plugin_dir.display()));
+}
+
+#[test]
+fn test_plugin_runs_with_args() {
+    let mut test_env = TestEnvironment::default();
+    add_plugin(&mut test_env, "hello", r#"echo "hello $*""#);
+
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["hello", "world", "--flag"]);
+    insta::assert_snapshot!(stdout, @r###"
+    hello world --flag
+    "###);
+
+    // Options before the subcommand are parsed by jj
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["--no-pager", "hello", "-h"]);
+    insta::assert_snapshot!(stdout, @r###"
+    hello -h
+    "###);
+}
+
+#[test]
+fn test_plugin_repo_context() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    add_plugin(
+        &mut test_env,
+        "context",
+        r#"echo "root=$JJ_WORKSPACE_ROOT"; echo "op=$JJ_OPERATION_ID""#,
+    );
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["context"]);
+    let op_id = test_env.current_operation_id(&repo_path);
+    let stdout = stdout.replace(&op_id, "<op id>");
+    insta::assert_snapshot!(stdout, @r###"
+    root=$TEST_ENV/repo
+    op=<op id>
+    "###);
+
+    // Outside a repo, there's no context
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["context"]);
+    insta::assert_snapshot!(stdout, @r###"
+    root=
+    op=
+    "###);
+}
+
+#[test]
+fn test_plugin_exit_code() {
+    let mut test_env = TestEnvironment::default();
+    add_plugin(&mut test_env, "fail", "echo 'plugin failed' >&2; exit 7");
+
+    let assert = test_env
+        .jj_cmd(test_env.env_root(), &["fail"])
+        .assert()
+        .code(7);
+    insta::assert_snapshot!(common::get_stderr_string(&assert), @r###"
+    plugin failed
+    "###);
+}
+
+#[test]
+fn test_plugin_help() {
+    let mut test_env = TestEnvironment::default();
+    add_plugin(&mut test_env, "hello", "echo hello");
+    add_plugin(&mut test_env, "described", "echo described");
+    std::fs::write(
+        test_env.env_root().join("plugins/jj-described.toml"),
+        "about = 'Does something useful'\n",
+    )
+    .unwrap();
+    // A built-in command can't be replaced
+    add_plugin(&mut test_env, "log", "echo not the real log");
+
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["--help"]);
+    let plugins: Vec<_> = stdout
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| {
+            ["hello", "described", "log"]
+                .contains(&line.split_whitespace().next().unwrap_or_default())
+        })
+        .collect();
+    insta::assert_snapshot!(plugins.join("\n"), @r###"
+      log                 Show commit history
+      described           Does something useful
+      hello               External subcommand ($TEST_ENV/plugins/jj-hello)
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["log"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1001]: There is no jj repo in "."
+    "###);
+
+    // Unknown commands are still reported as such
+    let stderr = test_env.jj_cmd_cli_error(test_env.env_root(), &["nonexistent"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: unrecognized subcommand 'nonexistent'
+
+      tip: a similar subcommand exists: 'next'
+
+    Usage: jj [OPTIONS] <COMMAND>
+
+    For more information, try '--help'.
+    "###);
+}
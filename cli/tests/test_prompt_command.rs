@@ -0,0 +1,109 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_prompt() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["prompt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    change_id=qpvuntsmwlqt
+    commit_id=230dd059e1b0
+    description=
+    branches=
+    conflict=false
+    divergent=false
+    empty=true
+    operation_id=27143b59c690
+    "###);
+
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "first line\nsecond line"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main", "topic"]);
+    std::fs::write(repo_path.join("file"), "contents").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["status"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["prompt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    change_id=qpvuntsmwlqt
+    commit_id=9d7f1ee8ddd6
+    description=first line
+    branches=main topic
+    conflict=false
+    divergent=false
+    empty=false
+    operation_id=ad64d48b6761
+    "###);
+
+    // The working copy isn't snapshotted
+    std::fs::write(repo_path.join("file"), "modified").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["prompt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    change_id=qpvuntsmwlqt
+    commit_id=9d7f1ee8ddd6
+    description=first line
+    branches=main topic
+    conflict=false
+    divergent=false
+    empty=false
+    operation_id=ad64d48b6761
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["op", "log", "-l1", "--no-graph", "-T", "id.short()"],
+    );
+    insta::assert_snapshot!(stdout, @"ad64d48b6761");
+}
+
+#[test]
+fn test_prompt_conflict_and_divergence() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "a"]);
+    std::fs::write(repo_path.join("file"), "b").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "b"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "description(a)"]);
+    std::fs::write(repo_path.join("file"), "c").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "c"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "description(b)", "description(c)"]);
+    // Make the working-copy commit divergent
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "merge"]);
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "merge2", "--at-op", "@-"]);
+    let (stdout, _stderr) = test_env.jj_cmd_ok(&repo_path, &["prompt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    change_id=royxmykxtrkr
+    commit_id=9dbdb4c6b66b
+    description=merge
+    branches=
+    conflict=true
+    divergent=true
+    empty=true
+    operation_id=118cec3bca0c
+    "###);
+}
+
+#[test]
+fn test_prompt_no_repo() {
+    let test_env = TestEnvironment::default();
+    let stderr = test_env.jj_cmd_failure(test_env.env_root(), &["prompt"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1001]: There is no jj repo in "."
+    "###);
+}
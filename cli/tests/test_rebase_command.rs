@@ -43,9 +43,9 @@ fn test_rebase_invalid() {
     let stderr = test_env.jj_cmd_cli_error(&repo_path, &["rebase"]);
     insta::assert_snapshot!(stderr, @r###"
     error: the following required arguments were not provided:
-      --destination <DESTINATION>
+      <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
 
-    Usage: jj rebase --destination <DESTINATION>
+    Usage: jj rebase <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
 
     For more information, try '--help'.
     "###);
@@ -56,7 +56,7 @@ fn test_rebase_invalid() {
     insta::assert_snapshot!(stderr, @r###"
     error: the argument '--revision <REVISION>' cannot be used with '--source <SOURCE>'
 
-    Usage: jj rebase --destination <DESTINATION> --revision <REVISION>
+    Usage: jj rebase --revision <REVISION> <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
 
     For more information, try '--help'.
     "###);
@@ -67,7 +67,7 @@ fn test_rebase_invalid() {
     insta::assert_snapshot!(stderr, @r###"
     error: the argument '--branch <BRANCH>' cannot be used with '--source <SOURCE>'
 
-    Usage: jj rebase --destination <DESTINATION> --branch <BRANCH>
+    Usage: jj rebase --branch <BRANCH> <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
 
     For more information, try '--help'.
     "###);
@@ -80,7 +80,7 @@ fn test_rebase_invalid() {
     insta::assert_snapshot!(stderr, @r###"
     error: the argument '--revision <REVISION>' cannot be used with '--skip-empty'
 
-    Usage: jj rebase --destination <DESTINATION> --revision <REVISION>
+    Usage: jj rebase --revision <REVISION> <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
 
     For more information, try '--help'.
     "###);
@@ -94,7 +94,7 @@ fn test_rebase_invalid() {
     // Rebase root with -r
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-r", "root()", "-d", "a"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The root commit 000000000000 is immutable
+    Error[E1012]: The root commit 000000000000 is immutable
     "###);
 
     // Rebase onto descendant with -s
@@ -167,7 +167,7 @@ fn test_rebase_branch() {
     test_env.jj_cmd_ok(&repo_path, &["undo"]);
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-b=e|d", "-d=b"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revset "e|d" resolved to more than one revision
+    Error[E1014]: Revset "e|d" resolved to more than one revision
     Hint: The revset "e|d" resolved to these revisions:
     znkkpsqq e52756c8 e | e
     vruxwmqv 514fa6b2 d | d
@@ -472,7 +472,7 @@ fn test_rebase_multiple_destinations() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-r", "a", "-d", "b|c"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revset "b|c" resolved to more than one revision
+    Error[E1014]: Revset "b|c" resolved to more than one revision
     Hint: The revset "b|c" resolved to these revisions:
     royxmykx fe2e8e8b c | c
     zsuskuln d370aee1 b | b
@@ -604,7 +604,7 @@ fn test_rebase_with_descendants() {
     test_env.jj_cmd_ok(&repo_path, &["undo"]);
     let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s=b|d", "-d=a"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revset "b|d" resolved to more than one revision
+    Error[E1014]: Revset "b|d" resolved to more than one revision
     Hint: The revset "b|d" resolved to these revisions:
     vruxwmqv df54a9fd d | d
     zsuskuln d370aee1 b | b
@@ -628,6 +628,259 @@ fn test_rebase_with_descendants() {
     "###);
 }
 
+#[test]
+fn test_rebase_insert_after() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    create_commit(&test_env, &repo_path, "c", &["b"]);
+    create_commit(&test_env, &repo_path, "d", &["a"]);
+    create_commit(&test_env, &repo_path, "e", &["d"]);
+    // Test the setup
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  e
+    ◉  d
+    │ ◉  c
+    │ ◉  b
+    ├─╯
+    ◉  a
+    ◉
+    "###);
+
+    // Insert d and e between a and b
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["rebase", "-s", "d", "-A", "a"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 4 commits
+    Working copy now at: znkkpsqq 6075e35f e | e
+    Parent commit      : vruxwmqv fcc805c4 d | d
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    ◉  c
+    ◉  b
+    @  e
+    ◉  d
+    ◉  a
+    ◉
+    "###);
+
+    // Insert the branch with b and c after d, so that e becomes a child of c
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["rebase", "-b", "c", "-A", "d"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 3 commits
+    Working copy now at: znkkpsqq 5feac633 e | e
+    Parent commit      : royxmykx 7b356064 c | c
+    Added 2 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  e
+    ◉  c
+    ◉  b
+    ◉  d
+    ◉  a
+    ◉
+    "###);
+
+    // Can't insert after a rebased revision
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s", "b", "-A", "c"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot rebase 6edd30b1fb5a onto descendant 7b3560647957
+    "###);
+}
+
+#[test]
+fn test_rebase_insert_before() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    create_commit(&test_env, &repo_path, "c", &["b"]);
+    create_commit(&test_env, &repo_path, "d", &["a"]);
+    create_commit(&test_env, &repo_path, "e", &["d"]);
+
+    // Insert d and e between b and c
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["rebase", "-s", "d", "-B", "c"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 3 commits
+    Working copy now at: znkkpsqq ce020b2c e | e
+    Parent commit      : vruxwmqv d6cdecff d | d
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    ◉  c
+    @  e
+    ◉  d
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+
+    // Insert before two revisions, which become children of the rebased
+    // revision
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()"]);
+    create_commit(&test_env, &repo_path, "f", &[]);
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["rebase", "-s", "f", "-B", "b", "-B", "d"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 5 commits
+    Working copy now at: nkmrtpmo 82f4947a f | f
+    Parent commit      : rlvkpnrz 2443ea76 a | a
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    ◉  e
+    ◉  d
+    │ ◉  c
+    │ ◉  b
+    ├─╯
+    @  f
+    ◉  a
+    ◉
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s", "b", "-B", "c"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot insert revisions before 4eaf06e9f1cd, which is being rebased
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s", "b", "-B", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error[E1012]: The root commit 000000000000 is immutable
+    "###);
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["rebase", "-r", "b", "-B", "a"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--revision <REVISION>' cannot be used with '--insert-before <INSERT_BEFORE>'
+
+    Usage: jj rebase --revision <REVISION> <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
+
+    For more information, try '--help'.
+    "###);
+}
+
+#[test]
+fn test_rebase_skip_empty() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    // b makes the same change as a
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()", "-m", "b"]);
+    std::fs::write(repo_path.join("a"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "b"]);
+    create_commit(&test_env, &repo_path, "c", &["b"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["rebase", "-s", "b", "-d", "a", "--skip-empty"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 commits
+    Abandoned 1 commits that became empty:
+      zsuskuln hidden deb2d8da b
+    Working copy now at: royxmykx 41889fcd c | c
+    Parent commit      : rlvkpnrz 2443ea76 a b | a
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  c
+    ◉  a b
+    ◉
+    "###);
+
+    // The config skips empty commits too, unless --keep-empty is given
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    test_env.add_config("rebase.skip-empty = true");
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["rebase", "-s", "b", "-d", "a", "--keep-empty"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 2 commits
+    Working copy now at: royxmykx 4da99a0c c | c
+    Parent commit      : zsuskuln c76b0b44 b | (empty) b
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["rebase", "-s", "b", "-d", "a"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 commits
+    Abandoned 1 commits that became empty:
+      zsuskuln hidden deb2d8da b
+    Working copy now at: royxmykx c3c483d2 c | c
+    Parent commit      : rlvkpnrz 2443ea76 a b | a
+    "###);
+}
+
+#[test]
+fn test_rebase_strategy() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[]);
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    create_commit(&test_env, &repo_path, "b", &["a"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    create_commit(&test_env, &repo_path, "c", &["a"]);
+    std::fs::write(repo_path.join("file"), "c\n").unwrap();
+    create_commit(&test_env, &repo_path, "d", &["c"]);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["rebase", "-s", "c", "-d", "b", "--strategy", "ours"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 2 commits
+    Resolved conflicts in royxmykx aa45ac27 c | c:
+      file
+    Working copy now at: vruxwmqv 90a48276 d | d
+    Parent commit      : royxmykx aa45ac27 c | c
+    Added 1 files, modified 1 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "c", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    b
+    "###);
+
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["rebase", "-s", "c", "-d", "b", "--strategy", "theirs"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 2 commits
+    Resolved conflicts in royxmykx a277c942 c | c:
+      file
+    Working copy now at: vruxwmqv c84ace00 d | d
+    Parent commit      : royxmykx a277c942 c | c
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "c", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    c
+    "###);
+
+    let stderr = test_env.jj_cmd_cli_error(
+        &repo_path,
+        &["rebase", "-r", "c", "-d", "b", "--strategy", "ours"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--revision <REVISION>' cannot be used with '--strategy <STRATEGY>'
+
+    Usage: jj rebase --revision <REVISION> <--destination <DESTINATION>|--insert-after <INSERT_AFTER>|--insert-before <INSERT_BEFORE>>
+
+    For more information, try '--help'.
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     test_env.jj_cmd_success(repo_path, &["log", "-T", "branches"])
 }
@@ -14,7 +14,7 @@
 
 use std::path::Path;
 
-use crate::common::TestEnvironment;
+use crate::common::{get_stderr_string, get_stdout_string, TestEnvironment};
 
 pub mod common;
 
@@ -64,7 +64,7 @@ fn test_resolution() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict
+    1: file    2-sided conflict
     "###);
     insta::assert_snapshot!(
     std::fs::read_to_string(repo_path.join("file")).unwrap()
@@ -198,7 +198,7 @@ conflict
     Parent commit      : royxmykx db6a4daf b | b
     Added 0 files, modified 1 files, removed 0 files
     After this operation, some files at this revision still have conflicts:
-    file    2-sided conflict
+    1: file    2-sided conflict
     "###);
     insta::assert_snapshot!(
         std::fs::read_to_string(test_env.env_root().join("editor2")).unwrap(), @r###"
@@ -224,7 +224,7 @@ conflict
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict
+    1: file    2-sided conflict
     "###);
 
     // Check that if merge tool leaves conflict markers in output file but
@@ -328,7 +328,7 @@ fn test_normal_conflict_input_files() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict
+    1: file    2-sided conflict
     "###);
     insta::assert_snapshot!(
     std::fs::read_to_string(repo_path.join("file")).unwrap()
@@ -369,7 +369,7 @@ fn test_baseless_conflict_input_files() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict
+    1: file    2-sided conflict
     "###);
     insta::assert_snapshot!(
     std::fs::read_to_string(repo_path.join("file")).unwrap()
@@ -400,12 +400,12 @@ fn test_too_many_parents() {
     create_commit(&test_env, &repo_path, "conflict", &["a", "b", "c"], &[]);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    3-sided conflict
+    1: file    3-sided conflict
     "###);
     // Test warning color
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "--color=always"]), 
     @r###"
-    file    [38;5;1m3-sided[38;5;3m conflict[39m
+    1: file    [38;5;1m3-sided[38;5;3m conflict[39m
     "###);
 
     let error = test_env.jj_cmd_failure(&repo_path, &["resolve"]);
@@ -439,7 +439,7 @@ fn test_edit_delete_conflict_input_files() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict including 1 deletion
+    1: file    2-sided modify/delete conflict
     "###);
     insta::assert_snapshot!(
     std::fs::read_to_string(repo_path.join("file")).unwrap()
@@ -457,6 +457,140 @@ fn test_edit_delete_conflict_input_files() {
     check_resolve_produces_input_file(&mut test_env, &repo_path, "right", "");
 }
 
+#[test]
+fn test_resolve_take_modify_delete() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(
+        &test_env,
+        &repo_path,
+        "base",
+        &[],
+        &[
+            ("file1", "base\n"),
+            ("file2", "base\n"),
+            ("file3", "base\n"),
+        ],
+    );
+    create_commit(
+        &test_env,
+        &repo_path,
+        "a",
+        &["base"],
+        &[("file1", "a\n"), ("file2", "a\n"), ("file3", "a\n")],
+    );
+    create_commit(&test_env, &repo_path, "b", &["base"], &[("file3", "b\n")]);
+    std::fs::remove_file(repo_path.join("file1")).unwrap();
+    std::fs::remove_file(repo_path.join("file2")).unwrap();
+    create_commit(&test_env, &repo_path, "conflict", &["a", "b"], &[]);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), @r###"
+    1: file1    2-sided modify/delete conflict
+    2: file2    2-sided modify/delete conflict
+    3: file3    2-sided conflict
+    "###);
+
+    // Only the given paths are resolved
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["resolve", "--take", "deleted", "file1"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file1
+    New conflicts appeared in these commits:
+      vruxwmqv c615fb8b conflict | (conflict) conflict
+    To resolve the conflicts, start by updating to it:
+      jj new vruxwmqvtpmx
+    Then use `jj resolve`, or edit the conflict markers in the file directly.
+    Once the conflicts are resolved, you may want inspect the result with `jj diff`.
+    Then run `jj squash` to move the resolution into the conflicted commit.
+    Working copy now at: vruxwmqv c615fb8b conflict | (conflict) conflict
+    Parent commit      : zsuskuln c0a4f9c5 a | a
+    Parent commit      : royxmykx c8b40407 b | b
+    Added 0 files, modified 0 files, removed 1 files
+    After this operation, some files at this revision still have conflicts:
+    2: file2    2-sided modify/delete conflict
+    3: file3    2-sided conflict
+    "###);
+    assert!(!repo_path.join("file1").exists());
+
+    // The other conflicts are left alone
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["resolve", "--take", "modified"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file2
+    Working copy now at: vruxwmqv 470b00d2 conflict | (conflict) conflict
+    Parent commit      : zsuskuln c0a4f9c5 a | a
+    Parent commit      : royxmykx c8b40407 b | b
+    Added 0 files, modified 1 files, removed 0 files
+    After this operation, some files at this revision still have conflicts:
+    3: file3    2-sided conflict
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file2")).unwrap(), @r###"
+    a
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve", "--take", "modified"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No conflicts between a modified and a deleted file found
+    "###);
+}
+
+#[test]
+fn test_resolve_tree_conflict_prompt() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "base", &[], &[("file", "base\n")]);
+    create_commit(&test_env, &repo_path, "a", &["base"], &[("file", "a\n")]);
+    create_commit(&test_env, &repo_path, "b", &["base"], &[]);
+    std::fs::remove_file(repo_path.join("file")).unwrap();
+    std::fs::create_dir(repo_path.join("file")).unwrap();
+    std::fs::write(repo_path.join("file").join("placeholder"), "").unwrap();
+    create_commit(&test_env, &repo_path, "conflict", &["a", "b"], &[]);
+
+    let assert = test_env
+        .jj_cmd_stdin(&repo_path, &["resolve"], "q\n")
+        .assert()
+        .code(1);
+    let stdout = test_env.normalize_output(&get_stdout_string(&assert));
+    let stderr = test_env.normalize_output(&get_stderr_string(&assert));
+    insta::assert_snapshot!(stdout, @r###"
+    The conflict can't be merged, choose the side to keep:
+    1: a file
+    2: a directory
+    q: quit the prompt
+    enter the side to keep: 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file
+    Error: The conflict was left unresolved
+    "###);
+
+    // Keep the directory
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(&repo_path, &["resolve"], "2\n");
+    insta::assert_snapshot!(stdout, @r###"
+    The conflict can't be merged, choose the side to keep:
+    1: a file
+    2: a directory
+    q: quit the prompt
+    enter the side to keep: 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file
+    Working copy now at: vruxwmqv 7f2fb994 conflict | conflict
+    Parent commit      : zsuskuln aa493daf a | a
+    Parent commit      : royxmykx 8602fc65 b | b
+    Added 1 files, modified 0 files, removed 1 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r=conflict", "--summary"]);
+    insta::assert_snapshot!(stdout, @r###"
+    D file
+    A file/placeholder
+    "###);
+}
+
 #[test]
 fn test_file_vs_dir() {
     let test_env = TestEnvironment::default();
@@ -483,7 +617,7 @@ fn test_file_vs_dir() {
 
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    2-sided conflict including a directory
+    1: file    2-sided conflict including a directory
     "###);
     let error = test_env.jj_cmd_failure(&repo_path, &["resolve"]);
     insta::assert_snapshot!(error, @r###"
@@ -534,12 +668,12 @@ fn test_description_with_dir_and_deletion() {
 
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    file    3-sided conflict including 1 deletion and a directory
+    1: file    3-sided conflict including 1 deletion and a directory
     "###);
     // Test warning color. The deletion is fine, so it's not highlighted
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "--color=always"]), 
     @r###"
-    file    [38;5;1m3-sided[38;5;3m conflict including 1 deletion and [38;5;1ma directory[39m
+    1: file    [38;5;1m3-sided[38;5;3m conflict including 1 deletion and [38;5;1ma directory[39m
     "###);
     let error = test_env.jj_cmd_failure(&repo_path, &["resolve"]);
     insta::assert_snapshot!(error, @r###"
@@ -635,14 +769,14 @@ fn test_multiple_conflicts() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    another_file                        2-sided conflict
-    this_file_has_a_very_long_name_to_test_padding 2-sided conflict
+    1: another_file                        2-sided conflict
+    2: this_file_has_a_very_long_name_to_test_padding 2-sided conflict
     "###);
     // Test colors
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "--color=always"]), 
     @r###"
-    another_file                        [38;5;3m2-sided conflict[39m
-    this_file_has_a_very_long_name_to_test_padding [38;5;3m2-sided conflict[39m
+    1: another_file                        [38;5;3m2-sided conflict[39m
+    2: this_file_has_a_very_long_name_to_test_padding [38;5;3m2-sided conflict[39m
     "###);
 
     let editor_script = test_env.set_up_fake_editor();
@@ -665,7 +799,7 @@ fn test_multiple_conflicts() {
     Parent commit      : royxmykx f68bc2f0 b | b
     Added 0 files, modified 1 files, removed 0 files
     After this operation, some files at this revision still have conflicts:
-    this_file_has_a_very_long_name_to_test_padding 2-sided conflict
+    2: this_file_has_a_very_long_name_to_test_padding 2-sided conflict
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["diff"]), 
     @r###"
@@ -680,7 +814,7 @@ fn test_multiple_conflicts() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    this_file_has_a_very_long_name_to_test_padding 2-sided conflict
+    2: this_file_has_a_very_long_name_to_test_padding 2-sided conflict
     "###);
 
     // Repeat the above with the `--quiet` option.
@@ -727,7 +861,7 @@ fn test_multiple_conflicts() {
     "###);
     insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), 
     @r###"
-    this_file_has_a_very_long_name_to_test_padding 2-sided conflict
+    2: this_file_has_a_very_long_name_to_test_padding 2-sided conflict
     "###);
     std::fs::write(
         &editor_script,
@@ -765,3 +899,118 @@ fn test_multiple_conflicts() {
     Error: No conflicts found at this revision
     "###);
 }
+
+#[test]
+fn test_resolve_numbered_conflicts() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(
+        &test_env,
+        &repo_path,
+        "base",
+        &[],
+        &[
+            ("file1", "base\n"),
+            ("file2", "base\n"),
+            ("file3", "base\n"),
+        ],
+    );
+    create_commit(
+        &test_env,
+        &repo_path,
+        "a",
+        &["base"],
+        &[("file1", "a\n"), ("file2", "a\n"), ("file3", "a\n")],
+    );
+    create_commit(
+        &test_env,
+        &repo_path,
+        "b",
+        &["base"],
+        &[("file1", "bb\n"), ("file2", "bb\n")],
+    );
+    std::fs::remove_file(repo_path.join("file3")).unwrap();
+    create_commit(&test_env, &repo_path, "conflict", &["a", "b"], &[]);
+
+    // The numbers don't change when filtering by path
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "file2"]), @r###"
+    2: file2    2-sided conflict
+    "###);
+    let template =
+        r#"separate(" ", number, path, sides, types.join(","), sizes.join(",")) ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    1 file1 2 file,file 2,3
+    2 file2 2 file,file 2,3
+    3 file3 2 file,absent 2,0
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve", "4"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: There is no conflict number 4 at this revision
+    "###);
+
+    // The numbers don't change when a conflict is resolved by editing the file
+    std::fs::write(repo_path.join("file1"), "resolution file1\n").unwrap();
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["resolve", "--list"]), @r###"
+    2: file2    2-sided conflict
+    3: file3    2-sided modify/delete conflict
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+
+    let editor_script = test_env.set_up_fake_editor();
+
+    // Resolve a conflict by its number
+    std::fs::write(&editor_script, "write\nresolution file2\n").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["resolve", "2"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file2
+    New conflicts appeared in these commits:
+      vruxwmqv 9323fef7 conflict | (conflict) conflict
+    To resolve the conflicts, start by updating to it:
+      jj new vruxwmqvtpmx
+    Then use `jj resolve`, or edit the conflict markers in the file directly.
+    Once the conflicts are resolved, you may want inspect the result with `jj diff`.
+    Then run `jj squash` to move the resolution into the conflicted commit.
+    Working copy now at: vruxwmqv 9323fef7 conflict | (conflict) conflict
+    Parent commit      : zsuskuln c0a4f9c5 a | a
+    Parent commit      : royxmykx 56a5cb00 b | b
+    Added 0 files, modified 1 files, removed 0 files
+    After this operation, some files at this revision still have conflicts:
+    1: file1    2-sided conflict
+    3: file3    2-sided modify/delete conflict
+    "###);
+
+    // Resolve all the other conflicts except one
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+    std::fs::write(
+        &editor_script,
+        "write\nresolution file1\n\0next invocation\n\0write\nresolution file2\n",
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["resolve", "--all-except", "3"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolving conflicts in: file1
+    Resolving conflicts in: file2
+    New conflicts appeared in these commits:
+      vruxwmqv e0383b2d conflict | (conflict) conflict
+    To resolve the conflicts, start by updating to it:
+      jj new vruxwmqvtpmx
+    Then use `jj resolve`, or edit the conflict markers in the file directly.
+    Once the conflicts are resolved, you may want inspect the result with `jj diff`.
+    Then run `jj squash` to move the resolution into the conflicted commit.
+    Working copy now at: vruxwmqv e0383b2d conflict | (conflict) conflict
+    Parent commit      : zsuskuln c0a4f9c5 a | a
+    Parent commit      : royxmykx 56a5cb00 b | b
+    Added 0 files, modified 2 files, removed 0 files
+    After this operation, some files at this revision still have conflicts:
+    3: file3    2-sided modify/delete conflict
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file2")).unwrap(), @r###"
+    resolution file2
+    "###);
+}
@@ -0,0 +1,194 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template =
+        r#"change_id.short() ++ " " ++ description.first_line() ++ if(divergent, " !divergence!")"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
+
+/// Creates a change with versions described as "one" and "two", on top of a
+/// parent with `file` containing "base", and a child on top of "one".
+fn create_divergence(test_env: &TestEnvironment, repo_path: &Path) {
+    std::fs::write(repo_path.join("file"), "base\n").unwrap();
+    test_env.jj_cmd_ok(repo_path, &["commit", "-m", "base"]);
+    std::fs::write(repo_path.join("file"), "base\none\n").unwrap();
+    test_env.jj_cmd_ok(repo_path, &["describe", "-m", "one"]);
+    // Rewrite the change concurrently
+    test_env.jj_cmd_ok(
+        repo_path,
+        &["describe", "-m", "two", "--at-operation", "@-"],
+    );
+    test_env.jj_cmd_ok(repo_path, &["new", "description(one)", "-m", "child"]);
+}
+
+#[test]
+fn test_resolve_divergence_keep() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    create_divergence(&test_env, &repo_path);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  mzvwutvlkqwt child
+    ◉  rlvkpnrzqnoo one !divergence!
+    │ ◉  rlvkpnrzqnoo two !divergence!
+    ├─╯
+    ◉  qpvuntsmwlqt base
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    // The version must belong to the change
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["resolve-divergence", "description(one)", "--keep", "@"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 68c121ec063e is not a version of change rlvkpnrzqnoo
+    "###);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "resolve-divergence",
+            "description(one)",
+            "--keep",
+            "description(two)",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolved divergent change to rlvkpnrz 0901da2e two
+    Rebased 1 descendant commits
+    Working copy now at: mzvwutvl c88b968d (empty) child
+    Parent commit      : rlvkpnrz 0901da2e two
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  mzvwutvlkqwt child
+    ◉  rlvkpnrzqnoo two
+    ◉  qpvuntsmwlqt base
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    // The change is no longer divergent
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve-divergence", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Change rlvkpnrzqnoo is not divergent
+    "###);
+}
+
+#[test]
+fn test_resolve_divergence_merge() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    create_divergence(&test_env, &repo_path);
+    // Change the contents of the other version too
+    test_env.jj_cmd_ok(&repo_path, &["new", "description(two)"]);
+    std::fs::write(repo_path.join("file2"), "two\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["squash"]);
+    test_env.jj_cmd_ok(&repo_path, &["edit", "description(child)"]);
+
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(&edit_script, "dump editor0\0write\none and two").unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["resolve-divergence", "description(one)", "--merge"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Resolved divergent change to rlvkpnrz 2caec317 one and two
+    Rebased 1 descendant commits
+    Working copy now at: mzvwutvl 9a6d899e (empty) child
+    Parent commit      : rlvkpnrz 2caec317 one and two
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("editor0")).unwrap(), @r###"
+    JJ: Enter a description for the merged versions of the change.
+    JJ: Description from version 1:
+    two
+    JJ: Description from version 2:
+    one
+
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  mzvwutvlkqwt child
+    ◉  rlvkpnrzqnoo one and two
+    ◉  qpvuntsmwlqt base
+    ◉  zzzzzzzzzzzz
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r", "@-", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file b/file
+    index df967b96a5...6d8dab9ba8 100644
+    --- a/file
+    +++ b/file
+    @@ -1,1 +1,2 @@
+     base
+    +one
+    diff --git a/file2 b/file2
+    new file mode 100644
+    index 0000000000..f719efd430
+    --- /dev/null
+    +++ b/file2
+    @@ -1,0 +1,1 @@
+    +two
+    "###);
+}
+
+#[test]
+fn test_resolve_divergence_prompt() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    create_divergence(&test_env, &repo_path);
+
+    // The change id can be used even though it's divergent
+    let change_id = test_env.jj_cmd_success(
+        &repo_path,
+        &["log", "--no-graph", "-r", "@-", "-T", "change_id.short()"],
+    );
+    let (stdout, stderr) =
+        test_env.jj_cmd_stdin_ok(&repo_path, &["resolve-divergence", &change_id], "2\n");
+    insta::assert_snapshot!(stdout, @r###"
+    Change rlvkpnrzqnoo is divergent, choose the version to keep:
+    1: rlvkpnrz?? 0901da2e two
+    2: rlvkpnrz?? 9924e620 one
+    m: merge all versions
+    q: quit the prompt
+    enter the version to keep: 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Resolved divergent change to rlvkpnrz 9924e620 one
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  mzvwutvlkqwt child
+    ◉  rlvkpnrzqnoo one
+    ◉  qpvuntsmwlqt base
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    // Not divergent
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve-divergence"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Change mzvwutvlkqwt is not divergent
+    "###);
+}
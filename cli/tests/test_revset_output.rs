@@ -24,7 +24,7 @@ fn test_syntax_error() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "x &"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:4
+    Error[E1010]: Failed to parse revset:  --> 1:4
       |
     1 | x &
       |    ^---
@@ -34,7 +34,7 @@ fn test_syntax_error() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "x - y"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:3
+    Error[E1010]: Failed to parse revset:  --> 1:3
       |
     1 | x - y
       |   ^
@@ -45,7 +45,7 @@ fn test_syntax_error() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "HEAD^"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:5
+    Error[E1010]: Failed to parse revset:  --> 1:5
       |
     1 | HEAD^
       |     ^
@@ -63,7 +63,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "all(or::nothing)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:5
+    Error[E1010]: Failed to parse revset:  --> 1:5
       |
     1 | all(or::nothing)
       |     ^---------^
@@ -73,7 +73,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "parents()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:9
+    Error[E1010]: Failed to parse revset:  --> 1:9
       |
     1 | parents()
       |         ^
@@ -83,7 +83,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "parents(foo, bar)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:9
+    Error[E1010]: Failed to parse revset:  --> 1:9
       |
     1 | parents(foo, bar)
       |         ^------^
@@ -93,7 +93,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "heads(foo, bar)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:7
+    Error[E1010]: Failed to parse revset:  --> 1:7
       |
     1 | heads(foo, bar)
       |       ^------^
@@ -103,7 +103,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "latest(a, not_an_integer)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:11
+    Error[E1010]: Failed to parse revset:  --> 1:11
       |
     1 | latest(a, not_an_integer)
       |           ^------------^
@@ -113,7 +113,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "file()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:6
+    Error[E1010]: Failed to parse revset:  --> 1:6
       |
     1 | file()
       |      ^
@@ -123,7 +123,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "file(a, not:a-string)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:9
+    Error[E1010]: Failed to parse revset:  --> 1:9
       |
     1 | file(a, not:a-string)
       |         ^----------^
@@ -133,7 +133,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", r#"file(a, "../out")"#]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:9
+    Error[E1010]: Failed to parse revset:  --> 1:9
       |
     1 | file(a, "../out")
       |         ^------^
@@ -143,7 +143,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "branches(bad:pattern)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:10
+    Error[E1010]: Failed to parse revset:  --> 1:10
       |
     1 | branches(bad:pattern)
       |          ^---------^
@@ -153,7 +153,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "root()::whatever()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:9
+    Error[E1010]: Failed to parse revset:  --> 1:9
       |
     1 | root()::whatever()
       |         ^------^
@@ -166,7 +166,7 @@ fn test_bad_function_call() {
         &["log", "-r", "remote_branches(a, b, remote=c)"],
     );
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:23
+    Error[E1010]: Failed to parse revset:  --> 1:23
       |
     1 | remote_branches(a, b, remote=c)
       |                       ^------^
@@ -177,7 +177,7 @@ fn test_bad_function_call() {
     let stderr =
         test_env.jj_cmd_failure(&repo_path, &["log", "-r", "remote_branches(remote=a, b)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:27
+    Error[E1010]: Failed to parse revset:  --> 1:27
       |
     1 | remote_branches(remote=a, b)
       |                           ^
@@ -187,7 +187,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "remote_branches(=foo)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:17
+    Error[E1010]: Failed to parse revset:  --> 1:17
       |
     1 | remote_branches(=foo)
       |                 ^---
@@ -197,7 +197,7 @@ fn test_bad_function_call() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "remote_branches(remote=)"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:24
+    Error[E1010]: Failed to parse revset:  --> 1:24
       |
     1 | remote_branches(remote=)
       |                        ^---
@@ -224,7 +224,7 @@ fn test_function_name_hint() {
 
     // The suggestion "branches" shouldn't be duplicated
     insta::assert_snapshot!(evaluate_err("branch()"), @r###"
-    Error: Failed to parse revset:  --> 1:1
+    Error[E1010]: Failed to parse revset:  --> 1:1
       |
     1 | branch()
       | ^----^
@@ -235,7 +235,7 @@ fn test_function_name_hint() {
 
     // Both builtin function and function alias should be suggested
     insta::assert_snapshot!(evaluate_err("author_()"), @r###"
-    Error: Failed to parse revset:  --> 1:1
+    Error[E1010]: Failed to parse revset:  --> 1:1
       |
     1 | author_()
       | ^-----^
@@ -276,7 +276,7 @@ fn test_alias() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "root() & syntax-error"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:10
+    Error[E1010]: Failed to parse revset:  --> 1:10
       |
     1 | root() & syntax-error
       |          ^----------^
@@ -292,7 +292,7 @@ fn test_alias() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "identity()"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:10
+    Error[E1010]: Failed to parse revset:  --> 1:10
       |
     1 | identity()
       |          ^
@@ -302,7 +302,7 @@ fn test_alias() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "my_author(none())"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:1
+    Error[E1010]: Failed to parse revset:  --> 1:1
       |
     1 | my_author(none())
       | ^---------------^
@@ -318,7 +318,7 @@ fn test_alias() {
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "root() & recurse"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: Failed to parse revset:  --> 1:10
+    Error[E1010]: Failed to parse revset:  --> 1:10
       |
     1 | root() & recurse
       |          ^-----^
@@ -371,7 +371,7 @@ fn test_alias_override() {
         ],
     );
     insta::assert_snapshot!(stderr, @r###"
-    Error: Revision "arg" doesn't exist
+    Error[E1011]: Revision "arg" doesn't exist
     "###);
 }
 
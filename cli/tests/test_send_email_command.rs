@@ -0,0 +1,243 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_send_email() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(
+        r#"
+        [send-email]
+        to = ["List <list@example.com>"]
+        cc = ["maintainer@example.com"]
+        "#,
+    );
+
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Add file"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "Update file"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+
+    // The emails are replies to the first one
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "send-email",
+            "-r",
+            "root()..@",
+            "--to",
+            "other@example.com",
+            "--dry-run",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    From: Test User <test.user@example.com>
+    To: List <list@example.com>, other@example.com
+    Cc: maintainer@example.com
+    Subject: [PATCH 1/2] Add file
+    Date: Sat, 3 Feb 2001 04:05:10 +0700
+    Message-Id: <jj.981147910000.0.test.user@example.com>
+
+    Change-Id: qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    ---
+    file | 1 +
+    1 file changed, 1 insertion(+), 0 deletions(-)
+
+    diff --git a/file b/file
+    new file mode 100644
+    index 0000000000..7898192261
+    --- /dev/null
+    +++ b/file
+    @@ -1,0 +1,1 @@
+    +a
+
+    From: Test User <test.user@example.com>
+    To: List <list@example.com>, other@example.com
+    Cc: maintainer@example.com
+    Subject: [PATCH 2/2] Update file
+    Date: Sat, 3 Feb 2001 04:05:11 +0700
+    Message-Id: <jj.981147910000.1.test.user@example.com>
+    In-Reply-To: <jj.981147910000.0.test.user@example.com>
+    References: <jj.981147910000.0.test.user@example.com>
+
+    Change-Id: kkmpptxzrspxrzommnulwmwkkqwworpl
+    ---
+    file | 2 +-
+    1 file changed, 1 insertion(+), 1 deletion(-)
+
+    diff --git a/file b/file
+    index 7898192261...6178079822 100644
+    --- a/file
+    +++ b/file
+    @@ -1,1 +1,1 @@
+    -a
+    +b
+
+    "###);
+
+    // The author is given in the body if it's not the sender
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "send-email",
+            "--dry-run",
+            "--config-toml",
+            "send-email.from='Sender <sender@example.com>'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    From: Sender <sender@example.com>
+    To: List <list@example.com>
+    Cc: maintainer@example.com
+    Subject: [PATCH] Update file
+    Date: Sat, 3 Feb 2001 04:05:11 +0700
+    Message-Id: <jj.981147911000.0.sender@example.com>
+
+    From: Test User <test.user@example.com>
+
+    Change-Id: kkmpptxzrspxrzommnulwmwkkqwworpl
+    ---
+    file | 2 +-
+    1 file changed, 1 insertion(+), 1 deletion(-)
+
+    diff --git a/file b/file
+    index 7898192261...6178079822 100644
+    --- a/file
+    +++ b/file
+    @@ -1,1 +1,1 @@
+    -a
+    +b
+
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "send-email",
+            "--dry-run",
+            "--config-toml",
+            "send-email.to=[]",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No recipients for the emails
+    Hint: Use --to or set send-email.to in the config.
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["send-email", "--to", "not an address"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid email address: not an address
+    Caused by: Invalid input
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["send-email"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No SMTP server configured
+    Hint: Set send-email.smtp-server in the config.
+    "###);
+}
+
+#[test]
+fn test_send_email_cover_letter() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let edit_script = test_env.set_up_fake_editor();
+
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "Add file"]);
+
+    std::fs::write(
+        &edit_script,
+        "dump editor0\0write\nAdd a file\n\nThis adds a file.\n",
+    )
+    .unwrap();
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "send-email",
+            "--to",
+            "list@example.com",
+            "--cover-letter",
+            "--dry-run",
+        ],
+    );
+    // The subject and description go in the blank lines at the top
+    let editor_content = std::fs::read_to_string(test_env.env_root().join("editor0")).unwrap();
+    assert!(editor_content.starts_with("\n\nJJ: "));
+    insta::assert_snapshot!(editor_content.trim_start(), @r###"
+    JJ: Enter the subject of the cover letter on the first line, followed
+    JJ: by a description of the patches:
+    JJ:   Add file
+    JJ: Lines starting with "JJ: " (like this one) will be removed.
+    "###);
+    insta::assert_snapshot!(stdout, @r###"
+    From: Test User <test.user@example.com>
+    To: list@example.com
+    Subject: [PATCH 0/1] Add a file
+    Date: Sat, 3 Feb 2001 04:05:09 +0700
+    Message-Id: <jj.981147909000.0.test.user@example.com>
+
+    This adds a file.
+
+    Test User (1):
+      Add file
+
+    file | 1 +
+    1 file changed, 1 insertion(+), 0 deletions(-)
+
+    From: Test User <test.user@example.com>
+    To: list@example.com
+    Subject: [PATCH 1/1] Add file
+    Date: Sat, 3 Feb 2001 04:05:10 +0700
+    Message-Id: <jj.981147909000.1.test.user@example.com>
+    In-Reply-To: <jj.981147909000.0.test.user@example.com>
+    References: <jj.981147909000.0.test.user@example.com>
+
+    Change-Id: qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    ---
+    file | 1 +
+    1 file changed, 1 insertion(+), 0 deletions(-)
+
+    diff --git a/file b/file
+    new file mode 100644
+    index 0000000000..7898192261
+    --- /dev/null
+    +++ b/file
+    @@ -1,0 +1,1 @@
+    +a
+
+    "###);
+
+    // An empty cover letter aborts
+    std::fs::write(&edit_script, "write\n").unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "send-email",
+            "--to",
+            "list@example.com",
+            "--cover-letter",
+            "--dry-run",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Aborted because the cover letter is empty
+    "###);
+}
@@ -177,3 +177,50 @@ fn test_sparse_manage_patterns() {
     file3
     "###);
 }
+
+#[test]
+fn test_sparse_filesets() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::create_dir(repo_path.join("lib")).unwrap();
+    for path in ["file1", "lib/file2", "lib/file3"] {
+        std::fs::write(repo_path.join(path), "contents").unwrap();
+    }
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "sparse",
+            "set",
+            "--clear",
+            "--add",
+            "file1 | lib/file2",
+            "--add",
+            "lib/file3",
+        ],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    file1
+    lib/file2
+    lib/file3
+    "###);
+
+    // Patterns matching a fileset can be removed
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["sparse", "set", "--remove", "glob:lib/*"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Added 0 files, modified 0 files, removed 2 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    file1
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["sparse", "set", "--add", "glob:lib/*"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Only paths can be added to the sparse patterns
+    Hint: Patterns like globs can only be removed.
+    "###);
+}
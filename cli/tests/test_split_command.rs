@@ -233,6 +233,90 @@ JJ: Lines starting with "JJ: " (like this one) will be removed.
     "###);
 }
 
+#[test]
+fn test_split_siblings() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let workspace_path = test_env.env_root().join("repo");
+
+    std::fs::write(workspace_path.join("file1"), "foo\n").unwrap();
+    std::fs::write(workspace_path.join("file2"), "bar\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["commit", "-m", "test"]);
+    std::fs::write(workspace_path.join("file3"), "baz\n").unwrap();
+    test_env.jj_cmd_ok(&workspace_path, &["describe", "-m", "child"]);
+    let edit_script = test_env.set_up_fake_editor();
+    std::fs::write(
+        edit_script,
+        [
+            "dump editor1",
+            "write\npart 1",
+            "next invocation\n",
+            "dump editor2",
+            "write\npart 2",
+        ]
+        .join("\0"),
+    )
+    .unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &workspace_path,
+        &["split", "-r", "@-", "--siblings", "file1"],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebased 1 descendant commits
+    First part: qpvuntsm 5951e4b1 part 1
+    Second part: zsuskuln e28b603c part 2
+    Working copy now at: rlvkpnrz d3b90fa3 child
+    Parent commit      : qpvuntsm 5951e4b1 part 1
+    Parent commit      : zsuskuln e28b603c part 2
+    "###);
+
+    assert_eq!(
+        std::fs::read_to_string(test_env.env_root().join("editor1")).unwrap(),
+        r#"JJ: Enter commit description for the first part.
+test
+
+JJ: This commit contains the following changes:
+JJ:     A file1
+
+JJ: Lines starting with "JJ: " (like this one) will be removed.
+"#
+    );
+    assert_eq!(
+        std::fs::read_to_string(test_env.env_root().join("editor2")).unwrap(),
+        r#"JJ: Enter commit description for the second part.
+test
+
+JJ: This commit contains the following changes:
+JJ:     A file2
+
+JJ: Lines starting with "JJ: " (like this one) will be removed.
+"#
+    );
+    insta::assert_snapshot!(get_log_output(&test_env, &workspace_path), @r###"
+    @    rlvkpnrzqnoo false child
+    ├─╮
+    │ ◉  zsuskulnrvyr false part 2
+    ◉ │  qpvuntsmwlqt false part 1
+    ├─╯
+    ◉  zzzzzzzzzzzz true
+    "###);
+
+    // Each part has its own changes, and the child still only adds file3
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["diff", "-s", "-r", "description(1)"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file1
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["diff", "-s", "-r", "description(2)"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file2
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file3
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, cwd: &Path) -> String {
     let template = r#"separate(" ", change_id.short(), empty, description)"#;
     test_env.jj_cmd_success(cwd, &["log", "-T", template])
@@ -265,6 +265,101 @@ fn test_squash_partial() {
     insta::assert_snapshot!(stdout, @"");
 }
 
+#[test]
+fn test_squash_lines() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "parent"]);
+    std::fs::write(repo_path.join("file1"), "a\nB\nc\nd\nE\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "new\n").unwrap();
+
+    // Only the hunk at line 2 is moved
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["squash", "--lines", "file1:1-3"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Working copy now at: rlvkpnrz e9a88a6c (no description set)
+    Parent commit      : qpvuntsm 25b0b82e parent
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    new file mode 100644
+    index 0000000000..6fe8acc878
+    --- /dev/null
+    +++ b/file1
+    @@ -1,0 +1,5 @@
+    +a
+    +B
+    +c
+    +d
+    +e
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index 6fe8acc878...62f4b38222 100644
+    --- a/file1
+    +++ b/file1
+    @@ -2,4 +2,4 @@ a
+     B
+     c
+     d
+    -e
+    +E
+    diff --git a/file2 b/file2
+    new file mode 100644
+    index 0000000000..3e757656cf
+    --- /dev/null
+    +++ b/file2
+    @@ -1,0 +1,1 @@
+    +new
+    "###);
+
+    // Selections in several files
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["squash", "--lines", "file1:5", "--lines", "file2:1"],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @"");
+
+    // Several selections in the same file add up
+    std::fs::write(repo_path.join("file1"), "A\nB\nc\nD\nE\nF\n").unwrap();
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["squash", "--lines", "file1:1", "--lines", "file1:4"],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "file1", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A
+    B
+    c
+    D
+    E
+    "###);
+    test_env.jj_cmd_ok(&repo_path, &["undo"]);
+
+    // Lines without changes
+    std::fs::write(repo_path.join("file1"), "a\nB\nc\nd\nE\nF\n").unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["squash", "--lines", "file1:1-2"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No changes selected
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["squash", "--lines", "missing:1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Can't select lines in missing: file doesn't exist
+    "###);
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["squash", "--lines", "file1:3-1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value 'file1:3-1' for '--lines <PATH:LINES>': expected PATH:LINES, like "src/main.rs:10-25", got "file1:3-1"
+
+    For more information, try '--help'.
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     let template = r#"commit_id.short() ++ " " ++ branches"#;
     test_env.jj_cmd_success(repo_path, &["log", "-T", template])
@@ -0,0 +1,243 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+/// Sets up a repo cloned from a bare repo, with two commits to submit.
+fn set_up() -> (TestEnvironment, PathBuf) {
+    let test_env = TestEnvironment::default();
+    let git_repo_path = test_env.env_root().join("origin.git");
+    git2::Repository::init_bare(&git_repo_path).unwrap();
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["git", "clone", git_repo_path.to_str().unwrap(), "repo"],
+    );
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "First"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Second\n\nThe body."]);
+    (test_env, repo_path)
+}
+
+/// Writes a fake forge CLI that logs its arguments to `<name>/log`, and then
+/// runs the `script` with `$state` set to that directory.
+fn write_fake_cli(test_env: &TestEnvironment, name: &str, script: &str) -> PathBuf {
+    let state_dir = test_env.env_root().join(name);
+    std::fs::create_dir(&state_dir).unwrap();
+    let path = test_env.env_root().join(format!("fake-{name}"));
+    std::fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\nstate={}\necho \"{name} $*\" >> $state/log\n{script}",
+            state_dir.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn take_log(test_env: &TestEnvironment, name: &str) -> String {
+    let log_path = test_env.env_root().join(name).join("log");
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    std::fs::remove_file(&log_path).ok();
+    log
+}
+
+fn get_review_refs(git_repo_path: &Path) -> String {
+    let git_repo = git2::Repository::open(git_repo_path).unwrap();
+    let mut lines = vec![];
+    for reference in git_repo.references_glob("refs/for/*").unwrap() {
+        let reference = reference.unwrap();
+        let commit = reference.peel_to_commit().unwrap();
+        lines.push(format!(
+            "{} {}",
+            reference.name().unwrap(),
+            commit.summary().unwrap()
+        ));
+    }
+    lines.sort();
+    lines.join("\n")
+}
+
+#[test]
+fn test_submit_github() {
+    let (test_env, repo_path) = set_up();
+    let gh_path = write_fake_cli(
+        &test_env,
+        "gh",
+        r#"case "$1 $2" in
+"pr list")
+    cat "$state/pr-$4" 2>/dev/null || echo "[]" ;;
+"pr create")
+    n=$(( $(cat $state/count 2>/dev/null || echo 0) + 1 ))
+    echo $n > $state/count
+    echo "[{\"number\": $n, \"url\": \"https://github.com/owner/repo/pull/$n\"}]" > "$state/pr-$4"
+    echo "https://github.com/owner/repo/pull/$n" ;;
+esac
+"#,
+    );
+    test_env.add_config(&format!(
+        r#"
+        github.program = '{}'
+        github.repository = "owner/repo"
+        submit.remotes.origin.forge = "github"
+        "#,
+        gh_path.display()
+    ));
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["submit", "-r", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-rlvkpnrzqnoo for revision 59124a8859bc
+    Branch changes to push to origin:
+      Add branch push-rlvkpnrzqnoo to 59124a8859bc
+    Pushed to origin:
+      push-rlvkpnrzqnoo: (new) -> 59124a8859bc
+    Created pull request https://github.com/owner/repo/pull/1
+    "###);
+    insta::assert_snapshot!(take_log(&test_env, "gh"), @r###"
+    gh pr list --head push-rlvkpnrzqnoo --state open --json number,url --repo owner/repo
+    gh pr create --head push-rlvkpnrzqnoo --title Second --body The body. --repo owner/repo
+    "###);
+
+    // The existing pull request is updated, and a new one is created for the
+    // parent
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["submit", "-r", "@--::@-", "--draft"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-qpvuntsmwlqt for revision 144d0742af09
+    Branch push-rlvkpnrzqnoo@origin already matches push-rlvkpnrzqnoo
+    Branch changes to push to origin:
+      Add branch push-qpvuntsmwlqt to 144d0742af09
+    Pushed to origin:
+      push-qpvuntsmwlqt: (new) -> 144d0742af09
+    Created pull request https://github.com/owner/repo/pull/2
+    Updated pull request https://github.com/owner/repo/pull/1
+    "###);
+    insta::assert_snapshot!(take_log(&test_env, "gh"), @r###"
+    gh pr list --head push-qpvuntsmwlqt --state open --json number,url --repo owner/repo
+    gh pr list --head push-rlvkpnrzqnoo --state open --json number,url --repo owner/repo
+    gh pr create --head push-qpvuntsmwlqt --title First --body  --draft --repo owner/repo
+    gh pr edit 1 --title Second --body The body. --base push-qpvuntsmwlqt --repo owner/repo
+    "###);
+}
+
+#[test]
+fn test_submit_gitlab() {
+    let (test_env, repo_path) = set_up();
+    let glab_path = write_fake_cli(
+        &test_env,
+        "glab",
+        r#"case "$1 $2" in
+"mr list")
+    cat "$state/mr-$4" 2>/dev/null || echo "[]" ;;
+"mr create")
+    n=$(( $(cat $state/count 2>/dev/null || echo 0) + 1 ))
+    echo $n > $state/count
+    echo "[{\"iid\": $n, \"web_url\": \"https://gitlab.com/group/project/-/merge_requests/$n\"}]" > "$state/mr-$4" ;;
+esac
+"#,
+    );
+    test_env.add_config(&format!(
+        r#"
+        gitlab.program = '{}'
+        gitlab.repository = "group/project"
+        submit.remotes.origin.forge = "gitlab"
+        "#,
+        glab_path.display()
+    ));
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["submit", "-r", "@--::@-", "--base", "main", "--draft"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Creating branch push-qpvuntsmwlqt for revision 144d0742af09
+    Creating branch push-rlvkpnrzqnoo for revision 59124a8859bc
+    Branch changes to push to origin:
+      Add branch push-qpvuntsmwlqt to 144d0742af09
+      Add branch push-rlvkpnrzqnoo to 59124a8859bc
+    Pushed to origin:
+      push-qpvuntsmwlqt: (new) -> 144d0742af09
+      push-rlvkpnrzqnoo: (new) -> 59124a8859bc
+    Created merge request https://gitlab.com/group/project/-/merge_requests/1
+    Created merge request https://gitlab.com/group/project/-/merge_requests/2
+    "###);
+    insta::assert_snapshot!(take_log(&test_env, "glab"), @r###"
+    glab mr list --source-branch push-qpvuntsmwlqt --output json --repo group/project
+    glab mr list --source-branch push-rlvkpnrzqnoo --output json --repo group/project
+    glab mr create --source-branch push-qpvuntsmwlqt --title First --description  --yes --target-branch main --draft --repo group/project
+    glab mr list --source-branch push-qpvuntsmwlqt --output json --repo group/project
+    glab mr create --source-branch push-rlvkpnrzqnoo --title Second --description The body. --yes --target-branch push-qpvuntsmwlqt --draft --repo group/project
+    glab mr list --source-branch push-rlvkpnrzqnoo --output json --repo group/project
+    "###);
+
+    test_env.jj_cmd_ok(&repo_path, &["describe", "@-", "-m", "Second, again"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["submit", "-r", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Branch changes to push to origin:
+      Force branch push-rlvkpnrzqnoo from 59124a8859bc to 6007aec24fb4
+    Pushed to origin:
+      push-rlvkpnrzqnoo: 59124a8859bc -> 6007aec24fb4 (forced)
+    Updated merge request https://gitlab.com/group/project/-/merge_requests/2
+    "###);
+    insta::assert_snapshot!(take_log(&test_env, "glab"), @r###"
+    glab mr list --source-branch push-rlvkpnrzqnoo --output json --repo group/project
+    glab mr update 2 --title Second, again --description  --repo group/project
+    "###);
+}
+
+#[test]
+fn test_submit_gerrit() {
+    let (test_env, repo_path) = set_up();
+    test_env.add_config(r#"submit.remotes.origin.forge = "gerrit""#);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["submit", "-r", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No branch to send the changes for
+    Hint: Give the branch on the command line, or set gerrit.default-branch.
+    "###);
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &["submit", "-r", "@-", "--base", "main", "--draft"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Added Change-Id to 2 commits
+    Rebased 1 descendant commits
+    Changes to send to origin for review on main:
+      rlvkpnrz 0e63f45e Second
+      qpvuntsm 8b6d0173 First
+    Working copy now at: kkmpptxz 258f2f85 (empty) (no description set)
+    Parent commit      : rlvkpnrz 0e63f45e Second
+    "###);
+    insta::assert_snapshot!(get_review_refs(&test_env.env_root().join("origin.git")), @"refs/for/main%wip Second");
+}
+
+#[test]
+fn test_submit_unknown_forge() {
+    let (test_env, repo_path) = set_up();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["submit"]);
+    insta::assert_snapshot!(stderr.replace(test_env.env_root().to_str().unwrap(), "$TEST_ENV"), @r###"
+    Error: Don't know which forge hosts remote origin: $TEST_ENV/origin.git
+    Hint: Set submit.remotes.origin.forge to "github", "gitlab", or "gerrit".
+    "###);
+}
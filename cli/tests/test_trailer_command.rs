@@ -0,0 +1,102 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"separate(" ", change_id.short(), description) ++ "\n""#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
+
+#[test]
+fn test_trailer_add_remove() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "First"]);
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "Second\n\nBug: 123"]);
+
+    // Trailers are added across the stack, except to empty descriptions
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "trailer",
+            "add",
+            "-r",
+            "root()..@",
+            "Reviewed-by: A <a@example.com>",
+            "Bug: 123",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Updated the trailers of 2 commits
+    Working copy now at: kkmpptxz f2d2cfbd (empty) (no description set)
+    Parent commit      : rlvkpnrz 17b781f9 (empty) Second
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  kkmpptxzrspx
+    ◉  rlvkpnrzqnoo Second
+    │
+    │  Bug: 123
+    │  Reviewed-by: A <a@example.com>
+    │
+    ◉  qpvuntsmwlqt First
+    │
+    │  Reviewed-by: A <a@example.com>
+    │  Bug: 123
+    │
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    let (stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["trailer", "remove", "-r", "@-", "bug"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Updated the trailers of 1 commits
+    Rebased 1 descendant commits
+    Working copy now at: kkmpptxz b265e26d (empty) (no description set)
+    Parent commit      : rlvkpnrz c91b3d70 (empty) Second
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  kkmpptxzrspx
+    ◉  rlvkpnrzqnoo Second
+    │
+    │  Reviewed-by: A <a@example.com>
+    │
+    ◉  qpvuntsmwlqt First
+    │
+    │  Reviewed-by: A <a@example.com>
+    │  Bug: 123
+    │
+    ◉  zzzzzzzzzzzz
+    "###);
+
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["trailer", "remove", "-r", "@-", "Bug: 456"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["trailer", "add", "Not a trailer"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value 'Not a trailer' for '<TRAILERS>...': expected a trailer like "Key: value"
+
+    For more information, try '--help'.
+    "###);
+}
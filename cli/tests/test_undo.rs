@@ -48,6 +48,84 @@ fn test_undo_rewrite_with_child() {
     "###);
 }
 
+#[test]
+fn test_undo_interactive() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "initial"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "main"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "child"]);
+
+    // Quitting changes nothing
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(&repo_path, &["undo", "-i"], "q\n");
+    insta::assert_snapshot!(stdout, @r###"
+    Recent operations:
+    1: 3ee53dd39882 new empty commit
+    2: ea06c88e0ac7 create branch main pointing to commit e343cd4a2ce7fc2af6ff0a959a79da4bc6cfdb11
+    3: 9bea95f5767d describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    4: 27143b59c690 add workspace 'default'
+    5: 0e8aee02e242 initialize repo
+    q: quit the prompt
+    enter the index of the operation to undo, or r and the index to restore the repo to the state after it: 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+
+    // Undo an older operation, with a preview of the changes
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(&repo_path, &["undo", "-i"], "2\ny\n");
+    insta::assert_snapshot!(stdout, @r###"
+    Recent operations:
+    1: 3ee53dd39882 new empty commit
+    2: ea06c88e0ac7 create branch main pointing to commit e343cd4a2ce7fc2af6ff0a959a79da4bc6cfdb11
+    3: 9bea95f5767d describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    4: 27143b59c690 add workspace 'default'
+    5: 0e8aee02e242 initialize repo
+    q: quit the prompt
+    enter the index of the operation to undo, or r and the index to restore the repo to the state after it: Changes:
+      Branch main: e343cd4a2ce7 -> (absent)
+    Undo operation ea06c88e0ac7? (yn): 
+    "###);
+    insta::assert_snapshot!(stderr, @"");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", "description ++ branches"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  child
+    ◉  initial
+    ◉
+    "###);
+
+    // Restore to the state after an operation, but decline the change
+    let (stdout, stderr) = test_env.jj_cmd_stdin_ok(&repo_path, &["undo", "-i"], "r4\nn\n");
+    insta::assert_snapshot!(stdout, @r###"
+    Recent operations:
+    1: 6ea409940603 undo operation ea06c88e0ac73e1a44c2991c262f873851bbb2ae1c419177407f75e5ec66263cf261b838478dc11c5bf85f428cb3009672a8b54a42e3c6bed25203d855ceead1
+    2: 3ee53dd39882 new empty commit
+    3: ea06c88e0ac7 create branch main pointing to commit e343cd4a2ce7fc2af6ff0a959a79da4bc6cfdb11
+    4: 9bea95f5767d describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    5: 27143b59c690 add workspace 'default'
+    6: 0e8aee02e242 initialize repo
+    q: quit the prompt
+    enter the index of the operation to undo, or r and the index to restore the repo to the state after it: Commits that would be hidden:
+      zsuskuln hidden 87750eaa (empty) child
+    Restore to operation 9bea95f5767d? (yn): 
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing changed.
+    "###);
+
+    // An operation and --interactive can't be used together
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["undo", "-i", "@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--interactive' cannot be used with '[OPERATION]'
+
+    Usage: jj undo --interactive [OPERATION]
+
+    For more information, try '--help'.
+    "###);
+}
+
 #[test]
 fn test_git_push_undo() {
     let test_env = TestEnvironment::default();
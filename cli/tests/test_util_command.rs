@@ -32,6 +32,9 @@ fn test_util_config_schema() {
             "description": "User configuration for Jujutsu VCS. See https://github.com/martinvonz/jj/blob/main/docs/config.md for details",
             "properties": {
                 [...]
+            },
+            "definitions": {
+                [...]
             }
         }
         "###)
@@ -49,7 +52,9 @@ fn test_gc_args() {
     let repo_path = test_env.env_root().join("repo");
 
     let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "gc"]);
-    insta::assert_snapshot!(stderr, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing to remove.
+    "###);
 
     let stderr = test_env.jj_cmd_failure(&repo_path, &["util", "gc", "--at-op=@-"]);
     insta::assert_snapshot!(stderr, @r###"
@@ -94,3 +99,217 @@ fn test_gc_operation_log() {
     Error: No operation ID matching "35688918195690874cbf1f282140cda33c882e48a84dbb0f92c262b52ace4a5753777432b18e9de01bc23121b23261eb2c828622836b9ec7ded7c0ca3c7c1670"
     "###);
 }
+
+#[test]
+fn test_gc_commits() {
+    let test_env = TestEnvironment::default();
+    // Use the local backend because GitBackend::gc() depends on the git CLI.
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["init", "repo", "--config-toml=ui.allow-init-native=true"],
+    );
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "kept\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "kept"]);
+    std::fs::write(repo_path.join("file"), "removed\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "removed"]);
+    let removed_id =
+        test_env.jj_cmd_success(&repo_path, &["log", "--no-graph", "-T=commit_id", "-r=@"]);
+    test_env.jj_cmd_ok(&repo_path, &["abandon", "@"]);
+
+    // The abandoned commit is still reachable from the operation log. Only the
+    // index files of the old operations are removed.
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "gc", "--expire=now"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Removed unreachable objects: 6 index files
+    "###);
+
+    test_env.jj_cmd_ok(&repo_path, &["operation", "abandon", "..@-"]);
+    let (_stdout, stderr) =
+        test_env.jj_cmd_ok(&repo_path, &["util", "gc", "--expire=now", "--dry-run"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Would remove unreachable objects: 7 operations, 6 views, 3 commits, 2 other objects, 1 index files
+    "###);
+    let removed_path = repo_path.join(".jj/repo/store/commits").join(&removed_id);
+    assert!(removed_path.exists());
+
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "gc", "--expire=now"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Removed unreachable objects: 7 operations, 6 views, 3 commits, 2 other objects, 1 index files
+    "###);
+    assert!(!removed_path.exists());
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&repo_path, &["log", "-T=description", "-p"]), @r###"
+    @
+    ◉  kept
+    │  Added regular file file:
+    │          1: kept
+    ◉
+    "###);
+}
+
+#[test]
+fn test_util_maintenance() {
+    let test_env = TestEnvironment::default();
+    // Use the local backend because GitBackend::run_maintenance() depends on
+    // the git CLI.
+    test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &["init", "repo", "--config-toml=ui.allow-init-native=true"],
+    );
+    let repo_path = test_env.env_root().join("repo");
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "maintenance"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing to remove.
+    Compacted the commit index.
+    "###);
+    assert!(repo_path.join(".jj/repo/maintenance/last-run").is_file());
+
+    // Not due yet
+    test_env.jj_cmd_ok(&repo_path, &["new"]);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["util", "maintenance", "--auto"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @"");
+
+    // Due after the configured number of operations
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "util",
+            "maintenance",
+            "--auto",
+            "--config-toml=maintenance.auto-after-operations=1",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Nothing to remove.
+    Compacted the commit index.
+    "###);
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "util",
+            "maintenance",
+            "--auto",
+            "--config-toml=maintenance.auto-after-operations=1",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @"");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["util", "maintenance", "--at-op=@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot run maintenance from a non-head operation
+    "###);
+}
+
+#[test]
+fn test_util_complete() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir").join("file1"), "").unwrap();
+    std::fs::write(repo_path.join("dir").join("file2"), "").unwrap();
+    std::fs::write(repo_path.join("file3"), "").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["commit", "-m", "first"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "-r@-", "main", "maint"]);
+    test_env.jj_cmd_ok(
+        &repo_path,
+        &["git", "remote", "add", "origin", "http://example.com"],
+    );
+    let complete = |words: &[&str]| {
+        let mut args = vec!["util", "complete", "--"];
+        args.extend_from_slice(words);
+        test_env.jj_cmd_success(&repo_path, &args)
+    };
+
+    // Revisions
+    insta::assert_snapshot!(complete(&["jj", "log", "-r", ""]), @r###"
+    main
+    maint
+    rlvkpnrzqnoo
+    qpvuntsmwlqt
+    zzzzzzzzzzzz
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "new", "ma"]), @r###"
+    main
+    maint
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "--no-pager", "rebase", "-d", "maint"]), @r###"
+    maint
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["util", "complete", "--descriptions", "--", "jj", "show", ""],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    main	branch
+    maint	branch
+    rlvkpnrzqnoo
+    qpvuntsmwlqt	first
+    zzzzzzzzzzzz
+    "###);
+
+    // Branches
+    insta::assert_snapshot!(complete(&["jj", "branch", "delete", "main", ""]), @r###"
+    main
+    maint
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "git", "push", "--branch", "mai"]), @r###"
+    main
+    maint
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "branch", "create", ""]), @"");
+
+    // Remotes
+    insta::assert_snapshot!(complete(&["jj", "git", "fetch", "--remote", ""]), @r###"
+    origin
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "git", "remote", "remove", ""]), @r###"
+    origin
+    "###);
+
+    // Paths in the revision given by -r, or in the working copy
+    insta::assert_snapshot!(complete(&["jj", "cat", "-r", "@-", ""]), @r###"
+    dir/
+    file3
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "cat", "-r", "@-", "dir/"]), @r###"
+    dir/file1
+    dir/file2
+    "###);
+    insta::assert_snapshot!(complete(&["jj", "diff", "--", "f"]), @r###"
+    file3
+    "###);
+
+    // Aliases are resolved
+    test_env.add_config(r#"aliases.l = ["log", "--no-graph"]"#);
+    insta::assert_snapshot!(complete(&["jj", "l", "-r", "mai"]), @r###"
+    main
+    maint
+    "###);
+
+    // Subcommands, flags, and other values are left to the static completions
+    insta::assert_snapshot!(complete(&["jj", "bra"]), @"");
+    insta::assert_snapshot!(complete(&["jj", "log", "--no"]), @"");
+    insta::assert_snapshot!(complete(&["jj", "log", "-T", ""]), @"");
+
+    // Nothing to complete outside of a repo
+    let stdout = test_env.jj_cmd_success(
+        test_env.env_root(),
+        &["util", "complete", "--", "jj", "log", "-r", ""],
+    );
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_util_completion_script() {
+    let test_env = TestEnvironment::default();
+    let stdout = test_env.jj_cmd_success(test_env.env_root(), &["util", "completion", "--fish"]);
+    insta::assert_snapshot!(stdout.lines().last().unwrap(), @"complete -c jj -a '(jj util complete --descriptions -- (commandline -opc) (commandline -ct) 2>/dev/null)'");
+}
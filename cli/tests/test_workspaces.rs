@@ -275,14 +275,14 @@ fn test_workspaces_conflicting_edits() {
     "###);
     let stderr = test_env.jj_cmd_failure(&secondary_path, &["st"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The working copy is stale (not updated since operation a07b009d6eba).
+    Error[E1002]: The working copy is stale (not updated since operation a07b009d6eba).
     Hint: Run `jj workspace update-stale` to update it.
     See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-working-copy for more information.
     "###);
     // Same error on second run, and from another command
     let stderr = test_env.jj_cmd_failure(&secondary_path, &["log"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The working copy is stale (not updated since operation a07b009d6eba).
+    Error[E1002]: The working copy is stale (not updated since operation a07b009d6eba).
     Hint: Run `jj workspace update-stale` to update it.
     See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-working-copy for more information.
     "###);
@@ -362,7 +362,7 @@ fn test_workspaces_updated_by_other() {
     "###);
     let stderr = test_env.jj_cmd_failure(&secondary_path, &["st"]);
     insta::assert_snapshot!(stderr, @r###"
-    Error: The working copy is stale (not updated since operation a07b009d6eba).
+    Error[E1002]: The working copy is stale (not updated since operation a07b009d6eba).
     Hint: Run `jj workspace update-stale` to update it.
     See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-working-copy for more information.
     "###);
@@ -413,6 +413,65 @@ fn test_workspaces_update_stale_noop() {
     "###);
 }
 
+#[test]
+fn test_workspaces_repair() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["init", "--git", "main"]);
+    let main_path = test_env.env_root().join("main");
+    std::fs::write(main_path.join("file"), "contents\n").unwrap();
+    test_env.jj_cmd_ok(&main_path, &["commit", "-m", "initial"]);
+    std::fs::write(main_path.join("file"), "modified\n").unwrap();
+    test_env.jj_cmd_ok(&main_path, &["status"]);
+
+    // Corrupt the working copy state, and make more changes on disk
+    let tree_state_path = main_path.join(".jj/working_copy/tree_state");
+    std::fs::write(&tree_state_path, "garbage").unwrap();
+    std::fs::write(main_path.join("untracked"), "new\n").unwrap();
+    let stderr = test_env.jj_cmd_internal_error(&main_path, &["status"]);
+    insta::assert_snapshot!(stderr.lines().next().unwrap(), @"Internal error: Failed to access working copy state");
+
+    let stderr = test_env.jj_cmd_failure(
+        &main_path,
+        &["workspace", "repair", "--ignore-working-copy"],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: This command must be able to update the working copy.
+    Hint: Don't use --ignore-working-copy.
+    "###);
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&main_path, &["workspace", "repair"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebuilt the working copy state from commit 7d7fdf8c56fc
+    Working copy now at: rlvkpnrz 3e710495 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&main_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy changes:
+    M file
+    A untracked
+    Working copy : rlvkpnrz 3e710495 (no description set)
+    Parent commit: qpvuntsm 52c893bf initial
+    "###);
+
+    // A deleted working copy state can be repaired too
+    std::fs::remove_file(&tree_state_path).unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&main_path, &["workspace", "repair"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Rebuilt the working copy state from commit 3e710495685c
+    Working copy now at: rlvkpnrz 3e710495 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&main_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy changes:
+    M file
+    A untracked
+    Working copy : rlvkpnrz 3e710495 (no description set)
+    Parent commit: qpvuntsm 52c893bf initial
+    "###);
+}
+
 /// Test "update-stale" in a dirty, but not stale working copy.
 #[test]
 fn test_workspaces_update_stale_snapshot() {
@@ -414,6 +414,13 @@ pub trait Backend: Send + Sync + Debug {
 
     async fn read_file(&self, path: &RepoPath, id: &FileId) -> BackendResult<Box<dyn Read>>;
 
+    /// Tells the backend that the given files are about to be read, so it can
+    /// fetch the ones that aren't available locally in one batch. Does nothing
+    /// by default.
+    fn prefetch_files(&self, _ids: &[FileId]) -> BackendResult<()> {
+        Ok(())
+    }
+
     fn write_file(&self, path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId>;
 
     async fn read_symlink(&self, path: &RepoPath, id: &SymlinkId) -> BackendResult<String>;
@@ -456,5 +463,23 @@ pub trait Backend: Send + Sync + Debug {
     /// All commits found in the `index` won't be removed. In addition to that,
     /// objects created after `keep_newer` will be preserved. This mitigates a
     /// risk of deleting new commits created concurrently by another process.
-    fn gc(&self, index: &dyn Index, keep_newer: SystemTime) -> BackendResult<()>;
+    /// If `dry_run` is true, nothing is removed, but the returned stats count
+    /// what would be.
+    fn gc(
+        &self,
+        index: &dyn Index,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<BackendGcStats>;
+}
+
+/// Statistics of [`Backend::gc()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BackendGcStats {
+    /// Number of unreachable commits that were removed.
+    pub removed_commits: usize,
+    /// Number of other unreachable objects, such as trees and files, that were
+    /// removed. Backends that delegate the removal to another tool may not
+    /// count them.
+    pub removed_objects: usize,
 }
@@ -0,0 +1,141 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bisection of a range of commits to find the first bad one.
+
+#![allow(missing_docs)]
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use itertools::Itertools;
+
+use crate::backend::CommitId;
+use crate::repo::Repo;
+use crate::revset::{RevsetEvaluationError, RevsetExpression};
+
+/// The result of evaluating a commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evaluation {
+    /// The commit doesn't have the problem. Its ancestors are assumed not to
+    /// have it either.
+    Good,
+    /// The commit has the problem. Its descendants are assumed to have it too.
+    Bad,
+    /// The commit couldn't be evaluated.
+    Skip,
+}
+
+/// What to do next in a bisection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NextStep {
+    /// Evaluate this commit and `mark()` it.
+    Evaluate(CommitId),
+    /// The bisection is done.
+    Done(BisectionResult),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BisectionResult {
+    /// None of the evaluated commits was bad.
+    NoBadCommits,
+    /// The first bad commit. There may be more than one if the range has
+    /// several branches with bad commits.
+    Found(Vec<CommitId>),
+    /// The first bad commit is one of these, but some of them were skipped, so
+    /// it's not known which.
+    Indeterminate(Vec<CommitId>),
+}
+
+/// Finds the first bad commit in a range by evaluating commits in the middle
+/// of the remaining candidates.
+pub struct Bisector<'repo> {
+    repo: &'repo dyn Repo,
+    input_range: Rc<RevsetExpression>,
+    good_commits: HashSet<CommitId>,
+    bad_commits: HashSet<CommitId>,
+    skipped_commits: HashSet<CommitId>,
+}
+
+impl<'repo> Bisector<'repo> {
+    pub fn new(repo: &'repo dyn Repo, input_range: Rc<RevsetExpression>) -> Self {
+        Bisector {
+            repo,
+            input_range,
+            good_commits: HashSet::new(),
+            bad_commits: HashSet::new(),
+            skipped_commits: HashSet::new(),
+        }
+    }
+
+    /// Records the evaluation of a commit.
+    pub fn mark(&mut self, id: CommitId, evaluation: Evaluation) {
+        self.good_commits.remove(&id);
+        self.bad_commits.remove(&id);
+        self.skipped_commits.remove(&id);
+        match evaluation {
+            Evaluation::Good => self.good_commits.insert(id),
+            Evaluation::Bad => self.bad_commits.insert(id),
+            Evaluation::Skip => self.skipped_commits.insert(id),
+        };
+    }
+
+    /// Returns the next commit to evaluate, or the result if there are no
+    /// candidates left.
+    pub fn next_step(&self) -> Result<NextStep, RevsetEvaluationError> {
+        let good_expr = commits_expression(&self.good_commits);
+        let bad_expr = commits_expression(&self.bad_commits);
+        let skipped_expr = commits_expression(&self.skipped_commits);
+        // The first bad commit is in the range, not an ancestor of a good
+        // commit, and an ancestor of the earliest bad commits if there are any.
+        let mut possible_expr = self.input_range.minus(&good_expr.ancestors());
+        if !self.bad_commits.is_empty() {
+            possible_expr = possible_expr.intersection(&bad_expr.roots().ancestors());
+        }
+        let candidates = self.evaluate(&possible_expr.minus(&bad_expr).minus(&skipped_expr))?;
+        if !candidates.is_empty() {
+            // The commits are in reverse topological order, so this splits the
+            // candidates in halves along the history.
+            let commit_id = candidates[candidates.len() / 2].clone();
+            return Ok(NextStep::Evaluate(commit_id));
+        }
+        if self.bad_commits.is_empty() {
+            return Ok(NextStep::Done(BisectionResult::NoBadCommits));
+        }
+        // Only the bad and skipped commits are left.
+        let first_bad = self.evaluate(&possible_expr.intersection(&bad_expr).roots())?;
+        let skipped = self.evaluate(&possible_expr.intersection(&skipped_expr))?;
+        let result = if skipped.is_empty() {
+            BisectionResult::Found(first_bad)
+        } else {
+            BisectionResult::Indeterminate(first_bad.into_iter().chain(skipped).collect())
+        };
+        Ok(NextStep::Done(result))
+    }
+
+    fn evaluate(
+        &self,
+        expression: &Rc<RevsetExpression>,
+    ) -> Result<Vec<CommitId>, RevsetEvaluationError> {
+        Ok(expression
+            .clone()
+            .evaluate_programmatic(self.repo)?
+            .iter()
+            .collect_vec())
+    }
+}
+
+fn commits_expression(commit_ids: &HashSet<CommitId>) -> Rc<RevsetExpression> {
+    RevsetExpression::commits(commit_ids.iter().cloned().collect())
+}
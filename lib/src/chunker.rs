@@ -0,0 +1,219 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content-defined chunking of file contents.
+//!
+//! The chunk boundaries are determined by a rolling "gear" hash as described
+//! in the FastCDC paper, so inserting or removing bytes in the middle of a file
+//! only affects the chunks around the edit.
+
+use std::io;
+use std::io::Read;
+
+const fn make_gear_table() -> [u64; 256] {
+    // splitmix64, seeded with a fixed value so the boundaries are stable.
+    let mut table = [0; 256];
+    let mut state: u64 = 0x6a09_e667_f3bc_c908;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+static GEAR: [u64; 256] = make_gear_table();
+
+/// Size constraints for content-defined chunks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChunkerParams {
+    /// No chunk (except the last one) is smaller than this.
+    pub min_size: usize,
+    /// The size chunks are normalized towards. Must be a power of two.
+    pub avg_size: usize,
+    /// No chunk is larger than this.
+    pub max_size: usize,
+}
+
+impl ChunkerParams {
+    fn masks(&self) -> (u64, u64) {
+        assert!(self.avg_size.is_power_of_two());
+        assert!(self.min_size <= self.avg_size && self.avg_size <= self.max_size);
+        let bits = self.avg_size.trailing_zeros();
+        // Use the high bits of the hash since they depend on the most input
+        // bytes. Chunks shorter than the average size need one more bit to
+        // match, longer ones one bit less ("normalized chunking").
+        let mask_small = !0u64 << (64 - (bits + 1));
+        let mask_large = !0u64 << (64 - (bits - 1));
+        (mask_small, mask_large)
+    }
+
+    /// Returns the length of the first chunk in `data`. If `data` is shorter
+    /// than `max_size` and contains no boundary, `data.len()` is returned.
+    pub fn find_boundary(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let (mask_small, mask_large) = self.masks();
+        let end = data.len().min(self.max_size);
+        let normal = end.min(self.avg_size);
+        let mut hash: u64 = 0;
+        for (i, &b) in data.iter().enumerate().take(end).skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[b as usize]);
+            let mask = if i < normal { mask_small } else { mask_large };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+}
+
+impl Default for ChunkerParams {
+    fn default() -> Self {
+        ChunkerParams {
+            min_size: 16 * 1024,
+            avg_size: 64 * 1024,
+            max_size: 256 * 1024,
+        }
+    }
+}
+
+/// Splits the contents of a reader into content-defined chunks.
+pub struct Chunker<R> {
+    reader: R,
+    params: ChunkerParams,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Chunker<R> {
+    /// Creates a chunker reading from `reader`.
+    pub fn new(reader: R, params: ChunkerParams) -> Self {
+        Chunker {
+            reader,
+            params,
+            buf: Vec::with_capacity(params.max_size),
+            eof: false,
+        }
+    }
+
+    /// Returns the next chunk, or `None` once the reader is exhausted.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        while !self.eof && self.buf.len() < self.params.max_size {
+            let len = self.buf.len();
+            self.buf.resize(self.params.max_size, 0);
+            match self.reader.read(&mut self.buf[len..]) {
+                Ok(0) => {
+                    self.buf.truncate(len);
+                    self.eof = true;
+                }
+                Ok(n) => self.buf.truncate(len + n),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => self.buf.truncate(len),
+                Err(err) => {
+                    self.buf.truncate(len);
+                    return Err(err);
+                }
+            }
+        }
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let len = self.params.find_boundary(&self.buf);
+        let rest = self.buf.split_off(len);
+        Ok(Some(std::mem::replace(&mut self.buf, rest)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: ChunkerParams = ChunkerParams {
+        min_size: 64,
+        avg_size: 256,
+        max_size: 1024,
+    };
+
+    fn pseudo_random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    fn chunk_all(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut chunker = Chunker::new(data, PARAMS);
+        let mut chunks = vec![];
+        while let Some(chunk) = chunker.next_chunk().unwrap() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_chunk_sizes() {
+        assert_eq!(chunk_all(b""), Vec::<Vec<u8>>::new());
+        assert_eq!(chunk_all(b"abc"), vec![b"abc".to_vec()]);
+
+        let data = pseudo_random_bytes(100_000, 1);
+        let chunks = chunk_all(&data);
+        assert_eq!(chunks.concat(), data);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(!last.is_empty() && last.len() <= PARAMS.max_size);
+        for chunk in rest {
+            assert!(chunk.len() > PARAMS.min_size);
+            assert!(chunk.len() <= PARAMS.max_size);
+        }
+        // Boundaries should be found well before the max size on random data
+        assert!(chunks.len() > data.len() / PARAMS.max_size * 2);
+
+        // Data without boundaries is split at the max size
+        let chunks = chunk_all(&[0; 3000]);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            vec![1024, 1024, 952]
+        );
+    }
+
+    #[test]
+    fn test_chunk_boundaries_resync_after_edit() {
+        let data = pseudo_random_bytes(100_000, 2);
+        let mut edited = data.clone();
+        edited.splice(50_000..50_000, b"inserted".iter().copied());
+        let chunks = chunk_all(&data);
+        let edited_chunks = chunk_all(&edited);
+        assert_eq!(edited_chunks.concat(), edited);
+
+        // Only the chunks around the edit should differ
+        let unchanged = edited_chunks
+            .iter()
+            .filter(|chunk| chunks.contains(chunk))
+            .count();
+        assert!(
+            unchanged + 3 >= chunks.len(),
+            "{unchanged} {}",
+            chunks.len()
+        );
+    }
+}
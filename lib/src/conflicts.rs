@@ -33,24 +33,44 @@ const CONFLICT_END_LINE: &[u8] = b">>>>>>>\n";
 const CONFLICT_DIFF_LINE: &[u8] = b"%%%%%%%\n";
 const CONFLICT_MINUS_LINE: &[u8] = b"-------\n";
 const CONFLICT_PLUS_LINE: &[u8] = b"+++++++\n";
+// Follows a line of a conflict hunk that doesn't end with a newline, so that
+// the next conflict marker still starts at the beginning of a line.
+const NO_NEWLINE_LINE: &[u8] = b"\\ No newline at end of file\n";
+
+/// Writes `line` with the given prefix. If the line doesn't end with a
+/// newline, one is added, followed by a line saying that there was none.
+fn write_line(prefix: &[u8], line: &[u8], file: &mut dyn Write) -> std::io::Result<()> {
+    file.write_all(prefix)?;
+    file.write_all(line)?;
+    if !line.ends_with(b"\n") {
+        file.write_all(b"\n")?;
+        file.write_all(NO_NEWLINE_LINE)?;
+    }
+    Ok(())
+}
+
+/// Writes a term of a conflict hunk as a snapshot.
+fn write_snapshot(content: &[u8], file: &mut dyn Write) -> std::io::Result<()> {
+    for line in content.split_inclusive(|b| *b == b'\n') {
+        write_line(b"", line, file)?;
+    }
+    Ok(())
+}
 
 fn write_diff_hunks(hunks: &[DiffHunk], file: &mut dyn Write) -> std::io::Result<()> {
     for hunk in hunks {
         match hunk {
             DiffHunk::Matching(content) => {
                 for line in content.split_inclusive(|b| *b == b'\n') {
-                    file.write_all(b" ")?;
-                    file.write_all(line)?;
+                    write_line(b" ", line, file)?;
                 }
             }
             DiffHunk::Different(content) => {
                 for line in content[0].split_inclusive(|b| *b == b'\n') {
-                    file.write_all(b"-")?;
-                    file.write_all(line)?;
+                    write_line(b"-", line, file)?;
                 }
                 for line in content[1].split_inclusive(|b| *b == b'\n') {
-                    file.write_all(b"+")?;
-                    file.write_all(line)?;
+                    write_line(b"+", line, file)?;
                 }
             }
         }
@@ -198,7 +218,7 @@ pub fn materialize_merge_result(
                             // If we have no more positive terms, emit the remaining negative
                             // terms as snapshots.
                             output.write_all(CONFLICT_MINUS_LINE)?;
-                            output.write_all(&left.0)?;
+                            write_snapshot(&left.0, output)?;
                             continue;
                         };
                         let diff1 = Diff::for_tokenizer(&[&left.0, &right1.0], &find_line_ranges)
@@ -217,7 +237,7 @@ pub fn materialize_merge_result(
                                 // the current positive term as a snapshot and the next
                                 // positive term as a diff.
                                 output.write_all(CONFLICT_PLUS_LINE)?;
-                                output.write_all(&right1.0)?;
+                                write_snapshot(&right1.0, output)?;
                                 output.write_all(CONFLICT_DIFF_LINE)?;
                                 write_diff_hunks(&diff2, output)?;
                                 add_index += 2;
@@ -233,7 +253,7 @@ pub fn materialize_merge_result(
                     //  Emit the remaining positive terms as snapshots.
                     for slice in hunk.adds().skip(add_index) {
                         output.write_all(CONFLICT_PLUS_LINE)?;
-                        output.write_all(&slice.0)?;
+                        write_snapshot(&slice.0, output)?;
                     }
                     output.write_all(CONFLICT_END_LINE)?;
                 }
@@ -308,22 +328,50 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
     let mut state = State::Unknown;
     let mut removes = vec![];
     let mut adds = vec![];
-    for line in input.split_inclusive(|b| *b == b'\n') {
+    // Whether the previous line was added to the last remove and add term,
+    // respectively
+    let mut last_line_in = (false, false);
+    let mut lines = input.split_inclusive(|b| *b == b'\n').peekable();
+    while let Some(line) = lines.next() {
         match line {
             CONFLICT_DIFF_LINE => {
                 state = State::Diff;
                 removes.push(ContentHunk(vec![]));
                 adds.push(ContentHunk(vec![]));
+                last_line_in = (false, false);
                 continue;
             }
             CONFLICT_MINUS_LINE => {
                 state = State::Minus;
                 removes.push(ContentHunk(vec![]));
+                last_line_in = (false, false);
                 continue;
             }
             CONFLICT_PLUS_LINE => {
                 state = State::Plus;
                 adds.push(ContentHunk(vec![]));
+                last_line_in = (false, false);
+                continue;
+            }
+            // Lines of a diff have a prefix, so the marker can't be confused
+            // with one. Lines of a snapshot don't, so there it only counts as
+            // the marker if it ends the term.
+            NO_NEWLINE_LINE
+                if last_line_in != (false, false)
+                    && (matches!(state, State::Diff)
+                        || lines.peek().map_or(true, |next| {
+                            [CONFLICT_DIFF_LINE, CONFLICT_MINUS_LINE, CONFLICT_PLUS_LINE]
+                                .contains(next)
+                        })) =>
+            {
+                let (in_remove, in_add) = last_line_in;
+                if in_remove {
+                    strip_newline(removes.last_mut().unwrap());
+                }
+                if in_add {
+                    strip_newline(adds.last_mut().unwrap());
+                }
+                last_line_in = (false, false);
                 continue;
             }
             _ => {}
@@ -332,11 +380,14 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
             State::Diff => {
                 if let Some(rest) = line.strip_prefix(b"-") {
                     removes.last_mut().unwrap().0.extend_from_slice(rest);
+                    last_line_in = (true, false);
                 } else if let Some(rest) = line.strip_prefix(b"+") {
                     adds.last_mut().unwrap().0.extend_from_slice(rest);
+                    last_line_in = (false, true);
                 } else if let Some(rest) = line.strip_prefix(b" ") {
                     removes.last_mut().unwrap().0.extend_from_slice(rest);
                     adds.last_mut().unwrap().0.extend_from_slice(rest);
+                    last_line_in = (true, true);
                 } else {
                     // Doesn't look like a conflict
                     return Merge::resolved(ContentHunk(vec![]));
@@ -344,9 +395,11 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
             }
             State::Minus => {
                 removes.last_mut().unwrap().0.extend_from_slice(line);
+                last_line_in = (true, false);
             }
             State::Plus => {
                 adds.last_mut().unwrap().0.extend_from_slice(line);
+                last_line_in = (false, true);
             }
             State::Unknown => {
                 // Doesn't look like a conflict
@@ -358,6 +411,12 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
     Merge::from_removes_adds(removes, adds)
 }
 
+fn strip_newline(term: &mut ContentHunk) {
+    if term.0.ends_with(b"\n") {
+        term.0.pop();
+    }
+}
+
 /// Parses conflict markers in `content` and returns an updated version of
 /// `file_ids` with the new contents. If no (valid) conflict markers remain, a
 /// single resolves `FileId` will be returned.
@@ -0,0 +1,192 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of renamed and copied files in diffs.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read as _;
+
+use itertools::Itertools;
+
+use crate::backend::{BackendError, BackendResult, FileId, TreeValue};
+use crate::diff::{self, DiffGranularity, DiffHunk};
+use crate::merge::MergedTreeValue;
+use crate::object_id::ObjectId as _;
+use crate::repo_path::{RepoPath, RepoPathBuf};
+use crate::store::Store;
+
+/// Above this number of pairs of added files and candidate sources, only
+/// files with identical contents are paired up, since comparing the contents
+/// of all pairs would be slow.
+const MAX_INEXACT_PAIRS: usize = 10_000;
+
+/// How a file in a diff came from another file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyOperation {
+    /// The source file was removed.
+    Rename,
+    /// The source file was kept.
+    Copy,
+}
+
+/// An added file in a diff that was renamed or copied from another file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CopyRecord {
+    /// The path of the file it came from.
+    pub source: RepoPathBuf,
+    /// The path of the added file.
+    pub target: RepoPathBuf,
+    /// Whether the source was renamed or copied.
+    pub operation: CopyOperation,
+    /// How similar the contents of the files are, in percent.
+    pub similarity: u32,
+}
+
+/// Finds the added files in the `diff` that were renamed or copied from the
+/// removed or modified files, if their contents are at least `min_similarity`
+/// percent similar.
+///
+/// Each removed file becomes the source of at most one rename, to the most
+/// similar added file. Other added files that are similar to a source become
+/// copies of it. Empty files are never paired up. The records are sorted by
+/// target path.
+pub fn detect_copies(
+    store: &Store,
+    diff: &[(RepoPathBuf, MergedTreeValue, MergedTreeValue)],
+    min_similarity: u32,
+) -> BackendResult<Vec<CopyRecord>> {
+    let mut sources = vec![];
+    let mut targets = vec![];
+    for (path, before, after) in diff {
+        if let Some(Some(TreeValue::File { id, .. })) = before.as_resolved() {
+            sources.push((path, id, after.is_absent()));
+        } else if before.is_absent() {
+            if let Some(Some(TreeValue::File { id, .. })) = after.as_resolved() {
+                targets.push((path, id));
+            }
+        }
+    }
+    if sources.is_empty() || targets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let inexact = min_similarity < 100 && sources.len() * targets.len() <= MAX_INEXACT_PAIRS;
+    let mut contents: HashMap<&FileId, Vec<u8>> = HashMap::new();
+    let mut candidates = vec![];
+    for (target_index, &(target_path, target_id)) in targets.iter().enumerate() {
+        for (source_index, &(source_path, source_id, _)) in sources.iter().enumerate() {
+            if source_id != target_id && !inexact {
+                continue;
+            }
+            for (path, id) in [(source_path, source_id), (target_path, target_id)] {
+                if !contents.contains_key(id) {
+                    contents.insert(id, read_file_content(store, path, id)?);
+                }
+            }
+            let (source_content, target_content) = (&contents[source_id], &contents[target_id]);
+            // Empty files would all be copies of each other
+            if source_content.is_empty() || target_content.is_empty() {
+                continue;
+            }
+            let similarity = if source_id == target_id {
+                100
+            } else {
+                similarity(source_content, target_content)
+            };
+            if similarity >= min_similarity {
+                candidates.push((similarity, target_index, source_index));
+            }
+        }
+    }
+
+    // Pair up the most similar files first, and prefer renames to copies
+    candidates.sort_by_key(|&(similarity, target_index, source_index)| {
+        let (_, _, removed) = sources[source_index];
+        (
+            std::cmp::Reverse(similarity),
+            !removed,
+            target_index,
+            source_index,
+        )
+    });
+    let mut paired_targets = HashSet::new();
+    let mut renamed_sources = HashSet::new();
+    let mut records = vec![];
+    for (similarity, target_index, source_index) in candidates {
+        if paired_targets.contains(&target_index) {
+            continue;
+        }
+        let (source_path, _, removed) = sources[source_index];
+        let operation = if removed && renamed_sources.insert(source_index) {
+            CopyOperation::Rename
+        } else {
+            CopyOperation::Copy
+        };
+        paired_targets.insert(target_index);
+        records.push(CopyRecord {
+            source: source_path.clone(),
+            target: targets[target_index].0.clone(),
+            operation,
+            similarity,
+        });
+    }
+    Ok(records
+        .into_iter()
+        .sorted_by(|a, b| a.target.cmp(&b.target))
+        .collect())
+}
+
+fn read_file_content(store: &Store, path: &RepoPath, id: &FileId) -> BackendResult<Vec<u8>> {
+    let mut content = vec![];
+    store
+        .read_file(path, id)?
+        .read_to_end(&mut content)
+        .map_err(|err| BackendError::ReadObject {
+            object_type: id.object_type(),
+            hash: id.hex(),
+            source: err.into(),
+        })?;
+    Ok(content)
+}
+
+/// Returns how similar the contents are, as the percentage of the larger one
+/// that matches the other line by line.
+fn similarity(left: &[u8], right: &[u8]) -> u32 {
+    let size = left.len().max(right.len());
+    if size == 0 {
+        return 100;
+    }
+    let matching: usize = diff::diff_with_granularity(left, right, DiffGranularity::Line)
+        .iter()
+        .map(|hunk| match hunk {
+            DiffHunk::Matching(content) => content.len(),
+            DiffHunk::Different(_) => 0,
+        })
+        .sum();
+    (matching * 100 / size) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity(b"", b""), 100);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\n"), 100);
+        assert_eq!(similarity(b"a\nb\n", b"a\nc\n"), 50);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\nc\nd\n"), 50);
+        assert_eq!(similarity(b"a\n", b"b\n"), 0);
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-commit Bloom filters of the paths changed by each commit.
+//!
+//! A filter contains every changed file path and all of its parent
+//! directories, so a path-limited query can skip commits that definitely
+//! don't touch the path without diffing their trees.
+
+#![allow(missing_docs)]
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use crate::backend::CommitId;
+use crate::object_id::ObjectId;
+use crate::repo_path::RepoPath;
+use crate::stacked_table::{ReadonlyTable, TableSegment};
+
+const BITS_PER_PATH: usize = 10;
+const NUM_HASHES: u64 = 7;
+const MIN_FILTER_BYTES: usize = 8;
+/// Commits changing more paths than this are stored without a filter, like
+/// Git does for its changed-path filters.
+pub(super) const MAX_CHANGED_PATHS: usize = 512;
+
+/// FNV-1a. The hash must be stable since the filters are persisted.
+fn path_hash(path: &RepoPath, seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for &b in path.as_internal_file_string().as_bytes() {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn bit_positions(path: &RepoPath, num_bits: u64) -> impl Iterator<Item = u64> {
+    let h1 = path_hash(path, 0);
+    let h2 = path_hash(path, 0x9e37_79b9_7f4a_7c15) | 1;
+    (0..NUM_HASHES).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+/// Bloom filter of the paths changed by a commit.
+#[derive(Clone, Eq, PartialEq)]
+pub struct ChangedPathFilter {
+    bits: Vec<u8>,
+}
+
+impl ChangedPathFilter {
+    /// Builds a filter from the changed file paths. Parent directories are
+    /// added implicitly.
+    pub fn from_paths<'a>(paths: impl IntoIterator<Item = &'a RepoPath>) -> Self {
+        let mut all_paths = vec![];
+        for path in paths {
+            let mut path = Some(path);
+            while let Some(p) = path.filter(|p| !p.is_root()) {
+                all_paths.push(p);
+                path = p.parent();
+            }
+        }
+        all_paths.sort_unstable();
+        all_paths.dedup();
+        let num_bytes = (all_paths.len() * BITS_PER_PATH + 7) / 8;
+        let mut bits = vec![0; num_bytes.max(MIN_FILTER_BYTES)];
+        let num_bits = bits.len() as u64 * 8;
+        for path in all_paths {
+            for pos in bit_positions(path, num_bits) {
+                bits[(pos / 8) as usize] |= 1 << (pos % 8);
+            }
+        }
+        ChangedPathFilter { bits }
+    }
+
+    /// Parses the serialized filter. Returns `None` if no filter was stored.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (!bytes.is_empty()).then(|| ChangedPathFilter {
+            bits: bytes.to_vec(),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// Returns false if the `path` (or any path under it) was definitely not
+    /// changed.
+    pub fn may_contain(&self, path: &RepoPath) -> bool {
+        if path.is_root() {
+            return true;
+        }
+        let num_bits = self.bits.len() as u64 * 8;
+        bit_positions(path, num_bits)
+            .all(|pos| self.bits[(pos / 8) as usize] & (1 << (pos % 8)) != 0)
+    }
+}
+
+impl Debug for ChangedPathFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangedPathFilter")
+            .field("num_bytes", &self.bits.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Persisted changed-path filters keyed by commit id.
+#[derive(Clone)]
+pub struct ChangedPathIndex {
+    table: Arc<ReadonlyTable>,
+}
+
+impl ChangedPathIndex {
+    pub(super) fn new(table: Arc<ReadonlyTable>) -> Self {
+        ChangedPathIndex { table }
+    }
+
+    pub fn num_commits(&self) -> usize {
+        self.table.num_entries()
+    }
+
+    pub fn has_commit(&self, commit_id: &CommitId) -> bool {
+        self.table.get_value(commit_id.as_bytes()).is_some()
+    }
+
+    /// Returns the filter for the given commit if one was built.
+    pub fn filter(&self, commit_id: &CommitId) -> Option<ChangedPathFilter> {
+        ChangedPathFilter::from_bytes(self.table.get_value(commit_id.as_bytes())?)
+    }
+}
+
+impl Debug for ChangedPathIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangedPathIndex")
+            .field("num_commits", &self.num_commits())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_path(value: &str) -> &RepoPath {
+        RepoPath::from_internal_string(value)
+    }
+
+    #[test]
+    fn test_changed_path_filter() {
+        let filter = ChangedPathFilter::from_paths([repo_path("dir/sub/file"), repo_path("top")]);
+        assert!(filter.may_contain(RepoPath::root()));
+        assert!(filter.may_contain(repo_path("dir")));
+        assert!(filter.may_contain(repo_path("dir/sub")));
+        assert!(filter.may_contain(repo_path("dir/sub/file")));
+        assert!(filter.may_contain(repo_path("top")));
+        assert!(!filter.may_contain(repo_path("other")));
+        assert!(!filter.may_contain(repo_path("dir/other")));
+
+        let filter = ChangedPathFilter::from_bytes(filter.as_bytes()).unwrap();
+        assert!(filter.may_contain(repo_path("dir/sub/file")));
+        assert!(!filter.may_contain(repo_path("other")));
+
+        let filter = ChangedPathFilter::from_paths([]);
+        assert!(!filter.may_contain(repo_path("file")));
+
+        assert_eq!(ChangedPathFilter::from_bytes(&[]), None);
+    }
+
+    #[test]
+    fn test_changed_path_filter_false_positive_rate() {
+        let paths = (0..100)
+            .map(|i| format!("dir{}/file{i}", i % 10))
+            .collect::<Vec<_>>();
+        let filter = ChangedPathFilter::from_paths(paths.iter().map(|p| repo_path(p)));
+        for path in &paths {
+            assert!(filter.may_contain(repo_path(path)));
+        }
+        let false_positives = (0..1000)
+            .filter(|i| filter.may_contain(repo_path(&format!("other{i}"))))
+            .count();
+        assert!(false_positives < 50, "{false_positives}");
+    }
+}
@@ -14,7 +14,7 @@
 
 #![allow(missing_docs)]
 
-use std::cmp::{max, min, Ordering};
+use std::cmp::{max, Ordering};
 use std::collections::{BTreeSet, BinaryHeap, HashSet};
 use std::iter;
 use std::sync::Arc;
@@ -281,17 +281,27 @@ impl<'a> CompositeIndex<'a> {
     }
 
     pub fn heads_pos(
+        &self,
+        candidate_positions: BTreeSet<IndexPosition>,
+    ) -> BTreeSet<IndexPosition> {
+        self.heads_pos_visiting(candidate_positions, |_| {})
+    }
+
+    /// Like `heads_pos()`, but calls `visit` with each ancestor it walks.
+    pub(super) fn heads_pos_visiting(
         &self,
         mut candidate_positions: BTreeSet<IndexPosition>,
+        mut visit: impl FnMut(IndexPosition),
     ) -> BTreeSet<IndexPosition> {
         // Add all parents of the candidates to the work queue. The parents and their
         // ancestors are not heads.
-        // Also find the smallest generation number among the candidates.
+        // Also order the candidates by generation number to know the smallest one
+        // among those that are still possible heads.
         let mut work = BinaryHeap::new();
-        let mut min_generation = u32::MAX;
+        let mut candidates_by_generation = BTreeSet::new();
         for pos in &candidate_positions {
             let entry = self.entry_by_pos(*pos);
-            min_generation = min(min_generation, entry.generation_number());
+            candidates_by_generation.insert(IndexPositionByGeneration::from(&entry));
             for parent_entry in entry.parents() {
                 work.push(IndexPositionByGeneration::from(&parent_entry));
             }
@@ -299,12 +309,19 @@ impl<'a> CompositeIndex<'a> {
 
         // Walk ancestors of the parents of the candidates. Remove visited commits from
         // set of candidates. Stop walking when we have gone past the minimum
-        // candidate generation.
+        // generation of the remaining candidates, since their ancestors can't
+        // include any of them.
         while let Some(item) = dedup_pop(&mut work) {
+            let min_generation = candidates_by_generation
+                .first()
+                .map_or(u32::MAX, |candidate| candidate.generation);
             if item.generation < min_generation {
                 break;
             }
-            candidate_positions.remove(&item.pos);
+            visit(item.pos);
+            if candidate_positions.remove(&item.pos) {
+                candidates_by_generation.remove(&item);
+            }
             let entry = self.entry_by_pos(item.pos);
             for parent_entry in entry.parents() {
                 assert!(parent_entry.position() < entry.position());
@@ -319,7 +336,7 @@ impl<'a> CompositeIndex<'a> {
         expression: &ResolvedExpression,
         store: &Arc<Store>,
     ) -> Result<Box<dyn Revset + 'a>, RevsetEvaluationError> {
-        let revset_impl = revset_engine::evaluate(expression, store, *self)?;
+        let revset_impl = revset_engine::evaluate(expression, store, *self, None)?;
         Ok(Box::new(revset_impl))
     }
 }
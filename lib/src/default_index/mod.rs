@@ -14,6 +14,7 @@
 
 #![allow(missing_docs)]
 
+mod changed_path;
 mod composite;
 mod entry;
 mod mutable;
@@ -23,6 +24,7 @@ pub mod revset_engine;
 pub mod revset_graph_iterator;
 mod store;
 
+pub use self::changed_path::{ChangedPathFilter, ChangedPathIndex};
 pub use self::composite::{AsCompositeIndex, CompositeIndex, IndexLevelStats, IndexStats};
 pub use self::entry::{IndexEntry, IndexPosition};
 pub use self::mutable::DefaultMutableIndex;
@@ -834,6 +836,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_walk_revs_take_until_roots() {
+        let mut new_change_id = change_id_generator();
+        let mut index = DefaultMutableIndex::full(3, 16);
+        // 7
+        // |\
+        // 6 |
+        // | |
+        // 5 |
+        // | |
+        // 4 |
+        // | 3
+        // | |
+        // | 2
+        // | |
+        // | 1
+        // |/
+        // 0
+        let id_0 = CommitId::from_hex("000000");
+        let id_1 = CommitId::from_hex("111111");
+        let id_2 = CommitId::from_hex("222222");
+        let id_3 = CommitId::from_hex("333333");
+        let id_4 = CommitId::from_hex("444444");
+        let id_5 = CommitId::from_hex("555555");
+        let id_6 = CommitId::from_hex("666666");
+        let id_7 = CommitId::from_hex("777777");
+        index.add_commit_data(id_0.clone(), new_change_id(), &[]);
+        index.add_commit_data(id_1.clone(), new_change_id(), &[id_0.clone()]);
+        index.add_commit_data(id_2.clone(), new_change_id(), &[id_1.clone()]);
+        index.add_commit_data(id_3.clone(), new_change_id(), &[id_2.clone()]);
+        index.add_commit_data(id_4.clone(), new_change_id(), &[id_0.clone()]);
+        index.add_commit_data(id_5.clone(), new_change_id(), &[id_4.clone()]);
+        index.add_commit_data(id_6.clone(), new_change_id(), &[id_5.clone()]);
+        index.add_commit_data(id_7.clone(), new_change_id(), &[id_6.clone(), id_3.clone()]);
+
+        let walk_commit_ids = |heads: &[CommitId], roots: &[CommitId]| {
+            let index = index.as_composite();
+            let head_positions = to_positions_vec(index, heads);
+            let root_positions = to_positions_vec(index, roots);
+            index
+                .walk_revs(&head_positions, &[])
+                .take_until_roots(&root_positions)
+                .map(|entry| entry.commit_id())
+                .collect_vec()
+        };
+
+        // Entries on the side branch below the generation of the root are not
+        // visited even though their positions are above the root.
+        assert_eq!(
+            walk_commit_ids(&[id_7.clone()], &[id_3.clone()]),
+            vec![id_7.clone(), id_6.clone(), id_3.clone()]
+        );
+        assert_eq!(
+            walk_commit_ids(&[id_7.clone()], &[id_2.clone()]),
+            vec![
+                id_7.clone(),
+                id_6.clone(),
+                id_5.clone(),
+                id_3.clone(),
+                id_2.clone()
+            ]
+        );
+        assert!(walk_commit_ids(&[id_3.clone()], &[]).is_empty());
+        assert_eq!(
+            walk_commit_ids(&[id_7.clone()], &[id_0.clone()]),
+            vec![id_7, id_6, id_5, id_4, id_3, id_2, id_1, id_0]
+        );
+    }
+
     #[test]
     fn test_walk_revs_filter_by_generation() {
         let mut new_change_id = change_id_generator();
@@ -1151,4 +1222,72 @@ mod tests {
             vec![id_3.clone(), id_5.clone()]
         );
     }
+
+    #[test]
+    fn test_heads_stops_walk_early() {
+        let mut new_change_id = change_id_generator();
+        let mut index = DefaultMutableIndex::full(3, 16);
+        // 6
+        // |
+        // 5 (merge of 1, 2, 3, and 4)
+        // |
+        // 1 2 3 4 (all children of 0)
+        // |
+        // 0
+        let id_0 = CommitId::from_hex("000000");
+        let id_1 = CommitId::from_hex("111111");
+        let id_2 = CommitId::from_hex("222222");
+        let id_3 = CommitId::from_hex("333333");
+        let id_4 = CommitId::from_hex("444444");
+        let id_5 = CommitId::from_hex("555555");
+        let id_6 = CommitId::from_hex("666666");
+        index.add_commit_data(id_0.clone(), new_change_id(), &[]);
+        index.add_commit_data(id_1.clone(), new_change_id(), &[id_0.clone()]);
+        index.add_commit_data(id_2.clone(), new_change_id(), &[id_0.clone()]);
+        index.add_commit_data(id_3.clone(), new_change_id(), &[id_0.clone()]);
+        index.add_commit_data(id_4.clone(), new_change_id(), &[id_0.clone()]);
+        let parents = [&id_1, &id_2, &id_3, &id_4].map(Clone::clone);
+        index.add_commit_data(id_5.clone(), new_change_id(), &parents);
+        index.add_commit_data(id_6.clone(), new_change_id(), &[id_5.clone()]);
+
+        let index = index.as_composite();
+        let heads_and_visited = |candidates: &[CommitId]| {
+            let mut visited = vec![];
+            let heads = index.heads_pos_visiting(
+                to_positions_vec(index, candidates).into_iter().collect(),
+                |pos| visited.push(index.entry_by_pos(pos).commit_id()),
+            );
+            let heads = heads
+                .into_iter()
+                .map(|pos| index.entry_by_pos(pos).commit_id())
+                .collect_vec();
+            (heads, visited)
+        };
+
+        // The walk doesn't go below the generation of the candidates
+        assert_eq!(
+            heads_and_visited(&[id_6.clone(), id_5.clone()]),
+            (vec![id_6.clone()], vec![id_5.clone()])
+        );
+        // Once the candidate of the lowest generation is found to be an
+        // ancestor, the walk stops at the generation of the remaining ones
+        assert_eq!(
+            heads_and_visited(&[id_5.clone(), id_4.clone()]),
+            (vec![id_5.clone()], vec![id_4.clone()])
+        );
+        // The other parents of the same generation need to be visited if the
+        // candidate might be among them
+        assert_eq!(
+            heads_and_visited(&[id_5.clone(), id_1.clone()]),
+            (
+                vec![id_5.clone()],
+                vec![id_4.clone(), id_3.clone(), id_2.clone(), id_1.clone()]
+            )
+        );
+        // Candidates far below are still found to be ancestors
+        assert_eq!(
+            heads_and_visited(&[id_6.clone(), id_0.clone()]).0,
+            vec![id_6.clone()]
+        );
+    }
 }
@@ -270,6 +270,16 @@ impl MutableIndexSegment {
     }
 
     pub(super) fn save_in(self, dir: &Path) -> io::Result<Arc<ReadonlyIndexSegment>> {
+        self.save_in_impl(Some(dir))
+    }
+
+    /// Converts the segment to a readonly segment without writing it to disk.
+    pub(super) fn freeze(self) -> Arc<ReadonlyIndexSegment> {
+        self.save_in_impl(None)
+            .expect("converting without writing should not fail")
+    }
+
+    fn save_in_impl(self, dir: Option<&Path>) -> io::Result<Arc<ReadonlyIndexSegment>> {
         if self.num_local_commits() == 0 && self.parent_file.is_some() {
             return Ok(self.parent_file.unwrap());
         }
@@ -281,12 +291,14 @@ impl MutableIndexSegment {
         let mut hasher = Blake2b512::new();
         hasher.update(&buf);
         let index_file_id_hex = hex::encode(hasher.finalize());
-        let index_file_path = dir.join(&index_file_id_hex);
 
-        let mut temp_file = NamedTempFile::new_in(dir)?;
-        let file = temp_file.as_file_mut();
-        file.write_all(&buf)?;
-        persist_content_addressed_temp_file(temp_file, index_file_path)?;
+        if let Some(dir) = dir {
+            let index_file_path = dir.join(&index_file_id_hex);
+            let mut temp_file = NamedTempFile::new_in(dir)?;
+            let file = temp_file.as_file_mut();
+            file.write_all(&buf)?;
+            persist_content_addressed_temp_file(temp_file, index_file_path)?;
+        }
 
         Ok(ReadonlyIndexSegment::load_with_parent_file(
             &mut &buf[local_entries_offset..],
@@ -488,4 +500,8 @@ impl MutableIndex for DefaultMutableIndex {
             .expect("index to merge in must be a DefaultReadonlyIndex");
         self.0.merge_in(other.as_segment().clone());
     }
+
+    fn into_readonly(self: Box<Self>) -> Box<dyn ReadonlyIndex> {
+        Box::new(DefaultReadonlyIndex::from_segment(self.0.freeze()))
+    }
 }
@@ -26,9 +26,11 @@ use std::sync::Arc;
 use smallvec::SmallVec;
 use thiserror::Error;
 
+use super::changed_path::ChangedPathIndex;
 use super::composite::{AsCompositeIndex, ChangeIdIndexImpl, CompositeIndex, IndexSegment};
 use super::entry::{IndexPosition, LocalPosition, SmallIndexPositionsVec};
 use super::mutable::DefaultMutableIndex;
+use super::revset_engine;
 use crate::backend::{ChangeId, CommitId};
 use crate::index::{AllHeadsForGcUnsupported, ChangeIdIndex, Index, MutableIndex, ReadonlyIndex};
 use crate::object_id::{HexPrefix, ObjectId, PrefixResolution};
@@ -61,6 +63,12 @@ impl ReadonlyIndexLoadError {
         }
     }
 
+    /// Returns true if the file doesn't exist, such as when it was removed by
+    /// a concurrent process.
+    pub(super) fn is_not_found(&self) -> bool {
+        self.error.kind() == io::ErrorKind::NotFound
+    }
+
     /// Returns true if the underlying error suggests data corruption.
     pub(super) fn is_corrupt_or_not_found(&self) -> bool {
         // If the parent file name field is corrupt, the file wouldn't be found.
@@ -461,21 +469,39 @@ impl IndexSegment for ReadonlyIndexSegment {
 
 /// Commit index backend which stores data on local disk.
 #[derive(Clone, Debug)]
-pub struct DefaultReadonlyIndex(Arc<ReadonlyIndexSegment>);
+pub struct DefaultReadonlyIndex {
+    segment: Arc<ReadonlyIndexSegment>,
+    changed_paths: Option<ChangedPathIndex>,
+}
 
 impl DefaultReadonlyIndex {
     pub(super) fn from_segment(segment: Arc<ReadonlyIndexSegment>) -> Self {
-        DefaultReadonlyIndex(segment)
+        DefaultReadonlyIndex {
+            segment,
+            changed_paths: None,
+        }
+    }
+
+    pub(super) fn with_changed_paths(self, changed_paths: Option<ChangedPathIndex>) -> Self {
+        DefaultReadonlyIndex {
+            changed_paths,
+            ..self
+        }
     }
 
     pub(super) fn as_segment(&self) -> &Arc<ReadonlyIndexSegment> {
-        &self.0
+        &self.segment
+    }
+
+    /// Changed-path filters of the indexed commits, if they have been built.
+    pub fn changed_paths(&self) -> Option<&ChangedPathIndex> {
+        self.changed_paths.as_ref()
     }
 }
 
 impl AsCompositeIndex for DefaultReadonlyIndex {
     fn as_composite(&self) -> CompositeIndex<'_> {
-        self.0.as_composite()
+        self.segment.as_composite()
     }
 }
 
@@ -520,7 +546,13 @@ impl Index for DefaultReadonlyIndex {
         expression: &ResolvedExpression,
         store: &Arc<Store>,
     ) -> Result<Box<dyn Revset + 'index>, RevsetEvaluationError> {
-        self.as_composite().evaluate_revset(expression, store)
+        let revset_impl = revset_engine::evaluate(
+            expression,
+            store,
+            self.as_composite(),
+            self.changed_paths.as_ref(),
+        )?;
+        Ok(Box::new(revset_impl))
     }
 }
 
@@ -542,6 +574,6 @@ impl ReadonlyIndex for DefaultReadonlyIndex {
     }
 
     fn start_modification(&self) -> Box<dyn MutableIndex> {
-        Box::new(DefaultMutableIndex::incremental(self.0.clone()))
+        Box::new(DefaultMutableIndex::incremental(self.segment.clone()))
     }
 }
@@ -205,7 +205,11 @@ pub struct RevWalk<'a>(RevWalkImpl<'a, CompositeIndex<'a>>);
 impl<'a> RevWalk<'a> {
     pub(super) fn new(index: CompositeIndex<'a>) -> Self {
         let queue = RevWalkQueue::new();
-        RevWalk(RevWalkImpl { index, queue })
+        RevWalk(RevWalkImpl {
+            index,
+            queue,
+            min_generation: 0,
+        })
     }
 
     pub(super) fn extend_wanted(&mut self, positions: impl IntoIterator<Item = IndexPosition>) {
@@ -233,13 +237,19 @@ impl<'a> RevWalk<'a> {
     /// Use this if you are only interested in descendants of the given roots.
     /// The caller still needs to filter out unwanted entries.
     pub fn take_until_roots(
-        self,
+        mut self,
         root_positions: &[IndexPosition],
     ) -> impl Iterator<Item = IndexEntry<'a>> + Clone + 'a {
-        // We can also make it stop visiting based on the generation number. Maybe
-        // it will perform better for unbalanced branchy history.
-        // https://github.com/martinvonz/jj/pull/1492#discussion_r1160678325
         let bottom_position = *root_positions.iter().min().unwrap_or(&IndexPosition::MAX);
+        // Entries of lower generation than the roots can't be descendants of
+        // the roots, and neither can their ancestors. This helps if there are
+        // long side branches above the bottom position.
+        let index = self.0.index;
+        self.0.min_generation = root_positions
+            .iter()
+            .map(|&pos| index.entry_by_pos(pos).generation_number())
+            .min()
+            .unwrap_or(u32::MAX);
         self.take_while(move |entry| entry.position() >= bottom_position)
     }
 
@@ -295,6 +305,9 @@ impl<'a> Iterator for RevWalk<'a> {
 struct RevWalkImpl<'a, I: RevWalkIndex<'a>> {
     index: I,
     queue: RevWalkQueue<I::Position, ()>,
+    /// Wanted entries of lower generation number are skipped along with
+    /// their ancestors.
+    min_generation: u32,
 }
 
 impl<'a, I: RevWalkIndex<'a>> RevWalkImpl<'a, I> {
@@ -303,6 +316,9 @@ impl<'a, I: RevWalkIndex<'a>> RevWalkImpl<'a, I> {
             self.queue.skip_while_eq(&item.pos);
             if item.is_wanted() {
                 let entry = self.index.entry_by_pos(item.pos);
+                if entry.generation_number() < self.min_generation {
+                    continue;
+                }
                 self.queue
                     .extend_wanted(self.index.adjacent_positions(&entry), ());
                 return Some(entry);
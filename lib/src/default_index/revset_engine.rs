@@ -21,13 +21,17 @@ use std::iter::Peekable;
 use std::ops::Range;
 use std::sync::Arc;
 
+use futures::StreamExt as _;
 use itertools::Itertools;
+use pollster::FutureExt as _;
 
+use super::changed_path::ChangedPathIndex;
 use super::revset_graph_iterator::RevsetGraphIterator;
 use crate::backend::{ChangeId, CommitId, MillisSinceEpoch};
 use crate::default_index::{AsCompositeIndex, CompositeIndex, IndexEntry, IndexPosition};
-use crate::matchers::{EverythingMatcher, Matcher, PrefixMatcher, Visit};
-use crate::repo_path::RepoPath;
+use crate::fileset::FilesetExpression;
+use crate::matchers::{Matcher, Visit};
+use crate::repo_path::{RepoPath, RepoPathBuf};
 use crate::revset::{
     ResolvedExpression, ResolvedPredicateExpression, Revset, RevsetEvaluationError,
     RevsetFilterPredicate, GENERATION_RANGE_FULL,
@@ -565,10 +569,12 @@ pub fn evaluate<I: AsCompositeIndex>(
     expression: &ResolvedExpression,
     store: &Arc<Store>,
     index: I,
+    changed_paths: Option<&ChangedPathIndex>,
 ) -> Result<RevsetImpl<I>, RevsetEvaluationError> {
     let context = EvaluationContext {
         store: store.clone(),
         index: index.as_composite(),
+        changed_paths: changed_paths.cloned(),
     };
     let internal_revset = context.evaluate(expression)?;
     Ok(RevsetImpl::new(internal_revset, index))
@@ -577,6 +583,7 @@ pub fn evaluate<I: AsCompositeIndex>(
 struct EvaluationContext<'index> {
     store: Arc<Store>,
     index: CompositeIndex<'index>,
+    changed_paths: Option<ChangedPathIndex>,
 }
 
 fn to_u32_generation_range(range: &Range<u64>) -> Result<Range<u32>, RevsetEvaluationError> {
@@ -780,9 +787,11 @@ impl<'index> EvaluationContext<'index> {
         expression: &ResolvedPredicateExpression,
     ) -> Result<Box<dyn ToPredicateFn>, RevsetEvaluationError> {
         match expression {
-            ResolvedPredicateExpression::Filter(predicate) => {
-                Ok(build_predicate_fn(self.store.clone(), predicate))
-            }
+            ResolvedPredicateExpression::Filter(predicate) => Ok(build_predicate_fn(
+                self.store.clone(),
+                self.changed_paths.clone(),
+                predicate,
+            )),
             ResolvedPredicateExpression::Set(expression) => {
                 Ok(self.evaluate(expression)?.into_predicate())
             }
@@ -830,6 +839,9 @@ impl<'index> EvaluationContext<'index> {
         // Maintain min-heap containing the latest (greatest) count items. For small
         // count and large candidate set, this is probably cheaper than building vec
         // and applying selection algorithm.
+        // Unlike heads(), this can't stop at a generation number, since committer
+        // timestamps aren't ordered by generation. That would need corrected commit
+        // dates in the index.
         let mut candidate_iter = candidate_set.iter(self.index).map(make_rev_item).fuse();
         let mut latest_items = BinaryHeap::from_iter(candidate_iter.by_ref().take(count));
         for item in candidate_iter {
@@ -885,6 +897,7 @@ fn box_pure_predicate_fn<'a>(
 
 fn build_predicate_fn(
     store: Arc<Store>,
+    changed_paths: Option<ChangedPathIndex>,
     predicate: &RevsetFilterPredicate,
 ) -> Box<dyn ToPredicateFn> {
     match predicate {
@@ -919,15 +932,35 @@ fn build_predicate_fn(
                     || pattern.matches(&commit.committer().email)
             })
         }
-        RevsetFilterPredicate::File(paths) => {
-            // TODO: Add support for globs and other formats
-            let matcher: Box<dyn Matcher> = if let Some(paths) = paths {
-                Box::new(PrefixMatcher::new(paths))
-            } else {
-                Box::new(EverythingMatcher)
-            };
+        RevsetFilterPredicate::File(fileset) => {
+            // Predicates like executable() depend on the trees of each commit
+            let fileset = fileset.clone();
+            let matcher = (!fileset.has_predicates()).then(|| fileset.to_matcher());
+            // The filters can only rule out commits if the fileset is a list of
+            // paths, none of which is the root.
+            let filter_paths: Option<Vec<RepoPathBuf>> = fileset
+                .as_explicit_paths()
+                .filter(|paths| paths.iter().all(|path| !path.is_root()))
+                .map(|paths| paths.into_iter().map(ToOwned::to_owned).collect());
+            let changed_paths = changed_paths.filter(|_| filter_paths.is_some());
+            // The filters of merge commits don't account for renames detected
+            // when merging their parents
+            let merge_renames = store.merge_rename_similarity().is_some();
             box_pure_predicate_fn(move |index, entry| {
-                has_diff_from_parent(&store, index, entry, matcher.as_ref())
+                if let Some(filter) = changed_paths
+                    .as_ref()
+                    .filter(|_| !(merge_renames && entry.num_parents() > 1))
+                    .and_then(|changed_paths| changed_paths.filter(&entry.commit_id()))
+                {
+                    let paths = filter_paths.as_ref().unwrap();
+                    if !paths.iter().any(|path| filter.may_contain(path)) {
+                        return false;
+                    }
+                }
+                match &matcher {
+                    Some(matcher) => has_diff_from_parent(&store, index, entry, matcher.as_ref()),
+                    None => has_diff_from_parent_in_fileset(&store, index, entry, &fileset),
+                }
             })
         }
         RevsetFilterPredicate::HasConflict => box_pure_predicate_fn(move |_index, entry| {
@@ -956,7 +989,28 @@ fn has_diff_from_parent(
     }
     let from_tree = rewrite::merge_commit_trees_without_repo(store, &index, &parents).unwrap();
     let to_tree = commit.tree().unwrap();
-    from_tree.diff(&to_tree, matcher).next().is_some()
+    let mut tree_diff = from_tree.diff_stream(&to_tree, matcher);
+    async { tree_diff.next().await.is_some() }.block_on()
+}
+
+/// Like `has_diff_from_parent()`, but resolves the predicates in the `fileset`
+/// against the trees of the commit and its parents.
+fn has_diff_from_parent_in_fileset(
+    store: &Arc<Store>,
+    index: CompositeIndex<'_>,
+    entry: &IndexEntry<'_>,
+    fileset: &FilesetExpression,
+) -> bool {
+    let commit = store.get_commit(&entry.commit_id()).unwrap();
+    let from_tree =
+        rewrite::merge_commit_trees_without_repo(store, &index, &commit.parents()).unwrap();
+    let to_tree = commit.tree().unwrap();
+    let matcher = fileset
+        .resolve_predicates(&[&from_tree, &to_tree])
+        .unwrap()
+        .to_matcher();
+    let mut tree_diff = from_tree.diff_stream(&to_tree, matcher.as_ref());
+    async { tree_diff.next().await.is_some() }.block_on()
 }
 
 #[cfg(test)]
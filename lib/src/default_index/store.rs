@@ -19,25 +19,35 @@ use std::collections::HashSet;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
 use std::{fs, io};
 
 use itertools::Itertools;
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
+use super::changed_path::{ChangedPathFilter, ChangedPathIndex, MAX_CHANGED_PATHS};
+use super::composite::{AsCompositeIndex as _, IndexSegment as _};
+use super::entry::IndexPosition;
 use super::mutable::DefaultMutableIndex;
 use super::readonly::{DefaultReadonlyIndex, ReadonlyIndexLoadError, ReadonlyIndexSegment};
 use crate::backend::{BackendError, BackendInitError, CommitId};
 use crate::commit::CommitByCommitterTimestamp;
-use crate::dag_walk;
 use crate::file_util::{persist_content_addressed_temp_file, IoResultExt as _, PathError};
+use crate::fsync::Fsync;
 use crate::index::{
     Index, IndexReadError, IndexStore, IndexWriteError, MutableIndex, ReadonlyIndex,
 };
+use crate::lock::LockBackend;
+use crate::matchers::EverythingMatcher;
 use crate::object_id::ObjectId;
 use crate::op_store::{OpStoreError, OperationId};
 use crate::operation::Operation;
+use crate::repo_path::RepoPathBuf;
+use crate::stacked_table::{TableSegment as _, TableStore, TableStoreError};
 use crate::store::Store;
+use crate::tree::TreeMergeError;
+use crate::{dag_walk, rewrite};
 
 // BLAKE2b-512 hash length in hex string
 const SEGMENT_FILE_NAME_LENGTH: usize = 64 * 2;
@@ -69,6 +79,8 @@ pub enum DefaultIndexStoreError {
     LoadIndex(ReadonlyIndexLoadError),
     #[error("Failed to write commit index file")]
     SaveIndex(#[source] io::Error),
+    #[error("Failed to remove old commit index files")]
+    RemoveOldFiles(#[source] PathError),
     #[error("Failed to index commits at operation {op_id}", op_id = op_id.hex())]
     IndexCommits {
         op_id: OperationId,
@@ -76,11 +88,22 @@ pub enum DefaultIndexStoreError {
     },
     #[error(transparent)]
     OpStore(#[from] OpStoreError),
+    #[error("Failed to load changed-path index")]
+    LoadChangedPaths(#[source] TableStoreError),
+    #[error("Failed to write changed-path index")]
+    SaveChangedPaths(#[source] TableStoreError),
+    #[error("Failed to compute changed paths of commit {commit_id}", commit_id = commit_id.hex())]
+    ChangedPaths {
+        commit_id: CommitId,
+        source: TreeMergeError,
+    },
 }
 
 #[derive(Debug)]
 pub struct DefaultIndexStore {
     dir: PathBuf,
+    lock_backend: LockBackend,
+    fsync: Fsync,
 }
 
 impl DefaultIndexStore {
@@ -88,17 +111,25 @@ impl DefaultIndexStore {
         "default"
     }
 
-    pub fn init(dir: &Path) -> Result<Self, DefaultIndexStoreInitError> {
+    pub fn init(
+        dir: &Path,
+        lock_backend: LockBackend,
+        fsync: Fsync,
+    ) -> Result<Self, DefaultIndexStoreInitError> {
         let op_dir = dir.join("operations");
         std::fs::create_dir(&op_dir).context(&op_dir)?;
         Ok(DefaultIndexStore {
             dir: dir.to_owned(),
+            lock_backend,
+            fsync,
         })
     }
 
-    pub fn load(dir: &Path) -> DefaultIndexStore {
+    pub fn load(dir: &Path, lock_backend: LockBackend, fsync: Fsync) -> DefaultIndexStore {
         DefaultIndexStore {
             dir: dir.to_owned(),
+            lock_backend,
+            fsync,
         }
     }
 
@@ -139,6 +170,125 @@ impl DefaultIndexStore {
         .map_err(DefaultIndexStoreError::LoadIndex)
     }
 
+    fn changed_path_dir(&self) -> PathBuf {
+        self.dir.join("changed_paths")
+    }
+
+    /// Loads the changed-path filters if they have ever been built.
+    fn load_changed_path_index(
+        &self,
+        commit_id_length: usize,
+    ) -> Result<Option<ChangedPathIndex>, DefaultIndexStoreError> {
+        let dir = self.changed_path_dir();
+        if !dir.join("heads").is_dir() {
+            return Ok(None);
+        }
+        let table = TableStore::load(dir, commit_id_length, self.lock_backend, self.fsync.clone())
+            .get_head()
+            .map_err(DefaultIndexStoreError::LoadChangedPaths)?;
+        Ok(Some(ChangedPathIndex::new(table)))
+    }
+
+    /// Computes changed-path filters for the commits in the `index` that
+    /// don't have one yet. Returns the updated index.
+    ///
+    /// This takes the lock of the filters and writes them, so it shouldn't be
+    /// called when only reading the repo.
+    pub fn build_changed_path_index(
+        &self,
+        index: &DefaultReadonlyIndex,
+        store: &Arc<Store>,
+    ) -> Result<DefaultReadonlyIndex, DefaultIndexStoreError> {
+        self.add_changed_paths(index, store, IndexPosition(0))
+    }
+
+    /// Computes changed-path filters for the commits added by the last segment
+    /// of the `index` if the filters have ever been built.
+    fn update_changed_path_index(
+        &self,
+        index: DefaultReadonlyIndex,
+        store: &Arc<Store>,
+    ) -> Result<DefaultReadonlyIndex, DefaultIndexStoreError> {
+        let Some(changed_paths) = index.changed_paths() else {
+            return Ok(index);
+        };
+        let segment = index.as_segment();
+        let start_pos = IndexPosition(segment.num_parent_commits());
+        let composite = segment.as_composite();
+        let has_all = (start_pos.0..composite.num_commits()).all(|pos| {
+            changed_paths.has_commit(&composite.entry_by_pos(IndexPosition(pos)).commit_id())
+        });
+        if has_all {
+            return Ok(index);
+        }
+        self.add_changed_paths(&index, store, start_pos)
+    }
+
+    fn add_changed_paths(
+        &self,
+        index: &DefaultReadonlyIndex,
+        store: &Arc<Store>,
+        start_pos: IndexPosition,
+    ) -> Result<DefaultReadonlyIndex, DefaultIndexStoreError> {
+        let dir = self.changed_path_dir();
+        let table_store = if dir.is_dir() {
+            TableStore::load(
+                dir,
+                store.commit_id_length(),
+                self.lock_backend,
+                self.fsync.clone(),
+            )
+        } else {
+            fs::create_dir(&dir).map_err(DefaultIndexStoreError::SaveIndex)?;
+            TableStore::init(
+                dir,
+                store.commit_id_length(),
+                self.lock_backend,
+                self.fsync.clone(),
+            )
+        };
+        let (table, _lock) = table_store
+            .get_head_locked()
+            .map_err(DefaultIndexStoreError::LoadChangedPaths)?;
+        let mut mut_table = table.start_mutation();
+        let composite = index.as_composite();
+        for entry in (start_pos.0..composite.num_commits())
+            .map(|pos| composite.entry_by_pos(IndexPosition(pos)))
+        {
+            let commit_id = entry.commit_id();
+            if table.get_value(commit_id.as_bytes()).is_some() {
+                continue;
+            }
+            // What a merge commit changes depends on whether its parents are
+            // merged with rename detection. The filters are built without it,
+            // so merge commits get no filter if it's enabled.
+            if entry.num_parents() > 1 && store.merge_rename_similarity().is_some() {
+                mut_table.add_entry(commit_id.to_bytes(), vec![]);
+                continue;
+            }
+            let paths = changed_paths_of_commit(store, index, &commit_id).map_err(|source| {
+                DefaultIndexStoreError::ChangedPaths {
+                    commit_id: commit_id.clone(),
+                    source,
+                }
+            })?;
+            let value = if paths.len() > MAX_CHANGED_PATHS {
+                vec![]
+            } else {
+                ChangedPathFilter::from_paths(paths.iter().map(AsRef::as_ref))
+                    .as_bytes()
+                    .to_vec()
+            };
+            mut_table.add_entry(commit_id.to_bytes(), value);
+        }
+        let table = table_store
+            .save_table(mut_table)
+            .map_err(DefaultIndexStoreError::SaveChangedPaths)?;
+        Ok(index
+            .clone()
+            .with_changed_paths(Some(ChangedPathIndex::new(table))))
+    }
+
     /// Rebuilds index for the given `operation`.
     ///
     /// The index to be built will be calculated from one of the ancestor
@@ -148,15 +298,146 @@ impl DefaultIndexStore {
         operation: &Operation,
         store: &Arc<Store>,
     ) -> Result<DefaultReadonlyIndex, DefaultIndexStoreError> {
-        let index_segment = self.build_index_segments_at_operation(operation, store)?;
-        Ok(DefaultReadonlyIndex::from_segment(index_segment))
+        let index_segment = self.build_index_segments_at_operation(operation, store, true)?;
+        let changed_paths = self.load_changed_path_index(store.commit_id_length())?;
+        let index =
+            DefaultReadonlyIndex::from_segment(index_segment).with_changed_paths(changed_paths);
+        self.update_changed_path_index(index, store)
     }
 
+    /// Rebuilds the index at the `operation` as a single segment.
+    ///
+    /// The segments the index of the `operation` was loaded from before are
+    /// kept, since concurrent processes may still be loading them. They're
+    /// marked as superseded, and removed by `gc()` once the mark is older than
+    /// its `keep_newer` time.
+    #[tracing::instrument(skip(self, store))]
+    pub fn compact(
+        &self,
+        operation: &Operation,
+        store: &Arc<Store>,
+    ) -> Result<(), DefaultIndexStoreError> {
+        let old_segment_names = match self.load_index_segments_at_operation(
+            operation.id(),
+            store.commit_id_length(),
+            store.change_id_length(),
+        ) {
+            Ok(segment) => segment_chain_names(&segment),
+            Err(_) => vec![],
+        };
+        let index_segment = self.build_index_segments_at_operation(operation, store, false)?;
+        let superseded_dir = self.superseded_dir();
+        fs::create_dir_all(&superseded_dir)
+            .context(&superseded_dir)
+            .map_err(DefaultIndexStoreError::RemoveOldFiles)?;
+        for name in old_segment_names {
+            if name != index_segment.name() {
+                let path = superseded_dir.join(name);
+                fs::write(&path, b"")
+                    .context(&path)
+                    .map_err(DefaultIndexStoreError::RemoveOldFiles)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn superseded_dir(&self) -> PathBuf {
+        self.dir.join("superseded")
+    }
+
+    /// Removes the operation links older than `keep_newer`, except the ones of
+    /// the `head_op_ids`, and then the index segments that no remaining link
+    /// needs. Segments newer than `keep_newer`, or superseded by `compact()`
+    /// after that, are kept so that concurrent processes can still load them.
+    ///
+    /// Returns the number of removed (or, if `dry_run`, removable) segments.
+    #[tracing::instrument(skip(self, store))]
+    pub fn gc(
+        &self,
+        head_op_ids: &[OperationId],
+        store: &Arc<Store>,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> Result<usize, DefaultIndexStoreError> {
+        let is_old = |entry: &fs::DirEntry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|mtime| mtime <= keep_newer)
+        };
+        let remove_old_files = || -> Result<usize, PathError> {
+            let op_dir = self.dir.join("operations");
+            let mut needed_segments = HashSet::new();
+            for entry in op_dir.read_dir().context(&op_dir)? {
+                let entry = entry.context(&op_dir)?;
+                let path = entry.path();
+                let Some(op_id) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| OperationId::try_from_hex(name).ok())
+                else {
+                    continue;
+                };
+                if is_old(&entry) && !head_op_ids.contains(&op_id) {
+                    if !dry_run {
+                        fs::remove_file(&path).context(&path)?;
+                    }
+                    continue;
+                }
+                if let Ok(segment) = self.load_index_segments_at_operation(
+                    &op_id,
+                    store.commit_id_length(),
+                    store.change_id_length(),
+                ) {
+                    needed_segments.extend(segment_chain_names(&segment));
+                }
+            }
+            let superseded_dir = self.superseded_dir();
+            let mut recently_superseded = HashSet::new();
+            if superseded_dir.is_dir() {
+                for entry in superseded_dir.read_dir().context(&superseded_dir)? {
+                    let entry = entry.context(&superseded_dir)?;
+                    if !is_old(&entry) {
+                        recently_superseded.insert(entry.file_name());
+                    } else if !dry_run {
+                        let path = entry.path();
+                        fs::remove_file(&path).context(&path)?;
+                    }
+                }
+            }
+            let mut removed_count = 0;
+            for entry in self.dir.read_dir().context(&self.dir)? {
+                let entry = entry.context(&self.dir)?;
+                let path = entry.path();
+                let name = entry.file_name();
+                if name.len() != SEGMENT_FILE_NAME_LENGTH
+                    || name
+                        .to_str()
+                        .map_or(true, |name| needed_segments.contains(name))
+                    || recently_superseded.contains(&name)
+                    || !is_old(&entry)
+                {
+                    continue;
+                }
+                if !dry_run {
+                    fs::remove_file(&path).context(&path)?;
+                }
+                removed_count += 1;
+            }
+            Ok(removed_count)
+        };
+        remove_old_files().map_err(DefaultIndexStoreError::RemoveOldFiles)
+    }
+
+    /// Indexes the commits reachable from the `operation`. If
+    /// `reuse_existing` is true, the index of an ancestor operation is used as
+    /// the parent segment.
     #[tracing::instrument(skip(self, store))]
     fn build_index_segments_at_operation(
         &self,
         operation: &Operation,
         store: &Arc<Store>,
+        reuse_existing: bool,
     ) -> Result<Arc<ReadonlyIndexSegment>, DefaultIndexStoreError> {
         let view = operation.view()?;
         let operations_dir = self.dir.join("operations");
@@ -168,21 +449,33 @@ impl DefaultIndexStore {
             .iter()
             .map(|commit_id| (commit_id.clone(), operation.id().clone()))
             .collect();
+        let has_index =
+            |op: &Operation| reuse_existing && operations_dir.join(op.id().hex()).is_file();
+        // Pick the latest existing ancestor operation as the parent segment.
+        // Perhaps, breadth-first search is more appropriate here, but that
+        // wouldn't matter in practice as the operation log is mostly linear.
+        // Since the parent segment contains all commits reachable from its
+        // operation, there's no need to walk ancestors of that operation.
         let mut parent_op_id: Option<OperationId> = None;
-        for op in dag_walk::dfs_ok(
+        let mut parent_op_found = false;
+        let ops = dag_walk::dfs_ok(
             [Ok(operation.clone())],
             |op: &Operation| op.id().clone(),
-            |op: &Operation| op.parents().collect_vec(),
-        ) {
+            |op: &Operation| {
+                if !parent_op_found && has_index(op) {
+                    parent_op_found = true;
+                    vec![]
+                } else {
+                    op.parents().collect_vec()
+                }
+            },
+        );
+        for op in ops {
             let op = op?;
-            // Pick the latest existing ancestor operation as the parent
-            // segment. Perhaps, breadth-first search is more appropriate here,
-            // but that wouldn't matter in practice as the operation log is
-            // mostly linear.
-            if parent_op_id.is_none() && operations_dir.join(op.id().hex()).is_file() {
+            if parent_op_id.is_none() && has_index(&op) {
                 parent_op_id = Some(op.id().clone());
+                continue;
             }
-            // TODO: no need to walk ancestors of the parent_op_id operation
             for commit_id in op.view()?.all_referenced_commit_ids() {
                 if visited_heads.insert(commit_id.clone()) {
                     historical_heads.push((commit_id.clone(), op.id().clone()));
@@ -303,15 +596,30 @@ impl IndexStore for DefaultIndexStore {
         op: &Operation,
         store: &Arc<Store>,
     ) -> Result<Box<dyn ReadonlyIndex>, IndexReadError> {
-        let index_segment = match self.load_index_segments_at_operation(
-            op.id(),
-            store.commit_id_length(),
-            store.change_id_length(),
-        ) {
+        let load = || {
+            self.load_index_segments_at_operation(
+                op.id(),
+                store.commit_id_length(),
+                store.change_id_length(),
+            )
+        };
+        let index_segment = match load() {
+            // The segment may have been removed by a concurrent gc after the
+            // link was read, in which case the link has been updated since.
+            Err(DefaultIndexStoreError::LoadIndex(err)) if err.is_not_found() => load(),
+            result => result,
+        };
+        let index_segment = match index_segment {
             Err(DefaultIndexStoreError::LoadAssociation(err))
                 if err.kind() == io::ErrorKind::NotFound =>
             {
-                self.build_index_segments_at_operation(op, store)
+                self.build_index_segments_at_operation(op, store, true)
+            }
+            // The link still points to a removed segment. Other links may too,
+            // so the index is rebuilt without reusing any, but the files other
+            // processes use are left alone.
+            Err(DefaultIndexStoreError::LoadIndex(err)) if err.is_not_found() => {
+                self.build_index_segments_at_operation(op, store, false)
             }
             Err(DefaultIndexStoreError::LoadIndex(err)) if err.is_corrupt_or_not_found() => {
                 // If the index was corrupt (maybe it was written in a different format),
@@ -319,18 +627,26 @@ impl IndexStore for DefaultIndexStore {
                 // TODO: Move this message to a callback or something.
                 println!("The index was corrupt (maybe the format has changed). Reindexing...");
                 self.reinit().map_err(|err| IndexReadError(err.into()))?;
-                self.build_index_segments_at_operation(op, store)
+                self.build_index_segments_at_operation(op, store, true)
             }
             result => result,
         }
         .map_err(|err| IndexReadError(err.into()))?;
-        Ok(Box::new(DefaultReadonlyIndex::from_segment(index_segment)))
+        let changed_paths = self
+            .load_changed_path_index(store.commit_id_length())
+            .map_err(|err| IndexReadError(err.into()))?;
+        // The filters of the commits indexed while loading are built by
+        // maintenance, so that reading doesn't write.
+        let index =
+            DefaultReadonlyIndex::from_segment(index_segment).with_changed_paths(changed_paths);
+        Ok(Box::new(index))
     }
 
     fn write_index(
         &self,
         index: Box<dyn MutableIndex>,
         op_id: &OperationId,
+        store: &Arc<Store>,
     ) -> Result<Box<dyn ReadonlyIndex>, IndexWriteError> {
         let index = index
             .into_any()
@@ -339,6 +655,44 @@ impl IndexStore for DefaultIndexStore {
         let index_segment = self
             .save_mutable_index(*index, op_id)
             .map_err(|err| IndexWriteError(err.into()))?;
-        Ok(Box::new(DefaultReadonlyIndex::from_segment(index_segment)))
+        let changed_paths = self
+            .load_changed_path_index(index_segment.commit_id_length())
+            .map_err(|err| IndexWriteError(err.into()))?;
+        let index =
+            DefaultReadonlyIndex::from_segment(index_segment).with_changed_paths(changed_paths);
+        let index = self
+            .update_changed_path_index(index, store)
+            .map_err(|err| IndexWriteError(err.into()))?;
+        Ok(Box::new(index))
+    }
+}
+
+/// Lists the names of the `segment` and its ancestor segments.
+fn segment_chain_names(segment: &Arc<ReadonlyIndexSegment>) -> Vec<String> {
+    let mut names = vec![];
+    let mut maybe_segment = Some(segment);
+    while let Some(segment) = maybe_segment {
+        names.push(segment.name().to_owned());
+        maybe_segment = segment.parent_file();
     }
+    names
+}
+
+/// Lists the paths changed by the commit compared to its (merged) parents.
+fn changed_paths_of_commit(
+    store: &Arc<Store>,
+    index: &DefaultReadonlyIndex,
+    commit_id: &CommitId,
+) -> Result<Vec<RepoPathBuf>, TreeMergeError> {
+    let commit = store.get_commit(commit_id)?;
+    let parents = commit.parents();
+    let from_tree = rewrite::merge_commit_trees_without_repo(store, index, &parents)?;
+    let to_tree = commit.tree()?;
+    from_tree
+        .diff(&to_tree, &EverythingMatcher)
+        .map(|(path, diff)| {
+            diff?;
+            Ok(path)
+        })
+        .try_collect()
 }
@@ -82,6 +82,87 @@ pub fn find_nonword_ranges(text: &[u8]) -> Vec<Range<usize>> {
     ranges
 }
 
+/// Splits the text into characters. A character starts at every byte that
+/// isn't a UTF-8 continuation byte, and includes the continuation bytes after
+/// it. The text isn't validated, so stray continuation bytes are grouped with
+/// the preceding character, or form a character of their own at the start of
+/// the text.
+pub fn find_char_ranges(text: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    for (i, b) in text.iter().enumerate().skip(1) {
+        // UTF-8 continuation bytes are 0b10xxxxxx
+        if b & 0xc0 != 0x80 {
+            ranges.push(start..i);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        ranges.push(start..text.len());
+    }
+    ranges
+}
+
+/// How finely changed lines are compared to find the changed parts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DiffGranularity {
+    /// Only whole lines are compared.
+    Line,
+    /// Changed lines are compared word by word (and by punctuation character).
+    #[default]
+    Word,
+    /// Changed words are further compared character by character.
+    Char,
+}
+
+/// How lines are compared when looking for the matching lines.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineCompareMode {
+    /// Lines must be identical.
+    #[default]
+    Exact,
+    /// Changes in the amount of whitespace, and whitespace at the end of lines,
+    /// are ignored.
+    IgnoreSpaceChange,
+    /// All whitespace is ignored.
+    IgnoreAllSpace,
+}
+
+impl LineCompareMode {
+    /// Appends the part of the `line` that's compared to `out`. The newline
+    /// is preserved so a missing newline at the end of the file is still a
+    /// difference.
+    fn normalize_line(self, line: &[u8], out: &mut Vec<u8>) {
+        let (content, newline) = match line.strip_suffix(b"\n") {
+            Some(content) => (content, &b"\n"[..]),
+            None => (line, &b""[..]),
+        };
+        match self {
+            LineCompareMode::Exact => out.extend_from_slice(content),
+            LineCompareMode::IgnoreSpaceChange => {
+                // Trailing whitespace is dropped since a space is only emitted
+                // before the next non-whitespace byte.
+                let mut in_space = false;
+                for &b in content {
+                    if b.is_ascii_whitespace() {
+                        in_space = true;
+                    } else {
+                        if in_space {
+                            out.push(b' ');
+                            in_space = false;
+                        }
+                        out.push(b);
+                    }
+                }
+            }
+            LineCompareMode::IgnoreAllSpace => {
+                out.extend(content.iter().filter(|b| !b.is_ascii_whitespace()));
+            }
+        }
+        out.extend_from_slice(newline);
+    }
+}
+
 struct Histogram<'a> {
     word_to_positions: HashMap<&'a [u8], Vec<usize>>,
     count_to_words: BTreeMap<usize, Vec<&'a [u8]>>,
@@ -454,9 +535,25 @@ impl<'input> Diff<'input> {
     // probably mean that many callers repeat the same code. Perhaps it
     // should be possible to refine a whole diff *or* individual hunks.
     pub fn default_refinement(inputs: &[&'input [u8]]) -> Self {
+        Diff::with_granularity(inputs, DiffGranularity::Word)
+    }
+
+    /// Diffs the inputs by lines, and then refines the changed lines to the
+    /// given granularity.
+    pub fn with_granularity(inputs: &[&'input [u8]], granularity: DiffGranularity) -> Self {
         let mut diff = Diff::for_tokenizer(inputs, &find_line_ranges);
-        diff.refine_changed_regions(&find_word_ranges);
-        diff.refine_changed_regions(&find_nonword_ranges);
+        match granularity {
+            DiffGranularity::Line => {}
+            DiffGranularity::Word => {
+                diff.refine_changed_regions(&find_word_ranges);
+                diff.refine_changed_regions(&find_nonword_ranges);
+            }
+            DiffGranularity::Char => {
+                diff.refine_changed_regions(&find_word_ranges);
+                diff.refine_changed_regions(&find_nonword_ranges);
+                diff.refine_changed_regions(&find_char_ranges);
+            }
+        }
         diff
     }
 
@@ -620,6 +717,15 @@ impl<'diff, 'input> Iterator for DiffHunkIterator<'diff, 'input> {
 /// algorithm correctly). It first diffs lines in the input and then refines
 /// the changed ranges at the word level.
 pub fn diff<'a>(left: &'a [u8], right: &'a [u8]) -> Vec<DiffHunk<'a>> {
+    diff_with_granularity(left, right, DiffGranularity::Word)
+}
+
+/// Like `diff()`, but refines the changed lines to the given granularity.
+pub fn diff_with_granularity<'a>(
+    left: &'a [u8],
+    right: &'a [u8],
+    granularity: DiffGranularity,
+) -> Vec<DiffHunk<'a>> {
     if left == right {
         return vec![DiffHunk::Matching(left)];
     }
@@ -630,11 +736,110 @@ pub fn diff<'a>(left: &'a [u8], right: &'a [u8]) -> Vec<DiffHunk<'a>> {
         return vec![DiffHunk::Different(vec![left, b""])];
     }
 
-    Diff::default_refinement(&[left, right])
+    Diff::with_granularity(&[left, right], granularity)
         .hunks()
         .collect_vec()
 }
 
+/// Diffs the inputs by lines, comparing them as specified by `compare_mode`,
+/// and then refines the changed lines like [`diff_with_granularity()`]. Lines
+/// that match only after normalization are reported with the content of the
+/// left side.
+pub fn diff_with_options<'a>(
+    left: &'a [u8],
+    right: &'a [u8],
+    granularity: DiffGranularity,
+    compare_mode: LineCompareMode,
+) -> Vec<DiffHunk<'a>> {
+    if compare_mode == LineCompareMode::Exact || left.is_empty() || right.is_empty() {
+        return diff_with_granularity(left, right, granularity);
+    }
+
+    let normalize = |text: &[u8], line_ranges: &[Range<usize>]| {
+        let mut normalized = vec![];
+        let mut normalized_ranges = vec![];
+        for range in line_ranges {
+            let start = normalized.len();
+            compare_mode.normalize_line(&text[range.clone()], &mut normalized);
+            normalized_ranges.push(start..normalized.len());
+        }
+        (normalized, normalized_ranges)
+    };
+    let left_lines = find_line_ranges(left);
+    let right_lines = find_line_ranges(right);
+    let (left_normalized, left_normalized_ranges) = normalize(left, &left_lines);
+    let (right_normalized, right_normalized_ranges) = normalize(right, &right_lines);
+    // Only the last line can be empty after normalization, so the line of a
+    // range can be found by its start.
+    let line_index = |ranges: &[Range<usize>], range: &Range<usize>| {
+        ranges
+            .binary_search_by_key(&range.start, |r| r.start)
+            .unwrap()
+    };
+    let line_span = |text: &'a [u8], lines: &[Range<usize>], span: Range<usize>| {
+        let start = lines.get(span.start).map_or(text.len(), |r| r.start);
+        let end = if span.is_empty() {
+            start
+        } else {
+            lines[span.end - 1].end
+        };
+        &text[start..end]
+    };
+
+    let different_hunks = |left_span: Range<usize>, right_span: Range<usize>| {
+        let left_text = line_span(left, &left_lines, left_span);
+        let right_text = line_span(right, &right_lines, right_span);
+        if left_text.is_empty() && right_text.is_empty() {
+            vec![]
+        } else if granularity == DiffGranularity::Line
+            || left_text.is_empty()
+            || right_text.is_empty()
+        {
+            vec![DiffHunk::Different(vec![left_text, right_text])]
+        } else {
+            Diff::with_granularity(&[left_text, right_text], granularity)
+                .hunks()
+                .collect_vec()
+        }
+    };
+    let mut matching_spans: Vec<(Range<usize>, Range<usize>)> = vec![];
+    for (left_range, right_range) in unchanged_ranges(
+        &left_normalized,
+        &right_normalized,
+        &left_normalized_ranges,
+        &right_normalized_ranges,
+    ) {
+        let left_index = line_index(&left_normalized_ranges, &left_range);
+        let right_index = line_index(&right_normalized_ranges, &right_range);
+        match matching_spans.last_mut() {
+            Some((left_span, right_span))
+                if left_span.end == left_index && right_span.end == right_index =>
+            {
+                left_span.end += 1;
+                right_span.end += 1;
+            }
+            _ => matching_spans.push((left_index..left_index + 1, right_index..right_index + 1)),
+        }
+    }
+    let mut hunks = vec![];
+    let mut left_pos = 0;
+    let mut right_pos = 0;
+    for (left_span, right_span) in matching_spans {
+        hunks.extend(different_hunks(
+            left_pos..left_span.start,
+            right_pos..right_span.start,
+        ));
+        left_pos = left_span.end;
+        right_pos = right_span.end;
+        hunks.push(DiffHunk::Matching(line_span(left, &left_lines, left_span)));
+    }
+    hunks.extend(different_hunks(
+        left_pos..left_lines.len(),
+        right_pos..right_lines.len(),
+    ));
+    hunks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -695,6 +900,15 @@ mod tests {
         assert_eq!(find_word_ranges("⊢".as_bytes()), vec![0..3])
     }
 
+    #[test]
+    fn test_find_char_ranges() {
+        assert_eq!(find_char_ranges(b""), no_ranges());
+        assert_eq!(find_char_ranges(b"ab"), vec![0..1, 1..2]);
+        assert_eq!(find_char_ranges("a⊢b".as_bytes()), vec![0..1, 1..4, 4..5]);
+        // Stray continuation bytes stick to the preceding character
+        assert_eq!(find_char_ranges(b"\x80a\xbf"), vec![0..1, 1..3]);
+    }
+
     #[test]
     fn test_find_lcs_empty() {
         let empty: Vec<(usize, usize)> = vec![];
@@ -1002,6 +1216,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_diff_ignore_space_change() {
+        let diff = |left: &'static [u8], right: &'static [u8]| {
+            diff_with_options(
+                left,
+                right,
+                DiffGranularity::Line,
+                LineCompareMode::IgnoreSpaceChange,
+            )
+        };
+        assert_eq!(
+            diff(b"a  b\nc\n", b"a b \nd\n"),
+            vec![
+                DiffHunk::Matching(b"a  b\n"),
+                DiffHunk::Different(vec![b"c\n", b"d\n"]),
+            ]
+        );
+        // Whitespace can't be added to or removed from between words
+        assert_eq!(
+            diff(b"ab\n", b"a b\n"),
+            vec![DiffHunk::Different(vec![b"ab\n", b"a b\n"])]
+        );
+        // Nor at the start of a line
+        assert_eq!(
+            diff(b"a\n", b"  a\n"),
+            vec![DiffHunk::Different(vec![b"a\n", b"  a\n"])]
+        );
+        // A missing newline at the end is still a difference
+        assert_eq!(
+            diff(b"a\nb", b"a\nb\n"),
+            vec![
+                DiffHunk::Matching(b"a\n"),
+                DiffHunk::Different(vec![b"b", b"b\n"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_ignore_all_space() {
+        let diff = |left: &'static [u8], right: &'static [u8], granularity| {
+            diff_with_options(left, right, granularity, LineCompareMode::IgnoreAllSpace)
+        };
+        assert_eq!(
+            diff(b"a\nb c\nd\n", b"  a\nbc\r\ne\n", DiffGranularity::Line),
+            vec![
+                DiffHunk::Matching(b"a\nb c\n"),
+                DiffHunk::Different(vec![b"d\n", b"e\n"]),
+            ]
+        );
+        assert_eq!(
+            diff(b"\t\nx\n", b"\ny\n", DiffGranularity::Line),
+            vec![
+                DiffHunk::Matching(b"\t\n"),
+                DiffHunk::Different(vec![b"x\n", b"y\n"]),
+            ]
+        );
+        // Changed lines are refined to the given granularity
+        assert_eq!(
+            diff(b" a\nfoo bar\n", b"a\nfoo baz\n", DiffGranularity::Word),
+            vec![
+                DiffHunk::Matching(b" a\n"),
+                DiffHunk::Matching(b"foo "),
+                DiffHunk::Different(vec![b"bar", b"baz"]),
+                DiffHunk::Matching(b"\n"),
+            ]
+        );
+    }
+
     #[test]
     fn test_diff_real_case_write_fmt() {
         // This is from src/ui.rs in commit f44d246e3f88 in this repo. It highlights the
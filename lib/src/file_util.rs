@@ -14,9 +14,10 @@
 
 #![allow(missing_docs)]
 
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::path::{Component, Path, PathBuf};
-use std::{io, iter};
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::{env, io, iter};
 
 use tempfile::{NamedTempFile, PersistError};
 use thiserror::Error;
@@ -60,9 +61,13 @@ pub fn create_or_reuse_dir(dirname: &Path) -> io::Result<()> {
 /// Both `from` and `to` paths are supposed to be absolute and normalized in the
 /// same manner.
 pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    // On Windows, one of the paths may be in the extended-length form (e.g.
+    // canonicalized) and the other not.
+    let from = to_extended_length_path(from);
+    let extended_to = to_extended_length_path(to);
     // Find common prefix.
     for (i, base) in from.ancestors().enumerate() {
-        if let Ok(suffix) = to.strip_prefix(base) {
+        if let Ok(suffix) = extended_to.strip_prefix(base) {
             if i == 0 && suffix.as_os_str().is_empty() {
                 return ".".into();
             } else {
@@ -103,6 +108,70 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     }
 }
 
+/// Converts an absolute Windows path to the extended-length form
+/// (`\\?\C:\...` or `\\?\UNC\server\share\...`), which can be longer than
+/// `MAX_PATH`.
+///
+/// Since Windows doesn't normalize paths in this form, `.` and `..` are
+/// consumed without considering symlinks. Paths that are already in this form,
+/// relative paths, and all paths on other platforms are returned as is.
+pub fn to_extended_length_path(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+    let mut result = match prefix.kind() {
+        Prefix::Disk(letter) => {
+            OsString::from(format!(r"\\?\{}:", char::from(letter.to_ascii_uppercase())))
+        }
+        Prefix::UNC(server, share) => {
+            let mut result = OsString::from(r"\\?\UNC\");
+            result.push(server);
+            result.push(r"\");
+            result.push(share);
+            result
+        }
+        _ => return path.to_owned(),
+    };
+    if components.next() != Some(Component::RootDir) {
+        // Relative to the current directory of the drive, e.g. "C:foo"
+        return path.to_owned();
+    }
+    let mut names = vec![];
+    for c in components {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                names.pop();
+            }
+            Component::Normal(name) => names.push(name),
+            Component::Prefix(_) | Component::RootDir => unreachable!(),
+        }
+    }
+    if names.is_empty() {
+        result.push(r"\");
+    }
+    for name in names {
+        result.push(r"\");
+        result.push(name);
+    }
+    result.into()
+}
+
+/// Like `Path::canonicalize()`, but on Windows, falls back to the
+/// extended-length form of the absolute path if the path exists but can't be
+/// resolved, which happens on some network shares.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match path.canonicalize() {
+        Ok(canonical_path) => Ok(canonical_path),
+        Err(_) if cfg!(windows) && path.exists() => {
+            let absolute_path = env::current_dir()?.join(path);
+            Ok(to_extended_length_path(&absolute_path))
+        }
+        Err(err) => Err(err),
+    }
+}
+
 /// Like `NamedTempFile::persist()`, but doesn't try to overwrite the existing
 /// target on Windows.
 pub fn persist_content_addressed_temp_file<P: AsRef<Path>>(
@@ -156,6 +225,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/repo"), Path::new("/repo/dir/file")),
+            Path::new("dir/file")
+        );
+        assert_eq!(
+            relative_path(Path::new("/repo/dir"), Path::new("/repo/file")),
+            Path::new("../file")
+        );
+        assert_eq!(
+            relative_path(Path::new("/repo"), Path::new("/repo")),
+            Path::new(".")
+        );
+        assert_eq!(
+            relative_path(Path::new("/repo"), Path::new("/other/file")),
+            Path::new("../other/file")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_relative_path_extended_length() {
+        assert_eq!(
+            relative_path(Path::new(r"\\?\C:\repo"), Path::new(r"c:\repo\dir\file")),
+            Path::new(r"dir\file")
+        );
+        assert_eq!(
+            relative_path(
+                Path::new(r"\\server\share\repo\dir"),
+                Path::new(r"\\?\UNC\server\share\repo\file")
+            ),
+            Path::new(r"..\file")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_to_extended_length_path() {
+        assert_eq!(
+            to_extended_length_path(Path::new(r"c:\foo/bar\..\.\baz")),
+            Path::new(r"\\?\C:\foo\baz")
+        );
+        assert_eq!(
+            to_extended_length_path(Path::new(r"C:\")),
+            Path::new(r"\\?\C:\")
+        );
+        assert_eq!(
+            to_extended_length_path(Path::new(r"\\server\share\foo")),
+            Path::new(r"\\?\UNC\server\share\foo")
+        );
+        assert_eq!(
+            to_extended_length_path(Path::new(r"\\?\C:\foo\..")),
+            Path::new(r"\\?\C:\foo\..")
+        );
+        assert_eq!(
+            to_extended_length_path(Path::new(r"C:foo")),
+            Path::new(r"C:foo")
+        );
+        assert_eq!(
+            to_extended_length_path(Path::new(r"foo\bar")),
+            Path::new(r"foo\bar")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_extended_length_path() {
+        assert_eq!(
+            to_extended_length_path(Path::new("/foo/../bar")),
+            Path::new("/foo/../bar")
+        );
+    }
+
     #[test]
     fn test_persist_no_existing_file() {
         let temp_dir = testutils::new_temp_dir();
@@ -21,7 +21,7 @@ use std::ops::Range;
 use itertools::Itertools;
 
 use crate::diff;
-use crate::diff::{Diff, DiffHunk};
+use crate::diff::{Diff, DiffGranularity, DiffHunk, LineCompareMode};
 use crate::merge::{trivial_merge, Merge};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -48,7 +48,25 @@ impl DiffLine<'_> {
 }
 
 pub fn diff<'a>(left: &'a [u8], right: &'a [u8]) -> DiffLineIterator<'a> {
-    let diff_hunks = diff::diff(left, right);
+    diff_with_granularity(left, right, DiffGranularity::Word)
+}
+
+pub fn diff_with_granularity<'a>(
+    left: &'a [u8],
+    right: &'a [u8],
+    granularity: DiffGranularity,
+) -> DiffLineIterator<'a> {
+    let diff_hunks = diff::diff_with_granularity(left, right, granularity);
+    DiffLineIterator::new(diff_hunks)
+}
+
+pub fn diff_with_options<'a>(
+    left: &'a [u8],
+    right: &'a [u8],
+    granularity: DiffGranularity,
+    compare_mode: LineCompareMode,
+) -> DiffLineIterator<'a> {
+    let diff_hunks = diff::diff_with_options(left, right, granularity, compare_mode);
     DiffLineIterator::new(diff_hunks)
 }
 
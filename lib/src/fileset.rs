@@ -0,0 +1,701 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Functional language for selecting a set of paths.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::{error, fmt, iter};
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use pest::iterators::Pair;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use pest::Parser as _;
+use thiserror::Error;
+
+use self::parser::{FilesetParser, Rule};
+use crate::backend::{BackendResult, TreeValue};
+use crate::matchers::{
+    DifferenceMatcher, EverythingMatcher, FileGlobsMatcher, FilesMatcher, IntersectionMatcher,
+    Matcher, NothingMatcher, PrefixMatcher, UnionMatcher,
+};
+use crate::merged_tree::MergedTree;
+use crate::repo_path::{FsPathParseError, RepoPath, RepoPathBuf};
+use crate::revset;
+
+mod parser {
+    #[derive(pest_derive::Parser)]
+    #[grammar = "fileset.pest"]
+    pub struct FilesetParser;
+}
+
+impl Rule {
+    fn to_symbol(self) -> Option<&'static str> {
+        match self {
+            Rule::negate_op | Rule::difference_op => Some("~"),
+            Rule::union_op => Some("|"),
+            Rule::intersection_op => Some("&"),
+            Rule::pattern_kind_op => Some(":"),
+            _ => None,
+        }
+    }
+}
+
+/// Error occurred during fileset parsing.
+#[derive(Debug, Eq, PartialEq)]
+pub struct FilesetParseError {
+    kind: FilesetParseErrorKind,
+    pest_error: Option<Box<pest::error::Error<Rule>>>,
+}
+
+/// Categories of fileset parsing error.
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum FilesetParseErrorKind {
+    /// Syntax error.
+    #[error("Syntax error")]
+    SyntaxError,
+    /// No such function.
+    #[error(r#"Function "{name}" doesn't exist"#)]
+    NoSuchFunction {
+        /// Function name.
+        name: String,
+        /// Similar function names.
+        candidates: Vec<String>,
+    },
+    /// No such kind of pattern.
+    #[error(r#"Invalid file pattern kind "{0}:""#)]
+    NoSuchPatternKind(String),
+    /// Invalid file path.
+    #[error("Invalid file pattern")]
+    FsPathParseError(#[source] FsPathParseError),
+    /// Invalid glob pattern.
+    #[error("Invalid glob pattern: {0}")]
+    GlobPatternError(String),
+}
+
+impl FilesetParseError {
+    fn with_span(kind: FilesetParseErrorKind, span: pest::Span<'_>) -> Self {
+        let message = iter::successors(Some(&kind as &dyn error::Error), |e| e.source()).join(": ");
+        let err = pest::error::Error::new_from_span(
+            pest::error::ErrorVariant::CustomError { message },
+            span,
+        );
+        FilesetParseError {
+            kind,
+            pest_error: Some(Box::new(err)),
+        }
+    }
+
+    /// Category of the underlying error.
+    pub fn kind(&self) -> &FilesetParseErrorKind {
+        &self.kind
+    }
+
+    /// Consumes this and returns the category of the underlying error.
+    pub fn into_kind(self) -> FilesetParseErrorKind {
+        self.kind
+    }
+}
+
+impl From<pest::error::Error<Rule>> for FilesetParseError {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        let err = err.renamed_rules(|rule| {
+            rule.to_symbol()
+                .map(|sym| format!("`{sym}`"))
+                .unwrap_or_else(|| format!("<{rule:?}>"))
+        });
+        FilesetParseError {
+            kind: FilesetParseErrorKind::SyntaxError,
+            pest_error: Some(Box::new(err)),
+        }
+    }
+}
+
+impl fmt::Display for FilesetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(err) = &self.pest_error {
+            err.fmt(f)
+        } else {
+            self.kind.fmt(f)
+        }
+    }
+}
+
+impl error::Error for FilesetParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            // SyntaxError is a wrapper for pest::error::Error.
+            FilesetParseErrorKind::SyntaxError => {
+                self.pest_error.as_ref().map(|e| e as &dyn error::Error)
+            }
+            // Otherwise the kind represents this error.
+            e => e.source(),
+        }
+    }
+}
+
+/// Basic pattern to match `RepoPath`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FilePattern {
+    /// Matches the file at the path.
+    FilePath(RepoPathBuf),
+    /// Matches the file at the path, or the files under the directory at it.
+    PrefixPath(RepoPathBuf),
+    /// Matches the files under the directory whose relative paths match the
+    /// glob pattern.
+    FileGlob {
+        /// The directory the pattern is relative to.
+        dir: RepoPathBuf,
+        /// The pattern, in which wildcards don't match path separators except
+        /// for `**`.
+        pattern: glob::Pattern,
+    },
+}
+
+/// AST-level representation of a fileset expression.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FilesetExpression {
+    /// Matches nothing.
+    None,
+    /// Matches everything.
+    All,
+    /// Matches the paths matched by the pattern.
+    Pattern(FilePattern),
+    /// Matches the executable files. Must be resolved against trees with
+    /// `resolve_predicates()` before being turned into a matcher.
+    Executable,
+    /// Matches the paths matched by any of the expressions.
+    UnionAll(Vec<FilesetExpression>),
+    /// Matches the paths matched by both expressions.
+    Intersection(Box<FilesetExpression>, Box<FilesetExpression>),
+    /// Matches the paths matched by the first expression but not by the
+    /// second.
+    Difference(Box<FilesetExpression>, Box<FilesetExpression>),
+}
+
+impl FilesetExpression {
+    /// Expression that matches nothing.
+    pub fn none() -> Self {
+        FilesetExpression::None
+    }
+
+    /// Expression that matches everything.
+    pub fn all() -> Self {
+        FilesetExpression::All
+    }
+
+    /// Expression that matches the file at the `path`.
+    pub fn file_path(path: RepoPathBuf) -> Self {
+        FilesetExpression::Pattern(FilePattern::FilePath(path))
+    }
+
+    /// Expression that matches the file at the `path`, or the files under the
+    /// directory at it.
+    pub fn prefix_path(path: RepoPathBuf) -> Self {
+        FilesetExpression::Pattern(FilePattern::PrefixPath(path))
+    }
+
+    /// Expression that matches the paths matched by any of the `expressions`.
+    pub fn union_all(expressions: Vec<FilesetExpression>) -> Self {
+        match expressions.len() {
+            0 => FilesetExpression::None,
+            1 => expressions.into_iter().next().unwrap(),
+            _ => FilesetExpression::UnionAll(expressions),
+        }
+    }
+
+    /// Expression that matches the paths matched by both `self` and `other`.
+    pub fn intersection(self, other: Self) -> Self {
+        FilesetExpression::Intersection(Box::new(self), Box::new(other))
+    }
+
+    /// Expression that matches the paths matched by `self` but not by `other`.
+    pub fn difference(self, other: Self) -> Self {
+        FilesetExpression::Difference(Box::new(self), Box::new(other))
+    }
+
+    /// Returns the paths if this expression only consists of `FilePath` and
+    /// `PrefixPath` patterns, like a list of plain paths does.
+    pub fn as_explicit_paths(&self) -> Option<Vec<&RepoPath>> {
+        match self {
+            FilesetExpression::Pattern(
+                FilePattern::FilePath(path) | FilePattern::PrefixPath(path),
+            ) => Some(vec![path]),
+            FilesetExpression::UnionAll(expressions) => {
+                let mut paths = vec![];
+                for expression in expressions {
+                    paths.extend(expression.as_explicit_paths()?);
+                }
+                Some(paths)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this expression contains predicates, like `executable()`, that
+    /// depend on the contents of trees.
+    pub fn has_predicates(&self) -> bool {
+        match self {
+            FilesetExpression::None | FilesetExpression::All | FilesetExpression::Pattern(_) => {
+                false
+            }
+            FilesetExpression::Executable => true,
+            FilesetExpression::UnionAll(expressions) => expressions
+                .iter()
+                .any(|expression| expression.has_predicates()),
+            FilesetExpression::Intersection(expression1, expression2)
+            | FilesetExpression::Difference(expression1, expression2) => {
+                expression1.has_predicates() || expression2.has_predicates()
+            }
+        }
+    }
+
+    /// Replaces the predicates with the paths they match in any of the
+    /// `trees`.
+    pub fn resolve_predicates(&self, trees: &[&MergedTree]) -> BackendResult<Self> {
+        if !self.has_predicates() {
+            return Ok(self.clone());
+        }
+        let resolved = match self {
+            FilesetExpression::Executable => {
+                let mut paths = BTreeSet::new();
+                for tree in trees {
+                    for (path, value) in tree.entries() {
+                        let executable = value.adds().any(|value| {
+                            matches!(
+                                value,
+                                Some(TreeValue::File {
+                                    executable: true,
+                                    ..
+                                })
+                            )
+                        });
+                        if executable {
+                            paths.insert(path);
+                        }
+                    }
+                }
+                FilesetExpression::union_all(
+                    paths
+                        .into_iter()
+                        .map(FilesetExpression::file_path)
+                        .collect(),
+                )
+            }
+            FilesetExpression::UnionAll(expressions) => FilesetExpression::UnionAll(
+                expressions
+                    .iter()
+                    .map(|expression| expression.resolve_predicates(trees))
+                    .try_collect()?,
+            ),
+            FilesetExpression::Intersection(expression1, expression2) => expression1
+                .resolve_predicates(trees)?
+                .intersection(expression2.resolve_predicates(trees)?),
+            FilesetExpression::Difference(expression1, expression2) => expression1
+                .resolve_predicates(trees)?
+                .difference(expression2.resolve_predicates(trees)?),
+            FilesetExpression::None | FilesetExpression::All | FilesetExpression::Pattern(_) => {
+                unreachable!()
+            }
+        };
+        Ok(resolved)
+    }
+
+    /// Transforms the expression tree to a `Matcher` object. Predicates that
+    /// haven't been resolved match nothing.
+    pub fn to_matcher(&self) -> Box<dyn Matcher> {
+        match self {
+            FilesetExpression::None | FilesetExpression::Executable => Box::new(NothingMatcher),
+            FilesetExpression::All => Box::new(EverythingMatcher),
+            FilesetExpression::Pattern(pattern) => patterns_to_matcher([pattern]),
+            FilesetExpression::UnionAll(expressions) => {
+                // Combine the patterns into a few matchers
+                let (patterns, others): (Vec<_>, Vec<_>) =
+                    expressions
+                        .iter()
+                        .partition_map(|expression| match expression {
+                            FilesetExpression::Pattern(pattern) => itertools::Either::Left(pattern),
+                            _ => itertools::Either::Right(expression),
+                        });
+                others
+                    .into_iter()
+                    .map(|expression| expression.to_matcher())
+                    .fold(patterns_to_matcher(patterns), |matcher, other| {
+                        Box::new(UnionMatcher::new(matcher, other))
+                    })
+            }
+            FilesetExpression::Intersection(expression1, expression2) => Box::new(
+                IntersectionMatcher::new(expression1.to_matcher(), expression2.to_matcher()),
+            ),
+            FilesetExpression::Difference(expression1, expression2) => Box::new(
+                DifferenceMatcher::new(expression1.to_matcher(), expression2.to_matcher()),
+            ),
+        }
+    }
+}
+
+fn patterns_to_matcher<'a>(
+    patterns: impl IntoIterator<Item = &'a FilePattern>,
+) -> Box<dyn Matcher> {
+    let mut file_paths = vec![];
+    let mut prefix_paths = vec![];
+    let mut globs = vec![];
+    for pattern in patterns {
+        match pattern {
+            FilePattern::FilePath(path) => file_paths.push(path),
+            FilePattern::PrefixPath(path) => prefix_paths.push(path),
+            FilePattern::FileGlob { dir, pattern } => globs.push((dir.clone(), pattern.clone())),
+        }
+    }
+    let mut matchers: Vec<Box<dyn Matcher>> = vec![];
+    if !file_paths.is_empty() {
+        matchers.push(Box::new(FilesMatcher::new(file_paths)));
+    }
+    if !prefix_paths.is_empty() {
+        matchers.push(Box::new(PrefixMatcher::new(prefix_paths)));
+    }
+    if !globs.is_empty() {
+        matchers.push(Box::new(FileGlobsMatcher::new(globs)));
+    }
+    matchers
+        .into_iter()
+        .reduce(|matcher1, matcher2| Box::new(UnionMatcher::new(matcher1, matcher2)))
+        .unwrap_or_else(|| Box::new(NothingMatcher))
+}
+
+/// Context needed to resolve the paths in a fileset expression.
+#[derive(Clone, Copy, Debug)]
+pub struct FilesetParseContext<'a> {
+    /// Directory that relative paths are resolved against.
+    pub cwd: &'a Path,
+    /// Root of the workspace.
+    pub workspace_root: &'a Path,
+}
+
+impl FilesetParseContext<'_> {
+    fn parse_path(
+        &self,
+        input: &str,
+        span: pest::Span<'_>,
+    ) -> Result<RepoPathBuf, FilesetParseError> {
+        RepoPathBuf::parse_fs_path(self.cwd, self.workspace_root, input).map_err(|err| {
+            FilesetParseError::with_span(FilesetParseErrorKind::FsPathParseError(err), span)
+        })
+    }
+
+    fn parse_root_path(
+        &self,
+        input: &str,
+        span: pest::Span<'_>,
+    ) -> Result<RepoPathBuf, FilesetParseError> {
+        RepoPathBuf::parse_fs_path(self.workspace_root, self.workspace_root, input).map_err(|err| {
+            FilesetParseError::with_span(FilesetParseErrorKind::FsPathParseError(err), span)
+        })
+    }
+}
+
+const FUNCTION_NAMES: &[&str] = &["all", "none", "executable"];
+
+/// Parses the `text` as a fileset expression.
+///
+/// A plain path, or a string that isn't valid fileset syntax, is parsed as a
+/// path prefix relative to the current directory.
+pub fn parse(
+    text: &str,
+    context: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    let mut pairs = FilesetParser::parse(Rule::program, text)?;
+    let first = pairs.next().unwrap();
+    match first.as_rule() {
+        Rule::expression => parse_expression_rule(first, context),
+        Rule::bare_string => {
+            let path = context.parse_path(first.as_str(), first.as_span())?;
+            Ok(FilesetExpression::prefix_path(path))
+        }
+        r => panic!("unexpected program rule {r:?}"),
+    }
+}
+
+fn parse_expression_rule(
+    pair: Pair<Rule>,
+    context: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    static PRATT: Lazy<PrattParser<Rule>> = Lazy::new(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::union_op, Assoc::Left))
+            .op(Op::infix(Rule::intersection_op, Assoc::Left)
+                | Op::infix(Rule::difference_op, Assoc::Left))
+            .op(Op::prefix(Rule::negate_op))
+    });
+    PRATT
+        .map_primary(|primary| parse_primary_rule(primary, context))
+        .map_prefix(|op, rhs| match op.as_rule() {
+            Rule::negate_op => Ok(FilesetExpression::all().difference(rhs?)),
+            r => panic!("unexpected prefix operator rule {r:?}"),
+        })
+        .map_infix(|lhs, op, rhs| match op.as_rule() {
+            Rule::union_op => Ok(FilesetExpression::union_all(vec![lhs?, rhs?])),
+            Rule::intersection_op => Ok(lhs?.intersection(rhs?)),
+            Rule::difference_op => Ok(lhs?.difference(rhs?)),
+            r => panic!("unexpected infix operator rule {r:?}"),
+        })
+        .parse(pair.into_inner())
+}
+
+fn parse_primary_rule(
+    pair: Pair<Rule>,
+    context: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    assert_eq!(pair.as_rule(), Rule::primary);
+    let first = pair.into_inner().next().unwrap();
+    match first.as_rule() {
+        Rule::expression => parse_expression_rule(first, context),
+        Rule::function => {
+            let name_pair = first.into_inner().next().unwrap();
+            match name_pair.as_str() {
+                "all" => Ok(FilesetExpression::all()),
+                "none" => Ok(FilesetExpression::none()),
+                "executable" => Ok(FilesetExpression::Executable),
+                name => Err(FilesetParseError::with_span(
+                    FilesetParseErrorKind::NoSuchFunction {
+                        name: name.to_owned(),
+                        candidates: revset::collect_similar(name, FUNCTION_NAMES),
+                    },
+                    name_pair.as_span(),
+                )),
+            }
+        }
+        Rule::string_pattern => {
+            let span = first.as_span();
+            let mut inner = first.into_inner();
+            let kind_pair = inner.next().unwrap();
+            let _op = inner.next().unwrap();
+            let value = parse_string(inner.next().unwrap());
+            parse_file_pattern(kind_pair.as_str(), &value, span, context)
+        }
+        Rule::identifier | Rule::literal_string => {
+            let span = first.as_span();
+            let path = context.parse_path(&parse_string(first), span)?;
+            Ok(FilesetExpression::prefix_path(path))
+        }
+        r => panic!("unexpected primary rule {r:?}"),
+    }
+}
+
+fn parse_string(pair: Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::identifier => pair.as_str().to_owned(),
+        // TODO: Add support for \-escape syntax
+        Rule::literal_string => pair
+            .as_str()
+            .strip_prefix('"')
+            .unwrap()
+            .strip_suffix('"')
+            .unwrap()
+            .to_owned(),
+        r => panic!("unexpected string rule {r:?}"),
+    }
+}
+
+fn parse_file_pattern(
+    kind: &str,
+    value: &str,
+    span: pest::Span<'_>,
+    context: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    match kind {
+        "cwd" => Ok(FilesetExpression::prefix_path(
+            context.parse_path(value, span)?,
+        )),
+        "file" => Ok(FilesetExpression::file_path(
+            context.parse_path(value, span)?,
+        )),
+        "root" => Ok(FilesetExpression::prefix_path(
+            context.parse_root_path(value, span)?,
+        )),
+        "glob" => {
+            let path = context.parse_path(value, span)?;
+            let (dir, pattern) = split_glob_path(&path);
+            let pattern = glob::Pattern::new(&pattern).map_err(|err| {
+                FilesetParseError::with_span(
+                    FilesetParseErrorKind::GlobPatternError(err.to_string()),
+                    span,
+                )
+            })?;
+            Ok(FilesetExpression::Pattern(FilePattern::FileGlob {
+                dir,
+                pattern,
+            }))
+        }
+        _ => Err(FilesetParseError::with_span(
+            FilesetParseErrorKind::NoSuchPatternKind(kind.to_owned()),
+            span,
+        )),
+    }
+}
+
+/// Splits the `path` into the leading directories without glob wildcards, and
+/// the pattern for the rest.
+fn split_glob_path(path: &RepoPath) -> (RepoPathBuf, String) {
+    let names = path.components().map(|name| name.as_str()).collect_vec();
+    let dir_len = names[..names.len().saturating_sub(1)]
+        .iter()
+        .take_while(|name| !name.contains(['*', '?', '[']))
+        .count();
+    let dir = RepoPathBuf::from_internal_string(names[..dir_len].join("/"));
+    (dir, names[dir_len..].join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_in(text: &str, cwd: &str) -> Result<FilesetExpression, FilesetParseErrorKind> {
+        let context = FilesetParseContext {
+            cwd: &Path::new("/ws").join(cwd),
+            workspace_root: Path::new("/ws"),
+        };
+        parse(text, &context).map_err(|err| err.kind)
+    }
+
+    fn repo_path_buf(value: &str) -> RepoPathBuf {
+        RepoPathBuf::from_internal_string(value)
+    }
+
+    #[test]
+    fn test_parse_paths() {
+        assert_eq!(
+            parse_in("foo/bar.txt", "").unwrap(),
+            FilesetExpression::prefix_path(repo_path_buf("foo/bar.txt"))
+        );
+        assert_eq!(
+            parse_in("../bar", "sub/dir").unwrap(),
+            FilesetExpression::prefix_path(repo_path_buf("sub/bar"))
+        );
+        assert_eq!(
+            parse_in(r#""with space""#, "").unwrap(),
+            FilesetExpression::prefix_path(repo_path_buf("with space"))
+        );
+        // Not a valid expression, but a valid path
+        assert_eq!(
+            parse_in("my file.txt", "sub").unwrap(),
+            FilesetExpression::prefix_path(repo_path_buf("sub/my file.txt"))
+        );
+        assert_eq!(
+            parse_in("file:foo", "sub").unwrap(),
+            FilesetExpression::file_path(repo_path_buf("sub/foo"))
+        );
+        assert_eq!(
+            parse_in(r#"root:"foo""#, "sub").unwrap(),
+            FilesetExpression::prefix_path(repo_path_buf("foo"))
+        );
+        assert!(matches!(
+            parse_in("bad:foo", ""),
+            Err(FilesetParseErrorKind::NoSuchPatternKind(_))
+        ));
+        assert!(matches!(
+            parse_in("../..", "sub"),
+            Err(FilesetParseErrorKind::FsPathParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_globs() {
+        assert_eq!(
+            parse_in("glob:*.rs", "src").unwrap(),
+            FilesetExpression::Pattern(FilePattern::FileGlob {
+                dir: repo_path_buf("src"),
+                pattern: glob::Pattern::new("*.rs").unwrap(),
+            })
+        );
+        assert_eq!(
+            parse_in(r#"glob:"a/**/b?/*.txt""#, "").unwrap(),
+            FilesetExpression::Pattern(FilePattern::FileGlob {
+                dir: repo_path_buf("a"),
+                pattern: glob::Pattern::new("**/b?/*.txt").unwrap(),
+            })
+        );
+        assert_eq!(
+            parse_in("glob:a/b", "").unwrap(),
+            FilesetExpression::Pattern(FilePattern::FileGlob {
+                dir: repo_path_buf("a"),
+                pattern: glob::Pattern::new("b").unwrap(),
+            })
+        );
+        assert!(matches!(
+            parse_in(r#"glob:"[a""#, ""),
+            Err(FilesetParseErrorKind::GlobPatternError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let foo = || FilesetExpression::prefix_path(repo_path_buf("foo"));
+        let bar = || FilesetExpression::prefix_path(repo_path_buf("bar"));
+        let baz = || FilesetExpression::prefix_path(repo_path_buf("baz"));
+        assert_eq!(
+            parse_in("foo | bar & baz", "").unwrap(),
+            FilesetExpression::union_all(vec![foo(), bar().intersection(baz())])
+        );
+        assert_eq!(
+            parse_in("(foo|bar)~baz", "").unwrap(),
+            FilesetExpression::union_all(vec![foo(), bar()]).difference(baz())
+        );
+        assert_eq!(
+            parse_in("~foo & all()", "").unwrap(),
+            FilesetExpression::all()
+                .difference(foo())
+                .intersection(FilesetExpression::all())
+        );
+        assert_eq!(
+            parse_in("executable() ~ none()", "").unwrap(),
+            FilesetExpression::Executable.difference(FilesetExpression::none())
+        );
+        assert!(matches!(
+            parse_in("exec()", ""),
+            Err(FilesetParseErrorKind::NoSuchFunction { .. })
+        ));
+        assert!(matches!(
+            parse_in("foo &", ""),
+            Err(FilesetParseErrorKind::SyntaxError)
+        ));
+    }
+
+    #[test]
+    fn test_to_matcher() {
+        let matcher = parse_in("glob:*.rs ~ glob:test_* | file:README", "src")
+            .unwrap()
+            .to_matcher();
+        assert!(matcher.matches(RepoPath::from_internal_string("src/lib.rs")));
+        assert!(!matcher.matches(RepoPath::from_internal_string("src/test_lib.rs")));
+        assert!(!matcher.matches(RepoPath::from_internal_string("src/sub/lib.rs")));
+        assert!(matcher.matches(RepoPath::from_internal_string("src/README")));
+        assert!(!matcher.matches(RepoPath::from_internal_string("src/README/foo")));
+
+        let matcher = parse_in("~foo", "").unwrap().to_matcher();
+        assert!(!matcher.matches(RepoPath::from_internal_string("foo/bar")));
+        assert!(matcher.matches(RepoPath::from_internal_string("bar")));
+
+        let expression = parse_in("foo | file:bar", "").unwrap();
+        assert_eq!(
+            expression.as_explicit_paths(),
+            Some(vec![
+                RepoPath::from_internal_string("foo"),
+                RepoPath::from_internal_string("bar")
+            ])
+        );
+        assert_eq!(parse_in("glob:*", "").unwrap().as_explicit_paths(), None);
+    }
+}
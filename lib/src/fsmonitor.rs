@@ -59,14 +59,15 @@ impl FromStr for FsmonitorKind {
 #[cfg(feature = "watchman")]
 pub mod watchman {
     use std::path::{Path, PathBuf};
+    use std::time::Duration;
 
     use itertools::Itertools;
     use thiserror::Error;
     use tracing::{info, instrument};
-    use watchman_client::expr;
     use watchman_client::prelude::{
-        Clock as InnerClock, ClockSpec, NameOnly, QueryRequestCommon, QueryResult,
+        Clock as InnerClock, ClockSpec, NameOnly, QueryRequestCommon, QueryResult, SubscribeRequest,
     };
+    use watchman_client::{expr, Subscription, SubscriptionData};
 
     /// Represents an instance in time from the perspective of the filesystem
     /// monitor.
@@ -130,6 +131,36 @@ pub mod watchman {
 
         #[error("Failed to query Watchman")]
         WatchmanQueryError(#[source] watchman_client::Error),
+
+        #[error("Failed to subscribe to Watchman")]
+        WatchmanSubscribeError(#[source] watchman_client::Error),
+
+        #[error("Watchman canceled the subscription")]
+        SubscriptionCanceled,
+
+        #[error("Failed to start the async runtime")]
+        RuntimeError(#[source] std::io::Error),
+    }
+
+    /// Matches the files outside of the `.git` and `.jj` directories.
+    fn working_copy_files_expression() -> expr::Expr {
+        let exclude_dirs = [Path::new(".git"), Path::new(".jj")];
+        let excludes = itertools::chain(
+            // the directories themselves
+            [expr::Expr::Name(expr::NameTerm {
+                paths: exclude_dirs.iter().map(|&name| name.to_owned()).collect(),
+                wholename: true,
+            })],
+            // and all files under the directories
+            exclude_dirs.iter().map(|&name| {
+                expr::Expr::DirName(expr::DirNameTerm {
+                    path: name.to_owned(),
+                    depth: None,
+                })
+            }),
+        )
+        .collect();
+        expr::Expr::Not(Box::new(expr::Expr::Any(excludes)))
     }
 
     /// Handle to the underlying Watchman instance.
@@ -175,23 +206,7 @@ pub mod watchman {
         ) -> Result<(Clock, Option<Vec<PathBuf>>), Error> {
             // TODO: might be better to specify query options by caller, but we
             // shouldn't expose the underlying watchman API too much.
-            let exclude_dirs = [Path::new(".git"), Path::new(".jj")];
-            let excludes = itertools::chain(
-                // the directories themselves
-                [expr::Expr::Name(expr::NameTerm {
-                    paths: exclude_dirs.iter().map(|&name| name.to_owned()).collect(),
-                    wholename: true,
-                })],
-                // and all files under the directories
-                exclude_dirs.iter().map(|&name| {
-                    expr::Expr::DirName(expr::DirNameTerm {
-                        path: name.to_owned(),
-                        depth: None,
-                    })
-                }),
-            )
-            .collect();
-            let expression = expr::Expr::Not(Box::new(expr::Expr::Any(excludes)));
+            let expression = working_copy_files_expression();
 
             info!("Querying Watchman for changed files...");
             let QueryResult {
@@ -234,4 +249,72 @@ pub mod watchman {
             }
         }
     }
+
+    /// Waits for files in the working copy to change. Unlike `Fsmonitor`, this
+    /// keeps a subscription to Watchman open for as long as it lives.
+    pub struct FileWatcher {
+        runtime: tokio::runtime::Runtime,
+        _client: watchman_client::Client,
+        subscription: Subscription<NameOnly>,
+    }
+
+    impl FileWatcher {
+        /// Subscribes to changes to the files in the working copy, except for
+        /// the ones in the `.git` and `.jj` directories.
+        #[instrument]
+        pub fn new(working_copy_path: &Path) -> Result<Self, Error> {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(Error::RuntimeError)?;
+            let (client, subscription) = runtime.block_on(async {
+                let Fsmonitor {
+                    client,
+                    resolved_root,
+                } = Fsmonitor::init(working_copy_path).await?;
+                let (subscription, _) = client
+                    .subscribe::<NameOnly>(
+                        &resolved_root,
+                        SubscribeRequest {
+                            expression: Some(working_copy_files_expression()),
+                            empty_on_fresh_instance: true,
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                    .map_err(Error::WatchmanSubscribeError)?;
+                Ok::<_, Error>((client, subscription))
+            })?;
+            Ok(FileWatcher {
+                runtime,
+                _client: client,
+                subscription,
+            })
+        }
+
+        /// Waits until files change or the `timeout` elapses. Returns whether
+        /// any files changed.
+        pub fn wait_for_changes(&mut self, timeout: Duration) -> Result<bool, Error> {
+            let subscription = &mut self.subscription;
+            self.runtime.block_on(async move {
+                let deadline = tokio::time::Instant::now() + timeout;
+                loop {
+                    let Ok(data) = tokio::time::timeout_at(deadline, subscription.next()).await
+                    else {
+                        return Ok(false);
+                    };
+                    match data.map_err(Error::WatchmanQueryError)? {
+                        SubscriptionData::FilesChanged(result)
+                            if result.files.as_ref().is_some_and(|files| !files.is_empty()) =>
+                        {
+                            return Ok(true);
+                        }
+                        SubscriptionData::Canceled => return Err(Error::SubscriptionCanceled),
+                        // No files changed, or other Watchman clients changed states
+                        _ => {}
+                    }
+                }
+            })
+        }
+    }
 }
@@ -0,0 +1,240 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Flushing written files to storage, so that they survive a crash of the
+//! machine.
+//!
+//! By default, nothing is flushed, and files are only as durable as the
+//! operating system makes them. Which files are flushed is set by an `Fsync`
+//! that the stores writing files get from the `UserSettings`.
+
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use tracing::instrument;
+
+/// The kinds of files that can be flushed when written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FsyncTarget {
+    /// Objects written by the local backend, and the tables of metadata that
+    /// the backends keep next to the objects.
+    Objects,
+    /// Operations, views, and operation heads.
+    Operations,
+    /// The state of the working copy.
+    WorkingCopy,
+}
+
+impl FromStr for FsyncTarget {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "objects" => Ok(Self::Objects),
+            "operations" => Ok(Self::Operations),
+            "working-copy" => Ok(Self::WorkingCopy),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown fsync target: {other}"
+            ))),
+        }
+    }
+}
+
+/// When the written files are flushed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FsyncMethod {
+    /// Flush each file as soon as it's written.
+    #[default]
+    Immediate,
+    /// Flush the written files together, before the operation that refers to
+    /// them is published, and before the working-copy state is saved. This is
+    /// faster when many files are written.
+    Batch,
+}
+
+impl FromStr for FsyncMethod {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(Self::Immediate),
+            "batch" => Ok(Self::Batch),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown fsync method: {other}"
+            ))),
+        }
+    }
+}
+
+/// Which files are flushed, and when.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FsyncConfig {
+    /// Whether to flush `FsyncTarget::Objects`.
+    pub objects: bool,
+    /// Whether to flush `FsyncTarget::Operations`.
+    pub operations: bool,
+    /// Whether to flush `FsyncTarget::WorkingCopy`.
+    pub working_copy: bool,
+    /// When to flush the files.
+    pub method: FsyncMethod,
+}
+
+impl FsyncConfig {
+    /// Flushes all kinds of files.
+    pub fn all(method: FsyncMethod) -> Self {
+        FsyncConfig {
+            objects: true,
+            operations: true,
+            working_copy: true,
+            method,
+        }
+    }
+
+    fn is_enabled(&self, target: FsyncTarget) -> bool {
+        match target {
+            FsyncTarget::Objects => self.objects,
+            FsyncTarget::Operations => self.operations,
+            FsyncTarget::WorkingCopy => self.working_copy,
+        }
+    }
+}
+
+/// Flushes written files as set by a `FsyncConfig`. Clones share the files
+/// that are pending in batch mode, so the files written by the different stores
+/// of a repo are flushed together.
+#[derive(Clone, Debug, Default)]
+pub struct Fsync {
+    config: FsyncConfig,
+    /// Files and directories written in batch mode that haven't been flushed.
+    pending_paths: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+impl Fsync {
+    /// Creates an `Fsync` that doesn't share pending files with any other.
+    pub fn new(config: FsyncConfig) -> Self {
+        Fsync {
+            config,
+            pending_paths: Default::default(),
+        }
+    }
+
+    /// Which files are flushed, and when.
+    pub fn config(&self) -> &FsyncConfig {
+        &self.config
+    }
+
+    /// Flushes the file at `path`, which was just written, and the directory
+    /// containing it, if files of the `target` kind should be flushed. In
+    /// batch mode, they're only flushed by the next `sync_pending()`.
+    pub fn sync_written_file(&self, target: FsyncTarget, path: &Path) -> io::Result<()> {
+        if !self.config.is_enabled(target) {
+            return Ok(());
+        }
+        let paths = [Some(path), path.parent()];
+        match self.config.method {
+            FsyncMethod::Immediate => {
+                for path in paths.into_iter().flatten() {
+                    sync_path(path)?;
+                }
+            }
+            FsyncMethod::Batch => {
+                let mut pending_paths = self.pending_paths.lock().unwrap();
+                for path in paths.into_iter().flatten() {
+                    pending_paths.insert(path.to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the files written in batch mode that haven't been flushed yet.
+    #[instrument(skip_all)]
+    pub fn sync_pending(&self) -> io::Result<()> {
+        let pending_paths = std::mem::take(&mut *self.pending_paths.lock().unwrap());
+        for path in &pending_paths {
+            match sync_path(path) {
+                Ok(()) => {}
+                // The file may have been replaced or removed since then
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sync_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        // Directories can't be opened or flushed on Windows. Renames are
+        // flushed with the file there.
+        if cfg!(unix) {
+            File::open(path)?.sync_all()?;
+        }
+        Ok(())
+    } else {
+        // Flushing needs write access on Windows.
+        let file = OpenOptions::new()
+            .read(true)
+            .write(cfg!(windows))
+            .open(path)?;
+        file.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fsync_target() {
+        assert_eq!(
+            "objects".parse::<FsyncTarget>().unwrap(),
+            FsyncTarget::Objects
+        );
+        assert_eq!(
+            "working-copy".parse::<FsyncTarget>().unwrap(),
+            FsyncTarget::WorkingCopy
+        );
+        assert!("index".parse::<FsyncTarget>().is_err());
+    }
+
+    #[test]
+    fn sync_path_file_and_dir() {
+        let temp_dir = testutils::new_temp_dir();
+        let file_path = temp_dir.path().join("file");
+        std::fs::write(&file_path, "contents").unwrap();
+        sync_path(&file_path).unwrap();
+        sync_path(temp_dir.path()).unwrap();
+        assert!(sync_path(&temp_dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn sync_batch_shared_by_clones() {
+        let temp_dir = testutils::new_temp_dir();
+        let file_path = temp_dir.path().join("file");
+        std::fs::write(&file_path, "contents").unwrap();
+        let fsync = Fsync::new(FsyncConfig::all(FsyncMethod::Batch));
+        let other = fsync.clone();
+        fsync
+            .sync_written_file(FsyncTarget::Objects, &file_path)
+            .unwrap();
+        assert_eq!(other.pending_paths.lock().unwrap().len(), 2);
+        other.sync_pending().unwrap();
+        assert!(fsync.pending_paths.lock().unwrap().is_empty());
+    }
+}
@@ -15,10 +15,13 @@
 #![allow(missing_docs)]
 
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{hash_map, BTreeMap, HashMap, HashSet, VecDeque};
 use std::default::Default;
 use std::io::Read;
+use std::num::NonZeroU32;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::{fmt, iter, str};
 
 use git2::Oid;
@@ -26,15 +29,21 @@ use itertools::Itertools;
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
-use crate::backend::{BackendError, CommitId};
+use crate::backend::{BackendError, CommitId, MergedTreeId};
 use crate::commit::Commit;
+use crate::content_hash::blake2b_hash;
+use crate::dag_walk;
 use crate::git_backend::GitBackend;
 use crate::object_id::ObjectId;
-use crate::op_store::{RefTarget, RefTargetOptionExt, RemoteRef, RemoteRefState};
+use crate::op_store::{
+    OpStore, OpStoreError, OperationId, RefTarget, RefTargetOptionExt, RemoteRef, RemoteRefState,
+    ViewId,
+};
 use crate::refs::BranchPushUpdate;
-use crate::repo::{MutableRepo, Repo};
+use crate::repo::{MutableRepo, ReadonlyRepo, Repo};
 use crate::revset::RevsetExpression;
 use crate::settings::GitSettings;
+use crate::simple_op_store::SimpleOpStore;
 use crate::store::Store;
 use crate::str_util::StringPattern;
 use crate::view::View;
@@ -1088,6 +1097,20 @@ pub enum GitFetchError {
     InternalGitError(#[from] git2::Error),
 }
 
+/// How much history `fetch()` downloads.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FetchDepth {
+    /// All of the history, except that a shallow repo stays shallow and only
+    /// gets the history above its shallow boundary.
+    #[default]
+    Unlimited,
+    /// Only this many commits from the tips of the fetched branches.
+    Depth(NonZeroU32),
+    /// This many more commits below the current shallow boundary. Same as
+    /// `Unlimited` if the repo isn't shallow.
+    Deepen(NonZeroU32),
+}
+
 /// Describes successful `fetch()` result.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GitFetchStats {
@@ -1095,6 +1118,10 @@ pub struct GitFetchStats {
     pub default_branch: Option<String>,
     /// Changes made by the import.
     pub import_stats: GitImportStats,
+    /// Commits that were at the shallow boundary before the fetch, and whose
+    /// parents have now been fetched. The index still records them as
+    /// children of the root commit, so it should be rebuilt.
+    pub unshallowed_commits: Vec<CommitId>,
 }
 
 #[tracing::instrument(skip(mut_repo, git_repo, callbacks))]
@@ -1103,9 +1130,11 @@ pub fn fetch(
     git_repo: &git2::Repository,
     remote_name: &str,
     branch_names: &[StringPattern],
+    depth: FetchDepth,
     callbacks: RemoteCallbacks<'_>,
     git_settings: &GitSettings,
 ) -> Result<GitFetchStats, GitFetchError> {
+    let git_backend = get_git_backend(mut_repo.store()).ok_or(GitImportError::UnexpectedBackend)?;
     // Perform a `git fetch` on the local git repo, updating the remote-tracking
     // branches in the git repo.
     let mut remote = git_repo.find_remote(remote_name).map_err(|err| {
@@ -1121,6 +1150,22 @@ pub fn fetch(
     fetch_options.proxy_options(proxy_options);
     let callbacks = callbacks.into_git();
     fetch_options.remote_callbacks(callbacks);
+    let old_shallow_commits = git_backend
+        .shallow_commits()
+        .map_err(GitImportError::InternalBackend)?;
+    let depth = match depth {
+        FetchDepth::Unlimited => None,
+        FetchDepth::Depth(depth) => Some(depth.get()),
+        // The protocol can deepen relative to the current boundary, but
+        // libgit2 doesn't support that, so ask for the equivalent depth.
+        FetchDepth::Deepen(deepen) => {
+            shallow_history_depth(git_repo, remote_name, &old_shallow_commits)?
+                .map(|depth| depth.saturating_add(deepen.get()))
+        }
+    };
+    if let Some(depth) = depth {
+        fetch_options.depth(i32::try_from(depth).unwrap_or(i32::MAX));
+    }
     // At this point, we are only updating Git's remote tracking branches, not the
     // local branches.
     let refspecs: Vec<_> = branch_names
@@ -1140,6 +1185,7 @@ pub fn fetch(
             import_stats: GitImportStats {
                 abandoned_commits: vec![],
             },
+            unshallowed_commits: vec![],
         };
         return Ok(stats);
     }
@@ -1165,6 +1211,30 @@ pub fn fetch(
     tracing::debug!("remote.disconnect");
     remote.disconnect()?;
 
+    // The imported ancestors of the commits at the old shallow boundary end
+    // there, so the newly fetched history below them has to be imported
+    // explicitly.
+    let new_shallow_commits = git_backend
+        .shallow_commits()
+        .map_err(GitImportError::InternalBackend)?;
+    let unshallowed_commits = old_shallow_commits
+        .difference(&new_shallow_commits)
+        .cloned()
+        .sorted()
+        .collect_vec();
+    let mut unshallowed_parent_ids = vec![];
+    for id in &unshallowed_commits {
+        let commit = git_repo.find_commit(Oid::from_bytes(id.as_bytes()).unwrap())?;
+        unshallowed_parent_ids.extend(
+            commit
+                .parent_ids()
+                .map(|oid| CommitId::from_bytes(oid.as_bytes())),
+        );
+    }
+    git_backend
+        .import_head_commits(&unshallowed_parent_ids)
+        .map_err(GitImportError::InternalBackend)?;
+
     // Import the remote-tracking branches into the jj repo and update jj's
     // local branches. We also import local tags since remote tags should have
     // been merged by Git.
@@ -1177,10 +1247,149 @@ pub fn fetch(
     let stats = GitFetchStats {
         default_branch,
         import_stats,
+        unshallowed_commits,
     };
     Ok(stats)
 }
 
+/// Returns the length of the longest path from the remote's tracking branches
+/// to the shallow boundary, counting both ends, or `None` if the history of
+/// the branches doesn't reach the boundary.
+fn shallow_history_depth(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    shallow_commits: &HashSet<CommitId>,
+) -> Result<Option<u32>, git2::Error> {
+    let mut depths = HashMap::new();
+    let mut queue = VecDeque::new();
+    for reference in git_repo.references_glob(&format!("refs/remotes/{remote_name}/*"))? {
+        if let Ok(commit) = reference?.peel_to_commit() {
+            if depths.insert(commit.id(), 1).is_none() {
+                queue.push_back(commit.id());
+            }
+        }
+    }
+    // Breadth-first, so each commit is reached by its shortest path first, as
+    // the remote would count it.
+    let mut max_depth = None;
+    while let Some(oid) = queue.pop_front() {
+        let depth = depths[&oid];
+        if shallow_commits.contains(&CommitId::from_bytes(oid.as_bytes())) {
+            max_depth = max_depth.max(Some(depth));
+            continue;
+        }
+        for parent_id in git_repo.find_commit(oid)?.parent_ids() {
+            if let hash_map::Entry::Vacant(entry) = depths.entry(parent_id) {
+                entry.insert(depth + 1);
+                queue.push_back(parent_id);
+            }
+        }
+    }
+    Ok(max_depth)
+}
+
+/// Lists the branches and tags on the remote, with the commits they point to.
+/// Annotated tags are peeled to the commits they tag.
+#[tracing::instrument(skip(git_repo, callbacks))]
+pub fn list_remote_refs(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    callbacks: RemoteCallbacks<'_>,
+) -> Result<BTreeMap<String, CommitId>, GitFetchError> {
+    let mut remote = git_repo.find_remote(remote_name).map_err(|err| {
+        if is_remote_not_found_err(&err) {
+            GitFetchError::NoSuchRemote(remote_name.to_string())
+        } else {
+            GitFetchError::InternalGitError(err)
+        }
+    })?;
+    let mut proxy_options = git2::ProxyOptions::new();
+    proxy_options.auto();
+    let connection = remote.connect_auth(
+        git2::Direction::Fetch,
+        Some(callbacks.into_git()),
+        Some(proxy_options),
+    )?;
+    let mut refs = BTreeMap::new();
+    for head in connection.list()? {
+        let id = CommitId::from_bytes(head.oid().as_bytes());
+        if let Some(tag_name) = head.name().strip_suffix("^{}") {
+            refs.insert(tag_name.to_owned(), id);
+        } else if head.name().starts_with("refs/heads/") || head.name().starts_with("refs/tags/") {
+            refs.entry(head.name().to_owned()).or_insert(id);
+        }
+    }
+    Ok(refs)
+}
+
+/// Makes the branches and tags in the repo exactly match the remote's.
+///
+/// All branches and tags are fetched, and the remote-tracking branches and
+/// tags that no longer exist on the remote are deleted. Then the local
+/// branches are set to the remote's branches and track them, and the other
+/// local branches are deleted.
+#[tracing::instrument(skip(mut_repo, git_repo, callbacks))]
+pub fn mirror_from_remote(
+    mut_repo: &mut MutableRepo,
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    callbacks: RemoteCallbacks<'_>,
+    git_settings: &GitSettings,
+) -> Result<GitImportStats, GitFetchError> {
+    let mut remote = git_repo.find_remote(remote_name).map_err(|err| {
+        if is_remote_not_found_err(&err) {
+            GitFetchError::NoSuchRemote(remote_name.to_string())
+        } else {
+            GitFetchError::InternalGitError(err)
+        }
+    })?;
+    let mut fetch_options = git2::FetchOptions::new();
+    let mut proxy_options = git2::ProxyOptions::new();
+    proxy_options.auto();
+    fetch_options.proxy_options(proxy_options);
+    fetch_options.remote_callbacks(callbacks.into_git());
+    let refspecs = [
+        format!("+refs/heads/*:refs/remotes/{remote_name}/*"),
+        "+refs/tags/*:refs/tags/*".to_owned(),
+    ];
+    tracing::debug!("remote.download");
+    remote.download(&refspecs, Some(&mut fetch_options))?;
+    tracing::debug!("remote.prune");
+    remote.prune(None)?;
+    tracing::debug!("remote.update_tips");
+    remote.update_tips(None, false, git2::AutotagOption::None, None)?;
+    tracing::debug!("remote.disconnect");
+    remote.disconnect()?;
+
+    tracing::debug!("import_refs");
+    let import_stats = import_some_refs(mut_repo, git_settings, |ref_name| {
+        to_remote_branch(ref_name, remote_name).is_some() || matches!(ref_name, RefName::Tag(_))
+    })?;
+    let remote_targets: BTreeMap<String, RefTarget> = mut_repo
+        .view()
+        .remote_branches(remote_name)
+        .map(|(name, remote_ref)| (name.to_owned(), remote_ref.target.clone()))
+        .collect();
+    let stale_branches = mut_repo
+        .view()
+        .local_branches()
+        .map(|(name, _)| name.to_owned())
+        .filter(|name| !remote_targets.contains_key(name))
+        .collect_vec();
+    for name in stale_branches {
+        mut_repo.set_local_branch_target(&name, RefTarget::absent());
+    }
+    for (name, target) in remote_targets {
+        mut_repo.set_local_branch_target(&name, target.clone());
+        let remote_ref = RemoteRef {
+            target,
+            state: RemoteRefState::Tracking,
+        };
+        mut_repo.set_remote_branch(&name, remote_name, remote_ref);
+    }
+    Ok(import_stats)
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum GitPushError {
     #[error("No git remote named '{0}'")]
@@ -1206,6 +1415,30 @@ pub struct GitBranchPushTargets {
     pub force_pushed_branches: HashSet<String>,
 }
 
+impl GitBranchPushTargets {
+    /// Returns the updates of the Git refs on the remote that push the
+    /// branches.
+    pub fn ref_updates(&self) -> Vec<GitRefUpdate> {
+        self.branch_updates
+            .iter()
+            .map(|(branch_name, update)| GitRefUpdate {
+                qualified_name: format!("refs/heads/{branch_name}"),
+                force: self.force_pushed_branches.contains(branch_name),
+                new_target: update.new_target.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Describes successful `push_updates()` and `push_branches()` results.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GitPushStats {
+    /// Refs that the remote reported as updated, in the order it reported
+    /// them.
+    pub pushed_refs: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
 pub struct GitRefUpdate {
     pub qualified_name: String,
     // TODO: We want this to be a `current_target: Option<CommitId>` for the expected current
@@ -1222,21 +1455,22 @@ pub fn push_branches(
     remote_name: &str,
     targets: &GitBranchPushTargets,
     callbacks: RemoteCallbacks<'_>,
-) -> Result<(), GitPushError> {
-    let ref_updates = targets
-        .branch_updates
-        .iter()
-        .map(|(branch_name, update)| GitRefUpdate {
-            qualified_name: format!("refs/heads/{branch_name}"),
-            force: targets.force_pushed_branches.contains(branch_name),
-            new_target: update.new_target.clone(),
-        })
-        .collect_vec();
-    push_updates(git_repo, remote_name, &ref_updates, callbacks)?;
-
+) -> Result<GitPushStats, GitPushError> {
+    let stats = push_updates(git_repo, remote_name, &targets.ref_updates(), callbacks)?;
     // TODO: add support for partially pushed refs? we could update the view
     // excluding rejected refs, but the transaction would be aborted anyway
     // if we returned an Err.
+    record_pushed_branches(mut_repo, remote_name, targets);
+    Ok(stats)
+}
+
+/// Updates the remote-tracking branches in the view after the `targets` have
+/// been pushed with [`push_updates()`].
+pub fn record_pushed_branches(
+    mut_repo: &mut MutableRepo,
+    remote_name: &str,
+    targets: &GitBranchPushTargets,
+) {
     for (branch_name, update) in &targets.branch_updates {
         let git_ref_name = format!("refs/remotes/{remote_name}/{branch_name}");
         let new_remote_ref = RemoteRef {
@@ -1246,8 +1480,6 @@ pub fn push_branches(
         mut_repo.set_git_ref_target(&git_ref_name, new_remote_ref.target.clone());
         mut_repo.set_remote_branch(branch_name, remote_name, new_remote_ref);
     }
-
-    Ok(())
 }
 
 /// Pushes the specified Git refs without updating the repo view.
@@ -1256,7 +1488,11 @@ pub fn push_updates(
     remote_name: &str,
     updates: &[GitRefUpdate],
     callbacks: RemoteCallbacks<'_>,
-) -> Result<(), GitPushError> {
+) -> Result<GitPushStats, GitPushError> {
+    // The remote name is part of the temporary ref names below.
+    if !git2::Remote::is_valid_name(remote_name) {
+        return Err(GitPushError::NoSuchRemote(remote_name.to_owned()));
+    }
     let mut temp_refs = vec![];
     let mut qualified_remote_refs = vec![];
     let mut refspecs = vec![];
@@ -1264,7 +1500,8 @@ pub fn push_updates(
         qualified_remote_refs.push(update.qualified_name.as_str());
         if let Some(new_target) = &update.new_target {
             // Create a temporary ref to work around https://github.com/libgit2/libgit2/issues/3178
-            let temp_ref_name = format!("refs/jj/git-push/{}", new_target.hex());
+            // It's per remote so that pushes to several remotes can run at once.
+            let temp_ref_name = format!("refs/jj/git-push/{remote_name}/{}", new_target.hex());
             temp_refs.push(git_repo.reference(
                 &temp_ref_name,
                 git2::Oid::from_bytes(new_target.as_bytes()).unwrap(),
@@ -1309,7 +1546,7 @@ fn push_refs(
     qualified_remote_refs: &[&str],
     refspecs: &[String],
     callbacks: RemoteCallbacks<'_>,
-) -> Result<(), GitPushError> {
+) -> Result<GitPushStats, GitPushError> {
     if remote_name == REMOTE_NAME_FOR_LOCAL_GIT_REPO {
         return Err(GitPushError::RemoteReservedForLocalGitRepo);
     }
@@ -1321,6 +1558,7 @@ fn push_refs(
         }
     })?;
     let mut remaining_remote_refs: HashSet<_> = qualified_remote_refs.iter().copied().collect();
+    let mut pushed_refs = vec![];
     let mut push_options = git2::PushOptions::new();
     let mut proxy_options = git2::ProxyOptions::new();
     proxy_options.auto();
@@ -1330,6 +1568,7 @@ fn push_refs(
         // The status is Some if the ref update was rejected
         if status.is_none() {
             remaining_remote_refs.remove(refname);
+            pushed_refs.push(refname.to_owned());
         }
         Ok(())
     });
@@ -1344,7 +1583,7 @@ fn push_refs(
         })?;
     drop(push_options);
     if remaining_remote_refs.is_empty() {
-        Ok(())
+        Ok(GitPushStats { pushed_refs })
     } else {
         Err(GitPushError::RefUpdateRejected(
             remaining_remote_refs
@@ -1356,6 +1595,382 @@ fn push_refs(
     }
 }
 
+/// Ref through which the operation log is synchronized with a remote.
+pub const OP_LOG_SYNC_REF: &str = "refs/jj/sync/op-log";
+
+/// Ref that tracks the synchronized operation log of the remote.
+fn remote_op_log_sync_ref(remote_name: &str) -> String {
+    format!("refs/jj/remote-sync/{remote_name}/op-log")
+}
+
+#[derive(Error, Debug)]
+pub enum GitOpSyncError {
+    #[error("No git remote named '{0}'")]
+    NoSuchRemote(String),
+    #[error("The operation log can only be synchronized in repos with the simple op store")]
+    UnsupportedOpStore,
+    #[error("The repo is not backed by a Git repo")]
+    UnexpectedBackend,
+    #[error("Invalid {kind} {name} in the synchronized operation log")]
+    InvalidObject { kind: &'static str, name: String },
+    #[error(transparent)]
+    Push(#[from] GitPushError),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    OpStore(#[from] OpStoreError),
+    #[error("Failed to write to the operation log")]
+    Io(#[from] std::io::Error),
+    #[error("Unexpected git error when synchronizing the operation log")]
+    InternalGitError(#[from] git2::Error),
+}
+
+fn simple_op_store(repo: &ReadonlyRepo) -> Result<&SimpleOpStore, GitOpSyncError> {
+    repo.op_store()
+        .as_any()
+        .downcast_ref::<SimpleOpStore>()
+        .ok_or(GitOpSyncError::UnsupportedOpStore)
+}
+
+/// Fetches the operation log that was synchronized to the remote, and adds
+/// the operations, and the metadata of the commits they refer to, to the
+/// repo. The remote's operation heads become operation heads of the repo, so
+/// the next time the repo is loaded, they're merged with the local ones.
+///
+/// Returns the number of operations that were added.
+#[tracing::instrument(skip(repo, git_repo, callbacks))]
+pub fn fetch_op_log(
+    repo: &ReadonlyRepo,
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    callbacks: RemoteCallbacks<'_>,
+) -> Result<usize, GitOpSyncError> {
+    let op_store = simple_op_store(repo)?;
+    let git_backend = get_git_backend(repo.store()).ok_or(GitOpSyncError::UnexpectedBackend)?;
+    let mut remote = git_repo.find_remote(remote_name).map_err(|err| {
+        if is_remote_not_found_err(&err) {
+            GitOpSyncError::NoSuchRemote(remote_name.to_string())
+        } else {
+            GitOpSyncError::InternalGitError(err)
+        }
+    })?;
+    let tracking_ref = remote_op_log_sync_ref(remote_name);
+    let mut fetch_options = git2::FetchOptions::new();
+    let mut proxy_options = git2::ProxyOptions::new();
+    proxy_options.auto();
+    fetch_options.proxy_options(proxy_options);
+    fetch_options.remote_callbacks(callbacks.into_git());
+    // A glob refspec, so it's not an error if the remote has nothing synchronized
+    let refspec = format!("+refs/jj/sync/*:refs/jj/remote-sync/{remote_name}/*");
+    remote.fetch(&[refspec], Some(&mut fetch_options), None)?;
+    remote.disconnect()?;
+    let tree = match git_repo.find_reference(&tracking_ref) {
+        Ok(reference) => reference.peel_to_tree()?,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    // The commits' metadata must be imported before the commits are read
+    // through the views, or they would be assigned new change ids.
+    let extras: Vec<_> = read_sync_dir(git_repo, &tree, "extras")?
+        .into_iter()
+        .map(|(name, data)| {
+            let id = CommitId::try_from_hex(&name).map_err(|_| GitOpSyncError::InvalidObject {
+                kind: "commit",
+                name: name.clone(),
+            })?;
+            Ok((id, data))
+        })
+        .try_collect::<_, _, GitOpSyncError>()?;
+    git_backend.import_extra_metadata(extras)?;
+
+    for (name, data) in read_sync_dir(git_repo, &tree, "views")? {
+        let invalid = || GitOpSyncError::InvalidObject {
+            kind: "view",
+            name: name.clone(),
+        };
+        let id = ViewId::try_from_hex(&name).map_err(|_| invalid())?;
+        let path = op_store.view_path(&id);
+        if !path.exists() {
+            write_sync_object(&path, &data)?;
+            let valid = op_store
+                .read_view(&id)
+                .is_ok_and(|view| blake2b_hash(&view).as_slice() == id.as_bytes());
+            if !valid {
+                std::fs::remove_file(&path)?;
+                return Err(invalid());
+            }
+        }
+    }
+    let mut imported_count = 0;
+    for (name, data) in read_sync_dir(git_repo, &tree, "operations")? {
+        let invalid = || GitOpSyncError::InvalidObject {
+            kind: "operation",
+            name: name.clone(),
+        };
+        let id = OperationId::try_from_hex(&name).map_err(|_| invalid())?;
+        let path = op_store.operation_path(&id);
+        if !path.exists() {
+            write_sync_object(&path, &data)?;
+            let valid = op_store
+                .read_operation(&id)
+                .is_ok_and(|operation| blake2b_hash(&operation).as_slice() == id.as_bytes());
+            if !valid {
+                std::fs::remove_file(&path)?;
+                return Err(invalid());
+            }
+            imported_count += 1;
+        }
+    }
+
+    let local_heads: HashSet<_> = repo.op_heads_store().get_op_heads().into_iter().collect();
+    for name in read_sync_heads(git_repo, &tree)? {
+        let id = OperationId::try_from_hex(&name).map_err(|_| GitOpSyncError::InvalidObject {
+            kind: "operation",
+            name: name.clone(),
+        })?;
+        if !local_heads.contains(&id) {
+            repo.op_store().read_operation(&id)?;
+            repo.op_heads_store().update_op_heads(&[], &id);
+        }
+    }
+    Ok(imported_count)
+}
+
+/// Pushes the operation log, the metadata of the commits it refers to, and
+/// the commits themselves to the remote. The operation log of the remote
+/// should be fetched with `fetch_op_log()` first, or the push will be
+/// rejected.
+///
+/// All commits that are reachable from the operation log are pushed,
+/// including hidden ones, and stay reachable on the remote even after they
+/// are garbage-collected locally.
+///
+/// Returns the number of operations that the remote didn't have.
+#[tracing::instrument(skip(repo, git_repo, callbacks))]
+pub fn push_op_log(
+    repo: &ReadonlyRepo,
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    callbacks: RemoteCallbacks<'_>,
+) -> Result<usize, GitOpSyncError> {
+    let op_store = simple_op_store(repo)?;
+    let git_backend = get_git_backend(repo.store()).ok_or(GitOpSyncError::UnexpectedBackend)?;
+    let tracking_ref = remote_op_log_sync_ref(remote_name);
+    let remote_commit = match git_repo.find_reference(&tracking_ref) {
+        Ok(reference) => Some(reference.peel_to_commit()?),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let remote_operations: HashSet<String> = match &remote_commit {
+        Some(commit) => read_sync_dir(git_repo, &commit.tree()?, "operations")?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    // The operations reachable from the heads, and their views
+    let op_heads = repo.op_heads_store().get_op_heads();
+    let mut operations_builder = git_repo.treebuilder(None)?;
+    let mut views_builder = git_repo.treebuilder(None)?;
+    let mut exported_count = 0;
+    for op_id in dag_walk::dfs(
+        op_heads.iter().cloned(),
+        |id| id.clone(),
+        |id| {
+            repo.op_store()
+                .read_operation(id)
+                .map(|operation| operation.parents)
+                .unwrap_or_default()
+        },
+    ) {
+        if op_id == *repo.op_store().root_operation_id() {
+            continue;
+        }
+        let operation = repo.op_store().read_operation(&op_id)?;
+        let op_blob = git_repo.blob(&std::fs::read(op_store.operation_path(&op_id))?)?;
+        operations_builder.insert(op_id.hex(), op_blob, 0o100644)?;
+        let view_blob = git_repo.blob(&std::fs::read(op_store.view_path(&operation.view_id))?)?;
+        views_builder.insert(operation.view_id.hex(), view_blob, 0o100644)?;
+        if !remote_operations.contains(&op_id.hex()) {
+            exported_count += 1;
+        }
+    }
+
+    // The commits are kept reachable as parents of the sync commits. Each sync
+    // commit only adds the heads that the previous one doesn't reach, and the
+    // metadata of the commits they make reachable.
+    let root_commit_id = repo.store().root_commit_id();
+    let commit_heads = match repo.index().all_heads_for_gc() {
+        Ok(heads) => heads.collect_vec(),
+        Err(_) => repo.view().all_referenced_commit_ids().cloned().collect(),
+    };
+    let mut new_commits_walk = git_repo.revwalk()?;
+    for commit_id in &commit_heads {
+        if commit_id != root_commit_id {
+            new_commits_walk.push(Oid::from_bytes(commit_id.as_bytes())?)?;
+        }
+    }
+    if let Some(commit) = &remote_commit {
+        new_commits_walk.hide(commit.id())?;
+    }
+    let new_commits: HashSet<Oid> = new_commits_walk.try_collect()?;
+    let remote_tree = remote_commit
+        .as_ref()
+        .map(|commit| commit.tree())
+        .transpose()?;
+    let remote_dir = |dir: &str| -> Result<Option<git2::Tree>, git2::Error> {
+        match remote_tree.as_ref().and_then(|tree| tree.get_name(dir)) {
+            Some(entry) => Ok(Some(git_repo.find_tree(entry.id())?)),
+            None => Ok(None),
+        }
+    };
+    let mut extras_builder = git_repo.treebuilder(remote_dir("extras")?.as_ref())?;
+    let mut trees_builder = git_repo.treebuilder(remote_dir("trees")?.as_ref())?;
+    for oid in &new_commits {
+        let commit_id = CommitId::from_bytes(oid.as_bytes());
+        let Some(extras) = git_backend.read_extra_metadata(&commit_id)? else {
+            continue;
+        };
+        let extras_blob = git_repo.blob(&extras)?;
+        extras_builder.insert(commit_id.hex(), extras_blob, 0o100644)?;
+        // The trees of conflicted commits are only referenced by the metadata
+        let commit = repo.store().get_commit(&commit_id)?;
+        if let MergedTreeId::Merge(tree_ids) = commit.tree_id() {
+            if !tree_ids.is_resolved() {
+                for tree_id in tree_ids.iter() {
+                    let oid = Oid::from_bytes(tree_id.as_bytes())?;
+                    trees_builder.insert(tree_id.hex(), oid, 0o040000)?;
+                }
+            }
+        }
+    }
+
+    let heads_blob = git_repo.blob(
+        op_heads
+            .iter()
+            .map(|id| id.hex() + "\n")
+            .join("")
+            .as_bytes(),
+    )?;
+    let mut root_builder = git_repo.treebuilder(None)?;
+    root_builder.insert("heads", heads_blob, 0o100644)?;
+    for (name, builder) in [
+        ("operations", operations_builder),
+        ("views", views_builder),
+        ("extras", extras_builder),
+        ("trees", trees_builder),
+    ] {
+        if !builder.is_empty() {
+            root_builder.insert(name, builder.write()?, 0o040000)?;
+        }
+    }
+    let tree = git_repo.find_tree(root_builder.write()?)?;
+    if remote_commit
+        .as_ref()
+        .is_some_and(|commit| commit.tree_id() == tree.id())
+    {
+        return Ok(0);
+    }
+
+    let mut parents = remote_commit.into_iter().collect_vec();
+    for commit_id in &commit_heads {
+        let oid = Oid::from_bytes(commit_id.as_bytes())?;
+        if new_commits.contains(&oid) {
+            parents.push(git_repo.find_commit(oid)?);
+        }
+    }
+    let signature = git2::Signature::now("jj", "jj@localhost")?;
+    let sync_commit_id = git_repo.commit(
+        None,
+        &signature,
+        &signature,
+        "Synchronize the jj operation log",
+        &tree,
+        &parents.iter().collect_vec(),
+    )?;
+    git_repo.reference(
+        OP_LOG_SYNC_REF,
+        sync_commit_id,
+        true,
+        "synchronize the operation log",
+    )?;
+    push_refs(
+        git_repo,
+        remote_name,
+        &[OP_LOG_SYNC_REF],
+        &[format!("{OP_LOG_SYNC_REF}:{OP_LOG_SYNC_REF}")],
+        callbacks,
+    )?;
+    git_repo.reference(
+        &tracking_ref,
+        sync_commit_id,
+        true,
+        "synchronize the operation log",
+    )?;
+    Ok(exported_count)
+}
+
+/// Reads the blobs in the directory of the synchronized operation log.
+fn read_sync_dir(
+    git_repo: &git2::Repository,
+    tree: &git2::Tree,
+    dir: &str,
+) -> Result<Vec<(String, Vec<u8>)>, GitOpSyncError> {
+    let Some(entry) = tree.get_name(dir) else {
+        return Ok(vec![]);
+    };
+    let dir_tree = git_repo.find_tree(entry.id())?;
+    dir_tree
+        .iter()
+        .map(|entry| {
+            let name = entry.name().unwrap_or_default().to_owned();
+            let blob = git_repo.find_blob(entry.id())?;
+            Ok((name, blob.content().to_vec()))
+        })
+        .collect()
+}
+
+fn read_sync_heads(
+    git_repo: &git2::Repository,
+    tree: &git2::Tree,
+) -> Result<Vec<String>, GitOpSyncError> {
+    let Some(entry) = tree.get_name("heads") else {
+        return Ok(vec![]);
+    };
+    let blob = git_repo.find_blob(entry.id())?;
+    let content = str::from_utf8(blob.content()).map_err(|_| GitOpSyncError::InvalidObject {
+        kind: "file",
+        name: "heads".to_owned(),
+    })?;
+    Ok(content.lines().map(|line| line.to_owned()).collect())
+}
+
+fn write_sync_object(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    let mut temp_file = NamedTempFile::new_in(path.parent().unwrap())?;
+    std::io::Write::write_all(&mut temp_file, data)?;
+    temp_file.persist(path)?;
+    Ok(())
+}
+
+/// A way to authenticate with a remote.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    /// A key from `ssh-agent`.
+    Agent,
+    /// The key files from `RemoteCallbacks::get_ssh_keys`.
+    KeyFile,
+    /// A password from `RemoteCallbacks::get_password` or
+    /// `RemoteCallbacks::get_username_password`.
+    Password,
+}
+
+/// The authentication methods tried when none are configured.
+pub const DEFAULT_AUTH_METHODS: &[AuthMethod] =
+    &[AuthMethod::Agent, AuthMethod::KeyFile, AuthMethod::Password];
+
 #[non_exhaustive]
 #[derive(Default)]
 #[allow(clippy::type_complexity)]
@@ -1364,26 +1979,57 @@ pub struct RemoteCallbacks<'a> {
     pub get_ssh_keys: Option<&'a mut dyn FnMut(&str) -> Vec<PathBuf>>,
     pub get_password: Option<&'a mut dyn FnMut(&str, &str) -> Option<String>>,
     pub get_username_password: Option<&'a mut dyn FnMut(&str) -> Option<(String, String)>>,
+    /// The username to use if the URL doesn't have one.
+    pub username: Option<&'a str>,
+    /// The authentication methods to try, in order. Defaults to
+    /// `DEFAULT_AUTH_METHODS`.
+    pub auth_methods: Option<&'a [AuthMethod]>,
 }
 
 impl<'a> RemoteCallbacks<'a> {
     fn into_git(mut self) -> git2::RemoteCallbacks<'a> {
         let mut callbacks = git2::RemoteCallbacks::new();
         if let Some(progress_cb) = self.progress {
-            callbacks.transfer_progress(move |progress| {
-                progress_cb(&Progress {
-                    bytes_downloaded: (progress.received_objects() < progress.total_objects())
-                        .then(|| progress.received_bytes() as u64),
-                    overall: (progress.indexed_objects() + progress.indexed_deltas()) as f32
-                        / (progress.total_objects() + progress.total_deltas()) as f32,
-                });
-                true
+            // The same callback reports fetch, pack building, and push progress.
+            let progress_cb = Rc::new(RefCell::new(progress_cb));
+            callbacks.transfer_progress({
+                let progress_cb = progress_cb.clone();
+                move |progress| {
+                    (progress_cb.borrow_mut())(&Progress {
+                        bytes_transferred: (progress.received_objects() < progress.total_objects())
+                            .then(|| progress.received_bytes() as u64),
+                        overall: (progress.indexed_objects() + progress.indexed_deltas()) as f32
+                            / (progress.total_objects() + progress.total_deltas()) as f32,
+                    });
+                    true
+                }
+            });
+            callbacks.pack_progress({
+                let progress_cb = progress_cb.clone();
+                move |_stage, current, total| {
+                    if total > 0 {
+                        (progress_cb.borrow_mut())(&Progress {
+                            bytes_transferred: None,
+                            overall: current as f32 / total as f32,
+                        });
+                    }
+                }
+            });
+            callbacks.push_transfer_progress(move |current, total, bytes| {
+                if total > 0 {
+                    (progress_cb.borrow_mut())(&Progress {
+                        bytes_transferred: (current < total).then_some(bytes as u64),
+                        overall: current as f32 / total as f32,
+                    });
+                }
             });
         }
         // TODO: We should expose the callbacks to the caller instead -- the library
         // crate shouldn't read environment variables.
         let mut tried_ssh_agent = false;
         let mut ssh_key_paths_to_try: Option<Vec<PathBuf>> = None;
+        let configured_username = self.username;
+        let auth_methods = self.auth_methods.unwrap_or(DEFAULT_AUTH_METHODS);
         callbacks.credentials(move |url, username_from_url, allowed_types| {
             let span = tracing::debug_span!("RemoteCallbacks.credentials");
             let _ = span.enter();
@@ -1391,55 +2037,75 @@ impl<'a> RemoteCallbacks<'a> {
             let git_config = git2::Config::open_default();
             let credential_helper = git_config
                 .and_then(|conf| git2::Cred::credential_helper(&conf, url, username_from_url));
+            let username = username_from_url.or(configured_username);
             if let Ok(creds) = credential_helper {
                 tracing::info!("using credential_helper");
                 return Ok(creds);
-            } else if let Some(username) = username_from_url {
-                if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-                    // Try to get the SSH key from the agent once. We don't even check if
-                    // $SSH_AUTH_SOCK is set because Windows uses another mechanism.
-                    if !tried_ssh_agent {
-                        tracing::info!(username, "trying ssh_key_from_agent");
-                        tried_ssh_agent = true;
-                        return git2::Cred::ssh_key_from_agent(username).map_err(|err| {
-                            tracing::error!(err = %err);
-                            err
-                        });
-                    }
-
-                    let paths = ssh_key_paths_to_try.get_or_insert_with(|| {
-                        if let Some(ref mut cb) = self.get_ssh_keys {
-                            let mut paths = cb(username);
-                            paths.reverse();
-                            paths
-                        } else {
-                            vec![]
-                        }
-                    });
-
-                    if let Some(path) = paths.pop() {
-                        tracing::info!(username, path = ?path, "trying ssh_key");
-                        return git2::Cred::ssh_key(username, None, &path, None).map_err(|err| {
-                            tracing::error!(err = %err);
-                            err
-                        });
-                    }
+            } else if let Some(username) = username {
+                if allowed_types.contains(git2::CredentialType::USERNAME) {
+                    tracing::info!(username, "using configured username");
+                    return git2::Cred::username(username);
                 }
-                if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-                    if let Some(ref mut cb) = self.get_password {
-                        if let Some(pw) = cb(url, username) {
-                            tracing::info!(
-                                username,
-                                "using userpass_plaintext with username from url"
-                            );
-                            return git2::Cred::userpass_plaintext(username, &pw).map_err(|err| {
+                for method in auth_methods {
+                    match method {
+                        // Try to get the SSH key from the agent once. We don't even check if
+                        // $SSH_AUTH_SOCK is set because Windows uses another mechanism.
+                        AuthMethod::Agent
+                            if allowed_types.contains(git2::CredentialType::SSH_KEY)
+                                && !tried_ssh_agent =>
+                        {
+                            tracing::info!(username, "trying ssh_key_from_agent");
+                            tried_ssh_agent = true;
+                            return git2::Cred::ssh_key_from_agent(username).map_err(|err| {
                                 tracing::error!(err = %err);
                                 err
                             });
                         }
+                        AuthMethod::KeyFile
+                            if allowed_types.contains(git2::CredentialType::SSH_KEY) =>
+                        {
+                            let paths = ssh_key_paths_to_try.get_or_insert_with(|| {
+                                if let Some(ref mut cb) = self.get_ssh_keys {
+                                    let mut paths = cb(username);
+                                    paths.reverse();
+                                    paths
+                                } else {
+                                    vec![]
+                                }
+                            });
+
+                            if let Some(path) = paths.pop() {
+                                tracing::info!(username, path = ?path, "trying ssh_key");
+                                return git2::Cred::ssh_key(username, None, &path, None).map_err(
+                                    |err| {
+                                        tracing::error!(err = %err);
+                                        err
+                                    },
+                                );
+                            }
+                        }
+                        AuthMethod::Password
+                            if allowed_types
+                                .contains(git2::CredentialType::USER_PASS_PLAINTEXT) =>
+                        {
+                            if let Some(ref mut cb) = self.get_password {
+                                if let Some(pw) = cb(url, username) {
+                                    tracing::info!(username, "using userpass_plaintext");
+                                    return git2::Cred::userpass_plaintext(username, &pw).map_err(
+                                        |err| {
+                                            tracing::error!(err = %err);
+                                            err
+                                        },
+                                    );
+                                }
+                            }
+                        }
+                        _ => {}
                     }
                 }
-            } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+                && auth_methods.contains(&AuthMethod::Password)
+            {
                 if let Some(ref mut cb) = self.get_username_password {
                     if let Some((username, pw)) = cb(url) {
                         tracing::info!(username, "using userpass_plaintext");
@@ -1458,8 +2124,8 @@ impl<'a> RemoteCallbacks<'a> {
 }
 
 pub struct Progress {
-    /// `Some` iff data transfer is currently in progress
-    pub bytes_downloaded: Option<u64>,
+    /// `Some` iff data transfer (download or upload) is currently in progress
+    pub bytes_transferred: Option<u64>,
     pub overall: f32,
 }
 
@@ -17,9 +17,10 @@
 use std::any::Any;
 use std::collections::HashSet;
 use std::fmt::{Debug, Error, Formatter};
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write as _};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Stdio};
+use std::str::FromStr;
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::SystemTime;
 use std::{fs, io, str};
@@ -32,12 +33,13 @@ use smallvec::SmallVec;
 use thiserror::Error;
 
 use crate::backend::{
-    make_root_commit, Backend, BackendError, BackendInitError, BackendLoadError, BackendResult,
-    ChangeId, Commit, CommitId, Conflict, ConflictId, ConflictTerm, FileId, MergedTreeId,
-    MillisSinceEpoch, SecureSig, Signature, SigningFn, SymlinkId, Timestamp, Tree, TreeId,
-    TreeValue,
+    make_root_commit, Backend, BackendError, BackendGcStats, BackendInitError, BackendLoadError,
+    BackendResult, ChangeId, Commit, CommitId, Conflict, ConflictId, ConflictTerm, FileId,
+    MergedTreeId, MillisSinceEpoch, SecureSig, Signature, SigningFn, SymlinkId, Timestamp, Tree,
+    TreeId, TreeValue,
 };
 use crate::file_util::{IoResultExt as _, PathError};
+use crate::hex_util::{to_forward_hex, to_reverse_hex};
 use crate::index::Index;
 use crate::lock::FileLock;
 use crate::merge::{Merge, MergeBuilder};
@@ -53,6 +55,56 @@ const CHANGE_ID_LENGTH: usize = 16;
 /// Ref namespace used only for preventing GC.
 const NO_GC_REF_NAMESPACE: &str = "refs/jj/keep/";
 const CONFLICT_SUFFIX: &str = ".jjconflict";
+/// Commit header holding the change id, in the same "reverse hex" form as it's
+/// displayed.
+const CHANGE_ID_HEADER: &str = "change-id";
+/// Commit message trailer holding the change id, in the same form as
+/// `CHANGE_ID_HEADER`.
+const CHANGE_ID_TRAILER: &str = "JJ-Change-Id";
+
+/// Where the change id of written commits is recorded in the Git commit
+/// itself, so that it survives when the commit is copied or rewritten by other
+/// Git tools. Change ids recorded in headers are recognized when importing
+/// commits regardless of this setting, and ones in trailers only if trailers
+/// are enabled.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ChangeIdInCommit {
+    /// Only in jj's own metadata.
+    #[default]
+    None,
+    /// In a `change-id` header. Headers are kept when the commit is pushed or
+    /// fetched, but dropped when it's rewritten by tools such as `git rebase`.
+    Header,
+    /// In a `JJ-Change-Id` trailer at the end of the commit message. The
+    /// trailer is kept when the commit is rewritten by most Git tools, and is
+    /// removed from the description when the commit is read back.
+    Trailer,
+}
+
+impl FromStr for ChangeIdInCommit {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "header" => Ok(Self::Header),
+            "trailer" => Ok(Self::Trailer),
+            other => Err(config::ConfigError::Message(format!(
+                "invalid git.change-id-in-commits: {other}"
+            ))),
+        }
+    }
+}
+
+fn change_id_in_commit_from_settings(
+    settings: &UserSettings,
+) -> Result<ChangeIdInCommit, config::ConfigError> {
+    match settings.config().get_string("git.change-id-in-commits") {
+        Ok(value) => value.parse(),
+        Err(config::ConfigError::NotFound(_)) => Ok(ChangeIdInCommit::default()),
+        Err(err) => Err(err),
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum GitBackendInitError {
@@ -62,6 +114,8 @@ pub enum GitBackendInitError {
     OpenRepository(#[source] gix::open::Error),
     #[error(transparent)]
     Path(PathError),
+    #[error(transparent)]
+    Config(config::ConfigError),
 }
 
 impl From<Box<GitBackendInitError>> for BackendInitError {
@@ -76,6 +130,8 @@ pub enum GitBackendLoadError {
     OpenRepository(#[source] gix::open::Error),
     #[error(transparent)]
     Path(PathError),
+    #[error(transparent)]
+    Config(config::ConfigError),
 }
 
 impl From<Box<GitBackendLoadError>> for BackendLoadError {
@@ -91,6 +147,10 @@ pub enum GitBackendError {
     ReadMetadata(#[source] TableStoreError),
     #[error("Failed to write non-git metadata")]
     WriteMetadata(#[source] TableStoreError),
+    #[error("Failed to run git fetch command for missing objects")]
+    FetchMissingObjects(#[source] std::io::Error),
+    #[error("git fetch command for missing objects exited with an error: {0}")]
+    FetchMissingObjectsStatus(ExitStatus),
 }
 
 impl From<GitBackendError> for BackendError {
@@ -107,6 +167,18 @@ pub enum GitGcError {
     GcCommandErrorStatus(ExitStatus),
 }
 
+#[derive(Debug, Error)]
+pub enum GitMaintenanceError {
+    #[error("Failed to run git {0} command")]
+    Command(&'static str, #[source] std::io::Error),
+    #[error("git {0} command exited with an error: {1}")]
+    CommandErrorStatus(&'static str, ExitStatus),
+}
+
+/// Called with the remote name and the number of objects before missing
+/// objects are fetched from a partial clone's promisor remote.
+pub type FetchMissingObjectsProgress = dyn Fn(&str, usize) + Send + Sync;
+
 pub struct GitBackend {
     // While gix::Repository can be created from gix::ThreadSafeRepository, it's
     // cheaper to cache the thread-local instance behind a mutex than creating
@@ -121,6 +193,8 @@ pub struct GitBackend {
     cached_extra_metadata: Mutex<Option<Arc<ReadonlyTable>>>,
     /// Whether tree of imported commit should be promoted to non-legacy format.
     imported_commit_uses_tree_conflict_format: bool,
+    change_id_in_commit: ChangeIdInCommit,
+    fetch_missing_objects_progress: Mutex<Option<Box<FetchMissingObjectsProgress>>>,
 }
 
 impl GitBackend {
@@ -132,6 +206,7 @@ impl GitBackend {
         base_repo: gix::ThreadSafeRepository,
         extra_metadata_store: TableStore,
         imported_commit_uses_tree_conflict_format: bool,
+        change_id_in_commit: ChangeIdInCommit,
     ) -> Self {
         let repo = Mutex::new(base_repo.to_thread_local());
         let root_commit_id = CommitId::from_bytes(&[0; HASH_LENGTH]);
@@ -146,6 +221,8 @@ impl GitBackend {
             extra_metadata_store,
             cached_extra_metadata: Mutex::new(None),
             imported_commit_uses_tree_conflict_format,
+            change_id_in_commit,
+            fetch_missing_objects_progress: Mutex::new(None),
         }
     }
 
@@ -214,6 +291,8 @@ impl GitBackend {
         git_repo_path: &Path,
         git_repo: gix::ThreadSafeRepository,
     ) -> Result<Self, Box<GitBackendInitError>> {
+        let change_id_in_commit =
+            change_id_in_commit_from_settings(settings).map_err(GitBackendInitError::Config)?;
         let extra_path = store_path.join("extra");
         fs::create_dir(&extra_path)
             .context(&extra_path)
@@ -238,11 +317,20 @@ impl GitBackend {
                 .context(&target_path)
                 .map_err(GitBackendInitError::Path)?;
         };
-        let extra_metadata_store = TableStore::init(extra_path, HASH_LENGTH);
+        let lock_backend = settings
+            .lock_backend()
+            .map_err(GitBackendInitError::Config)?;
+        let extra_metadata_store = TableStore::init(
+            extra_path,
+            HASH_LENGTH,
+            lock_backend,
+            settings.fsync().clone(),
+        );
         Ok(GitBackend::new(
             git_repo,
             extra_metadata_store,
             settings.use_tree_conflict_format(),
+            change_id_in_commit,
         ))
     }
 
@@ -265,11 +353,22 @@ impl GitBackend {
             gix_open_opts_from_settings(settings),
         )
         .map_err(GitBackendLoadError::OpenRepository)?;
-        let extra_metadata_store = TableStore::load(store_path.join("extra"), HASH_LENGTH);
+        let change_id_in_commit =
+            change_id_in_commit_from_settings(settings).map_err(GitBackendLoadError::Config)?;
+        let lock_backend = settings
+            .lock_backend()
+            .map_err(GitBackendLoadError::Config)?;
+        let extra_metadata_store = TableStore::load(
+            store_path.join("extra"),
+            HASH_LENGTH,
+            lock_backend,
+            settings.fsync().clone(),
+        );
         Ok(GitBackend::new(
             repo,
             extra_metadata_store,
             settings.use_tree_conflict_format(),
+            change_id_in_commit,
         ))
     }
 
@@ -292,6 +391,30 @@ impl GitBackend {
         self.base_repo.path()
     }
 
+    /// Packs the Git refs and writes the commit-graph file, which speed up
+    /// resolving refs and walking the history. Objects aren't pruned; that's
+    /// done by `gc()`.
+    pub fn run_maintenance(&self) -> Result<(), GitMaintenanceError> {
+        let tasks: [(&'static str, &[&str]); 2] = [
+            ("pack-refs", &["--all"]),
+            ("commit-graph", &["write", "--reachable"]),
+        ];
+        for (subcommand, args) in tasks {
+            let mut git = Command::new("git");
+            git.arg("--git-dir=."); // turn off discovery
+            git.arg(subcommand);
+            git.args(args);
+            git.current_dir(self.git_repo_path());
+            let status = git
+                .status()
+                .map_err(|err| GitMaintenanceError::Command(subcommand, err))?;
+            if !status.success() {
+                return Err(GitMaintenanceError::CommandErrorStatus(subcommand, status));
+            }
+        }
+        Ok(())
+    }
+
     /// Path to the working directory if the repository isn't bare.
     pub fn git_workdir(&self) -> Option<&Path> {
         self.base_repo.work_dir()
@@ -335,6 +458,20 @@ impl GitBackend {
         Ok(())
     }
 
+    /// Returns the commits at the shallow boundary of the backing Git repo,
+    /// whose parents haven't been fetched. Empty if the repo isn't shallow.
+    pub fn shallow_commits(&self) -> BackendResult<HashSet<CommitId>> {
+        let locked_repo = self.lock_git_repo();
+        let shallow_commits = locked_repo
+            .shallow_commits()
+            .map_err(|err| BackendError::Other(err.into()))?;
+        Ok(shallow_commits
+            .iter()
+            .flat_map(|ids| ids.iter())
+            .map(|id| CommitId::from_bytes(id.as_bytes()))
+            .collect())
+    }
+
     /// Imports the given commits and ancestors from the backing Git repo.
     ///
     /// The `head_ids` may contain commits that have already been imported, but
@@ -373,19 +510,104 @@ impl GitBackend {
             &table_lock,
             &head_ids,
             self.imported_commit_uses_tree_conflict_format,
+            self.change_id_in_commit,
         )?;
         self.save_extra_metadata_table(mut_table, &table_lock)
     }
 
+    /// Returns the serialized extra metadata, such as the change id, of the
+    /// commit. Returns `None` if the commit hasn't been imported.
+    pub fn read_extra_metadata(&self, id: &CommitId) -> BackendResult<Option<Vec<u8>>> {
+        let table = self.cached_extra_metadata_table()?;
+        Ok(table.get_value(id.as_bytes()).map(|extras| extras.to_vec()))
+    }
+
+    /// Adds extra metadata returned by `read_extra_metadata()` in another repo
+    /// for commits copied from that repo. The existing metadata of commits
+    /// isn't changed.
+    pub fn import_extra_metadata(
+        &self,
+        entries: impl IntoIterator<Item = (CommitId, Vec<u8>)>,
+    ) -> BackendResult<()> {
+        let entries = entries
+            .into_iter()
+            .filter(|(id, _)| *id != self.root_commit_id)
+            .collect_vec();
+        if entries.is_empty() {
+            return Ok(());
+        }
+        for (id, extras) in &entries {
+            crate::protos::git_store::Commit::decode(extras.as_slice())
+                .map_err(|err| to_read_object_err(err, id))?;
+        }
+        let (table, table_lock) = self.read_extra_metadata_table_locked()?;
+        let mut mut_table = table.start_mutation();
+        for (id, extras) in entries {
+            if mut_table.get_value(id.as_bytes()).is_none() {
+                mut_table.add_entry(id.to_bytes(), extras);
+            }
+        }
+        self.save_extra_metadata_table(mut_table, &table_lock)
+    }
+
+    /// Sets the callback that's called before missing objects are fetched
+    /// from the promisor remote of a partial clone.
+    pub fn set_fetch_missing_objects_progress(
+        &self,
+        progress: Option<Box<FetchMissingObjectsProgress>>,
+    ) {
+        *self.fetch_missing_objects_progress.lock().unwrap() = progress;
+    }
+
+    /// Fetches the objects from the promisor remote, if the repo is a partial
+    /// clone. The repo must not be locked by the caller.
+    fn fetch_missing_objects(&self, git_ids: &[gix::ObjectId]) -> BackendResult<()> {
+        if git_ids.is_empty() {
+            return Ok(());
+        }
+        let Some(remote) = promisor_remote(&self.lock_git_repo()) else {
+            return Ok(());
+        };
+        if let Some(progress) = &*self.fetch_missing_objects_progress.lock().unwrap() {
+            progress(&remote, git_ids.len());
+        }
+        fetch_missing_objects(self.git_repo_path(), &remote, git_ids)?;
+        Ok(())
+    }
+
+    /// Finds the object and passes it to `f`. If the repo is a partial clone
+    /// and doesn't have the object, it's fetched from the promisor remote
+    /// first, without holding the repo lock.
+    fn with_object<T>(
+        &self,
+        git_id: gix::ObjectId,
+        id: &impl ObjectId,
+        f: impl FnOnce(&gix::Repository, gix::Object<'_>) -> BackendResult<T>,
+    ) -> BackendResult<T> {
+        {
+            let locked_repo = self.lock_git_repo();
+            match locked_repo.find_object(git_id) {
+                Ok(object) => return f(&locked_repo, object),
+                Err(gix::object::find::existing::Error::NotFound { .. }) => {}
+                Err(err) => return Err(map_not_found_err(err, id)),
+            };
+        }
+        self.fetch_missing_objects(&[git_id])?;
+        let locked_repo = self.lock_git_repo();
+        let object = locked_repo
+            .find_object(git_id)
+            .map_err(|err| map_not_found_err(err, id))?;
+        f(&locked_repo, object)
+    }
+
     fn read_file_sync(&self, id: &FileId) -> BackendResult<Box<dyn Read>> {
         let git_blob_id = validate_git_object_id(id)?;
-        let locked_repo = self.lock_git_repo();
-        let mut blob = locked_repo
-            .find_object(git_blob_id)
-            .map_err(|err| map_not_found_err(err, id))?
-            .try_into_blob()
-            .map_err(|err| to_read_object_err(err, id))?;
-        Ok(Box::new(Cursor::new(blob.take_data())))
+        self.with_object(git_blob_id, id, |_, object| {
+            let mut blob = object
+                .try_into_blob()
+                .map_err(|err| to_read_object_err(err, id))?;
+            Ok(Box::new(Cursor::new(blob.take_data())) as Box<dyn Read>)
+        })
     }
 }
 
@@ -424,6 +646,7 @@ fn commit_from_git_without_root_parent(
     id: &CommitId,
     git_object: &gix::Object,
     uses_tree_conflict_format: bool,
+    change_id_in_commit: ChangeIdInCommit,
 ) -> Result<Commit, BackendError> {
     let commit = git_object
         .try_to_commit_ref()
@@ -435,13 +658,33 @@ fn commit_from_git_without_root_parent(
     // would have been enough to pick the last 16 bytes instead of the
     // leading 16 bytes to address that. We also reverse the bits to make it less
     // likely that users depend on any relationship between the two ids.
-    let change_id = ChangeId::new(
-        id.as_bytes()[4..HASH_LENGTH]
-            .iter()
-            .rev()
-            .map(|b| b.reverse_bits())
-            .collect(),
-    );
+    //
+    // If the change id was recorded in the commit when it was written, we use
+    // that instead, so the change keeps its id after the commit was rewritten by
+    // other tools. The trailer is only looked for if trailers are enabled, since
+    // it's otherwise part of the description.
+    let mut description = String::from_utf8_lossy(commit.message).into_owned();
+    let header_change_id = commit
+        .extra_headers
+        .iter()
+        .find(|(k, _)| *k == CHANGE_ID_HEADER)
+        .and_then(|(_, v)| parse_change_id(&String::from_utf8_lossy(v)));
+    let trailer_change_id = match change_id_in_commit {
+        ChangeIdInCommit::Trailer => {
+            strip_change_id_trailer(&mut description, header_change_id.as_ref())
+        }
+        ChangeIdInCommit::None | ChangeIdInCommit::Header => None,
+    };
+    let recorded_change_id = header_change_id.or(trailer_change_id);
+    let change_id = recorded_change_id.unwrap_or_else(|| {
+        ChangeId::new(
+            id.as_bytes()[4..HASH_LENGTH]
+                .iter()
+                .rev()
+                .map(|b| b.reverse_bits())
+                .collect(),
+        )
+    });
     let parents = commit
         .parents()
         .map(|oid| CommitId::from_bytes(oid.as_bytes()))
@@ -454,10 +697,9 @@ fn commit_from_git_without_root_parent(
     } else {
         MergedTreeId::Legacy(tree_id)
     };
-    // Use lossy conversion as commit message with "mojibake" is still better than
-    // nothing.
+    // The description was decoded above with lossy conversion, as commit message
+    // with "mojibake" is still better than nothing.
     // TODO: what should we do with commit.encoding?
-    let description = String::from_utf8_lossy(commit.message).into_owned();
     let author = signature_from_git(commit.author());
     let committer = signature_from_git(commit.committer());
 
@@ -494,6 +736,55 @@ fn commit_from_git_without_root_parent(
     })
 }
 
+fn parse_change_id(reverse_hex: &str) -> Option<ChangeId> {
+    let change_id = ChangeId::try_from_hex(&to_forward_hex(reverse_hex.trim())?).ok()?;
+    (change_id.as_bytes().len() == CHANGE_ID_LENGTH).then_some(change_id)
+}
+
+/// Appends the `CHANGE_ID_TRAILER` to the description, in a paragraph of its
+/// own. `strip_change_id_trailer()` undoes this. If the description doesn't end
+/// with a newline, neither does the trailer, so that this can be undone.
+fn append_change_id_trailer(description: &str, change_id: &ChangeId) -> String {
+    let trailer = format!(
+        "{CHANGE_ID_TRAILER}: {}",
+        to_reverse_hex(&change_id.hex()).unwrap()
+    );
+    if description.is_empty() {
+        format!("{trailer}\n")
+    } else if description.ends_with('\n') {
+        format!("{description}\n{trailer}\n")
+    } else {
+        format!("{description}\n\n{trailer}")
+    }
+}
+
+/// Removes the `CHANGE_ID_TRAILER` from the end of the description, and
+/// returns the change id it holds. If `expected_change_id` is set, the trailer
+/// is only removed if it holds that change id.
+fn strip_change_id_trailer(
+    description: &mut String,
+    expected_change_id: Option<&ChangeId>,
+) -> Option<ChangeId> {
+    let (without_newline, has_newline) = match description.strip_suffix('\n') {
+        Some(without_newline) => (without_newline, true),
+        None => (description.as_str(), false),
+    };
+    let line_start = without_newline.rfind('\n').map_or(0, |pos| pos + 1);
+    let value = without_newline[line_start..].strip_prefix(CHANGE_ID_TRAILER)?;
+    let change_id = parse_change_id(value.strip_prefix(':')?)?;
+    if expected_change_id.is_some_and(|expected| *expected != change_id) {
+        return None;
+    }
+    let rest = &description[..line_start];
+    let rest = match rest.strip_suffix("\n\n") {
+        Some(without_separator) if has_newline => &rest[..without_separator.len() + 1],
+        Some(without_separator) => without_separator,
+        None => rest,
+    };
+    *description = rest.to_owned();
+    Some(change_id)
+}
+
 const EMPTY_STRING_PLACEHOLDER: &str = "JJ_EMPTY_STRING";
 
 fn signature_from_git(signature: gix::actor::SignatureRef) -> Signature {
@@ -621,13 +912,15 @@ fn to_ref_deletion(git_ref: gix::refs::Reference) -> gix::refs::transaction::Ref
 
 /// Recreates `refs/jj/keep` refs for the `new_heads`, and removes the other
 /// unreachable and non-head refs.
+/// Replaces the no-gc refs with refs to the `new_heads`. Returns the commits
+/// whose no-gc refs were (or would be in a dry run) deleted.
 fn recreate_no_gc_refs(
     git_repo: &gix::Repository,
-    new_heads: impl IntoIterator<Item = CommitId>,
+    new_heads: &HashSet<CommitId>,
     keep_newer: SystemTime,
-) -> Result<(), BackendError> {
+    dry_run: bool,
+) -> Result<Vec<CommitId>, BackendError> {
     // Calculate diff between existing no-gc refs and new heads.
-    let new_heads: HashSet<CommitId> = new_heads.into_iter().collect();
     let mut no_gc_refs_to_keep_count: usize = 0;
     let mut no_gc_refs_to_delete: Vec<gix::refs::Reference> = Vec::new();
     let git_references = git_repo
@@ -676,6 +969,14 @@ fn recreate_no_gc_refs(
         no_gc_refs_to_delete_count = no_gc_refs_to_delete.len(),
         "collected reachable refs"
     );
+    let deleted_heads = no_gc_refs_to_delete
+        .iter()
+        .filter_map(|git_ref| git_ref.target.try_id())
+        .map(|oid| CommitId::from_bytes(oid.as_bytes()))
+        .collect_vec();
+    if dry_run {
+        return Ok(deleted_heads);
+    }
 
     // It's slow to delete packed refs one by one, so update refs all at once.
     let ref_edits = itertools::chain(
@@ -686,6 +987,92 @@ fn recreate_no_gc_refs(
         .edit_references(ref_edits)
         .map_err(|err| BackendError::Other(err.into()))?;
 
+    Ok(deleted_heads)
+}
+
+/// Counts the commits that are reachable from the `deleted_heads`, but not
+/// from the `new_heads` or any ref other than the no-gc refs. These are the
+/// commits that "git gc" will eventually prune.
+fn count_unreachable_commits(
+    git_repo: &git2::Repository,
+    deleted_heads: &[CommitId],
+    new_heads: &HashSet<CommitId>,
+) -> Result<usize, git2::Error> {
+    if deleted_heads.is_empty() {
+        return Ok(0);
+    }
+    let mut revwalk = git_repo.revwalk()?;
+    for id in deleted_heads {
+        revwalk.push(git2::Oid::from_bytes(id.as_bytes())?)?;
+    }
+    for id in new_heads {
+        revwalk.hide(git2::Oid::from_bytes(id.as_bytes())?)?;
+    }
+    for git_ref in git_repo.references()? {
+        let git_ref = git_ref?;
+        if git_ref
+            .name_bytes()
+            .starts_with(NO_GC_REF_NAMESPACE.as_bytes())
+        {
+            continue;
+        }
+        // Refs to non-commit objects are irrelevant
+        if let Ok(commit) = git_ref.peel_to_commit() {
+            revwalk.hide(commit.id())?;
+        }
+    }
+    let mut count = 0;
+    for oid in revwalk {
+        oid?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Returns the remote that a partial clone fetches its missing objects from.
+fn promisor_remote(git_repo: &gix::Repository) -> Option<String> {
+    let config = git_repo.config_snapshot();
+    if let Some(name) = config.string("extensions.partialClone") {
+        return Some(name.to_string());
+    }
+    git_repo
+        .remote_names()
+        .into_iter()
+        .find(|name| config.boolean(format!("remote.{name}.promisor").as_str()) == Some(true))
+        .map(|name| name.to_string())
+}
+
+/// Fetches objects that are missing from a partial clone, the same way Git
+/// does when it needs them.
+fn fetch_missing_objects(
+    git_dir: &Path,
+    remote: &str,
+    ids: &[gix::ObjectId],
+) -> Result<(), GitBackendError> {
+    let mut git = Command::new("git");
+    git.arg("--git-dir=."); // turn off discovery
+                            // Don't negotiate common commits, which would only slow down the fetch
+    git.args(["-c", "fetch.negotiationAlgorithm=noop", "fetch", remote]);
+    git.args([
+        "--no-tags",
+        "--no-write-fetch-head",
+        "--recurse-submodules=no",
+        "--filter=blob:none",
+        "--stdin",
+    ]);
+    git.current_dir(git_dir);
+    git.stdin(Stdio::piped());
+    git.stdout(Stdio::null());
+    let mut child = git.spawn().map_err(GitBackendError::FetchMissingObjects)?;
+    let mut stdin = child.stdin.take().unwrap();
+    for id in ids {
+        writeln!(stdin, "{id}").map_err(GitBackendError::FetchMissingObjects)?;
+    }
+    drop(stdin);
+    let status = child.wait().map_err(GitBackendError::FetchMissingObjects)?;
+    if !status.success() {
+        return Err(GitBackendError::FetchMissingObjectsStatus(status));
+    }
     Ok(())
 }
 
@@ -747,12 +1134,23 @@ fn to_invalid_utf8_err(source: str::Utf8Error, id: &impl ObjectId) -> BackendErr
     }
 }
 
+/// Returns true if the commit is at the shallow boundary of a shallow clone.
+/// Its parents are missing from the repo, so it's read as a child of the root
+/// commit instead.
+fn is_shallow_commit(git_repo: &gix::Repository, id: gix::ObjectId) -> BackendResult<bool> {
+    let shallow_commits = git_repo
+        .shallow_commits()
+        .map_err(|err| BackendError::Other(err.into()))?;
+    Ok(shallow_commits.is_some_and(|ids| ids.binary_search(&id).is_ok()))
+}
+
 fn import_extra_metadata_entries_from_heads(
     git_repo: &gix::Repository,
     mut_table: &mut MutableTable,
     _table_lock: &FileLock,
     head_ids: &HashSet<&CommitId>,
     uses_tree_conflict_format: bool,
+    change_id_in_commit: ChangeIdInCommit,
 ) -> BackendResult<()> {
     let mut work_ids = head_ids
         .iter()
@@ -766,9 +1164,16 @@ fn import_extra_metadata_entries_from_heads(
         // TODO(#1624): Should we read the root tree here and check if it has a
         // `.jjconflict-...` entries? That could happen if the user used `git` to e.g.
         // change the description of a commit with tree-level conflicts.
-        let commit =
-            commit_from_git_without_root_parent(&id, &git_object, uses_tree_conflict_format)?;
+        let mut commit = commit_from_git_without_root_parent(
+            &id,
+            &git_object,
+            uses_tree_conflict_format,
+            change_id_in_commit,
+        )?;
         mut_table.add_entry(id.to_bytes(), serialize_extras(&commit));
+        if is_shallow_commit(git_repo, git_object.id)? {
+            commit.parents.clear();
+        }
         work_ids.extend(
             commit
                 .parents
@@ -825,6 +1230,19 @@ impl Backend for GitBackend {
         self.read_file_sync(id)
     }
 
+    fn prefetch_files(&self, ids: &[FileId]) -> BackendResult<()> {
+        let git_ids: Vec<_> = ids.iter().map(validate_git_object_id).try_collect()?;
+        let missing_ids = {
+            let locked_repo = self.lock_git_repo();
+            git_ids
+                .into_iter()
+                .filter(|git_id| !locked_repo.has_object(git_id))
+                .unique()
+                .collect_vec()
+        };
+        self.fetch_missing_objects(&missing_ids)
+    }
+
     fn write_file(&self, _path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
         let mut bytes = Vec::new();
         contents.read_to_end(&mut bytes).unwrap();
@@ -840,13 +1258,13 @@ impl Backend for GitBackend {
 
     async fn read_symlink(&self, _path: &RepoPath, id: &SymlinkId) -> Result<String, BackendError> {
         let git_blob_id = validate_git_object_id(id)?;
-        let locked_repo = self.lock_git_repo();
-        let mut blob = locked_repo
-            .find_object(git_blob_id)
-            .map_err(|err| map_not_found_err(err, id))?
-            .try_into_blob()
-            .map_err(|err| to_read_object_err(err, id))?;
-        let target = String::from_utf8(blob.take_data())
+        let data = self.with_object(git_blob_id, id, |_, object| {
+            let mut blob = object
+                .try_into_blob()
+                .map_err(|err| to_read_object_err(err, id))?;
+            Ok(blob.take_data())
+        })?;
+        let target = String::from_utf8(data)
             .map_err(|err| to_invalid_utf8_err(err.utf8_error(), id))?
             .to_owned();
         Ok(target)
@@ -870,61 +1288,60 @@ impl Backend for GitBackend {
         }
         let git_tree_id = validate_git_object_id(id)?;
 
-        let locked_repo = self.lock_git_repo();
-        let git_tree = locked_repo
-            .find_object(git_tree_id)
-            .map_err(|err| map_not_found_err(err, id))?
-            .try_into_tree()
-            .map_err(|err| to_read_object_err(err, id))?;
-        let mut tree = Tree::default();
-        for entry in git_tree.iter() {
-            let entry = entry.map_err(|err| to_read_object_err(err, id))?;
-            let name =
-                str::from_utf8(entry.filename()).map_err(|err| to_invalid_utf8_err(err, id))?;
-            let (name, value) = match entry.mode().kind() {
-                gix::object::tree::EntryKind::Tree => {
-                    let id = TreeId::from_bytes(entry.oid().as_bytes());
-                    (name, TreeValue::Tree(id))
-                }
-                gix::object::tree::EntryKind::Blob => {
-                    let id = FileId::from_bytes(entry.oid().as_bytes());
-                    if let Some(basename) = name.strip_suffix(CONFLICT_SUFFIX) {
-                        (
-                            basename,
-                            TreeValue::Conflict(ConflictId::from_bytes(entry.oid().as_bytes())),
-                        )
-                    } else {
+        self.with_object(git_tree_id, id, |_, object| {
+            let git_tree = object
+                .try_into_tree()
+                .map_err(|err| to_read_object_err(err, id))?;
+            let mut tree = Tree::default();
+            for entry in git_tree.iter() {
+                let entry = entry.map_err(|err| to_read_object_err(err, id))?;
+                let name =
+                    str::from_utf8(entry.filename()).map_err(|err| to_invalid_utf8_err(err, id))?;
+                let (name, value) = match entry.mode().kind() {
+                    gix::object::tree::EntryKind::Tree => {
+                        let id = TreeId::from_bytes(entry.oid().as_bytes());
+                        (name, TreeValue::Tree(id))
+                    }
+                    gix::object::tree::EntryKind::Blob => {
+                        let id = FileId::from_bytes(entry.oid().as_bytes());
+                        if let Some(basename) = name.strip_suffix(CONFLICT_SUFFIX) {
+                            (
+                                basename,
+                                TreeValue::Conflict(ConflictId::from_bytes(entry.oid().as_bytes())),
+                            )
+                        } else {
+                            (
+                                name,
+                                TreeValue::File {
+                                    id,
+                                    executable: false,
+                                },
+                            )
+                        }
+                    }
+                    gix::object::tree::EntryKind::BlobExecutable => {
+                        let id = FileId::from_bytes(entry.oid().as_bytes());
                         (
                             name,
                             TreeValue::File {
                                 id,
-                                executable: false,
+                                executable: true,
                             },
                         )
                     }
-                }
-                gix::object::tree::EntryKind::BlobExecutable => {
-                    let id = FileId::from_bytes(entry.oid().as_bytes());
-                    (
-                        name,
-                        TreeValue::File {
-                            id,
-                            executable: true,
-                        },
-                    )
-                }
-                gix::object::tree::EntryKind::Link => {
-                    let id = SymlinkId::from_bytes(entry.oid().as_bytes());
-                    (name, TreeValue::Symlink(id))
-                }
-                gix::object::tree::EntryKind::Commit => {
-                    let id = CommitId::from_bytes(entry.oid().as_bytes());
-                    (name, TreeValue::GitSubmodule(id))
-                }
-            };
-            tree.set(RepoPathComponentBuf::from(name), value);
-        }
-        Ok(tree)
+                    gix::object::tree::EntryKind::Link => {
+                        let id = SymlinkId::from_bytes(entry.oid().as_bytes());
+                        (name, TreeValue::Symlink(id))
+                    }
+                    gix::object::tree::EntryKind::Commit => {
+                        let id = CommitId::from_bytes(entry.oid().as_bytes());
+                        (name, TreeValue::GitSubmodule(id))
+                    }
+                };
+                tree.set(RepoPathComponentBuf::from(name), value);
+            }
+            Ok(tree)
+        })
     }
 
     fn write_tree(&self, _path: &RepoPath, contents: &Tree) -> BackendResult<TreeId> {
@@ -1028,13 +1445,18 @@ impl Backend for GitBackend {
         }
         let git_commit_id = validate_git_object_id(id)?;
 
-        let mut commit = {
-            let locked_repo = self.lock_git_repo();
-            let git_object = locked_repo
-                .find_object(git_commit_id)
-                .map_err(|err| map_not_found_err(err, id))?;
-            commit_from_git_without_root_parent(id, &git_object, false)?
-        };
+        let mut commit = self.with_object(git_commit_id, id, |locked_repo, git_object| {
+            let mut commit = commit_from_git_without_root_parent(
+                id,
+                &git_object,
+                false,
+                self.change_id_in_commit,
+            )?;
+            if is_shallow_commit(locked_repo, git_commit_id)? {
+                commit.parents.clear();
+            }
+            Ok(commit)
+        })?;
         if commit.parents.is_empty() {
             commit.parents.push(self.root_commit_id.clone());
         };
@@ -1073,7 +1495,19 @@ impl Backend for GitBackend {
         };
         let author = signature_to_git(&contents.author);
         let mut committer = signature_to_git(&contents.committer);
-        let message = &contents.description;
+        let message = match self.change_id_in_commit {
+            ChangeIdInCommit::Trailer => {
+                append_change_id_trailer(&contents.description, &contents.change_id)
+            }
+            ChangeIdInCommit::None | ChangeIdInCommit::Header => contents.description.clone(),
+        };
+        let mut extra_headers: Vec<(gix::bstr::BString, gix::bstr::BString)> = vec![];
+        if self.change_id_in_commit == ChangeIdInCommit::Header {
+            extra_headers.push((
+                CHANGE_ID_HEADER.into(),
+                to_reverse_hex(&contents.change_id.hex()).unwrap().into(),
+            ));
+        }
         if contents.parents.is_empty() {
             return Err(BackendError::Other(
                 "Cannot write a commit with no parents".into(),
@@ -1108,13 +1542,13 @@ impl Backend for GitBackend {
         let (table, table_lock) = self.read_extra_metadata_table_locked()?;
         let id = loop {
             let mut commit = gix::objs::Commit {
-                message: message.to_owned().into(),
+                message: message.clone().into(),
                 tree: git_tree_id,
                 author: author.into(),
                 committer: committer.into(),
                 encoding: None,
                 parents: parents.clone(),
-                extra_headers: Default::default(),
+                extra_headers: extra_headers.clone(),
             };
 
             if let Some(sign) = &mut sign_with {
@@ -1166,19 +1600,38 @@ impl Backend for GitBackend {
     }
 
     #[tracing::instrument(skip(self, index))]
-    fn gc(&self, index: &dyn Index, keep_newer: SystemTime) -> BackendResult<()> {
+    fn gc(
+        &self,
+        index: &dyn Index,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<BackendGcStats> {
         let git_repo = self.lock_git_repo();
-        let new_heads = index
+        let new_heads: HashSet<CommitId> = index
             .all_heads_for_gc()
             .map_err(|err| BackendError::Other(err.into()))?
-            .filter(|id| *id != self.root_commit_id);
-        recreate_no_gc_refs(&git_repo, new_heads, keep_newer)?;
+            .filter(|id| *id != self.root_commit_id)
+            .collect();
+        let deleted_heads = recreate_no_gc_refs(&git_repo, &new_heads, keep_newer, dry_run)?;
+        // The objects are still there until "git gc" prunes them.
+        let removed_commits = self
+            .open_git_repo()
+            .and_then(|git2_repo| count_unreachable_commits(&git2_repo, &deleted_heads, &new_heads))
+            .map_err(|err| BackendError::Other(err.into()))?;
+        let stats = BackendGcStats {
+            removed_commits,
+            removed_objects: 0,
+        };
+        if dry_run {
+            return Ok(stats);
+        }
         // TODO: remove unreachable entries from extras table if segment file
         // mtime <= keep_newer? (it won't be consistent with no-gc refs
         // preserved by the keep_newer timestamp though)
         // TODO: remove unreachable extras table segments
         // TODO: pass in keep_newer to "git gc" command
-        run_git_gc(self.git_repo_path()).map_err(|err| BackendError::Other(err.into()))
+        run_git_gc(self.git_repo_path()).map_err(|err| BackendError::Other(err.into()))?;
+        Ok(stats)
     }
 }
 
@@ -1506,6 +1959,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_shallow_git_commit() {
+        let settings = user_settings();
+        let temp_dir = testutils::new_temp_dir();
+        let store_path = temp_dir.path();
+        let git_repo_path = temp_dir.path().join("git");
+        let git_repo = git2::Repository::init(git_repo_path).unwrap();
+
+        let signature =
+            git2::Signature::new("Someone", "someone@example.com", &git2::Time::new(0, 0)).unwrap();
+        let empty_tree_id = Oid::from_str("4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap();
+        let empty_tree = git_repo.find_tree(empty_tree_id).unwrap();
+        let create_commit = |message: &str, parents: &[&git2::Commit]| {
+            git_repo
+                .commit(None, &signature, &signature, message, &empty_tree, parents)
+                .unwrap()
+        };
+        let git_commit_id1 = create_commit("commit 1", &[]);
+        let git_commit_id2 = create_commit(
+            "commit 2",
+            &[&git_repo.find_commit(git_commit_id1).unwrap()],
+        );
+        let git_commit_id3 = create_commit(
+            "commit 3",
+            &[&git_repo.find_commit(git_commit_id2).unwrap()],
+        );
+        let commit_id1 = CommitId::from_bytes(git_commit_id1.as_bytes());
+        let commit_id2 = CommitId::from_bytes(git_commit_id2.as_bytes());
+        let commit_id3 = CommitId::from_bytes(git_commit_id3.as_bytes());
+
+        // Make the repo look like a shallow clone whose boundary is commit 2
+        let shallow_file = git_repo.path().join("shallow");
+        std::fs::write(&shallow_file, format!("{git_commit_id2}\n")).unwrap();
+        let hex = git_commit_id1.to_string();
+        let object_path = git_repo
+            .path()
+            .join("objects")
+            .join(&hex[..2])
+            .join(&hex[2..]);
+        std::fs::remove_file(object_path).unwrap();
+
+        let backend = GitBackend::init_external(&settings, store_path, git_repo.path()).unwrap();
+        assert_eq!(
+            backend.shallow_commits().unwrap(),
+            HashSet::from([commit_id2.clone()])
+        );
+        // The missing parent isn't imported
+        backend.import_head_commits([&commit_id3]).unwrap();
+        let commit = backend.read_commit(&commit_id2).block_on().unwrap();
+        assert_eq!(commit.parents, vec![backend.root_commit_id().clone()]);
+        let commit = backend.read_commit(&commit_id3).block_on().unwrap();
+        assert_eq!(commit.parents, vec![commit_id2.clone()]);
+
+        // Once the history is deepened, the parent is read again
+        create_commit("commit 1", &[]);
+        std::fs::remove_file(&shallow_file).unwrap();
+        assert_eq!(backend.shallow_commits().unwrap(), HashSet::new());
+        backend.import_head_commits([&commit_id1]).unwrap();
+        let commit = backend.read_commit(&commit_id2).block_on().unwrap();
+        assert_eq!(commit.parents, vec![commit_id1]);
+    }
+
     #[test]
     fn read_signed_git_commit() {
         let settings = user_settings();
@@ -1663,6 +2178,173 @@ mod tests {
         );
     }
 
+    #[test_case(ChangeIdInCommit::Header; "header")]
+    #[test_case(ChangeIdInCommit::Trailer; "trailer")]
+    fn change_id_recorded_in_commit(change_id_in_commit: ChangeIdInCommit) {
+        let value = match change_id_in_commit {
+            ChangeIdInCommit::None => "none",
+            ChangeIdInCommit::Header => "header",
+            ChangeIdInCommit::Trailer => "trailer",
+        };
+        let settings = {
+            let config = config::Config::builder()
+                .set_override("git.change-id-in-commits", value)
+                .unwrap()
+                .build()
+                .unwrap();
+            UserSettings::from_config(config)
+        };
+        let temp_dir = testutils::new_temp_dir();
+        let store_path = temp_dir.path();
+        let git_repo_path = temp_dir.path().join("git");
+        let git_repo = git2::Repository::init(git_repo_path).unwrap();
+
+        let backend = GitBackend::init_external(&settings, store_path, git_repo.path()).unwrap();
+        let change_id = ChangeId::from_hex("c64ee0b6e16777fe53991f9281a6cd25");
+        let commit = Commit {
+            parents: vec![backend.root_commit_id().clone()],
+            predecessors: vec![],
+            root_tree: MergedTreeId::Legacy(backend.empty_tree_id().clone()),
+            change_id: change_id.clone(),
+            description: "my change\n".to_string(),
+            author: create_signature(),
+            committer: create_signature(),
+            secure_sig: None,
+        };
+        let (commit_id, _) = backend.write_commit(commit.clone(), None).unwrap();
+        assert_eq!(backend.read_commit(&commit_id).block_on().unwrap(), commit);
+        let git_commit = git_repo.find_commit(git_id(&commit_id)).unwrap();
+        match change_id_in_commit {
+            ChangeIdInCommit::Header => {
+                assert_eq!(git_commit.message(), Some("my change\n"));
+                assert_eq!(
+                    git_commit.header_field_bytes("change-id").unwrap().as_str(),
+                    Some("ntvllzotlytssskluwqqykqxryptnmxu")
+                );
+            }
+            _ => assert_eq!(
+                git_commit.message(),
+                Some("my change\n\nJJ-Change-Id: ntvllzotlytssskluwqqykqxryptnmxu\n")
+            ),
+        }
+
+        // Copy the commit like another Git tool would, keeping its message and
+        // headers. The copy is read with the same change id.
+        let copied_git_id = {
+            let repo = backend.git_repo();
+            let git_object = repo
+                .find_object(validate_git_object_id(&commit_id).unwrap())
+                .unwrap();
+            let mut git_commit: gix::objs::Commit = git_object.try_to_commit_ref().unwrap().into();
+            git_commit.committer.time.seconds += 1;
+            repo.write_object(&git_commit).unwrap().detach()
+        };
+        let copied_id = CommitId::from_bytes(copied_git_id.as_bytes());
+        assert_ne!(copied_id, commit_id);
+        backend.import_head_commits([&copied_id]).unwrap();
+        let copied_commit = backend.read_commit(&copied_id).block_on().unwrap();
+        assert_eq!(copied_commit.change_id, change_id);
+        assert_eq!(copied_commit.description, "my change\n");
+    }
+
+    #[test]
+    fn change_id_trailer_ignored_unless_enabled() {
+        let settings_with = |value: &str| {
+            let config = config::Config::builder()
+                .set_override("git.change-id-in-commits", value)
+                .unwrap()
+                .build()
+                .unwrap();
+            UserSettings::from_config(config)
+        };
+        let temp_dir = testutils::new_temp_dir();
+        let git_repo_path = temp_dir.path().join("git");
+        let git_repo = git2::Repository::init(git_repo_path).unwrap();
+
+        // Write a commit whose message ends with a trailer, like another Git tool
+        // might have
+        let message = "my change\n\nJJ-Change-Id: ntvllzotlytssskluwqqykqxryptnmxu\n";
+        let git_commit_id = {
+            let signature = git2::Signature::now("Someone", "someone@example.com").unwrap();
+            let tree = git_repo
+                .find_tree(git_repo.index().unwrap().write_tree().unwrap())
+                .unwrap();
+            git_repo
+                .commit(None, &signature, &signature, message, &tree, &[])
+                .unwrap()
+        };
+        let commit_id = CommitId::from_bytes(git_commit_id.as_bytes());
+        // Each setting gets its own store, since the change id is kept in the
+        // store once the commit is read
+        let read_commit_with = |value: &str| {
+            let store_path = temp_dir.path().join(value);
+            fs::create_dir(&store_path).unwrap();
+            let backend =
+                GitBackend::init_external(&settings_with(value), &store_path, git_repo.path())
+                    .unwrap();
+            backend.read_commit(&commit_id).block_on().unwrap()
+        };
+
+        // Without trailers enabled, the trailer is part of the description, and
+        // the change id isn't taken from it
+        let commit = read_commit_with("none");
+        assert_eq!(commit.description, message);
+        assert_ne!(
+            commit.change_id,
+            ChangeId::from_hex("c64ee0b6e16777fe53991f9281a6cd25")
+        );
+
+        let commit = read_commit_with("trailer");
+        assert_eq!(commit.description, "my change\n");
+        assert_eq!(
+            commit.change_id,
+            ChangeId::from_hex("c64ee0b6e16777fe53991f9281a6cd25")
+        );
+    }
+
+    #[test]
+    fn change_id_trailer_round_trip() {
+        let change_id = ChangeId::from_hex("c64ee0b6e16777fe53991f9281a6cd25");
+        for description in [
+            "",
+            "subject\n",
+            "subject",
+            "subject\n\nbody\n\n",
+            "subject\n\nbody",
+        ] {
+            let mut message = append_change_id_trailer(description, &change_id);
+            assert_eq!(
+                strip_change_id_trailer(&mut message, None),
+                Some(change_id.clone())
+            );
+            assert_eq!(message, description);
+            let mut message = append_change_id_trailer(description, &change_id);
+            assert_eq!(
+                strip_change_id_trailer(&mut message, Some(&change_id)),
+                Some(change_id.clone())
+            );
+            assert_eq!(message, description);
+        }
+        // Trailers with invalid values, or not at the end, are kept
+        for message in [
+            "subject\n\nJJ-Change-Id: abc\n",
+            "subject\n\nJJ-Change-Id: ntvllzotlytssskluwqqykqxryptnmxu\nSigned-off-by: me\n",
+        ] {
+            let mut description = message.to_owned();
+            assert_eq!(strip_change_id_trailer(&mut description, None), None);
+            assert_eq!(description, message);
+        }
+        // A trailer with another change id than the expected one is kept
+        let other_change_id = ChangeId::from_hex("0123456789abcdef0123456789abcdef");
+        let message = append_change_id_trailer("subject\n", &other_change_id);
+        let mut description = message.clone();
+        assert_eq!(
+            strip_change_id_trailer(&mut description, Some(&change_id)),
+            None
+        );
+        assert_eq!(description, message);
+    }
+
     #[test]
     fn write_tree_conflicts() {
         let settings = user_settings();
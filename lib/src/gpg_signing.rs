@@ -0,0 +1,319 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend for OpenPGP signatures created and verified by `gpg`, and
+//! the plumbing shared with other programs that have the same command line.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt::Debug;
+use std::io::Write;
+use std::process::{Command, ExitStatus, Output, Stdio};
+
+use thiserror::Error;
+
+use crate::settings::{ConfigResultExt as _, UserSettings};
+use crate::signing::{
+    SigStatus, SignError, SignInitError, SignResult, SigningBackend, Verification,
+};
+
+const SIGNATURE_PREFIX: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+
+/// An error from running a signing program.
+#[derive(Debug, Error)]
+pub enum GpgError {
+    /// The program exited with a non-zero status.
+    #[error("Signing program failed with {exit_status}:\n{stderr}")]
+    Command {
+        /// The exit status of the program.
+        exit_status: ExitStatus,
+        /// What the program printed to stderr.
+        stderr: String,
+    },
+    /// The program didn't report the verification result.
+    #[error("Signing program didn't report a verification status")]
+    MissingStatus,
+    /// The program couldn't be started.
+    #[error("Failed to start signing program")]
+    Spawn(#[source] std::io::Error),
+    /// The program couldn't be run.
+    #[error("Failed to run signing program")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<GpgError> for SignError {
+    fn from(e: GpgError) -> Self {
+        match e {
+            GpgError::Spawn(_) => SignError::Unavailable(Box::new(e)),
+            _ => SignError::Backend(Box::new(e)),
+        }
+    }
+}
+
+impl GpgError {
+    fn from_output(output: &Output) -> Self {
+        GpgError::Command {
+            exit_status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_owned(),
+        }
+    }
+}
+
+/// A program with the command line and status output of `gpg`, such as `gpg`
+/// itself, `gpgsm`, or `gitsign`.
+#[derive(Debug)]
+pub(crate) struct GpgProgram {
+    program: OsString,
+    envs: Vec<(OsString, OsString)>,
+}
+
+impl GpgProgram {
+    pub fn new(program: OsString) -> Self {
+        Self {
+            program,
+            envs: vec![],
+        }
+    }
+
+    pub fn env(&mut self, key: OsString, value: OsString) {
+        self.envs.push((key, value));
+    }
+
+    /// Runs the program with `input` on stdin and returns its output,
+    /// regardless of the exit status.
+    fn run(&self, args: &[&OsStr], input: &[u8]) -> Result<Output, GpgError> {
+        let mut child = Command::new(&self.program)
+            .envs(self.envs.iter().map(|(key, value)| (key, value)))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(GpgError::Spawn)?;
+        // The program may exit without reading all of its input, e.g. on bad
+        // arguments. Its exit status and stderr describe that better.
+        match child.stdin.take().unwrap().write_all(input) {
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Creates a detached signature of `data`.
+    pub fn sign(&self, args: &[&OsStr], data: &[u8]) -> SignResult<Vec<u8>> {
+        let output = self.run(args, data)?;
+        if !output.status.success() {
+            return Err(GpgError::from_output(&output).into());
+        }
+        Ok(output.stdout)
+    }
+
+    /// Verifies a detached signature of `data`. The `args` go before the
+    /// `--verify` argument.
+    pub fn verify(
+        &self,
+        args: &[&OsStr],
+        data: &[u8],
+        signature: &[u8],
+        allow_expired_keys: bool,
+    ) -> SignResult<Verification> {
+        let mut signature_file = tempfile::Builder::new()
+            .prefix("jj-signature-")
+            .tempfile()
+            .map_err(GpgError::Io)?;
+        signature_file
+            .write_all(signature)
+            .and_then(|()| signature_file.flush())
+            .map_err(GpgError::Io)?;
+        let mut args = args.to_vec();
+        args.extend([
+            "--status-fd=1".as_ref(),
+            "--verify".as_ref(),
+            signature_file.path().as_os_str(),
+            "-".as_ref(),
+        ]);
+        // A bad signature makes the program fail, so the status output is what
+        // tells us the result.
+        let output = self.run(&args, data)?;
+        match parse_status(&output.stdout, allow_expired_keys) {
+            Some(verification) => Ok(verification),
+            None if output.status.success() => Err(GpgError::MissingStatus.into()),
+            None => Err(GpgError::from_output(&output).into()),
+        }
+    }
+}
+
+/// Parses the `[GNUPG:]` status lines printed by the verification.
+fn parse_status(output: &[u8], allow_expired_keys: bool) -> Option<Verification> {
+    let output = String::from_utf8_lossy(output);
+    output.lines().find_map(|line| {
+        let line = line.strip_prefix("[GNUPG:] ")?;
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let status = match keyword {
+            "GOODSIG" => SigStatus::Good,
+            "EXPKEYSIG" if allow_expired_keys => SigStatus::Good,
+            "BADSIG" | "EXPKEYSIG" | "REVKEYSIG" => SigStatus::Bad,
+            "ERRSIG" | "NO_PUBKEY" => SigStatus::Unknown,
+            _ => return None,
+        };
+        let mut parts = rest.splitn(2, ' ');
+        let key = parts
+            .next()
+            .filter(|key| !key.is_empty())
+            .map(str::to_owned);
+        let display = match status {
+            SigStatus::Unknown => None,
+            _ => parts.next().map(str::to_owned),
+        };
+        Some(Verification {
+            status,
+            key,
+            display,
+        })
+    })
+}
+
+/// Signs and verifies commits with OpenPGP keys by running `gpg`.
+///
+/// The key to sign with is selected by the `key` passed to
+/// [`SigningBackend::sign`], usually the `signing.key` config. It can be
+/// anything `gpg` accepts for `--local-user`, such as a key ID, fingerprint, or
+/// email address. Without it, `gpg` uses its default key. Passphrases are asked
+/// for by `gpg-agent` through the configured pinentry program.
+#[derive(Debug)]
+pub struct GpgBackend {
+    program: GpgProgram,
+    allow_expired_keys: bool,
+}
+
+impl GpgBackend {
+    /// Creates a backend that runs the given program.
+    pub fn new(program: OsString, allow_expired_keys: bool) -> Self {
+        Self {
+            program: GpgProgram::new(program),
+            allow_expired_keys,
+        }
+    }
+
+    /// Creates a backend from the `signing.backends.gpg` config.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, SignInitError> {
+        let config = settings.config();
+        let program = config
+            .get_string("signing.backends.gpg.program")
+            .optional()
+            .map_err(|e| SignInitError::Backend(Box::new(e)))?
+            .unwrap_or_else(|| "gpg".to_owned());
+        let allow_expired_keys = config
+            .get_bool("signing.backends.gpg.allow-expired-keys")
+            .optional()
+            .map_err(|e| SignInitError::Backend(Box::new(e)))?
+            .unwrap_or(false);
+        Ok(Self::new(program.into(), allow_expired_keys))
+    }
+}
+
+impl SigningBackend for GpgBackend {
+    fn name(&self) -> &str {
+        "gpg"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(SIGNATURE_PREFIX)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let mut args: Vec<&OsStr> = vec!["--keyid-format=long".as_ref(), "-ab".as_ref()];
+        if let Some(key) = key {
+            args.extend::<[&OsStr; 2]>(["--local-user".as_ref(), key.as_ref()]);
+        }
+        self.program.sign(&args, data)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        self.program.verify(
+            &["--keyid-format=long".as_ref()],
+            data,
+            signature,
+            self.allow_expired_keys,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_good() {
+        let output = b"[GNUPG:] NEWSIG\n\
+            [GNUPG:] GOODSIG 0123ABCD /CN=Test User/EMailAddress=test@example.com\n\
+            [GNUPG:] VALIDSIG 0123456789ABCDEF\n";
+        assert_eq!(
+            parse_status(output, false),
+            Some(Verification {
+                status: SigStatus::Good,
+                key: Some("0123ABCD".to_owned()),
+                display: Some("/CN=Test User/EMailAddress=test@example.com".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_status_bad() {
+        let output = b"[GNUPG:] NEWSIG\n[GNUPG:] BADSIG 0123ABCD Test User <test@example.com>\n";
+        assert_eq!(
+            parse_status(output, false),
+            Some(Verification {
+                status: SigStatus::Bad,
+                key: Some("0123ABCD".to_owned()),
+                display: Some("Test User <test@example.com>".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_status_expired() {
+        let output = b"[GNUPG:] EXPKEYSIG 0123ABCD Test User <test@example.com>\n";
+        assert_eq!(
+            parse_status(output, false).map(|v| v.status),
+            Some(SigStatus::Bad)
+        );
+        assert_eq!(
+            parse_status(output, true).map(|v| v.status),
+            Some(SigStatus::Good)
+        );
+    }
+
+    #[test]
+    fn test_parse_status_unknown() {
+        let output = b"[GNUPG:] ERRSIG 0123ABCD 1 8 00 1700000000 9\n";
+        assert_eq!(
+            parse_status(output, false),
+            Some(Verification {
+                status: SigStatus::Unknown,
+                key: Some("0123ABCD".to_owned()),
+                display: None,
+            })
+        );
+        assert_eq!(parse_status(b"gpg: some message\n", false), None);
+    }
+
+    #[test]
+    fn test_can_read() {
+        let backend = GpgBackend::new("gpg".into(), false);
+        assert!(backend.can_read(b"-----BEGIN PGP SIGNATURE-----\n"));
+        assert!(!backend.can_read(b"-----BEGIN SIGNED MESSAGE-----\nMIAG\n"));
+    }
+}
@@ -59,6 +59,7 @@ pub trait IndexStore: Send + Sync + Debug {
         &self,
         index: Box<dyn MutableIndex>,
         op_id: &OperationId,
+        store: &Arc<Store>,
     ) -> Result<Box<dyn ReadonlyIndex>, IndexWriteError>;
 }
 
@@ -123,6 +124,10 @@ pub trait MutableIndex {
     fn add_commit(&mut self, commit: &Commit);
 
     fn merge_in(&mut self, other: &dyn ReadonlyIndex);
+
+    /// Converts the index to a readonly index without writing it to the index
+    /// store.
+    fn into_readonly(self: Box<Self>) -> Box<dyn ReadonlyIndex>;
 }
 
 pub trait ChangeIdIndex: Send + Sync {
@@ -22,20 +22,26 @@
 pub mod content_hash;
 
 pub mod backend;
+pub mod bisect;
+pub mod chunker;
 pub mod commit;
 pub mod commit_builder;
 pub mod conflicts;
+pub mod copies;
 pub mod dag_walk;
 pub mod default_index;
 pub mod default_submodule_store;
 pub mod diff;
 pub mod file_util;
 pub mod files;
+pub mod fileset;
 pub mod fmt_util;
 pub mod fsmonitor;
+pub mod fsync;
 pub mod git;
 pub mod git_backend;
 pub mod gitignore;
+pub mod gpg_signing;
 pub mod hex_util;
 pub mod id_prefix;
 pub mod index;
@@ -60,8 +66,10 @@ pub mod revset_graph;
 pub mod rewrite;
 pub mod settings;
 pub mod signing;
+pub mod sigstore_signing;
 pub mod simple_op_heads_store;
 pub mod simple_op_store;
+pub mod ssh_signing;
 pub mod stacked_table;
 pub mod store;
 pub mod str_util;
@@ -72,3 +80,4 @@ pub mod tree_builder;
 pub mod view;
 pub mod working_copy;
 pub mod workspace;
+pub mod x509_signing;
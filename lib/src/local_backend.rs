@@ -15,25 +15,29 @@
 #![allow(missing_docs)]
 
 use std::any::Any;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use async_trait::async_trait;
 use blake2::{Blake2b512, Digest};
+use pollster::FutureExt as _;
 use prost::Message;
 use tempfile::NamedTempFile;
 
 use crate::backend::{
-    make_root_commit, Backend, BackendError, BackendResult, ChangeId, Commit, CommitId, Conflict,
-    ConflictId, ConflictTerm, FileId, MergedTreeId, MillisSinceEpoch, SecureSig, Signature,
-    SigningFn, SymlinkId, Timestamp, Tree, TreeId, TreeValue,
+    make_root_commit, Backend, BackendError, BackendGcStats, BackendResult, ChangeId, Commit,
+    CommitId, Conflict, ConflictId, ConflictTerm, FileId, MergedTreeId, MillisSinceEpoch,
+    SecureSig, Signature, SigningFn, SymlinkId, Timestamp, Tree, TreeId, TreeValue,
 };
+use crate::chunker::{Chunker, ChunkerParams};
 use crate::content_hash::blake2b_hash;
-use crate::file_util::persist_content_addressed_temp_file;
+use crate::file_util::{create_or_reuse_dir, persist_content_addressed_temp_file};
+use crate::fsync::{Fsync, FsyncTarget};
 use crate::index::Index;
 use crate::merge::MergeBuilder;
 use crate::object_id::ObjectId;
@@ -41,6 +45,10 @@ use crate::repo_path::{RepoPath, RepoPathComponentBuf};
 
 const COMMIT_ID_LENGTH: usize = 64;
 const CHANGE_ID_LENGTH: usize = 16;
+const CHUNK_ID_LENGTH: usize = 64;
+/// Files at least this large are split into content-defined chunks so that
+/// chunks shared between revisions are only stored once.
+const CHUNKED_FILE_THRESHOLD: u64 = 1 << 20;
 
 fn map_not_found_err(err: std::io::Error, id: &impl ObjectId) -> BackendError {
     if err.kind() == std::io::ErrorKind::NotFound {
@@ -68,6 +76,7 @@ pub struct LocalBackend {
     root_commit_id: CommitId,
     root_change_id: ChangeId,
     empty_tree_id: TreeId,
+    fsync: Fsync,
 }
 
 impl LocalBackend {
@@ -75,13 +84,15 @@ impl LocalBackend {
         "local"
     }
 
-    pub fn init(store_path: &Path) -> Self {
+    pub fn init(store_path: &Path, fsync: Fsync) -> Self {
         fs::create_dir(store_path.join("commits")).unwrap();
         fs::create_dir(store_path.join("trees")).unwrap();
         fs::create_dir(store_path.join("files")).unwrap();
+        fs::create_dir(store_path.join("chunked_files")).unwrap();
+        fs::create_dir(store_path.join("chunks")).unwrap();
         fs::create_dir(store_path.join("symlinks")).unwrap();
         fs::create_dir(store_path.join("conflicts")).unwrap();
-        let backend = Self::load(store_path);
+        let backend = Self::load(store_path, fsync);
         let empty_tree_id = backend
             .write_tree(RepoPath::root(), &Tree::default())
             .unwrap();
@@ -89,7 +100,7 @@ impl LocalBackend {
         backend
     }
 
-    pub fn load(store_path: &Path) -> Self {
+    pub fn load(store_path: &Path, fsync: Fsync) -> Self {
         let root_commit_id = CommitId::from_bytes(&[0; COMMIT_ID_LENGTH]);
         let root_change_id = ChangeId::from_bytes(&[0; CHANGE_ID_LENGTH]);
         let empty_tree_id = TreeId::from_hex("482ae5a29fbe856c7272f2071b8b0f0359ee2d89ff392b8a900643fbd0836eccd067b8bf41909e206c90d45d6e7d8b6686b93ecaee5fe1a9060d87b672101310");
@@ -98,13 +109,29 @@ impl LocalBackend {
             root_commit_id,
             root_change_id,
             empty_tree_id,
+            fsync,
         }
     }
 
+    fn persist_object(&self, temp_file: NamedTempFile, path: PathBuf) -> BackendResult<()> {
+        persist_content_addressed_temp_file(temp_file, &path).map_err(to_other_err)?;
+        self.fsync
+            .sync_written_file(FsyncTarget::Objects, &path)
+            .map_err(to_other_err)
+    }
+
     fn file_path(&self, id: &FileId) -> PathBuf {
         self.path.join("files").join(id.hex())
     }
 
+    fn chunked_file_path(&self, id: &FileId) -> PathBuf {
+        self.path.join("chunked_files").join(id.hex())
+    }
+
+    fn chunk_path(&self, chunk_id: &[u8]) -> PathBuf {
+        self.path.join("chunks").join(hex::encode(chunk_id))
+    }
+
     fn symlink_path(&self, id: &SymlinkId) -> PathBuf {
         self.path.join("symlinks").join(id.hex())
     }
@@ -120,6 +147,255 @@ impl LocalBackend {
     fn conflict_path(&self, id: &ConflictId) -> PathBuf {
         self.path.join("conflicts").join(id.hex())
     }
+
+    fn write_whole_file(&self, contents: &mut dyn Read) -> BackendResult<FileId> {
+        let temp_file = NamedTempFile::new_in(&self.path).map_err(to_other_err)?;
+        let mut encoder = zstd::Encoder::new(temp_file.as_file(), 0).map_err(to_other_err)?;
+        let mut hasher = Blake2b512::new();
+        let mut buff: Vec<u8> = vec![0; 1 << 14];
+        loop {
+            let bytes_read = contents.read(&mut buff).map_err(to_other_err)?;
+            if bytes_read == 0 {
+                break;
+            }
+            let bytes = &buff[..bytes_read];
+            encoder.write_all(bytes).map_err(to_other_err)?;
+            hasher.update(bytes);
+        }
+        encoder.finish().map_err(to_other_err)?;
+        let id = FileId::new(hasher.finalize().to_vec());
+
+        self.persist_object(temp_file, self.file_path(&id))?;
+        Ok(id)
+    }
+
+    /// Writes the file as a list of chunks. The file id is still the hash of
+    /// the whole content, so it doesn't matter how the file was stored.
+    fn write_chunked_file(&self, contents: &mut dyn Read) -> BackendResult<FileId> {
+        // The directories don't exist in repos created by older versions.
+        create_or_reuse_dir(&self.path.join("chunks")).map_err(to_other_err)?;
+        create_or_reuse_dir(&self.path.join("chunked_files")).map_err(to_other_err)?;
+        let mut chunker = Chunker::new(contents, ChunkerParams::default());
+        let mut hasher = Blake2b512::new();
+        let mut manifest = vec![];
+        while let Some(chunk) = chunker.next_chunk().map_err(to_other_err)? {
+            hasher.update(&chunk);
+            let chunk_id = Blake2b512::digest(&chunk);
+            // Existing chunks are written again, like any other object, so
+            // that their modification time is renewed and gc doesn't remove
+            // them while the new file is unreachable.
+            let temp_file = NamedTempFile::new_in(&self.path).map_err(to_other_err)?;
+            zstd::stream::copy_encode(chunk.as_slice(), temp_file.as_file(), 0)
+                .map_err(to_other_err)?;
+            self.persist_object(temp_file, self.chunk_path(&chunk_id))?;
+            manifest.extend_from_slice(&chunk_id);
+        }
+        let id = FileId::new(hasher.finalize().to_vec());
+
+        let temp_file = NamedTempFile::new_in(&self.path).map_err(to_other_err)?;
+        temp_file
+            .as_file()
+            .write_all(&manifest)
+            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.chunked_file_path(&id))?;
+        Ok(id)
+    }
+
+    fn read_chunked_file(&self, id: &FileId) -> BackendResult<Box<dyn Read>> {
+        let path = self.chunked_file_path(id);
+        let manifest = fs::read(path).map_err(|err| map_not_found_err(err, id))?;
+        if manifest.len() % CHUNK_ID_LENGTH != 0 {
+            return Err(BackendError::ReadObject {
+                object_type: id.object_type(),
+                hash: id.hex(),
+                source: "Invalid chunk list".into(),
+            });
+        }
+        let chunk_paths = manifest
+            .chunks_exact(CHUNK_ID_LENGTH)
+            .rev()
+            .map(|chunk_id| self.chunk_path(chunk_id))
+            .collect();
+        Ok(Box::new(ChunkedFileReader {
+            chunk_paths,
+            current: None,
+        }))
+    }
+
+    /// Collects the objects reachable from the `heads` through the parents and
+    /// predecessors of commits. Returns the hex ids of the objects by the name
+    /// of the directory they're stored in.
+    fn collect_reachable_objects(
+        &self,
+        heads: Vec<CommitId>,
+    ) -> BackendResult<HashMap<&'static str, HashSet<String>>> {
+        let mut reachable: HashMap<&'static str, HashSet<String>> = HashMap::new();
+        let mut tree_values = vec![TreeValue::Tree(self.empty_tree_id.clone())];
+        let mut visited_commits: HashSet<CommitId> = HashSet::new();
+        let mut commit_ids = heads;
+        while let Some(commit_id) = commit_ids.pop() {
+            if commit_id == self.root_commit_id || !visited_commits.insert(commit_id.clone()) {
+                continue;
+            }
+            let commit = self.read_commit(&commit_id).block_on()?;
+            match &commit.root_tree {
+                MergedTreeId::Legacy(tree_id) => {
+                    tree_values.push(TreeValue::Tree(tree_id.clone()));
+                }
+                MergedTreeId::Merge(tree_ids) => {
+                    tree_values.extend(tree_ids.iter().cloned().map(TreeValue::Tree));
+                }
+            }
+            commit_ids.extend(commit.parents);
+            commit_ids.extend(commit.predecessors);
+            reachable
+                .entry("commits")
+                .or_default()
+                .insert(commit_id.hex());
+        }
+        while let Some(value) = tree_values.pop() {
+            match value {
+                TreeValue::File { id, .. } => {
+                    if !reachable.entry("files").or_default().insert(id.hex()) {
+                        continue;
+                    }
+                    // Large files are stored as chunks instead
+                    reachable
+                        .entry("chunked_files")
+                        .or_default()
+                        .insert(id.hex());
+                    if let Ok(manifest) = fs::read(self.chunked_file_path(&id)) {
+                        let chunks = reachable.entry("chunks").or_default();
+                        for chunk_id in manifest.chunks_exact(CHUNK_ID_LENGTH) {
+                            chunks.insert(hex::encode(chunk_id));
+                        }
+                    }
+                }
+                TreeValue::Symlink(id) => {
+                    reachable.entry("symlinks").or_default().insert(id.hex());
+                }
+                TreeValue::Tree(id) => {
+                    if !reachable.entry("trees").or_default().insert(id.hex()) {
+                        continue;
+                    }
+                    let tree = self.read_tree(RepoPath::root(), &id).block_on()?;
+                    tree_values.extend(tree.entries().map(|entry| entry.value().clone()));
+                }
+                TreeValue::Conflict(id) => {
+                    if !reachable.entry("conflicts").or_default().insert(id.hex()) {
+                        continue;
+                    }
+                    let conflict = self.read_conflict(RepoPath::root(), &id)?;
+                    tree_values.extend(
+                        itertools::chain(conflict.removes, conflict.adds).map(|term| term.value),
+                    );
+                }
+                TreeValue::GitSubmodule(_) => {}
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// Collects the chunks of the chunked files that aren't `reachable` but are
+    /// kept anyway because they're newer than `keep_newer`.
+    fn collect_chunks_of_kept_files(
+        &self,
+        reachable: Option<&HashSet<String>>,
+        keep_newer: SystemTime,
+    ) -> BackendResult<HashSet<String>> {
+        let mut chunks = HashSet::new();
+        // The directory doesn't exist in repos created by older versions.
+        let Ok(entries) = self.path.join("chunked_files").read_dir() else {
+            return Ok(chunks);
+        };
+        for entry in entries {
+            let entry = entry.map_err(to_other_err)?;
+            let Some(name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+                continue;
+            };
+            if hex::decode(&name).is_err() || reachable.is_some_and(|ids| ids.contains(&name)) {
+                continue;
+            }
+            let mtime = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(to_other_err)?;
+            if mtime <= keep_newer {
+                continue;
+            }
+            let manifest = fs::read(entry.path()).map_err(to_other_err)?;
+            chunks.extend(manifest.chunks_exact(CHUNK_ID_LENGTH).map(hex::encode));
+        }
+        Ok(chunks)
+    }
+
+    /// Removes the objects in the directory `dir` that aren't `reachable` and
+    /// are older than `keep_newer`. Returns the number of removed objects.
+    fn prune_objects(
+        &self,
+        dir: &str,
+        reachable: Option<&HashSet<String>>,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<usize> {
+        let dir_path = self.path.join(dir);
+        // The directory doesn't exist in repos created by older versions.
+        let Ok(entries) = dir_path.read_dir() else {
+            return Ok(0);
+        };
+        let mut removed_count = 0;
+        for entry in entries {
+            let entry = entry.map_err(to_other_err)?;
+            let Some(name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+                continue;
+            };
+            if hex::decode(&name).is_err() || reachable.is_some_and(|ids| ids.contains(&name)) {
+                continue;
+            }
+            // Check timestamp, but there's still TOCTOU problem if an existing
+            // file is renewed.
+            let mtime = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(to_other_err)?;
+            if mtime > keep_newer {
+                continue;
+            }
+            if !dry_run {
+                fs::remove_file(entry.path()).map_err(to_other_err)?;
+            }
+            removed_count += 1;
+        }
+        Ok(removed_count)
+    }
+}
+
+/// Streams the content of a chunked file, decoding one chunk at a time.
+struct ChunkedFileReader {
+    /// Paths of the chunks not yet opened, in reverse order.
+    chunk_paths: Vec<PathBuf>,
+    current: Option<zstd::Decoder<'static, BufReader<File>>>,
+}
+
+impl Read for ChunkedFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(decoder) = &mut self.current {
+                let bytes_read = decoder.read(buf)?;
+                if bytes_read > 0 {
+                    return Ok(bytes_read);
+                }
+                self.current = None;
+            }
+            let Some(path) = self.chunk_paths.pop() else {
+                return Ok(0);
+            };
+            self.current = Some(zstd::Decoder::new(File::open(path)?)?);
+        }
+    }
 }
 
 #[async_trait]
@@ -158,30 +434,26 @@ impl Backend for LocalBackend {
 
     async fn read_file(&self, _path: &RepoPath, id: &FileId) -> BackendResult<Box<dyn Read>> {
         let path = self.file_path(id);
-        let file = File::open(path).map_err(|err| map_not_found_err(err, id))?;
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return self.read_chunked_file(id);
+            }
+            Err(err) => return Err(map_not_found_err(err, id)),
+        };
         Ok(Box::new(zstd::Decoder::new(file).map_err(to_other_err)?))
     }
 
     fn write_file(&self, _path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
-        let temp_file = NamedTempFile::new_in(&self.path).map_err(to_other_err)?;
-        let mut encoder = zstd::Encoder::new(temp_file.as_file(), 0).map_err(to_other_err)?;
-        let mut hasher = Blake2b512::new();
-        let mut buff: Vec<u8> = vec![0; 1 << 14];
-        loop {
-            let bytes_read = contents.read(&mut buff).map_err(to_other_err)?;
-            if bytes_read == 0 {
-                break;
-            }
-            let bytes = &buff[..bytes_read];
-            encoder.write_all(bytes).map_err(to_other_err)?;
-            hasher.update(bytes);
-        }
-        encoder.finish().map_err(to_other_err)?;
-        let id = FileId::new(hasher.finalize().to_vec());
-
-        persist_content_addressed_temp_file(temp_file, self.file_path(&id))
+        let mut prefix = vec![];
+        Read::take(&mut *contents, CHUNKED_FILE_THRESHOLD)
+            .read_to_end(&mut prefix)
             .map_err(to_other_err)?;
-        Ok(id)
+        if (prefix.len() as u64) < CHUNKED_FILE_THRESHOLD {
+            self.write_whole_file(&mut prefix.as_slice())
+        } else {
+            self.write_chunked_file(&mut prefix.as_slice().chain(contents))
+        }
     }
 
     async fn read_symlink(&self, _path: &RepoPath, id: &SymlinkId) -> Result<String, BackendError> {
@@ -199,8 +471,7 @@ impl Backend for LocalBackend {
         hasher.update(target.as_bytes());
         let id = SymlinkId::new(hasher.finalize().to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.symlink_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.symlink_path(&id))?;
         Ok(id)
     }
 
@@ -223,8 +494,7 @@ impl Backend for LocalBackend {
 
         let id = TreeId::new(blake2b_hash(tree).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.tree_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.tree_path(&id))?;
         Ok(id)
     }
 
@@ -247,8 +517,7 @@ impl Backend for LocalBackend {
 
         let id = ConflictId::new(blake2b_hash(conflict).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.conflict_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.conflict_path(&id))?;
         Ok(id)
     }
 
@@ -296,13 +565,49 @@ impl Backend for LocalBackend {
 
         let id = CommitId::new(blake2b_hash(&commit).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.commit_path(&id))
-            .map_err(to_other_err)?;
+        self.persist_object(temp_file, self.commit_path(&id))?;
         Ok((id, commit))
     }
 
-    fn gc(&self, _index: &dyn Index, _keep_newer: SystemTime) -> BackendResult<()> {
-        Ok(())
+    #[tracing::instrument(skip(self, index))]
+    fn gc(
+        &self,
+        index: &dyn Index,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<BackendGcStats> {
+        let heads = index.all_heads_for_gc().map_err(to_other_err)?.collect();
+        let mut reachable = self.collect_reachable_objects(heads)?;
+        // The chunks of the files that are kept must be kept too, even if
+        // they're older.
+        let kept_chunks =
+            self.collect_chunks_of_kept_files(reachable.get("chunked_files"), keep_newer)?;
+        reachable.entry("chunks").or_default().extend(kept_chunks);
+        tracing::info!(
+            reachable_commit_count = reachable.get("commits").map_or(0, |ids| ids.len()),
+            "collected reachable objects"
+        );
+        let mut stats = BackendGcStats {
+            removed_commits: self.prune_objects(
+                "commits",
+                reachable.get("commits"),
+                keep_newer,
+                dry_run,
+            )?,
+            removed_objects: 0,
+        };
+        for dir in [
+            "trees",
+            "files",
+            "chunked_files",
+            "chunks",
+            "symlinks",
+            "conflicts",
+        ] {
+            stats.removed_objects +=
+                self.prune_objects(dir, reachable.get(dir), keep_newer, dry_run)?;
+        }
+        Ok(stats)
     }
 }
 
@@ -492,11 +797,15 @@ fn conflict_term_to_proto(part: &ConflictTerm) -> crate::protos::local_store::co
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
+    use std::time::Duration;
+
     use assert_matches::assert_matches;
     use pollster::FutureExt;
 
     use super::*;
     use crate::backend::MillisSinceEpoch;
+    use crate::default_index::DefaultMutableIndex;
 
     /// Test that parents get written correctly
     #[test]
@@ -504,7 +813,7 @@ mod tests {
         let temp_dir = testutils::new_temp_dir();
         let store_path = temp_dir.path();
 
-        let backend = LocalBackend::init(store_path);
+        let backend = LocalBackend::init(store_path, Fsync::default());
         let mut commit = Commit {
             parents: vec![],
             predecessors: vec![],
@@ -548,6 +857,94 @@ mod tests {
         assert_eq!(root_merge_commit, commit);
     }
 
+    #[test]
+    fn write_large_file_chunked() {
+        let temp_dir = testutils::new_temp_dir();
+        let store_path = temp_dir.path();
+        let backend = LocalBackend::init(store_path, Fsync::default());
+        let path = RepoPath::from_internal_string("file");
+        let read_file = |id: &FileId| {
+            let mut content = vec![];
+            let mut reader = backend.read_file(path, id).block_on().unwrap();
+            reader.read_to_end(&mut content).unwrap();
+            content
+        };
+        let count_chunks = || fs::read_dir(store_path.join("chunks")).unwrap().count();
+
+        // Small files are stored whole
+        let small_id = backend.write_file(path, &mut &b"small"[..]).unwrap();
+        assert_eq!(small_id, FileId::new(Blake2b512::digest(b"small").to_vec()));
+        assert_eq!(read_file(&small_id), b"small");
+        assert_eq!(count_chunks(), 0);
+
+        let mut state = 1u64;
+        let content: Vec<u8> = (0..3 * CHUNKED_FILE_THRESHOLD)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect();
+        let id = backend.write_file(path, &mut content.as_slice()).unwrap();
+        assert_eq!(id, FileId::new(Blake2b512::digest(&content).to_vec()));
+        assert!(!backend.file_path(&id).exists());
+        assert!(backend.chunked_file_path(&id).exists());
+        assert_eq!(read_file(&id), content);
+        let num_chunks = count_chunks();
+        assert!(num_chunks > 1);
+
+        // Modifying the middle of the file only adds a few chunks
+        let mut modified_content = content.clone();
+        modified_content.splice(1000000..1000000, b"modified".iter().copied());
+        let modified_id = backend
+            .write_file(path, &mut modified_content.as_slice())
+            .unwrap();
+        assert_ne!(modified_id, id);
+        assert_eq!(read_file(&modified_id), modified_content);
+        assert!(count_chunks() <= num_chunks + 3);
+    }
+
+    #[test]
+    fn gc_keeps_reused_chunks() {
+        let temp_dir = testutils::new_temp_dir();
+        let store_path = temp_dir.path();
+        let backend = LocalBackend::init(store_path, Fsync::default());
+        let path = RepoPath::from_internal_string("file");
+        let index = DefaultMutableIndex::full(COMMIT_ID_LENGTH, CHANGE_ID_LENGTH);
+        let mut state = 1u64;
+        let content: Vec<u8> = (0..3 * CHUNKED_FILE_THRESHOLD)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect();
+
+        // The old file is unreachable and older than the cutoff
+        let old_id = backend.write_file(path, &mut content.as_slice()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let keep_newer = SystemTime::now();
+        thread::sleep(Duration::from_millis(50));
+
+        // The new file shares most of its chunks with the old one
+        let mut new_content = content.clone();
+        new_content.splice(1000000..1000000, b"modified".iter().copied());
+        let new_id = backend
+            .write_file(path, &mut new_content.as_slice())
+            .unwrap();
+
+        for _ in 0..2 {
+            backend.gc(&index, keep_newer, false).unwrap();
+            assert!(!backend.chunked_file_path(&old_id).exists());
+            let mut read_content = vec![];
+            let mut reader = backend.read_file(path, &new_id).block_on().unwrap();
+            reader.read_to_end(&mut read_content).unwrap();
+            assert_eq!(read_content, new_content);
+        }
+    }
+
     fn create_signature() -> Signature {
         Signature {
             name: "Someone".to_string(),
@@ -25,12 +25,13 @@ use std::os::unix::fs::symlink;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
 use std::time::UNIX_EPOCH;
 use std::{fs, iter, mem, slice};
 
-use futures::StreamExt;
+use futures::{stream, StreamExt};
 use itertools::{EitherOrBoth, Itertools};
 use once_cell::unsync::OnceCell;
 use pollster::FutureExt;
@@ -46,25 +47,29 @@ use crate::backend::{
 };
 use crate::commit::Commit;
 use crate::conflicts::{self, materialize_tree_value, MaterializedTreeValue};
+use crate::file_util;
 #[cfg(feature = "watchman")]
 use crate::fsmonitor::watchman;
 use crate::fsmonitor::FsmonitorKind;
+use crate::fsync::{Fsync, FsyncTarget};
 use crate::gitignore::GitIgnoreFile;
-use crate::lock::FileLock;
+use crate::lock::{FileLock, LockBackend};
 use crate::matchers::{
-    DifferenceMatcher, EverythingMatcher, FilesMatcher, IntersectionMatcher, Matcher, PrefixMatcher,
+    DifferenceMatcher, EverythingMatcher, FilesMatcher, IntersectionMatcher, Matcher,
+    PrefixMatcher, Visit,
 };
 use crate::merge::{Merge, MergeBuilder, MergedTreeValue};
 use crate::merged_tree::{MergedTree, MergedTreeBuilder};
 use crate::object_id::ObjectId;
 use crate::op_store::{OperationId, WorkspaceId};
 use crate::repo_path::{RepoPath, RepoPathBuf, RepoPathComponent};
-use crate::settings::HumanByteSize;
+use crate::settings::{HumanByteSize, UserSettings};
 use crate::store::Store;
 use crate::tree::Tree;
 use crate::working_copy::{
-    CheckoutError, CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions,
-    SnapshotProgress, WorkingCopy, WorkingCopyFactory, WorkingCopyStateError,
+    CheckoutError, CheckoutOptions, CheckoutProgress, CheckoutStats, LockedWorkingCopy, ResetError,
+    SnapshotError, SnapshotOptions, WorkingCopy, WorkingCopyFactory, WorkingCopyProgress,
+    WorkingCopyStateError,
 };
 
 #[cfg(unix)]
@@ -221,6 +226,11 @@ impl<'a> FileStates<'a> {
         Self::from_sorted(&self.data[range])
     }
 
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
     /// Returns true if this contains no entries.
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
@@ -289,6 +299,7 @@ pub struct TreeState {
     // Currently only path prefixes
     sparse_patterns: Vec<RepoPathBuf>,
     own_mtime: MillisSinceEpoch,
+    fsync: Fsync,
 
     /// The most recent clock value returned by Watchman. Will only be set if
     /// the repo is configured to use the Watchman filesystem monitor and
@@ -501,6 +512,10 @@ pub enum TreeStateError {
     Fsmonitor(#[source] Box<dyn Error + Send + Sync>),
 }
 
+/// Maximum number of changed paths whose files are prefetched together on
+/// checkout.
+const PREFETCH_BATCH_SIZE: usize = 1000;
+
 impl TreeState {
     pub fn working_copy_path(&self) -> &Path {
         &self.working_copy_path
@@ -526,24 +541,31 @@ impl TreeState {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        fsync: Fsync,
     ) -> Result<TreeState, TreeStateError> {
-        let mut wc = TreeState::empty(store, working_copy_path, state_path);
+        let mut wc = TreeState::empty(store, working_copy_path, state_path, fsync);
         wc.save()?;
         Ok(wc)
     }
 
-    fn empty(store: Arc<Store>, working_copy_path: PathBuf, state_path: PathBuf) -> TreeState {
+    fn empty(
+        store: Arc<Store>,
+        working_copy_path: PathBuf,
+        state_path: PathBuf,
+        fsync: Fsync,
+    ) -> TreeState {
         let tree_id = store.empty_merged_tree_id();
         // Canonicalize the working copy path because "repo/." makes libgit2 think that
         // everything should be ignored
         TreeState {
             store,
-            working_copy_path: working_copy_path.canonicalize().unwrap(),
+            working_copy_path: file_util::canonicalize(&working_copy_path).unwrap(),
             state_path,
             tree_id,
             file_states: FileStatesMap::new(),
             sparse_patterns: vec![RepoPathBuf::root()],
             own_mtime: MillisSinceEpoch(0),
+            fsync,
             watchman_clock: None,
         }
     }
@@ -552,11 +574,12 @@ impl TreeState {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        fsync: Fsync,
     ) -> Result<TreeState, TreeStateError> {
         let tree_state_path = state_path.join("tree_state");
         let file = match File::open(&tree_state_path) {
             Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {
-                return TreeState::init(store, working_copy_path, state_path);
+                return TreeState::init(store, working_copy_path, state_path, fsync);
             }
             Err(err) => {
                 return Err(TreeStateError::ReadTreeState {
@@ -567,7 +590,7 @@ impl TreeState {
             Ok(file) => file,
         };
 
-        let mut wc = TreeState::empty(store, working_copy_path, state_path);
+        let mut wc = TreeState::empty(store, working_copy_path, state_path, fsync);
         wc.read(&tree_state_path, file)?;
         Ok(wc)
     }
@@ -653,6 +676,13 @@ impl TreeState {
                     source: error,
                 }
             })?;
+        self.fsync
+            .sync_written_file(FsyncTarget::WorkingCopy, &target_path)
+            .and_then(|()| self.fsync.sync_pending())
+            .map_err(|err| TreeStateError::PersistTreeState {
+                path: target_path.clone(),
+                source: err,
+            })?;
         Ok(())
     }
 
@@ -747,6 +777,21 @@ impl TreeState {
         let (file_states_tx, file_states_rx) = channel();
         let (present_files_tx, present_files_rx) = channel();
 
+        // With a fsmonitor, only the changed files are visited, so we can't
+        // tell how many there will be.
+        let total = (fsmonitor_matcher.visit(RepoPath::root()) == Visit::AllRecursively)
+            .then(|| self.file_states.all().len());
+        let processed = AtomicUsize::new(0);
+        let file_progress = progress.map(|progress| {
+            move |path: &RepoPath| {
+                progress(WorkingCopyProgress {
+                    path,
+                    processed: processed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                });
+            }
+        });
+
         trace_span!("traverse filesystem").in_scope(|| -> Result<(), SnapshotError> {
             let current_tree = self.current_tree()?;
             let directory_to_visit = DirectoryToVisit {
@@ -762,7 +807,7 @@ impl TreeState {
                 file_states_tx,
                 present_files_tx,
                 directory_to_visit,
-                progress,
+                file_progress.as_ref().map(|x| x as _),
                 max_new_file_size,
             )
         })?;
@@ -835,7 +880,7 @@ impl TreeState {
         file_states_tx: Sender<(RepoPathBuf, FileState)>,
         present_files_tx: Sender<RepoPathBuf>,
         directory_to_visit: DirectoryToVisit,
-        progress: Option<&SnapshotProgress>,
+        progress: Option<&(dyn Fn(&RepoPath) + Sync)>,
         max_new_file_size: u64,
     ) -> Result<(), SnapshotError> {
         let DirectoryToVisit {
@@ -1239,7 +1284,12 @@ impl TreeState {
         Ok(())
     }
 
-    pub fn check_out(&mut self, new_tree: &MergedTree) -> Result<CheckoutStats, CheckoutError> {
+    pub fn check_out(
+        &mut self,
+        new_tree: &MergedTree,
+        options: CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError> {
+        let CheckoutOptions { progress } = options;
         let old_tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => CheckoutError::SourceNotFound {
                 source: Box::new(err),
@@ -1247,7 +1297,12 @@ impl TreeState {
             other => CheckoutError::InternalBackendError(other),
         })?;
         let stats = self
-            .update(&old_tree, new_tree, self.sparse_matcher().as_ref())
+            .update(
+                &old_tree,
+                new_tree,
+                self.sparse_matcher().as_ref(),
+                progress,
+            )
             .block_on()?;
         self.tree_id = new_tree.id();
         Ok(stats)
@@ -1256,7 +1311,9 @@ impl TreeState {
     pub fn set_sparse_patterns(
         &mut self,
         sparse_patterns: Vec<RepoPathBuf>,
+        options: CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
+        let CheckoutOptions { progress } = options;
         let tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => CheckoutError::SourceNotFound {
                 source: Box::new(err),
@@ -1268,9 +1325,11 @@ impl TreeState {
         let added_matcher = DifferenceMatcher::new(&new_matcher, &old_matcher);
         let removed_matcher = DifferenceMatcher::new(&old_matcher, &new_matcher);
         let empty_tree = MergedTree::resolved(Tree::null(self.store.clone(), RepoPathBuf::root()));
-        let added_stats = self.update(&empty_tree, &tree, &added_matcher).block_on()?;
+        let added_stats = self
+            .update(&empty_tree, &tree, &added_matcher, progress)
+            .block_on()?;
         let removed_stats = self
-            .update(&tree, &empty_tree, &removed_matcher)
+            .update(&tree, &empty_tree, &removed_matcher, progress)
             .block_on()?;
         self.sparse_patterns = sparse_patterns;
         assert_eq!(added_stats.updated_files, 0);
@@ -1291,6 +1350,7 @@ impl TreeState {
         old_tree: &MergedTree,
         new_tree: &MergedTree,
         matcher: &dyn Matcher,
+        progress: Option<&CheckoutProgress<'_>>,
     ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: maybe it's better not include the skipped counts in the "intended"
         // counts
@@ -1302,9 +1362,36 @@ impl TreeState {
         };
         let mut changed_file_states = Vec::new();
         let mut deleted_files = HashSet::new();
+        // Count the files to update for the progress first. This only reads
+        // the trees, which are cached for the diff below, so it's cheap
+        // compared to materializing the files.
+        let total = match progress {
+            Some(_) => Some(old_tree.diff_stream(new_tree, matcher).count().await),
+            None => None,
+        };
+        // Let the backend fetch the files in batches if they're stored
+        // remotely.
         let mut diff_stream = Box::pin(
             old_tree
                 .diff_stream(new_tree, matcher)
+                .ready_chunks(PREFETCH_BATCH_SIZE)
+                .flat_map(|mut diffs| {
+                    let file_ids = diffs
+                        .iter()
+                        .filter_map(|(_, diff)| diff.as_ref().ok())
+                        .flat_map(|(_, after)| after.iter().flatten())
+                        .filter_map(|value| match value {
+                            TreeValue::File { id, .. } => Some(id.clone()),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    if let Err(err) = self.store.prefetch_files(&file_ids) {
+                        // Fail at the first path of the batch
+                        diffs.truncate(1);
+                        diffs[0].1 = Err(err);
+                    }
+                    stream::iter(diffs)
+                })
                 .map(|(path, diff)| async {
                     match diff {
                         Ok((before, after)) => {
@@ -1316,8 +1403,17 @@ impl TreeState {
                 })
                 .buffered(self.store.concurrency().max(1)),
         );
+        let mut processed = 0;
         while let Some((path, data)) = diff_stream.next().await {
             let (present_before, after) = data?;
+            processed += 1;
+            if let Some(progress) = progress {
+                progress(WorkingCopyProgress {
+                    path: &path,
+                    processed,
+                    total,
+                });
+            }
             if after.is_absent() {
                 stats.removed_files += 1;
             } else if !present_before {
@@ -1456,6 +1552,8 @@ pub struct LocalWorkingCopy {
     store: Arc<Store>,
     working_copy_path: PathBuf,
     state_path: PathBuf,
+    lock_backend: LockBackend,
+    fsync: Fsync,
     checkout_state: OnceCell<CheckoutState>,
     tree_state: OnceCell<TreeState>,
 }
@@ -1490,13 +1588,52 @@ impl WorkingCopy for LocalWorkingCopy {
     }
 
     fn start_mutation(&self) -> Result<Box<dyn LockedWorkingCopy>, WorkingCopyStateError> {
-        let lock_path = self.state_path.join("working_copy.lock");
-        let lock = FileLock::lock(lock_path);
+        let lock = self.lock()?;
+        self.start_mutation_with_lock(lock)
+    }
+
+    fn try_start_mutation(
+        &self,
+    ) -> Result<Option<Box<dyn LockedWorkingCopy>>, WorkingCopyStateError> {
+        let lock = FileLock::try_lock(self.lock_path(), self.lock_backend).map_err(|err| {
+            WorkingCopyStateError {
+                message: "Failed to lock the working copy".to_string(),
+                err: err.into(),
+            }
+        })?;
+        let Some(lock) = lock else {
+            return Ok(None);
+        };
+        self.start_mutation_with_lock(lock).map(Some)
+    }
+}
+
+impl LocalWorkingCopy {
+    pub fn name() -> &'static str {
+        "local"
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.state_path.join("working_copy.lock")
+    }
+
+    fn lock(&self) -> Result<FileLock, WorkingCopyStateError> {
+        FileLock::lock(self.lock_path(), self.lock_backend).map_err(|err| WorkingCopyStateError {
+            message: "Failed to lock the working copy".to_string(),
+            err: err.into(),
+        })
+    }
 
+    fn start_mutation_with_lock(
+        &self,
+        lock: FileLock,
+    ) -> Result<Box<dyn LockedWorkingCopy>, WorkingCopyStateError> {
         let wc = LocalWorkingCopy {
             store: self.store.clone(),
             working_copy_path: self.working_copy_path.clone(),
             state_path: self.state_path.clone(),
+            lock_backend: self.lock_backend,
+            fsync: self.fsync.clone(),
             // Empty so we re-read the state after taking the lock
             checkout_state: OnceCell::new(),
             // TODO: It's expensive to reload the whole tree. We should copy it from `self` if it
@@ -1513,12 +1650,6 @@ impl WorkingCopy for LocalWorkingCopy {
             tree_state_dirty: false,
         }))
     }
-}
-
-impl LocalWorkingCopy {
-    pub fn name() -> &'static str {
-        "local"
-    }
 
     /// Initializes a new working copy at `working_copy_path`. The working
     /// copy's state will be stored in the `state_path` directory. The working
@@ -1529,6 +1660,8 @@ impl LocalWorkingCopy {
         state_path: PathBuf,
         operation_id: OperationId,
         workspace_id: WorkspaceId,
+        lock_backend: LockBackend,
+        fsync: Fsync,
     ) -> Result<LocalWorkingCopy, WorkingCopyStateError> {
         let proto = crate::protos::working_copy::Checkout {
             operation_id: operation_id.to_bytes(),
@@ -1540,17 +1673,22 @@ impl LocalWorkingCopy {
             .open(state_path.join("checkout"))
             .unwrap();
         file.write_all(&proto.encode_to_vec()).unwrap();
-        let tree_state =
-            TreeState::init(store.clone(), working_copy_path.clone(), state_path.clone()).map_err(
-                |err| WorkingCopyStateError {
-                    message: "Failed to initialize working copy state".to_string(),
-                    err: err.into(),
-                },
-            )?;
+        let tree_state = TreeState::init(
+            store.clone(),
+            working_copy_path.clone(),
+            state_path.clone(),
+            fsync.clone(),
+        )
+        .map_err(|err| WorkingCopyStateError {
+            message: "Failed to initialize working copy state".to_string(),
+            err: err.into(),
+        })?;
         Ok(LocalWorkingCopy {
             store,
             working_copy_path,
             state_path,
+            lock_backend,
+            fsync,
             checkout_state: OnceCell::new(),
             tree_state: OnceCell::with_value(tree_state),
         })
@@ -1560,11 +1698,15 @@ impl LocalWorkingCopy {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        lock_backend: LockBackend,
+        fsync: Fsync,
     ) -> LocalWorkingCopy {
         LocalWorkingCopy {
             store,
             working_copy_path,
             state_path,
+            lock_backend,
+            fsync,
             checkout_state: OnceCell::new(),
             tree_state: OnceCell::new(),
         }
@@ -1582,7 +1724,12 @@ impl LocalWorkingCopy {
             .unwrap();
         // TODO: Retry if persisting fails (it will on Windows if the file happened to
         // be open for read).
-        temp_file.persist(self.state_path.join("checkout")).unwrap();
+        let path = self.state_path.join("checkout");
+        temp_file.persist(&path).unwrap();
+        self.fsync
+            .sync_written_file(FsyncTarget::WorkingCopy, &path)
+            .unwrap();
+        self.fsync.sync_pending().unwrap();
     }
 
     fn checkout_state(&self) -> &CheckoutState {
@@ -1615,6 +1762,7 @@ impl LocalWorkingCopy {
                     self.store.clone(),
                     self.working_copy_path.clone(),
                     self.state_path.clone(),
+                    self.fsync.clone(),
                 )
             })
             .map_err(|err| WorkingCopyStateError {
@@ -1640,6 +1788,59 @@ impl LocalWorkingCopy {
         });
     }
 
+    /// Rebuilds the working copy state from the tree of `commit`, discarding
+    /// the recorded state even if it's missing or can't be read. The files on
+    /// disk aren't touched; they're compared against the commit's tree the
+    /// next time the working copy is snapshotted, so untracked files are
+    /// preserved.
+    ///
+    /// The state cached in `self` isn't updated. Load the working copy again to
+    /// see the new state.
+    pub fn recover(
+        &self,
+        commit: &Commit,
+        operation_id: OperationId,
+        workspace_id: WorkspaceId,
+    ) -> Result<(), WorkingCopyStateError> {
+        let _lock = self.lock()?;
+
+        let mut tree_state = TreeState::empty(
+            self.store.clone(),
+            self.working_copy_path.clone(),
+            self.state_path.clone(),
+            self.fsync.clone(),
+        );
+        // Keep the sparse patterns if the old state can still be read
+        if let Ok(old_tree_state) = TreeState::load(
+            self.store.clone(),
+            self.working_copy_path.clone(),
+            self.state_path.clone(),
+            self.fsync.clone(),
+        ) {
+            tree_state.sparse_patterns = old_tree_state.sparse_patterns;
+        }
+        let new_tree = commit.tree().map_err(|err| WorkingCopyStateError {
+            message: format!("Failed to read the tree of commit {}", commit.id().hex()),
+            err: err.into(),
+        })?;
+        tree_state
+            .reset(&new_tree)
+            .block_on()
+            .map_err(|err| WorkingCopyStateError {
+                message: "Failed to rebuild the working copy state".to_string(),
+                err: err.into(),
+            })?;
+        tree_state.save().map_err(|err| WorkingCopyStateError {
+            message: "Failed to write the working copy state".to_string(),
+            err: err.into(),
+        })?;
+        self.write_proto(crate::protos::working_copy::Checkout {
+            operation_id: operation_id.to_bytes(),
+            workspace_id: workspace_id.as_str().to_string(),
+        });
+        Ok(())
+    }
+
     #[cfg(feature = "watchman")]
     pub fn query_watchman(
         &self,
@@ -1663,13 +1864,22 @@ impl WorkingCopyFactory for LocalWorkingCopyFactory {
         state_path: PathBuf,
         operation_id: OperationId,
         workspace_id: WorkspaceId,
+        settings: &UserSettings,
     ) -> Result<Box<dyn WorkingCopy>, WorkingCopyStateError> {
+        let lock_backend = settings
+            .lock_backend()
+            .map_err(|err| WorkingCopyStateError {
+                message: "Invalid lock backend".to_string(),
+                err: err.into(),
+            })?;
         Ok(Box::new(LocalWorkingCopy::init(
             store,
             working_copy_path,
             state_path,
             operation_id,
             workspace_id,
+            lock_backend,
+            settings.fsync().clone(),
         )?))
     }
 
@@ -1678,8 +1888,17 @@ impl WorkingCopyFactory for LocalWorkingCopyFactory {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        settings: &UserSettings,
     ) -> Box<dyn WorkingCopy> {
-        Box::new(LocalWorkingCopy::load(store, working_copy_path, state_path))
+        // The lock backend is validated when the settings are loaded.
+        let lock_backend = settings.lock_backend().unwrap_or_default();
+        Box::new(LocalWorkingCopy::load(
+            store,
+            working_copy_path,
+            state_path,
+            lock_backend,
+            settings.fsync().clone(),
+        ))
     }
 }
 
@@ -1723,7 +1942,11 @@ impl LockedWorkingCopy for LockedLocalWorkingCopy {
         Ok(tree_state.current_tree_id().clone())
     }
 
-    fn check_out(&mut self, commit: &Commit) -> Result<CheckoutStats, CheckoutError> {
+    fn check_out(
+        &mut self,
+        commit: &Commit,
+        options: CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: Write a "pending_checkout" file with the new TreeId so we can
         // continue an interrupted update if we find such a file.
         let new_tree = commit.tree()?;
@@ -1734,7 +1957,7 @@ impl LockedWorkingCopy for LockedLocalWorkingCopy {
                 message: "Failed to load the working copy state".to_string(),
                 err: err.into(),
             })?
-            .check_out(&new_tree)?;
+            .check_out(&new_tree, options)?;
         self.tree_state_dirty = true;
         Ok(stats)
     }
@@ -1759,6 +1982,7 @@ impl LockedWorkingCopy for LockedLocalWorkingCopy {
     fn set_sparse_patterns(
         &mut self,
         new_sparse_patterns: Vec<RepoPathBuf>,
+        options: CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: Write a "pending_checkout" file with new sparse patterns so we can
         // continue an interrupted update if we find such a file.
@@ -1769,7 +1993,7 @@ impl LockedWorkingCopy for LockedLocalWorkingCopy {
                 message: "Failed to load the working copy state".to_string(),
                 err: err.into(),
             })?
-            .set_sparse_patterns(new_sparse_patterns)?;
+            .set_sparse_patterns(new_sparse_patterns, options)?;
         self.tree_state_dirty = true;
         Ok(stats)
     }
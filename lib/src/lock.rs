@@ -14,11 +14,138 @@
 
 #![allow(missing_docs)]
 
-#[cfg_attr(unix, path = "lock/unix.rs")]
-#[cfg_attr(not(unix), path = "lock/fallback.rs")]
-mod platform;
+mod fallback;
+#[cfg(unix)]
+mod unix;
 
-pub use platform::FileLock;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// How lock files are held.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LockBackend {
+    /// `flock()` on Unix, and `LockFile` elsewhere.
+    #[default]
+    Native,
+    /// Exclusively creates the lock file, and keeps updating it while the lock
+    /// is held so that locks left behind by killed processes can be detected.
+    /// Unlike `flock()`, this works on network file systems such as NFS.
+    LockFile,
+}
+
+impl FromStr for LockBackend {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(Self::Native),
+            "lockfile" => Ok(Self::LockFile),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown lock backend: {other}"
+            ))),
+        }
+    }
+}
+
+/// Error while taking a lock.
+#[derive(Debug, Error)]
+pub enum FileLockError {
+    /// The lock file was left behind by a process that no longer holds it.
+    #[error("{}", stale_lock_message(path, holder, *age))]
+    Stale {
+        path: PathBuf,
+        /// The process recorded in the lock file, as `<pid>@<hostname>`.
+        holder: String,
+        /// How long ago the lock file was last updated, if known.
+        age: Option<Duration>,
+    },
+    #[error("Failed to lock {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+}
+
+fn lock_io_error(path: &Path, err: io::Error) -> FileLockError {
+    FileLockError::Io {
+        path: path.to_owned(),
+        err,
+    }
+}
+
+fn stale_lock_message(path: &Path, holder: &str, age: Option<Duration>) -> String {
+    let mut message = format!("Lock file {} is no longer being updated", path.display());
+    if !holder.is_empty() {
+        message.push_str(&format!(" by its holder (process {holder})"));
+    }
+    if let Some(age) = age {
+        message.push_str(&format!(", and was last updated {}s ago", age.as_secs()));
+    }
+    message.push_str(
+        ". The process holding it was probably killed. If no jj process is using the repository, \
+         remove the lock file and try again.",
+    );
+    message
+}
+
+pub struct FileLock {
+    _inner: FileLockImpl,
+}
+
+// The locks are released when dropped.
+enum FileLockImpl {
+    #[cfg(unix)]
+    Flock {
+        _lock: unix::FileLock,
+    },
+    LockFile {
+        _lock: fallback::FileLock,
+    },
+}
+
+impl FileLock {
+    /// Waits for the lock at `path` to be released and takes it.
+    ///
+    /// With `LockBackend::LockFile`, fails if the lock file was left behind by
+    /// a process that no longer holds it.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn lock(path: PathBuf, backend: LockBackend) -> Result<FileLock, FileLockError> {
+        #[cfg(unix)]
+        if backend == LockBackend::Native {
+            let _lock = unix::FileLock::lock(path)?;
+            return Ok(FileLock {
+                _inner: FileLockImpl::Flock { _lock },
+            });
+        }
+        let _lock = fallback::FileLock::lock(path)?;
+        Ok(FileLock {
+            _inner: FileLockImpl::LockFile { _lock },
+        })
+    }
+
+    /// Like `lock()`, but returns `None` instead of waiting if the lock is
+    /// already held.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn try_lock(
+        path: PathBuf,
+        backend: LockBackend,
+    ) -> Result<Option<FileLock>, FileLockError> {
+        #[cfg(unix)]
+        if backend == LockBackend::Native {
+            return Ok(unix::FileLock::try_lock(path)?.map(|_lock| FileLock {
+                _inner: FileLockImpl::Flock { _lock },
+            }));
+        }
+        Ok(fallback::FileLock::try_lock(path)?.map(|_lock| FileLock {
+            _inner: FileLockImpl::LockFile { _lock },
+        }))
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -26,22 +153,46 @@ mod tests {
     use std::time::Duration;
     use std::{fs, thread};
 
+    use assert_matches::assert_matches;
+    use test_case::test_case;
+
     use super::*;
 
-    #[test]
-    fn lock_basic() {
+    #[test_case(LockBackend::Native ; "native")]
+    #[test_case(LockBackend::LockFile ; "lock file")]
+    fn lock_basic(backend: LockBackend) {
         let temp_dir = testutils::new_temp_dir();
         let lock_path = temp_dir.path().join("test.lock");
         assert!(!lock_path.exists());
         {
-            let _lock = FileLock::lock(lock_path.clone());
+            let _lock = FileLock::lock(lock_path.clone(), backend).unwrap();
             assert!(lock_path.exists());
         }
         assert!(!lock_path.exists());
     }
 
-    #[test]
-    fn lock_concurrent() {
+    #[test_case(LockBackend::Native ; "native")]
+    #[test_case(LockBackend::LockFile ; "lock file")]
+    fn lock_try(backend: LockBackend) {
+        let temp_dir = testutils::new_temp_dir();
+        let lock_path = temp_dir.path().join("test.lock");
+        {
+            let _lock = FileLock::try_lock(lock_path.clone(), backend)
+                .unwrap()
+                .unwrap();
+            assert!(FileLock::try_lock(lock_path.clone(), backend)
+                .unwrap()
+                .is_none());
+        }
+        assert!(!lock_path.exists());
+        assert!(FileLock::try_lock(lock_path.clone(), backend)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test_case(LockBackend::Native ; "native")]
+    #[test_case(LockBackend::LockFile ; "lock file")]
+    fn lock_concurrent(backend: LockBackend) {
         let temp_dir = testutils::new_temp_dir();
         let data_path = temp_dir.path().join("test");
         let lock_path = temp_dir.path().join("test.lock");
@@ -50,7 +201,7 @@ mod tests {
         thread::scope(|s| {
             for _ in 0..num_threads {
                 s.spawn(|| {
-                    let _lock = FileLock::lock(lock_path.clone());
+                    let _lock = FileLock::lock(lock_path.clone(), backend).unwrap();
                     let data = fs::read(&data_path).unwrap();
                     let value = u32::from_le_bytes(data.try_into().unwrap());
                     thread::sleep(Duration::from_millis(1));
@@ -62,4 +213,49 @@ mod tests {
         let value = u32::from_le_bytes(data.try_into().unwrap());
         assert_eq!(value, num_threads as u32);
     }
+
+    #[test]
+    fn lock_file_heartbeat() {
+        let temp_dir = testutils::new_temp_dir();
+        let lock_path = temp_dir.path().join("test.lock");
+        let _lock = FileLock::lock(lock_path.clone(), LockBackend::LockFile).unwrap();
+        let contents = fs::read_to_string(&lock_path).unwrap();
+        assert!(contents.starts_with(&format!("{}@", std::process::id())));
+        assert!(contents.ends_with("\n0\n"));
+        thread::sleep(Duration::from_millis(2500));
+        let new_contents = fs::read_to_string(&lock_path).unwrap();
+        assert_ne!(new_contents, contents);
+        assert_eq!(new_contents.lines().next(), contents.lines().next());
+    }
+
+    #[test]
+    fn lock_file_stale() {
+        let temp_dir = testutils::new_temp_dir();
+        let lock_path = temp_dir.path().join("test.lock");
+        fs::write(&lock_path, "12345@host\n").unwrap();
+        let err = fallback::FileLock::lock_with_stale_timeout(
+            lock_path.clone(),
+            Duration::from_millis(100),
+        )
+        .err()
+        .unwrap();
+        assert_matches!(
+            err,
+            FileLockError::Stale { path, holder, .. } if path == lock_path && holder == "12345@host"
+        );
+        assert!(lock_path.exists());
+    }
+
+    #[test]
+    fn parse_lock_backend() {
+        assert_eq!(
+            "native".parse::<LockBackend>().unwrap(),
+            LockBackend::Native
+        );
+        assert_eq!(
+            "lockfile".parse::<LockBackend>().unwrap(),
+            LockBackend::LockFile
+        );
+        assert!("flock".parse::<LockBackend>().is_err());
+    }
 }
@@ -13,60 +13,145 @@
 // limitations under the License.
 
 use std::fs::{File, OpenOptions};
-use std::path::PathBuf;
-use std::time::Duration;
+use std::io::{Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, io};
 
-use backoff::{retry, ExponentialBackoff};
 use tracing::instrument;
 
+use super::{lock_io_error, FileLockError};
+
+/// How often the holder of a lock increments the counter in the lock file.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the counter in the lock file can go without an update before the
+/// lock is considered to be left behind by a killed process. This is measured
+/// by the waiting process, so the clocks of different machines sharing the file
+/// system don't matter. The contents are compared rather than the mtime, since
+/// NFS clients cache file attributes for up to a minute, but revalidate the
+/// contents when the file is opened.
+const STALE_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct FileLock {
     path: PathBuf,
     _file: File,
+    heartbeat: Option<(mpsc::Sender<()>, JoinHandle<()>)>,
 }
 
 impl FileLock {
-    pub fn lock(path: PathBuf) -> FileLock {
-        let mut options = OpenOptions::new();
-        options.create_new(true);
-        options.write(true);
-        let try_write_lock_file = || match options.open(&path) {
-            Ok(file) => Ok(FileLock {
-                path: path.clone(),
-                _file: file,
-            }),
-            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
-                Err(backoff::Error::Transient {
-                    err,
-                    retry_after: None,
-                })
+    pub fn lock(path: PathBuf) -> Result<FileLock, FileLockError> {
+        Self::lock_with_stale_timeout(path, STALE_LOCK_TIMEOUT)
+    }
+
+    pub(super) fn lock_with_stale_timeout(
+        path: PathBuf,
+        stale_timeout: Duration,
+    ) -> Result<FileLock, FileLockError> {
+        let mut last_contents = None;
+        let mut last_change = Instant::now();
+        let mut interval = Duration::from_millis(1);
+        loop {
+            if let Some(file_lock) = create_lock_file(&path)? {
+                return Ok(file_lock);
             }
-            Err(err) if cfg!(windows) && err.kind() == std::io::ErrorKind::PermissionDenied => {
-                Err(backoff::Error::Transient {
-                    err,
-                    retry_after: None,
-                })
+            // The lock file may have been removed since then, which is fine.
+            let contents = fs::read(&path).ok();
+            if contents != last_contents {
+                last_contents = contents;
+                last_change = Instant::now();
+            } else if last_change.elapsed() > stale_timeout {
+                return Err(stale_lock_error(path));
+            }
+            thread::sleep(interval);
+            interval = (interval * 2).min(HEARTBEAT_INTERVAL);
+        }
+    }
+
+    /// Like `lock()`, but returns `None` instead of waiting if the lock is
+    /// already held.
+    pub fn try_lock(path: PathBuf) -> Result<Option<FileLock>, FileLockError> {
+        create_lock_file(&path)
+    }
+}
+
+/// Creates the lock file, or returns `None` if it already exists.
+fn create_lock_file(path: &Path) -> Result<Option<FileLock>, FileLockError> {
+    let mut options = OpenOptions::new();
+    options.create_new(true);
+    options.write(true);
+    let mut file = match options.open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => return Ok(None),
+        Err(err) if cfg!(windows) && err.kind() == io::ErrorKind::PermissionDenied => {
+            return Ok(None)
+        }
+        Err(err) => return Err(lock_io_error(path, err)),
+    };
+    // Record the holder so that it can be identified if the lock is left
+    // behind, followed by the heartbeat counter.
+    let holder = format!("{}@{}", std::process::id(), whoami::hostname());
+    file.write_all(format!("{holder}\n0\n").as_bytes())
+        .map_err(|err| lock_io_error(path, err))?;
+    let heartbeat = match file.try_clone() {
+        Ok(file) => Some(spawn_heartbeat(file, holder)),
+        // Without heartbeats, waiters will eventually consider the lock stale,
+        // but that's better than failing.
+        Err(_) => None,
+    };
+    Ok(Some(FileLock {
+        path: path.to_owned(),
+        _file: file,
+        heartbeat,
+    }))
+}
+
+/// Keeps incrementing the counter in the lock file, until the sender is
+/// dropped. The counter only grows, so rewriting the file from the start
+/// leaves no stale bytes behind.
+fn spawn_heartbeat(mut file: File, holder: String) -> (mpsc::Sender<()>, JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let mut counter: u64 = 0;
+        while let Err(mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(HEARTBEAT_INTERVAL) {
+            counter += 1;
+            // Flush the write so that it reaches the server of network file
+            // systems.
+            let result = file
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| file.write_all(format!("{holder}\n{counter}\n").as_bytes()))
+                .and_then(|_| file.sync_data());
+            if let Err(err) = result {
+                tracing::warn!(?err, "failed to update lock file");
             }
-            Err(err) => Err(backoff::Error::Permanent(err)),
-        };
-        let backoff = ExponentialBackoff {
-            initial_interval: Duration::from_millis(1),
-            max_elapsed_time: Some(Duration::from_secs(10)),
-            ..Default::default()
-        };
-        match retry(backoff, try_write_lock_file) {
-            Err(err) => panic!(
-                "failed to create lock file {}: {}",
-                path.to_string_lossy(),
-                err
-            ),
-            Ok(file_lock) => file_lock,
         }
+    });
+    (sender, handle)
+}
+
+fn stale_lock_error(path: PathBuf) -> FileLockError {
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let holder = contents.lines().next().unwrap_or_default();
+    let age = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    FileLockError::Stale {
+        path,
+        holder: holder.trim().to_owned(),
+        age,
     }
 }
 
 impl Drop for FileLock {
     #[instrument(skip_all)]
     fn drop(&mut self) {
+        if let Some((sender, handle)) = self.heartbeat.take() {
+            drop(sender);
+            handle.join().ok();
+        }
         std::fs::remove_file(&self.path).expect("failed to delete lock file");
     }
 }
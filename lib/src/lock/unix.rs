@@ -20,21 +20,23 @@ use std::path::PathBuf;
 use rustix::fs::FlockOperation;
 use tracing::instrument;
 
+use super::{lock_io_error, FileLockError};
+
 pub struct FileLock {
     path: PathBuf,
     file: File,
 }
 
 impl FileLock {
-    pub fn lock(path: PathBuf) -> FileLock {
+    pub fn lock(path: PathBuf) -> Result<FileLock, FileLockError> {
         loop {
             // Create lockfile, or open pre-existing one
-            let file = File::create(&path).expect("failed to open lockfile");
+            let file = File::create(&path).map_err(|err| lock_io_error(&path, err))?;
             // If the lock was already held, wait for it to be released
             rustix::fs::flock(&file, FlockOperation::LockExclusive)
-                .expect("failed to lock lockfile");
+                .map_err(|err| lock_io_error(&path, err.into()))?;
 
-            let stat = rustix::fs::fstat(&file).expect("failed to stat lockfile");
+            let stat = rustix::fs::fstat(&file).map_err(|err| lock_io_error(&path, err.into()))?;
             if stat.st_nlink == 0 {
                 // Lockfile was deleted, probably by the previous holder's `Drop` impl; create a
                 // new one so our ownership is visible, rather than hidden in an
@@ -43,7 +45,28 @@ impl FileLock {
                 continue;
             }
 
-            return Self { path, file };
+            return Ok(Self { path, file });
+        }
+    }
+
+    /// Like `lock()`, but returns `None` instead of waiting if the lock is
+    /// already held.
+    pub fn try_lock(path: PathBuf) -> Result<Option<FileLock>, FileLockError> {
+        loop {
+            let file = File::create(&path).map_err(|err| lock_io_error(&path, err))?;
+            match rustix::fs::flock(&file, FlockOperation::NonBlockingLockExclusive) {
+                Ok(()) => {}
+                Err(rustix::io::Errno::WOULDBLOCK) => return Ok(None),
+                Err(err) => return Err(lock_io_error(&path, err.into())),
+            }
+
+            let stat = rustix::fs::fstat(&file).map_err(|err| lock_io_error(&path, err.into()))?;
+            if stat.st_nlink == 0 {
+                // See lock()
+                continue;
+            }
+
+            return Ok(Some(Self { path, file }));
         }
     }
 }
@@ -19,7 +19,7 @@ use std::iter;
 
 use tracing::instrument;
 
-use crate::repo_path::{RepoPath, RepoPathComponentBuf};
+use crate::repo_path::{RepoPath, RepoPathBuf, RepoPathComponentBuf};
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Visit {
@@ -72,6 +72,26 @@ pub trait Matcher: Sync {
     fn visit(&self, dir: &RepoPath) -> Visit;
 }
 
+impl<T: Matcher + ?Sized> Matcher for &T {
+    fn matches(&self, file: &RepoPath) -> bool {
+        <T as Matcher>::matches(self, file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        <T as Matcher>::visit(self, dir)
+    }
+}
+
+impl<T: Matcher + ?Sized> Matcher for Box<T> {
+    fn matches(&self, file: &RepoPath) -> bool {
+        <T as Matcher>::matches(self, file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        <T as Matcher>::visit(self, dir)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub struct NothingMatcher;
 
@@ -160,22 +180,125 @@ impl Matcher for PrefixMatcher {
     }
 }
 
+/// Matches file paths with glob patterns, each relative to a directory.
+///
+/// Wildcards don't match path separators, except for `**`.
+#[derive(Clone, Debug)]
+pub struct FileGlobsMatcher {
+    globs: Vec<(RepoPathBuf, glob::Pattern)>,
+}
+
+impl FileGlobsMatcher {
+    pub fn new(globs: impl IntoIterator<Item = (RepoPathBuf, glob::Pattern)>) -> Self {
+        FileGlobsMatcher {
+            globs: globs.into_iter().collect(),
+        }
+    }
+}
+
+impl Matcher for FileGlobsMatcher {
+    fn matches(&self, file: &RepoPath) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        self.globs.iter().any(|(dir, pattern)| {
+            file.strip_prefix(dir)
+                .is_some_and(|tail| pattern.matches_with(tail.as_internal_file_string(), options))
+        })
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        let mut dirs = HashSet::new();
+        for (glob_dir, _) in &self.globs {
+            if dir.starts_with(glob_dir) {
+                return Visit::Specific {
+                    dirs: VisitDirs::All,
+                    files: VisitFiles::All,
+                };
+            }
+            if let Some(name) = glob_dir
+                .strip_prefix(dir)
+                .and_then(|tail| tail.components().next())
+            {
+                dirs.insert(name.to_owned());
+            }
+        }
+        Visit::sets(dirs, HashSet::new())
+    }
+}
+
+/// Matches paths that are matched by any of the input matchers.
+#[derive(Clone, Debug)]
+pub struct UnionMatcher<M1, M2> {
+    input1: M1,
+    input2: M2,
+}
+
+impl<M1: Matcher, M2: Matcher> UnionMatcher<M1, M2> {
+    pub fn new(input1: M1, input2: M2) -> Self {
+        Self { input1, input2 }
+    }
+}
+
+impl<M1: Matcher, M2: Matcher> Matcher for UnionMatcher<M1, M2> {
+    fn matches(&self, file: &RepoPath) -> bool {
+        self.input1.matches(file) || self.input2.matches(file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        match self.input1.visit(dir) {
+            Visit::AllRecursively => Visit::AllRecursively,
+            Visit::Nothing => self.input2.visit(dir),
+            Visit::Specific {
+                dirs: dirs1,
+                files: files1,
+            } => match self.input2.visit(dir) {
+                Visit::AllRecursively => Visit::AllRecursively,
+                Visit::Nothing => Visit::Specific {
+                    dirs: dirs1,
+                    files: files1,
+                },
+                Visit::Specific {
+                    dirs: dirs2,
+                    files: files2,
+                } => {
+                    let dirs = match (dirs1, dirs2) {
+                        (VisitDirs::Set(dirs1), VisitDirs::Set(dirs2)) => {
+                            VisitDirs::Set(dirs1.union(&dirs2).cloned().collect())
+                        }
+                        _ => VisitDirs::All,
+                    };
+                    let files = match (files1, files2) {
+                        (VisitFiles::Set(files1), VisitFiles::Set(files2)) => {
+                            VisitFiles::Set(files1.union(&files2).cloned().collect())
+                        }
+                        _ => VisitFiles::All,
+                    };
+                    Visit::Specific { dirs, files }
+                }
+            },
+        }
+    }
+}
+
 /// Matches paths that are matched by the first input matcher but not by the
 /// second.
-pub struct DifferenceMatcher<'input> {
+#[derive(Clone, Debug)]
+pub struct DifferenceMatcher<M1, M2> {
     /// The minuend
-    wanted: &'input dyn Matcher,
+    wanted: M1,
     /// The subtrahend
-    unwanted: &'input dyn Matcher,
+    unwanted: M2,
 }
 
-impl<'input> DifferenceMatcher<'input> {
-    pub fn new(wanted: &'input dyn Matcher, unwanted: &'input dyn Matcher) -> Self {
+impl<M1: Matcher, M2: Matcher> DifferenceMatcher<M1, M2> {
+    pub fn new(wanted: M1, unwanted: M2) -> Self {
         Self { wanted, unwanted }
     }
 }
 
-impl Matcher for DifferenceMatcher<'_> {
+impl<M1: Matcher, M2: Matcher> Matcher for DifferenceMatcher<M1, M2> {
     fn matches(&self, file: &RepoPath) -> bool {
         self.wanted.matches(file) && !self.unwanted.matches(file)
     }
@@ -196,18 +319,19 @@ impl Matcher for DifferenceMatcher<'_> {
 }
 
 /// Matches paths that are matched by both input matchers.
-pub struct IntersectionMatcher<'input> {
-    input1: &'input dyn Matcher,
-    input2: &'input dyn Matcher,
+#[derive(Clone, Debug)]
+pub struct IntersectionMatcher<M1, M2> {
+    input1: M1,
+    input2: M2,
 }
 
-impl<'input> IntersectionMatcher<'input> {
-    pub fn new(input1: &'input dyn Matcher, input2: &'input dyn Matcher) -> Self {
+impl<M1: Matcher, M2: Matcher> IntersectionMatcher<M1, M2> {
+    pub fn new(input1: M1, input2: M2) -> Self {
         Self { input1, input2 }
     }
 }
 
-impl Matcher for IntersectionMatcher<'_> {
+impl<M1: Matcher, M2: Matcher> Matcher for IntersectionMatcher<M1, M2> {
     fn matches(&self, file: &RepoPath) -> bool {
         self.input1.matches(file) && self.input2.matches(file)
     }
@@ -657,4 +781,73 @@ mod tests {
         );
         assert_eq!(m.visit(repo_path("foo/bar")), Visit::AllRecursively);
     }
+
+    #[test]
+    fn test_fileglobsmatcher() {
+        let m = FileGlobsMatcher::new([
+            (
+                repo_path("foo").to_owned(),
+                glob::Pattern::new("*.rs").unwrap(),
+            ),
+            (
+                repo_path("bar/baz").to_owned(),
+                glob::Pattern::new("**/*.txt").unwrap(),
+            ),
+        ]);
+
+        assert!(m.matches(repo_path("foo/a.rs")));
+        assert!(!m.matches(repo_path("foo/sub/a.rs")));
+        assert!(!m.matches(repo_path("a.rs")));
+        assert!(m.matches(repo_path("bar/baz/a.txt")));
+        assert!(m.matches(repo_path("bar/baz/sub/a.txt")));
+        assert!(!m.matches(repo_path("bar/a.txt")));
+
+        assert_eq!(
+            m.visit(RepoPath::root()),
+            Visit::sets(
+                hashset! {RepoPathComponentBuf::from("foo"), RepoPathComponentBuf::from("bar")},
+                hashset! {}
+            )
+        );
+        assert_eq!(
+            m.visit(repo_path("bar")),
+            Visit::sets(hashset! {RepoPathComponentBuf::from("baz")}, hashset! {})
+        );
+        assert_eq!(
+            m.visit(repo_path("foo")),
+            Visit::Specific {
+                dirs: VisitDirs::All,
+                files: VisitFiles::All,
+            }
+        );
+        assert_eq!(m.visit(repo_path("baz")), Visit::Nothing);
+    }
+
+    #[test]
+    fn test_unionmatcher() {
+        let m1 = FilesMatcher::new([repo_path("foo/bar"), repo_path("baz")]);
+        let m2 = PrefixMatcher::new([repo_path("foo/qux")]);
+        let m = UnionMatcher::new(&m1, &m2);
+
+        assert!(m.matches(repo_path("foo/bar")));
+        assert!(m.matches(repo_path("baz")));
+        assert!(m.matches(repo_path("foo/qux/file")));
+        assert!(!m.matches(repo_path("foo/other")));
+
+        assert_eq!(
+            m.visit(RepoPath::root()),
+            Visit::sets(
+                hashset! {RepoPathComponentBuf::from("foo")},
+                hashset! {RepoPathComponentBuf::from("baz")}
+            )
+        );
+        assert_eq!(
+            m.visit(repo_path("foo")),
+            Visit::sets(
+                hashset! {RepoPathComponentBuf::from("qux")},
+                hashset! {RepoPathComponentBuf::from("bar"), RepoPathComponentBuf::from("qux")}
+            )
+        );
+        assert_eq!(m.visit(repo_path("foo/qux")), Visit::AllRecursively);
+    }
 }
@@ -15,7 +15,8 @@
 //! A lazily merged view of a set of trees.
 
 use std::cmp::{max, Ordering};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::Read as _;
 use std::iter::zip;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -25,8 +26,13 @@ use std::{iter, vec};
 use futures::stream::StreamExt;
 use futures::{Future, Stream, TryStreamExt};
 use itertools::Itertools;
+use pollster::FutureExt;
 
-use crate::backend::{BackendError, BackendResult, ConflictId, MergedTreeId, TreeId, TreeValue};
+use crate::backend::{
+    BackendError, BackendResult, ConflictId, FileId, MergedTreeId, TreeId, TreeValue,
+};
+use crate::copies::{self, CopyOperation, CopyRecord};
+use crate::files::{self, MergeResult};
 use crate::matchers::{EverythingMatcher, Matcher};
 use crate::merge::{Merge, MergeBuilder, MergedTreeValue};
 use crate::repo_path::{RepoPath, RepoPathBuf, RepoPathComponent, RepoPathComponentsIter};
@@ -64,6 +70,17 @@ impl MergedTreeVal<'_> {
     }
 }
 
+/// The side to take when resolving conflicts automatically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Take the first side of each conflict. When merging `other` into a tree
+    /// with `MergedTree::merge()`, that's the tree itself.
+    Ours,
+    /// Take the last side of each conflict. When merging `other` into a tree
+    /// with `MergedTree::merge()`, that's `other`.
+    Theirs,
+}
+
 /// Summary of the changes between two trees.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DiffSummary {
@@ -208,6 +225,30 @@ impl MergedTree {
         }
     }
 
+    /// Resolves the conflicts in this tree by taking one side of each, as
+    /// chosen by the `strategy`. Returns the resolved tree and the paths of
+    /// the conflicts.
+    pub fn resolve_conflicts_with(
+        &self,
+        strategy: MergeStrategy,
+    ) -> BackendResult<(MergedTree, Vec<RepoPathBuf>)> {
+        let mut tree_builder = MergedTreeBuilder::new(self.id());
+        let mut paths = vec![];
+        for (path, conflict) in self.conflicts() {
+            let side = match strategy {
+                MergeStrategy::Ours => conflict.first(),
+                MergeStrategy::Theirs => conflict.adds().last().unwrap(),
+            };
+            tree_builder.set_or_remove(path.clone(), Merge::resolved(side.clone()));
+            paths.push(path);
+        }
+        if paths.is_empty() {
+            return Ok((self.clone(), paths));
+        }
+        let tree_id = tree_builder.write_tree(self.store())?;
+        Ok((self.store().get_root_tree(&tree_id)?, paths))
+    }
+
     /// Gets the `MergeTree` in a subdirectory of the current tree. If the path
     /// doesn't correspond to a tree in any of the inputs to the merge, then
     /// that entry will be replace by an empty tree in the result.
@@ -315,19 +356,28 @@ impl MergedTree {
         matcher: &'matcher dyn Matcher,
     ) -> TreeDiffStream<'matcher> {
         let concurrency = self.store().concurrency();
-        if concurrency <= 1 {
-            Box::pin(futures::stream::iter(TreeDiffIterator::new(
+        if concurrency > 1 {
+            Box::pin(TreeDiffStreamImpl::new(
                 self.clone(),
                 other.clone(),
                 matcher,
-            )))
-        } else {
-            Box::pin(TreeDiffStreamImpl::new(
+                concurrency,
+            ))
+        } else if let Some(thread_pool) = self.store().diff_thread_pool() {
+            // The backend blocks on reads, so read subtrees on the thread pool
+            // instead.
+            Box::pin(TreeDiffStreamImpl::new_with_thread_pool(
                 self.clone(),
                 other.clone(),
                 matcher,
-                concurrency,
+                thread_pool.clone(),
             ))
+        } else {
+            Box::pin(futures::stream::iter(TreeDiffIterator::new(
+                self.clone(),
+                other.clone(),
+                matcher,
+            )))
         }
     }
 
@@ -362,10 +412,55 @@ impl MergedTree {
     }
 
     /// Merges this tree with `other`, using `base` as base.
+    ///
+    /// If the store detects renames in merges, a file that was renamed on one
+    /// side and modified on the other side is merged at the new path instead of
+    /// conflicting.
     pub fn merge(
         &self,
         base: &MergedTree,
         other: &MergedTree,
+    ) -> Result<MergedTree, TreeMergeError> {
+        let merged_tree = self.merge_no_renames(base, other)?;
+        let Some(min_similarity) = self.store().merge_rename_similarity() else {
+            return Ok(merged_tree);
+        };
+        if !merged_tree.has_conflict() {
+            return Ok(merged_tree);
+        }
+
+        // A file renamed on one side and modified on the other side is a
+        // modify/delete conflict at the old path. Merge the changes into the
+        // file at the new path, and remove the old path.
+        let store = self.store();
+        let conflicted_paths: HashSet<RepoPathBuf> =
+            merged_tree.conflicts().map(|(path, _)| path).collect();
+        let mut builder = MergedTreeBuilder::new(merged_tree.id());
+        let mut resolved_any = false;
+        for (renamed_side, other_side) in [(self, other), (other, self)] {
+            let renames =
+                detect_renames(store, base, renamed_side, &conflicted_paths, min_similarity)?;
+            for record in &renames {
+                if let Some(value) =
+                    merge_renamed_file(store, record, base, renamed_side, other_side)?
+                {
+                    builder.set_or_remove(record.source.clone(), Merge::absent());
+                    builder.set_or_remove(record.target.clone(), Merge::normal(value));
+                    resolved_any = true;
+                }
+            }
+        }
+        if !resolved_any {
+            return Ok(merged_tree);
+        }
+        let tree_id = builder.write_tree(store)?;
+        Ok(store.get_root_tree(&tree_id)?)
+    }
+
+    fn merge_no_renames(
+        &self,
+        base: &MergedTree,
+        other: &MergedTree,
     ) -> Result<MergedTree, TreeMergeError> {
         if let (MergedTree::Legacy(this), MergedTree::Legacy(base), MergedTree::Legacy(other)) =
             (self, base, other)
@@ -475,6 +570,83 @@ fn trees_value<'a>(trees: &'a Merge<Tree>, basename: &RepoPathComponent) -> Merg
     MergedTreeVal::Conflict(value.map(|x| x.cloned()))
 }
 
+/// Finds the files that were renamed from `conflicted_paths` between `base` and
+/// `side`.
+fn detect_renames(
+    store: &Store,
+    base: &MergedTree,
+    side: &MergedTree,
+    conflicted_paths: &HashSet<RepoPathBuf>,
+    min_similarity: u32,
+) -> Result<Vec<CopyRecord>, TreeMergeError> {
+    let mut diff = vec![];
+    for (path, values) in base.diff(side, &EverythingMatcher) {
+        let (before, after) = values?;
+        let removed_conflict = after.is_absent() && conflicted_paths.contains(&path);
+        if removed_conflict || before.is_absent() {
+            diff.push((path, before, after));
+        }
+    }
+    Ok(copies::detect_copies(store, &diff, min_similarity)?
+        .into_iter()
+        .filter(|record| record.operation == CopyOperation::Rename)
+        .collect())
+}
+
+/// Merges the changes to the source of a rename on `other_side` into the
+/// renamed file on `renamed_side`. Returns `None` if the other side didn't
+/// modify the source file or added a file at the target, or if the contents
+/// conflict.
+fn merge_renamed_file(
+    store: &Store,
+    record: &CopyRecord,
+    base: &MergedTree,
+    renamed_side: &MergedTree,
+    other_side: &MergedTree,
+) -> Result<Option<TreeValue>, TreeMergeError> {
+    let file_id = |value: MergedTreeValue| match value.into_resolved() {
+        Ok(Some(TreeValue::File { id, executable })) => Some((id, executable)),
+        _ => None,
+    };
+    let (Some((base_id, _)), Some((renamed_id, executable)), Some((other_id, _))) = (
+        file_id(base.path_value(&record.source)),
+        file_id(renamed_side.path_value(&record.target)),
+        file_id(other_side.path_value(&record.source)),
+    ) else {
+        return Ok(None);
+    };
+    if other_id == base_id || other_side.path_value(&record.target).is_present() {
+        return Ok(None);
+    }
+    // The contents are read at the paths they were written at
+    let read = |path: &RepoPath, id: &FileId| -> Result<Vec<u8>, TreeMergeError> {
+        let mut content = vec![];
+        store
+            .read_file(path, id)?
+            .read_to_end(&mut content)
+            .map_err(|err| TreeMergeError::ReadError {
+                source: err,
+                file_id: id.clone(),
+            })?;
+        Ok(content)
+    };
+    let contents = Merge::from_removes_adds(
+        vec![read(&record.source, &base_id)?],
+        vec![
+            read(&record.target, &renamed_id)?,
+            read(&record.source, &other_id)?,
+        ],
+    );
+    let merge_result = files::merge(&contents.map(|content| content.as_slice()));
+    match merge_result {
+        MergeResult::Resolved(merged_content) => {
+            let id = store.write_file(&record.target, &mut merged_content.0.as_slice())?;
+            Ok(Some(TreeValue::File { id, executable }))
+        }
+        MergeResult::Conflict(_) => Ok(None),
+    }
+}
+
 fn merge_trees(merge: &Merge<Tree>) -> Result<Merge<Tree>, TreeMergeError> {
     if let Some(tree) = merge.resolve_trivial() {
         return Ok(Merge::resolved(tree.clone()));
@@ -914,6 +1086,10 @@ pub struct TreeDiffStreamImpl<'matcher> {
     /// limit because we have a file item that's blocked by pending subdirectory
     /// items.
     max_queued_items: usize,
+    /// The thread pool to read trees on, if any. The reads are started when
+    /// the futures are first polled, so at most about `max_concurrent_reads`
+    /// of them are queued on the pool at a time.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 /// A wrapper around `RepoPath` that allows us to optionally sort files after
@@ -977,6 +1153,32 @@ impl<'matcher> TreeDiffStreamImpl<'matcher> {
             pending_trees: VecDeque::new(),
             max_concurrent_reads,
             max_queued_items: 10000,
+            thread_pool: None,
+        };
+        stream.add_dir_diff_items(RepoPathBuf::root(), Ok((tree1, tree2)));
+        stream
+    }
+
+    /// Like `new()`, but reads the trees on the `thread_pool`, one pair of
+    /// trees per thread at a time. This is useful if the backend reads
+    /// synchronously. The pool shouldn't be shared with work that waits for
+    /// the stream, since the reads block the pool's threads.
+    pub fn new_with_thread_pool(
+        tree1: MergedTree,
+        tree2: MergedTree,
+        matcher: &'matcher dyn Matcher,
+        thread_pool: Arc<rayon::ThreadPool>,
+    ) -> Self {
+        let max_concurrent_reads = thread_pool.current_num_threads();
+        let mut stream = Self {
+            matcher,
+            legacy_format_before: matches!(tree1, MergedTree::Legacy(_)),
+            legacy_format_after: matches!(tree2, MergedTree::Legacy(_)),
+            items: BTreeMap::new(),
+            pending_trees: VecDeque::new(),
+            max_concurrent_reads,
+            max_queued_items: 10000,
+            thread_pool: Some(thread_pool),
         };
         stream.add_dir_diff_items(RepoPathBuf::root(), Ok((tree1, tree2)));
         stream
@@ -1060,22 +1262,44 @@ impl<'matcher> TreeDiffStreamImpl<'matcher> {
 
             // If the path was a tree on either side of the diff, read those trees.
             if tree_matches {
-                let before_tree_future = Self::tree(
-                    tree1.store().clone(),
-                    self.legacy_format_before,
-                    path.clone(),
-                    before.clone(),
-                );
-                let after_tree_future = Self::tree(
-                    tree2.store().clone(),
-                    self.legacy_format_after,
-                    path.clone(),
-                    after.clone(),
-                );
-                let both_trees_future =
-                    async { futures::try_join!(before_tree_future, after_tree_future) };
-                self.pending_trees
-                    .push_back((path.clone(), Box::pin(both_trees_future)));
+                let store1 = tree1.store().clone();
+                let store2 = tree2.store().clone();
+                let legacy_format_before = self.legacy_format_before;
+                let legacy_format_after = self.legacy_format_after;
+                let dir = path.clone();
+                let (before, after) = (before.clone(), after.clone());
+                let read_trees = move || async move {
+                    let before_tree_future =
+                        Self::tree(store1, legacy_format_before, dir.clone(), before);
+                    let after_tree_future = Self::tree(store2, legacy_format_after, dir, after);
+                    futures::try_join!(before_tree_future, after_tree_future)
+                };
+                if let Some(thread_pool) = &self.thread_pool {
+                    let thread_pool = thread_pool.clone();
+                    let dir = path.clone();
+                    let both_trees_future = async move {
+                        let (sender, receiver) = futures::channel::oneshot::channel();
+                        thread_pool.spawn(move || {
+                            // The receiver is gone if the stream was dropped early.
+                            sender.send(read_trees().block_on()).ok();
+                        });
+                        // The sender is dropped without sending if the read panicked.
+                        receiver.await.unwrap_or_else(|_| {
+                            Err(BackendError::Other(
+                                format!(
+                                    "Reading trees at {:?} was cancelled",
+                                    dir.as_internal_file_string()
+                                )
+                                .into(),
+                            ))
+                        })
+                    };
+                    self.pending_trees
+                        .push_back((path.clone(), Box::pin(both_trees_future)));
+                } else {
+                    self.pending_trees
+                        .push_back((path.clone(), Box::pin(read_trees())));
+                }
             }
 
             self.items
@@ -52,6 +52,12 @@ pub trait OpHeadsStore: Send + Sync + Debug {
     /// operations. It is not needed for correctness; implementations are free
     /// to return a type that doesn't hold.
     fn lock(&self) -> Box<dyn OpHeadsStoreLock + '_>;
+
+    /// Like `lock()`, but returns `None` instead of waiting if another process
+    /// holds the lock. Implementations that can't tell wait for the lock.
+    fn try_lock(&self) -> Option<Box<dyn OpHeadsStoreLock + '_>> {
+        Some(self.lock())
+    }
 }
 
 // Given an OpHeadsStore, fetch and resolve its op heads down to one under a
@@ -63,6 +69,32 @@ pub fn resolve_op_heads<E>(
     op_store: &Arc<dyn OpStore>,
     resolver: impl FnOnce(Vec<Operation>) -> Result<Operation, E>,
 ) -> Result<Operation, E>
+where
+    E: From<OpHeadResolutionError> + From<OpStoreError>,
+{
+    resolve_op_heads_impl(op_heads_store, op_store, resolver, true)
+}
+
+/// Like `resolve_op_heads()`, but doesn't wait if another process holds the
+/// lock. The heads are then resolved as by `resolve_op_heads_read_only()`,
+/// leaving it to the other process to record the resolved operation.
+pub fn resolve_op_heads_without_waiting<E>(
+    op_heads_store: &dyn OpHeadsStore,
+    op_store: &Arc<dyn OpStore>,
+    resolver: impl FnOnce(Vec<Operation>) -> Result<Operation, E>,
+) -> Result<Operation, E>
+where
+    E: From<OpHeadResolutionError> + From<OpStoreError>,
+{
+    resolve_op_heads_impl(op_heads_store, op_store, resolver, false)
+}
+
+fn resolve_op_heads_impl<E>(
+    op_heads_store: &dyn OpHeadsStore,
+    op_store: &Arc<dyn OpStore>,
+    resolver: impl FnOnce(Vec<Operation>) -> Result<Operation, E>,
+    wait_for_lock: bool,
+) -> Result<Operation, E>
 where
     E: From<OpHeadResolutionError> + From<OpStoreError>,
 {
@@ -87,7 +119,13 @@ where
     // Note that the locking isn't necessary for correctness; we take the lock
     // only to prevent other concurrent processes from doing the same work (and
     // producing another set of divergent heads).
-    let _lock = op_heads_store.lock();
+    let _lock = if wait_for_lock {
+        op_heads_store.lock()
+    } else if let Some(lock) = op_heads_store.try_lock() {
+        lock
+    } else {
+        return resolve_op_heads_read_only(op_heads_store, op_store, resolver);
+    };
     let op_head_ids = op_heads_store.get_op_heads();
 
     if op_head_ids.is_empty() {
@@ -100,6 +138,52 @@ where
         return Ok(Operation::new(op_store.clone(), op_head_id, op_head));
     }
 
+    let (op_heads, ancestor_op_heads) = read_and_filter_op_heads(op_store, &op_head_ids)?;
+
+    // Return without creating a merge operation
+    if let [op_head] = &*op_heads {
+        op_heads_store.update_op_heads(&ancestor_op_heads, op_head.id());
+        return Ok(op_head.clone());
+    }
+
+    let new_op = resolver(op_heads)?;
+    let mut old_op_heads = ancestor_op_heads;
+    old_op_heads.extend_from_slice(new_op.parent_ids());
+    op_heads_store.update_op_heads(&old_op_heads, new_op.id());
+    Ok(new_op)
+}
+
+/// Like `resolve_op_heads()`, but never updates the op heads store or takes
+/// its lock. If there are multiple heads, the operation returned by the
+/// `resolver` isn't recorded as the new head, so the heads will be resolved
+/// again the next time.
+pub fn resolve_op_heads_read_only<E>(
+    op_heads_store: &dyn OpHeadsStore,
+    op_store: &Arc<dyn OpStore>,
+    resolver: impl FnOnce(Vec<Operation>) -> Result<Operation, E>,
+) -> Result<Operation, E>
+where
+    E: From<OpHeadResolutionError> + From<OpStoreError>,
+{
+    let op_head_ids = op_heads_store.get_op_heads();
+    if op_head_ids.is_empty() {
+        return Err(OpHeadResolutionError::NoHeads.into());
+    }
+    let (mut op_heads, _) = read_and_filter_op_heads(op_store, &op_head_ids)?;
+    if op_heads.len() == 1 {
+        return Ok(op_heads.pop().unwrap());
+    }
+    resolver(op_heads)
+}
+
+/// Reads the operations, and removes the ones that are ancestors of other
+/// heads so we don't create merge operation with an operation and its
+/// ancestor. Returns the remaining heads sorted by end time, and the IDs of
+/// the removed ones.
+fn read_and_filter_op_heads(
+    op_store: &Arc<dyn OpStore>,
+    op_head_ids: &[OperationId],
+) -> Result<(Vec<Operation>, Vec<OperationId>), OpStoreError> {
     let op_heads: Vec<_> = op_head_ids
         .iter()
         .map(|op_id: &OperationId| -> Result<Operation, OpStoreError> {
@@ -107,8 +191,6 @@ where
             Ok(Operation::new(op_store.clone(), op_id.clone(), data))
         })
         .try_collect()?;
-    // Remove ancestors so we don't create merge operation with an operation and its
-    // ancestor
     let op_head_ids_before: HashSet<_> = op_heads.iter().map(|op| op.id().clone()).collect();
     let filtered_op_heads = dag_walk::heads_ok(
         op_heads.into_iter().map(Ok),
@@ -122,17 +204,6 @@ where
         .cloned()
         .collect_vec();
     let mut op_heads = filtered_op_heads.into_iter().collect_vec();
-
-    // Return without creating a merge operation
-    if let [op_head] = &*op_heads {
-        op_heads_store.update_op_heads(&ancestor_op_heads, op_head.id());
-        return Ok(op_head.clone());
-    }
-
     op_heads.sort_by_key(|op| op.store_operation().metadata.end_time.timestamp.clone());
-    let new_op = resolver(op_heads)?;
-    let mut old_op_heads = ancestor_op_heads;
-    old_op_heads.extend_from_slice(new_op.parent_ids());
-    op_heads_store.update_op_heads(&old_op_heads, new_op.id());
-    Ok(new_op)
+    Ok((op_heads, ancestor_op_heads))
 }
@@ -266,25 +266,41 @@ pub struct BranchTarget<'a> {
     pub remote_refs: Vec<(&'a str, &'a RemoteRef)>,
 }
 
-content_hash! {
-    /// Represents the way the repo looks at a given time, just like how a Tree
-    /// object represents how the file system looks at a given time.
-    #[derive(PartialEq, Eq, Clone, Debug, Default)]
-    pub struct View {
-        /// All head commits
-        pub head_ids: HashSet<CommitId>,
-        pub local_branches: BTreeMap<String, RefTarget>,
-        pub tags: BTreeMap<String, RefTarget>,
-        pub remote_views: BTreeMap<String, RemoteView>,
-        pub git_refs: BTreeMap<String, RefTarget>,
-        /// The commit the Git HEAD points to.
-        // TODO: Support multiple Git worktrees?
-        // TODO: Do we want to store the current branch name too?
-        pub git_head: RefTarget,
-        // The commit that *should be* checked out in the workspace. Note that the working copy
-        // (.jj/working_copy/) has the source of truth about which commit *is* checked out (to be
-        // precise: the commit to which we most recently completed an update to).
-        pub wc_commit_ids: HashMap<WorkspaceId, CommitId>,
+/// Represents the way the repo looks at a given time, just like how a Tree
+/// object represents how the file system looks at a given time.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct View {
+    /// All head commits
+    pub head_ids: HashSet<CommitId>,
+    pub local_branches: BTreeMap<String, RefTarget>,
+    pub tags: BTreeMap<String, RefTarget>,
+    /// Local-only names for commits, which aren't exported to Git.
+    pub pins: BTreeMap<String, RefTarget>,
+    pub remote_views: BTreeMap<String, RemoteView>,
+    pub git_refs: BTreeMap<String, RefTarget>,
+    /// The commit the Git HEAD points to.
+    // TODO: Support multiple Git worktrees?
+    // TODO: Do we want to store the current branch name too?
+    pub git_head: RefTarget,
+    // The commit that *should be* checked out in the workspace. Note that the working copy
+    // (.jj/working_copy/) has the source of truth about which commit *is* checked out (to be
+    // precise: the commit to which we most recently completed an update to).
+    pub wc_commit_ids: HashMap<WorkspaceId, CommitId>,
+}
+
+impl ContentHash for View {
+    fn hash(&self, state: &mut impl digest::Update) {
+        self.head_ids.hash(state);
+        self.local_branches.hash(state);
+        self.tags.hash(state);
+        self.remote_views.hash(state);
+        self.git_refs.hash(state);
+        self.git_head.hash(state);
+        self.wc_commit_ids.hash(state);
+        // Views without pins keep the ids they had before pins were added.
+        if !self.pins.is_empty() {
+            self.pins.hash(state);
+        }
     }
 }
 
@@ -460,9 +476,23 @@ pub trait OpStore: Send + Sync + Debug {
     /// All operations and views reachable from the `head_ids` won't be
     /// removed. In addition to that, objects created after `keep_newer` will be
     /// preserved. This mitigates a risk of deleting new heads created
-    /// concurrently by another process.
-    // TODO: return stats?
-    fn gc(&self, head_ids: &[OperationId], keep_newer: SystemTime) -> OpStoreResult<()>;
+    /// concurrently by another process. If `dry_run` is true, nothing is
+    /// removed, but the returned stats count what would be.
+    fn gc(
+        &self,
+        head_ids: &[OperationId],
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> OpStoreResult<OpStoreGcStats>;
+}
+
+/// Statistics of [`OpStore::gc()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpStoreGcStats {
+    /// Number of unreachable operations that were removed.
+    pub removed_operations: usize,
+    /// Number of unreachable views that were removed.
+    pub removed_views: usize,
 }
 
 #[cfg(test)]
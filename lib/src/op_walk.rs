@@ -73,8 +73,10 @@ pub fn resolve_op_for_load(
 ) -> Result<Operation, OpsetEvaluationError> {
     let op_store = repo_loader.op_store();
     let op_heads_store = repo_loader.op_heads_store().as_ref();
+    // Nothing is recorded if there are multiple heads, so there's no need to
+    // wait for the lock.
     let get_current_op = || {
-        op_heads_store::resolve_op_heads(op_heads_store, op_store, |_| {
+        op_heads_store::resolve_op_heads_without_waiting(op_heads_store, op_store, |_| {
             Err(OpsetResolutionError::MultipleOperations("@".to_owned()).into())
         })
     };
@@ -28,13 +28,15 @@ pub mod ref_conflict {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RefTarget {
-    /// New `RefConflict` type represents both `commit_id` and `conflict_legacy`.
+    /// New `RefConflict` type represents both `commit_id` and
+    /// `conflict_legacy`.
     #[prost(oneof = "ref_target::Value", tags = "1, 2, 3")]
     pub value: ::core::option::Option<ref_target::Value>,
 }
 /// Nested message and enum types in `RefTarget`.
 pub mod ref_target {
-    /// New `RefConflict` type represents both `commit_id` and `conflict_legacy`.
+    /// New `RefConflict` type represents both `commit_id` and
+    /// `conflict_legacy`.
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -95,6 +97,14 @@ pub struct Tag {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Pin {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub target: ::core::option::Option<RefTarget>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct View {
     #[prost(bytes = "vec", repeated, tag = "1")]
     pub head_ids: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
@@ -102,14 +112,15 @@ pub struct View {
     #[prost(bytes = "vec", tag = "2")]
     pub wc_commit_id: ::prost::alloc::vec::Vec<u8>,
     #[prost(map = "string, bytes", tag = "8")]
-    pub wc_commit_ids: ::std::collections::HashMap<
-        ::prost::alloc::string::String,
-        ::prost::alloc::vec::Vec<u8>,
-    >,
+    pub wc_commit_ids:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::vec::Vec<u8>>,
     #[prost(message, repeated, tag = "5")]
     pub branches: ::prost::alloc::vec::Vec<Branch>,
     #[prost(message, repeated, tag = "6")]
     pub tags: ::prost::alloc::vec::Vec<Tag>,
+    /// Local-only pins, which aren't exported to Git.
+    #[prost(message, repeated, tag = "11")]
+    pub pins: ::prost::alloc::vec::Vec<Pin>,
     /// Only a subset of the refs. For example, does not include refs/notes/.
     #[prost(message, repeated, tag = "3")]
     pub git_refs: ::prost::alloc::vec::Vec<GitRef>,
@@ -158,10 +169,8 @@ pub struct OperationMetadata {
     #[prost(string, tag = "5")]
     pub username: ::prost::alloc::string::String,
     #[prost(map = "string, string", tag = "6")]
-    pub tags: ::std::collections::HashMap<
-        ::prost::alloc::string::String,
-        ::prost::alloc::string::String,
-    >,
+    pub tags:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -173,7 +182,8 @@ impl RemoteRefState {
     /// String value of the enum field names used in the ProtoBuf definition.
     ///
     /// The values are not transformed in any way and thus are considered stable
-    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    /// (if the ProtoBuf definition does not change) and safe for programmatic
+    /// use.
     pub fn as_str_name(&self) -> &'static str {
         match self {
             RemoteRefState::New => "New",
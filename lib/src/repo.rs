@@ -36,7 +36,7 @@ use crate::commit::{Commit, CommitByCommitterTimestamp};
 use crate::commit_builder::CommitBuilder;
 use crate::default_index::DefaultIndexStore;
 use crate::default_submodule_store::DefaultSubmoduleStore;
-use crate::file_util::{IoResultExt as _, PathError};
+use crate::file_util::{self, IoResultExt as _, PathError};
 use crate::git_backend::GitBackend;
 use crate::index::{ChangeIdIndex, Index, IndexStore, MutableIndex, ReadonlyIndex};
 use crate::local_backend::LocalBackend;
@@ -121,18 +121,32 @@ pub enum RepoInitError {
 
 impl ReadonlyRepo {
     pub fn default_op_store_initializer() -> &'static OpStoreInitializer<'static> {
-        &|_settings, store_path| Box::new(SimpleOpStore::init(store_path))
+        &|settings, store_path| Box::new(SimpleOpStore::init(store_path, settings.fsync().clone()))
     }
 
     pub fn default_op_heads_store_initializer() -> &'static OpHeadsStoreInitializer<'static> {
-        &|_settings, store_path| {
-            let store = SimpleOpHeadsStore::init(store_path);
+        &|settings, store_path| {
+            // The lock backend is validated when the settings are loaded.
+            let store = SimpleOpHeadsStore::init(
+                store_path,
+                settings.lock_backend().unwrap_or_default(),
+                settings.fsync().clone(),
+            );
             Box::new(store)
         }
     }
 
     pub fn default_index_store_initializer() -> &'static IndexStoreInitializer<'static> {
-        &|_settings, store_path| Ok(Box::new(DefaultIndexStore::init(store_path)?))
+        &|settings, store_path| {
+            let lock_backend = settings
+                .lock_backend()
+                .map_err(|err| BackendInitError(err.into()))?;
+            Ok(Box::new(DefaultIndexStore::init(
+                store_path,
+                lock_backend,
+                settings.fsync().clone(),
+            )?))
+        }
     }
 
     pub fn default_submodule_store_initializer() -> &'static SubmoduleStoreInitializer<'static> {
@@ -150,14 +164,20 @@ impl ReadonlyRepo {
         index_store_initializer: &IndexStoreInitializer,
         submodule_store_initializer: &SubmoduleStoreInitializer,
     ) -> Result<Arc<ReadonlyRepo>, RepoInitError> {
-        let repo_path = repo_path.canonicalize().context(repo_path)?;
+        let repo_path = file_util::canonicalize(repo_path).context(repo_path)?;
 
         let store_path = repo_path.join("store");
         fs::create_dir(&store_path).context(&store_path)?;
         let backend = backend_initializer(user_settings, &store_path)?;
         let backend_path = store_path.join("type");
         fs::write(&backend_path, backend.name()).context(&backend_path)?;
-        let store = Store::new(backend, signer, user_settings.use_tree_conflict_format());
+        let store = Store::new(
+            backend,
+            signer,
+            user_settings.use_tree_conflict_format(),
+            user_settings.merge_rename_similarity(),
+            user_settings.diff_threads(),
+        );
         let repo_settings = user_settings.with_repo(&repo_path).unwrap();
 
         let op_store_path = repo_path.join("op_store");
@@ -363,7 +383,12 @@ impl Default for StoreFactories {
         // Backends
         factories.add_backend(
             LocalBackend::name(),
-            Box::new(|_settings, store_path| Ok(Box::new(LocalBackend::load(store_path)))),
+            Box::new(|settings, store_path| {
+                Ok(Box::new(LocalBackend::load(
+                    store_path,
+                    settings.fsync().clone(),
+                )))
+            }),
         );
         factories.add_backend(
             GitBackend::name(),
@@ -373,19 +398,38 @@ impl Default for StoreFactories {
         // OpStores
         factories.add_op_store(
             SimpleOpStore::name(),
-            Box::new(|_settings, store_path| Box::new(SimpleOpStore::load(store_path))),
+            Box::new(|settings, store_path| {
+                Box::new(SimpleOpStore::load(store_path, settings.fsync().clone()))
+            }),
         );
 
         // OpHeadsStores
         factories.add_op_heads_store(
             SimpleOpHeadsStore::name(),
-            Box::new(|_settings, store_path| Box::new(SimpleOpHeadsStore::load(store_path))),
+            Box::new(|settings, store_path| {
+                // The lock backend is validated when the settings are loaded.
+                let lock_backend = settings.lock_backend().unwrap_or_default();
+                Box::new(SimpleOpHeadsStore::load(
+                    store_path,
+                    lock_backend,
+                    settings.fsync().clone(),
+                ))
+            }),
         );
 
         // Index
         factories.add_index_store(
             DefaultIndexStore::name(),
-            Box::new(|_settings, store_path| Ok(Box::new(DefaultIndexStore::load(store_path)))),
+            Box::new(|settings, store_path| {
+                let lock_backend = settings
+                    .lock_backend()
+                    .map_err(|err| BackendLoadError(err.into()))?;
+                Ok(Box::new(DefaultIndexStore::load(
+                    store_path,
+                    lock_backend,
+                    settings.fsync().clone(),
+                )))
+            }),
         );
 
         // SubmoduleStores
@@ -608,6 +652,8 @@ impl RepoLoader {
             store_factories.load_backend(user_settings, &repo_path.join("store"))?,
             Signer::from_settings(user_settings)?,
             user_settings.use_tree_conflict_format(),
+            user_settings.merge_rename_similarity(),
+            user_settings.diff_threads(),
         );
         let repo_settings = user_settings.with_repo(repo_path).unwrap();
         let op_store =
@@ -1195,6 +1241,22 @@ impl MutableRepo {
         view.set_tag_target(name, new_target);
     }
 
+    pub fn get_pin(&self, name: &str) -> RefTarget {
+        self.view.with_ref(|v| v.get_pin(name).clone())
+    }
+
+    pub fn set_pin_target(&mut self, name: &str, target: RefTarget) {
+        self.view_mut().set_pin_target(name, target);
+    }
+
+    pub fn merge_pin(&mut self, name: &str, base_target: &RefTarget, other_target: &RefTarget) {
+        let view = self.view.get_mut();
+        let index = self.index.as_index();
+        let self_target = view.get_pin(name);
+        let new_target = merge_ref_targets(index, self_target, base_target, other_target);
+        view.set_pin_target(name, new_target);
+    }
+
     pub fn get_git_ref(&self, name: &str) -> RefTarget {
         self.view.with_ref(|v| v.get_git_ref(name).clone())
     }
@@ -1288,6 +1350,11 @@ impl MutableRepo {
             self.merge_tag(name, base_target, other_target);
         }
 
+        let changed_pins = diff_named_ref_targets(base.pins(), other.pins());
+        for (name, (base_target, other_target)) in changed_pins {
+            self.merge_pin(name, base_target, other_target);
+        }
+
         let changed_git_refs = diff_named_ref_targets(base.git_refs(), other.git_refs());
         for (name, (base_target, other_target)) in changed_git_refs {
             self.merge_git_ref(name, base_target, other_target);
@@ -33,12 +33,12 @@ use thiserror::Error;
 
 use crate::backend::{BackendError, BackendResult, ChangeId, CommitId};
 use crate::commit::Commit;
+use crate::fileset::{self, FilesetExpression, FilesetParseContext, FilesetParseErrorKind};
 use crate::git;
 use crate::hex_util::to_forward_hex;
 use crate::object_id::{HexPrefix, PrefixResolution};
 use crate::op_store::WorkspaceId;
 use crate::repo::Repo;
-use crate::repo_path::{FsPathParseError, RepoPathBuf};
 use crate::revset_graph::RevsetGraphEdge;
 use crate::store::Store;
 use crate::str_util::StringPattern;
@@ -174,8 +174,8 @@ pub enum RevsetParseErrorKind {
     },
     #[error("Invalid arguments to revset function \"{name}\": {message}")]
     InvalidFunctionArguments { name: String, message: String },
-    #[error("Invalid file pattern")]
-    FsPathParseError(#[source] FsPathParseError),
+    #[error(transparent)]
+    FilesetParseError(FilesetParseErrorKind),
     #[error("Cannot resolve file pattern without workspace")]
     FsPathWithoutWorkspace,
     #[error(r#"Cannot resolve "@" without workspace"#)]
@@ -320,6 +320,7 @@ pub enum RevsetCommitRef {
         remote_pattern: StringPattern,
     },
     Tags,
+    Pins(StringPattern),
     GitRefs,
     GitHead,
 }
@@ -334,8 +335,8 @@ pub enum RevsetFilterPredicate {
     Author(StringPattern),
     /// Commits with committer's name or email containing the needle.
     Committer(StringPattern),
-    /// Commits modifying the paths specified by the pattern.
-    File(Option<Vec<RepoPathBuf>>), // TODO: embed matcher expression?
+    /// Commits modifying the paths specified by the fileset.
+    File(FilesetExpression),
     /// Commits with conflicts
     HasConflict,
 }
@@ -447,6 +448,10 @@ impl RevsetExpression {
         Rc::new(RevsetExpression::CommitRef(RevsetCommitRef::Tags))
     }
 
+    pub fn pins(pattern: StringPattern) -> Rc<RevsetExpression> {
+        Rc::new(RevsetExpression::CommitRef(RevsetCommitRef::Pins(pattern)))
+    }
+
     pub fn git_refs() -> Rc<RevsetExpression> {
         Rc::new(RevsetExpression::CommitRef(RevsetCommitRef::GitRefs))
     }
@@ -1115,7 +1120,7 @@ fn collect_function_names(aliases_map: &RevsetAliasesMap) -> Vec<String> {
     names
 }
 
-fn collect_similar(name: &str, candidates: &[impl AsRef<str>]) -> Vec<String> {
+pub(crate) fn collect_similar(name: &str, candidates: &[impl AsRef<str>]) -> Vec<String> {
     candidates
         .iter()
         .filter(|cand| {
@@ -1221,6 +1226,11 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
         expect_no_arguments(name, arguments_pair)?;
         Ok(RevsetExpression::tags())
     });
+    map.insert("pin", |name, arguments_pair, state| {
+        let arg = expect_one_argument(name, arguments_pair)?;
+        let pattern = parse_function_argument_to_string_pattern(name, arg, state)?;
+        Ok(RevsetExpression::pins(pattern))
+    });
     map.insert("git_refs", |name, arguments_pair, _state| {
         expect_no_arguments(name, arguments_pair)?;
         Ok(RevsetExpression::git_refs())
@@ -1274,27 +1284,32 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
     });
     map.insert("empty", |name, arguments_pair, _state| {
         expect_no_arguments(name, arguments_pair)?;
-        Ok(RevsetExpression::filter(RevsetFilterPredicate::File(None)).negated())
+        Ok(
+            RevsetExpression::filter(RevsetFilterPredicate::File(FilesetExpression::all()))
+                .negated(),
+        )
     });
     map.insert("file", |name, arguments_pair, state| {
         if let Some(ctx) = state.workspace_ctx {
             let arguments_span = arguments_pair.as_span();
-            let paths: Vec<_> = arguments_pair
+            let fileset_ctx = FilesetParseContext {
+                cwd: ctx.cwd,
+                workspace_root: ctx.workspace_root,
+            };
+            let filesets: Vec<_> = arguments_pair
                 .into_inner()
                 .map(|arg| -> Result<_, RevsetParseError> {
                     let span = arg.as_span();
                     let needle = parse_function_argument_to_string(name, arg, state)?;
-                    let path = RepoPathBuf::parse_fs_path(ctx.cwd, ctx.workspace_root, needle)
-                        .map_err(|e| {
-                            RevsetParseError::with_span(
-                                RevsetParseErrorKind::FsPathParseError(e),
-                                span,
-                            )
-                        })?;
-                    Ok(path)
+                    fileset::parse(&needle, &fileset_ctx).map_err(|e| {
+                        RevsetParseError::with_span(
+                            RevsetParseErrorKind::FilesetParseError(e.into_kind()),
+                            span,
+                        )
+                    })
                 })
                 .try_collect()?;
-            if paths.is_empty() {
+            if filesets.is_empty() {
                 Err(RevsetParseError::with_span(
                     RevsetParseErrorKind::InvalidFunctionArguments {
                         name: name.to_owned(),
@@ -1303,9 +1318,9 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
                     arguments_span,
                 ))
             } else {
-                Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                    paths,
-                ))))
+                Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                    FilesetExpression::union_all(filesets),
+                )))
             }
         } else {
             Err(RevsetParseError::new(
@@ -2178,6 +2193,14 @@ fn resolve_commit_ref(
             }
             Ok(commit_ids)
         }
+        RevsetCommitRef::Pins(pattern) => {
+            let commit_ids = pattern
+                .filter_btree_map(repo.view().pins())
+                .flat_map(|(_, target)| target.added_ids())
+                .cloned()
+                .collect();
+            Ok(commit_ids)
+        }
         RevsetCommitRef::GitRefs => {
             let mut commit_ids = vec![];
             for ref_target in repo.view().git_refs().values() {
@@ -2486,6 +2509,7 @@ pub struct RevsetWorkspaceContext<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repo_path::RepoPathBuf;
 
     fn parse(revset_str: &str) -> Result<Rc<RevsetExpression>, RevsetParseErrorKind> {
         parse_with_aliases(revset_str, [] as [(&str, &str); 0])
@@ -2850,9 +2874,9 @@ mod tests {
                 StringPattern::Substring("arg1".to_string())
             ))
             .minus(&RevsetExpression::filter(RevsetFilterPredicate::File(
-                Some(vec![
-                    RepoPathBuf::from_internal_string("arg1"),
-                    RepoPathBuf::from_internal_string("arg2"),
+                FilesetExpression::union_all(vec![
+                    FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("arg1")),
+                    FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("arg2")),
                 ])
             )))
             .minus(&RevsetExpression::visible_heads()))
@@ -3100,25 +3124,28 @@ mod tests {
         );
         assert_eq!(
             parse_with_workspace("empty()", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(None)).negated())
+            Ok(
+                RevsetExpression::filter(RevsetFilterPredicate::File(FilesetExpression::all()))
+                    .negated()
+            )
         );
         assert!(parse_with_workspace("empty(foo)", &WorkspaceId::default()).is_err());
         assert!(parse_with_workspace("file()", &WorkspaceId::default()).is_err());
         assert_eq!(
             parse_with_workspace("file(foo)", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                vec![RepoPathBuf::from_internal_string("foo")]
-            ))))
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("foo"))
+            )))
         );
         assert_eq!(
             parse_with_workspace("file(foo, bar, baz)", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                vec![
-                    RepoPathBuf::from_internal_string("foo"),
-                    RepoPathBuf::from_internal_string("bar"),
-                    RepoPathBuf::from_internal_string("baz"),
-                ]
-            ))))
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::union_all(vec![
+                    FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("foo")),
+                    FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("bar")),
+                    FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("baz")),
+                ])
+            )))
         );
     }
 
@@ -3716,7 +3743,7 @@ mod tests {
         insta::assert_debug_snapshot!(optimize(parse("~empty()").unwrap()), @r###"
         Filter(
             File(
-                None,
+                All,
             ),
         )
         "###);
@@ -3953,10 +3980,10 @@ mod tests {
             ),
             Filter(
                 File(
-                    Some(
-                        [
+                    Pattern(
+                        PrefixPath(
                             "bar",
-                        ],
+                        ),
                     ),
                 ),
             ),
@@ -3975,10 +4002,10 @@ mod tests {
                 ),
                 Filter(
                     File(
-                        Some(
-                            [
+                        Pattern(
+                            PrefixPath(
                                 "bar",
-                            ],
+                            ),
                         ),
                     ),
                 ),
@@ -4008,10 +4035,10 @@ mod tests {
             ),
             Filter(
                 File(
-                    Some(
-                        [
+                    Pattern(
+                        PrefixPath(
                             "bar",
-                        ],
+                        ),
                     ),
                 ),
             ),
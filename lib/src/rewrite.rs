@@ -22,20 +22,21 @@ use itertools::Itertools;
 use pollster::FutureExt;
 use tracing::instrument;
 
-use crate::backend::{BackendError, BackendResult, CommitId, MergedTreeId};
+use crate::backend::{BackendError, BackendResult, CommitId, MergedTreeId, TreeValue};
 use crate::commit::Commit;
-use crate::dag_walk;
 use crate::index::Index;
 use crate::matchers::{Matcher, Visit};
-use crate::merged_tree::{MergedTree, MergedTreeBuilder};
+use crate::merge::Merge;
+use crate::merged_tree::{MergeStrategy, MergedTree, MergedTreeBuilder};
 use crate::object_id::ObjectId;
 use crate::op_store::RefTarget;
 use crate::repo::{MutableRepo, Repo};
-use crate::repo_path::RepoPath;
+use crate::repo_path::{RepoPath, RepoPathBuf};
 use crate::revset::{RevsetExpression, RevsetIteratorExt};
-use crate::settings::UserSettings;
+use crate::settings::{SignSettings, UserSettings};
 use crate::store::Store;
 use crate::tree::TreeMergeError;
+use crate::{conflicts, dag_walk};
 
 #[instrument(skip(repo))]
 pub fn merge_commit_trees(
@@ -65,7 +66,10 @@ pub fn merge_commit_trees_without_repo(
                 .iter()
                 .map(|id| store.get_commit(id))
                 .try_collect()?;
-            let ancestor_tree = merge_commit_trees_without_repo(store, index, &ancestors)?;
+            let mut ancestor_tree = merge_commit_trees_without_repo(store, index, &ancestors)?;
+            if ancestors.len() > 1 {
+                ancestor_tree = materialize_virtual_ancestor(&ancestor_tree)?;
+            }
             let other_tree = other_commit.tree()?;
             new_tree = new_tree.merge(&ancestor_tree, &other_tree)?;
         }
@@ -73,6 +77,47 @@ pub fn merge_commit_trees_without_repo(
     }
 }
 
+/// Makes the merge of several common ancestors (a "virtual ancestor") usable as
+/// the base of a merge, like Git's recursive merge strategy does. The files
+/// that conflict between the ancestors are replaced by their contents with
+/// conflict markers. The merge with this base is then a 3-way merge of the
+/// files, which conflicts if the sides resolved the conflict differently,
+/// instead of a nested conflict that may even cancel out.
+fn materialize_virtual_ancestor(tree: &MergedTree) -> Result<MergedTree, TreeMergeError> {
+    let store = tree.store();
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    let mut materialized_any = false;
+    for (path, value) in tree.conflicts() {
+        // Conflicts involving absent files, symlinks, etc. are kept as is
+        let mut executable = false;
+        let all_files = value.iter().all(|term| match term {
+            Some(TreeValue::File {
+                executable: term_executable,
+                ..
+            }) => {
+                executable |= term_executable;
+                true
+            }
+            _ => false,
+        });
+        if !all_files {
+            continue;
+        }
+        let mut content = vec![];
+        conflicts::materialize(&value, store, &path, &mut content)
+            .block_on()
+            .expect("Failed to materialize conflict to in-memory buffer");
+        let id = store.write_file(&path, &mut content.as_slice())?;
+        tree_builder.set_or_remove(path, Merge::normal(TreeValue::File { id, executable }));
+        materialized_any = true;
+    }
+    if !materialized_any {
+        return Ok(tree.clone());
+    }
+    let tree_id = tree_builder.write_tree(store)?;
+    Ok(store.get_root_tree(&tree_id)?)
+}
+
 /// Restore matching paths from the source into the destination.
 pub fn restore_tree(
     source: &MergedTree,
@@ -143,10 +188,11 @@ pub fn rebase_commit_with_options(
         let old_base_tree = merge_commit_trees(mut_repo, &old_parents)?;
         let new_base_tree = merge_commit_trees(mut_repo, new_parents)?;
         let old_tree = old_commit.tree()?;
-        (
-            Some(old_base_tree.id()),
-            new_base_tree.merge(&old_base_tree, &old_tree)?.id(),
-        )
+        let mut new_tree = new_base_tree.merge(&old_base_tree, &old_tree)?;
+        if let Some(strategy) = options.strategy {
+            (new_tree, _) = new_tree.resolve_conflicts_with(strategy)?;
+        }
+        (Some(old_base_tree.id()), new_tree.id())
     };
     // Ensure we don't abandon commits with multiple parents (merge commits), even
     // if they're empty.
@@ -183,6 +229,40 @@ pub fn rebase_commit_with_options(
         .write()?)
 }
 
+/// Signs the `commits` that should be signed according to `sign_settings` but
+/// aren't signed yet, e.g. before they're pushed. The `commits` must be sorted
+/// with parents before their children. Commits whose parents are rewritten are
+/// rewritten too. Returns a map from the old to the new commit ids.
+pub fn sign_commits(
+    settings: &UserSettings,
+    mut_repo: &mut MutableRepo,
+    commits: &[Commit],
+    sign_settings: &SignSettings,
+) -> BackendResult<HashMap<CommitId, CommitId>> {
+    let mut rewritten: HashMap<CommitId, CommitId> = HashMap::new();
+    for commit in commits {
+        let new_parent_ids = commit
+            .parent_ids()
+            .iter()
+            .map(|id| rewritten.get(id).unwrap_or(id).clone())
+            .collect_vec();
+        let needs_signature =
+            !commit.is_signed() && sign_settings.should_sign(commit.store_commit());
+        if !needs_signature && new_parent_ids == commit.parent_ids() {
+            continue;
+        }
+        let new_commit = mut_repo
+            .rewrite_commit(settings, commit)
+            .set_parents(new_parent_ids)
+            .set_committer(commit.committer().clone())
+            .set_sign_behavior(sign_settings.behavior)
+            .set_sign_key(sign_settings.key.clone())
+            .write()?;
+        rewritten.insert(commit.id().clone(), new_commit.id().clone());
+    }
+    Ok(rewritten)
+}
+
 pub fn rebase_to_dest_parent(
     repo: &dyn Repo,
     source: &Commit,
@@ -205,19 +285,38 @@ pub fn back_out_commit(
     old_commit: &Commit,
     new_parents: &[Commit],
 ) -> Result<Commit, TreeMergeError> {
+    let (new_commit, _) =
+        back_out_commit_with_strategy(settings, mut_repo, old_commit, new_parents, None)?;
+    Ok(new_commit)
+}
+
+/// Like `back_out_commit()`, but resolves the conflicts in the new commit with
+/// the `strategy`, if any. Also returns the paths of the resolved conflicts.
+pub fn back_out_commit_with_strategy(
+    settings: &UserSettings,
+    mut_repo: &mut MutableRepo,
+    old_commit: &Commit,
+    new_parents: &[Commit],
+    strategy: Option<MergeStrategy>,
+) -> Result<(Commit, Vec<RepoPathBuf>), TreeMergeError> {
     let old_base_tree = merge_commit_trees(mut_repo, &old_commit.parents())?;
     let new_base_tree = merge_commit_trees(mut_repo, new_parents)?;
     let old_tree = old_commit.tree()?;
-    let new_tree = new_base_tree.merge(&old_tree, &old_base_tree)?;
+    let mut new_tree = new_base_tree.merge(&old_tree, &old_base_tree)?;
+    let mut resolved_paths = vec![];
+    if let Some(strategy) = strategy {
+        (new_tree, resolved_paths) = new_tree.resolve_conflicts_with(strategy)?;
+    }
     let new_parent_ids = new_parents
         .iter()
         .map(|commit| commit.id().clone())
         .collect();
     // TODO: i18n the description based on repo language
-    Ok(mut_repo
+    let new_commit = mut_repo
         .new_commit(settings, new_parent_ids, new_tree.id())
         .set_description(format!("backout of commit {}", &old_commit.id().hex()))
-        .write()?)
+        .write()?;
+    Ok((new_commit, resolved_paths))
 }
 
 #[derive(Clone, Default, PartialEq, Eq, Debug)]
@@ -244,6 +343,9 @@ pub enum EmptyBehaviour {
 #[derive(Clone, Default, PartialEq, Eq, Debug)]
 pub struct RebaseOptions {
     pub empty: EmptyBehaviour,
+    /// If set, conflicts in the rebased commits are resolved with this
+    /// strategy, where "ours" is the new parents and "theirs" is the commit.
+    pub strategy: Option<MergeStrategy>,
 }
 
 /// Rebases descendants of a commit onto a new commit (or several).
@@ -24,6 +24,8 @@ use rand_chacha::ChaCha20Rng;
 use crate::backend::{ChangeId, Commit, Signature, Timestamp};
 use crate::fmt_util::binary_prefix;
 use crate::fsmonitor::FsmonitorKind;
+use crate::fsync::{Fsync, FsyncConfig, FsyncTarget};
+use crate::lock::LockBackend;
 use crate::signing::SignBehavior;
 
 #[derive(Debug, Clone)]
@@ -31,6 +33,7 @@ pub struct UserSettings {
     config: config::Config,
     timestamp: Option<Timestamp>,
     rng: Arc<JJRng>,
+    fsync: Fsync,
 }
 
 #[derive(Debug, Clone)]
@@ -74,6 +77,9 @@ pub struct SignSettings {
     pub user_email: String,
     /// The signing backend specific key, to be passed to the signing backend.
     pub key: Option<String>,
+    /// Whether commits are signed only when they're pushed instead of when
+    /// they're created or rewritten (`signing.when = "push"`).
+    pub sign_on_push: bool,
 }
 
 impl SignSettings {
@@ -83,14 +89,26 @@ impl SignSettings {
             .config()
             .get_bool("signing.sign-all")
             .unwrap_or(false);
+        let sign_on_push = settings
+            .config()
+            .get_string("signing.when")
+            .is_ok_and(|when| when == "push");
         Self {
-            behavior: if sign_all {
+            behavior: if sign_on_push {
+                // Signatures are added when pushing
+                SignBehavior::Drop
+            } else if sign_all {
                 SignBehavior::Own
             } else {
                 SignBehavior::Keep
             },
             user_email: settings.user_email(),
-            key: settings.config().get_string("signing.key").ok(),
+            key: settings
+                .config()
+                .get_string("signing.key")
+                .ok()
+                .map(|key| expand_config_path(&key)),
+            sign_on_push,
         }
     }
 
@@ -108,6 +126,68 @@ impl SignSettings {
     }
 }
 
+/// Expands a leading `~/` to the home directory, and `$VAR`, `${VAR}`, and
+/// `${VAR:-default}` to the values of environment variables, in a config value
+/// that names a path or a command. Unset variables expand to the empty string.
+pub fn expand_config_path(value: &str) -> String {
+    let home_dir = dirs::home_dir();
+    expand_config_path_with(value, home_dir.as_deref().and_then(Path::to_str), |name| {
+        std::env::var(name).ok()
+    })
+}
+
+fn expand_config_path_with(
+    value: &str,
+    home_dir: Option<&str>,
+    get_var: impl Fn(&str) -> Option<String>,
+) -> String {
+    let is_var_name = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let mut expanded = String::new();
+    let mut rest = value;
+    if let Some(remainder) = value.strip_prefix("~/") {
+        if let Some(home_dir) = home_dir {
+            expanded.push_str(home_dir);
+            expanded.push('/');
+            rest = remainder;
+        }
+    }
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some((inner, after)) = rest.strip_prefix('{').and_then(|s| s.split_once('}')) {
+            let (name, default) = match inner.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (inner, None),
+            };
+            if is_var_name(name) {
+                let value = get_var(name).unwrap_or_default();
+                match default {
+                    Some(default) if value.is_empty() => expanded.push_str(default),
+                    _ => expanded.push_str(&value),
+                }
+                rest = after;
+                continue;
+            }
+        } else {
+            let name_len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if is_var_name(&rest[..name_len]) {
+                expanded.push_str(&get_var(&rest[..name_len]).unwrap_or_default());
+                rest = &rest[name_len..];
+                continue;
+            }
+        }
+        // Not a variable reference
+        expanded.push('$');
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
 fn get_timestamp_config(config: &config::Config, key: &str) -> Option<Timestamp> {
     match config.get_string(key) {
         Ok(timestamp_str) => match DateTime::parse_from_rfc3339(&timestamp_str) {
@@ -118,6 +198,33 @@ fn get_timestamp_config(config: &config::Config, key: &str) -> Option<Timestamp>
     }
 }
 
+fn get_fsync_config(config: &config::Config) -> Result<FsyncConfig, config::ConfigError> {
+    let method = match config.get_string("core.fsync-method") {
+        Ok(method) => method.parse()?,
+        Err(config::ConfigError::NotFound(_)) => Default::default(),
+        Err(err) => return Err(err),
+    };
+    let targets: Vec<String> = match config.get_string("core.fsync") {
+        Ok(value) if value == "all" => return Ok(FsyncConfig::all(method)),
+        Ok(value) if value == "none" => vec![],
+        Ok(value) => vec![value],
+        Err(config::ConfigError::NotFound(_)) => vec![],
+        Err(_) => config.get("core.fsync")?,
+    };
+    let mut fsync_config = FsyncConfig {
+        method,
+        ..Default::default()
+    };
+    for target in targets {
+        match target.parse()? {
+            FsyncTarget::Objects => fsync_config.objects = true,
+            FsyncTarget::Operations => fsync_config.operations = true,
+            FsyncTarget::WorkingCopy => fsync_config.working_copy = true,
+        }
+    }
+    Ok(fsync_config)
+}
+
 fn get_rng_seed_config(config: &config::Config) -> Option<u64> {
     config
         .get_string("debug.randomness-seed")
@@ -129,10 +236,13 @@ impl UserSettings {
     pub fn from_config(config: config::Config) -> Self {
         let timestamp = get_timestamp_config(&config, "debug.commit-timestamp");
         let rng_seed = get_rng_seed_config(&config);
+        // An invalid setting is reported by `fsync_config()`.
+        let fsync = Fsync::new(get_fsync_config(&config).unwrap_or_default());
         UserSettings {
             config,
             timestamp,
             rng: Arc::new(JJRng::new(rng_seed)),
+            fsync,
         }
     }
 
@@ -153,6 +263,21 @@ impl UserSettings {
             .unwrap_or(false)
     }
 
+    pub fn merge_rename_similarity(&self) -> Option<u32> {
+        if !self.config.get_bool("merge.renames").unwrap_or(false) {
+            return None;
+        }
+        let similarity = self.config.get_int("merge.rename-similarity").unwrap_or(50);
+        Some(similarity.clamp(0, 100) as u32)
+    }
+
+    /// The number of threads to read trees on when diffing them, if the
+    /// backend can't read them concurrently by itself.
+    pub fn diff_threads(&self) -> usize {
+        let threads = self.config.get_int("core.diff-threads").unwrap_or(1);
+        threads.max(1) as usize
+    }
+
     pub fn user_name(&self) -> String {
         self.config.get_string("user.name").unwrap_or_default()
     }
@@ -172,6 +297,28 @@ impl UserSettings {
         }
     }
 
+    pub fn lock_backend(&self) -> Result<LockBackend, config::ConfigError> {
+        match self.config.get_string("core.lock-backend") {
+            Ok(lock_backend) => lock_backend.parse(),
+            Err(config::ConfigError::NotFound(_)) => Ok(LockBackend::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Which files are flushed to storage when written. `core.fsync` is
+    /// `"all"`, `"none"`, or a list of `"objects"`, `"operations"`, and
+    /// `"working-copy"`.
+    pub fn fsync_config(&self) -> Result<FsyncConfig, config::ConfigError> {
+        get_fsync_config(&self.config)
+    }
+
+    /// Flushes the files written by the stores of a repo as set by
+    /// `fsync_config()`, or not at all if that's invalid. Clones share the
+    /// files pending in batch mode.
+    pub fn fsync(&self) -> &Fsync {
+        &self.fsync
+    }
+
     // Must not be changed to avoid git pushing older commits with no set email
     // address
     pub const USER_EMAIL_PLACEHOLDER: &'static str = "(no email configured)";
@@ -391,6 +538,67 @@ fn parse_human_byte_size(v: &str) -> Result<u64, &str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fsync::FsyncMethod;
+
+    #[test]
+    fn test_expand_config_path() {
+        let expand = |value| {
+            expand_config_path_with(value, Some("/home/me"), |name| match name {
+                "HOME" => Some("/home/me".to_owned()),
+                "EDITOR" => Some("vim".to_owned()),
+                "EMPTY" => Some("".to_owned()),
+                _ => None,
+            })
+        };
+        assert_eq!(expand("~/bin/tool"), "/home/me/bin/tool");
+        assert_eq!(expand("/a/~/b"), "/a/~/b");
+        assert_eq!(expand("$EDITOR"), "vim");
+        assert_eq!(expand("${EDITOR}-wrapper"), "vim-wrapper");
+        assert_eq!(expand("$HOME/.ssh/id.pub"), "/home/me/.ssh/id.pub");
+        assert_eq!(expand("$UNSET/x"), "/x");
+        assert_eq!(expand("${UNSET:-nano}"), "nano");
+        assert_eq!(expand("${EMPTY:-nano}"), "nano");
+        assert_eq!(expand("${EDITOR:-nano}"), "vim");
+        assert_eq!(expand("a$ b$1 ${} ${x"), "a$ b$1 ${} ${x");
+        assert_eq!(expand("$"), "$");
+    }
+
+    #[test]
+    fn test_fsync_config() {
+        let fsync_config = |toml: &str| {
+            let config = config::Config::builder()
+                .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap();
+            UserSettings::from_config(config).fsync_config()
+        };
+        assert_eq!(fsync_config("").unwrap(), FsyncConfig::default());
+        assert_eq!(
+            fsync_config(r#"core.fsync = "all""#).unwrap(),
+            FsyncConfig::all(FsyncMethod::Immediate)
+        );
+        assert_eq!(
+            fsync_config(r#"core.fsync = "none""#).unwrap(),
+            FsyncConfig::default()
+        );
+        assert_eq!(
+            fsync_config(
+                r#"
+                core.fsync = ["operations", "working-copy"]
+                core.fsync-method = "batch"
+                "#
+            )
+            .unwrap(),
+            FsyncConfig {
+                objects: false,
+                operations: true,
+                working_copy: true,
+                method: FsyncMethod::Batch,
+            }
+        );
+        assert!(fsync_config(r#"core.fsync = ["index"]"#).is_err());
+        assert!(fsync_config(r#"core.fsync-method = "later""#).is_err());
+    }
 
     #[test]
     fn byte_size_parse() {
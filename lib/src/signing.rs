@@ -22,7 +22,11 @@ use std::sync::RwLock;
 use thiserror::Error;
 
 use crate::backend::CommitId;
+use crate::gpg_signing::GpgBackend;
 use crate::settings::UserSettings;
+use crate::sigstore_signing::SigstoreBackend;
+use crate::ssh_signing::SshBackend;
+use crate::x509_signing::X509Backend;
 
 /// A status of the signature, part of the [Verification] type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -94,6 +98,10 @@ pub enum SignError {
     /// The verification failed because the signature *format* was invalid.
     #[error("Invalid signature")]
     InvalidSignatureFormat,
+    /// The program of the backend couldn't be run, e.g. because it isn't
+    /// installed.
+    #[error("Signing program is unavailable")]
+    Unavailable(#[source] Box<dyn std::error::Error + Send + Sync>),
     /// A generic error from the backend impl.
     #[error("Signing error")]
     Backend(#[source] Box<dyn std::error::Error + Send + Sync>),
@@ -151,9 +159,10 @@ impl Signer {
     /// chooses one of them to be used for signing depending on the config.
     pub fn from_settings(settings: &UserSettings) -> Result<Self, SignInitError> {
         let mut backends: Vec<Box<dyn SigningBackend>> = vec![
-            // Box::new(GpgBackend::from_settings(settings)?),
-            // Box::new(SshBackend::from_settings(settings)?),
-            // Box::new(X509Backend::from_settings(settings)?),
+            Box::new(GpgBackend::from_settings(settings)?),
+            Box::new(SshBackend::from_settings(settings)?),
+            Box::new(X509Backend::from_settings(settings)?),
+            Box::new(SigstoreBackend::from_settings(settings)?),
         ];
 
         let main_backend = settings
@@ -209,6 +218,7 @@ impl Signer {
             return Ok(check);
         }
 
+        let mut unavailable = None;
         let verification = self
             .main_backend
             .iter()
@@ -219,9 +229,18 @@ impl Signer {
             .find_map(|backend| match backend.verify(data, signature) {
                 Ok(check) if check.status == SigStatus::Unknown => None,
                 Err(SignError::InvalidSignatureFormat) => None,
+                // x509 and sigstore read the same signatures, but usually only
+                // one of their programs is installed
+                Err(err @ SignError::Unavailable(_)) => {
+                    unavailable.get_or_insert(err);
+                    None
+                }
                 e => Some(e),
             })
             .transpose()?;
+        if let (None, Some(err)) = (&verification, unavailable) {
+            return Err(err);
+        }
 
         if let Some(verification) = verification {
             // a key might get imported before next call?.
@@ -0,0 +1,82 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend for keyless Sigstore signatures created by `gitsign`.
+
+use crate::settings::{ConfigResultExt as _, UserSettings};
+use crate::signing::{SignInitError, SignResult, SigningBackend, Verification};
+use crate::x509_signing::X509Backend;
+
+/// Config options under `signing.backends.sigstore`, and the `gitsign`
+/// environment variables they're passed as.
+const ENV_OPTIONS: &[(&str, &str)] = &[
+    ("fulcio-url", "GITSIGN_FULCIO_URL"),
+    ("rekor-url", "GITSIGN_REKOR_URL"),
+    ("oidc-issuer", "GITSIGN_OIDC_ISSUER"),
+    ("connector-id", "GITSIGN_CONNECTOR_ID"),
+];
+
+/// Signs commits with short-lived certificates issued by Sigstore after an
+/// OIDC login, and verifies them against the Rekor transparency log.
+///
+/// `gitsign` has the same command line as `gpgsm` and produces the same kind of
+/// signatures, so this runs it like [`X509Backend`] does.
+#[derive(Debug)]
+pub struct SigstoreBackend {
+    inner: X509Backend,
+}
+
+impl SigstoreBackend {
+    /// Creates a backend that runs the given program.
+    pub fn new(inner: X509Backend) -> Self {
+        Self { inner }
+    }
+
+    /// Creates a backend from the `signing.backends.sigstore` config.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, SignInitError> {
+        let get = |name: &str| {
+            settings
+                .config()
+                .get_string(&format!("signing.backends.sigstore.{name}"))
+                .optional()
+                .map_err(|e| SignInitError::Backend(Box::new(e)))
+        };
+        let program = get("program")?.unwrap_or_else(|| "gitsign".to_owned());
+        let mut inner = X509Backend::new(program.into());
+        for (name, env) in ENV_OPTIONS {
+            if let Some(value) = get(name)? {
+                inner = inner.env(env, value);
+            }
+        }
+        Ok(Self::new(inner))
+    }
+}
+
+impl SigningBackend for SigstoreBackend {
+    fn name(&self) -> &str {
+        "sigstore"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        self.inner.can_read(signature)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        self.inner.sign(data, key)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        self.inner.verify(data, signature)
+    }
+}
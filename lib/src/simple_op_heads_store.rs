@@ -19,13 +19,16 @@ use std::fmt::{Debug, Formatter};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::lock::FileLock;
+use crate::fsync::{Fsync, FsyncTarget};
+use crate::lock::{FileLock, LockBackend};
 use crate::object_id::ObjectId;
 use crate::op_heads_store::{OpHeadsStore, OpHeadsStoreLock};
 use crate::op_store::OperationId;
 
 pub struct SimpleOpHeadsStore {
     dir: PathBuf,
+    lock_backend: LockBackend,
+    fsync: Fsync,
 }
 
 impl Debug for SimpleOpHeadsStore {
@@ -41,19 +44,31 @@ impl SimpleOpHeadsStore {
         "simple_op_heads_store"
     }
 
-    pub fn init(dir: &Path) -> Self {
+    pub fn init(dir: &Path, lock_backend: LockBackend, fsync: Fsync) -> Self {
         let op_heads_dir = dir.join("heads");
         fs::create_dir(&op_heads_dir).unwrap();
-        Self { dir: op_heads_dir }
+        Self {
+            dir: op_heads_dir,
+            lock_backend,
+            fsync,
+        }
     }
 
-    pub fn load(dir: &Path) -> Self {
+    pub fn load(dir: &Path, lock_backend: LockBackend, fsync: Fsync) -> Self {
         let op_heads_dir = dir.join("heads");
-        Self { dir: op_heads_dir }
+        Self {
+            dir: op_heads_dir,
+            lock_backend,
+            fsync,
+        }
     }
 
     fn add_op_head(&self, id: &OperationId) {
-        std::fs::write(self.dir.join(id.hex()), "").unwrap();
+        let path = self.dir.join(id.hex());
+        std::fs::write(&path, "").unwrap();
+        self.fsync
+            .sync_written_file(FsyncTarget::Operations, &path)
+            .unwrap();
     }
 
     fn remove_op_head(&self, id: &OperationId) {
@@ -66,7 +81,7 @@ impl SimpleOpHeadsStore {
 }
 
 struct SimpleOpHeadsStoreLock {
-    _lock: FileLock,
+    _lock: Option<FileLock>,
 }
 
 impl OpHeadsStoreLock for SimpleOpHeadsStoreLock {}
@@ -82,7 +97,11 @@ impl OpHeadsStore for SimpleOpHeadsStore {
 
     fn update_op_heads(&self, old_ids: &[OperationId], new_id: &OperationId) {
         assert!(!old_ids.contains(new_id));
+        // The new operation and everything it refers to must be flushed before
+        // it's published, and the new head before the old ones are removed.
+        self.fsync.sync_pending().unwrap();
         self.add_op_head(new_id);
+        self.fsync.sync_pending().unwrap();
         for old_id in old_ids {
             self.remove_op_head(old_id)
         }
@@ -101,8 +120,27 @@ impl OpHeadsStore for SimpleOpHeadsStore {
     }
 
     fn lock(&self) -> Box<dyn OpHeadsStoreLock + '_> {
-        Box::new(SimpleOpHeadsStoreLock {
-            _lock: FileLock::lock(self.dir.join("lock")),
-        })
+        // The lock isn't needed for correctness, so carry on without it if it
+        // can't be taken (e.g. because it was left behind by a killed process).
+        let lock = match FileLock::lock(self.dir.join("lock"), self.lock_backend) {
+            Ok(lock) => Some(lock),
+            Err(err) => {
+                tracing::warn!(%err, "failed to lock the op heads store");
+                None
+            }
+        };
+        Box::new(SimpleOpHeadsStoreLock { _lock: lock })
+    }
+
+    fn try_lock(&self) -> Option<Box<dyn OpHeadsStoreLock + '_>> {
+        let lock = match FileLock::try_lock(self.dir.join("lock"), self.lock_backend) {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => return None,
+            Err(err) => {
+                tracing::warn!(%err, "failed to lock the op heads store");
+                None
+            }
+        };
+        Some(Box::new(SimpleOpHeadsStoreLock { _lock: lock }))
     }
 }
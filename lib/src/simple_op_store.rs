@@ -30,11 +30,12 @@ use thiserror::Error;
 use crate::backend::{CommitId, MillisSinceEpoch, Timestamp};
 use crate::content_hash::blake2b_hash;
 use crate::file_util::{persist_content_addressed_temp_file, IoResultExt as _, PathError};
+use crate::fsync::{Fsync, FsyncTarget};
 use crate::merge::Merge;
 use crate::object_id::{HexPrefix, ObjectId, PrefixResolution};
 use crate::op_store::{
-    OpStore, OpStoreError, OpStoreResult, Operation, OperationId, OperationMetadata, RefTarget,
-    RemoteRef, RemoteRefState, RemoteView, View, ViewId, WorkspaceId,
+    OpStore, OpStoreError, OpStoreGcStats, OpStoreResult, Operation, OperationId,
+    OperationMetadata, RefTarget, RemoteRef, RemoteRefState, RemoteView, View, ViewId, WorkspaceId,
 };
 use crate::{dag_walk, git, op_store};
 
@@ -62,6 +63,7 @@ pub struct SimpleOpStore {
     path: PathBuf,
     empty_view_id: ViewId,
     root_operation_id: OperationId,
+    fsync: Fsync,
 }
 
 impl SimpleOpStore {
@@ -70,26 +72,27 @@ impl SimpleOpStore {
     }
 
     /// Creates an empty OpStore, panics if it already exists
-    pub fn init(store_path: &Path) -> Self {
+    pub fn init(store_path: &Path, fsync: Fsync) -> Self {
         fs::create_dir(store_path.join("views")).unwrap();
         fs::create_dir(store_path.join("operations")).unwrap();
-        Self::load(store_path)
+        Self::load(store_path, fsync)
     }
 
     /// Load an existing OpStore
-    pub fn load(store_path: &Path) -> Self {
+    pub fn load(store_path: &Path, fsync: Fsync) -> Self {
         SimpleOpStore {
             path: store_path.to_path_buf(),
             empty_view_id: ViewId::from_bytes(&[0; VIEW_ID_LENGTH]),
             root_operation_id: OperationId::from_bytes(&[0; OPERATION_ID_LENGTH]),
+            fsync,
         }
     }
 
-    fn view_path(&self, id: &ViewId) -> PathBuf {
+    pub fn view_path(&self, id: &ViewId) -> PathBuf {
         self.path.join("views").join(id.hex())
     }
 
-    fn operation_path(&self, id: &OperationId) -> PathBuf {
+    pub fn operation_path(&self, id: &OperationId) -> PathBuf {
         self.path.join("operations").join(id.hex())
     }
 }
@@ -135,7 +138,11 @@ impl OpStore for SimpleOpStore {
 
         let id = ViewId::new(blake2b_hash(view).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.view_path(&id))
+        let path = self.view_path(&id);
+        persist_content_addressed_temp_file(temp_file, &path)
+            .map_err(|err| io_to_write_error(err, "view"))?;
+        self.fsync
+            .sync_written_file(FsyncTarget::Operations, &path)
             .map_err(|err| io_to_write_error(err, "view"))?;
         Ok(id)
     }
@@ -176,7 +183,11 @@ impl OpStore for SimpleOpStore {
 
         let id = OperationId::new(blake2b_hash(operation).to_vec());
 
-        persist_content_addressed_temp_file(temp_file, self.operation_path(&id))
+        let path = self.operation_path(&id);
+        persist_content_addressed_temp_file(temp_file, &path)
+            .map_err(|err| io_to_write_error(err, "operation"))?;
+        self.fsync
+            .sync_written_file(FsyncTarget::Operations, &path)
             .map_err(|err| io_to_write_error(err, "operation"))?;
         Ok(id)
     }
@@ -227,7 +238,12 @@ impl OpStore for SimpleOpStore {
     }
 
     #[tracing::instrument(skip(self))]
-    fn gc(&self, head_ids: &[OperationId], keep_newer: SystemTime) -> OpStoreResult<()> {
+    fn gc(
+        &self,
+        head_ids: &[OperationId],
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> OpStoreResult<OpStoreGcStats> {
         let to_op_id = |entry: &fs::DirEntry| -> Option<OperationId> {
             let name = entry.file_name().into_string().ok()?;
             OperationId::try_from_hex(&name).ok()
@@ -236,7 +252,8 @@ impl OpStore for SimpleOpStore {
             let name = entry.file_name().into_string().ok()?;
             ViewId::try_from_hex(&name).ok()
         };
-        let remove_file_if_not_new = |entry: &fs::DirEntry| -> Result<(), PathError> {
+        // Returns whether the file was (or would be) removed.
+        let remove_file_if_not_new = |entry: &fs::DirEntry| -> Result<bool, PathError> {
             let path = entry.path();
             // Check timestamp, but there's still TOCTOU problem if an existing
             // file is renewed.
@@ -244,10 +261,14 @@ impl OpStore for SimpleOpStore {
             let mtime = metadata.modified().expect("unsupported platform?");
             if mtime > keep_newer {
                 tracing::trace!(?path, "not removing");
-                Ok(())
+                Ok(false)
+            } else if dry_run {
+                tracing::trace!(?path, "would remove");
+                Ok(true)
             } else {
                 tracing::trace!(?path, "removing");
-                fs::remove_file(&path).context(&path)
+                fs::remove_file(&path).context(&path)?;
+                Ok(true)
             }
         };
 
@@ -269,7 +290,8 @@ impl OpStore for SimpleOpStore {
             "collected reachable objects"
         );
 
-        let prune_ops = || -> Result<(), PathError> {
+        let mut stats = OpStoreGcStats::default();
+        let mut prune_ops = || -> Result<(), PathError> {
             let op_dir = self.path.join("operations");
             for entry in op_dir.read_dir().context(&op_dir)? {
                 let entry = entry.context(&op_dir)?;
@@ -283,13 +305,15 @@ impl OpStore for SimpleOpStore {
                 // If the operation was added after collecting reachable_views,
                 // its view mtime would also be renewed. So there's no need to
                 // update the reachable_views set to preserve the view.
-                remove_file_if_not_new(&entry)?;
+                if remove_file_if_not_new(&entry)? {
+                    stats.removed_operations += 1;
+                }
             }
             Ok(())
         };
         prune_ops().map_err(|err| OpStoreError::Other(err.into()))?;
 
-        let prune_views = || -> Result<(), PathError> {
+        let mut prune_views = || -> Result<(), PathError> {
             let view_dir = self.path.join("views");
             for entry in view_dir.read_dir().context(&view_dir)? {
                 let entry = entry.context(&view_dir)?;
@@ -300,13 +324,15 @@ impl OpStore for SimpleOpStore {
                 if reachable_views.contains(&id) {
                     continue;
                 }
-                remove_file_if_not_new(&entry)?;
+                if remove_file_if_not_new(&entry)? {
+                    stats.removed_views += 1;
+                }
             }
             Ok(())
         };
         prune_views().map_err(|err| OpStoreError::Other(err.into()))?;
 
-        Ok(())
+        Ok(stats)
     }
 }
 
@@ -422,6 +448,13 @@ fn view_to_proto(view: &View) -> crate::protos::op_store::View {
         });
     }
 
+    for (name, target) in &view.pins {
+        proto.pins.push(crate::protos::op_store::Pin {
+            name: name.clone(),
+            target: ref_target_to_proto(target),
+        });
+    }
+
     for (git_ref_name, target) in &view.git_refs {
         proto.git_refs.push(crate::protos::op_store::GitRef {
             name: git_ref_name.clone(),
@@ -461,6 +494,11 @@ fn view_from_proto(proto: crate::protos::op_store::View) -> View {
             .insert(tag_proto.name, ref_target_from_proto(tag_proto.target));
     }
 
+    for pin_proto in proto.pins {
+        view.pins
+            .insert(pin_proto.name, ref_target_from_proto(pin_proto.target));
+    }
+
     for git_ref in proto.git_refs {
         let target = if git_ref.target.is_some() {
             ref_target_from_proto(git_ref.target)
@@ -718,6 +756,7 @@ mod tests {
             tags: btreemap! {
                 "v1.0".to_string() => tag_v1_target,
             },
+            pins: btreemap! {},
             remote_views: btreemap! {
                 "origin".to_string() => RemoteView {
                     branches: btreemap! {
@@ -738,6 +777,15 @@ mod tests {
         }
     }
 
+    fn create_view_with_pins() -> View {
+        View {
+            pins: btreemap! {
+                "bug".to_string() => RefTarget::normal(CommitId::from_hex("eee111")),
+            },
+            ..create_view()
+        }
+    }
+
     fn create_operation() -> Operation {
         Operation {
             view_id: ViewId::from_hex("aaa111"),
@@ -772,6 +820,11 @@ mod tests {
             ViewId::new(blake2b_hash(&create_view()).to_vec()).hex(),
             @"19af495b5a828c38e933c518a53eb9f7fb7129348a0950d7971b86adf7eb25a13bf45c7f358da1776b27fe6f4bc9134787389d976b768e46ac6cef56bc20c083"
         );
+        // Pins are only hashed if there are any
+        assert_ne!(
+            blake2b_hash(&create_view_with_pins()),
+            blake2b_hash(&create_view())
+        );
     }
 
     #[test]
@@ -786,17 +839,22 @@ mod tests {
     #[test]
     fn test_read_write_view() {
         let temp_dir = testutils::new_temp_dir();
-        let store = SimpleOpStore::init(temp_dir.path());
+        let store = SimpleOpStore::init(temp_dir.path(), Fsync::default());
         let view = create_view();
         let view_id = store.write_view(&view).unwrap();
         let read_view = store.read_view(&view_id).unwrap();
         assert_eq!(read_view, view);
+
+        let view = create_view_with_pins();
+        let view_id = store.write_view(&view).unwrap();
+        let read_view = store.read_view(&view_id).unwrap();
+        assert_eq!(read_view, view);
     }
 
     #[test]
     fn test_read_write_operation() {
         let temp_dir = testutils::new_temp_dir();
-        let store = SimpleOpStore::init(temp_dir.path());
+        let store = SimpleOpStore::init(temp_dir.path(), Fsync::default());
         let operation = create_operation();
         let op_id = store.write_operation(&operation).unwrap();
         let read_operation = store.read_operation(&op_id).unwrap();
@@ -0,0 +1,278 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend for SSH signatures created and verified by `ssh-keygen`.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt::Debug;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+use crate::settings::{expand_config_path, ConfigResultExt as _, UserSettings};
+use crate::signing::{
+    SigStatus, SignError, SignInitError, SignResult, SigningBackend, Verification,
+};
+
+const SIGNATURE_PREFIX: &[u8] = b"-----BEGIN SSH SIGNATURE-----";
+
+/// The namespace Git uses for commit signatures.
+const NAMESPACE: &str = "git";
+
+/// An error from signing or verifying with `ssh-keygen`.
+#[derive(Debug, Error)]
+pub enum SshError {
+    /// There's no key to sign with.
+    #[error("No SSH key to sign with. Set `signing.key` to a public key or a key file.")]
+    MissingKey,
+    /// Signing failed. `ssh-keygen` has printed the reason to stderr.
+    #[error("ssh-keygen failed with {0}")]
+    Command(ExitStatus),
+    /// `ssh-keygen` couldn't be run.
+    #[error("Failed to run ssh-keygen")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<SshError> for SignError {
+    fn from(e: SshError) -> Self {
+        SignError::Backend(Box::new(e))
+    }
+}
+
+/// Signs and verifies commits with SSH keys by running `ssh-keygen`, like Git
+/// does with `gpg.format = ssh`.
+///
+/// The key to sign with is the `key` passed to [`SigningBackend::sign`],
+/// usually the `signing.key` config. It can be the path to a key file, or a
+/// public key like `ssh-ed25519 AAAA...` whose private key is in `ssh-agent`.
+/// Hardware-backed `sk-` keys work too.
+///
+/// Signatures are verified against an allowed signers file in the format of
+/// `ssh-keygen`, which maps principals (usually email addresses) to their
+/// public keys. Without one, valid signatures are reported as made by an
+/// unknown key.
+#[derive(Debug)]
+pub struct SshBackend {
+    program: OsString,
+    allowed_signers: Option<PathBuf>,
+}
+
+impl SshBackend {
+    /// Creates a backend that runs the given program, and verifies signatures
+    /// against the given allowed signers file.
+    pub fn new(program: OsString, allowed_signers: Option<PathBuf>) -> Self {
+        Self {
+            program,
+            allowed_signers,
+        }
+    }
+
+    /// Creates a backend from the `signing.backends.ssh` config.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, SignInitError> {
+        let get = |name: &str| {
+            settings
+                .config()
+                .get_string(&format!("signing.backends.ssh.{name}"))
+                .optional()
+                .map_err(|e| SignInitError::Backend(Box::new(e)))
+        };
+        let program = get("program")?.unwrap_or_else(|| "ssh-keygen".to_owned());
+        let allowed_signers = get("allowed-signers")?;
+        Ok(Self::new(
+            expand_config_path(&program).into(),
+            allowed_signers.map(|path| expand_config_path(&path).into()),
+        ))
+    }
+
+    fn run(&self, args: &[&OsStr], input: &[u8], stderr: Stdio) -> Result<Output, SshError> {
+        let mut child = Command::new(&self.program)
+            .arg("-Y")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .spawn()?;
+        // The program may exit without reading all of its input, e.g. on bad
+        // arguments. Its exit status and stderr describe that better.
+        match child.stdin.take().unwrap().write_all(input) {
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Checks that the signature is valid, without checking who made it.
+    fn check_novalidate(&self, data: &[u8], signature_path: &Path) -> SignResult<Verification> {
+        let args = [
+            "check-novalidate".as_ref(),
+            "-n".as_ref(),
+            NAMESPACE.as_ref(),
+            "-s".as_ref(),
+            signature_path.as_os_str(),
+        ];
+        let output = self.run(&args, data, Stdio::piped())?;
+        Ok(Verification {
+            status: if output.status.success() {
+                SigStatus::Unknown
+            } else {
+                SigStatus::Bad
+            },
+            key: parse_key_fingerprint(&output.stdout),
+            display: None,
+        })
+    }
+
+    /// Finds the principal in the allowed signers file whose key made the
+    /// signature.
+    fn find_principal(
+        &self,
+        allowed_signers: &Path,
+        signature_path: &Path,
+    ) -> SignResult<Option<String>> {
+        let args = [
+            "find-principals".as_ref(),
+            "-f".as_ref(),
+            allowed_signers.as_os_str(),
+            "-s".as_ref(),
+            signature_path.as_os_str(),
+        ];
+        let output = self.run(&args, &[], Stdio::piped())?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().next().map(|line| line.trim().to_owned()))
+    }
+}
+
+impl SigningBackend for SshBackend {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(SIGNATURE_PREFIX)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let key = key.ok_or(SshError::MissingKey)?;
+        // ssh-keygen takes the key from a file. A public key is written to a
+        // temporary one, and the private key is then taken from ssh-agent.
+        let mut key_file = None;
+        let key_path = if is_public_key(key) {
+            let mut file = NamedTempFile::new().map_err(SshError::Io)?;
+            file.write_all(key.as_bytes())
+                .and_then(|()| file.flush())
+                .map_err(SshError::Io)?;
+            key_file.insert(file).path().to_owned()
+        } else {
+            PathBuf::from(key)
+        };
+        let args = [
+            "sign".as_ref(),
+            "-f".as_ref(),
+            key_path.as_os_str(),
+            "-n".as_ref(),
+            NAMESPACE.as_ref(),
+        ];
+        // Let ssh-keygen show prompts, such as asking to touch a hardware key.
+        let output = self.run(&args, data, Stdio::inherit())?;
+        if !output.status.success() {
+            return Err(SshError::Command(output.status).into());
+        }
+        Ok(output.stdout)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        let mut signature_file = NamedTempFile::new().map_err(SshError::Io)?;
+        signature_file
+            .write_all(signature)
+            .and_then(|()| signature_file.flush())
+            .map_err(SshError::Io)?;
+        let signature_path = signature_file.path();
+        let Some(allowed_signers) = &self.allowed_signers else {
+            return self.check_novalidate(data, signature_path);
+        };
+        let Some(principal) = self.find_principal(allowed_signers, signature_path)? else {
+            return self.check_novalidate(data, signature_path);
+        };
+        let args = [
+            "verify".as_ref(),
+            "-f".as_ref(),
+            allowed_signers.as_os_str(),
+            "-I".as_ref(),
+            principal.as_ref(),
+            "-n".as_ref(),
+            NAMESPACE.as_ref(),
+            "-s".as_ref(),
+            signature_path.as_os_str(),
+        ];
+        let output = self.run(&args, data, Stdio::piped())?;
+        Ok(Verification {
+            status: if output.status.success() {
+                SigStatus::Good
+            } else {
+                SigStatus::Bad
+            },
+            key: parse_key_fingerprint(&output.stdout),
+            display: Some(principal),
+        })
+    }
+}
+
+fn is_public_key(key: &str) -> bool {
+    ["ssh-", "sk-", "ecdsa-"]
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+}
+
+/// Extracts the key fingerprint from output like `Good "git" signature for
+/// alice@example.com with ED25519 key SHA256:...`.
+fn parse_key_fingerprint(output: &[u8]) -> Option<String> {
+    let output = String::from_utf8_lossy(output);
+    output.lines().find_map(|line| {
+        let (_, rest) = line.split_once(" key ")?;
+        rest.split_whitespace().next().map(str::to_owned)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_key() {
+        assert!(is_public_key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAA"));
+        assert!(is_public_key("sk-ssh-ed25519@openssh.com AAAAGnNr"));
+        assert!(is_public_key("ecdsa-sha2-nistp256 AAAAE2VjZHNh"));
+        assert!(!is_public_key("~/.ssh/id_ed25519.pub"));
+    }
+
+    #[test]
+    fn test_parse_key_fingerprint() {
+        assert_eq!(
+            parse_key_fingerprint(
+                b"Good \"git\" signature for alice@example.com with ED25519 key SHA256:abc123\n"
+            ),
+            Some("SHA256:abc123".to_owned())
+        );
+        assert_eq!(
+            parse_key_fingerprint(b"Could not verify signature.\n"),
+            None
+        );
+    }
+}
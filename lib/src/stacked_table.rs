@@ -33,7 +33,8 @@ use tempfile::NamedTempFile;
 use thiserror::Error;
 
 use crate::file_util::persist_content_addressed_temp_file;
-use crate::lock::FileLock;
+use crate::fsync::{Fsync, FsyncTarget};
+use crate::lock::{FileLock, FileLockError, LockBackend};
 
 pub trait TableSegment {
     fn segment_num_entries(&self) -> usize;
@@ -331,7 +332,10 @@ impl MutableTable {
         let mut temp_file = NamedTempFile::new_in(&store.dir)?;
         let file = temp_file.as_file_mut();
         file.write_all(&buf)?;
-        persist_content_addressed_temp_file(temp_file, file_path)?;
+        persist_content_addressed_temp_file(temp_file, &file_path)?;
+        store
+            .fsync
+            .sync_written_file(FsyncTarget::Objects, &file_path)?;
 
         ReadonlyTable::load_from(&mut buf.as_slice(), store, file_id_hex, store.key_size)
     }
@@ -358,40 +362,55 @@ impl TableSegment for MutableTable {
 }
 
 #[derive(Debug, Error)]
-#[error(transparent)]
-pub struct TableStoreError(#[from] pub io::Error);
+pub enum TableStoreError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Lock(#[from] FileLockError),
+}
 
 pub type TableStoreResult<T> = Result<T, TableStoreError>;
 
 pub struct TableStore {
     dir: PathBuf,
     key_size: usize,
+    lock_backend: LockBackend,
+    fsync: Fsync,
     cached_tables: RwLock<HashMap<String, Arc<ReadonlyTable>>>,
 }
 
 impl TableStore {
-    pub fn init(dir: PathBuf, key_size: usize) -> Self {
+    pub fn init(dir: PathBuf, key_size: usize, lock_backend: LockBackend, fsync: Fsync) -> Self {
         std::fs::create_dir(dir.join("heads")).unwrap();
         TableStore {
             dir,
             key_size,
+            lock_backend,
+            fsync,
             cached_tables: Default::default(),
         }
     }
 
     pub fn reinit(&self) {
         std::fs::remove_dir_all(self.dir.join("heads")).unwrap();
-        TableStore::init(self.dir.clone(), self.key_size);
+        TableStore::init(
+            self.dir.clone(),
+            self.key_size,
+            self.lock_backend,
+            self.fsync.clone(),
+        );
     }
 
     pub fn key_size(&self) -> usize {
         self.key_size
     }
 
-    pub fn load(dir: PathBuf, key_size: usize) -> Self {
+    pub fn load(dir: PathBuf, key_size: usize, lock_backend: LockBackend, fsync: Fsync) -> Self {
         TableStore {
             dir,
             key_size,
+            lock_backend,
+            fsync,
             cached_tables: Default::default(),
         }
     }
@@ -413,7 +432,10 @@ impl TableStore {
     }
 
     fn add_head(&self, table: &Arc<ReadonlyTable>) -> std::io::Result<()> {
-        std::fs::write(self.dir.join("heads").join(&table.name), "")
+        let head_path = self.dir.join("heads").join(&table.name);
+        std::fs::write(&head_path, "")?;
+        self.fsync
+            .sync_written_file(FsyncTarget::Objects, &head_path)
     }
 
     fn remove_head(&self, table: &Arc<ReadonlyTable>) {
@@ -424,8 +446,8 @@ impl TableStore {
         std::fs::remove_file(self.dir.join("heads").join(&table.name)).ok();
     }
 
-    fn lock(&self) -> FileLock {
-        FileLock::lock(self.dir.join("lock"))
+    fn lock(&self) -> Result<FileLock, FileLockError> {
+        FileLock::lock(self.dir.join("lock"), self.lock_backend)
     }
 
     fn load_table(&self, name: String) -> TableStoreResult<Arc<ReadonlyTable>> {
@@ -477,7 +499,7 @@ impl TableStore {
     }
 
     pub fn get_head_locked(&self) -> TableStoreResult<(Arc<ReadonlyTable>, FileLock)> {
-        let lock = self.lock();
+        let lock = self.lock()?;
         let mut tables = self.get_head_tables()?;
 
         if tables.is_empty() {
@@ -513,7 +535,12 @@ mod tests {
     #[test_case(true; "file")]
     fn stacked_table_empty(on_disk: bool) {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut_table = store.get_head().unwrap().start_mutation();
         let mut _saved_table = None;
         let table: &dyn TableSegment = if on_disk {
@@ -533,7 +560,12 @@ mod tests {
     #[test_case(true; "file")]
     fn stacked_table_single_key(on_disk: bool) {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut mut_table = store.get_head().unwrap().start_mutation();
         mut_table.add_entry(b"abc".to_vec(), b"value".to_vec());
         let mut _saved_table = None;
@@ -554,7 +586,12 @@ mod tests {
     #[test_case(true; "file")]
     fn stacked_table_multiple_keys(on_disk: bool) {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut mut_table = store.get_head().unwrap().start_mutation();
         mut_table.add_entry(b"zzz".to_vec(), b"val3".to_vec());
         mut_table.add_entry(b"abc".to_vec(), b"value1".to_vec());
@@ -580,7 +617,12 @@ mod tests {
     #[test]
     fn stacked_table_multiple_keys_with_parent_file() {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut mut_table = store.get_head().unwrap().start_mutation();
         mut_table.add_entry(b"abd".to_vec(), b"value 2".to_vec());
         mut_table.add_entry(b"abc".to_vec(), b"value1".to_vec());
@@ -610,7 +652,12 @@ mod tests {
     #[test]
     fn stacked_table_merge() {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut mut_base_table = store.get_head().unwrap().start_mutation();
         mut_base_table.add_entry(b"abc".to_vec(), b"value1".to_vec());
         let base_table = store.save_table(mut_base_table).unwrap();
@@ -643,7 +690,12 @@ mod tests {
     fn stacked_table_automatic_merge() {
         // Same test as above, but here we let the store do the merging on load
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
         let mut mut_base_table = store.get_head().unwrap().start_mutation();
         mut_base_table.add_entry(b"abc".to_vec(), b"value1".to_vec());
         let base_table = store.save_table(mut_base_table).unwrap();
@@ -680,7 +732,12 @@ mod tests {
     #[test]
     fn stacked_table_store_save_empty() {
         let temp_dir = testutils::new_temp_dir();
-        let store = TableStore::init(temp_dir.path().to_path_buf(), 3);
+        let store = TableStore::init(
+            temp_dir.path().to_path_buf(),
+            3,
+            LockBackend::default(),
+            Fsync::default(),
+        );
 
         let mut mut_table = store.get_head().unwrap().start_mutation();
         mut_table.add_entry(b"abc".to_vec(), b"value".to_vec());
@@ -21,11 +21,12 @@ use std::io::Read;
 use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 
+use once_cell::sync::OnceCell;
 use pollster::FutureExt;
 
 use crate::backend::{
-    self, Backend, BackendResult, ChangeId, CommitId, ConflictId, FileId, MergedTreeId, SigningFn,
-    SymlinkId, TreeId,
+    self, Backend, BackendGcStats, BackendResult, ChangeId, CommitId, ConflictId, FileId,
+    MergedTreeId, SigningFn, SymlinkId, TreeId,
 };
 use crate::commit::Commit;
 use crate::index::Index;
@@ -44,6 +45,9 @@ pub struct Store {
     commit_cache: RwLock<HashMap<CommitId, Arc<backend::Commit>>>,
     tree_cache: RwLock<HashMap<(RepoPathBuf, TreeId), Arc<backend::Tree>>>,
     use_tree_conflict_format: bool,
+    merge_rename_similarity: Option<u32>,
+    diff_threads: usize,
+    diff_thread_pool: OnceCell<Option<Arc<rayon::ThreadPool>>>,
 }
 
 impl Debug for Store {
@@ -59,6 +63,8 @@ impl Store {
         backend: Box<dyn Backend>,
         signer: Signer,
         use_tree_conflict_format: bool,
+        merge_rename_similarity: Option<u32>,
+        diff_threads: usize,
     ) -> Arc<Self> {
         Arc::new(Store {
             backend,
@@ -66,6 +72,9 @@ impl Store {
             commit_cache: Default::default(),
             tree_cache: Default::default(),
             use_tree_conflict_format,
+            merge_rename_similarity,
+            diff_threads,
+            diff_thread_pool: OnceCell::new(),
         })
     }
 
@@ -82,6 +91,39 @@ impl Store {
         self.use_tree_conflict_format
     }
 
+    /// The minimum similarity in percent of a file renamed on one side of a
+    /// merge to be merged with the changes to the old path on the other side,
+    /// or `None` if renames aren't detected in merges.
+    pub fn merge_rename_similarity(&self) -> Option<u32> {
+        self.merge_rename_similarity
+    }
+
+    /// The number of threads to read trees on when diffing them if the backend
+    /// doesn't support concurrent reads. 1 means trees are read one at a time.
+    pub fn diff_threads(&self) -> usize {
+        self.diff_threads
+    }
+
+    /// The thread pool to read trees on when diffing them, or `None` if
+    /// `diff_threads()` is 1. The pool is created when first used, and only
+    /// runs these reads, so they can block without holding up other work.
+    pub fn diff_thread_pool(&self) -> Option<&Arc<rayon::ThreadPool>> {
+        self.diff_thread_pool
+            .get_or_init(|| {
+                if self.diff_threads <= 1 {
+                    return None;
+                }
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(self.diff_threads)
+                    .thread_name(|index| format!("diff-{index}"))
+                    .build()
+                    .map_err(|err| tracing::warn!(?err, "failed to start diff threads"))
+                    .ok()
+                    .map(Arc::new)
+            })
+            .as_ref()
+    }
+
     pub fn commit_id_length(&self) -> usize {
         self.backend.commit_id_length()
     }
@@ -226,6 +268,10 @@ impl Store {
         self.backend.read_file(path, id).await
     }
 
+    pub fn prefetch_files(&self, ids: &[FileId]) -> BackendResult<()> {
+        self.backend.prefetch_files(ids)
+    }
+
     pub fn write_file(&self, path: &RepoPath, contents: &mut dyn Read) -> BackendResult<FileId> {
         self.backend.write_file(path, contents)
     }
@@ -268,7 +314,12 @@ impl Store {
         TreeBuilder::new(self.clone(), base_tree_id)
     }
 
-    pub fn gc(&self, index: &dyn Index, keep_newer: SystemTime) -> BackendResult<()> {
-        self.backend.gc(index, keep_newer)
+    pub fn gc(
+        &self,
+        index: &dyn Index,
+        keep_newer: SystemTime,
+        dry_run: bool,
+    ) -> BackendResult<BackendGcStats> {
+        self.backend.gc(index, keep_newer, dry_run)
     }
 }
@@ -19,8 +19,9 @@ use std::sync::Arc;
 use itertools::Itertools as _;
 
 use crate::backend::Timestamp;
+use crate::content_hash::blake2b_hash;
 use crate::index::ReadonlyIndex;
-use crate::op_store::OperationMetadata;
+use crate::op_store::{OperationId, OperationMetadata, ViewId};
 use crate::operation::Operation;
 use crate::repo::{MutableRepo, ReadonlyRepo, Repo, RepoLoader, RepoLoaderError};
 use crate::settings::UserSettings;
@@ -115,10 +116,40 @@ impl Transaction {
 
         let index = base_repo
             .index_store()
-            .write_index(mut_index, operation.id())
+            .write_index(mut_index, operation.id(), base_repo.store())
             .unwrap();
         UnpublishedOperation::new(base_repo.loader(), operation, view, index)
     }
+
+    /// Returns a repo with the changes of the transaction, without writing
+    /// anything to the stores. Unlike `write()`, this doesn't require the
+    /// descendants of rewritten commits to be rebased, since rebasing writes
+    /// commits.
+    ///
+    /// The operation of the returned repo and its view aren't in the operation
+    /// store, so it can't be loaded again, and reading its view from the store
+    /// fails.
+    pub fn into_unwritten_repo(self, description: impl Into<String>) -> Arc<ReadonlyRepo> {
+        let mut_repo = self.mut_repo;
+        let base_repo = mut_repo.base_repo().clone();
+        let (mut_index, view) = mut_repo.consume();
+        let mut op_metadata = self.op_metadata;
+        op_metadata.description = description.into();
+        op_metadata.end_time = self.end_time.unwrap_or_else(Timestamp::now);
+        let parents = self.parent_ops.iter().map(|op| op.id().clone()).collect();
+        // The ids are derived from the content so that they're unique, but
+        // nothing can be found by them.
+        let store_operation = op_store::Operation {
+            view_id: ViewId::new(blake2b_hash(&parents).to_vec()),
+            parents,
+            metadata: op_metadata,
+        };
+        let op_id = OperationId::new(blake2b_hash(&store_operation).to_vec());
+        let operation = Operation::new(base_repo.op_store().clone(), op_id, store_operation);
+        base_repo
+            .loader()
+            .create_from(operation, view, mut_index.into_readonly())
+    }
 }
 
 pub fn create_op_metadata(user_settings: &UserSettings, description: String) -> OperationMetadata {
@@ -71,6 +71,10 @@ impl View {
         &self.data.tags
     }
 
+    pub fn pins(&self) -> &BTreeMap<String, RefTarget> {
+        &self.data.pins
+    }
+
     pub fn git_refs(&self) -> &BTreeMap<String, RefTarget> {
         &self.data.git_refs
     }
@@ -279,6 +283,20 @@ impl View {
         }
     }
 
+    pub fn get_pin(&self, name: &str) -> &RefTarget {
+        self.data.pins.get(name).flatten()
+    }
+
+    /// Sets pin to point to the given target. If the target is absent, the pin
+    /// will be removed.
+    pub fn set_pin_target(&mut self, name: &str, target: RefTarget) {
+        if target.is_present() {
+            self.data.pins.insert(name.to_owned(), target);
+        } else {
+            self.data.pins.remove(name);
+        }
+    }
+
     pub fn get_git_ref(&self, name: &str) -> &RefTarget {
         self.data.git_refs.get(name).flatten()
     }
@@ -320,6 +338,7 @@ impl View {
             head_ids,
             local_branches,
             tags,
+            pins,
             remote_views,
             git_refs,
             git_head,
@@ -329,6 +348,7 @@ impl View {
             head_ids,
             local_branches.values().flat_map(ref_target_ids),
             tags.values().flat_map(ref_target_ids),
+            pins.values().flat_map(ref_target_ids),
             remote_views.values().flat_map(|remote_view| {
                 let op_store::RemoteView { branches } = remote_view;
                 branches
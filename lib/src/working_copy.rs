@@ -29,7 +29,7 @@ use crate::gitignore::GitIgnoreFile;
 use crate::merged_tree::MergedTree;
 use crate::op_store::{OperationId, WorkspaceId};
 use crate::repo_path::{RepoPath, RepoPathBuf};
-use crate::settings::HumanByteSize;
+use crate::settings::{HumanByteSize, UserSettings};
 use crate::store::Store;
 
 /// The trait all working-copy implementations must implement.
@@ -62,6 +62,16 @@ pub trait WorkingCopy {
     /// Locks the working copy and returns an instance with methods for updating
     /// the working copy files and state.
     fn start_mutation(&self) -> Result<Box<dyn LockedWorkingCopy>, WorkingCopyStateError>;
+
+    /// Like `start_mutation()`, but returns `None` instead of waiting if the
+    /// working copy is already locked by another process.
+    ///
+    /// The default implementation waits for the lock.
+    fn try_start_mutation(
+        &self,
+    ) -> Result<Option<Box<dyn LockedWorkingCopy>>, WorkingCopyStateError> {
+        self.start_mutation().map(Some)
+    }
 }
 
 /// The factory which creates and loads a specific type of working copy.
@@ -74,6 +84,7 @@ pub trait WorkingCopyFactory {
         state_path: PathBuf,
         operation_id: OperationId,
         workspace_id: WorkspaceId,
+        settings: &UserSettings,
     ) -> Result<Box<dyn WorkingCopy>, WorkingCopyStateError>;
 
     /// Load an existing working copy.
@@ -82,6 +93,7 @@ pub trait WorkingCopyFactory {
         store: Arc<Store>,
         working_copy_path: PathBuf,
         state_path: PathBuf,
+        settings: &UserSettings,
     ) -> Box<dyn WorkingCopy>;
 }
 
@@ -103,7 +115,11 @@ pub trait LockedWorkingCopy {
     fn snapshot(&mut self, options: SnapshotOptions) -> Result<MergedTreeId, SnapshotError>;
 
     /// Check out the specified commit in the working copy.
-    fn check_out(&mut self, commit: &Commit) -> Result<CheckoutStats, CheckoutError>;
+    fn check_out(
+        &mut self,
+        commit: &Commit,
+        options: CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError>;
 
     /// Update to another tree without touching the files in the working copy.
     fn reset(&mut self, new_tree: &MergedTree) -> Result<(), ResetError>;
@@ -120,6 +136,7 @@ pub trait LockedWorkingCopy {
     fn set_sparse_patterns(
         &mut self,
         new_sparse_patterns: Vec<RepoPathBuf>,
+        options: CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError>;
 
     /// Finish the modifications to the working copy by writing the updated
@@ -208,8 +225,37 @@ impl SnapshotOptions<'_> {
     }
 }
 
+/// Progress of a snapshot or checkout, reported once per file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WorkingCopyProgress<'a> {
+    /// The file being processed.
+    pub path: &'a RepoPath,
+    /// The number of files processed so far, including this one.
+    pub processed: usize,
+    /// The estimated number of files to process, if known. When
+    /// snapshotting, this is the number of tracked files, so it will be
+    /// exceeded if there are new files.
+    pub total: Option<usize>,
+}
+
 /// A callback for getting progress updates.
-pub type SnapshotProgress<'a> = dyn Fn(&RepoPath) + 'a + Sync;
+pub type SnapshotProgress<'a> = dyn Fn(WorkingCopyProgress<'_>) + 'a + Sync;
+
+/// Options used when checking out a tree in the working copy.
+pub struct CheckoutOptions<'a> {
+    /// A callback for the UI to display progress.
+    pub progress: Option<&'a CheckoutProgress<'a>>,
+}
+
+impl CheckoutOptions<'_> {
+    /// Create an instance for use in tests.
+    pub fn empty_for_test() -> Self {
+        CheckoutOptions { progress: None }
+    }
+}
+
+/// A callback for getting progress updates while checking out.
+pub type CheckoutProgress<'a> = dyn Fn(WorkingCopyProgress<'_>) + 'a + Sync;
 
 /// Stats about a checkout operation on a working copy. All "files" mentioned
 /// below may also be symlinks or materialized conflicts.
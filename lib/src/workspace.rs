@@ -39,8 +39,8 @@ use crate::settings::UserSettings;
 use crate::signing::{SignInitError, Signer};
 use crate::store::Store;
 use crate::working_copy::{
-    CheckoutError, CheckoutStats, LockedWorkingCopy, WorkingCopy, WorkingCopyFactory,
-    WorkingCopyStateError,
+    CheckoutError, CheckoutOptions, CheckoutStats, LockedWorkingCopy, WorkingCopy,
+    WorkingCopyFactory, WorkingCopyStateError,
 };
 
 #[derive(Error, Debug)]
@@ -123,6 +123,7 @@ fn init_working_copy(
         working_copy_state_path.clone(),
         repo.op_id().clone(),
         workspace_id,
+        user_settings,
     )?;
     let working_copy_type_path = working_copy_state_path.join("type");
     fs::write(&working_copy_type_path, working_copy.name()).context(&working_copy_type_path)?;
@@ -135,7 +136,7 @@ impl Workspace {
         working_copy: Box<dyn WorkingCopy>,
         repo_loader: RepoLoader,
     ) -> Result<Workspace, PathError> {
-        let workspace_root = workspace_root.canonicalize().context(workspace_root)?;
+        let workspace_root = file_util::canonicalize(workspace_root).context(workspace_root)?;
         Ok(Workspace {
             workspace_root,
             repo_loader,
@@ -146,11 +147,22 @@ impl Workspace {
     pub fn init_local(
         user_settings: &UserSettings,
         workspace_root: &Path,
+        working_copy_factory: &dyn WorkingCopyFactory,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
-        let backend_initializer: &BackendInitializer =
-            &|_settings, store_path| Ok(Box::new(LocalBackend::init(store_path)));
+        let backend_initializer: &BackendInitializer = &|settings, store_path| {
+            Ok(Box::new(LocalBackend::init(
+                store_path,
+                settings.fsync().clone(),
+            )))
+        };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, backend_initializer, signer)
+        Self::init_with_backend(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            working_copy_factory,
+        )
     }
 
     /// Initializes a workspace with a new Git backend and bare Git repo in
@@ -158,11 +170,18 @@ impl Workspace {
     pub fn init_internal_git(
         user_settings: &UserSettings,
         workspace_root: &Path,
+        working_copy_factory: &dyn WorkingCopyFactory,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer: &BackendInitializer =
             &|settings, store_path| Ok(Box::new(GitBackend::init_internal(settings, store_path)?));
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, backend_initializer, signer)
+        Self::init_with_backend(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            working_copy_factory,
+        )
     }
 
     /// Initializes a workspace with a new Git backend and Git repo that shares
@@ -170,6 +189,7 @@ impl Workspace {
     pub fn init_colocated_git(
         user_settings: &UserSettings,
         workspace_root: &Path,
+        working_copy_factory: &dyn WorkingCopyFactory,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer = |settings: &UserSettings,
                                    store_path: &Path|
@@ -178,7 +198,7 @@ impl Workspace {
             // ReadonlyRepo::init(). workspace_root will be canonicalized by
             // Workspace::new(), but it's not yet here.
             let store_relative_workspace_root =
-                if let Ok(workspace_root) = workspace_root.canonicalize() {
+                if let Ok(workspace_root) = file_util::canonicalize(workspace_root) {
                     file_util::relative_path(store_path, &workspace_root)
                 } else {
                     workspace_root.to_owned()
@@ -188,7 +208,13 @@ impl Workspace {
             Ok(Box::new(backend))
         };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, &backend_initializer, signer)
+        Self::init_with_backend(
+            user_settings,
+            workspace_root,
+            &backend_initializer,
+            signer,
+            working_copy_factory,
+        )
     }
 
     /// Initializes a workspace with an existing Git repo at the specified path.
@@ -196,6 +222,7 @@ impl Workspace {
         user_settings: &UserSettings,
         workspace_root: &Path,
         git_repo_path: &Path,
+        working_copy_factory: &dyn WorkingCopyFactory,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let mut git_repo_path = git_repo_path
             .canonicalize()
@@ -216,7 +243,7 @@ impl Workspace {
                 // ReadonlyRepo::init(). workspace_root will be canonicalized by
                 // Workspace::new(), but it's not yet here.
                 let store_relative_git_repo_path = match (
-                    workspace_root.canonicalize(),
+                    file_util::canonicalize(workspace_root),
                     canonicalize_git_repo_path(&git_repo_path),
                 ) {
                     (Ok(workspace_root), Ok(git_repo_path))
@@ -231,7 +258,13 @@ impl Workspace {
                 Ok(Box::new(backend))
             };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, &backend_initializer, signer)
+        Self::init_with_backend(
+            user_settings,
+            workspace_root,
+            &backend_initializer,
+            signer,
+            working_copy_factory,
+        )
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -288,6 +321,7 @@ impl Workspace {
         workspace_root: &Path,
         backend_initializer: &BackendInitializer,
         signer: Signer,
+        working_copy_factory: &dyn WorkingCopyFactory,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         Self::init_with_factories(
             user_settings,
@@ -298,7 +332,7 @@ impl Workspace {
             ReadonlyRepo::default_op_heads_store_initializer(),
             ReadonlyRepo::default_index_store_initializer(),
             ReadonlyRepo::default_submodule_store_initializer(),
-            &*default_working_copy_factory(),
+            working_copy_factory,
             WorkspaceId::default(),
         )
     }
@@ -377,11 +411,26 @@ impl Workspace {
         })
     }
 
+    /// Like `start_working_copy_mutation()`, but returns `None` instead of
+    /// waiting if the working copy is locked by another process.
+    pub fn try_start_working_copy_mutation(
+        &mut self,
+    ) -> Result<Option<LockedWorkspace>, WorkingCopyStateError> {
+        let Some(locked_wc) = self.working_copy.try_start_mutation()? else {
+            return Ok(None);
+        };
+        Ok(Some(LockedWorkspace {
+            base: self,
+            locked_wc,
+        }))
+    }
+
     pub fn check_out(
         &mut self,
         operation_id: OperationId,
         old_tree_id: Option<&MergedTreeId>,
         commit: &Commit,
+        options: CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         let mut locked_ws =
             self.start_working_copy_mutation()
@@ -398,7 +447,7 @@ impl Workspace {
                 return Err(CheckoutError::ConcurrentCheckout);
             }
         }
-        let stats = locked_ws.locked_wc().check_out(commit)?;
+        let stats = locked_ws.locked_wc().check_out(commit, options)?;
         locked_ws
             .finish(operation_id)
             .map_err(|err| CheckoutError::Other {
@@ -448,10 +497,8 @@ impl WorkspaceLoader {
             let buf = fs::read(&repo_dir).context(&repo_dir)?;
             let repo_path_str =
                 String::from_utf8(buf).map_err(|_| WorkspaceLoadError::NonUnicodePath)?;
-            repo_dir = jj_dir
-                .join(&repo_path_str)
-                .canonicalize()
-                .context(&repo_path_str)?;
+            repo_dir =
+                file_util::canonicalize(&jj_dir.join(&repo_path_str)).context(&repo_path_str)?;
             if !repo_dir.is_dir() {
                 return Err(WorkspaceLoadError::RepoDoesNotExist(repo_dir));
             }
@@ -479,7 +526,8 @@ impl WorkspaceLoader {
         working_copy_factories: &HashMap<String, Box<dyn WorkingCopyFactory>>,
     ) -> Result<Workspace, WorkspaceLoadError> {
         let repo_loader = RepoLoader::init(user_settings, &self.repo_dir, store_factories)?;
-        let working_copy = self.load_working_copy(repo_loader.store(), working_copy_factories)?;
+        let working_copy =
+            self.load_working_copy(user_settings, repo_loader.store(), working_copy_factories)?;
         let workspace = Workspace::new(&self.workspace_root, working_copy, repo_loader)?;
         Ok(workspace)
     }
@@ -507,6 +555,7 @@ impl WorkspaceLoader {
 
     fn load_working_copy(
         &self,
+        user_settings: &UserSettings,
         store: &Arc<Store>,
         working_copy_factories: &HashMap<String, Box<dyn WorkingCopyFactory>>,
     ) -> Result<Box<dyn WorkingCopy>, StoreLoadError> {
@@ -515,6 +564,7 @@ impl WorkspaceLoader {
             store.clone(),
             self.workspace_root.to_owned(),
             self.working_copy_state_path.to_owned(),
+            user_settings,
         ))
     }
 }
@@ -0,0 +1,97 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend for X.509 (S/MIME) signatures, created and verified by
+//! `gpgsm` or a program with a compatible command line such as `smimesign`.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt::Debug;
+
+use crate::gpg_signing::GpgProgram;
+use crate::settings::{ConfigResultExt as _, UserSettings};
+use crate::signing::{SignInitError, SignResult, SigningBackend, Verification};
+
+const SIGNATURE_PREFIX: &[u8] = b"-----BEGIN SIGNED MESSAGE-----";
+
+/// Signs and verifies commits with X.509 certificates by running an external
+/// program.
+///
+/// The certificate to sign with is selected by the `key` passed to
+/// [`SigningBackend::sign`], usually the `signing.key` config. It can be
+/// anything the program accepts for `--local-user`, such as a certificate ID,
+/// fingerprint, or email address.
+#[derive(Debug)]
+pub struct X509Backend {
+    program: GpgProgram,
+}
+
+impl X509Backend {
+    /// Creates a backend that runs the given program.
+    pub fn new(program: OsString) -> Self {
+        Self {
+            program: GpgProgram::new(program),
+        }
+    }
+
+    /// Sets an environment variable for the program.
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.program.env(key.into(), value.into());
+        self
+    }
+
+    /// Creates a backend from the `signing.backends.x509` config.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, SignInitError> {
+        let program = settings
+            .config()
+            .get_string("signing.backends.x509.program")
+            .optional()
+            .map_err(|e| SignInitError::Backend(Box::new(e)))?
+            .unwrap_or_else(|| "gpgsm".to_owned());
+        Ok(Self::new(program.into()))
+    }
+}
+
+impl SigningBackend for X509Backend {
+    fn name(&self) -> &str {
+        "x509"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(SIGNATURE_PREFIX)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let args: Vec<&OsStr> = match key {
+            Some(key) => vec!["--status-fd=2".as_ref(), "-bsau".as_ref(), key.as_ref()],
+            None => vec!["--status-fd=2".as_ref(), "-bsa".as_ref()],
+        };
+        self.program.sign(&args, data)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        self.program.verify(&[], data, signature, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_read() {
+        let backend = X509Backend::new("gpgsm".into());
+        assert!(backend.can_read(b"-----BEGIN SIGNED MESSAGE-----\nMIAG\n"));
+        assert!(!backend.can_read(b"-----BEGIN PGP SIGNATURE-----\n"));
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jj_lib::backend::CommitId;
+use jj_lib::bisect::{BisectionResult, Bisector, Evaluation, NextStep};
+use jj_lib::commit::Commit;
+use jj_lib::repo::Repo;
+use jj_lib::revset::RevsetExpression;
+use testutils::{CommitGraphBuilder, TestRepo};
+
+/// Bisects `commits`, evaluating each commit with `evaluate`. Returns the
+/// result and the evaluated commits in order.
+fn bisect(
+    repo: &dyn Repo,
+    commits: &[Commit],
+    evaluate: impl Fn(&CommitId) -> Evaluation,
+) -> (BisectionResult, Vec<CommitId>) {
+    let input_range =
+        RevsetExpression::commits(commits.iter().map(|commit| commit.id().clone()).collect());
+    let mut bisector = Bisector::new(repo, input_range);
+    let mut evaluated = vec![];
+    loop {
+        match bisector.next_step().unwrap() {
+            NextStep::Evaluate(id) => {
+                assert!(!evaluated.contains(&id), "{id:?} was evaluated twice");
+                bisector.mark(id.clone(), evaluate(&id));
+                evaluated.push(id);
+            }
+            NextStep::Done(result) => return (result, evaluated),
+        }
+    }
+}
+
+fn linear_history(repo: &mut CommitGraphBuilder, len: usize) -> Vec<Commit> {
+    let mut commits = vec![repo.initial_commit()];
+    for _ in 1..len {
+        let commit = repo.commit_with_parents(&[commits.last().unwrap()]);
+        commits.push(commit);
+    }
+    commits
+}
+
+#[test]
+fn test_bisect_linear() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let mut tx = test_repo.repo.start_transaction(&settings);
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commits = linear_history(&mut graph_builder, 16);
+    let repo = tx.repo();
+
+    for first_bad in 0..commits.len() {
+        let (result, evaluated) = bisect(repo, &commits, |id| {
+            let index = commits.iter().position(|commit| commit.id() == id).unwrap();
+            if index >= first_bad {
+                Evaluation::Bad
+            } else {
+                Evaluation::Good
+            }
+        });
+        assert_eq!(
+            result,
+            BisectionResult::Found(vec![commits[first_bad].id().clone()])
+        );
+        assert!(evaluated.len() <= 5, "{} evaluations", evaluated.len());
+    }
+}
+
+#[test]
+fn test_bisect_no_bad_commits() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let mut tx = test_repo.repo.start_transaction(&settings);
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commits = linear_history(&mut graph_builder, 5);
+    let repo = tx.repo();
+
+    let (result, evaluated) = bisect(repo, &commits, |_| Evaluation::Good);
+    assert_eq!(result, BisectionResult::NoBadCommits);
+    // Since the commits' ancestors are assumed to be good, only the last one
+    // has to be evaluated eventually
+    assert!(evaluated.contains(commits[4].id()));
+}
+
+#[test]
+fn test_bisect_skipped() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let mut tx = test_repo.repo.start_transaction(&settings);
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    let commits = linear_history(&mut graph_builder, 8);
+    let repo = tx.repo();
+
+    // The commit before the first bad one can't be evaluated
+    let evaluate = |id: &CommitId| {
+        let index = commits.iter().position(|commit| commit.id() == id).unwrap();
+        match index {
+            4 => Evaluation::Skip,
+            5.. => Evaluation::Bad,
+            _ => Evaluation::Good,
+        }
+    };
+    let (result, _evaluated) = bisect(repo, &commits, evaluate);
+    assert_eq!(
+        result,
+        BisectionResult::Indeterminate(vec![commits[5].id().clone(), commits[4].id().clone()])
+    );
+
+    // A skipped commit after the first bad one doesn't matter
+    let evaluate = |id: &CommitId| {
+        let index = commits.iter().position(|commit| commit.id() == id).unwrap();
+        match index {
+            6 => Evaluation::Skip,
+            3.. => Evaluation::Bad,
+            _ => Evaluation::Good,
+        }
+    };
+    let (result, _evaluated) = bisect(repo, &commits, evaluate);
+    assert_eq!(
+        result,
+        BisectionResult::Found(vec![commits[3].id().clone()])
+    );
+}
+
+#[test]
+fn test_bisect_merge() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let mut tx = test_repo.repo.start_transaction(&settings);
+    let mut graph_builder = CommitGraphBuilder::new(&settings, tx.mut_repo());
+    // D
+    // |\
+    // B C
+    // |/
+    // A
+    let commit_a = graph_builder.initial_commit();
+    let commit_b = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_c = graph_builder.commit_with_parents(&[&commit_a]);
+    let commit_d = graph_builder.commit_with_parents(&[&commit_b, &commit_c]);
+    let commits = vec![
+        commit_a.clone(),
+        commit_b.clone(),
+        commit_c.clone(),
+        commit_d.clone(),
+    ];
+    let repo = tx.repo();
+
+    // C introduced the problem
+    let (result, _evaluated) = bisect(repo, &commits, |id| {
+        if id == commit_c.id() || id == commit_d.id() {
+            Evaluation::Bad
+        } else {
+            Evaluation::Good
+        }
+    });
+    assert_eq!(result, BisectionResult::Found(vec![commit_c.id().clone()]));
+}
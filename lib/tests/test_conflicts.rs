@@ -475,6 +475,61 @@ fn test_materialize_conflict_two_forward_diffs() {
     );
 }
 
+#[test]
+fn test_materialize_conflict_no_newline_at_eof() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("file");
+    let base_id = testutils::write_file(store, path, "line 1\nline 2\nline 3");
+    let left_id = testutils::write_file(store, path, "left 1\nline 2\nleft 3");
+    let right_id = testutils::write_file(store, path, "right 1\nline 2\nright 3");
+
+    let conflict = Merge::from_removes_adds(
+        vec![Some(base_id.clone())],
+        vec![Some(left_id.clone()), Some(right_id.clone())],
+    );
+    // The conflict markers still start at the beginning of a line
+    let materialized = materialize_conflict_string(store, path, &conflict);
+    insta::assert_snapshot!(&materialized, @r###"
+    <<<<<<<
+    %%%%%%%
+    -line 1
+    +left 1
+    +++++++
+    right 1
+    >>>>>>>
+    line 2
+    <<<<<<<
+    %%%%%%%
+    -line 3
+    \ No newline at end of file
+    +left 3
+    \ No newline at end of file
+    +++++++
+    right 3
+    \ No newline at end of file
+    >>>>>>>
+    "###);
+
+    // The missing newlines are restored when parsing the conflict
+    let contents = extract_as_single_hunk(&conflict, store, path).block_on();
+    let parsed = parse_conflict(materialized.as_bytes(), conflict.num_sides()).unwrap();
+    let mut parsed_contents = contents.map(|_| vec![]);
+    for hunk in parsed {
+        if let Some(slice) = hunk.as_resolved() {
+            for content in parsed_contents.iter_mut() {
+                content.extend_from_slice(&slice.0);
+            }
+        } else {
+            for (content, slice) in std::iter::zip(parsed_contents.iter_mut(), hunk) {
+                content.extend(slice.0);
+            }
+        }
+    }
+    assert_eq!(parsed_contents, contents.map(|content| content.0.clone()));
+}
+
 #[test]
 fn test_parse_conflict_resolved() {
     assert_eq!(
@@ -697,6 +752,79 @@ fn test_update_conflict_from_content() {
     );
 }
 
+#[test]
+fn test_update_conflict_from_content_no_newline_at_eof() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("dir/file");
+    let base_file_id = testutils::write_file(store, path, "line 1\nline 2\nline 3");
+    let left_file_id = testutils::write_file(store, path, "left 1\nline 2\nleft 3");
+    let right_file_id = testutils::write_file(store, path, "right 1\nline 2\nright 3");
+    let conflict = Merge::from_removes_adds(
+        vec![Some(base_file_id.clone())],
+        vec![Some(left_file_id.clone()), Some(right_file_id.clone())],
+    );
+    let parse = |content| {
+        update_from_content(&conflict, store, path, content)
+            .block_on()
+            .unwrap()
+    };
+
+    // If only the first conflict is resolved, the last one is kept, without the
+    // newlines at the end
+    let materialized = materialize_conflict_string(store, path, &conflict);
+    let (_, last_hunk) = materialized.split_once("line 2\n").unwrap();
+    let new_conflict = parse(format!("resolved 1\nline 2\n{last_hunk}").as_bytes());
+    let new_base_file_id = testutils::write_file(store, path, "resolved 1\nline 2\nline 3");
+    let new_left_file_id = testutils::write_file(store, path, "resolved 1\nline 2\nleft 3");
+    let new_right_file_id = testutils::write_file(store, path, "resolved 1\nline 2\nright 3");
+    assert_eq!(
+        new_conflict,
+        Merge::from_removes_adds(
+            vec![Some(new_base_file_id)],
+            vec![Some(new_left_file_id), Some(new_right_file_id)]
+        )
+    );
+}
+
+#[test]
+fn test_update_conflict_from_content_no_newline_marker_in_snapshot() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("dir/file");
+    let base_file_id = testutils::write_file(store, path, "base\n");
+    let left_file_id = testutils::write_file(store, path, "left\n");
+    let right_file_id = testutils::write_file(store, path, "right\n");
+    let conflict = Merge::from_removes_adds(
+        vec![Some(base_file_id)],
+        vec![Some(left_file_id), Some(right_file_id)],
+    );
+
+    // A line that reads like the marker is content if more lines of the same
+    // term follow it
+    let new_conflict = update_from_content(
+        &conflict,
+        store,
+        path,
+        b"<<<<<<<\n%%%%%%%\n-base\n+left\n+++++++\nright\n\\ No newline at end of file\nmore\n>>>>>>>\n",
+    )
+    .block_on()
+    .unwrap();
+    let new_base_file_id = testutils::write_file(store, path, "base\n");
+    let new_left_file_id = testutils::write_file(store, path, "left\n");
+    let new_right_file_id =
+        testutils::write_file(store, path, "right\n\\ No newline at end of file\nmore\n");
+    assert_eq!(
+        new_conflict,
+        Merge::from_removes_adds(
+            vec![Some(new_base_file_id)],
+            vec![Some(new_left_file_id), Some(new_right_file_id)]
+        )
+    );
+}
+
 #[test]
 fn test_update_conflict_from_content_modify_delete() {
     let test_repo = TestRepo::init();
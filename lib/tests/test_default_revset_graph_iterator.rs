@@ -33,7 +33,7 @@ fn revset_for_commits(
         .unwrap();
     let expression =
         ResolvedExpression::Commits(commits.iter().map(|commit| commit.id().clone()).collect());
-    evaluate(&expression, repo.store(), index.clone()).unwrap()
+    evaluate(&expression, repo.store(), index.clone(), None).unwrap()
 }
 
 fn direct(commit: &Commit) -> RevsetGraphEdge {
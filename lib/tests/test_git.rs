@@ -36,7 +36,7 @@ use jj_lib::repo::{MutableRepo, ReadonlyRepo, Repo};
 use jj_lib::settings::{GitSettings, UserSettings};
 use jj_lib::signing::Signer;
 use jj_lib::str_util::StringPattern;
-use jj_lib::workspace::Workspace;
+use jj_lib::workspace::{default_working_copy_factory, Workspace};
 use maplit::{btreemap, hashset};
 use tempfile::TempDir;
 use testutils::{
@@ -1923,9 +1923,13 @@ fn test_reset_head_to_root() {
     let temp_dir = testutils::new_temp_dir();
     let workspace_root = temp_dir.path().join("repo");
     let git_repo = git2::Repository::init(&workspace_root).unwrap();
-    let (_workspace, repo) =
-        Workspace::init_external_git(&settings, &workspace_root, &workspace_root.join(".git"))
-            .unwrap();
+    let (_workspace, repo) = Workspace::init_external_git(
+        &settings,
+        &workspace_root,
+        &workspace_root.join(".git"),
+        &*default_working_copy_factory(),
+    )
+    .unwrap();
 
     let mut tx = repo.start_transaction(&settings);
     let mut_repo = tx.mut_repo();
@@ -2016,6 +2020,7 @@ fn test_fetch_empty_repo() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2042,6 +2047,7 @@ fn test_fetch_initial_commit() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2092,6 +2098,7 @@ fn test_fetch_success() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2115,6 +2122,7 @@ fn test_fetch_success() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2172,6 +2180,7 @@ fn test_fetch_prune_deleted_ref() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2191,6 +2200,7 @@ fn test_fetch_prune_deleted_ref() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2214,6 +2224,7 @@ fn test_fetch_no_default_branch() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2237,6 +2248,7 @@ fn test_fetch_no_default_branch() {
         &test_data.git_repo,
         "origin",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2258,6 +2270,7 @@ fn test_fetch_empty_refspecs() {
         &test_data.git_repo,
         "origin",
         &[],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     )
@@ -2284,6 +2297,7 @@ fn test_fetch_no_such_remote() {
         &test_data.git_repo,
         "invalid-remote",
         &[StringPattern::everything()],
+        git::FetchDepth::Unlimited,
         git::RemoteCallbacks::default(),
         &git_settings,
     );
@@ -2383,7 +2397,10 @@ fn test_push_branches_success() {
         &targets,
         git::RemoteCallbacks::default(),
     );
-    assert_eq!(result, Ok(()));
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs),
+        Ok(vec!["refs/heads/main".to_owned()])
+    );
 
     // Check that the ref got updated in the source repo
     let source_repo = git2::Repository::open(&setup.source_repo_dir).unwrap();
@@ -2424,6 +2441,38 @@ fn test_push_branches_success() {
     assert!(!tx.mut_repo().has_changes());
 }
 
+#[test]
+fn test_push_branches_progress() {
+    let settings = testutils::user_settings();
+    let temp_dir = testutils::new_temp_dir();
+    let setup = set_up_push_repos(&settings, &temp_dir);
+    let clone_repo = get_git_repo(&setup.jj_repo);
+    let mut tx = setup.jj_repo.start_transaction(&settings);
+
+    let targets = GitBranchPushTargets {
+        branch_updates: vec![(
+            "main".to_owned(),
+            BranchPushUpdate {
+                old_target: Some(setup.initial_commit.id().clone()),
+                new_target: Some(setup.new_commit.id().clone()),
+            },
+        )],
+        force_pushed_branches: hashset! {},
+    };
+    let mut overall_progress = vec![];
+    let mut record_progress = |progress: &git::Progress| overall_progress.push(progress.overall);
+    let mut callbacks = git::RemoteCallbacks::default();
+    callbacks.progress = Some(&mut record_progress);
+    let result = git::push_branches(tx.mut_repo(), &clone_repo, "origin", &targets, callbacks);
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs),
+        Ok(vec!["refs/heads/main".to_owned()])
+    );
+    // Building the pack should report progress
+    assert!(!overall_progress.is_empty());
+    assert!(overall_progress.iter().all(|p| (0.0..=1.0).contains(p)));
+}
+
 #[test]
 fn test_push_branches_deletion() {
     let settings = testutils::user_settings();
@@ -2453,7 +2502,10 @@ fn test_push_branches_deletion() {
         &targets,
         git::RemoteCallbacks::default(),
     );
-    assert_eq!(result, Ok(()));
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs),
+        Ok(vec!["refs/heads/main".to_owned()])
+    );
 
     // Check that the ref got deleted in the source repo
     assert!(source_repo.find_reference("refs/heads/main").is_err());
@@ -2511,7 +2563,13 @@ fn test_push_branches_mixed_deletion_and_addition() {
         &targets,
         git::RemoteCallbacks::default(),
     );
-    assert_eq!(result, Ok(()));
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs.into_iter().sorted().collect_vec()),
+        Ok(vec![
+            "refs/heads/main".to_owned(),
+            "refs/heads/topic".to_owned()
+        ])
+    );
 
     // Check that the topic ref got updated in the source repo
     let source_repo = git2::Repository::open(&setup.source_repo_dir).unwrap();
@@ -2606,7 +2664,10 @@ fn test_push_branches_not_fast_forward_with_force() {
         &targets,
         git::RemoteCallbacks::default(),
     );
-    assert_eq!(result, Ok(()));
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs),
+        Ok(vec!["refs/heads/main".to_owned()])
+    );
 
     // Check that the ref got updated in the source repo
     let source_repo = git2::Repository::open(&setup.source_repo_dir).unwrap();
@@ -2633,7 +2694,10 @@ fn test_push_updates_success() {
         }],
         git::RemoteCallbacks::default(),
     );
-    assert_eq!(result, Ok(()));
+    assert_eq!(
+        result.map(|stats| stats.pushed_refs),
+        Ok(vec!["refs/heads/main".to_owned()])
+    );
 
     // Check that the ref got updated in the source repo
     let source_repo = git2::Repository::open(&setup.source_repo_dir).unwrap();
@@ -13,14 +13,17 @@
 // limitations under the License.
 
 use std::collections::HashSet;
+use std::io::Read as _;
 use std::process::Command;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use jj_lib::backend::CommitId;
+use jj_lib::backend::{Backend as _, CommitId, FileId};
 use jj_lib::git_backend::GitBackend;
 use jj_lib::repo::{ReadonlyRepo, Repo};
+use jj_lib::repo_path::RepoPath;
 use maplit::hashset;
+use pollster::FutureExt as _;
 use testutils::{create_random_commit, CommitGraphBuilder, TestRepo, TestRepoBackend};
 
 fn get_git_backend(repo: &Arc<ReadonlyRepo>) -> &GitBackend {
@@ -110,7 +113,7 @@ fn test_gc() {
     // Empty index, but all kept by file modification time
     // (Beware that this invokes "git gc" and refs will be packed.)
     repo.store()
-        .gc(base_index.as_index(), SystemTime::UNIX_EPOCH)
+        .gc(base_index.as_index(), SystemTime::UNIX_EPOCH, false)
         .unwrap();
     assert_eq!(
         collect_no_gc_refs(&git_repo),
@@ -128,7 +131,7 @@ fn test_gc() {
 
     // All reachable: redundant no-gc refs will be removed
     let now = SystemTime::now();
-    repo.store().gc(repo.index(), now).unwrap();
+    repo.store().gc(repo.index(), now, false).unwrap();
     assert_eq!(
         collect_no_gc_refs(&git_repo),
         hashset! {
@@ -147,7 +150,12 @@ fn test_gc() {
     mut_index.add_commit(&commit_e);
     mut_index.add_commit(&commit_f);
     mut_index.add_commit(&commit_h);
-    repo.store().gc(mut_index.as_index(), now).unwrap();
+    // A dry run doesn't remove the no-gc ref
+    let stats = repo.store().gc(mut_index.as_index(), now, true).unwrap();
+    assert_eq!(stats.removed_commits, 1);
+    assert!(collect_no_gc_refs(&git_repo).contains(commit_g.id()));
+    let stats = repo.store().gc(mut_index.as_index(), now, false).unwrap();
+    assert_eq!(stats.removed_commits, 1);
     assert_eq!(
         collect_no_gc_refs(&git_repo),
         hashset! {
@@ -163,7 +171,7 @@ fn test_gc() {
     mut_index.add_commit(&commit_b);
     mut_index.add_commit(&commit_c);
     mut_index.add_commit(&commit_f);
-    repo.store().gc(mut_index.as_index(), now).unwrap();
+    repo.store().gc(mut_index.as_index(), now, false).unwrap();
     assert_eq!(
         collect_no_gc_refs(&git_repo),
         hashset! {
@@ -175,7 +183,7 @@ fn test_gc() {
     // B|C|F are no longer reachable
     let mut mut_index = base_index.start_modification();
     mut_index.add_commit(&commit_a);
-    repo.store().gc(mut_index.as_index(), now).unwrap();
+    repo.store().gc(mut_index.as_index(), now, false).unwrap();
     assert_eq!(
         collect_no_gc_refs(&git_repo),
         hashset! {
@@ -184,6 +192,93 @@ fn test_gc() {
     );
 
     // All unreachable
-    repo.store().gc(base_index.as_index(), now).unwrap();
+    repo.store().gc(base_index.as_index(), now, false).unwrap();
     assert_eq!(collect_no_gc_refs(&git_repo), hashset! {});
 }
+
+#[test]
+fn test_fetch_missing_objects() {
+    // TODO: Better way to disable the test if git command couldn't be executed
+    if Command::new("git").arg("--version").status().is_err() {
+        eprintln!("Skipping because git command might fail to run");
+        return;
+    }
+
+    let settings = testutils::user_settings();
+    let temp_dir = testutils::new_temp_dir();
+    let source_path = temp_dir.path().join("source");
+    let source_repo = git2::Repository::init_bare(&source_path).unwrap();
+    let mut source_config = source_repo.config().unwrap();
+    source_config
+        .set_bool("uploadpack.allowFilter", true)
+        .unwrap();
+    source_config
+        .set_bool("uploadpack.allowAnySHA1InWant", true)
+        .unwrap();
+    let blob_id1 = source_repo.blob(b"content1").unwrap();
+    let blob_id2 = source_repo.blob(b"content2").unwrap();
+    let mut tree_builder = source_repo.treebuilder(None).unwrap();
+    tree_builder.insert("file1", blob_id1, 0o100644).unwrap();
+    tree_builder.insert("file2", blob_id2, 0o100644).unwrap();
+    let tree = source_repo
+        .find_tree(tree_builder.write().unwrap())
+        .unwrap();
+    let signature = git2::Signature::now("Someone", "someone@example.com").unwrap();
+    source_repo
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "message",
+            &tree,
+            &[],
+        )
+        .unwrap();
+
+    // Make a partial clone without any blobs
+    let clone_path = temp_dir.path().join("clone");
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--bare", "--filter=blob:none"])
+        .arg(format!("file://{}", source_path.display()))
+        .arg(&clone_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let store_path = temp_dir.path().join("store");
+    std::fs::create_dir(&store_path).unwrap();
+    let backend = GitBackend::init_external(&settings, &store_path, &clone_path).unwrap();
+    let has_object = |id: git2::Oid| {
+        backend
+            .git_repo()
+            .has_object(gix::ObjectId::from(id.as_bytes()))
+    };
+    assert!(!has_object(blob_id1));
+    assert!(!has_object(blob_id2));
+    let fetches = Arc::new(Mutex::new(vec![]));
+    backend.set_fetch_missing_objects_progress(Some(Box::new({
+        let fetches = fetches.clone();
+        move |remote, count| fetches.lock().unwrap().push((remote.to_owned(), count))
+    })));
+
+    // A missing file is fetched when it's read
+    let file_id1 = FileId::from_bytes(blob_id1.as_bytes());
+    let mut content = vec![];
+    backend
+        .read_file(RepoPath::from_internal_string("file1"), &file_id1)
+        .block_on()
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert_eq!(content, b"content1");
+    assert!(!has_object(blob_id2));
+    assert_eq!(*fetches.lock().unwrap(), [("origin".to_owned(), 1)]);
+
+    // Missing files can be fetched in advance
+    let file_id2 = FileId::from_bytes(blob_id2.as_bytes());
+    backend.prefetch_files(&[file_id1, file_id2]).unwrap();
+    assert!(has_object(blob_id2));
+    assert_eq!(
+        *fetches.lock().unwrap(),
+        [("origin".to_owned(), 1), ("origin".to_owned(), 1)]
+    );
+}
@@ -0,0 +1,111 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+
+use jj_lib::gpg_signing::GpgBackend;
+use jj_lib::settings::UserSettings;
+use jj_lib::signing::{SigStatus, SigningBackend};
+
+/// A stand-in for `gpg` that "signs" data with its checksum. Signatures made
+/// with the key "expired" are reported as made by an expired key.
+const FAKE_PROGRAM: &str = r#"#!/bin/sh
+[ "$1" = "--keyid-format=long" ] || exit 2
+case "$2" in
+  -ab)
+    [ "$3" = "--local-user" ] && key=$4 || key=DEFAULTKEY
+    sum=$(cksum)
+    printf -- '-----BEGIN PGP SIGNATURE-----\n%s\n%s\n-----END PGP SIGNATURE-----\n' "$key" "$sum"
+    ;;
+  --status-fd=1)
+    sum=$(cksum)
+    key=$(sed -n 2p "$4")
+    if [ "$(sed -n 3p "$4")" != "$sum" ]; then
+      echo "[GNUPG:] BADSIG $key Test User <test.user@example.com>"
+      exit 1
+    elif [ "$key" = expired ]; then
+      echo "[GNUPG:] EXPKEYSIG $key Test User <test.user@example.com>"
+    else
+      echo "[GNUPG:] GOODSIG $key Test User <test.user@example.com>"
+    fi
+    ;;
+  *)
+    exit 2
+    ;;
+esac
+"#;
+
+fn fake_backend(dir: &Path, extra_config: &str) -> GpgBackend {
+    let path = dir.join("fake-gpg");
+    std::fs::write(&path, FAKE_PROGRAM).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let config = testutils::base_config()
+        .add_source(config::File::from_str(
+            &format!(
+                r#"
+                signing.backends.gpg.program = "{}"
+                {extra_config}
+                "#,
+                path.display()
+            ),
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    GpgBackend::from_settings(&UserSettings::from_config(config)).unwrap()
+}
+
+#[test]
+fn test_sign_and_verify() {
+    let temp_dir = testutils::new_temp_dir();
+    let backend = fake_backend(temp_dir.path(), "");
+
+    let signature = backend.sign(b"hello", Some("ABCD1234")).unwrap();
+    assert!(backend.can_read(&signature));
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+    assert_eq!(verification.key.as_deref(), Some("ABCD1234"));
+    assert_eq!(
+        verification.display.as_deref(),
+        Some("Test User <test.user@example.com>")
+    );
+
+    let verification = backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+
+    // Without a key, gpg signs with its default key
+    let signature = backend.sign(b"hello", None).unwrap();
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.key.as_deref(), Some("DEFAULTKEY"));
+}
+
+#[test]
+fn test_expired_key() {
+    let temp_dir = testutils::new_temp_dir();
+    let backend = fake_backend(temp_dir.path(), "");
+    let signature = backend.sign(b"hello", Some("expired")).unwrap();
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+
+    let temp_dir = testutils::new_temp_dir();
+    let backend = fake_backend(
+        temp_dir.path(),
+        "signing.backends.gpg.allow-expired-keys = true",
+    );
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+}
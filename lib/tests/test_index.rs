@@ -14,6 +14,7 @@
 
 use std::fs;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use assert_matches::assert_matches;
 use jj_lib::backend::{ChangeId, CommitId};
@@ -23,15 +24,19 @@ use jj_lib::default_index::{
     AsCompositeIndex as _, CompositeIndex, DefaultIndexStore, DefaultIndexStoreError,
     DefaultMutableIndex, DefaultReadonlyIndex, IndexPosition,
 };
+use jj_lib::fileset::FilesetExpression;
 use jj_lib::index::Index as _;
+use jj_lib::merged_tree::MergedTree;
 use jj_lib::object_id::{HexPrefix, ObjectId as _, PrefixResolution};
 use jj_lib::op_store::{RefTarget, RemoteRef};
 use jj_lib::repo::{MutableRepo, ReadonlyRepo, Repo};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::revset::{RevsetExpression, RevsetFilterPredicate};
 use jj_lib::settings::UserSettings;
 use testutils::test_backend::TestBackend;
 use testutils::{
-    commit_transactions, create_random_commit, load_repo_at_head, write_random_commit,
-    CommitGraphBuilder, TestRepo,
+    commit_transactions, create_random_commit, create_single_tree, create_tree, load_repo_at_head,
+    write_random_commit, CommitGraphBuilder, TestRepo,
 };
 
 fn child_commit<'repo>(
@@ -635,6 +640,42 @@ fn test_reindex_from_merged_operation() {
     assert_eq!(index.num_commits(), 4);
 }
 
+#[test]
+fn test_reindex_from_indexed_ancestor_operation() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings);
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit("test");
+    let old_operation = repo.operation().clone();
+    let mut tx = repo.start_transaction(&settings);
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit("test");
+    let indexed_op_id = repo.op_id().clone();
+    let mut tx = repo.start_transaction(&settings);
+    write_random_commit(tx.mut_repo(), &settings);
+    let repo = tx.commit("test");
+    let operation_to_reload = repo.operation();
+
+    // Remove the view of an old operation. It shouldn't be needed since the
+    // index at a later operation can be reused.
+    let view_path = repo
+        .repo_path()
+        .join("op_store")
+        .join("views")
+        .join(old_operation.view_id().hex());
+    fs::remove_file(view_path).unwrap();
+    let index_operations_dir = repo.repo_path().join("index").join("operations");
+    fs::remove_file(index_operations_dir.join(repo.op_id().hex())).unwrap();
+    assert!(index_operations_dir.join(indexed_op_id.hex()).is_file());
+
+    let repo = repo.reload_at(operation_to_reload).unwrap();
+    let index = as_readonly_composite(&repo);
+    assert_eq!(index.num_commits(), 4);
+}
+
 #[test]
 fn test_reindex_missing_commit() {
     let settings = testutils::user_settings();
@@ -667,6 +708,246 @@ fn test_reindex_missing_commit() {
     assert_matches!(err, DefaultIndexStoreError::IndexCommits { op_id, .. } if op_id == *bad_op_id);
 }
 
+#[test]
+fn test_index_compact() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let repo = create_n_commits(&settings, repo, 30);
+    let repo = create_n_commits(&settings, &repo, 15);
+    let repo = create_n_commits(&settings, &repo, 7);
+    assert_eq!(commits_by_level(&repo), vec![31, 15, 7]);
+
+    let default_index_store: &DefaultIndexStore =
+        repo.index_store().as_any().downcast_ref().unwrap();
+    default_index_store
+        .compact(repo.operation(), repo.store())
+        .unwrap();
+
+    // The superseded segments are kept until they've been superseded for long
+    // enough
+    let head_op_ids = [repo.op_id().clone()];
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    let removed_count = default_index_store
+        .gc(&head_op_ids, repo.store(), past, false)
+        .unwrap();
+    assert_eq!(removed_count, 0);
+    let future = SystemTime::now() + Duration::from_secs(3600);
+    let removed_count = default_index_store
+        .gc(&head_op_ids, repo.store(), future, true)
+        .unwrap();
+    assert_eq!(removed_count, 4);
+    let removed_count = default_index_store
+        .gc(&head_op_ids, repo.store(), future, false)
+        .unwrap();
+    assert_eq!(removed_count, 4);
+
+    // The index at the head operation is loaded from a single segment
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    assert_eq!(commits_by_level(&repo), vec![53]);
+    // Old operations are reindexed as needed
+    let repo = repo
+        .reload_at(repo.operation().parents().next().unwrap().as_ref().unwrap())
+        .unwrap();
+    assert_eq!(as_readonly_composite(&repo).num_commits(), 46);
+}
+
+#[test]
+fn test_index_load_concurrently_compacted() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let repo = create_n_commits(&settings, repo, 30);
+    let repo = create_n_commits(&settings, &repo, 15);
+    assert_eq!(commits_by_level(&repo), vec![31, 15]);
+
+    // Another process reads the link of the head operation before it's
+    // compacted, and loads the segments after they've been removed
+    let index_dir = repo.repo_path().join("index");
+    let op_link_path = index_dir.join("operations").join(repo.op_id().hex());
+    let old_op_link = fs::read(&op_link_path).unwrap();
+    let default_index_store: &DefaultIndexStore =
+        repo.index_store().as_any().downcast_ref().unwrap();
+    default_index_store
+        .compact(repo.operation(), repo.store())
+        .unwrap();
+    let future = SystemTime::now() + Duration::from_secs(3600);
+    default_index_store
+        .gc(&[repo.op_id().clone()], repo.store(), future, false)
+        .unwrap();
+    let new_op_link = fs::read(&op_link_path).unwrap();
+    fs::write(&op_link_path, &old_op_link).unwrap();
+    // A link written by yet another process
+    let parent_op_id = repo.operation().parent_ids()[0].clone();
+    let other_op_link_path = index_dir.join("operations").join(parent_op_id.hex());
+    fs::write(&other_op_link_path, &new_op_link).unwrap();
+
+    // The index is rebuilt without removing the files of other processes
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    assert_eq!(as_readonly_composite(&repo).num_commits(), 46);
+    assert!(other_op_link_path.is_file());
+    let compacted_name = String::from_utf8(new_op_link).unwrap();
+    assert!(index_dir.join(compacted_name).is_file());
+}
+
+#[test]
+fn test_changed_path_index() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let file_path = RepoPath::from_internal_string("dir/file");
+    let other_path = RepoPath::from_internal_string("other");
+    let tree = create_tree(repo, &[(file_path, "contents")]);
+    let mut tx = repo.start_transaction(&settings);
+    let commit = create_random_commit(tx.mut_repo(), &settings)
+        .set_tree_id(tree.id())
+        .write()
+        .unwrap();
+    let repo = tx.commit("test");
+
+    let default_index_store: &DefaultIndexStore =
+        repo.index_store().as_any().downcast_ref().unwrap();
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    assert!(index.changed_paths().is_none());
+    let index = default_index_store
+        .build_changed_path_index(index, repo.store())
+        .unwrap();
+    let changed_paths = index.changed_paths().unwrap();
+    assert_eq!(changed_paths.num_commits(), 2);
+    let filter = changed_paths.filter(commit.id()).unwrap();
+    assert!(filter.may_contain(RepoPath::from_internal_string("dir")));
+    assert!(filter.may_contain(file_path));
+    assert!(!filter.may_contain(other_path));
+
+    // The filters are loaded with the index
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    assert_eq!(index.changed_paths().unwrap().num_commits(), 2);
+
+    // Commits indexed later get filters without rebuilding
+    let tree = create_tree(&repo, &[(file_path, "contents"), (other_path, "other")]);
+    let mut tx = repo.start_transaction(&settings);
+    let child = create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit.id().clone()])
+        .set_tree_id(tree.id())
+        .write()
+        .unwrap();
+    let repo = tx.commit("test");
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    let changed_paths = index.changed_paths().unwrap();
+    assert_eq!(changed_paths.num_commits(), 3);
+    let filter = changed_paths.filter(child.id()).unwrap();
+    assert!(filter.may_contain(other_path));
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    assert_eq!(index.changed_paths().unwrap().num_commits(), 3);
+}
+
+#[test]
+fn test_changed_path_index_not_built_on_load() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings);
+    let commit = create_random_commit(tx.mut_repo(), &settings)
+        .write()
+        .unwrap();
+    tx.commit("test");
+
+    // The filters are built at the operation before the commit was indexed
+    let default_index_store: &DefaultIndexStore =
+        repo.index_store().as_any().downcast_ref().unwrap();
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    let index = default_index_store
+        .build_changed_path_index(index, repo.store())
+        .unwrap();
+    assert_eq!(index.changed_paths().unwrap().num_commits(), 1);
+
+    // Loading the index doesn't write a filter for the commit
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    let changed_paths = index.changed_paths().unwrap();
+    assert_eq!(changed_paths.num_commits(), 1);
+    assert!(!changed_paths.has_commit(commit.id()));
+
+    // Building the filters again adds it
+    let index = default_index_store
+        .build_changed_path_index(index, repo.store())
+        .unwrap();
+    assert!(index.changed_paths().unwrap().has_commit(commit.id()));
+}
+
+#[test]
+fn test_changed_path_index_merge_renames() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    // One side renames the file and the other modifies it. The merge keeps the
+    // renamed file and drops the modification.
+    let old_path = RepoPath::from_internal_string("old");
+    let new_path = RepoPath::from_internal_string("new");
+    let base_tree = create_single_tree(repo, &[(old_path, "a\nb\nc\nd\n")]);
+    let renamed_tree = create_single_tree(repo, &[(new_path, "a\nb\nc\nd\n")]);
+    let modified_tree = create_single_tree(repo, &[(old_path, "A\nb\nc\nd\n")]);
+    let mut tx = repo.start_transaction(&settings);
+    let mut_repo = tx.mut_repo();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let mut new_commit = |parents: Vec<CommitId>, tree| {
+        mut_repo
+            .new_commit(&settings, parents, MergedTree::resolved(tree).id())
+            .write()
+            .unwrap()
+    };
+    let base = new_commit(vec![root_commit_id], base_tree);
+    let renamed = new_commit(vec![base.id().clone()], renamed_tree.clone());
+    let modified = new_commit(vec![base.id().clone()], modified_tree);
+    let merge = new_commit(
+        vec![renamed.id().clone(), modified.id().clone()],
+        renamed_tree,
+    );
+    let repo = tx.commit("test");
+
+    // Merge commits get filters, which are built without rename detection
+    let default_index_store: &DefaultIndexStore =
+        repo.index_store().as_any().downcast_ref().unwrap();
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    let index = default_index_store
+        .build_changed_path_index(index, repo.store())
+        .unwrap();
+    assert!(index.changed_paths().unwrap().filter(merge.id()).is_some());
+
+    let resolve_file = |repo: &Arc<ReadonlyRepo>, path: &RepoPath| -> Vec<CommitId> {
+        let expression = RevsetExpression::filter(RevsetFilterPredicate::File(
+            FilesetExpression::prefix_path(path.to_owned()),
+        ));
+        let revset = expression.evaluate_programmatic(repo.as_ref()).unwrap();
+        revset.iter().collect()
+    };
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    assert_eq!(resolve_file(&repo, new_path), vec![renamed.id().clone()]);
+
+    // With rename detection, the merge commit drops the modification merged
+    // into the new path, which mustn't be ruled out by the filter
+    let config = testutils::base_config()
+        .add_source(config::File::from_str(
+            "merge.renames = true",
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    let renames_settings = UserSettings::from_config(config);
+    let repo = load_repo_at_head(&renames_settings, repo.repo_path());
+    let index: &DefaultReadonlyIndex = repo.readonly_index().as_any().downcast_ref().unwrap();
+    assert!(index.changed_paths().is_some());
+    assert_eq!(
+        resolve_file(&repo, new_path),
+        vec![merge.id().clone(), renamed.id().clone()]
+    );
+}
+
 /// Test that .jj/repo/index/type is created when the repo is created, and that
 /// it is created when an old repo is loaded.
 #[test]
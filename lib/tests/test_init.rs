@@ -18,7 +18,7 @@ use jj_lib::git_backend::GitBackend;
 use jj_lib::op_store::WorkspaceId;
 use jj_lib::repo::Repo;
 use jj_lib::settings::UserSettings;
-use jj_lib::workspace::Workspace;
+use jj_lib::workspace::{default_working_copy_factory, Workspace};
 use test_case::test_case;
 use testutils::{write_random_commit, TestRepoBackend, TestWorkspace};
 
@@ -33,7 +33,8 @@ fn test_init_local() {
     let settings = testutils::user_settings();
     let temp_dir = testutils::new_temp_dir();
     let (canonical, uncanonical) = canonicalize(temp_dir.path());
-    let (workspace, repo) = Workspace::init_local(&settings, &uncanonical).unwrap();
+    let (workspace, repo) =
+        Workspace::init_local(&settings, &uncanonical, &*default_working_copy_factory()).unwrap();
     assert!(repo
         .store()
         .backend_impl()
@@ -52,7 +53,9 @@ fn test_init_internal_git() {
     let settings = testutils::user_settings();
     let temp_dir = testutils::new_temp_dir();
     let (canonical, uncanonical) = canonicalize(temp_dir.path());
-    let (workspace, repo) = Workspace::init_internal_git(&settings, &uncanonical).unwrap();
+    let (workspace, repo) =
+        Workspace::init_internal_git(&settings, &uncanonical, &*default_working_copy_factory())
+            .unwrap();
     let git_backend = repo
         .store()
         .backend_impl()
@@ -80,7 +83,9 @@ fn test_init_colocated_git() {
     let settings = testutils::user_settings();
     let temp_dir = testutils::new_temp_dir();
     let (canonical, uncanonical) = canonicalize(temp_dir.path());
-    let (workspace, repo) = Workspace::init_colocated_git(&settings, &uncanonical).unwrap();
+    let (workspace, repo) =
+        Workspace::init_colocated_git(&settings, &uncanonical, &*default_working_copy_factory())
+            .unwrap();
     let git_backend = repo
         .store()
         .backend_impl()
@@ -112,6 +117,7 @@ fn test_init_external_git() {
         &settings,
         &uncanonical.join("jj"),
         &git_repo_path.join(".git"),
+        &*default_working_copy_factory(),
     )
     .unwrap();
     let git_backend = repo
@@ -23,19 +23,24 @@ use std::os::unix::fs::PermissionsExt;
 #[cfg(unix)]
 use std::os::unix::net::UnixListener;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use itertools::Itertools;
 use jj_lib::backend::{MergedTreeId, TreeId, TreeValue};
 use jj_lib::fsmonitor::FsmonitorKind;
+use jj_lib::fsync::Fsync;
 use jj_lib::local_working_copy::LocalWorkingCopy;
+use jj_lib::lock::LockBackend;
 use jj_lib::merge::Merge;
 use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
 use jj_lib::op_store::{OperationId, WorkspaceId};
 use jj_lib::repo::{ReadonlyRepo, Repo};
 use jj_lib::repo_path::{RepoPath, RepoPathBuf, RepoPathComponent};
 use jj_lib::settings::UserSettings;
-use jj_lib::working_copy::{CheckoutStats, SnapshotError, SnapshotOptions};
+use jj_lib::working_copy::{
+    CheckoutOptions, CheckoutStats, SnapshotError, SnapshotOptions, WorkingCopy as _,
+    WorkingCopyProgress,
+};
 use jj_lib::workspace::LockedWorkspace;
 use test_case::test_case;
 use testutils::{
@@ -198,10 +203,20 @@ fn test_checkout_file_transitions(backend: TestRepoBackend) {
     let right_commit = commit_with_tree(&store, right_tree_id.clone());
 
     let ws = &mut test_workspace.workspace;
-    ws.check_out(repo.op_id().clone(), None, &left_commit)
-        .unwrap();
-    ws.check_out(repo.op_id().clone(), None, &right_commit)
-        .unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &left_commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &right_commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Check that the working copy is clean.
     let new_tree = test_workspace.snapshot().unwrap();
@@ -284,9 +299,20 @@ fn test_conflict_subdirectory() {
     let merged_commit = commit_with_tree(repo.store(), merged_tree.id());
     let repo = &test_workspace.repo;
     let ws = &mut test_workspace.workspace;
-    ws.check_out(repo.op_id().clone(), None, &commit1).unwrap();
-    ws.check_out(repo.op_id().clone(), None, &merged_commit)
-        .unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit1,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &merged_commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 }
 
 #[test]
@@ -300,7 +326,13 @@ fn test_tree_builder_file_directory_transition() {
     let mut check_out_tree = |tree_id: &TreeId| {
         let tree = repo.store().get_tree(RepoPath::root(), tree_id).unwrap();
         let commit = commit_with_tree(repo.store(), MergedTreeId::Legacy(tree.id().clone()));
-        ws.check_out(repo.op_id().clone(), None, &commit).unwrap();
+        ws.check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
     };
 
     let parent_path = RepoPath::from_internal_string("foo/bar");
@@ -377,7 +409,14 @@ fn test_conflicting_changes_on_disk() {
     )
     .unwrap();
 
-    let stats = ws.check_out(repo.op_id().clone(), None, &commit).unwrap();
+    let stats = ws
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
     assert_eq!(
         stats,
         CheckoutStats {
@@ -421,7 +460,13 @@ fn test_reset() {
 
     let ws = &mut test_workspace.workspace;
     let commit = commit_with_tree(repo.store(), tree_with_file.id());
-    ws.check_out(repo.op_id().clone(), None, &commit).unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Test the setup: the file should exist on disk and in the tree state.
     assert!(ignored_path.to_fs_path(&workspace_root).is_file());
@@ -453,6 +498,151 @@ fn test_reset() {
     assert_eq!(new_tree.id(), tree_with_file.id());
 }
 
+#[test]
+fn test_recover() {
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = &test_workspace.repo;
+    let op_id = repo.op_id().clone();
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+
+    let file1_path = RepoPath::from_internal_string("file1");
+    let file2_path = RepoPath::from_internal_string("file2");
+    let untracked_path = RepoPath::from_internal_string("untracked");
+
+    let tree = create_tree(repo, &[(file1_path, "1"), (file2_path, "2")]);
+    let commit = commit_with_tree(repo.store(), tree.id());
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(
+        op_id.clone(),
+        None,
+        &commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    let wc: &LocalWorkingCopy = ws.working_copy().as_any().downcast_ref().unwrap();
+    let state_path = wc.state_path().to_path_buf();
+
+    // Corrupt the tree state, and modify the files on disk
+    std::fs::write(state_path.join("tree_state"), "garbage").unwrap();
+    std::fs::write(file1_path.to_fs_path(&workspace_root), "modified").unwrap();
+    std::fs::write(untracked_path.to_fs_path(&workspace_root), "untracked").unwrap();
+    let store = repo.store().clone();
+    let wc = LocalWorkingCopy::load(
+        store.clone(),
+        workspace_root.clone(),
+        state_path.clone(),
+        LockBackend::default(),
+        Fsync::default(),
+    );
+    assert!(wc.file_states().is_err());
+
+    // The state is rebuilt from the commit, and the files on disk are untouched
+    wc.recover(&commit, op_id.clone(), WorkspaceId::default())
+        .unwrap();
+    let wc = LocalWorkingCopy::load(
+        store,
+        workspace_root.clone(),
+        state_path,
+        LockBackend::default(),
+        Fsync::default(),
+    );
+    assert_eq!(wc.tree_id().unwrap(), &tree.id());
+    assert_eq!(wc.operation_id(), &op_id);
+    assert!(wc.file_states().unwrap().contains_path(file2_path));
+    assert!(!wc.file_states().unwrap().contains_path(untracked_path));
+    assert_eq!(
+        std::fs::read_to_string(file1_path.to_fs_path(&workspace_root)).unwrap(),
+        "modified"
+    );
+
+    // The changes on disk are picked up by the next snapshot
+    let new_tree = test_workspace.snapshot().unwrap();
+    let expected_tree = create_tree(
+        &test_workspace.repo,
+        &[
+            (file1_path, "modified"),
+            (file2_path, "2"),
+            (untracked_path, "untracked"),
+        ],
+    );
+    assert_eq!(new_tree.id(), expected_tree.id());
+}
+
+#[test]
+fn test_checkout_and_snapshot_progress() {
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+
+    let file1_path = RepoPath::from_internal_string("file1");
+    let file2_path = RepoPath::from_internal_string("dir/file2");
+    let file3_path = RepoPath::from_internal_string("file3");
+    let tree = create_tree(&repo, &[(file1_path, "1"), (file2_path, "2")]);
+    let commit = commit_with_tree(repo.store(), tree.id());
+
+    let updates = Mutex::new(vec![]);
+    let record_progress = |progress: WorkingCopyProgress| {
+        updates.lock().unwrap().push((
+            progress.path.to_owned(),
+            progress.processed,
+            progress.total,
+        ));
+    };
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        CheckoutOptions {
+            progress: Some(&record_progress),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        updates.lock().unwrap().drain(..).collect_vec(),
+        vec![
+            (file2_path.to_owned(), 1, Some(2)),
+            (file1_path.to_owned(), 2, Some(2)),
+        ]
+    );
+
+    // The total is estimated from the tracked files, so new files exceed it
+    std::fs::write(file3_path.to_fs_path(&workspace_root), "3").unwrap();
+    let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+    locked_ws
+        .locked_wc()
+        .snapshot(SnapshotOptions {
+            progress: Some(&record_progress),
+            ..SnapshotOptions::empty_for_test()
+        })
+        .unwrap();
+    // Files are visited in parallel, so the order isn't deterministic
+    let updates = updates.into_inner().unwrap();
+    assert_eq!(
+        updates
+            .iter()
+            .map(|(path, _, _)| path.clone())
+            .sorted()
+            .collect_vec(),
+        vec![
+            file2_path.to_owned(),
+            file1_path.to_owned(),
+            file3_path.to_owned()
+        ]
+    );
+    assert_eq!(
+        updates
+            .iter()
+            .map(|(_, processed, _)| *processed)
+            .sorted()
+            .collect_vec(),
+        vec![1, 2, 3]
+    );
+    assert!(updates.iter().all(|(_, _, total)| *total == Some(2)));
+}
+
 #[test]
 fn test_checkout_discard() {
     // Start a mutation, do a checkout, and then discard the mutation. The working
@@ -473,7 +663,13 @@ fn test_checkout_discard() {
     let commit2 = commit_with_tree(repo.store(), tree2.id());
 
     let ws = &mut test_workspace.workspace;
-    ws.check_out(repo.op_id().clone(), None, &commit1).unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit1,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
     let wc: &LocalWorkingCopy = ws.working_copy().as_any().downcast_ref().unwrap();
     let state_path = wc.state_path().to_path_buf();
 
@@ -484,12 +680,20 @@ fn test_checkout_discard() {
 
     // Start a checkout
     let mut locked_ws = ws.start_working_copy_mutation().unwrap();
-    locked_ws.locked_wc().check_out(&commit2).unwrap();
+    locked_ws
+        .locked_wc()
+        .check_out(&commit2, CheckoutOptions::empty_for_test())
+        .unwrap();
     // The change should be reflected in the working copy but not saved
     assert!(!file1_path.to_fs_path(&workspace_root).is_file());
     assert!(file2_path.to_fs_path(&workspace_root).is_file());
-    let reloaded_wc =
-        LocalWorkingCopy::load(store.clone(), workspace_root.clone(), state_path.clone());
+    let reloaded_wc = LocalWorkingCopy::load(
+        store.clone(),
+        workspace_root.clone(),
+        state_path.clone(),
+        LockBackend::default(),
+        Fsync::default(),
+    );
     assert!(reloaded_wc.file_states().unwrap().contains_path(file1_path));
     assert!(!reloaded_wc.file_states().unwrap().contains_path(file2_path));
     drop(locked_ws);
@@ -500,11 +704,50 @@ fn test_checkout_discard() {
     assert!(!wc.file_states().unwrap().contains_path(file2_path));
     assert!(!file1_path.to_fs_path(&workspace_root).is_file());
     assert!(file2_path.to_fs_path(&workspace_root).is_file());
-    let reloaded_wc = LocalWorkingCopy::load(store.clone(), workspace_root, state_path);
+    let reloaded_wc = LocalWorkingCopy::load(
+        store.clone(),
+        workspace_root,
+        state_path,
+        LockBackend::default(),
+        Fsync::default(),
+    );
     assert!(reloaded_wc.file_states().unwrap().contains_path(file1_path));
     assert!(!reloaded_wc.file_states().unwrap().contains_path(file2_path));
 }
 
+#[test]
+fn test_checkout_and_snapshot_long_path() {
+    // Tests that files can be materialized and snapshotted even if their path
+    // is longer than MAX_PATH (260 characters) on Windows.
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+
+    let dir_name = "d".repeat(50);
+    let file_path_string = format!("{}/file", [dir_name.as_str(); 6].join("/"));
+    let file_path = RepoPath::from_internal_string(&file_path_string);
+    let tree1 = create_tree(&repo, &[(file_path, "contents")]);
+    let tree2 = create_tree(&repo, &[(file_path, "modified")]);
+    let commit1 = commit_with_tree(repo.store(), tree1.id());
+
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit1,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    let disk_path = file_path.to_fs_path(&workspace_root);
+    assert!(disk_path.as_os_str().len() > 300);
+    assert_eq!(std::fs::read(&disk_path).unwrap(), b"contents");
+
+    std::fs::write(&disk_path, "modified").unwrap();
+    let new_tree = test_workspace.snapshot().unwrap();
+    assert_eq!(new_tree.id(), tree2.id());
+}
+
 #[test]
 fn test_snapshot_racy_timestamps() {
     // Tests that file modifications are detected even if they happen the same
@@ -675,7 +918,13 @@ fn test_gitignores_in_ignored_dir() {
     let tree1 = create_tree(&test_workspace.repo, &[(gitignore_path, "ignored\n")]);
     let commit1 = commit_with_tree(test_workspace.repo.store(), tree1.id());
     let ws = &mut test_workspace.workspace;
-    ws.check_out(op_id.clone(), None, &commit1).unwrap();
+    ws.check_out(
+        op_id.clone(),
+        None,
+        &commit1,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     testutils::write_working_copy_file(&workspace_root, nested_gitignore_path, "!file\n");
     testutils::write_working_copy_file(&workspace_root, ignored_path, "contents");
@@ -732,7 +981,14 @@ fn test_gitignores_checkout_never_overwrites_ignored() {
     // "contents". The exiting contents ("garbage") shouldn't be replaced in the
     // working copy.
     let ws = &mut test_workspace.workspace;
-    assert!(ws.check_out(repo.op_id().clone(), None, &commit).is_ok());
+    assert!(ws
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test()
+        )
+        .is_ok());
 
     // Check that the old contents are in the working copy
     let path = workspace_root.join("modified");
@@ -806,7 +1062,13 @@ fn test_gitignores_ignored_directory_already_tracked() {
 
     // Check out the tree with the files in `ignored/`
     let ws = &mut test_workspace.workspace;
-    ws.check_out(repo.op_id().clone(), None, &commit).unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Make some changes inside the ignored directory and check that they are
     // detected when we snapshot. The files that are still there should not be
@@ -916,7 +1178,13 @@ fn test_gitsubmodule() {
     let tree = store.get_root_tree(&tree_id).unwrap();
     let commit = commit_with_tree(repo.store(), tree.id());
     let ws = &mut test_workspace.workspace;
-    ws.check_out(repo.op_id().clone(), None, &commit).unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     std::fs::create_dir(submodule_path.to_fs_path(&workspace_root)).unwrap();
 
@@ -956,7 +1224,14 @@ fn test_existing_directory_symlink() {
 
     // Checkout should fail because "parent" already exists and is a symlink.
     let ws = &mut test_workspace.workspace;
-    assert!(ws.check_out(repo.op_id().clone(), None, &commit).is_err());
+    assert!(ws
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test()
+        )
+        .is_err());
 
     // Therefore, "../escaped" shouldn't be created.
     assert!(!workspace_root.parent().unwrap().join("escaped").exists());
@@ -13,11 +13,13 @@
 // limitations under the License.
 
 use itertools::Itertools;
+use jj_lib::fsync::Fsync;
 use jj_lib::local_working_copy::LocalWorkingCopy;
+use jj_lib::lock::LockBackend;
 use jj_lib::matchers::EverythingMatcher;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::{RepoPath, RepoPathBuf};
-use jj_lib::working_copy::{CheckoutStats, WorkingCopy};
+use jj_lib::working_copy::{CheckoutOptions, CheckoutStats, WorkingCopy};
 use testutils::{commit_with_tree, create_tree, TestWorkspace};
 
 fn to_owned_path_vec(paths: &[&RepoPath]) -> Vec<RepoPathBuf> {
@@ -56,7 +58,12 @@ fn test_sparse_checkout() {
 
     test_workspace
         .workspace
-        .check_out(repo.op_id().clone(), None, &commit)
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test(),
+        )
         .unwrap();
     let ws = &mut test_workspace.workspace;
 
@@ -65,7 +72,7 @@ fn test_sparse_checkout() {
     let sparse_patterns = to_owned_path_vec(&[dir1_path]);
     let stats = locked_ws
         .locked_wc()
-        .set_sparse_patterns(sparse_patterns.clone())
+        .set_sparse_patterns(sparse_patterns.clone(), CheckoutOptions::empty_for_test())
         .unwrap();
     assert_eq!(
         stats,
@@ -103,6 +110,8 @@ fn test_sparse_checkout() {
         repo.store().clone(),
         wc.path().to_path_buf(),
         wc.state_path().to_path_buf(),
+        LockBackend::default(),
+        Fsync::default(),
     );
     assert_eq!(
         wc.file_states().unwrap().paths().collect_vec(),
@@ -114,7 +123,7 @@ fn test_sparse_checkout() {
     let mut locked_wc = wc.start_mutation().unwrap();
     let sparse_patterns = to_owned_path_vec(&[root_file1_path, dir1_subdir1_path, dir2_path]);
     let stats = locked_wc
-        .set_sparse_patterns(sparse_patterns.clone())
+        .set_sparse_patterns(sparse_patterns.clone(), CheckoutOptions::empty_for_test())
         .unwrap();
     assert_eq!(
         stats,
@@ -169,7 +178,12 @@ fn test_sparse_commit() {
     let commit = commit_with_tree(repo.store(), tree.id());
     test_workspace
         .workspace
-        .check_out(repo.op_id().clone(), None, &commit)
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            CheckoutOptions::empty_for_test(),
+        )
         .unwrap();
 
     // Set sparse patterns to only dir1/
@@ -180,7 +194,7 @@ fn test_sparse_commit() {
     let sparse_patterns = to_owned_path_vec(&[dir1_path]);
     locked_ws
         .locked_wc()
-        .set_sparse_patterns(sparse_patterns)
+        .set_sparse_patterns(sparse_patterns, CheckoutOptions::empty_for_test())
         .unwrap();
     locked_ws.finish(repo.op_id().clone()).unwrap();
 
@@ -206,7 +220,7 @@ fn test_sparse_commit() {
     let sparse_patterns = to_owned_path_vec(&[dir1_path, dir2_path]);
     locked_ws
         .locked_wc()
-        .set_sparse_patterns(sparse_patterns)
+        .set_sparse_patterns(sparse_patterns, CheckoutOptions::empty_for_test())
         .unwrap();
     locked_ws.finish(op_id).unwrap();
 
@@ -239,7 +253,7 @@ fn test_sparse_commit_gitignore() {
     let sparse_patterns = to_owned_path_vec(&[dir1_path]);
     locked_ws
         .locked_wc()
-        .set_sparse_patterns(sparse_patterns)
+        .set_sparse_patterns(sparse_patterns, CheckoutOptions::empty_for_test())
         .unwrap();
     locked_ws.finish(repo.op_id().clone()).unwrap();
 
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
 use futures::executor::block_on;
 use futures::StreamExt;
 use itertools::Itertools;
@@ -20,10 +22,12 @@ use jj_lib::files::MergeResult;
 use jj_lib::matchers::{EverythingMatcher, FilesMatcher, Matcher, PrefixMatcher};
 use jj_lib::merge::{Merge, MergeBuilder};
 use jj_lib::merged_tree::{
-    MergedTree, MergedTreeBuilder, MergedTreeVal, TreeDiffIterator, TreeDiffStreamImpl,
+    MergeStrategy, MergedTree, MergedTreeBuilder, MergedTreeVal, TreeDiffIterator,
+    TreeDiffStreamImpl,
 };
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::{RepoPath, RepoPathComponent};
+use jj_lib::settings::UserSettings;
 use jj_lib::tree::merge_trees;
 use pretty_assertions::assert_eq;
 use testutils::{create_single_tree, write_file, TestRepo};
@@ -46,6 +50,21 @@ fn diff_stream_equals_iter(tree1: &MergedTree, tree2: &MergedTree, matcher: &dyn
             .collect(),
     );
     assert_eq!(stream_diff, iter_diff);
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    let thread_pool_diff: Vec<_> = block_on(
+        TreeDiffStreamImpl::new_with_thread_pool(
+            tree1.clone(),
+            tree2.clone(),
+            matcher,
+            Arc::new(thread_pool),
+        )
+        .map(|(path, diff)| (path, diff.unwrap()))
+        .collect(),
+    );
+    assert_eq!(thread_pool_diff, iter_diff);
 }
 
 #[test]
@@ -1236,6 +1255,42 @@ fn test_merge_partial_resolution() {
     assert_eq!(merged, expected_merged);
 }
 
+/// Resolve the conflicts in a merge by taking one side
+#[test]
+fn test_resolve_conflicts_with_strategy() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let path1 = RepoPath::from_internal_string("dir1/file");
+    let path2 = RepoPath::from_internal_string("dir2/file");
+    let base1 = create_single_tree(repo, &[(path1, "base"), (path2, "base")]);
+    let side1 = create_single_tree(repo, &[(path1, "side1"), (path2, "side1")]);
+    let side2 = create_single_tree(repo, &[(path1, "base"), (path2, "side2")]);
+    let base1_merged = MergedTree::new(Merge::resolved(base1));
+    let side1_merged = MergedTree::new(Merge::resolved(side1));
+    let side2_merged = MergedTree::new(Merge::resolved(side2));
+    let merged = side1_merged.merge(&base1_merged, &side2_merged).unwrap();
+
+    let (resolved, paths) = merged.resolve_conflicts_with(MergeStrategy::Ours).unwrap();
+    let expected = create_single_tree(repo, &[(path1, "side1"), (path2, "side1")]);
+    assert_eq!(resolved, MergedTree::resolved(expected));
+    assert_eq!(paths, vec![path2.to_owned()]);
+
+    let (resolved, paths) = merged
+        .resolve_conflicts_with(MergeStrategy::Theirs)
+        .unwrap();
+    let expected = create_single_tree(repo, &[(path1, "side1"), (path2, "side2")]);
+    assert_eq!(resolved, MergedTree::resolved(expected));
+    assert_eq!(paths, vec![path2.to_owned()]);
+
+    // A tree without conflicts is unchanged
+    let (resolved, paths) = side1_merged
+        .resolve_conflicts_with(MergeStrategy::Ours)
+        .unwrap();
+    assert_eq!(resolved, side1_merged);
+    assert_eq!(paths, vec![]);
+}
+
 /// Merge 3 resolved trees, including one empty legacy tree
 #[test]
 fn test_merge_with_empty_legacy_tree() {
@@ -1486,3 +1541,85 @@ fn test_merge_simplify_file_conflict_with_absent() {
     let merged = child1_merged.merge(&parent_merged, &child2_merged).unwrap();
     assert_eq!(merged, expected_merged);
 }
+
+fn merge_renames_settings() -> UserSettings {
+    let config = testutils::base_config()
+        .add_source(config::File::from_str(
+            "merge.renames = true",
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    UserSettings::from_config(config)
+}
+
+#[test]
+fn test_merge_renamed_and_modified() {
+    let test_repo = TestRepo::init_with_settings(&merge_renames_settings());
+    let repo = &test_repo.repo;
+
+    let old_path = RepoPath::from_internal_string("old");
+    let new_path = RepoPath::from_internal_string("dir/new");
+    let base = create_single_tree(repo, &[(old_path, "a\nb\nc\nd\n")]);
+    // Renamed, and the last line modified
+    let renamed = create_single_tree(repo, &[(new_path, "a\nb\nc\nD\n")]);
+    // The first line modified
+    let modified = create_single_tree(repo, &[(old_path, "A\nb\nc\nd\n")]);
+    let expected = create_single_tree(repo, &[(new_path, "A\nb\nc\nD\n")]);
+
+    let renamed_merged = MergedTree::resolved(renamed);
+    let base_merged = MergedTree::resolved(base);
+    let modified_merged = MergedTree::resolved(modified);
+    let expected_merged = MergedTree::resolved(expected);
+    // The rename can be on either side
+    let merged = renamed_merged
+        .merge(&base_merged, &modified_merged)
+        .unwrap();
+    assert_eq!(merged, expected_merged);
+    let merged = modified_merged
+        .merge(&base_merged, &renamed_merged)
+        .unwrap();
+    assert_eq!(merged, expected_merged);
+}
+
+#[test]
+fn test_merge_renamed_and_modified_dissimilar() {
+    let test_repo = TestRepo::init_with_settings(&merge_renames_settings());
+    let repo = &test_repo.repo;
+
+    // The added file is too different from the removed file to be a rename
+    let old_path = RepoPath::from_internal_string("old");
+    let new_path = RepoPath::from_internal_string("new");
+    let base = create_single_tree(repo, &[(old_path, "a\nb\nc\nd\n")]);
+    let renamed = create_single_tree(repo, &[(new_path, "w\nx\ny\nd\n")]);
+    let modified = create_single_tree(repo, &[(old_path, "A\nb\nc\nd\n")]);
+
+    let merged = MergedTree::resolved(renamed)
+        .merge(&MergedTree::resolved(base), &MergedTree::resolved(modified))
+        .unwrap();
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        vec![old_path.to_owned()]
+    );
+}
+
+#[test]
+fn test_merge_renamed_and_modified_disabled() {
+    // Renames aren't detected unless `merge.renames` is enabled
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let old_path = RepoPath::from_internal_string("old");
+    let new_path = RepoPath::from_internal_string("new");
+    let base = create_single_tree(repo, &[(old_path, "a\nb\nc\nd\n")]);
+    let renamed = create_single_tree(repo, &[(new_path, "a\nb\nc\nd\n")]);
+    let modified = create_single_tree(repo, &[(old_path, "A\nb\nc\nd\n")]);
+
+    let merged = MergedTree::resolved(renamed)
+        .merge(&MergedTree::resolved(base), &MergedTree::resolved(modified))
+        .unwrap();
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        vec![old_path.to_owned()]
+    );
+}
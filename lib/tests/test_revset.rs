@@ -23,6 +23,7 @@ use assert_matches::assert_matches;
 use itertools::Itertools;
 use jj_lib::backend::{CommitId, MillisSinceEpoch, Signature, Timestamp};
 use jj_lib::commit::Commit;
+use jj_lib::fileset::FilesetExpression;
 use jj_lib::git;
 use jj_lib::git_backend::GitBackend;
 use jj_lib::object_id::ObjectId;
@@ -1817,6 +1818,41 @@ fn test_evaluate_expression_branches() {
     );
 }
 
+#[test]
+fn test_evaluate_expression_pins() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings);
+    let mut_repo = tx.mut_repo();
+
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = write_random_commit(mut_repo, &settings);
+
+    // Can get pins when there are none
+    assert_eq!(resolve_commit_ids(mut_repo, "pin(foo)"), vec![]);
+    // Can get pins with matching names
+    mut_repo.set_pin_target("foo1", RefTarget::normal(commit1.id().clone()));
+    mut_repo.set_pin_target("foo2", RefTarget::normal(commit2.id().clone()));
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "pin(exact:foo1)"),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "pin(foo)"),
+        vec![commit2.id().clone(), commit1.id().clone()]
+    );
+    // Pins aren't branches
+    assert_eq!(resolve_commit_ids(mut_repo, "branches()"), vec![]);
+    // Can remove a pin
+    mut_repo.set_pin_target("foo2", RefTarget::absent());
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "pin(foo)"),
+        vec![commit1.id().clone()]
+    );
+}
+
 #[test]
 fn test_evaluate_expression_remote_branches() {
     let settings = testutils::user_settings();
@@ -2633,10 +2669,9 @@ fn test_evaluate_expression_file() {
 
     let resolve = |file_path: &RepoPath| -> Vec<CommitId> {
         let mut_repo = &*mut_repo;
-        let expression =
-            RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                vec![file_path.to_owned()],
-            )));
+        let expression = RevsetExpression::filter(RevsetFilterPredicate::File(
+            FilesetExpression::prefix_path(file_path.to_owned()),
+        ));
         let revset = expression.evaluate_programmatic(mut_repo).unwrap();
         revset.iter().collect()
     };
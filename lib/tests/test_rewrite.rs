@@ -19,7 +19,9 @@ use jj_lib::merged_tree::MergedTree;
 use jj_lib::op_store::{RefTarget, RemoteRef, RemoteRefState, WorkspaceId};
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
-use jj_lib::rewrite::{rebase_commit_with_options, restore_tree, EmptyBehaviour, RebaseOptions};
+use jj_lib::rewrite::{
+    merge_commit_trees, rebase_commit_with_options, restore_tree, EmptyBehaviour, RebaseOptions,
+};
 use maplit::{hashmap, hashset};
 use test_case::test_case;
 use testutils::{
@@ -61,6 +63,79 @@ fn test_restore_tree() {
     assert_eq!(restored, expected.id());
 }
 
+/// Creates a criss-cross history where the sides `x` and `y` each merge the
+/// commits `b1` and `b2`, which were based on `a`, and returns the merge of
+/// `x` and `y`.
+fn merge_criss_cross(
+    test_repo: &TestRepo,
+    a: &str,
+    b1: &str,
+    b2: &str,
+    x: &str,
+    y: &str,
+) -> MergedTree {
+    let settings = testutils::user_settings();
+    let repo = &test_repo.repo;
+    let path = RepoPath::from_internal_string("file");
+    let mut tx = repo.start_transaction(&settings);
+    let mut write_commit = |parents: Vec<&Commit>, content: &str| {
+        let tree = create_tree(repo, &[(path, content)]);
+        let parent_ids = parents.iter().map(|commit| commit.id().clone()).collect();
+        tx.mut_repo()
+            .new_commit(&settings, parent_ids, tree.id())
+            .write()
+            .unwrap()
+    };
+    let root_commit = repo.store().root_commit();
+    let commit_a = write_commit(vec![&root_commit], a);
+    let commit_b1 = write_commit(vec![&commit_a], b1);
+    let commit_b2 = write_commit(vec![&commit_a], b2);
+    let commit_x = write_commit(vec![&commit_b1, &commit_b2], x);
+    let commit_y = write_commit(vec![&commit_b2, &commit_b1], y);
+    merge_commit_trees(tx.repo(), &[commit_x, commit_y]).unwrap()
+}
+
+#[test]
+fn test_merge_criss_cross_same_resolution() {
+    let test_repo = TestRepo::init();
+    let path = RepoPath::from_internal_string("file");
+
+    // Both sides resolved the conflict between b1 and b2 the same way, and x
+    // also changed the last line
+    let merged = merge_criss_cross(
+        &test_repo,
+        "1\n2\n3\n",
+        "1b1\n2\n3\n",
+        "1b2\n2\n3\n",
+        "1b\n2\n3x\n",
+        "1b\n2\n3\n",
+    );
+    let expected = create_tree(&test_repo.repo, &[(path, "1b\n2\n3x\n")]);
+    assert_eq!(merged.id(), expected.id());
+}
+
+#[test]
+fn test_merge_criss_cross_different_resolutions() {
+    let test_repo = TestRepo::init();
+    let path = RepoPath::from_internal_string("file");
+
+    // The sides resolved the conflict differently, by picking b1 in x and b2
+    // in y. Merging the ancestors without conflict markers would cancel the
+    // sides out and silently result in a.
+    let merged = merge_criss_cross(
+        &test_repo,
+        "1\n2\n3\n",
+        "1b1\n2\n3\n",
+        "1b2\n2\n3\n",
+        "1b1\n2\n3\n",
+        "1b2\n2\n3\n",
+    );
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        vec![path.to_owned()]
+    );
+}
+
 #[test]
 fn test_rebase_descendants_sideways() {
     let settings = testutils::user_settings();
@@ -1543,6 +1618,7 @@ fn test_empty_commit_option(empty_behavior: EmptyBehaviour) {
             &settings,
             RebaseOptions {
                 empty: empty_behavior.clone(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -1683,6 +1759,7 @@ fn test_rebase_abandoning_empty() {
 
     let rebase_options = RebaseOptions {
         empty: EmptyBehaviour::AbandonAllEmpty,
+        ..Default::default()
     };
     rebase_commit_with_options(
         &settings,
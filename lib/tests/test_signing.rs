@@ -1,5 +1,6 @@
 use jj_lib::backend::{MillisSinceEpoch, Signature, Timestamp};
 use jj_lib::repo::Repo;
+use jj_lib::rewrite::sign_commits;
 use jj_lib::settings::UserSettings;
 use jj_lib::signing::{SigStatus, SignBehavior, Signer, Verification};
 use test_case::test_case;
@@ -169,3 +170,60 @@ fn configured(backend: TestRepoBackend) {
     let commit = repo.store().get_commit(commit.id()).unwrap();
     assert_eq!(commit.verification().unwrap(), good_verification());
 }
+
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn sign_on_push(backend: TestRepoBackend) {
+    let config = testutils::base_config()
+        .add_source(config::File::from_str(
+            r#"
+            signing.key = "impeccable"
+            signing.sign-all = true
+            signing.when = "push"
+            "#,
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    let settings = UserSettings::from_config(config);
+
+    let signer = Signer::new(Some(Box::new(TestSigningBackend)), vec![]);
+    let test_workspace = TestWorkspace::init_with_backend_and_signer(&settings, backend, signer);
+    let repo = &test_workspace.repo;
+
+    // Commits aren't signed when they're written
+    let mut tx = repo.start_transaction(&settings);
+    let mut_repo = tx.mut_repo();
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![commit1.id().clone()])
+        .set_author(someone_else())
+        .write()
+        .unwrap();
+    let commit3 = create_random_commit(mut_repo, &settings)
+        .set_parents(vec![commit2.id().clone()])
+        .write()
+        .unwrap();
+    assert_eq!(commit1.verification().unwrap(), None);
+    assert_eq!(commit3.verification().unwrap(), None);
+
+    // Our own commits are signed, and the others are rebased onto them
+    let mut sign_settings = settings.sign_settings();
+    sign_settings.behavior = SignBehavior::Own;
+    let commits = [commit1.clone(), commit2.clone(), commit3.clone()];
+    let rewritten = sign_commits(&settings, mut_repo, &commits, &sign_settings).unwrap();
+    assert_eq!(rewritten.len(), 3);
+    let store = mut_repo.store().clone();
+    let new_commit1 = store.get_commit(&rewritten[commit1.id()]).unwrap();
+    let new_commit2 = store.get_commit(&rewritten[commit2.id()]).unwrap();
+    let new_commit3 = store.get_commit(&rewritten[commit3.id()]).unwrap();
+    assert_eq!(new_commit1.verification().unwrap(), good_verification());
+    assert_eq!(new_commit2.verification().unwrap(), None);
+    assert_eq!(new_commit2.parent_ids(), [new_commit1.id().clone()]);
+    assert_eq!(new_commit3.verification().unwrap(), good_verification());
+    assert_eq!(new_commit3.parent_ids(), [new_commit2.id().clone()]);
+    assert_eq!(new_commit1.committer(), commit1.committer());
+
+    // Signed commits are left alone
+    let rewritten = sign_commits(&settings, mut_repo, &[new_commit1], &sign_settings).unwrap();
+    assert!(rewritten.is_empty());
+}
@@ -0,0 +1,115 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::{Path, PathBuf};
+
+use jj_lib::signing::{SigStatus, SigningBackend};
+use jj_lib::ssh_signing::SshBackend;
+
+/// A stand-in for `ssh-keygen` that "signs" data with its checksum and the
+/// contents of the key file.
+const FAKE_PROGRAM: &str = r#"#!/bin/sh
+[ "$1" = "-Y" ] || exit 2
+sig_key() { sed -n 2p "$1"; }
+sig_ok() { [ "$(sed -n 3p "$1")" = "$(cksum)" ]; }
+case "$2" in
+  sign)
+    [ "$5 $6" = "-n git" ] || exit 2
+    key=$(cat "$4") || exit 1
+    sum=$(cksum)
+    printf -- '-----BEGIN SSH SIGNATURE-----\n%s\n%s\n-----END SSH SIGNATURE-----\n' "$key" "$sum"
+    ;;
+  find-principals)
+    key=$(sig_key "$6")
+    grep -F "$key" "$4" | cut -d' ' -f1 | grep . || exit 1
+    ;;
+  verify)
+    key=$(sig_key "${10}")
+    sig_ok "${10}" || exit 1
+    echo "Good \"git\" signature for $6 with ED25519 key SHA256:$(echo "$key" | cut -d' ' -f2)"
+    ;;
+  check-novalidate)
+    key=$(sig_key "$6")
+    sig_ok "$6" || exit 1
+    echo "Good \"git\" signature with ED25519 key SHA256:$(echo "$key" | cut -d' ' -f2)"
+    ;;
+  *)
+    exit 2
+    ;;
+esac
+"#;
+
+fn write_fake_program(dir: &Path) -> PathBuf {
+    let path = dir.join("fake-ssh-keygen");
+    std::fs::write(&path, FAKE_PROGRAM).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn test_sign_and_verify_with_allowed_signers() {
+    let temp_dir = testutils::new_temp_dir();
+    let program = write_fake_program(temp_dir.path());
+    let allowed_signers = temp_dir.path().join("allowed_signers");
+    std::fs::write(
+        &allowed_signers,
+        "alice@example.com ssh-ed25519 AAAAalice\n",
+    )
+    .unwrap();
+    let backend = SshBackend::new(program.into(), Some(allowed_signers));
+
+    // A public key is passed to ssh-keygen in a file
+    let signature = backend
+        .sign(b"hello", Some("ssh-ed25519 AAAAalice"))
+        .unwrap();
+    assert!(backend.can_read(&signature));
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+    assert_eq!(verification.key.as_deref(), Some("SHA256:AAAAalice"));
+    assert_eq!(verification.display.as_deref(), Some("alice@example.com"));
+
+    let verification = backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+
+    // A key file is passed as is
+    let key_path = temp_dir.path().join("id_ed25519.pub");
+    std::fs::write(&key_path, "ssh-ed25519 AAAAbob").unwrap();
+    let signature = backend
+        .sign(b"hello", Some(key_path.to_str().unwrap()))
+        .unwrap();
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Unknown);
+    assert_eq!(verification.key.as_deref(), Some("SHA256:AAAAbob"));
+    assert_eq!(verification.display, None);
+}
+
+#[test]
+fn test_verify_without_allowed_signers() {
+    let temp_dir = testutils::new_temp_dir();
+    let program = write_fake_program(temp_dir.path());
+    let backend = SshBackend::new(program.into(), None);
+
+    let signature = backend
+        .sign(b"hello", Some("ssh-ed25519 AAAAalice"))
+        .unwrap();
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Unknown);
+    let verification = backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+
+    assert!(backend.sign(b"hello", None).is_err());
+}
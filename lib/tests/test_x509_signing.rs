@@ -0,0 +1,149 @@
+// Copyright 2024 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::{Path, PathBuf};
+
+use assert_matches::assert_matches;
+use jj_lib::backend::CommitId;
+use jj_lib::settings::UserSettings;
+use jj_lib::signing::{SigStatus, SignError, Signer, SigningBackend};
+use jj_lib::sigstore_signing::SigstoreBackend;
+use jj_lib::x509_signing::X509Backend;
+
+/// A stand-in for `gpgsm` that "signs" data with its checksum.
+const FAKE_PROGRAM: &str = r#"#!/bin/sh
+case "$2" in
+  -bsau)
+    sum=$(cksum)
+    printf -- '-----BEGIN SIGNED MESSAGE-----\n%s\n%s\n%s\n-----END SIGNED MESSAGE-----\n' \
+      "$3" "$sum" "$GITSIGN_REKOR_URL"
+    ;;
+  --verify)
+    sum=$(cksum)
+    key=$(sed -n 2p "$3")
+    if [ "$(sed -n 3p "$3")" = "$sum" ]; then
+      echo "[GNUPG:] GOODSIG $key /CN=$key"
+    else
+      echo "[GNUPG:] BADSIG $key /CN=$key"
+      exit 1
+    fi
+    ;;
+  *)
+    echo "unexpected arguments: $*" >&2
+    exit 2
+    ;;
+esac
+"#;
+
+fn write_fake_program(dir: &Path) -> PathBuf {
+    let path = dir.join("fake-gpgsm");
+    std::fs::write(&path, FAKE_PROGRAM).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn fake_backend(dir: &Path) -> X509Backend {
+    X509Backend::new(write_fake_program(dir).into())
+}
+
+#[test]
+fn test_sign_and_verify() {
+    let temp_dir = testutils::new_temp_dir();
+    let backend = fake_backend(temp_dir.path());
+
+    let signature = backend.sign(b"hello", Some("alice")).unwrap();
+    assert!(backend.can_read(&signature));
+
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+    assert_eq!(verification.key.as_deref(), Some("alice"));
+    assert_eq!(verification.display.as_deref(), Some("/CN=alice"));
+
+    let verification = backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+}
+
+#[test]
+fn test_sign_failure() {
+    let temp_dir = testutils::new_temp_dir();
+    let backend = fake_backend(temp_dir.path());
+
+    // Without a key, the fake program gets arguments it doesn't understand.
+    let err = backend.sign(b"hello", None).unwrap_err();
+    let source = std::error::Error::source(&err).unwrap().to_string();
+    assert!(
+        source.contains("unexpected arguments: --status-fd=2 -bsa"),
+        "{source}"
+    );
+}
+
+#[test]
+fn test_sigstore_from_settings() {
+    let temp_dir = testutils::new_temp_dir();
+    let program = write_fake_program(temp_dir.path());
+    let config = testutils::base_config()
+        .set_override(
+            "signing.backends.sigstore.program",
+            program.to_str().unwrap(),
+        )
+        .unwrap()
+        .set_override(
+            "signing.backends.sigstore.rekor-url",
+            "https://rekor.example.com",
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+    let backend = SigstoreBackend::from_settings(&UserSettings::from_config(config)).unwrap();
+    assert_eq!(backend.name(), "sigstore");
+
+    let signature = backend.sign(b"hello", Some("alice@example.com")).unwrap();
+    assert!(String::from_utf8(signature.clone())
+        .unwrap()
+        .contains("https://rekor.example.com"));
+    let verification = backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+    assert_eq!(verification.key.as_deref(), Some("alice@example.com"));
+}
+
+#[test]
+fn test_verify_skips_unavailable_program() {
+    let temp_dir = testutils::new_temp_dir();
+    let missing_backend = || X509Backend::new(temp_dir.path().join("missing").into());
+    let signature = fake_backend(temp_dir.path())
+        .sign(b"hello", Some("alice"))
+        .unwrap();
+    let commit_id = CommitId::from_hex("abcdef");
+
+    // gpgsm isn't installed, but gitsign reads the same signatures
+    let signer = Signer::new(
+        Some(Box::new(missing_backend())),
+        vec![Box::new(SigstoreBackend::new(fake_backend(
+            temp_dir.path(),
+        )))],
+    );
+    let verification = signer.verify(&commit_id, b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+
+    // Neither program is installed
+    let signer = Signer::new(
+        Some(Box::new(missing_backend())),
+        vec![Box::new(SigstoreBackend::new(missing_backend()))],
+    );
+    let err = signer.verify(&commit_id, b"hello", &signature).unwrap_err();
+    assert!(matches!(err, SignError::Unavailable(_)), "{err:?}");
+}
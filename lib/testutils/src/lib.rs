@@ -39,7 +39,7 @@ use jj_lib::transaction::Transaction;
 use jj_lib::tree::Tree;
 use jj_lib::tree_builder::TreeBuilder;
 use jj_lib::working_copy::{SnapshotError, SnapshotOptions};
-use jj_lib::workspace::Workspace;
+use jj_lib::workspace::{default_working_copy_factory, Workspace};
 use tempfile::TempDir;
 
 use crate::test_backend::TestBackend;
@@ -120,7 +120,10 @@ impl TestRepoBackend {
     ) -> Result<Box<dyn Backend>, BackendInitError> {
         match self {
             TestRepoBackend::Git => Ok(Box::new(GitBackend::init_internal(settings, store_path)?)),
-            TestRepoBackend::Local => Ok(Box::new(LocalBackend::init(store_path))),
+            TestRepoBackend::Local => Ok(Box::new(LocalBackend::init(
+                store_path,
+                settings.fsync().clone(),
+            ))),
             TestRepoBackend::Test => Ok(Box::new(TestBackend::init(store_path))),
         }
     }
@@ -211,6 +214,7 @@ impl TestWorkspace {
             &workspace_root,
             &move |settings, store_path| backend.init_backend(settings, store_path),
             signer,
+            &*default_working_copy_factory(),
         )
         .unwrap();
 
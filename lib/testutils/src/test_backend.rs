@@ -22,8 +22,8 @@ use std::time::SystemTime;
 
 use async_trait::async_trait;
 use jj_lib::backend::{
-    make_root_commit, Backend, BackendError, BackendResult, ChangeId, Commit, CommitId, Conflict,
-    ConflictId, FileId, SecureSig, SigningFn, SymlinkId, Tree, TreeId,
+    make_root_commit, Backend, BackendError, BackendGcStats, BackendResult, ChangeId, Commit,
+    CommitId, Conflict, ConflictId, FileId, SecureSig, SigningFn, SymlinkId, Tree, TreeId,
 };
 use jj_lib::index::Index;
 use jj_lib::object_id::ObjectId;
@@ -300,7 +300,12 @@ impl Backend for TestBackend {
         Ok((id, contents))
     }
 
-    fn gc(&self, _index: &dyn Index, _keep_newer: SystemTime) -> BackendResult<()> {
-        Ok(())
+    fn gc(
+        &self,
+        _index: &dyn Index,
+        _keep_newer: SystemTime,
+        _dry_run: bool,
+    ) -> BackendResult<BackendGcStats> {
+        Ok(BackendGcStats::default())
     }
 }